@@ -2,11 +2,13 @@ use std::path::PathBuf;
 use std::process::exit;
 
 use clap::Parser;
-use quincy::Result;
-use quincy::config::{ClientConfig, FromPath};
+use quincy::config::{ClientConfig, FromEnv, FromPath};
+use quincy::network::dns::{dns_backup_path, restore_stale_dns_backup};
+use quincy::network::firewall::lift_kill_switch;
 use quincy::network::interface::tun_rs::TunRsInterface;
 use quincy::utils::tracing::log_subscriber;
-use quincy_client::client::QuincyClient;
+use quincy::{QuincyError, Result};
+use quincy_client::client::{QuincyClient, ShutdownReason};
 use tracing::error;
 
 #[derive(Parser)]
@@ -16,6 +18,12 @@ pub struct Args {
     pub config_path: PathBuf,
     #[arg(long, default_value = "QUINCY_")]
     pub env_prefix: String,
+    /// Build the configuration from environment variables only, ignoring the config file
+    #[arg(long)]
+    pub env_only: bool,
+    /// Lift a kill switch left engaged by a client that failed or crashed, then exit
+    #[arg(long)]
+    pub lift_kill_switch: bool,
 }
 
 #[tokio::main]
@@ -35,11 +43,27 @@ async fn main() {
 /// Runs the Quincy client.
 async fn run_client() -> Result<()> {
     let args = Args::parse();
-    let config = ClientConfig::from_path(&args.config_path, &args.env_prefix)?;
+    if args.lift_kill_switch {
+        return lift_kill_switch(false);
+    }
+
+    let config = if args.env_only {
+        ClientConfig::from_env(&args.env_prefix)?
+    } else {
+        ClientConfig::from_path(&args.config_path, &args.env_prefix)?
+    };
+    config.validate()?;
     // Enable tracing with the log level from the configuration.
     tracing::subscriber::set_global_default(log_subscriber(&config.log.level))?;
 
+    // Undo the DNS configuration of an earlier run that was killed before cleaning it up
+    restore_stale_dns_backup(&dns_backup_path())?;
+
     let mut client = QuincyClient::new(config);
     client.start::<TunRsInterface>().await?;
-    client.wait_for_shutdown().await
+
+    match client.run::<TunRsInterface>().await? {
+        ShutdownReason::UserRequest => Ok(()),
+        reason => Err(QuincyError::system(format!("Tunnel closed: {reason}"))),
+    }
 }
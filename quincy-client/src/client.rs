@@ -1,29 +1,188 @@
-use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, ToSocketAddrs};
-use std::time::Duration;
+use std::any::Any;
+use std::fmt;
+use std::future::Future;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use ipnet::IpNet;
-use quinn::{Connection, Endpoint};
-use tracing::{debug, info};
-
-use quincy::config::ClientConfig;
-use quincy::constants::QUINN_RUNTIME;
-use quincy::error::ConfigError;
-use quincy::ip_assignment;
-use quincy::network::interface::{Interface, InterfaceIO};
-use quincy::network::socket::bind_socket;
+use quinn::crypto::rustls::HandshakeData;
+use quinn::{Connecting, Connection, ConnectionError, Endpoint, TransportErrorCode, VarInt};
+use serde::{Deserialize, Serialize};
+use tokio::net::lookup_host;
+use tokio::sync::{Notify, watch};
+use tokio::task::JoinSet;
+use tracing::{debug, info, warn};
+
+use quincy::config::{ClientConfig, ClientProtocolConfig, NetworkConfig, alpn_protocol_ids};
+use quincy::constants::{AUTH_FAILED_ERROR_CODE, QUINN_RUNTIME};
+use quincy::error::{AuthError, ConfigError, NetworkError, QuicError, SocketError};
+use quincy::ip_assignment::{self, IpAssignment, PushedNetworkConfig, ResumptionRequest};
+use quincy::network::dns::validate_dns_servers;
+use quincy::network::firewall::{self, KillSwitch};
+use quincy::network::interface::{
+    ActiveInterface, Interface, InterfaceAddress, InterfaceIO, NetworkConfiguration,
+};
+use quincy::network::socket::{bind_socket, check_udp_offload};
 use quincy::{QuincyError, Result};
 
-use crate::relayer::ClientRelayer;
+use crate::discovery::{self, SystemSrvResolver};
+use crate::netmon::ResumeMonitor;
+use crate::relayer::{ClientRelayer, RelayOptions, RelaySignals};
+use crate::stats::ConnectionStats;
 
 /// Default timeout for receiving IP assignment from server.
 const IP_ASSIGNMENT_TIMEOUT: Duration = Duration::from_secs(10);
 
+/// TLS `no_application_protocol` alert, sent by a TLS server that shares no ALPN with us.
+const TLS_ALERT_NO_APPLICATION_PROTOCOL: u8 = 120;
+
+/// Number of seconds in a day, for reporting the remaining account validity.
+const SECONDS_PER_DAY: u64 = 86_400;
+
+/// Delay before a connection attempt to the next server address is started (RFC 8305).
+const CONNECTION_ATTEMPT_DELAY: Duration = Duration::from_millis(250);
+
+/// Longest delay between attempts to re-establish a lost connection.
+const MAX_RECONNECT_BACKOFF: Duration = Duration::from_secs(60);
+
+/// Range of the QUIC transport error codes carrying a TLS alert (RFC 9001, section 4.8).
+const CRYPTO_ERROR_CODES: std::ops::RangeInclusive<u64> = 0x0100..=0x01ff;
+
+/// Connection state of a Quincy client.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum ClientState {
+    /// Not connected
+    #[default]
+    Idle,
+    /// Establishing the QUIC connection (including the authenticating handshake)
+    Connecting,
+    /// Connected, waiting for the server to accept the client and assign an address
+    Authenticating,
+    /// The tunnel is up and relaying packets
+    Connected,
+    /// The connection was lost and is being re-established
+    Reconnecting,
+    /// The connection was closed for being idle and is re-established by outbound traffic
+    Dormant,
+    /// The client is shutting down
+    Disconnecting,
+    /// The client failed to connect or the connection was lost
+    Error {
+        /// Description of the failure
+        message: String,
+    },
+}
+
+impl ClientState {
+    /// Returns whether the tunnel is currently up (or being re-established).
+    pub fn is_running(&self) -> bool {
+        matches!(self, Self::Connected | Self::Reconnecting | Self::Dormant)
+    }
+}
+
+/// Reason why a Quincy client tunnel ended.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ShutdownReason {
+    /// The client was stopped locally (by the user or a shutdown signal)
+    UserRequest,
+    /// The server stopped responding and the connection timed out
+    IdleTimeout,
+    /// The server closed the connection (e.g. the client was kicked or the server shut down)
+    ServerClosed {
+        /// Application error code sent by the server
+        code: u64,
+        /// Reason phrase sent by the server
+        reason: String,
+    },
+    /// The tunnel failed with an error
+    Error {
+        /// Description of the failure
+        message: String,
+    },
+}
+
+impl ShutdownReason {
+    /// Determines the shutdown reason from the error that terminated a connection.
+    ///
+    /// ### Arguments
+    /// - `error` - the error the connection was closed with
+    pub fn from_connection_error(error: &ConnectionError) -> Self {
+        match error {
+            ConnectionError::LocallyClosed => Self::UserRequest,
+            ConnectionError::TimedOut => Self::IdleTimeout,
+            ConnectionError::ApplicationClosed(close) => Self::ServerClosed {
+                code: close.error_code.into_inner(),
+                reason: String::from_utf8_lossy(&close.reason).into_owned(),
+            },
+            error => Self::Error {
+                message: error.to_string(),
+            },
+        }
+    }
+}
+
+impl fmt::Display for ShutdownReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UserRequest => write!(f, "stopped by user"),
+            Self::IdleTimeout => write!(f, "connection timed out"),
+            Self::ServerClosed { code, reason } if reason.is_empty() => {
+                write!(f, "closed by server (code {code})")
+            }
+            Self::ServerClosed { code, reason } => {
+                write!(f, "closed by server (code {code}): {reason}")
+            }
+            Self::Error { message } => write!(f, "{message}"),
+        }
+    }
+}
+
+/// A tunnel interface kept up across connections (`network.persistent`).
+struct PersistentInterface {
+    /// The `ActiveInterface`, type-erased as the client is not generic over the interface type
+    interface: Arc<dyn Any + Send + Sync>,
+    network: Arc<dyn NetworkConfiguration>,
+    addresses: Vec<InterfaceAddress>,
+    mtu: u16,
+    /// The server IP address excluded from the routes and allowed by the kill switch
+    remote_address: IpAddr,
+}
+
 /// Represents a Quincy client that connects to a server and relays packets between the server and a TUN interface.
 pub struct QuincyClient {
     config: ClientConfig,
+    /// A reloaded configuration with changes that take effect when the client is next started
+    pending_config: Option<ClientConfig>,
+    /// The Quinn configuration, kept across reconnects so that TLS sessions can be resumed
+    quinn_config: Option<quinn::ClientConfig>,
+    zero_rtt_accepted: bool,
     relayer: Option<ClientRelayer>,
-    client_address: Option<IpNet>,
+    persistent_interface: Option<PersistentInterface>,
+    interface_address_tx: watch::Sender<Option<IpNet>>,
     server_address: Option<IpNet>,
+    secondary_interface_address: Option<IpNet>,
+    tunnel_mtu: Option<u16>,
+    account_expires_at: Option<SystemTime>,
+    /// The public IP address of the server the tunnel is connected to
+    remote_address: Option<IpAddr>,
+    /// The connection string of the server endpoint the tunnel is connected to
+    active_endpoint: Option<String>,
+    /// The index of the server endpoint tried first by the next connection
+    endpoint_index: usize,
+    /// The token of the last IP assignment, presented to get the same address on reconnects
+    resumption_token: Option<String>,
+    /// The network settings pushed by the server with the last IP assignment
+    pushed: PushedNetworkConfig,
+    /// The mode of the engaged strict kill switch, lifted only when the client is stopped
+    kill_switch: KillSwitch,
+    /// The server endpoints and their addresses allowed by the strict kill switch, connected
+    /// to instead of resolving the endpoints again while it blocks DNS
+    pinned_endpoints: Vec<(String, Vec<SocketAddr>)>,
+    state_tx: watch::Sender<ClientState>,
+    shutdown_reason_tx: watch::Sender<Option<ShutdownReason>>,
+    /// Notified when the relayer needs a new connection
+    signals: RelaySignals,
 }
 
 impl QuincyClient {
@@ -34,9 +193,26 @@ impl QuincyClient {
     pub fn new(config: ClientConfig) -> Self {
         Self {
             config,
+            pending_config: None,
+            quinn_config: None,
+            zero_rtt_accepted: false,
             relayer: None,
-            client_address: None,
+            persistent_interface: None,
+            interface_address_tx: watch::Sender::new(None),
             server_address: None,
+            secondary_interface_address: None,
+            tunnel_mtu: None,
+            account_expires_at: None,
+            remote_address: None,
+            active_endpoint: None,
+            endpoint_index: 0,
+            resumption_token: None,
+            pushed: PushedNetworkConfig::default(),
+            kill_switch: KillSwitch::Disabled,
+            pinned_endpoints: Vec::new(),
+            state_tx: watch::Sender::new(ClientState::Idle),
+            shutdown_reason_tx: watch::Sender::new(None),
+            signals: RelaySignals::default(),
         }
     }
 
@@ -44,69 +220,904 @@ impl QuincyClient {
     ///
     /// Authentication happens during the QUIC handshake (Noise allowed-keys or TLS mTLS).
     /// After the handshake, the server sends the IP assignment over a uni-stream.
+    ///
+    /// With `network.kill_switch_strict`, the kill switch is engaged before connecting and
+    /// stays engaged if starting fails, until the client is stopped.
     pub async fn start<I: InterfaceIO>(&mut self) -> Result<()> {
         if self.relayer.is_some() {
             return Err(QuincyError::system("Client is already started"));
         }
 
-        let (connection, server_addr) = self.connect_to_server().await?;
+        // Reloaded settings that require a new tunnel take effect now
+        if let Some(config) = self.pending_config.take() {
+            self.config = config;
+            self.quinn_config = None;
+        }
+
+        self.set_state(ClientState::Connecting);
+        // A new tunnel prefers the first server endpoint
+        self.endpoint_index = 0;
+
+        match self.establish_tunnel::<I>().await {
+            Ok(()) => {
+                // The relayer may already have stopped and recorded its own state
+                self.state_tx.send_if_modified(|state| {
+                    let authenticated = *state == ClientState::Authenticating;
+                    if authenticated {
+                        *state = ClientState::Connected;
+                    }
+                    authenticated
+                });
+                debug!("Client state: {:?}", self.state());
+                Ok(())
+            }
+            Err(e) => {
+                self.set_state(ClientState::Error {
+                    message: e.to_string(),
+                });
+                Err(e)
+            }
+        }
+    }
+
+    /// Connects to the server, receives the address assignment and starts relaying packets.
+    async fn establish_tunnel<I: InterfaceIO>(&mut self) -> Result<()> {
+        if self.config.network.strict_kill_switch() != KillSwitch::Disabled
+            && self.pinned_endpoints.is_empty()
+        {
+            self.pin_endpoints().await;
+        }
+        self.engage_kill_switch(None)?;
+
+        let (endpoint, connection, server_addr, assignment) = self.connect_to_endpoints().await?;
+
+        self.start_relayer::<I>(endpoint, connection, server_addr, assignment)
+    }
+
+    /// Connects to the first endpoint of the Quincy server that accepts the client.
+    ///
+    /// The endpoints are tried in order, starting at `endpoint_index` and wrapping around.
+    /// `srv://` connection strings are expanded into the endpoints their SRV records point at.
+    ///
+    /// ### Returns
+    /// A tuple of the Quinn endpoint, the connection, the resolved server socket address and
+    /// the IP assignment received from the server.
+    ///
+    /// ### Errors
+    /// Returns the error of the last endpoint tried if no endpoint accepts the client.
+    async fn connect_to_endpoints(
+        &mut self,
+    ) -> Result<(Endpoint, Connection, SocketAddr, IpAssignment)> {
+        let connection_strings: Vec<String> = if self.pinned_endpoints.is_empty() {
+            self.discover_endpoints().await?
+        } else {
+            self.pinned_endpoints
+                .iter()
+                .map(|(connection_string, _)| connection_string.clone())
+                .collect()
+        };
+        let endpoint_count = connection_strings.len();
+        let first = self.endpoint_index % endpoint_count.max(1);
+
+        let mut last_error: QuincyError = ConfigError::MissingField {
+            field: "connection_string".to_string(),
+        }
+        .into();
+        for index in (first..endpoint_count).chain(0..first) {
+            let connection_string = &connection_strings[index];
+
+            match self.connect_to_endpoint(connection_string).await {
+                Ok(connected) => {
+                    if endpoint_count > 1 {
+                        info!("Active server endpoint: {connection_string}");
+                    }
+                    self.endpoint_index = index;
+                    self.active_endpoint = Some(connection_string.clone());
+                    return Ok(connected);
+                }
+                Err(e) => {
+                    if endpoint_count > 1 {
+                        warn!("Server endpoint {connection_string} is unavailable: {e}");
+                    }
+                    last_error = e;
+                }
+            }
+        }
+
+        Err(last_error)
+    }
+
+    /// Resolves the configured connection strings into the endpoints of the Quincy server,
+    /// discovering the endpoints of `srv://` connection strings through their SRV records.
+    ///
+    /// ### Errors
+    /// Returns the error of the last failed discovery if no endpoint is found.
+    async fn discover_endpoints(&self) -> Result<Vec<String>> {
+        let mut endpoints = Vec::new();
+        let mut last_error = None;
+
+        for connection_string in self.config.server_endpoints() {
+            match discovery::resolve_connection_string(connection_string, &SystemSrvResolver).await
+            {
+                Ok(discovered) => endpoints.extend(discovered),
+                Err(e) => {
+                    warn!("Failed to discover the server endpoints of {connection_string}: {e}");
+                    last_error = Some(e);
+                }
+            }
+        }
+
+        match last_error {
+            Some(e) if endpoints.is_empty() => Err(e),
+            _ => Ok(endpoints),
+        }
+    }
+
+    /// Connects to an endpoint of the Quincy server and receives the IP assignment.
+    ///
+    /// ### Arguments
+    /// - `connection_string` - the address and port of the endpoint
+    ///
+    /// ### Returns
+    /// A tuple of the Quinn endpoint, the connection, the resolved server socket address and
+    /// the IP assignment received from the server.
+    async fn connect_to_endpoint(
+        &mut self,
+        connection_string: &str,
+    ) -> Result<(Endpoint, Connection, SocketAddr, IpAssignment)> {
+        let (endpoint, connection, server_addr) = self.connect_to_server(connection_string).await?;
+
+        // Fail fast if the peer is not a Quincy server, before exchanging anything else
+        if let ClientProtocolConfig::Tls(tls) = &self.config.protocol {
+            verify_server_protocol(&connection, &alpn_protocol_ids(&tls.alpn_protocols)?)?;
+        }
+
+        // Reconnects are reported as Reconnecting throughout
+        self.state_tx.send_if_modified(|state| {
+            let connecting = *state == ClientState::Connecting;
+            if connecting {
+                *state = ClientState::Authenticating;
+            }
+            connecting
+        });
+
+        // Ask for the address of the previous assignment before the server assigns one
+        let request = ResumptionRequest {
+            resumption_token: self.resumption_token.clone(),
+        };
+        if let Err(e) =
+            ip_assignment::send_resumption_request(&connection, &request, IP_ASSIGNMENT_TIMEOUT)
+                .await
+        {
+            debug!("Failed to send the resumption request: {e}");
+        }
 
-        // Receive IP assignment from server (sent over uni-stream after handshake)
-        let assignment =
-            ip_assignment::recv_ip_assignment(&connection, IP_ASSIGNMENT_TIMEOUT).await?;
+        let assignment = self.receive_assignment(&connection).await?;
+        if request.resumption_token.is_some() && !assignment.resumed {
+            info!("Server could not resume the previous address, assigned a new one");
+        }
+        self.resumption_token = assignment.resumption_token.clone();
 
+        Ok((endpoint, connection, server_addr, assignment))
+    }
+
+    /// Closes the current connection and establishes a new one, keeping the TUN interface up.
+    ///
+    /// If the server assigns the same addresses and tunnel MTU to the new connection, the
+    /// installed routes and DNS servers are left in place, and only changes to the settings
+    /// pushed by the server are applied. Otherwise the interface is recreated (or, if it is
+    /// persistent and only the addresses changed, has the new addresses applied).
+    ///
+    /// ### Errors
+    /// Returns an error if the client is not started or the new connection cannot
+    /// be established. In the latter case the client is stopped.
+    pub async fn reconnect<I: InterfaceIO>(&mut self) -> Result<()> {
+        let Some(relayer) = self.relayer.as_ref() else {
+            return Err(QuincyError::system("Client is not started"));
+        };
+
+        self.set_state(ClientState::Reconnecting);
+        relayer.detach().await?;
+
+        let result = self.replace_connection::<I>().await;
+        self.finish_reconnect(result).await
+    }
+
+    /// Re-establishes a lost connection, retrying failed attempts with exponential backoff.
+    ///
+    /// Each attempt is reported as `Reconnecting` and each failed attempt as `Error`. The
+    /// TUN interface stays up in between, as with [`QuincyClient::reconnect`]. The server
+    /// endpoint after the lost one is tried first, if several are configured. Retries stop
+    /// after `reconnect_max_attempts` attempts, or as soon as the server rejects the
+    /// client's credentials.
+    ///
+    /// ### Errors
+    /// Returns an error if the client is not started or the connection cannot be
+    /// re-established. In the latter case the client is stopped.
+    pub async fn reconnect_with_backoff<I: InterfaceIO>(&mut self) -> Result<()> {
+        let Some(relayer) = self.relayer.as_ref() else {
+            return Err(QuincyError::system("Client is not started"));
+        };
+
+        self.set_state(ClientState::Reconnecting);
+        relayer.detach().await?;
+
+        // Fail over to the next server endpoint first
+        self.endpoint_index = self.endpoint_index.wrapping_add(1);
+
+        let max_attempts = self.config.connection.reconnect_max_attempts;
+        let initial_backoff = Duration::from_millis(self.config.connection.reconnect_backoff_ms);
+
+        let mut attempt = 1;
+        let result = loop {
+            match self.replace_connection::<I>().await {
+                Ok(()) => break Ok(()),
+                Err(e @ QuincyError::Auth(AuthError::InvalidCredentials)) => {
+                    warn!("Server rejected the credentials, not reconnecting");
+                    break Err(e);
+                }
+                // The tunnel was restarted and could not be brought up again
+                Err(e) if self.relayer.is_none() => break Err(e),
+                Err(e) if max_attempts != 0 && attempt >= max_attempts => {
+                    warn!("Giving up reconnecting after {attempt} attempts");
+                    break Err(e);
+                }
+                Err(e) => {
+                    let delay = reconnect_delay(initial_backoff, attempt);
+                    warn!("Reconnect attempt {attempt} failed, retrying in {delay:?}: {e}");
+                    self.set_state(ClientState::Error {
+                        message: e.to_string(),
+                    });
+                    tokio::time::sleep(delay).await;
+
+                    // The relayer stops on its own, e.g. on a shutdown signal
+                    if self.shutdown_reason().is_some() {
+                        return Ok(());
+                    }
+                    self.set_state(ClientState::Reconnecting);
+                    attempt += 1;
+                }
+            }
+        };
+
+        self.finish_reconnect(result).await
+    }
+
+    /// Records the outcome of re-establishing the connection, stopping the client if it failed.
+    ///
+    /// ### Arguments
+    /// - `result` - the result of establishing the new connection
+    async fn finish_reconnect(&mut self, result: Result<()>) -> Result<()> {
+        if let Err(e) = result {
+            // Tear down whatever is left of the tunnel
+            if self.relayer.is_some() {
+                let _ = self.stop_relayer().await;
+                let _ = self.wait_for_shutdown().await;
+            }
+            self.set_state(ClientState::Error {
+                message: e.to_string(),
+            });
+            return Err(e);
+        }
+
+        self.state_tx.send_if_modified(|state| {
+            let reconnecting = *state == ClientState::Reconnecting;
+            if reconnecting {
+                *state = ClientState::Connected;
+            }
+            reconnecting
+        });
+        debug!("Client state: {:?}", self.state());
+
+        Ok(())
+    }
+
+    /// Establishes a new connection to the server and hands it to the detached relayer.
+    async fn replace_connection<I: InterfaceIO>(&mut self) -> Result<()> {
+        let (endpoint, connection, server_addr, assignment) = self.connect_to_endpoints().await?;
+
+        let tunnel_mtu =
+            ip_assignment::negotiate_mtu(self.config.connection.mtu, assignment.tunnel_mtu);
+        // The routes point at the server address, so they are only kept if it is unchanged
+        if Some(assignment.client_address) != self.interface_address()
+            || Some(assignment.server_address) != self.server_address
+            || assignment.secondary_client_address != self.secondary_interface_address
+            || Some(tunnel_mtu) != self.tunnel_mtu
+            // The routes and the kill switch exclude the public address of the server
+            || Some(server_addr.ip()) != self.remote_address
+        {
+            info!(
+                "Server assigned a new address ({}) or tunnel MTU ({tunnel_mtu}), or the server \
+                 address changed ({server_addr}), restarting the tunnel",
+                assignment.client_address
+            );
+            self.stop_relayer().await?;
+            self.wait_for_shutdown().await?;
+            self.set_state(ClientState::Reconnecting);
+
+            return self.start_relayer::<I>(endpoint, connection, server_addr, assignment);
+        }
+
+        let resume_monitor = self.resume_monitor(endpoint, server_addr)?;
+        let relayer = self
+            .relayer
+            .as_mut()
+            .ok_or_else(|| QuincyError::system("Client is not started"))?;
+        relayer.attach(connection, resume_monitor).await?;
+        self.account_expires_at = account_expiry(&assignment);
+
+        if assignment.pushed != self.pushed {
+            info!("Server pushed new network settings, applying them to the tunnel");
+            self.pushed = assignment.pushed;
+            self.update_network(&self.network_config())?;
+        }
+
+        Ok(())
+    }
+
+    /// Receives the IP assignment from the server (sent over a uni-stream after the handshake).
+    ///
+    /// Addresses of a disabled IP family are dropped from a dual-stack assignment.
+    ///
+    /// ### Errors
+    /// Returns `AuthError::InvalidCredentials` if the server does not accept the client's
+    /// identity, or `ConfigError::InvalidValue` if the IP families of all assigned addresses
+    /// are disabled.
+    async fn receive_assignment(&self, connection: &Connection) -> Result<IpAssignment> {
+        let received = ip_assignment::recv_ip_assignment(connection, IP_ASSIGNMENT_TIMEOUT)
+            .await
+            .map_err(|e| match connection.close_reason() {
+                Some(ConnectionError::ApplicationClosed(close))
+                    if close.error_code == VarInt::from_u32(AUTH_FAILED_ERROR_CODE) =>
+                {
+                    AuthError::InvalidCredentials.into()
+                }
+                _ => e,
+            })?;
+
+        let client_address = received.client_address;
+
+        info!("Received client address: {client_address}");
+        info!("Received server address: {}", received.server_address);
+        if let Some(secondary_address) = received.secondary_client_address {
+            info!("Received secondary client address: {secondary_address}");
+        }
+
+        let configured_mtu = self.config.connection.mtu;
+        let tunnel_mtu = ip_assignment::negotiate_mtu(configured_mtu, received.tunnel_mtu);
+        if tunnel_mtu < configured_mtu {
+            info!(
+                "Server supports a tunnel MTU of {tunnel_mtu}, lowering the configured MTU of {configured_mtu}"
+            );
+        } else {
+            info!("Negotiated tunnel MTU: {tunnel_mtu}");
+        }
+
+        if !received.pushed.is_empty() {
+            if self.config.network.accept_pushed_config {
+                info!("Received pushed network settings: {:?}", received.pushed);
+            } else {
+                info!("Ignoring the network settings pushed by the server");
+            }
+        }
+
+        if let Some(expires_at) = account_expiry(&received) {
+            match expires_at.duration_since(SystemTime::now()) {
+                Ok(remaining) => info!(
+                    "Account expires in {} days",
+                    remaining.as_secs() / SECONDS_PER_DAY
+                ),
+                Err(_) => warn!("Account has expired"),
+            }
+        }
+
+        let Some(assignment) = enabled_families(received, &self.config.network) else {
+            connection.close(VarInt::from_u32(0x01), "Client shutdown".as_bytes());
+            return Err(ConfigError::InvalidValue {
+                field: "network.enabled_families".to_string(),
+                reason: format!(
+                    "the server assigned {client_address}, whose IP family is disabled"
+                ),
+            }
+            .into());
+        };
+
+        Ok(assignment)
+    }
+
+    /// Creates (or reuses the persistent) TUN interface for the assigned address and starts relaying packets.
+    fn start_relayer<I: InterfaceIO>(
+        &mut self,
+        endpoint: Endpoint,
+        connection: Connection,
+        server_addr: SocketAddr,
+        assignment: IpAssignment,
+    ) -> Result<()> {
         let client_address = assignment.client_address;
         let server_address = assignment.server_address;
+        let tunnel_mtu =
+            ip_assignment::negotiate_mtu(self.config.connection.mtu, assignment.tunnel_mtu);
 
-        info!("Received client address: {client_address}");
-        info!("Received server address: {server_address}");
+        self.pushed = assignment.pushed.clone();
+        let network = self.network_config();
+        let dns_servers = match network.managed_dns_servers() {
+            Some(dns_servers) => Some(
+                validate_dns_servers(&dns_servers, network.max_dns_servers).inspect_err(|_| {
+                    connection.close(VarInt::from_u32(0x01), "Client shutdown".as_bytes())
+                })?,
+            ),
+            None => None,
+        };
 
         // Store the addresses for later access
-        self.client_address = Some(client_address);
+        self.set_interface_address(Some(client_address));
         self.server_address = Some(server_address);
+        self.secondary_interface_address = assignment.secondary_client_address;
+        self.tunnel_mtu = Some(tunnel_mtu);
+        self.account_expires_at = account_expiry(&assignment);
+        self.remote_address = Some(server_addr.ip());
 
-        let interface: Interface<I> = Interface::create(
-            client_address,
-            self.config.connection.mtu,
-            Some(server_address.addr()),
-            self.config.network.interface_name.clone(),
-            Some(self.config.network.routes.clone()),
-            Some(self.config.network.dns_servers.clone()),
-            Some(server_addr.ip()),
+        let mut addresses = vec![InterfaceAddress {
+            address: client_address,
+            gateway: Some(server_address.addr()),
+        }];
+        if let (Some(address), Some(server_address)) = (
+            assignment.secondary_client_address,
+            assignment.secondary_server_address,
+        ) {
+            addresses.push(InterfaceAddress {
+                address,
+                gateway: Some(server_address.addr()),
+            });
+        }
+
+        let interface = self.activate_interface::<I>(
+            &network,
+            addresses,
+            tunnel_mtu,
+            dns_servers,
+            server_addr.ip(),
         )?;
+        // Tunnel traffic is blocked until the kill switch allows the interface
+        if self.kill_switch != KillSwitch::Disabled {
+            self.engage_kill_switch(interface.name().as_deref())?;
+        }
+
+        let resume_monitor = self.resume_monitor(endpoint, server_addr)?;
+
+        self.shutdown_reason_tx.send_replace(None);
 
-        let relayer = ClientRelayer::start(interface, connection)?;
+        let relayer = ClientRelayer::start(
+            interface,
+            connection,
+            resume_monitor,
+            self.state_tx.clone(),
+            self.shutdown_reason_tx.clone(),
+            RelayOptions {
+                mss_clamp_mtu: self.config.network.clamp_mss.then_some(tunnel_mtu),
+                receive_tasks: self.config.connection.receive_tasks as usize,
+                transport_mode: self.config.connection.transport_mode,
+                app_ping: self.config.connection.app_ping()?,
+                idle_disconnect: self
+                    .config
+                    .connection
+                    .idle_disconnect_s
+                    .map(Duration::from_secs),
+                auto_reconnect: self.config.connection.auto_reconnect,
+                quality: self.config.connection.quality,
+            },
+            self.signals.clone(),
+        )?;
         self.relayer.replace(relayer);
 
         Ok(())
     }
 
+    /// Returns the configured TUN interface for the given addresses.
+    ///
+    /// A persistent interface of the same type and MTU is reused, with the addresses, routes
+    /// and DNS servers updated where they changed; unchanged routes and DNS servers are left in
+    /// place. Otherwise a new interface is created and configured, and kept for later
+    /// connections if `network.persistent` is enabled.
+    ///
+    /// ### Arguments
+    /// - `network` - the network configuration, merged with the settings pushed by the server
+    /// - `addresses` - the addresses assigned to the interface
+    /// - `mtu` - the negotiated MTU of the interface
+    /// - `dns_servers` - the DNS servers to configure, if DNS is managed
+    /// - `remote_address` - the server's IP address, excluded from the tunnel routes
+    fn activate_interface<I: InterfaceIO>(
+        &mut self,
+        network: &NetworkConfig,
+        addresses: Vec<InterfaceAddress>,
+        mtu: u16,
+        dns_servers: Option<Vec<IpAddr>>,
+        remote_address: IpAddr,
+    ) -> Result<Arc<ActiveInterface<I>>> {
+        if let Some(mut persistent) = self.persistent_interface.take().filter(|persistent| {
+            persistent.mtu == mtu && persistent.remote_address == remote_address
+        }) {
+            if let Ok(interface) = persistent
+                .interface
+                .clone()
+                .downcast::<ActiveInterface<I>>()
+            {
+                if persistent.addresses != addresses {
+                    info!("Applying the new addresses to the persistent interface");
+                    persistent.network.update_addresses(&addresses)?;
+                    persistent.addresses = addresses;
+                }
+                // Only the differences to the installed routes and DNS servers are applied
+                if let Some(routes) = network.managed_routes() {
+                    persistent.network.update_routes(&routes)?;
+                }
+                if let Some(dns_servers) = &dns_servers {
+                    persistent.network.update_dns(dns_servers)?;
+                }
+                self.persistent_interface = Some(persistent);
+
+                return Ok(interface);
+            }
+        }
+
+        let mut route_options = network.managed_route_options();
+        if let Some(split_tunnel) = route_options.split_tunnel.as_mut() {
+            // The marked packets of the tunnel itself are kept out of the split tunnel routing table
+            split_tunnel.fwmark = self.config.connection.socket_mark;
+        }
+
+        let interface: Interface<I> = Interface::create(
+            &addresses,
+            mtu,
+            network.interface_name.clone(),
+            self.config.connection.offload,
+            network.managed_routes(),
+            route_options,
+            dns_servers,
+            network.managed_dns_options(),
+            Some(remote_address),
+        )?;
+        interface.capture_packets(
+            self.config.log.capture_file.as_deref(),
+            self.config.log.capture_max_bytes,
+        )?;
+        let interface = Arc::new(interface.configure()?);
+
+        if network.persistent {
+            self.persistent_interface = Some(PersistentInterface {
+                interface: interface.clone(),
+                network: interface.clone(),
+                addresses,
+                mtu,
+                remote_address,
+            });
+        }
+
+        Ok(interface)
+    }
+
+    /// Resolves the server endpoints before the strict kill switch blocks DNS outside of the
+    /// tunnel. Connections use the resolved addresses until the kill switch is lifted.
+    ///
+    /// A kill switch left engaged by an earlier run already blocks DNS, so the endpoints it
+    /// pinned are used instead. Endpoints that cannot be resolved are blocked by the kill switch.
+    async fn pin_endpoints(&mut self) {
+        if let Some(endpoints) = firewall::load_pinned_endpoints(&firewall::pinned_endpoints_path())
+        {
+            info!("Kill switch is still engaged, connecting to the server addresses it pinned");
+            self.pinned_endpoints = endpoints;
+            return;
+        }
+
+        let endpoints = match self.discover_endpoints().await {
+            Ok(endpoints) => endpoints,
+            Err(e) => {
+                warn!("No server endpoint is allowed by the kill switch: {e}");
+                return;
+            }
+        };
+
+        for connection_string in endpoints {
+            match lookup_host(connection_string.as_str()).await {
+                Ok(server_addrs) => {
+                    self.pinned_endpoints
+                        .push((connection_string, server_addrs.collect()));
+                }
+                Err(e) => warn!(
+                    "Server endpoint {connection_string} cannot be resolved and is blocked by the kill switch: {e}"
+                ),
+            }
+        }
+    }
+
+    /// Engages the strict kill switch, if enabled, allowing the pinned server endpoints and
+    /// the excluded networks, as well as the tunnel interface once it is up.
+    ///
+    /// ### Arguments
+    /// - `tunnel_interface` - the name of the tunnel interface, if it is up yet
+    fn engage_kill_switch(&mut self, tunnel_interface: Option<&str>) -> Result<()> {
+        let mode = self.config.network.strict_kill_switch();
+        if mode == KillSwitch::Disabled {
+            return Ok(());
+        }
+
+        let allowed_networks: Vec<IpNet> = self
+            .pinned_endpoints
+            .iter()
+            .flat_map(|(_, server_addrs)| server_addrs)
+            .map(|server_addr| IpNet::from(server_addr.ip()))
+            .chain(self.config.network.exclude_routes.iter().copied())
+            .collect();
+
+        firewall::engage_kill_switch(
+            tunnel_interface,
+            &allowed_networks,
+            mode == KillSwitch::DryRun,
+        )?;
+        self.kill_switch = mode;
+
+        // Lets a client restarted while the kill switch is engaged reconnect without DNS
+        if mode == KillSwitch::Enabled {
+            let path = firewall::pinned_endpoints_path();
+            if let Err(e) = firewall::save_pinned_endpoints(&path, &self.pinned_endpoints) {
+                warn!("Failed to save the server endpoints pinned by the kill switch: {e}");
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Lifts the strict kill switch, if engaged.
+    fn lift_kill_switch(&mut self) -> Result<()> {
+        if self.kill_switch == KillSwitch::Disabled {
+            return Ok(());
+        }
+
+        firewall::lift_kill_switch(self.kill_switch == KillSwitch::DryRun)?;
+        self.kill_switch = KillSwitch::Disabled;
+        self.pinned_endpoints.clear();
+
+        Ok(())
+    }
+
+    /// Returns whether the strict kill switch is engaged, blocking all traffic outside of the tunnel.
+    pub fn kill_switch_engaged(&self) -> bool {
+        self.kill_switch != KillSwitch::Disabled
+    }
+
+    /// Tears down the persistent TUN interface, along with its routes and DNS servers.
+    ///
+    /// A running relayer keeps using the interface until it is stopped. Dropping
+    /// the client tears the interface down as well.
+    pub fn close_interface(&mut self) {
+        if self.persistent_interface.take().is_some() {
+            info!("Closing the persistent interface");
+        }
+    }
+
+    /// Creates the resume-from-sleep and network change monitor for a connection.
+    ///
+    /// ### Arguments
+    /// - `endpoint` - the endpoint carrying the connection
+    /// - `server_addr` - the address of the server the connection goes to
+    fn resume_monitor(&self, endpoint: Endpoint, server_addr: SocketAddr) -> Result<ResumeMonitor> {
+        // Replacement sockets are bound to the same address and family as the current one
+        let rebind_address = match self.config.connection.local_port {
+            Some(_) => None,
+            None => Some(SocketAddr::new(endpoint.local_addr()?.ip(), 0)),
+        };
+
+        Ok(ResumeMonitor::new(
+            endpoint,
+            server_addr,
+            rebind_address,
+            self.config.connection.send_buffer_size as usize,
+            self.config.connection.recv_buffer_size as usize,
+            self.config.connection.socket_options(),
+        ))
+    }
+
+    /// Applies a new configuration to the running client without reconnecting.
+    ///
+    /// Route and DNS server changes are applied to the live tunnel interface. If settings
+    /// that require a new tunnel changed as well, only the settings that can be applied live
+    /// are taken over, and the whole configuration takes effect the next time the client is
+    /// started.
+    ///
+    /// The configuration should have passed [`ClientConfig::validate`], which may resolve the
+    /// server endpoints and is best run before the client is locked for the reload.
+    ///
+    /// ### Arguments
+    /// - `config` - the new, validated client configuration
+    ///
+    /// ### Errors
+    /// Returns an error if the DNS servers are invalid or the routes or DNS servers
+    /// could not be applied.
+    pub fn reload_config(&mut self, config: ClientConfig) -> Result<()> {
+        let restart_required = self.config.restart_required_changes(&config);
+        let (live_config, pending_config) = if restart_required.is_empty() {
+            (config, None)
+        } else {
+            warn!(
+                "Configuration changes to {} require a restart to take effect",
+                restart_required.join(", ")
+            );
+            (self.config.with_reloadable_settings(&config), Some(config))
+        };
+
+        self.update_network(&live_config.network.merge_pushed(&self.pushed))?;
+
+        info!("Client configuration reloaded");
+        self.config = live_config;
+        self.pending_config = pending_config;
+
+        Ok(())
+    }
+
+    /// Returns the network configuration, merged with the settings pushed by the server.
+    fn network_config(&self) -> NetworkConfig {
+        self.config.network.merge_pushed(&self.pushed)
+    }
+
+    /// Applies the routes and DNS servers of a network configuration to the live tunnel, if any.
+    ///
+    /// ### Arguments
+    /// - `network` - the network configuration to apply
+    ///
+    /// ### Errors
+    /// Returns an error if the DNS servers are invalid or the routes or DNS servers
+    /// could not be applied.
+    fn update_network(&self, network: &NetworkConfig) -> Result<()> {
+        let dns_servers = match network.managed_dns_servers() {
+            Some(dns_servers) => Some(validate_dns_servers(&dns_servers, network.max_dns_servers)?),
+            None => None,
+        };
+
+        let interface_network = match (&self.persistent_interface, &self.relayer) {
+            (Some(persistent), _) => Some(persistent.network.clone()),
+            (None, Some(relayer)) => Some(relayer.network()?),
+            (None, None) => None,
+        };
+
+        if let Some(interface_network) = interface_network {
+            if let Some(routes) = network.managed_routes() {
+                interface_network.update_routes(&routes)?;
+            }
+
+            if let Some(dns_servers) = dns_servers {
+                interface_network.update_dns(&dns_servers)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the current connection state of the client.
+    pub fn state(&self) -> ClientState {
+        self.state_tx.borrow().clone()
+    }
+
+    /// Returns a receiver that is notified of every connection state transition.
+    pub fn subscribe_state(&self) -> watch::Receiver<ClientState> {
+        self.state_tx.subscribe()
+    }
+
     /// Returns whether the client is currently running.
     pub fn is_running(&self) -> bool {
-        // TODO: this will return false if something else called `wait_for_shutdown`
-        self.relayer.is_some()
+        self.state().is_running()
     }
 
-    /// Attempts to stop the client (if running).
+    /// Attempts to stop the client (if running) and lifts the strict kill switch.
+    ///
+    /// A persistent interface stays up until [`QuincyClient::close_interface`] is called.
     pub async fn stop(&mut self) -> Result<()> {
+        self.stop_relayer().await?;
+
+        self.lift_kill_switch()
+    }
+
+    /// Stops the relayer (if running) and clears the connection details, leaving the strict
+    /// kill switch engaged.
+    async fn stop_relayer(&mut self) -> Result<()> {
         if let Some(relayer) = self.relayer.as_mut() {
+            self.state_tx.send_replace(ClientState::Disconnecting);
             relayer.stop().await?;
         }
 
         // Clear stored addresses when stopping
-        self.client_address = None;
+        self.set_interface_address(None);
         self.server_address = None;
+        self.secondary_interface_address = None;
+        self.tunnel_mtu = None;
+        self.account_expires_at = None;
+        self.remote_address = None;
+        self.active_endpoint = None;
 
         Ok(())
     }
 
     /// Waits for the client to stop relaying packets and finishes the shutdown process.
-    pub async fn wait_for_shutdown(&mut self) -> Result<()> {
-        if let Some(relayer) = self.relayer.take() {
-            relayer.wait_for_shutdown().await?;
+    ///
+    /// ### Returns
+    /// - `ShutdownReason` - why the tunnel ended (`UserRequest` if the client was not running)
+    pub async fn wait_for_shutdown(&mut self) -> Result<ShutdownReason> {
+        match self.relayer.take() {
+            Some(relayer) => relayer.wait_for_shutdown().await,
+            None => Ok(ShutdownReason::UserRequest),
         }
+    }
 
-        Ok(())
+    /// Runs the client until the tunnel ends, re-establishing the connection whenever
+    /// outbound traffic wakes up a tunnel that was closed for being idle, or the connection
+    /// is lost with `auto_reconnect` enabled.
+    ///
+    /// ### Returns
+    /// - `ShutdownReason` - why the tunnel ended (`UserRequest` if the client was not running)
+    ///
+    /// The strict kill switch is lifted if the tunnel was stopped by the user (e.g. on a
+    /// shutdown signal), and stays engaged if it ended for any other reason.
+    ///
+    /// ### Errors
+    /// Returns an error if the connection cannot be re-established, in which case the
+    /// client is stopped.
+    pub async fn run<I: InterfaceIO>(&mut self) -> Result<ShutdownReason> {
+        let mut shutdown_reason_rx = self.shutdown_reason_tx.subscribe();
+
+        loop {
+            if self.relayer.is_none() {
+                return self.wait_for_shutdown().await;
+            }
+
+            let connection_lost = tokio::select! {
+                _ = shutdown_reason_rx.wait_for(Option::is_some) => {
+                    let reason = self.wait_for_shutdown().await?;
+                    if reason == ShutdownReason::UserRequest {
+                        self.lift_kill_switch()?;
+                    }
+                    return Ok(reason);
+                }
+                _ = self.signals.idle_wake.notified() => false,
+                _ = self.signals.connection_lost.notified() => true,
+            };
+
+            if connection_lost {
+                self.reconnect_with_backoff::<I>().await?;
+            } else {
+                self.reconnect::<I>().await?;
+            }
+        }
+    }
+
+    /// Returns the signal notified when outbound traffic needs the idle connection to be
+    /// re-established, for callers driving [`QuincyClient::reconnect`] themselves.
+    pub fn idle_wake(&self) -> Arc<Notify> {
+        self.signals.idle_wake.clone()
+    }
+
+    /// Returns the signal notified when the connection was lost with `auto_reconnect`
+    /// enabled, for callers driving [`QuincyClient::reconnect_with_backoff`] themselves.
+    pub fn connection_lost(&self) -> Arc<Notify> {
+        self.signals.connection_lost.clone()
+    }
+
+    /// Returns why the most recent tunnel ended, if it has ended.
+    pub fn shutdown_reason(&self) -> Option<ShutdownReason> {
+        self.shutdown_reason_tx.borrow().clone()
+    }
+
+    /// Records a state transition.
+    fn set_state(&self, state: ClientState) {
+        debug!("Client state: {state:?}");
+        self.state_tx.send_replace(state);
+    }
+
+    /// Records a new interface address, notifying subscribers if it changed.
+    fn set_interface_address(&self, address: Option<IpNet>) {
+        self.interface_address_tx.send_if_modified(|current| {
+            let changed = *current != address;
+            *current = address;
+            changed
+        });
     }
 
     /// Returns a reference to the client relayer, if running.
@@ -114,36 +1125,160 @@ impl QuincyClient {
         self.relayer.as_ref()
     }
 
+    /// Returns a snapshot of the statistics of the tunnel connection.
+    ///
+    /// ### Returns
+    /// - `Option<ConnectionStats>` - the statistics, or `None` if the client is not running
+    ///
+    /// ### Example
+    /// ```
+    /// # use quincy_client::client::QuincyClient;
+    /// # fn report(client: &QuincyClient) {
+    /// match client.stats() {
+    ///     Some(stats) => println!(
+    ///         "Connected to {} for {}s, congestion window {} bytes",
+    ///         stats.server_endpoint.as_deref().unwrap_or("unknown"),
+    ///         stats.connection_duration.as_secs(),
+    ///         stats.congestion_window
+    ///     ),
+    ///     None => println!("Not connected"),
+    /// }
+    /// # }
+    /// ```
+    pub fn stats(&self) -> Option<ConnectionStats> {
+        let relayer = self.relayer.as_ref()?;
+        let connection_stats = relayer.connection().stats();
+        let interface_stats = relayer.interface_stats().unwrap_or_default();
+
+        Some(ConnectionStats {
+            bytes_sent: connection_stats.udp_tx.bytes,
+            bytes_received: connection_stats.udp_rx.bytes,
+            packets_sent: connection_stats.udp_tx.datagrams,
+            packets_received: connection_stats.udp_rx.datagrams,
+            tun_bytes_in: interface_stats.bytes_written,
+            tun_bytes_out: interface_stats.bytes_read,
+            rtt: connection_stats.path.rtt,
+            app_rtt: relayer.app_rtt(),
+            congestion_window: connection_stats.path.cwnd,
+            quality: relayer.connection_quality(),
+            connection_duration: relayer.connection_duration(),
+            client_address: self.client_address(),
+            server_address: self.server_address(),
+            server_endpoint: self.active_endpoint.clone(),
+            tunnel_mtu: self.tunnel_mtu,
+        })
+    }
+
+    /// Returns the address (and tunnel network prefix) the server assigned to the tunnel interface.
+    ///
+    /// ### Returns
+    /// - `Option<IpNet>` - the interface address, or `None` if the client is not connected
+    pub fn interface_address(&self) -> Option<IpNet> {
+        *self.interface_address_tx.borrow()
+    }
+
+    /// Returns a receiver that is notified whenever the interface address changes.
+    ///
+    /// The address is set once the server has assigned it and cleared when the
+    /// client stops, so a reconnect that yields a different address is always observed.
+    pub fn subscribe_interface_address(&self) -> watch::Receiver<Option<IpNet>> {
+        self.interface_address_tx.subscribe()
+    }
+
+    /// Returns the address of the other IP family assigned to the tunnel interface
+    /// when the server runs a dual-stack tunnel.
+    ///
+    /// ### Returns
+    /// - `Option<IpNet>` - the secondary interface address, or `None` if the tunnel is
+    ///   single-stack or the client is not connected
+    pub fn secondary_interface_address(&self) -> Option<IpNet> {
+        self.secondary_interface_address
+    }
+
+    /// Returns the MTU of the tunnel interface, negotiated with the server during authentication.
+    ///
+    /// ### Returns
+    /// - `Option<u16>` - the smaller of the configured MTU and the MTU advertised by the server,
+    ///   or `None` if the client is not connected
+    pub fn tunnel_mtu(&self) -> Option<u16> {
+        self.tunnel_mtu
+    }
+
     /// Returns the client IP address assigned during authentication.
+    ///
+    /// Equivalent to [`QuincyClient::interface_address`].
     pub fn client_address(&self) -> Option<IpNet> {
-        self.client_address
+        self.interface_address()
     }
 
-    /// Returns the server IP address assigned during authentication.
+    /// Returns the server's address inside the tunnel network.
+    ///
+    /// This is the address the server answers on within the tunnel (not its
+    /// public address from the connection string) and is used as the gateway
+    /// of the tunnel interface.
+    ///
+    /// ### Returns
+    /// - `Option<IpNet>` - the server's tunnel address, or `None` if the client is not connected
     pub fn server_address(&self) -> Option<IpNet> {
         self.server_address
     }
 
+    /// Returns the connection string of the server endpoint the tunnel is connected to.
+    ///
+    /// ### Returns
+    /// - `Option<&str>` - the active endpoint, or `None` if the client is not connected
+    pub fn active_endpoint(&self) -> Option<&str> {
+        self.active_endpoint.as_deref()
+    }
+
+    /// Returns whether the server accepted 0-RTT early data on the current connection.
+    ///
+    /// ### Returns
+    /// - `bool` - true if the session was resumed with 0-RTT, false otherwise
+    pub fn zero_rtt_accepted(&self) -> bool {
+        self.zero_rtt_accepted
+    }
+
+    /// Returns when the user's account expires, as announced by the server.
+    ///
+    /// ### Returns
+    /// - `Option<SystemTime>` - the expiry time, or `None` if the account does not expire
+    ///   or the client is not connected
+    pub fn account_expires_at(&self) -> Option<SystemTime> {
+        self.account_expires_at
+    }
+
     /// Connects to the Quincy server.
     ///
+    /// Connection attempts to all addresses the connection string resolves to are raced
+    /// (RFC 8305): IPv6 and IPv4 addresses are interleaved and a new attempt is started every
+    /// `CONNECTION_ATTEMPT_DELAY` or as soon as the previous one fails. Addresses the bound
+    /// socket cannot reach (e.g. IPv6 addresses if dual-stack sockets are not available)
+    /// are skipped.
+    ///
+    /// ### Arguments
+    /// - `connection_string` - the address and port of the server endpoint
+    ///
     /// ### Returns
-    /// A tuple of the Quinn connection and the resolved server socket address.
-    async fn connect_to_server(&self) -> Result<(Connection, SocketAddr)> {
-        let quinn_config = self.config.quinn_client_config()?;
-
-        let (host_part, _port) =
-            self.config
-                .connection_string
-                .rsplit_once(':')
-                .ok_or_else(|| {
-                    QuincyError::Config(ConfigError::InvalidValue {
-                        field: "connection_string".to_string(),
-                        reason: format!(
-                            "expected 'host:port' format, got '{}'",
-                            self.config.connection_string
-                        ),
-                    })
-                })?;
+    /// A tuple of the Quinn endpoint, the connection and the resolved server socket address.
+    async fn connect_to_server(
+        &mut self,
+        connection_string: &str,
+    ) -> Result<(Endpoint, Connection, SocketAddr)> {
+        let quinn_config = match &self.quinn_config {
+            Some(quinn_config) => quinn_config.clone(),
+            None => self
+                .quinn_config
+                .insert(self.config.quinn_client_config()?)
+                .clone(),
+        };
+
+        let (host_part, _port) = connection_string.rsplit_once(':').ok_or_else(|| {
+            QuincyError::Config(ConfigError::InvalidValue {
+                field: "connection_string".to_string(),
+                reason: format!("expected 'host:port' format, got '{connection_string}'"),
+            })
+        })?;
 
         // Strip brackets from IPv6 addresses (e.g., "[::1]" -> "::1")
         let server_hostname = host_part
@@ -151,53 +1286,107 @@ impl QuincyClient {
             .and_then(|s| s.strip_suffix(']'))
             .unwrap_or(host_part);
 
-        let server_addr = self
-            .config
-            .connection_string
-            .to_socket_addrs()?
-            .next()
-            .ok_or_else(|| {
-                QuincyError::connection_failed(format!(
-                    "Connection string '{}' is invalid",
-                    self.config.connection_string
-                ))
-            })?;
+        let pinned_addrs = self
+            .pinned_endpoints
+            .iter()
+            .find(|(pinned, _)| pinned == connection_string)
+            .map(|(_, server_addrs)| server_addrs.clone());
+        let server_addrs: Vec<SocketAddr> = match pinned_addrs {
+            Some(server_addrs) => server_addrs,
+            None => lookup_host(connection_string)
+                .await
+                .map_err(|e| {
+                    debug!("Failed to resolve {connection_string}: {e}");
+                    NetworkError::AddressResolution {
+                        hostname: server_hostname.to_string(),
+                    }
+                })?
+                .collect(),
+        };
+        if server_addrs.is_empty() {
+            return Err(QuincyError::connection_failed(format!(
+                "Connection string '{connection_string}' is invalid"
+            )));
+        }
 
-        info!("Connecting: {}", self.config.connection_string);
+        info!("Connecting: {connection_string}");
 
-        let endpoint = self.create_quinn_endpoint(server_addr)?;
-        let connection = endpoint
-            .connect_with(quinn_config, server_addr, server_hostname)?
-            .await?;
+        let endpoint = self.create_quinn_endpoint(&server_addrs)?;
+        let local_addr = endpoint.local_addr()?;
+        let server_addrs: Vec<SocketAddr> = server_addrs
+            .into_iter()
+            .filter(|server_addr| reaches(local_addr, server_addr))
+            .collect();
+        if server_addrs.is_empty() {
+            return Err(NetworkError::InvalidAddress {
+                address: format!(
+                    "local address {} does not match the address family of {connection_string}",
+                    local_addr.ip()
+                ),
+            }
+            .into());
+        }
 
-        info!("Connection established: {}", self.config.connection_string);
+        let connection_timeout = self.config.connection.connection_timeout()?;
+        let enable_0rtt = self.config.enable_0rtt;
+        let attempts = interleave_families(server_addrs)
+            .into_iter()
+            .map(|server_addr| {
+                let connecting =
+                    endpoint.connect_with(quinn_config.clone(), server_addr, server_hostname);
+                (
+                    server_addr,
+                    await_connection(connecting, connection_timeout, enable_0rtt),
+                )
+            });
 
-        Ok((connection, server_addr))
+        let (server_addr, (connection, zero_rtt_accepted)) =
+            race_connection_attempts(attempts, CONNECTION_ATTEMPT_DELAY).await?;
+        info!("Connection established: {connection_string} ({server_addr})");
+        if zero_rtt_accepted {
+            debug!("Server accepted 0-RTT early data");
+        }
+        self.zero_rtt_accepted = zero_rtt_accepted;
+
+        Ok((endpoint, connection, server_addr))
     }
 
     /// Creates a Quinn endpoint.
     ///
+    /// Without a configured local address, the socket is dual-stack so that server addresses
+    /// of both IP families can be reached. Where dual-stack sockets are not available, an IPv4
+    /// socket is bound instead if the server has IPv4 addresses.
+    ///
     /// ### Arguments
-    /// - `remote_address` - the remote address to connect to
+    /// - `remote_addresses` - the remote addresses to connect to
     ///
     /// ### Returns
     /// - `Endpoint` - the Quinn endpoint
-    fn create_quinn_endpoint(&self, remote_address: SocketAddr) -> Result<Endpoint> {
-        let bind_addr: SocketAddr = SocketAddr::new(
-            match remote_address.ip() {
-                IpAddr::V4(_) => Ipv4Addr::UNSPECIFIED.into(),
-                IpAddr::V6(_) => Ipv6Addr::UNSPECIFIED.into(),
-            },
-            0,
-        );
-        debug!("QUIC socket local address: {:?}", bind_addr);
+    fn create_quinn_endpoint(&self, remote_addresses: &[SocketAddr]) -> Result<Endpoint> {
+        let connection = &self.config.connection;
+        let port = connection.local_port.unwrap_or(0);
+        let bind = |bind_addr: SocketAddr| {
+            debug!("QUIC socket local address: {:?}", bind_addr);
+            bind_socket(
+                bind_addr,
+                connection.send_buffer_size as usize,
+                connection.recv_buffer_size as usize,
+                false,
+                &connection.socket_options(),
+            )
+        };
 
-        let socket = bind_socket(
-            bind_addr,
-            self.config.connection.send_buffer_size as usize,
-            self.config.connection.recv_buffer_size as usize,
-            false,
-        )?;
+        let socket = match bind(client_bind_address(connection.local_address, port)) {
+            Err(QuincyError::Socket(SocketError::NotSupported { operation }))
+                if connection.local_address.is_none()
+                    && remote_addresses.iter().any(SocketAddr::is_ipv4) =>
+            {
+                warn!("Falling back to an IPv4-only socket: {operation} not supported");
+                bind(SocketAddr::new(Ipv4Addr::UNSPECIFIED.into(), port))?
+            }
+            result => result?,
+        };
+        check_udp_offload(&socket, connection.gso_segment_size)?;
 
         let endpoint_config = self
             .config
@@ -208,3 +1397,511 @@ impl QuincyClient {
         Ok(endpoint)
     }
 }
+
+/// Returns the delay before retrying after a failed attempt to re-establish the connection.
+///
+/// ### Arguments
+/// - `initial_backoff` - the delay after the first failed attempt
+/// - `attempt` - the number of failed attempts so far (starting at 1)
+fn reconnect_delay(initial_backoff: Duration, attempt: u32) -> Duration {
+    let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+
+    initial_backoff
+        .checked_mul(factor)
+        .unwrap_or(MAX_RECONNECT_BACKOFF)
+        .min(MAX_RECONNECT_BACKOFF)
+}
+
+/// Returns the account expiry announced in an IP assignment, if any.
+fn account_expiry(assignment: &IpAssignment) -> Option<SystemTime> {
+    assignment
+        .account_expires_at
+        .map(|secs| UNIX_EPOCH + Duration::from_secs(secs))
+}
+
+/// Returns the local address to bind the client socket to for the given remote address.
+///
+/// ### Arguments
+/// - `remote_address` - the remote address to connect to
+/// - `local_address` - the local address to bind to (unspecified if `None`)
+/// - `port` - the local port (0 for an ephemeral port)
+///
+/// ### Errors
+/// Returns `NetworkError::InvalidAddress` if the local address family does not match the
+/// remote address family.
+/// Restricts an IP assignment to the enabled IP families.
+///
+/// The secondary addresses are dropped if their family is disabled. If only the
+/// primary address's family is disabled, the secondary addresses take its place.
+///
+/// ### Arguments
+/// - `assignment` - the assignment received from the server
+/// - `network` - the client network configuration
+///
+/// ### Returns
+/// - `Option<IpAssignment>` - the restricted assignment, or `None` if no assigned family is enabled
+fn enabled_families(mut assignment: IpAssignment, network: &NetworkConfig) -> Option<IpAssignment> {
+    let secondary = assignment
+        .secondary_client_address
+        .zip(assignment.secondary_server_address)
+        .filter(|(address, _)| network.is_family_enabled(&address.addr()));
+    assignment.secondary_client_address = None;
+    assignment.secondary_server_address = None;
+
+    if network.is_family_enabled(&assignment.client_address.addr()) {
+        if let Some((client_address, server_address)) = secondary {
+            assignment.secondary_client_address = Some(client_address);
+            assignment.secondary_server_address = Some(server_address);
+        }
+        return Some(assignment);
+    }
+
+    let (client_address, server_address) = secondary?;
+    assignment.client_address = client_address;
+    assignment.server_address = server_address;
+
+    Some(assignment)
+}
+
+/// Reasons a connection attempt to a single server address can fail.
+#[derive(Debug)]
+enum AttemptError {
+    /// The server did not respond at this address; other addresses may still work.
+    Unreachable(QuincyError),
+    /// The server responded and refused the connection; other addresses will not help.
+    Refused(QuincyError),
+}
+
+/// Waits for a connection attempt to complete within the connection timeout.
+///
+/// With 0-RTT, the handshake of a resumed session is still awaited, so that the connection
+/// is only used once the server has been authenticated.
+///
+/// ### Arguments
+/// - `connecting` - the connection attempt, or the error starting it
+/// - `connection_timeout` - the time to wait for the handshake to complete
+/// - `enable_0rtt` - whether to send 0-RTT early data if a session can be resumed
+///
+/// ### Returns
+/// - `(Connection, bool)` - the connection and whether the server accepted 0-RTT early data
+async fn await_connection(
+    connecting: std::result::Result<Connecting, quinn::ConnectError>,
+    connection_timeout: Duration,
+    enable_0rtt: bool,
+) -> std::result::Result<(Connection, bool), AttemptError> {
+    let connecting = connecting.map_err(|e| AttemptError::Unreachable(e.into()))?;
+    let handshake = async move {
+        let connecting = if enable_0rtt {
+            match connecting.into_0rtt() {
+                Ok((connection, zero_rtt_accepted)) => {
+                    let zero_rtt_accepted = zero_rtt_accepted.await;
+                    return match connection.close_reason() {
+                        Some(e) => Err(e),
+                        None => Ok((connection, zero_rtt_accepted)),
+                    };
+                }
+                // No session to resume, fall back to a full handshake
+                Err(connecting) => connecting,
+            }
+        } else {
+            connecting
+        };
+
+        connecting.await.map(|connection| (connection, false))
+    };
+
+    match tokio::time::timeout(connection_timeout, handshake).await {
+        Ok(Ok(connection)) => Ok(connection),
+        Ok(Err(ConnectionError::ConnectionClosed(close)))
+            if close.error_code
+                == TransportErrorCode::crypto(TLS_ALERT_NO_APPLICATION_PROTOCOL) =>
+        {
+            Err(AttemptError::Refused(not_a_quincy_server()))
+        }
+        // The server aborted the handshake with a TLS alert, rejecting the client's credentials
+        Ok(Err(ConnectionError::ConnectionClosed(close)))
+            if CRYPTO_ERROR_CODES.contains(&u64::from(close.error_code)) =>
+        {
+            Err(AttemptError::Refused(AuthError::InvalidCredentials.into()))
+        }
+        Ok(Err(e @ (ConnectionError::TimedOut | ConnectionError::Reset))) => {
+            Err(AttemptError::Unreachable(e.into()))
+        }
+        Ok(Err(e)) => Err(AttemptError::Refused(e.into())),
+        Err(_) => Err(AttemptError::Unreachable(NetworkError::Timeout.into())),
+    }
+}
+
+/// Races connection attempts, returning the first connection that is established.
+///
+/// Attempts are started in order, each one `attempt_delay` after the previous one or as soon
+/// as the previous one fails. The remaining attempts are cancelled once a connection is
+/// established or the server refuses the connection.
+///
+/// ### Arguments
+/// - `attempts` - the server addresses with their connection attempts, in order of preference
+/// - `attempt_delay` - the delay before the next attempt is started
+///
+/// ### Returns
+/// - `(SocketAddr, T)` - the server address and the established connection
+///
+/// ### Errors
+/// Returns the error of a refused attempt, or `NetworkError::ConnectionFailed` listing all
+/// attempted addresses if none of them could be reached.
+async fn race_connection_attempts<T, F>(
+    attempts: impl IntoIterator<Item = (SocketAddr, F)>,
+    attempt_delay: Duration,
+) -> Result<(SocketAddr, T)>
+where
+    T: Send + 'static,
+    F: Future<Output = std::result::Result<T, AttemptError>> + Send + 'static,
+{
+    let mut attempts = attempts.into_iter();
+    let mut pending = JoinSet::new();
+    let mut failed_addresses = Vec::new();
+    let mut start_next = true;
+
+    loop {
+        if start_next {
+            if let Some((address, attempt)) = attempts.next() {
+                debug!("Attempting connection to {address}");
+                pending.spawn(async move { (address, attempt.await) });
+            }
+            start_next = false;
+        }
+
+        let joined = tokio::select! {
+            joined = pending.join_next() => joined,
+            _ = tokio::time::sleep(attempt_delay) => {
+                start_next = true;
+                continue;
+            }
+        };
+        let Some(joined) = joined else {
+            break;
+        };
+
+        let (address, result) =
+            joined.unwrap_or_else(|e| std::panic::resume_unwind(e.into_panic()));
+        match result {
+            Ok(connection) => return Ok((address, connection)),
+            Err(AttemptError::Refused(e)) => return Err(e),
+            Err(AttemptError::Unreachable(e)) => {
+                warn!("Failed to connect to {address}: {e}");
+                failed_addresses.push(address.to_string());
+                start_next = true;
+            }
+        }
+    }
+
+    Err(QuincyError::connection_failed(failed_addresses.join(", ")))
+}
+
+/// Orders server addresses for connection attempts, alternating between IPv6 and IPv4
+/// addresses and starting with IPv6 (RFC 8305).
+fn interleave_families(addresses: Vec<SocketAddr>) -> Vec<SocketAddr> {
+    let (ipv6, ipv4): (Vec<_>, Vec<_>) = addresses.into_iter().partition(SocketAddr::is_ipv6);
+    let mut ipv6 = ipv6.into_iter();
+    let mut ipv4 = ipv4.into_iter();
+    let mut interleaved = Vec::with_capacity(ipv6.len() + ipv4.len());
+
+    loop {
+        match (ipv6.next(), ipv4.next()) {
+            (None, None) => return interleaved,
+            (v6, v4) => interleaved.extend(v6.into_iter().chain(v4)),
+        }
+    }
+}
+
+/// Returns the address to bind the client QUIC socket to, the dual-stack unspecified IPv6
+/// address unless a local address is configured.
+fn client_bind_address(local_address: Option<IpAddr>, port: u16) -> SocketAddr {
+    SocketAddr::new(local_address.unwrap_or(Ipv6Addr::UNSPECIFIED.into()), port)
+}
+
+/// Returns whether a socket bound to `local_address` can send packets to `remote_address`.
+///
+/// Sockets bound to the unspecified IPv6 address are dual-stack and reach both IP families.
+fn reaches(local_address: SocketAddr, remote_address: &SocketAddr) -> bool {
+    local_address.ip() == IpAddr::V6(Ipv6Addr::UNSPECIFIED)
+        || local_address.is_ipv4() == remote_address.is_ipv4()
+}
+
+/// Verifies that the server negotiated the Quincy ALPN protocol during the TLS handshake.
+///
+/// Closes the connection if the negotiated protocol is missing or unexpected.
+///
+/// ### Arguments
+/// - `connection` - the established QUIC connection
+/// - `alpn_protocols` - the ALPN protocol identifiers offered to the server
+///
+/// ### Errors
+/// Returns `QuicError::ConnectionFailed` if the server is not a Quincy server.
+fn verify_server_protocol(connection: &Connection, alpn_protocols: &[Vec<u8>]) -> Result<()> {
+    let protocol = connection
+        .handshake_data()
+        .and_then(|data| data.downcast::<HandshakeData>().ok())
+        .and_then(|data| data.protocol);
+
+    match protocol {
+        Some(protocol) if alpn_protocols.contains(&protocol) => Ok(()),
+        _ => {
+            connection.close(
+                VarInt::from_u32(0x02),
+                "Unexpected application protocol".as_bytes(),
+            );
+            Err(not_a_quincy_server())
+        }
+    }
+}
+
+/// Returns the error reported when the remote peer does not speak the Quincy protocol.
+fn not_a_quincy_server() -> QuincyError {
+    QuicError::ConnectionFailed {
+        reason: "not a Quincy server".to_string(),
+    }
+    .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use quincy::network::IpFamily;
+    use quinn::ApplicationClose;
+    use std::pin::Pin;
+
+    #[test]
+    fn bind_address_uses_configured_local_address() {
+        let local_address = "192.168.1.10".parse().unwrap();
+
+        let bind_address = client_bind_address(Some(local_address), 40000);
+
+        assert_eq!(bind_address, SocketAddr::new(local_address, 40000));
+    }
+
+    #[test]
+    fn bind_address_defaults_to_dual_stack() {
+        let bind_address = client_bind_address(None, 0);
+
+        assert_eq!(bind_address, "[::]:0".parse().unwrap());
+        assert!(reaches(
+            bind_address,
+            &"198.51.100.1:55555".parse().unwrap()
+        ));
+        assert!(reaches(
+            bind_address,
+            &"[2001:db8::1]:55555".parse().unwrap()
+        ));
+    }
+
+    #[test]
+    fn local_address_only_reaches_its_family() {
+        let local_address = "192.168.1.10:0".parse().unwrap();
+
+        assert!(reaches(
+            local_address,
+            &"198.51.100.1:55555".parse().unwrap()
+        ));
+        assert!(!reaches(
+            local_address,
+            &"[2001:db8::1]:55555".parse().unwrap()
+        ));
+        assert!(!reaches(
+            "[2001:db8::10]:0".parse().unwrap(),
+            &"198.51.100.1:55555".parse().unwrap()
+        ));
+    }
+
+    #[test]
+    fn server_application_close_maps_to_server_closed() {
+        let error = ConnectionError::ApplicationClosed(ApplicationClose {
+            error_code: VarInt::from_u32(0x03),
+            reason: "Client kicked".into(),
+        });
+
+        let reason = ShutdownReason::from_connection_error(&error);
+
+        assert_eq!(
+            reason,
+            ShutdownReason::ServerClosed {
+                code: 0x03,
+                reason: "Client kicked".to_string(),
+            }
+        );
+        assert_eq!(
+            reason.to_string(),
+            "closed by server (code 3): Client kicked"
+        );
+    }
+
+    #[test]
+    fn timeout_and_local_close_map_to_reasons() {
+        assert_eq!(
+            ShutdownReason::from_connection_error(&ConnectionError::TimedOut),
+            ShutdownReason::IdleTimeout
+        );
+        assert_eq!(
+            ShutdownReason::from_connection_error(&ConnectionError::LocallyClosed),
+            ShutdownReason::UserRequest
+        );
+    }
+
+    fn dual_stack_assignment() -> IpAssignment {
+        IpAssignment {
+            client_address: "10.0.0.2/24".parse().unwrap(),
+            server_address: "10.0.0.1/24".parse().unwrap(),
+            secondary_client_address: Some("fd00::2/64".parse().unwrap()),
+            secondary_server_address: Some("fd00::1/64".parse().unwrap()),
+            ..Default::default()
+        }
+    }
+
+    fn network(families: &[IpFamily]) -> NetworkConfig {
+        NetworkConfig {
+            enabled_families: families.to_vec(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn dual_stack_assignment_is_kept_with_both_families_enabled() {
+        let assignment = enabled_families(
+            dual_stack_assignment(),
+            &network(&[IpFamily::V4, IpFamily::V6]),
+        );
+
+        assert_eq!(assignment, Some(dual_stack_assignment()));
+    }
+
+    #[test]
+    fn disabled_secondary_family_is_dropped() {
+        let assignment = enabled_families(dual_stack_assignment(), &network(&[IpFamily::V4]))
+            .expect("IPv4 is enabled");
+
+        assert_eq!(assignment.client_address, "10.0.0.2/24".parse().unwrap());
+        assert_eq!(assignment.secondary_client_address, None);
+        assert_eq!(assignment.secondary_server_address, None);
+    }
+
+    #[test]
+    fn secondary_replaces_disabled_primary_family() {
+        let assignment = enabled_families(dual_stack_assignment(), &network(&[IpFamily::V6]))
+            .expect("IPv6 is enabled");
+
+        assert_eq!(assignment.client_address, "fd00::2/64".parse().unwrap());
+        assert_eq!(assignment.server_address, "fd00::1/64".parse().unwrap());
+        assert_eq!(assignment.secondary_client_address, None);
+    }
+
+    #[test]
+    fn single_stack_assignment_of_disabled_family_is_rejected() {
+        let assignment = IpAssignment {
+            secondary_client_address: None,
+            secondary_server_address: None,
+            tunnel_mtu: None,
+            ..dual_stack_assignment()
+        };
+
+        assert_eq!(
+            enabled_families(assignment, &network(&[IpFamily::V6])),
+            None
+        );
+    }
+
+    #[test]
+    fn reconnect_delay_doubles_up_to_the_cap() {
+        let initial_backoff = Duration::from_millis(500);
+
+        assert_eq!(
+            reconnect_delay(initial_backoff, 1),
+            Duration::from_millis(500)
+        );
+        assert_eq!(reconnect_delay(initial_backoff, 2), Duration::from_secs(1));
+        assert_eq!(reconnect_delay(initial_backoff, 4), Duration::from_secs(4));
+        assert_eq!(reconnect_delay(initial_backoff, 10), MAX_RECONNECT_BACKOFF);
+        assert_eq!(
+            reconnect_delay(initial_backoff, u32::MAX),
+            MAX_RECONNECT_BACKOFF
+        );
+    }
+
+    type Attempt<T> = Pin<Box<dyn Future<Output = std::result::Result<T, AttemptError>> + Send>>;
+
+    fn socket_addr(address: &str) -> SocketAddr {
+        address.parse().unwrap()
+    }
+
+    #[test]
+    fn interleave_families_alternates_starting_with_ipv6() {
+        let addresses = [
+            "10.0.0.1:55555",
+            "10.0.0.2:55555",
+            "10.0.0.3:55555",
+            "[fd00::1]:55555",
+        ]
+        .map(socket_addr)
+        .to_vec();
+
+        assert_eq!(
+            interleave_families(addresses),
+            [
+                "[fd00::1]:55555",
+                "10.0.0.1:55555",
+                "10.0.0.2:55555",
+                "10.0.0.3:55555"
+            ]
+            .map(socket_addr)
+            .to_vec()
+        );
+    }
+
+    #[tokio::test]
+    async fn race_connects_to_good_address_after_unresponsive_one() {
+        let bad = socket_addr("[fd00::1]:55555");
+        let good = socket_addr("10.0.0.1:55555");
+        let attempts: Vec<(SocketAddr, Attempt<_>)> = vec![
+            (bad, Box::pin(std::future::pending())),
+            (good, Box::pin(async { Ok("connection") })),
+        ];
+
+        let result = race_connection_attempts(attempts, Duration::from_millis(10)).await;
+
+        assert_eq!(result.unwrap(), (good, "connection"));
+    }
+
+    #[tokio::test]
+    async fn race_reports_all_unreachable_addresses() {
+        let attempts = ["[fd00::1]:55555", "10.0.0.1:55555"].map(|address| {
+            let failure: std::result::Result<(), _> =
+                Err(AttemptError::Unreachable(NetworkError::Timeout.into()));
+            (socket_addr(address), std::future::ready(failure))
+        });
+
+        let result = race_connection_attempts(attempts, Duration::from_secs(60)).await;
+
+        match result {
+            Err(QuincyError::Network(NetworkError::ConnectionFailed { address })) => {
+                assert_eq!(address, "[fd00::1]:55555, 10.0.0.1:55555");
+            }
+            other => panic!("Expected ConnectionFailed, got {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn race_stops_when_server_refuses_connection() {
+        let attempts: Vec<(SocketAddr, Attempt<_>)> = vec![
+            (
+                socket_addr("[fd00::1]:55555"),
+                Box::pin(async { Err(AttemptError::Refused(not_a_quincy_server())) }),
+            ),
+            (socket_addr("10.0.0.1:55555"), Box::pin(async { Ok(()) })),
+        ];
+
+        let result = race_connection_attempts(attempts, Duration::from_secs(60)).await;
+
+        assert!(matches!(
+            result,
+            Err(QuincyError::Quic(QuicError::ConnectionFailed { .. }))
+        ));
+    }
+}
@@ -0,0 +1,249 @@
+//! Discovery of the server endpoints through DNS SRV records.
+//!
+//! A connection string of the form `srv://example.com` is resolved through the SRV records of
+//! `_quincy._udp.example.com` into the endpoints they point at, so that operators can move and
+//! add servers without touching the client configurations. Without SRV records, the domain
+//! itself is connected to on the default port.
+
+use std::future::Future;
+
+use hickory_resolver::TokioAsyncResolver;
+use hickory_resolver::error::ResolveErrorKind;
+use quincy::Result;
+use quincy::constants::SRV_CONNECTION_SCHEME;
+use quincy::error::NetworkError;
+use tracing::debug;
+
+/// Service and protocol labels prepended to the domain of an SRV connection string.
+pub const SRV_SERVICE: &str = "_quincy._udp";
+
+/// Port of a Quincy server discovered without SRV records.
+pub const DEFAULT_SERVER_PORT: u16 = 55555;
+
+/// An SRV record pointing at a server endpoint.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SrvRecord {
+    /// Lower priorities are tried first
+    pub priority: u16,
+    /// Among records of the same priority, higher weights are tried first
+    pub weight: u16,
+    /// The port of the server
+    pub port: u16,
+    /// The host name of the server
+    pub target: String,
+}
+
+/// Looks up SRV records.
+pub trait SrvResolver: Send + Sync {
+    /// Looks up the SRV records of a name.
+    ///
+    /// ### Arguments
+    /// - `name` - the name to look up, e.g. `_quincy._udp.example.com`
+    ///
+    /// ### Returns
+    /// - `Vec<SrvRecord>` - the records of the name, empty if it has none
+    ///
+    /// ### Errors
+    /// Returns `NetworkError::AddressResolution` if the lookup fails.
+    fn lookup_srv(&self, name: &str) -> impl Future<Output = Result<Vec<SrvRecord>>> + Send;
+}
+
+/// Looks up SRV records with the system's resolver configuration.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemSrvResolver;
+
+impl SrvResolver for SystemSrvResolver {
+    async fn lookup_srv(&self, name: &str) -> Result<Vec<SrvRecord>> {
+        let resolution_failed = || NetworkError::AddressResolution {
+            hostname: name.to_string(),
+        };
+
+        let resolver = TokioAsyncResolver::tokio_from_system_conf().map_err(|e| {
+            debug!("Failed to load the system resolver configuration: {e}");
+            resolution_failed()
+        })?;
+
+        let lookup = match resolver.srv_lookup(name).await {
+            Ok(lookup) => lookup,
+            Err(e) if matches!(e.kind(), ResolveErrorKind::NoRecordsFound { .. }) => {
+                return Ok(Vec::new());
+            }
+            Err(e) => {
+                debug!("SRV lookup of {name} failed: {e}");
+                return Err(resolution_failed().into());
+            }
+        };
+
+        Ok(lookup
+            .iter()
+            .map(|srv| SrvRecord {
+                priority: srv.priority(),
+                weight: srv.weight(),
+                port: srv.port(),
+                target: srv.target().to_utf8(),
+            })
+            .collect())
+    }
+}
+
+/// Resolves a connection string into the endpoints to connect to, in order.
+///
+/// Connection strings other than `srv://<domain>` are returned unchanged. The targets of the
+/// SRV records are ordered by ascending priority and, within a priority, by descending weight.
+/// Without SRV records, the domain is connected to on [`DEFAULT_SERVER_PORT`], resolving its
+/// A/AAAA records instead.
+///
+/// ### Arguments
+/// - `connection_string` - the configured connection string
+/// - `resolver` - the resolver looking up the SRV records
+///
+/// ### Returns
+/// - `Vec<String>` - the `host:port` endpoints to connect to
+///
+/// ### Errors
+/// Returns `NetworkError::AddressResolution` if the SRV lookup fails, or if the records
+/// state that the service is not available at the domain.
+pub async fn resolve_connection_string<R: SrvResolver>(
+    connection_string: &str,
+    resolver: &R,
+) -> Result<Vec<String>> {
+    let Some(domain) = connection_string.strip_prefix(SRV_CONNECTION_SCHEME) else {
+        return Ok(vec![connection_string.to_string()]);
+    };
+
+    let name = format!("{SRV_SERVICE}.{domain}");
+    let mut records = resolver.lookup_srv(&name).await?;
+    if records.is_empty() {
+        debug!("No SRV records for {name}, falling back to the addresses of {domain}");
+        return Ok(vec![format!("{domain}:{DEFAULT_SERVER_PORT}")]);
+    }
+
+    // A single record targeting "." states that the service is unavailable (RFC 2782)
+    records.retain(|record| !record.target.trim_end_matches('.').is_empty());
+    if records.is_empty() {
+        return Err(NetworkError::AddressResolution { hostname: name }.into());
+    }
+
+    records.sort_by(|a, b| {
+        a.priority
+            .cmp(&b.priority)
+            .then_with(|| b.weight.cmp(&a.weight))
+    });
+    debug!(
+        "Discovered {} server endpoints through {name}",
+        records.len()
+    );
+
+    Ok(records
+        .into_iter()
+        .map(|record| format!("{}:{}", record.target.trim_end_matches('.'), record.port))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use quincy::QuincyError;
+
+    /// Resolver answering every lookup with the same records, or failing it.
+    struct MockResolver {
+        records: Option<Vec<SrvRecord>>,
+    }
+
+    impl SrvResolver for MockResolver {
+        async fn lookup_srv(&self, name: &str) -> Result<Vec<SrvRecord>> {
+            assert!(name.starts_with("_quincy._udp."));
+
+            self.records.clone().ok_or_else(|| {
+                NetworkError::AddressResolution {
+                    hostname: name.to_string(),
+                }
+                .into()
+            })
+        }
+    }
+
+    fn record(priority: u16, weight: u16, port: u16, target: &str) -> SrvRecord {
+        SrvRecord {
+            priority,
+            weight,
+            port,
+            target: target.to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn srv_targets_are_ordered_by_priority_and_weight() {
+        let resolver = MockResolver {
+            records: Some(vec![
+                record(20, 0, 55555, "backup.example.com."),
+                record(10, 10, 55556, "light.example.com."),
+                record(10, 90, 55557, "heavy.example.com."),
+            ]),
+        };
+
+        let endpoints = resolve_connection_string("srv://example.com", &resolver)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            endpoints,
+            vec![
+                "heavy.example.com:55557",
+                "light.example.com:55556",
+                "backup.example.com:55555",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn missing_srv_records_fall_back_to_the_domain() {
+        let resolver = MockResolver {
+            records: Some(Vec::new()),
+        };
+
+        let endpoints = resolve_connection_string("srv://example.com", &resolver)
+            .await
+            .unwrap();
+
+        assert_eq!(endpoints, vec!["example.com:55555"]);
+    }
+
+    #[tokio::test]
+    async fn failed_lookups_are_resolution_errors() {
+        let resolver = MockResolver { records: None };
+
+        let result = resolve_connection_string("srv://example.com", &resolver).await;
+
+        assert!(matches!(
+            result,
+            Err(QuincyError::Network(NetworkError::AddressResolution { hostname }))
+                if hostname == "_quincy._udp.example.com"
+        ));
+    }
+
+    #[tokio::test]
+    async fn unavailable_service_is_a_resolution_error() {
+        let resolver = MockResolver {
+            records: Some(vec![record(0, 0, 0, ".")]),
+        };
+
+        let result = resolve_connection_string("srv://example.com", &resolver).await;
+
+        assert!(matches!(
+            result,
+            Err(QuincyError::Network(NetworkError::AddressResolution { .. }))
+        ));
+    }
+
+    #[tokio::test]
+    async fn plain_connection_strings_are_not_looked_up() {
+        let resolver = MockResolver { records: None };
+
+        let endpoints = resolve_connection_string("quincy.example.com:55555", &resolver)
+            .await
+            .unwrap();
+
+        assert_eq!(endpoints, vec!["quincy.example.com:55555"]);
+    }
+}
@@ -0,0 +1,256 @@
+//! Connection health monitoring for the client.
+//!
+//! Samples the path statistics of the connection to the server and grades its
+//! quality, so that the GUI can warn about a struggling tunnel before it fails.
+
+use std::fmt;
+use std::time::Duration;
+
+use quincy::Result;
+use quincy::config::QualityConfig;
+use quinn::{Connection, ConnectionStats};
+use serde::{Deserialize, Serialize};
+use tokio::sync::watch;
+use tracing::debug;
+
+/// Congestion window, in datagrams, that congestion controllers fall back to after
+/// persistent congestion.
+const MIN_CONGESTION_WINDOW_DATAGRAMS: u64 = 2;
+
+/// Quality of the connection to the Quincy server.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ConnectionQuality {
+    /// Round-trip time and packet loss are below the degraded thresholds
+    Good,
+    /// A degraded threshold was reached, or the congestion window has collapsed
+    Degraded,
+    /// A poor threshold was reached
+    Poor,
+}
+
+impl fmt::Display for ConnectionQuality {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Good => write!(f, "Good"),
+            Self::Degraded => write!(f, "Degraded"),
+            Self::Poor => write!(f, "Poor"),
+        }
+    }
+}
+
+/// A snapshot of the path statistics of a connection.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PathSample {
+    /// The smoothed round-trip time
+    pub rtt: Duration,
+    /// The packets sent over the connection so far
+    pub sent_packets: u64,
+    /// The packets declared lost so far
+    pub lost_packets: u64,
+    /// The congestion window in bytes
+    pub cwnd: u64,
+    /// The current maximum UDP payload size
+    pub mtu: u16,
+}
+
+impl From<&ConnectionStats> for PathSample {
+    fn from(stats: &ConnectionStats) -> Self {
+        Self {
+            rtt: stats.path.rtt,
+            sent_packets: stats.path.sent_packets,
+            lost_packets: stats.path.lost_packets,
+            cwnd: stats.path.cwnd,
+            mtu: stats.path.current_mtu,
+        }
+    }
+}
+
+impl PathSample {
+    /// Returns the percentage of the packets sent since an earlier sample that were lost.
+    ///
+    /// ### Arguments
+    /// - `previous` - the earlier sample of the same connection
+    fn loss_percent_since(&self, previous: &PathSample) -> f64 {
+        let sent = self.sent_packets.saturating_sub(previous.sent_packets);
+        let lost = self.lost_packets.saturating_sub(previous.lost_packets);
+
+        if sent == 0 {
+            return 0.0;
+        }
+
+        lost as f64 * 100.0 / sent as f64
+    }
+}
+
+/// Grades the quality of a connection from two consecutive samples of its path statistics.
+///
+/// ### Arguments
+/// - `previous` - the previous sample, against which the packet loss is measured
+/// - `current` - the current sample
+/// - `thresholds` - the thresholds of the quality grades
+///
+/// ### Returns
+/// - `ConnectionQuality` - the worst grade any of the statistics reaches
+pub fn assess_quality(
+    previous: &PathSample,
+    current: &PathSample,
+    thresholds: &QualityConfig,
+) -> ConnectionQuality {
+    let rtt_ms = current.rtt.as_millis() as u64;
+    let loss_percent = current.loss_percent_since(previous);
+
+    if rtt_ms >= thresholds.poor_rtt_ms || loss_percent >= thresholds.poor_loss_percent {
+        return ConnectionQuality::Poor;
+    }
+
+    let congestion_collapsed = current.cwnd <= MIN_CONGESTION_WINDOW_DATAGRAMS * current.mtu as u64;
+    if rtt_ms >= thresholds.degraded_rtt_ms
+        || loss_percent >= thresholds.degraded_loss_percent
+        || congestion_collapsed
+    {
+        return ConnectionQuality::Degraded;
+    }
+
+    ConnectionQuality::Good
+}
+
+/// Periodically samples the path statistics of a connection and grades its quality.
+///
+/// ### Arguments
+/// - `connection` - the connection to the Quincy server
+/// - `thresholds` - the sample interval and the thresholds of the quality grades
+/// - `quality_tx` - receives the quality graded from each sample
+pub async fn monitor_quality(
+    connection: Connection,
+    thresholds: QualityConfig,
+    quality_tx: watch::Sender<Option<ConnectionQuality>>,
+) -> Result<()> {
+    debug!("Started connection quality monitor");
+
+    let mut ticker = tokio::time::interval(Duration::from_secs(thresholds.sample_interval_s));
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    ticker.tick().await;
+
+    let mut previous = PathSample::from(&connection.stats());
+
+    loop {
+        ticker.tick().await;
+
+        let current = PathSample::from(&connection.stats());
+        let quality = assess_quality(&previous, &current, &thresholds);
+        let changed = quality_tx.send_replace(Some(quality)) != Some(quality);
+        if changed {
+            debug!(
+                "Connection quality is {quality} (RTT {:?}, {:.1}% lost, congestion window {} bytes)",
+                current.rtt,
+                current.loss_percent_since(&previous),
+                current.cwnd
+            );
+        }
+
+        previous = current;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A sample of a path with a congestion window of 100 full-size datagrams.
+    fn sample(rtt_ms: u64, sent_packets: u64, lost_packets: u64) -> PathSample {
+        PathSample {
+            rtt: Duration::from_millis(rtt_ms),
+            sent_packets,
+            lost_packets,
+            cwnd: 100 * 1200,
+            mtu: 1200,
+        }
+    }
+
+    #[test]
+    fn healthy_path_is_good() {
+        let quality = assess_quality(
+            &sample(20, 1000, 2),
+            &sample(20, 2000, 4),
+            &QualityConfig::default(),
+        );
+
+        assert_eq!(quality, ConnectionQuality::Good);
+    }
+
+    #[test]
+    fn rtt_thresholds_grade_quality() {
+        let thresholds = QualityConfig::default();
+        let previous = sample(20, 1000, 0);
+
+        assert_eq!(
+            assess_quality(&previous, &sample(149, 2000, 0), &thresholds),
+            ConnectionQuality::Good
+        );
+        assert_eq!(
+            assess_quality(&previous, &sample(150, 2000, 0), &thresholds),
+            ConnectionQuality::Degraded
+        );
+        assert_eq!(
+            assess_quality(&previous, &sample(400, 2000, 0), &thresholds),
+            ConnectionQuality::Poor
+        );
+    }
+
+    #[test]
+    fn loss_is_measured_since_previous_sample() {
+        let thresholds = QualityConfig::default();
+        // Heavy loss early in the connection does not count against later samples
+        let previous = sample(20, 1000, 300);
+
+        assert_eq!(
+            assess_quality(&previous, &sample(20, 2000, 305), &thresholds),
+            ConnectionQuality::Good
+        );
+        assert_eq!(
+            assess_quality(&previous, &sample(20, 2000, 320), &thresholds),
+            ConnectionQuality::Degraded
+        );
+        assert_eq!(
+            assess_quality(&previous, &sample(20, 2000, 350), &thresholds),
+            ConnectionQuality::Poor
+        );
+    }
+
+    #[test]
+    fn idle_path_has_no_loss() {
+        let quality = assess_quality(
+            &sample(20, 1000, 10),
+            &sample(20, 1000, 10),
+            &QualityConfig::default(),
+        );
+
+        assert_eq!(quality, ConnectionQuality::Good);
+    }
+
+    #[test]
+    fn collapsed_congestion_window_is_degraded() {
+        let current = PathSample {
+            cwnd: 2 * 1200,
+            ..sample(20, 2000, 0)
+        };
+
+        let quality = assess_quality(&sample(20, 1000, 0), &current, &QualityConfig::default());
+
+        assert_eq!(quality, ConnectionQuality::Degraded);
+    }
+
+    #[test]
+    fn custom_thresholds_apply() {
+        let thresholds = QualityConfig {
+            degraded_rtt_ms: 600,
+            poor_rtt_ms: 1200,
+            ..QualityConfig::default()
+        };
+
+        // A satellite link with 650 ms RTT is degraded, not poor
+        let quality = assess_quality(&sample(650, 1000, 0), &sample(650, 2000, 0), &thresholds);
+
+        assert_eq!(quality, ConnectionQuality::Degraded);
+    }
+}
@@ -1,2 +1,6 @@
 pub mod client;
+pub mod discovery;
+pub mod health;
+pub mod netmon;
 pub mod relayer;
+pub mod stats;
@@ -0,0 +1,484 @@
+//! Network condition monitoring for the client.
+//!
+//! Detects when the system resumes from sleep, and when the local network
+//! changes (e.g. when roaming between networks), so that the tunnel can be
+//! migrated proactively instead of waiting for the idle timeout to notice
+//! that the path is dead.
+//!
+//! Link, address and route changes are reported by a netlink socket on Linux,
+//! a routing socket on macOS and FreeBSD and the IP Helper API on Windows.
+
+#[cfg(any(target_os = "linux", target_os = "macos", target_os = "freebsd"))]
+mod posix;
+#[cfg(any(target_os = "linux", target_os = "macos", target_os = "freebsd"))]
+use posix::NetworkChangeListener;
+
+#[cfg(target_os = "windows")]
+mod windows;
+#[cfg(target_os = "windows")]
+use windows::NetworkChangeListener;
+
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, UdpSocket};
+use std::time::{Duration, Instant, SystemTime};
+
+use quincy::Result;
+use quincy::network::socket::{SocketOptions, bind_socket};
+use quinn::Endpoint;
+use tracing::{debug, info, warn};
+
+/// How often the clocks are sampled.
+const CHECK_INTERVAL: Duration = Duration::from_secs(5);
+/// Minimum unexplained time jump that is treated as a resume from sleep.
+const RESUME_THRESHOLD: Duration = Duration::from_secs(30);
+/// How long the network must be free of changes before the path is checked.
+const NETWORK_CHANGE_QUIET_PERIOD: Duration = Duration::from_millis(500);
+/// Longest delay of the path check during a continuous storm of network changes.
+const NETWORK_CHANGE_MAX_DELAY: Duration = Duration::from_secs(3);
+
+/// Detects suspend/resume cycles by comparing monotonic and wall-clock time.
+///
+/// On most platforms the monotonic clock stops while the system is suspended,
+/// whereas the wall clock keeps going, so a resume shows up as the wall clock
+/// advancing much further than the monotonic clock between two samples. On
+/// platforms whose monotonic clock keeps counting during sleep, the resume
+/// instead shows up as a sample arriving far later than scheduled.
+#[derive(Debug)]
+pub struct ClockSkewDetector {
+    interval: Duration,
+    threshold: Duration,
+    last_monotonic: Instant,
+    last_wall: SystemTime,
+}
+
+impl ClockSkewDetector {
+    /// Creates a new detector, taking the initial sample from the current time.
+    ///
+    /// ### Arguments
+    /// - `interval` - the expected interval between two checks
+    /// - `threshold` - the minimum unexplained time jump reported as a resume
+    pub fn new(interval: Duration, threshold: Duration) -> Self {
+        Self {
+            interval,
+            threshold,
+            last_monotonic: Instant::now(),
+            last_wall: SystemTime::now(),
+        }
+    }
+
+    /// Samples the current time and checks for a resume since the last sample.
+    ///
+    /// ### Returns
+    /// - `Option<Duration>` - the approximate time spent suspended, if a resume was detected
+    pub fn check(&mut self) -> Option<Duration> {
+        self.check_at(Instant::now(), SystemTime::now())
+    }
+
+    /// Checks for a resume using the given sample instead of the current time.
+    ///
+    /// ### Arguments
+    /// - `monotonic` - the monotonic clock sample
+    /// - `wall` - the wall clock sample
+    ///
+    /// ### Returns
+    /// - `Option<Duration>` - the approximate time spent suspended, if a resume was detected
+    pub fn check_at(&mut self, monotonic: Instant, wall: SystemTime) -> Option<Duration> {
+        let monotonic_elapsed = monotonic.saturating_duration_since(self.last_monotonic);
+        // Backwards wall-clock adjustments are not a resume
+        let wall_elapsed = wall.duration_since(self.last_wall).unwrap_or_default();
+
+        self.last_monotonic = monotonic;
+        self.last_wall = wall;
+
+        let skew = wall_elapsed.saturating_sub(monotonic_elapsed);
+        let overdue = monotonic_elapsed.saturating_sub(self.interval);
+        let suspended_for = skew.max(overdue);
+
+        (suspended_for >= self.threshold).then_some(suspended_for)
+    }
+}
+
+/// Coalesces bursts of events, such as the many link, address and route changes
+/// reported while a network interface comes up, into a single one.
+///
+/// The events settle once none has arrived for the quiet period, or at the latest
+/// once the maximum delay has passed since the first one.
+#[derive(Debug)]
+pub struct Debouncer {
+    quiet_period: Duration,
+    max_delay: Duration,
+    first_event: Option<Instant>,
+    last_event: Option<Instant>,
+}
+
+impl Debouncer {
+    /// Creates a new debouncer without pending events.
+    ///
+    /// ### Arguments
+    /// - `quiet_period` - how long no event may arrive before the pending events settle
+    /// - `max_delay` - the longest time pending events wait to settle
+    pub fn new(quiet_period: Duration, max_delay: Duration) -> Self {
+        Self {
+            quiet_period,
+            max_delay,
+            first_event: None,
+            last_event: None,
+        }
+    }
+
+    /// Records an event.
+    ///
+    /// ### Arguments
+    /// - `now` - when the event arrived
+    pub fn record(&mut self, now: Instant) {
+        self.first_event.get_or_insert(now);
+        self.last_event = Some(now);
+    }
+
+    /// Returns when the pending events settle, or `None` if there are none.
+    pub fn deadline(&self) -> Option<Instant> {
+        let first_event = self.first_event?;
+        let last_event = self.last_event?;
+
+        Some((last_event + self.quiet_period).min(first_event + self.max_delay))
+    }
+
+    /// Checks whether the pending events have settled, clearing them if so.
+    ///
+    /// ### Arguments
+    /// - `now` - the current time
+    ///
+    /// ### Returns
+    /// - `bool` - whether events were pending and have settled
+    pub fn settle(&mut self, now: Instant) -> bool {
+        let settled = self.deadline().is_some_and(|deadline| now >= deadline);
+        if settled {
+            self.first_event = None;
+            self.last_event = None;
+        }
+
+        settled
+    }
+}
+
+/// Migrates the client's QUIC connection to a fresh socket after a resume from sleep
+/// or a change of the local network.
+pub struct ResumeMonitor {
+    endpoint: Endpoint,
+    remote_address: SocketAddr,
+    rebind_address: Option<SocketAddr>,
+    send_buffer_size: usize,
+    recv_buffer_size: usize,
+    socket_options: SocketOptions,
+}
+
+impl ResumeMonitor {
+    /// Creates a new resume monitor.
+    ///
+    /// ### Arguments
+    /// - `endpoint` - the client endpoint carrying the tunnel connection
+    /// - `remote_address` - the address of the server the connection goes to
+    /// - `rebind_address` - the local address to bind replacement sockets to,
+    ///   or `None` if the socket must not be replaced (e.g. a fixed local port is configured)
+    /// - `send_buffer_size` - the send buffer size of replacement sockets
+    /// - `recv_buffer_size` - the receive buffer size of replacement sockets
+    /// - `socket_options` - the options applied to replacement sockets
+    pub fn new(
+        endpoint: Endpoint,
+        remote_address: SocketAddr,
+        rebind_address: Option<SocketAddr>,
+        send_buffer_size: usize,
+        recv_buffer_size: usize,
+        socket_options: SocketOptions,
+    ) -> Self {
+        Self {
+            endpoint,
+            remote_address,
+            rebind_address,
+            send_buffer_size,
+            recv_buffer_size,
+            socket_options,
+        }
+    }
+
+    /// Watches for resumes from sleep and local network changes, and migrates the
+    /// connection whenever one is detected.
+    ///
+    /// Network changes are debounced, and only migrate the connection if the local
+    /// address the server is reached from has changed, so that changes unrelated to
+    /// the path (including the tunnel's own routes) are ignored.
+    ///
+    /// Rebinding moves the connection onto a new path immediately: if the server
+    /// still knows the connection it validates the new path, otherwise it answers
+    /// with a stateless reset and the connection fails right away, handing over to
+    /// auto-reconnect if it is enabled.
+    pub async fn run(self) -> Result<()> {
+        debug!("Started resume-from-sleep and network change monitor");
+
+        let mut detector = ClockSkewDetector::new(CHECK_INTERVAL, RESUME_THRESHOLD);
+        let mut interval = tokio::time::interval(CHECK_INTERVAL);
+        let mut debouncer = Debouncer::new(NETWORK_CHANGE_QUIET_PERIOD, NETWORK_CHANGE_MAX_DELAY);
+        let mut listener = NetworkChangeListener::new()
+            .inspect_err(|e| warn!("Network change notifications are unavailable: {e}"))
+            .ok();
+        let mut local_address = self.local_address();
+
+        loop {
+            let deadline = debouncer.deadline();
+
+            tokio::select! {
+                _ = interval.tick() => {
+                    if let Some(suspended_for) = detector.check() {
+                        info!(
+                            "System resumed after ~{}s, migrating connection",
+                            suspended_for.as_secs()
+                        );
+                        self.migrate();
+                        local_address = self.local_address().or(local_address);
+                    }
+                }
+                result = next_network_change(&mut listener) => match result {
+                    Ok(()) => debouncer.record(Instant::now()),
+                    Err(e) => {
+                        warn!("Stopped listening for network changes: {e}");
+                        listener = None;
+                    }
+                },
+                _ = sleep_until(deadline) => {
+                    if !debouncer.settle(Instant::now()) {
+                        continue;
+                    }
+
+                    // Without a route to the server, wait for the network to come back
+                    let Some(current_address) = self.local_address() else {
+                        debug!("Network changed, the server is unreachable");
+                        continue;
+                    };
+                    if Some(current_address) != local_address {
+                        info!(
+                            "Network changed, reaching the server from {current_address}, migrating connection"
+                        );
+                        self.migrate();
+                    }
+                    local_address = Some(current_address);
+                }
+            }
+        }
+    }
+
+    /// Returns the local address the system sends packets to the server from, or
+    /// `None` if there is no route to the server.
+    fn local_address(&self) -> Option<IpAddr> {
+        let remote_address = match self.remote_address {
+            SocketAddr::V6(address) => match address.ip().to_ipv4_mapped() {
+                Some(ip) => SocketAddr::new(ip.into(), address.port()),
+                None => self.remote_address,
+            },
+            address => address,
+        };
+        let unspecified: IpAddr = match remote_address {
+            SocketAddr::V4(_) => Ipv4Addr::UNSPECIFIED.into(),
+            SocketAddr::V6(_) => Ipv6Addr::UNSPECIFIED.into(),
+        };
+
+        // Connecting a UDP socket only looks up the route, without sending anything
+        let socket = UdpSocket::bind((unspecified, 0)).ok()?;
+        socket.connect(remote_address).ok()?;
+
+        Some(socket.local_addr().ok()?.ip())
+    }
+
+    /// Replaces the endpoint's socket, triggering QUIC connection migration.
+    fn migrate(&self) {
+        let Some(rebind_address) = self.rebind_address else {
+            debug!("Connection migration is disabled with a fixed local port");
+            return;
+        };
+
+        let result = bind_socket(
+            rebind_address,
+            self.send_buffer_size,
+            self.recv_buffer_size,
+            false,
+            &self.socket_options,
+        )
+        .and_then(|socket| Ok(self.endpoint.rebind(socket)?));
+
+        if let Err(e) = result {
+            warn!("Failed to migrate connection after resume: {e}");
+        }
+    }
+}
+
+/// Waits for the next network change, or forever without a listener.
+///
+/// ### Arguments
+/// - `listener` - the listener reporting network changes, if available
+async fn next_network_change(listener: &mut Option<NetworkChangeListener>) -> Result<()> {
+    match listener {
+        Some(listener) => listener.changed().await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Sleeps until the deadline, or forever without one.
+async fn sleep_until(deadline: Option<Instant>) {
+    match deadline {
+        Some(deadline) => tokio::time::sleep_until(deadline.into()).await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Placeholder on platforms without network change notifications.
+#[cfg(not(any(
+    target_os = "linux",
+    target_os = "macos",
+    target_os = "freebsd",
+    target_os = "windows"
+)))]
+struct NetworkChangeListener;
+
+#[cfg(not(any(
+    target_os = "linux",
+    target_os = "macos",
+    target_os = "freebsd",
+    target_os = "windows"
+)))]
+impl NetworkChangeListener {
+    fn new() -> Result<Self> {
+        Err(quincy::QuincyError::system(
+            "not supported on this platform",
+        ))
+    }
+
+    async fn changed(&mut self) -> Result<()> {
+        std::future::pending().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INTERVAL: Duration = Duration::from_secs(5);
+    const THRESHOLD: Duration = Duration::from_secs(30);
+
+    #[test]
+    fn regular_tick_is_not_a_resume() {
+        let mut detector = ClockSkewDetector::new(INTERVAL, THRESHOLD);
+        let (monotonic, wall) = (detector.last_monotonic, detector.last_wall);
+
+        assert_eq!(
+            detector.check_at(monotonic + INTERVAL, wall + INTERVAL),
+            None
+        );
+    }
+
+    #[test]
+    fn wall_clock_jump_triggers_resume() {
+        let mut detector = ClockSkewDetector::new(INTERVAL, THRESHOLD);
+        let (monotonic, wall) = (detector.last_monotonic, detector.last_wall);
+        let sleep = Duration::from_secs(3600);
+
+        // The monotonic clock stood still while the system was suspended
+        let suspended_for = detector.check_at(monotonic + INTERVAL, wall + INTERVAL + sleep);
+
+        assert_eq!(suspended_for, Some(sleep));
+        // The next regular tick no longer reports a resume
+        assert_eq!(
+            detector.check_at(monotonic + INTERVAL * 2, wall + INTERVAL * 2 + sleep),
+            None
+        );
+    }
+
+    #[test]
+    fn overdue_monotonic_tick_triggers_resume() {
+        let mut detector = ClockSkewDetector::new(INTERVAL, THRESHOLD);
+        let (monotonic, wall) = (detector.last_monotonic, detector.last_wall);
+        let sleep = Duration::from_secs(600);
+
+        // Both clocks kept counting during the suspend
+        let suspended_for =
+            detector.check_at(monotonic + INTERVAL + sleep, wall + INTERVAL + sleep);
+
+        assert_eq!(suspended_for, Some(sleep));
+    }
+
+    #[test]
+    fn small_jumps_and_backwards_adjustments_are_ignored() {
+        let mut detector = ClockSkewDetector::new(INTERVAL, THRESHOLD);
+        let (monotonic, wall) = (detector.last_monotonic, detector.last_wall);
+
+        assert_eq!(
+            detector.check_at(
+                monotonic + INTERVAL,
+                wall + INTERVAL + Duration::from_secs(2)
+            ),
+            None
+        );
+        assert_eq!(
+            detector.check_at(monotonic + INTERVAL * 2, wall - Duration::from_secs(3600)),
+            None
+        );
+    }
+
+    const QUIET_PERIOD: Duration = Duration::from_millis(500);
+    const MAX_DELAY: Duration = Duration::from_secs(3);
+
+    #[test]
+    fn debouncer_settles_after_quiet_period() {
+        let mut debouncer = Debouncer::new(QUIET_PERIOD, MAX_DELAY);
+        let start = Instant::now();
+
+        assert_eq!(debouncer.deadline(), None);
+        assert!(!debouncer.settle(start));
+
+        debouncer.record(start);
+        assert_eq!(debouncer.deadline(), Some(start + QUIET_PERIOD));
+        assert!(!debouncer.settle(start + QUIET_PERIOD / 2));
+        assert!(debouncer.settle(start + QUIET_PERIOD));
+
+        // Settled events are cleared
+        assert_eq!(debouncer.deadline(), None);
+        assert!(!debouncer.settle(start + QUIET_PERIOD * 2));
+    }
+
+    #[test]
+    fn debouncer_coalesces_bursts() {
+        let mut debouncer = Debouncer::new(QUIET_PERIOD, MAX_DELAY);
+        let start = Instant::now();
+
+        for offset_ms in [0, 100, 200, 300] {
+            debouncer.record(start + Duration::from_millis(offset_ms));
+        }
+
+        // Each event restarts the quiet period
+        let last_event = start + Duration::from_millis(300);
+        assert_eq!(debouncer.deadline(), Some(last_event + QUIET_PERIOD));
+        assert!(!debouncer.settle(start + QUIET_PERIOD));
+        assert!(debouncer.settle(last_event + QUIET_PERIOD));
+    }
+
+    #[test]
+    fn debouncer_caps_delay_during_event_storm() {
+        let mut debouncer = Debouncer::new(QUIET_PERIOD, MAX_DELAY);
+        let start = Instant::now();
+
+        // An event every 100 ms never leaves a quiet period
+        let mut now = start;
+        while now < start + MAX_DELAY * 2 {
+            debouncer.record(now);
+            if debouncer.settle(now) {
+                break;
+            }
+            now += Duration::from_millis(100);
+        }
+
+        assert_eq!(now, start + MAX_DELAY);
+        // The storm goes on, starting a new round of events
+        debouncer.record(now + Duration::from_millis(100));
+        assert_eq!(
+            debouncer.deadline(),
+            Some(now + Duration::from_millis(100) + QUIET_PERIOD)
+        );
+    }
+}
@@ -0,0 +1,132 @@
+use std::io;
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd, RawFd};
+
+use quincy::Result;
+use tokio::io::unix::AsyncFd;
+
+/// Size of the buffer notifications are read into; larger messages are truncated.
+const MESSAGE_BUFFER_SIZE: usize = 8192;
+
+/// Reports link, address and route changes using a netlink socket (Linux) or a
+/// routing socket (macOS, FreeBSD).
+pub struct NetworkChangeListener {
+    socket: AsyncFd<OwnedFd>,
+    buffer: Box<[u8]>,
+}
+
+impl NetworkChangeListener {
+    /// Opens a socket subscribed to network changes.
+    ///
+    /// ### Errors
+    /// - `QuincyError::Io` - if the socket cannot be opened
+    pub fn new() -> Result<Self> {
+        let socket = open_socket()?;
+
+        Ok(Self {
+            socket: AsyncFd::new(socket)?,
+            buffer: vec![0; MESSAGE_BUFFER_SIZE].into_boxed_slice(),
+        })
+    }
+
+    /// Waits for the next network change.
+    ///
+    /// ### Errors
+    /// - `QuincyError::Io` - if reading from the socket fails
+    pub async fn changed(&mut self) -> Result<()> {
+        loop {
+            let mut guard = self.socket.readable().await?;
+
+            match guard.try_io(|socket| receive(socket.as_raw_fd(), &mut self.buffer)) {
+                Ok(result) => return Ok(result?),
+                Err(_would_block) => continue,
+            }
+        }
+    }
+}
+
+/// Receives a single notification from the socket.
+///
+/// ### Arguments
+/// - `fd` - the socket file descriptor
+/// - `buffer` - the buffer to read the notification into
+fn receive(fd: RawFd, buffer: &mut [u8]) -> io::Result<()> {
+    // SAFETY: the buffer is valid for writes of its whole length
+    let received = unsafe { libc::recv(fd, buffer.as_mut_ptr().cast(), buffer.len(), 0) };
+    if received >= 0 {
+        return Ok(());
+    }
+
+    let error = io::Error::last_os_error();
+    match error.raw_os_error() {
+        // Notifications were dropped because the socket buffer overflowed
+        Some(libc::ENOBUFS) => Ok(()),
+        _ => Err(error),
+    }
+}
+
+/// Opens a netlink socket subscribed to link, address and route changes.
+#[cfg(target_os = "linux")]
+fn open_socket() -> io::Result<OwnedFd> {
+    // SAFETY: plain socket creation, the result is checked below
+    let fd = unsafe {
+        libc::socket(
+            libc::AF_NETLINK,
+            libc::SOCK_RAW | libc::SOCK_NONBLOCK | libc::SOCK_CLOEXEC,
+            libc::NETLINK_ROUTE,
+        )
+    };
+    if fd < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    // SAFETY: the file descriptor was just created and is owned by nothing else
+    let socket = unsafe { OwnedFd::from_raw_fd(fd) };
+
+    // SAFETY: sockaddr_nl is plain old data, for which all zeroes is valid
+    let mut address: libc::sockaddr_nl = unsafe { std::mem::zeroed() };
+    address.nl_family = libc::AF_NETLINK as libc::sa_family_t;
+    address.nl_groups = (libc::RTMGRP_LINK
+        | libc::RTMGRP_IPV4_IFADDR
+        | libc::RTMGRP_IPV6_IFADDR
+        | libc::RTMGRP_IPV4_ROUTE
+        | libc::RTMGRP_IPV6_ROUTE) as u32;
+
+    // SAFETY: the address is a valid sockaddr_nl of the given length
+    let result = unsafe {
+        libc::bind(
+            socket.as_raw_fd(),
+            (&address as *const libc::sockaddr_nl).cast(),
+            std::mem::size_of::<libc::sockaddr_nl>() as libc::socklen_t,
+        )
+    };
+    if result < 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    Ok(socket)
+}
+
+/// Opens a routing socket, which receives all routing and interface messages.
+#[cfg(any(target_os = "macos", target_os = "freebsd"))]
+fn open_socket() -> io::Result<OwnedFd> {
+    // SAFETY: plain socket creation, the result is checked below
+    let fd = unsafe { libc::socket(libc::PF_ROUTE, libc::SOCK_RAW, libc::AF_UNSPEC) };
+    if fd < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    // SAFETY: the file descriptor was just created and is owned by nothing else
+    let socket = unsafe { OwnedFd::from_raw_fd(fd) };
+
+    // SAFETY: fcntl on a valid file descriptor, the results are checked
+    unsafe {
+        if libc::fcntl(fd, libc::F_SETFD, libc::FD_CLOEXEC) < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        let flags = libc::fcntl(fd, libc::F_GETFL);
+        if flags < 0 || libc::fcntl(fd, libc::F_SETFL, flags | libc::O_NONBLOCK) < 0 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+
+    Ok(socket)
+}
@@ -0,0 +1,105 @@
+use std::ffi::c_void;
+use std::sync::Arc;
+
+use quincy::{QuincyError, Result};
+use tokio::sync::Notify;
+use windows_sys::Win32::Foundation::{HANDLE, NO_ERROR};
+use windows_sys::Win32::NetworkManagement::IpHelper::{
+    CancelMibChangeNotify2, MIB_IPFORWARD_ROW2, MIB_NOTIFICATION_TYPE, MIB_UNICASTIPADDRESS_ROW,
+    NotifyRouteChange2, NotifyUnicastIpAddressChange,
+};
+use windows_sys::Win32::Networking::WinSock::AF_UNSPEC;
+
+/// Reports address and route changes using IP Helper notifications.
+pub struct NetworkChangeListener {
+    // Declared first so that the registrations are cancelled, waiting for running
+    // callbacks to return, before the notifier they borrow is dropped
+    registrations: Vec<Registration>,
+    notify: Arc<Notify>,
+}
+
+impl NetworkChangeListener {
+    /// Registers for address and route change notifications.
+    ///
+    /// ### Errors
+    /// - `QuincyError::System` - if a registration fails
+    pub fn new() -> Result<Self> {
+        let notify = Arc::new(Notify::new());
+        let context = Arc::as_ptr(&notify).cast::<c_void>();
+        let mut registrations = Vec::with_capacity(2);
+
+        let mut handle: HANDLE = std::ptr::null_mut();
+        // SAFETY: the context outlives the registration, which is cancelled on drop
+        let result =
+            unsafe { NotifyRouteChange2(AF_UNSPEC, Some(route_changed), context, 0, &mut handle) };
+        if result != NO_ERROR {
+            return Err(QuincyError::system(format!(
+                "Failed to register for route changes: error {result}"
+            )));
+        }
+        registrations.push(Registration(handle));
+
+        let mut handle: HANDLE = std::ptr::null_mut();
+        // SAFETY: the context outlives the registration, which is cancelled on drop
+        let result = unsafe {
+            NotifyUnicastIpAddressChange(AF_UNSPEC, Some(address_changed), context, 0, &mut handle)
+        };
+        if result != NO_ERROR {
+            return Err(QuincyError::system(format!(
+                "Failed to register for address changes: error {result}"
+            )));
+        }
+        registrations.push(Registration(handle));
+
+        Ok(Self {
+            registrations,
+            notify,
+        })
+    }
+
+    /// Waits for the next network change.
+    pub async fn changed(&mut self) -> Result<()> {
+        self.notify.notified().await;
+
+        Ok(())
+    }
+}
+
+/// A change notification registration, cancelled when dropped.
+struct Registration(HANDLE);
+
+// SAFETY: the handle is only used to cancel the registration, which may happen on any thread
+unsafe impl Send for Registration {}
+// SAFETY: the handle is never used through a shared reference
+unsafe impl Sync for Registration {}
+
+impl Drop for Registration {
+    fn drop(&mut self) {
+        // SAFETY: the handle was returned by a successful registration
+        unsafe {
+            CancelMibChangeNotify2(self.0);
+        }
+    }
+}
+
+/// Called by the system when a route changes.
+unsafe extern "system" fn route_changed(
+    context: *const c_void,
+    _row: *const MIB_IPFORWARD_ROW2,
+    _notification_type: MIB_NOTIFICATION_TYPE,
+) {
+    // SAFETY: the context is the listener's notifier, which outlives the registration
+    let notify = unsafe { &*context.cast::<Notify>() };
+    notify.notify_one();
+}
+
+/// Called by the system when a unicast address changes.
+unsafe extern "system" fn address_changed(
+    context: *const c_void,
+    _row: *const MIB_UNICASTIPADDRESS_ROW,
+    _notification_type: MIB_NOTIFICATION_TYPE,
+) {
+    // SAFETY: the context is the listener's notifier, which outlives the registration
+    let notify = unsafe { &*context.cast::<Notify>() };
+    notify.notify_one();
+}
@@ -1,39 +1,189 @@
 use futures::StreamExt;
 use futures::stream::FuturesUnordered;
-use quincy::network::interface::{ActiveInterface, Interface, InterfaceIO};
+use quincy::config::{QualityConfig, TransportMode};
+use quincy::network::interface::{
+    ActiveInterface, InterfaceIO, InterfaceStats, InterfaceStatsProvider, NetworkConfiguration,
+};
+use quincy::network::packet::Packet;
+use quincy::network::transport::{PacketReceiver, PacketSender, decode_ping, encode_ping};
+#[cfg(feature = "profiling")]
+use quincy::utils::profiling::RelayProfiler;
 use quincy::utils::tasks::abort_all;
 use quincy::{QuincyError, Result};
 use quinn::{Connection, VarInt};
-use std::sync::Arc;
+use std::sync::{Arc, Weak};
+use std::time::{Duration, Instant};
 use tokio::signal;
-use tokio::sync::broadcast;
+use tokio::sync::{Notify, broadcast, mpsc, oneshot, watch};
 use tokio::task::JoinHandle;
-use tracing::{debug, info};
+use tracing::{debug, info, warn};
+
+use crate::client::{ClientState, ShutdownReason};
+use crate::health::{ConnectionQuality, monitor_quality};
+use crate::netmon::ResumeMonitor;
+
+/// Length of the IPv4 and TCP headers without options, subtracted from the MTU to get the MSS.
+const IPV4_TCP_HEADERS_LEN: u16 = 40;
+/// Length of the IPv6 and TCP headers without options, subtracted from the MTU to get the MSS.
+const IPV6_TCP_HEADERS_LEN: u16 = 60;
+/// IP protocol number of TCP.
+const IP_PROTOCOL_TCP: u8 = 6;
+/// SYN flag of the TCP header.
+const TCP_FLAG_SYN: u8 = 0x02;
+/// Kind of the TCP option list terminator.
+const TCP_OPTION_END: u8 = 0;
+/// Kind of the TCP no-operation (padding) option.
+const TCP_OPTION_NOP: u8 = 1;
+/// Kind of the TCP maximum segment size option.
+const TCP_OPTION_MSS: u8 = 2;
+/// Interval at which the tunnel traffic is checked for idleness.
+const IDLE_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Settings of the packet relay.
+#[derive(Clone, Copy, Debug)]
+pub struct RelayOptions {
+    /// The tunnel MTU to clamp the MSS of TCP SYN packets to, if enabled
+    pub mss_clamp_mtu: Option<u16>,
+    /// The number of tasks relaying datagrams from the server to the interface
+    pub receive_tasks: usize,
+    /// How packets are relayed over the connection
+    pub transport_mode: TransportMode,
+    /// The interval and echo deadline of the application keep-alive pings, if enabled
+    pub app_ping: Option<(Duration, Duration)>,
+    /// The period without tunnel traffic after which the connection is closed, if enabled
+    pub idle_disconnect: Option<Duration>,
+    /// Whether to wait for a new connection instead of stopping when the connection is lost
+    pub auto_reconnect: bool,
+    /// The sample interval and thresholds of the connection quality
+    pub quality: QualityConfig,
+}
+
+/// Signals through which the relayer asks the client for a new connection.
+#[derive(Clone, Debug, Default)]
+pub struct RelaySignals {
+    /// Notified when outbound traffic needs the idle connection to be re-established
+    pub idle_wake: Arc<Notify>,
+    /// Notified when the connection was lost and is to be re-established
+    pub connection_lost: Arc<Notify>,
+}
+
+/// Channels through which the relay task reports to the client.
+struct RelayReports {
+    /// Receives the client state when the connection is closed for being idle or lost
+    state_tx: watch::Sender<ClientState>,
+    /// Receives the round-trip time of the application keep-alive pings
+    app_rtt_tx: watch::Sender<Option<Duration>>,
+    /// Receives the quality graded from the path statistics of the connection
+    quality_tx: watch::Sender<Option<ConnectionQuality>>,
+    /// Notified when a new connection is needed
+    signals: RelaySignals,
+}
+
+/// Tracks how long the tunnel has relayed no payload.
+///
+/// Only packets read from or written to the TUN interface count as activity, so neither
+/// QUIC nor application keep-alives keep the tunnel from being idle.
+struct IdleTimer {
+    /// The period without payload after which the tunnel is idle
+    timeout: Duration,
+    /// The payload bytes relayed as of the last activity
+    payload_bytes: u64,
+    /// When payload was last seen
+    active_at: Instant,
+}
+
+/// Commands that move the relayer between connections while keeping the interface up.
+enum RelayerCommand {
+    /// Stop relaying over the current connection and close it
+    Detach { ack_tx: oneshot::Sender<()> },
+    /// Start relaying over a new connection
+    Attach {
+        connection: Connection,
+        resume_monitor: ResumeMonitor,
+    },
+}
 
 pub struct ClientRelayer {
     connection: Connection,
-    relayer_task: JoinHandle<Result<()>>,
+    /// When the current connection was attached to the relayer
+    connected_at: Instant,
+    relayer_task: JoinHandle<ShutdownReason>,
     shutdown_tx: broadcast::Sender<()>,
+    command_tx: mpsc::Sender<RelayerCommand>,
+    network: Weak<dyn NetworkConfiguration>,
+    stats: Weak<dyn InterfaceStatsProvider>,
+    app_rtt_rx: watch::Receiver<Option<Duration>>,
+    quality_rx: watch::Receiver<Option<ConnectionQuality>>,
 }
 
 impl ClientRelayer {
     /// Creates a new instance of the client relayer and starts relaying packets between
     /// the TUN interface and the QUIC connection.
-    pub fn start(interface: Interface<impl InterfaceIO>, connection: Connection) -> Result<Self> {
+    ///
+    /// ### Arguments
+    /// - `interface` - the configured TUN interface to relay packets from/to
+    /// - `connection` - the connection to the Quincy server
+    /// - `resume_monitor` - migrates the connection when the system resumes from sleep
+    /// - `state_tx` - receives the client state once relaying stops
+    /// - `shutdown_reason_tx` - receives the reason relaying stopped
+    /// - `options` - the settings of the packet relay
+    /// - `signals` - notified when a new connection is needed
+    pub fn start(
+        interface: Arc<ActiveInterface<impl InterfaceIO>>,
+        connection: Connection,
+        resume_monitor: ResumeMonitor,
+        state_tx: watch::Sender<ClientState>,
+        shutdown_reason_tx: watch::Sender<Option<ShutdownReason>>,
+        options: RelayOptions,
+        signals: RelaySignals,
+    ) -> Result<Self> {
         let (shutdown_tx, shutdown_rx) = broadcast::channel(1);
-        let active = interface.configure()?;
-        let active = Arc::new(active);
+        let (command_tx, command_rx) = mpsc::channel(1);
+        let network: Weak<dyn NetworkConfiguration> = Arc::downgrade(&interface);
+        let stats: Weak<dyn InterfaceStatsProvider> = Arc::downgrade(&interface);
+        let (app_rtt_tx, app_rtt_rx) = watch::channel(None);
+        let (quality_tx, quality_rx) = watch::channel(None);
+        let reports = RelayReports {
+            state_tx: state_tx.clone(),
+            app_rtt_tx,
+            quality_tx,
+            signals,
+        };
 
-        let relayer_task = tokio::spawn(Self::relay_packets(
-            active.clone(),
+        let relay = Self::relay_packets(
+            interface,
             connection.clone(),
+            resume_monitor,
             shutdown_rx,
-        ));
+            command_rx,
+            options,
+            reports,
+        );
+        let relayer_task = tokio::spawn(async move {
+            let reason = relay.await;
+            info!("Tunnel closed: {reason}");
+
+            state_tx.send_replace(match &reason {
+                ShutdownReason::UserRequest => ClientState::Idle,
+                reason => ClientState::Error {
+                    message: reason.to_string(),
+                },
+            });
+            shutdown_reason_tx.send_replace(Some(reason.clone()));
+
+            reason
+        });
 
         Ok(Self {
             connection,
+            connected_at: Instant::now(),
             relayer_task,
             shutdown_tx,
+            command_tx,
+            network,
+            stats,
+            app_rtt_rx,
+            quality_rx,
         })
     }
 
@@ -48,98 +198,841 @@ impl ClientRelayer {
     }
 
     /// Waits for the relayer task to finish. Consumes this Relayer instance.
-    pub async fn wait_for_shutdown(self) -> Result<()> {
+    ///
+    /// ### Returns
+    /// - `ShutdownReason` - why relaying stopped
+    pub async fn wait_for_shutdown(self) -> Result<ShutdownReason> {
         // Wait for the relayer task to finish
         self.relayer_task
             .await
-            .map_err(|_| QuincyError::system("Relayer task failed"))?
+            .map_err(|_| QuincyError::system("Relayer task failed"))
+    }
+
+    /// Stops relaying over the current connection and closes it, keeping the TUN interface up.
+    ///
+    /// No packets are relayed until a new connection is attached with
+    /// [`ClientRelayer::attach`].
+    pub async fn detach(&self) -> Result<()> {
+        let (ack_tx, ack_rx) = oneshot::channel();
+
+        self.command_tx
+            .send(RelayerCommand::Detach { ack_tx })
+            .await
+            .map_err(|_| QuincyError::system("Relayer is not running"))?;
+        ack_rx
+            .await
+            .map_err(|_| QuincyError::system("Relayer is not running"))
+    }
+
+    /// Resumes relaying over a new connection to the Quincy server.
+    ///
+    /// ### Arguments
+    /// - `connection` - the new connection to the Quincy server
+    /// - `resume_monitor` - migrates the new connection when the system resumes from sleep
+    pub async fn attach(
+        &mut self,
+        connection: Connection,
+        resume_monitor: ResumeMonitor,
+    ) -> Result<()> {
+        self.command_tx
+            .send(RelayerCommand::Attach {
+                connection: connection.clone(),
+                resume_monitor,
+            })
+            .await
+            .map_err(|_| QuincyError::system("Relayer is not running"))?;
+        self.connection = connection;
+        self.connected_at = Instant::now();
+
+        Ok(())
     }
 
     pub fn connection(&self) -> &Connection {
         &self.connection
     }
 
+    /// Returns how long the current connection has been relaying.
+    ///
+    /// ### Returns
+    /// - `Duration` - the time since the connection was attached to the relayer
+    pub fn connection_duration(&self) -> Duration {
+        self.connected_at.elapsed()
+    }
+
+    /// Returns the round-trip time of the last echoed application keep-alive ping.
+    ///
+    /// ### Returns
+    /// - `Option<Duration>` - the round-trip time, or `None` if pings are disabled or
+    ///   no ping of the current connection has been echoed yet
+    pub fn app_rtt(&self) -> Option<Duration> {
+        *self.app_rtt_rx.borrow()
+    }
+
+    /// Returns the quality of the connection, graded from its sampled path statistics.
+    ///
+    /// ### Returns
+    /// - `Option<ConnectionQuality>` - the quality, or `None` if the current connection
+    ///   has not been sampled yet
+    pub fn connection_quality(&self) -> Option<ConnectionQuality> {
+        *self.quality_rx.borrow()
+    }
+
+    /// Returns the runtime network configuration of the TUN interface.
+    ///
+    /// ### Returns
+    /// - `Arc<dyn NetworkConfiguration>` - the live interface configuration
+    ///
+    /// ### Errors
+    /// Returns an error if the relayer has stopped and the interface is gone.
+    pub fn network(&self) -> Result<Arc<dyn NetworkConfiguration>> {
+        self.network
+            .upgrade()
+            .ok_or_else(|| QuincyError::system("Relayer is not running"))
+    }
+
+    /// Returns the traffic counters of the TUN interface.
+    ///
+    /// Unlike the connection statistics, these count only the tunneled payload.
+    ///
+    /// ### Returns
+    /// - `InterfaceStats` - the packets and bytes read from and written to the interface
+    ///
+    /// ### Errors
+    /// Returns an error if the relayer has stopped and the interface is gone.
+    pub fn interface_stats(&self) -> Result<InterfaceStats> {
+        self.stats
+            .upgrade()
+            .map(|stats| stats.stats())
+            .ok_or_else(|| QuincyError::system("Relayer is not running"))
+    }
+
     /// Relays packets between the TUN interface and the Quincy clients.
     ///
+    /// If the tunnel relays no payload for the idle disconnect period, the connection is
+    /// closed and the client is woken up to re-establish it by the next outbound packets,
+    /// which are sent once a new connection is attached. With auto-reconnect, a lost
+    /// connection likewise leaves the interface up until a new connection is attached.
+    ///
     /// ### Arguments
     /// - `connection` - a Quinn connection representing the connection to the Quincy server
     /// - `interface` - the active TUN interface
+    /// - `resume_monitor` - migrates the connection when the system resumes from sleep
+    /// - `command_rx` - receives requests to switch to a new connection
+    /// - `options` - the settings of the packet relay
+    /// - `reports` - channels through which the relay task reports to the client
     async fn relay_packets(
         interface: Arc<ActiveInterface<impl InterfaceIO>>,
         connection: Connection,
+        resume_monitor: ResumeMonitor,
         mut shutdown_rx: broadcast::Receiver<()>,
-    ) -> Result<()> {
+        mut command_rx: mpsc::Receiver<RelayerCommand>,
+        options: RelayOptions,
+        reports: RelayReports,
+    ) -> ShutdownReason {
         let mut tasks = FuturesUnordered::new();
+        #[cfg(feature = "profiling")]
+        let profiler = Arc::new(RelayProfiler::new());
 
-        tasks.extend([
-            tokio::spawn(Self::process_inbound_traffic(
-                connection.clone(),
-                interface.clone(),
-            )),
-            tokio::spawn(Self::process_outgoing_traffic(
-                connection.clone(),
-                interface.clone(),
-            )),
-        ]);
+        let mut current_connection = None;
+        let mut next_connection = Some((connection, resume_monitor));
 
-        let result = tokio::select! {
-            Some(task_result) = tasks.next() => task_result?,
-            _ = shutdown_rx.recv() => {
-                info!("Received shutdown signal, shutting down");
-                Ok(())
-            },
-            _ = signal::ctrl_c() => {
-                info!("Received shutdown signal, shutting down");
-                Ok(())
-            },
+        let mut idle_timer = options.idle_disconnect.map(IdleTimer::new);
+        let mut idle_check = tokio::time::interval(IDLE_CHECK_INTERVAL);
+        // Whether the connection was closed for being idle, and the packets to send once
+        // a new connection is attached
+        let mut dormant = false;
+        let mut pending_packets = Vec::new();
+
+        let reason = loop {
+            if let Some((connection, resume_monitor)) = next_connection.take() {
+                let transport = Self::open_transport(&connection, &options).await;
+                current_connection = Some(connection.clone());
+                let (sender, receivers) = match transport {
+                    Ok(transport) => transport,
+                    Err(e) => break Self::shutdown_reason(current_connection.as_ref(), Err(e)),
+                };
+
+                // Sequence number of the last echoed ping; pings are numbered from 1
+                let (echo_tx, echo_rx) = watch::channel(0);
+                reports.app_rtt_tx.send_replace(None);
+                reports.quality_tx.send_replace(None);
+
+                tasks.extend(receivers.into_iter().map(|receiver| {
+                    tokio::spawn(Self::process_inbound_traffic(
+                        receiver,
+                        interface.clone(),
+                        options.mss_clamp_mtu,
+                        echo_tx.clone(),
+                        #[cfg(feature = "profiling")]
+                        profiler.clone(),
+                    ))
+                }));
+                tasks.extend([
+                    tokio::spawn(Self::process_outgoing_traffic(
+                        sender,
+                        interface.clone(),
+                        options.mss_clamp_mtu,
+                        std::mem::take(&mut pending_packets),
+                        #[cfg(feature = "profiling")]
+                        profiler.clone(),
+                    )),
+                    tokio::spawn(resume_monitor.run()),
+                    tokio::spawn(monitor_quality(
+                        connection.clone(),
+                        options.quality,
+                        reports.quality_tx.clone(),
+                    )),
+                ]);
+
+                if let Some((interval, timeout)) = options.app_ping {
+                    tasks.push(tokio::spawn(Self::ping_server(
+                        move |sequence| Ok(connection.send_datagram(encode_ping(sequence))?),
+                        echo_rx,
+                        reports.app_rtt_tx.clone(),
+                        interval,
+                        timeout,
+                    )));
+                }
+
+                if let Some(idle_timer) = idle_timer.as_mut() {
+                    idle_timer.reset(payload_bytes(interface.stats()), Instant::now());
+                }
+            }
+
+            tokio::select! {
+                Some(task_result) = tasks.next() => {
+                    let result = task_result.map_err(QuincyError::from).and_then(|result| result);
+                    let reason = Self::shutdown_reason(current_connection.as_ref(), result);
+                    if !options.auto_reconnect || reason == ShutdownReason::UserRequest {
+                        break reason;
+                    }
+
+                    warn!("Connection lost ({reason}), waiting for a new connection");
+                    let _ = abort_all(std::mem::take(&mut tasks)).await;
+                    if let Some(connection) = current_connection.take() {
+                        connection.close(VarInt::from_u32(0x01), "Client reconnecting".as_bytes());
+                    }
+                    reports.state_tx.send_replace(ClientState::Reconnecting);
+                    reports.signals.connection_lost.notify_one();
+                },
+                Some(command) = command_rx.recv() => match command {
+                    RelayerCommand::Detach { ack_tx } => {
+                        info!("Detaching from the current connection");
+                        let _ = abort_all(std::mem::take(&mut tasks)).await;
+                        if let Some(connection) = current_connection.take() {
+                            connection.close(VarInt::from_u32(0x01), "Client reconnecting".as_bytes());
+                        }
+                        let _ = ack_tx.send(());
+                    }
+                    RelayerCommand::Attach { connection, resume_monitor } => {
+                        info!("Attaching to a new connection");
+                        let _ = abort_all(std::mem::take(&mut tasks)).await;
+                        if let Some(connection) = current_connection.take() {
+                            connection.close(VarInt::from_u32(0x01), "Client reconnecting".as_bytes());
+                        }
+                        next_connection = Some((connection, resume_monitor));
+                        dormant = false;
+                    }
+                },
+                _ = idle_check.tick(), if idle_timer.is_some() && current_connection.is_some() => {
+                    let payload_bytes = payload_bytes(interface.stats());
+                    let idle = idle_timer
+                        .as_mut()
+                        .is_some_and(|idle_timer| idle_timer.is_idle(payload_bytes, Instant::now()));
+
+                    if idle {
+                        info!("No tunnel traffic, closing the connection until there is outbound traffic");
+                        let _ = abort_all(std::mem::take(&mut tasks)).await;
+                        if let Some(connection) = current_connection.take() {
+                            connection.close(VarInt::from_u32(0x01), "Client idle".as_bytes());
+                        }
+                        dormant = true;
+                        reports.state_tx.send_replace(ClientState::Dormant);
+                    }
+                },
+                packets = interface.read_packets(), if dormant && pending_packets.is_empty() => match packets {
+                    Ok(packets) => {
+                        info!("Outbound traffic on the idle tunnel, requesting a new connection");
+                        pending_packets = packets;
+                        reports.signals.idle_wake.notify_one();
+                    }
+                    Err(e) => break Self::shutdown_reason(None, Err(e)),
+                },
+                _ = shutdown_rx.recv() => {
+                    info!("Received shutdown signal, shutting down");
+                    break ShutdownReason::UserRequest;
+                },
+                _ = signal::ctrl_c() => {
+                    info!("Received shutdown signal, shutting down");
+                    break ShutdownReason::UserRequest;
+                },
+            }
         };
 
         // Stop all running tasks
         let _ = abort_all(tasks).await;
 
+        #[cfg(feature = "profiling")]
+        info!("Relay profile:\n{}", profiler.report());
+
         // Close the QUIC connection
-        connection.close(VarInt::from_u32(0x01), "Client shutdown".as_bytes());
+        if let Some(connection) = current_connection {
+            connection.close(VarInt::from_u32(0x01), "Client shutdown".as_bytes());
+        }
+
+        reason
+    }
+
+    /// Opens the packet sender and receivers relaying over a connection.
+    ///
+    /// Quinn reads the datagrams of a connection from a single socket, but they can be
+    /// written to the interface by several tasks in parallel. A stream is read by one task,
+    /// next to one reading the datagrams the server sends until it has accepted the stream.
+    ///
+    /// ### Arguments
+    /// - `connection` - the connection to the Quincy server
+    /// - `options` - the settings of the packet relay
+    ///
+    /// ### Returns
+    /// - `(PacketSender, Vec<PacketReceiver>)` - the sender and the receivers of packets
+    async fn open_transport(
+        connection: &Connection,
+        options: &RelayOptions,
+    ) -> Result<(PacketSender, Vec<PacketReceiver>)> {
+        match options.transport_mode {
+            TransportMode::Datagram => Ok((
+                PacketSender::Datagram(connection.clone()),
+                (0..options.receive_tasks)
+                    .map(|_| PacketReceiver::Datagram(connection.clone()))
+                    .collect(),
+            )),
+            TransportMode::Stream => {
+                let (sender, receiver) = PacketSender::open_stream(connection).await?;
+                debug!("Relaying packets over a QUIC stream");
+                Ok((
+                    sender,
+                    vec![receiver, PacketReceiver::Datagram(connection.clone())],
+                ))
+            }
+        }
+    }
 
-        result
+    /// Periodically pings the Quincy server, failing if a ping is not echoed in time.
+    ///
+    /// ### Arguments
+    /// - `send_ping` - sends the ping with the given sequence number to the server
+    /// - `echo_rx` - receives the sequence number of the last echoed ping
+    /// - `app_rtt_tx` - receives the round-trip time of each echoed ping
+    /// - `interval` - the interval between pings
+    /// - `timeout` - the deadline for the echo of a ping
+    ///
+    /// ### Errors
+    /// Returns an error if a ping cannot be sent or is not echoed within `timeout`.
+    async fn ping_server(
+        send_ping: impl Fn(u64) -> Result<()>,
+        mut echo_rx: watch::Receiver<u64>,
+        app_rtt_tx: watch::Sender<Option<Duration>>,
+        interval: Duration,
+        timeout: Duration,
+    ) -> Result<()> {
+        debug!("Started application keep-alive task");
+
+        let mut ticker = tokio::time::interval(interval);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+        for sequence in 1.. {
+            ticker.tick().await;
+
+            let sent_at = Instant::now();
+            send_ping(sequence)?;
+
+            let echo =
+                tokio::time::timeout(timeout, echo_rx.wait_for(|echoed| *echoed == sequence));
+            match echo.await {
+                Ok(Ok(_)) => {
+                    app_rtt_tx.send_replace(Some(sent_at.elapsed()));
+                }
+                Ok(Err(_)) => {
+                    return Err(QuincyError::system("Inbound traffic tasks have stopped"));
+                }
+                Err(_) => {
+                    return Err(QuincyError::system(format!(
+                        "Server did not echo the application keep-alive ping within {timeout:?}"
+                    )));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Determines why a relay task stopped.
+    ///
+    /// The close reason of the connection takes precedence over the task error, as
+    /// it tells apart a server kick or idle timeout from local failures.
+    ///
+    /// ### Arguments
+    /// - `connection` - the connection the task was relaying over
+    /// - `result` - the result of the task
+    fn shutdown_reason(connection: Option<&Connection>, result: Result<()>) -> ShutdownReason {
+        let Err(e) = result else {
+            return ShutdownReason::UserRequest;
+        };
+
+        match connection.and_then(Connection::close_reason) {
+            Some(close_reason) => ShutdownReason::from_connection_error(&close_reason),
+            None => ShutdownReason::Error {
+                message: e.to_string(),
+            },
+        }
     }
 
     /// Handles incoming packets from the TUN interface and relays them to the Quincy server.
     ///
     /// ### Arguments
-    /// - `connection` - a Quinn connection representing the connection to the Quincy server
+    /// - `sender` - sends packets to the Quincy server
     /// - `interface` - TUN interface
+    /// - `mss_clamp_mtu` - the tunnel MTU to clamp the MSS of TCP SYN packets to, if enabled
+    /// - `pending_packets` - packets read from the interface before the task was started
+    /// - `profiler` - relay path histograms (`profiling` feature only)
     async fn process_outgoing_traffic(
-        connection: Connection,
+        mut sender: PacketSender,
         interface: Arc<ActiveInterface<impl InterfaceIO>>,
+        mss_clamp_mtu: Option<u16>,
+        pending_packets: Vec<Packet>,
+        #[cfg(feature = "profiling")] profiler: Arc<RelayProfiler>,
     ) -> Result<()> {
         debug!("Started outgoing traffic task (interface -> QUIC tunnel)");
 
+        let mut packets = pending_packets;
+
         loop {
-            let packets = interface.read_packets().await?;
+            #[cfg(feature = "profiling")]
+            let (read_at, batch_size) = (Instant::now(), packets.len());
 
-            for packet in packets {
-                connection
-                    .send_datagram(packet.into())
+            for mut packet in packets {
+                if let Some(mtu) = mss_clamp_mtu {
+                    packet = clamp_mss(packet, mtu);
+                }
+                sender
+                    .send(packet.into())
+                    .await
                     .map_err(|e| QuincyError::system(format!("Failed to send packet: {e}")))?;
             }
+
+            #[cfg(feature = "profiling")]
+            profiler.record_outbound(read_at, batch_size);
+
+            packets = interface.read_packets().await?;
         }
     }
 
     /// Handles incoming packets from the Quincy server and relays them to the TUN interface queue.
     ///
     /// ### Arguments
-    /// - `connection` - a Quinn connection representing the connection to the Quincy server
+    /// - `receiver` - receives packets from the Quincy server
     /// - `interface` - TUN interface
+    /// - `mss_clamp_mtu` - the tunnel MTU to clamp the MSS of TCP SYN packets to, if enabled
+    /// - `echo_tx` - receives the sequence numbers of echoed application keep-alive pings
+    /// - `profiler` - relay path histograms (`profiling` feature only)
     async fn process_inbound_traffic(
-        connection: Connection,
+        mut receiver: PacketReceiver,
         interface: Arc<ActiveInterface<impl InterfaceIO>>,
+        mss_clamp_mtu: Option<u16>,
+        echo_tx: watch::Sender<u64>,
+        #[cfg(feature = "profiling")] profiler: Arc<RelayProfiler>,
     ) -> Result<()> {
         debug!("Started inbound traffic task (QUIC tunnel -> interface)");
 
         loop {
-            let packet = connection.read_datagram().await?.into();
+            let mut packet = receiver.recv().await?;
+            if let Some(sequence) = decode_ping(&packet) {
+                echo_tx.send_replace(sequence);
+                continue;
+            }
+            #[cfg(feature = "profiling")]
+            let received_at = Instant::now();
+
+            if let Some(mtu) = mss_clamp_mtu {
+                packet = clamp_mss(packet, mtu);
+            }
 
             interface.write_packet(packet).await?;
+
+            #[cfg(feature = "profiling")]
+            profiler.record_inbound(received_at);
+        }
+    }
+}
+
+impl IdleTimer {
+    /// Creates a new idle timer.
+    ///
+    /// ### Arguments
+    /// - `timeout` - the period without payload after which the tunnel is idle
+    fn new(timeout: Duration) -> Self {
+        Self {
+            timeout,
+            payload_bytes: 0,
+            active_at: Instant::now(),
+        }
+    }
+
+    /// Restarts the idle period, e.g. for a new connection.
+    ///
+    /// ### Arguments
+    /// - `payload_bytes` - the payload bytes relayed so far
+    /// - `now` - the current time
+    fn reset(&mut self, payload_bytes: u64, now: Instant) {
+        self.payload_bytes = payload_bytes;
+        self.active_at = now;
+    }
+
+    /// Records the payload relayed so far and checks whether the tunnel is idle.
+    ///
+    /// ### Arguments
+    /// - `payload_bytes` - the payload bytes relayed so far
+    /// - `now` - the current time
+    ///
+    /// ### Returns
+    /// - `bool` - whether no payload was relayed for the idle timeout
+    fn is_idle(&mut self, payload_bytes: u64, now: Instant) -> bool {
+        if payload_bytes != self.payload_bytes {
+            self.reset(payload_bytes, now);
+        }
+
+        now.duration_since(self.active_at) >= self.timeout
+    }
+}
+
+/// Returns the payload bytes relayed through the TUN interface in both directions.
+fn payload_bytes(stats: InterfaceStats) -> u64 {
+    stats.bytes_read + stats.bytes_written
+}
+
+/// Clamps the MSS option of a TCP SYN packet to fit the tunnel MTU.
+///
+/// The MSS is lowered to the MTU minus the IP and TCP headers and the TCP checksum
+/// is recomputed. Other packets, SYN packets without an MSS option and SYN packets
+/// whose MSS already fits are returned unchanged.
+///
+/// ### Arguments
+/// - `packet` - the IP packet
+/// - `mtu` - the MTU of the tunnel
+fn clamp_mss(packet: Packet, mtu: u16) -> Packet {
+    let Some((tcp_offset, mss_offset, headers_len)) = locate_mss_option(&packet) else {
+        return packet;
+    };
+
+    let max_mss = mtu.saturating_sub(headers_len);
+    let mss = u16::from_be_bytes([packet[mss_offset], packet[mss_offset + 1]]);
+    if mss <= max_mss {
+        return packet;
+    }
+
+    debug!("Clamping TCP MSS from {mss} to {max_mss}");
+
+    let mut data = packet.to_vec();
+    data[mss_offset..mss_offset + 2].copy_from_slice(&max_mss.to_be_bytes());
+    let checksum = tcp_checksum(&data, tcp_offset);
+    data[tcp_offset + 16..tcp_offset + 18].copy_from_slice(&checksum.to_be_bytes());
+
+    Packet::new(data.into())
+}
+
+/// Locates the MSS option of a TCP SYN packet.
+///
+/// IPv6 packets with extension headers and non-initial IPv4 fragments are not inspected.
+///
+/// ### Returns
+/// The offsets of the TCP header and the MSS value, and the length of the IP and TCP
+/// headers without options, or `None` if the packet is not a TCP SYN packet with an MSS option.
+fn locate_mss_option(data: &[u8]) -> Option<(usize, usize, u16)> {
+    let (tcp_offset, headers_len) = match data.first()? >> 4 {
+        4 => {
+            let header_len = usize::from(data[0] & 0x0f) * 4;
+            let fragment_offset = u16::from_be_bytes([*data.get(6)?, *data.get(7)?]) & 0x1fff;
+            if header_len < 20 || *data.get(9)? != IP_PROTOCOL_TCP || fragment_offset != 0 {
+                return None;
+            }
+            (header_len, IPV4_TCP_HEADERS_LEN)
+        }
+        6 => {
+            if *data.get(6)? != IP_PROTOCOL_TCP {
+                return None;
+            }
+            (40, IPV6_TCP_HEADERS_LEN)
+        }
+        _ => return None,
+    };
+
+    let tcp = data.get(tcp_offset..)?;
+    if tcp.len() < 20 || tcp[13] & TCP_FLAG_SYN == 0 {
+        return None;
+    }
+
+    let tcp_header_len = usize::from(tcp[12] >> 4) * 4;
+    let options = tcp.get(20..tcp_header_len)?;
+
+    let mut index = 0;
+    while index < options.len() {
+        match options[index] {
+            TCP_OPTION_END => return None,
+            TCP_OPTION_NOP => index += 1,
+            kind => {
+                let len = usize::from(*options.get(index + 1)?);
+                if len < 2 || index + len > options.len() {
+                    return None;
+                }
+                if kind == TCP_OPTION_MSS && len == 4 {
+                    return Some((tcp_offset, tcp_offset + 20 + index + 2, headers_len));
+                }
+                index += len;
+            }
+        }
+    }
+
+    None
+}
+
+/// Computes the TCP checksum of a packet, including the IPv4 or IPv6 pseudo-header.
+///
+/// The checksum field itself is left out of the sum.
+///
+/// ### Arguments
+/// - `data` - the IP packet
+/// - `tcp_offset` - the offset of the TCP header within the packet
+fn tcp_checksum(data: &[u8], tcp_offset: usize) -> u16 {
+    let segment = &data[tcp_offset..];
+    let addresses = match data[0] >> 4 {
+        4 => &data[12..20],
+        _ => &data[8..40],
+    };
+
+    let mut sum = ones_complement_sum(addresses)
+        + u32::from(IP_PROTOCOL_TCP)
+        + ones_complement_sum(&(segment.len() as u32).to_be_bytes())
+        + ones_complement_sum(&segment[..16])
+        + ones_complement_sum(&segment[18..]);
+    while sum > 0xffff {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+
+    !(sum as u16)
+}
+
+/// Sums the big-endian 16-bit words of `data`, padding an odd trailing byte with zero.
+fn ones_complement_sum(data: &[u8]) -> u32 {
+    data.chunks(2)
+        .map(|word| {
+            u32::from(u16::from_be_bytes([
+                word[0],
+                word.get(1).copied().unwrap_or(0),
+            ]))
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::Ipv6Addr;
+
+    const IPV4_HEADER_LEN: usize = 20;
+    const IPV6_HEADER_LEN: usize = 40;
+
+    /// Builds a TCP packet with the given flags and options and a valid checksum.
+    fn tcp_packet(version: u8, flags: u8, options: &[u8]) -> Vec<u8> {
+        let tcp_len = 20 + options.len();
+        let mut data = match version {
+            4 => {
+                let mut header = vec![0; IPV4_HEADER_LEN];
+                header[0] = 0x45;
+                header[2..4].copy_from_slice(&((IPV4_HEADER_LEN + tcp_len) as u16).to_be_bytes());
+                header[8] = 64;
+                header[9] = IP_PROTOCOL_TCP;
+                header[12..16].copy_from_slice(&[10, 0, 0, 2]);
+                header[16..20].copy_from_slice(&[192, 0, 2, 1]);
+                header
+            }
+            _ => {
+                let mut header = vec![0; IPV6_HEADER_LEN];
+                header[0] = 0x60;
+                header[4..6].copy_from_slice(&(tcp_len as u16).to_be_bytes());
+                header[6] = IP_PROTOCOL_TCP;
+                header[7] = 64;
+                header[8..24].copy_from_slice(&Ipv6Addr::new(0xfd00, 0, 0, 0, 0, 0, 0, 2).octets());
+                header[24..40]
+                    .copy_from_slice(&Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1).octets());
+                header
+            }
+        };
+        let tcp_offset = data.len();
+
+        let mut tcp = vec![0; 20];
+        tcp[0..2].copy_from_slice(&49152u16.to_be_bytes());
+        tcp[2..4].copy_from_slice(&443u16.to_be_bytes());
+        tcp[12] = ((tcp_len / 4) as u8) << 4;
+        tcp[13] = flags;
+        tcp[14..16].copy_from_slice(&64240u16.to_be_bytes());
+        tcp.extend_from_slice(options);
+        data.extend_from_slice(&tcp);
+
+        let checksum = tcp_checksum(&data, tcp_offset);
+        data[tcp_offset + 16..tcp_offset + 18].copy_from_slice(&checksum.to_be_bytes());
+        data
+    }
+
+    fn mss_option(mss: u16) -> [u8; 4] {
+        let [high, low] = mss.to_be_bytes();
+        [TCP_OPTION_MSS, 4, high, low]
+    }
+
+    fn mss(data: &[u8]) -> u16 {
+        let (_, mss_offset, _) = locate_mss_option(data).expect("packet has an MSS option");
+        u16::from_be_bytes([data[mss_offset], data[mss_offset + 1]])
+    }
+
+    /// Verifies the checksum by summing the whole segment, including the checksum field.
+    fn checksum_is_valid(data: &[u8], tcp_offset: usize) -> bool {
+        let segment = &data[tcp_offset..];
+        let addresses = match data[0] >> 4 {
+            4 => &data[12..20],
+            _ => &data[8..40],
+        };
+
+        let mut sum = ones_complement_sum(addresses)
+            + u32::from(IP_PROTOCOL_TCP)
+            + segment.len() as u32
+            + ones_complement_sum(segment);
+        while sum > 0xffff {
+            sum = (sum & 0xffff) + (sum >> 16);
+        }
+
+        sum == 0xffff
+    }
+
+    fn clamp(data: Vec<u8>, mtu: u16) -> Vec<u8> {
+        clamp_mss(Packet::new(data.into()), mtu).to_vec()
+    }
+
+    #[test]
+    fn clamps_ipv4_syn() {
+        let packet = tcp_packet(4, TCP_FLAG_SYN, &mss_option(1460));
+
+        let clamped = clamp(packet, 1400);
+
+        assert_eq!(mss(&clamped), 1360);
+        assert!(checksum_is_valid(&clamped, IPV4_HEADER_LEN));
+    }
+
+    #[test]
+    fn clamps_ipv6_syn_ack() {
+        let packet = tcp_packet(6, TCP_FLAG_SYN | 0x10, &mss_option(1440));
+
+        let clamped = clamp(packet, 1400);
+
+        assert_eq!(mss(&clamped), 1340);
+        assert!(checksum_is_valid(&clamped, IPV6_HEADER_LEN));
+    }
+
+    #[test]
+    fn finds_mss_after_other_options() {
+        // NOP, SACK permitted, then MSS at an odd offset within the TCP header
+        let mut options = vec![TCP_OPTION_NOP, 4, 2];
+        options.extend_from_slice(&mss_option(1460));
+        options.push(TCP_OPTION_END);
+        let packet = tcp_packet(4, TCP_FLAG_SYN, &options);
+
+        let clamped = clamp(packet, 1280);
+
+        assert_eq!(mss(&clamped), 1240);
+        assert!(checksum_is_valid(&clamped, IPV4_HEADER_LEN));
+    }
+
+    #[test]
+    fn keeps_smaller_mss() {
+        let packet = tcp_packet(4, TCP_FLAG_SYN, &mss_option(1200));
+
+        assert_eq!(clamp(packet.clone(), 1400), packet);
+    }
+
+    #[test]
+    fn ignores_packets_without_syn() {
+        let packet = tcp_packet(4, 0x10, &mss_option(1460));
+
+        assert_eq!(clamp(packet.clone(), 1400), packet);
+    }
+
+    #[test]
+    fn ignores_non_tcp_packets() {
+        let mut packet = tcp_packet(6, TCP_FLAG_SYN, &mss_option(1460));
+        // UDP
+        packet[6] = 17;
+
+        assert_eq!(clamp(packet.clone(), 1400), packet);
+        assert_eq!(clamp(vec![0x45, 0x00], 1400), vec![0x45, 0x00]);
+        assert_eq!(clamp(Vec::new(), 1400), Vec::<u8>::new());
+    }
+
+    #[test]
+    fn tunnel_without_payload_becomes_idle() {
+        let start = Instant::now();
+        let mut idle_timer = IdleTimer::new(Duration::from_secs(60));
+        idle_timer.reset(1000, start);
+
+        assert!(!idle_timer.is_idle(1000, start + Duration::from_secs(59)));
+        assert!(idle_timer.is_idle(1000, start + Duration::from_secs(60)));
+    }
+
+    #[test]
+    fn payload_restarts_idle_period() {
+        let start = Instant::now();
+        let mut idle_timer = IdleTimer::new(Duration::from_secs(60));
+        idle_timer.reset(1000, start);
+
+        assert!(!idle_timer.is_idle(1500, start + Duration::from_secs(50)));
+        assert!(!idle_timer.is_idle(1500, start + Duration::from_secs(100)));
+        assert!(idle_timer.is_idle(1500, start + Duration::from_secs(110)));
+    }
+
+    #[tokio::test]
+    async fn ping_fails_when_echo_is_withheld() {
+        let (_echo_tx, echo_rx) = watch::channel(0);
+        let (app_rtt_tx, app_rtt_rx) = watch::channel(None);
+
+        let result = ClientRelayer::ping_server(
+            |_| Ok(()),
+            echo_rx,
+            app_rtt_tx,
+            Duration::from_millis(10),
+            Duration::from_millis(50),
+        )
+        .await;
+
+        assert!(matches!(result, Err(e) if e.to_string().contains("did not echo")));
+        assert_eq!(*app_rtt_rx.borrow(), None);
+    }
+
+    #[tokio::test]
+    async fn ping_measures_round_trip_of_echo() {
+        let (echo_tx, echo_rx) = watch::channel(0);
+        let (app_rtt_tx, mut app_rtt_rx) = watch::channel(None);
+
+        let ping = ClientRelayer::ping_server(
+            move |sequence| {
+                echo_tx.send_replace(sequence);
+                Ok(())
+            },
+            echo_rx,
+            app_rtt_tx,
+            Duration::from_millis(10),
+            Duration::from_millis(50),
+        );
+
+        tokio::select! {
+            result = ping => panic!("ping stopped: {result:?}"),
+            rtt = app_rtt_rx.wait_for(Option::is_some) => assert!(rtt.is_ok()),
         }
     }
 }
@@ -0,0 +1,73 @@
+//! Connection statistics of the client.
+//!
+//! Exposes the traffic counters and path statistics of the tunnel as a plain snapshot,
+//! so that embedders of [`QuincyClient`](crate::client::QuincyClient) do not depend on
+//! the types of the underlying QUIC implementation.
+//!
+//! ```no_run
+//! use std::path::Path;
+//!
+//! use quincy::config::{ClientConfig, FromPath};
+//! use quincy::network::interface::tun_rs::TunRsInterface;
+//! use quincy_client::client::QuincyClient;
+//!
+//! # async fn example() -> quincy::Result<()> {
+//! let config = ClientConfig::from_path(Path::new("client.toml"), "QUINCY_")?;
+//! let mut client = QuincyClient::new(config);
+//! client.start::<TunRsInterface>().await?;
+//!
+//! if let Some(stats) = client.stats() {
+//!     println!(
+//!         "Sent {} bytes, received {} bytes, RTT {} ms",
+//!         stats.bytes_sent,
+//!         stats.bytes_received,
+//!         stats.rtt.as_millis()
+//!     );
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use std::time::Duration;
+
+use ipnet::IpNet;
+
+use crate::health::ConnectionQuality;
+
+/// A snapshot of the statistics of the tunnel connection.
+///
+/// The traffic counters cover the current connection and restart from zero when the
+/// client reconnects, while the TUN counters cover the lifetime of the interface.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ConnectionStats {
+    /// UDP payload bytes sent to the server, including QUIC overhead
+    pub bytes_sent: u64,
+    /// UDP payload bytes received from the server, including QUIC overhead
+    pub bytes_received: u64,
+    /// UDP datagrams sent to the server
+    pub packets_sent: u64,
+    /// UDP datagrams received from the server
+    pub packets_received: u64,
+    /// Payload bytes received through the tunnel and written to the TUN interface
+    pub tun_bytes_in: u64,
+    /// Payload bytes read from the TUN interface and sent through the tunnel
+    pub tun_bytes_out: u64,
+    /// Smoothed round-trip time of the connection
+    pub rtt: Duration,
+    /// Round-trip time of the last application keep-alive ping echoed by the server
+    pub app_rtt: Option<Duration>,
+    /// Congestion window of the connection in bytes
+    pub congestion_window: u64,
+    /// Quality graded from the sampled round-trip time, packet loss and congestion window
+    pub quality: Option<ConnectionQuality>,
+    /// How long the current connection has been relaying
+    pub connection_duration: Duration,
+    /// Address the server assigned to the tunnel interface
+    pub client_address: Option<IpNet>,
+    /// Address of the server inside the tunnel network
+    pub server_address: Option<IpNet>,
+    /// Connection string of the server endpoint the tunnel is connected to
+    pub server_endpoint: Option<String>,
+    /// MTU of the tunnel interface, negotiated with the server
+    pub tunnel_mtu: Option<u16>,
+}
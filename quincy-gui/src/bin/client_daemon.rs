@@ -2,14 +2,15 @@
 
 use clap::Parser;
 use quincy::config::{ClientConfig, FromPath};
+use quincy::network::dns::{dns_backup_path, restore_stale_dns_backup};
 use quincy::network::interface::tun_rs::TunRsInterface;
 use quincy::{QuincyError, Result};
-use quincy_client::client::QuincyClient;
+use quincy_client::client::{ClientState, QuincyClient, ShutdownReason};
 use quincy_gui::gui::GuiError;
 use quincy_gui::ipc::{ClientStatus, ConnectionMetrics, ConnectionStatus, IpcClient, IpcMessage};
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use std::time::{Duration, Instant};
+use std::time::Duration;
 use tokio::sync::{Mutex, broadcast, oneshot};
 use tokio::time::sleep;
 use tracing::{debug, error, info, warn};
@@ -20,23 +21,26 @@ use tracing_subscriber::EnvFilter;
 #[command(name = "quincy-client-daemon")]
 pub struct Args {
     /// Name of the client instance
-    #[arg(long)]
-    pub instance_name: String,
+    #[arg(long, required_unless_present = "print_default_config")]
+    pub instance_name: Option<String>,
     /// Path to the configuration file
-    #[arg(long)]
-    pub config_path: PathBuf,
+    #[arg(long, required_unless_present = "print_default_config")]
+    pub config_path: Option<PathBuf>,
     /// Path to the IPC socket to connect to
-    #[arg(long)]
-    pub socket_path: PathBuf,
+    #[arg(long, required_unless_present = "print_default_config")]
+    pub socket_path: Option<PathBuf>,
     /// Path to the log file
-    #[arg(long)]
-    pub log_path: PathBuf,
+    #[arg(long, required_unless_present = "print_default_config")]
+    pub log_path: Option<PathBuf>,
     /// Environment variable prefix for configuration
     #[arg(long, default_value = "QUINCY_")]
     pub env_prefix: String,
     /// Log level (trace, debug, info, warn, error)
     #[arg(long, default_value = "info")]
     pub log_level: String,
+    /// Print a client configuration template with default values and exit
+    #[arg(long, exclusive = true)]
+    pub print_default_config: bool,
 }
 
 /// The Quincy client daemon that manages VPN connections and IPC communication.
@@ -47,22 +51,25 @@ pub struct Args {
 struct ClientDaemon {
     /// The underlying Quincy VPN client instance
     client: Arc<Mutex<Option<QuincyClient>>>,
-    /// Timestamp when the current connection was established
-    connection_start_time: Arc<Mutex<Option<Instant>>>,
+    /// Configuration file the running client was started with
+    config_path: Arc<Mutex<Option<PathBuf>>>,
     /// Unique identifier for this daemon instance
     instance_name: String,
+    /// Prefix of the environment variables overriding the configuration
+    env_prefix: String,
     /// Broadcast sender for shutdown notifications
     shutdown_tx: broadcast::Sender<()>,
 }
 
 impl ClientDaemon {
     /// Creates a new ClientDaemon instance.
-    fn new(instance_name: String) -> Self {
+    fn new(instance_name: String, env_prefix: String) -> Self {
         let (shutdown_tx, _) = broadcast::channel(1);
         Self {
             client: Arc::new(Mutex::new(None)),
-            connection_start_time: Arc::new(Mutex::new(None)),
+            config_path: Arc::new(Mutex::new(None)),
             instance_name,
+            env_prefix,
             shutdown_tx,
         }
     }
@@ -72,7 +79,6 @@ impl ClientDaemon {
     async fn start_client_cancellable(
         &self,
         config_path: PathBuf,
-        env_prefix: &str,
         mut cancel_rx: oneshot::Receiver<()>,
     ) -> Result<bool> {
         let mut client_guard = self.client.lock().await;
@@ -81,7 +87,7 @@ impl ClientDaemon {
             return Err(QuincyError::system("Client is already running"));
         }
 
-        let config = ClientConfig::from_path(&config_path, env_prefix)?;
+        let config = ClientConfig::from_path(&config_path, &self.env_prefix)?;
         let mut client = QuincyClient::new(config);
 
         // Start the client in a separate task so we can listen for cancellation
@@ -91,7 +97,7 @@ impl ClientDaemon {
             result = start_future => {
                 match result {
                     Ok(()) => {
-                        *self.connection_start_time.lock().await = Some(Instant::now());
+                        *self.config_path.lock().await = Some(config_path);
                         *client_guard = Some(client);
                         info!("Client started successfully");
                         Ok(true)
@@ -101,8 +107,10 @@ impl ClientDaemon {
             }
             _ = &mut cancel_rx => {
                 info!("Client start cancelled");
-                // Client.start() was interrupted - drop the client
-                drop(client);
+                // Client.start() was interrupted - lift the kill switch it may have engaged
+                if let Err(e) = client.stop().await {
+                    error!("Failed to stop the cancelled client: {e}");
+                }
                 Ok(false)
             }
         }
@@ -115,69 +123,216 @@ impl ClientDaemon {
         if let Some(mut client) = client_guard.take() {
             client.stop().await?;
             client.wait_for_shutdown().await?;
-            *self.connection_start_time.lock().await = None;
+            *self.config_path.lock().await = None;
             info!("Client stopped successfully");
         }
 
         Ok(())
     }
 
+    /// Closes and re-establishes the connection of the running VPN client.
+    async fn reconnect_client(&self) -> Result<()> {
+        let mut client_guard = self.client.lock().await;
+
+        let Some(client) = client_guard.as_mut() else {
+            return Err(QuincyError::system("Client is not running"));
+        };
+
+        client.reconnect::<TunRsInterface>().await?;
+        info!("Client reconnected successfully");
+
+        Ok(())
+    }
+
+    /// Re-establishes the lost connection of the running VPN client, retrying with backoff.
+    async fn reconnect_client_with_backoff(&self) -> Result<()> {
+        let mut client_guard = self.client.lock().await;
+
+        let Some(client) = client_guard.as_mut() else {
+            return Err(QuincyError::system("Client is not running"));
+        };
+
+        client.reconnect_with_backoff::<TunRsInterface>().await?;
+        info!("Client reconnected successfully");
+
+        Ok(())
+    }
+
+    /// Waits until the connection of the running VPN client is lost with `auto_reconnect`
+    /// enabled. Never completes while no client is running.
+    async fn wait_for_connection_lost(&self) {
+        let connection_lost = self
+            .client
+            .lock()
+            .await
+            .as_ref()
+            .map(QuincyClient::connection_lost);
+
+        match connection_lost {
+            Some(connection_lost) => connection_lost.notified().await,
+            None => std::future::pending().await,
+        }
+    }
+
+    /// Waits until outbound traffic needs the idle connection of the running VPN client to be
+    /// re-established. Never completes while no client is running.
+    async fn wait_for_idle_wake(&self) {
+        let idle_wake = self
+            .client
+            .lock()
+            .await
+            .as_ref()
+            .map(QuincyClient::idle_wake);
+
+        match idle_wake {
+            Some(idle_wake) => idle_wake.notified().await,
+            None => std::future::pending().await,
+        }
+    }
+
+    /// Re-reads the configuration file of the running VPN client and applies it.
+    ///
+    /// Route and DNS changes are applied without reconnecting.
+    async fn reload_client_config(&self) -> Result<()> {
+        let Some(config_path) = self.config_path.lock().await.clone() else {
+            return Err(QuincyError::system("Client is not running"));
+        };
+
+        info!("Reloading configuration from {}", config_path.display());
+        // Validation resolves the server endpoints, so it runs on a blocking task before the
+        // client is locked, leaving status requests answered meanwhile
+        let env_prefix = self.env_prefix.clone();
+        let config = tokio::task::spawn_blocking(move || {
+            let config = ClientConfig::from_path(&config_path, &env_prefix)?;
+            config.validate()?;
+            Ok::<_, QuincyError>(config)
+        })
+        .await
+        .map_err(|e| QuincyError::system(format!("Failed to load the configuration: {e}")))??;
+
+        let mut client_guard = self.client.lock().await;
+        let Some(client) = client_guard.as_mut() else {
+            return Err(QuincyError::system("Client is not running"));
+        };
+        client.reload_config(config)?;
+
+        Ok(())
+    }
+
     /// Gets the current status and metrics of the VPN client.
     async fn get_status(&self) -> ClientStatus {
         let client_guard = self.client.lock().await;
 
         if let Some(client) = client_guard.as_ref() {
-            let status = self.determine_connection_status(client);
-            let metrics = self.extract_connection_metrics(client).await;
-            ClientStatus { status, metrics }
+            let status = self.determine_connection_status(&client.state());
+            let metrics = client.stats().map(ConnectionMetrics::from);
+            ClientStatus {
+                status,
+                metrics,
+                shutdown_reason: client.shutdown_reason(),
+            }
         } else {
             ClientStatus {
                 status: ConnectionStatus::Disconnected,
                 metrics: None,
+                shutdown_reason: None,
             }
         }
     }
 
     /// Determines the current connection status based on client state.
-    fn determine_connection_status(&self, client: &QuincyClient) -> ConnectionStatus {
-        if client.is_running() {
-            if let Some(relayer) = client.relayer() {
-                match relayer.connection().close_reason() {
-                    None => ConnectionStatus::Connected,
-                    Some(reason) => {
-                        ConnectionStatus::Error(GuiError::connection_closed(format!("{reason:?}")))
-                    }
-                }
-            } else {
+    fn determine_connection_status(&self, state: &ClientState) -> ConnectionStatus {
+        match state {
+            ClientState::Idle | ClientState::Disconnecting => ConnectionStatus::Disconnected,
+            ClientState::Connecting | ClientState::Authenticating | ClientState::Reconnecting => {
                 ConnectionStatus::Connecting
             }
-        } else {
-            ConnectionStatus::Disconnected
+            // The tunnel stays up and reconnects on outbound traffic
+            ClientState::Connected | ClientState::Dormant => ConnectionStatus::Connected,
+            ClientState::Error { message } => {
+                ConnectionStatus::Error(GuiError::connection_closed(message))
+            }
         }
     }
 
-    /// Extracts connection metrics from the client if available.
-    async fn extract_connection_metrics(&self, client: &QuincyClient) -> Option<ConnectionMetrics> {
-        if let Some(relayer) = client.relayer() {
-            let stats = relayer.connection().stats();
-            let connection_duration = self
-                .connection_start_time
-                .lock()
-                .await
-                .map(|start| start.elapsed())
-                .unwrap_or_default();
-
-            Some(ConnectionMetrics {
-                bytes_sent: stats.udp_tx.bytes,
-                bytes_received: stats.udp_rx.bytes,
-                packets_sent: stats.udp_tx.datagrams,
-                packets_received: stats.udp_rx.datagrams,
-                connection_duration,
-                client_address: client.client_address(),
-                server_address: client.server_address(),
-            })
-        } else {
-            None
+    /// Re-establishes the lost connection of the running VPN client, answering status
+    /// requests with the progress of the attempts meanwhile.
+    ///
+    /// A failed attempt that is retried is reported as still connecting, with the error of
+    /// the attempt as the shutdown reason. Stopping the client or shutting down abandons
+    /// the attempts. Returns true if the daemon should exit.
+    async fn handle_connection_lost(
+        &self,
+        ipc_client: &mut IpcClient,
+        config_path: &Path,
+    ) -> Result<bool> {
+        let Some(state_rx) = self
+            .client
+            .lock()
+            .await
+            .as_ref()
+            .map(QuincyClient::subscribe_state)
+        else {
+            return Ok(false);
+        };
+
+        let mut reconnect = Box::pin(self.reconnect_client_with_backoff());
+
+        let interrupt = loop {
+            tokio::select! {
+                result = &mut reconnect => {
+                    if let Err(e) = result {
+                        error!("Failed to reconnect: {}", e);
+                    }
+                    break None;
+                }
+                msg_result = ipc_client.recv() => {
+                    match msg_result {
+                        Ok(IpcMessage::GetStatus) => {
+                            let state = state_rx.borrow().clone();
+                            let status = match state {
+                                ClientState::Error { message } => ClientStatus {
+                                    status: ConnectionStatus::Connecting,
+                                    metrics: None,
+                                    shutdown_reason: Some(ShutdownReason::Error { message }),
+                                },
+                                state => ClientStatus {
+                                    status: self.determine_connection_status(&state),
+                                    metrics: None,
+                                    shutdown_reason: None,
+                                },
+                            };
+                            ipc_client.send(&IpcMessage::StatusUpdate(status)).await?;
+                        }
+                        Ok(message @ (IpcMessage::StopClient | IpcMessage::Shutdown)) => {
+                            info!("Abandoning the reconnect attempts");
+                            break Some(message);
+                        }
+                        Ok(_) => {
+                            ipc_client
+                                .send(&IpcMessage::Error(GuiError::other(
+                                    "Client is reconnecting",
+                                )))
+                                .await?;
+                        }
+                        Err(e) => {
+                            info!("IPC connection closed by GUI: {}", e);
+                            return Ok(true);
+                        }
+                    }
+                }
+            }
+        };
+
+        // Release the client before handling the interrupting request
+        drop(reconnect);
+
+        match interrupt {
+            Some(message) => {
+                self.handle_message_with_cancel(message, ipc_client, config_path)
+                    .await
+            }
+            None => Ok(false),
         }
     }
 
@@ -189,6 +344,7 @@ impl ClientDaemon {
         info!("Connected to GUI IPC server");
 
         let mut shutdown_rx = self.shutdown_tx.subscribe();
+        let mut reload_signal = ReloadSignal::new()?;
 
         // Main message loop
         loop {
@@ -197,6 +353,24 @@ impl ClientDaemon {
                     info!("IPC client received shutdown signal");
                     break;
                 }
+                _ = reload_signal.recv() => {
+                    info!("Received configuration reload signal");
+                    if let Err(e) = self.reload_client_config().await {
+                        error!("Failed to reload configuration: {}", e);
+                    }
+                }
+                _ = self.wait_for_connection_lost() => {
+                    info!("Connection lost, re-establishing it");
+                    if self.handle_connection_lost(&mut ipc_client, config_path).await? {
+                        break;
+                    }
+                }
+                _ = self.wait_for_idle_wake() => {
+                    info!("Re-establishing the idle connection for outbound traffic");
+                    if let Err(e) = self.reconnect_client().await {
+                        error!("Failed to reconnect: {}", e);
+                    }
+                }
                 result = ipc_client.recv() => {
                     match result {
                         Ok(message) => {
@@ -256,9 +430,7 @@ impl ClientDaemon {
 
                 // Start connecting in a spawned task
                 let mut connect_handle = tokio::spawn(async move {
-                    daemon
-                        .start_client_cancellable(path_clone, "QUINCY_", cancel_rx)
-                        .await
+                    daemon.start_client_cancellable(path_clone, cancel_rx).await
                 });
 
                 // While connecting, listen for IPC messages
@@ -321,6 +493,7 @@ impl ClientDaemon {
                                     let status = ClientStatus {
                                         status: ConnectionStatus::Connecting,
                                         metrics: None,
+                                        shutdown_reason: None,
                                     };
                                     if let Err(e) = ipc_client.send(&IpcMessage::StatusUpdate(status)).await {
                                         error!("Failed to send status: {}", e);
@@ -347,6 +520,16 @@ impl ClientDaemon {
                 ipc_client.send(&response).await?;
                 Ok(false)
             }
+            IpcMessage::Reconnect => {
+                let response = self.handle_reconnect_message().await;
+                ipc_client.send(&response).await?;
+                Ok(false)
+            }
+            IpcMessage::ReloadConfig => {
+                let response = self.handle_reload_config_message().await;
+                ipc_client.send(&response).await?;
+                Ok(false)
+            }
             IpcMessage::GetStatus => {
                 let status = self.get_status().await;
                 ipc_client.send(&IpcMessage::StatusUpdate(status)).await?;
@@ -411,6 +594,28 @@ impl ClientDaemon {
         }
     }
 
+    /// Handles a Reconnect IPC message.
+    async fn handle_reconnect_message(&self) -> IpcMessage {
+        match self.reconnect_client().await {
+            Ok(()) => {
+                let status = self.get_status().await;
+                IpcMessage::StatusUpdate(status)
+            }
+            Err(e) => IpcMessage::Error(e.into()),
+        }
+    }
+
+    /// Handles a ReloadConfig IPC message.
+    async fn handle_reload_config_message(&self) -> IpcMessage {
+        match self.reload_client_config().await {
+            Ok(()) => {
+                let status = self.get_status().await;
+                IpcMessage::StatusUpdate(status)
+            }
+            Err(e) => IpcMessage::Error(e.into()),
+        }
+    }
+
     /// Handles a Shutdown IPC message.
     async fn handle_shutdown_message(&self) -> IpcMessage {
         info!("Received shutdown request, stopping client and daemon");
@@ -430,30 +635,75 @@ impl Clone for ClientDaemon {
     fn clone(&self) -> Self {
         Self {
             client: self.client.clone(),
-            connection_start_time: self.connection_start_time.clone(),
+            config_path: self.config_path.clone(),
             instance_name: self.instance_name.clone(),
+            env_prefix: self.env_prefix.clone(),
             shutdown_tx: self.shutdown_tx.clone(),
         }
     }
 }
 
+/// Configuration reload requests delivered by the operating system.
+///
+/// On Unix platforms this listens for `SIGHUP`. Other platforms have no equivalent
+/// signal and rely on the `ReloadConfig` IPC message instead.
+struct ReloadSignal {
+    #[cfg(unix)]
+    signal: tokio::signal::unix::Signal,
+}
+
+impl ReloadSignal {
+    /// Starts listening for reload requests.
+    fn new() -> Result<Self> {
+        Ok(Self {
+            #[cfg(unix)]
+            signal: tokio::signal::unix::signal(tokio::signal::unix::SignalKind::hangup())
+                .map_err(|e| QuincyError::system(format!("Failed to listen for SIGHUP: {e}")))?,
+        })
+    }
+
+    /// Waits for the next reload request.
+    async fn recv(&mut self) {
+        #[cfg(unix)]
+        self.signal.recv().await;
+        #[cfg(not(unix))]
+        std::future::pending::<()>().await;
+    }
+}
+
 /// Main entry point for the Quincy client daemon.
 #[tokio::main]
 async fn main() -> Result<()> {
     let args = Args::parse();
-    initialize_logging(&args.log_level, &args.log_path);
+
+    if args.print_default_config {
+        print!("{}", ClientConfig::default_template());
+        return Ok(());
+    }
+
+    let (Some(instance_name), Some(config_path), Some(socket_path), Some(log_path)) = (
+        args.instance_name,
+        args.config_path,
+        args.socket_path,
+        args.log_path,
+    ) else {
+        unreachable!("clap requires the daemon arguments unless printing the default config");
+    };
+
+    initialize_logging(&args.log_level, &log_path);
 
     // Validate instance name defensively to prevent unsafe IPC names
     use quincy_gui::validation;
-    validation::validate_instance_name(&args.instance_name)?;
+    validation::validate_instance_name(&instance_name)?;
+
+    info!("Starting Quincy client daemon: {}", instance_name);
 
-    info!("Starting Quincy client daemon: {}", args.instance_name);
+    // Undo the DNS configuration of an earlier run that was killed before cleaning it up
+    restore_stale_dns_backup(&dns_backup_path())?;
 
-    let daemon = ClientDaemon::new(args.instance_name.clone());
+    let daemon = ClientDaemon::new(instance_name, args.env_prefix);
 
-    daemon
-        .run_ipc_client(&args.socket_path, &args.config_path)
-        .await?;
+    daemon.run_ipc_client(&socket_path, &config_path).await?;
 
     info!("Daemon shutdown complete");
     Ok(())
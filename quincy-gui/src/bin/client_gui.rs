@@ -2,6 +2,7 @@
 
 use clap::Parser;
 use iced::application;
+use quincy::config::ClientConfig;
 use quincy::{QuincyError, Result};
 use quincy_gui::gui::{QuincyGui, expand_path};
 use std::path::PathBuf;
@@ -27,6 +28,9 @@ pub struct Args {
     /// Log level (trace, debug, info, warn, error)
     #[arg(long, default_value = "info")]
     pub log_level: String,
+    /// Print a client configuration template with default values and exit
+    #[arg(long)]
+    pub print_default_config: bool,
 }
 
 /// Main entry point for the Quincy GUI application.
@@ -46,6 +50,11 @@ pub struct Args {
 fn main() -> Result<()> {
     let args = Args::parse();
 
+    if args.print_default_config {
+        print!("{}", ClientConfig::default_template());
+        return Ok(());
+    }
+
     // Initialize logging: prefer RUST_LOG env var, fall back to CLI arg
     let filter =
         EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(&args.log_level));
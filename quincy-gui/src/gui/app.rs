@@ -137,6 +137,8 @@ impl QuincyGui {
                 ConfigMsg::Selected(name) => self.handle_config_selected(name),
                 ConfigMsg::NameChanged(new_name) => self.handle_config_name_changed(new_name),
                 ConfigMsg::NameSaved => self.handle_config_name_saved(),
+                ConfigMsg::RoutesChanged(routes) => self.handle_config_routes_changed(routes),
+                ConfigMsg::RoutesSaved => self.handle_config_routes_saved(),
                 ConfigMsg::Delete => self.handle_config_delete(),
                 ConfigMsg::New => self.handle_new_config(),
             },
@@ -149,6 +151,7 @@ impl QuincyGui {
             Message::Instance(msg) => match msg {
                 InstanceMsg::Connect => self.handle_connect(),
                 InstanceMsg::Disconnect => self.handle_disconnect(),
+                InstanceMsg::Reconnect => self.handle_reconnect(),
                 InstanceMsg::CancelConnect => self.handle_cancel_connect(),
                 InstanceMsg::Connected(config_name) => self.handle_connected(config_name),
                 InstanceMsg::Disconnected => self.handle_disconnected(),
@@ -378,6 +381,7 @@ impl QuincyGui {
             state: ConfigState::default(),
             parsed: None,
             parse_error: None,
+            routes_draft: None,
         };
 
         Some(Ok((config_name, entry)))
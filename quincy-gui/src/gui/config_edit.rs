@@ -0,0 +1,172 @@
+use quincy::Result;
+use quincy::error::ConfigError;
+use toml_edit::{Array, DocumentMut, Item, Value};
+
+/// Sets a single key in a TOML configuration document.
+///
+/// Only the targeted key is changed; comments, formatting and key order of the rest of
+/// the document are kept intact. Missing tables along the key path are created.
+///
+/// # Arguments
+/// * `content` - The TOML document to edit
+/// * `key_path` - Path of the key to set, e.g. `["network", "routes"]`
+/// * `value` - The new value of the key
+///
+/// # Returns
+/// The edited TOML document
+///
+/// # Errors
+/// Returns an error if:
+/// - The document is not valid TOML
+/// - The key path is empty or passes through a key that is not a table
+pub fn set_config_value(
+    content: &str,
+    key_path: &[&str],
+    value: impl Into<Value>,
+) -> Result<String> {
+    let mut document = content
+        .parse::<DocumentMut>()
+        .map_err(|e| ConfigError::ParseError {
+            message: e.to_string(),
+        })?;
+
+    let Some((key, table_path)) = key_path.split_last() else {
+        return Err(ConfigError::InvalidValue {
+            field: String::new(),
+            reason: "empty key path".to_string(),
+        }
+        .into());
+    };
+
+    let mut table = document.as_table_mut();
+    for name in table_path {
+        table = table
+            .entry(name)
+            .or_insert(toml_edit::table())
+            .as_table_mut()
+            .ok_or_else(|| ConfigError::InvalidValue {
+                field: key_path.join("."),
+                reason: format!("'{name}' is not a table"),
+            })?;
+    }
+
+    let mut value = value.into();
+    match table.get_mut(key).and_then(Item::as_value_mut) {
+        Some(existing) => {
+            // Keep the whitespace and trailing comment around the old value
+            *value.decor_mut() = existing.decor().clone();
+            *existing = value;
+        }
+        None => {
+            table.insert(key, Item::Value(value));
+        }
+    }
+
+    Ok(document.to_string())
+}
+
+/// Sets the routes of a client configuration document.
+///
+/// # Arguments
+/// * `content` - The TOML document to edit
+/// * `routes` - The routes as entered in the configuration view, separated by commas
+///
+/// # Returns
+/// The edited TOML document
+///
+/// # Errors
+/// Returns an error if the document is not valid TOML or `network` is not a table
+pub fn set_routes(content: &str, routes: &str) -> Result<String> {
+    let routes = routes
+        .split(',')
+        .map(str::trim)
+        .filter(|route| !route.is_empty());
+
+    set_config_value(content, &["network", "routes"], Array::from_iter(routes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = r#"# The address and port the Quincy server is available at
+connection_string = "quincy:55555"
+
+[connection]
+# The MTU used by the QUIC tunnel and the spawned TUN interface
+mtu = 1400 # tuned for PPPoE
+
+[network]
+# Routes to send through the VPN tunnel
+routes = ["10.0.1.0/24"]
+
+[log]
+# The log level
+level = "info"
+"#;
+
+    #[test]
+    fn editing_one_field_keeps_comments_on_other_keys() {
+        let routes = Array::from_iter(["10.0.1.0/24", "10.0.2.0/24"]);
+
+        let edited = set_config_value(CONFIG, &["network", "routes"], routes).unwrap();
+
+        assert_eq!(
+            edited,
+            CONFIG.replace(
+                r#"routes = ["10.0.1.0/24"]"#,
+                r#"routes = ["10.0.1.0/24", "10.0.2.0/24"]"#
+            )
+        );
+    }
+
+    #[test]
+    fn editing_routes_keeps_comments_and_other_keys() {
+        let edited = set_routes(CONFIG, " 10.0.1.0/24,10.0.3.0/24 , ").unwrap();
+
+        assert_eq!(
+            edited,
+            CONFIG.replace(
+                r#"routes = ["10.0.1.0/24"]"#,
+                r#"routes = ["10.0.1.0/24", "10.0.3.0/24"]"#
+            )
+        );
+    }
+
+    #[test]
+    fn clearing_routes_leaves_an_empty_list() {
+        let edited = set_routes(CONFIG, "").unwrap();
+
+        assert!(edited.contains("# Routes to send through the VPN tunnel\nroutes = []\n"));
+        assert!(edited.contains(r#"connection_string = "quincy:55555""#));
+    }
+
+    #[test]
+    fn editing_a_field_keeps_its_trailing_comment() {
+        let edited = set_config_value(CONFIG, &["connection", "mtu"], 1350i64).unwrap();
+
+        assert!(edited.contains("mtu = 1350 # tuned for PPPoE"));
+        assert!(edited.contains("# The log level"));
+    }
+
+    #[test]
+    fn missing_tables_are_created() {
+        let edited = set_config_value(CONFIG, &["protocol", "mode"], "tls").unwrap();
+
+        let document = edited.parse::<DocumentMut>().unwrap();
+
+        assert_eq!(document["protocol"]["mode"].as_str(), Some("tls"));
+        assert!(edited.contains("# The log level"));
+    }
+
+    #[test]
+    fn key_path_through_a_value_is_rejected() {
+        let result = set_config_value(CONFIG, &["connection_string", "host"], "quincy");
+
+        assert!(matches!(
+            result,
+            Err(quincy::QuincyError::Config(ConfigError::InvalidValue { field, .. }))
+                if field == "connection_string.host"
+        ));
+    }
+}
@@ -8,6 +8,7 @@ use std::time::Instant;
 use tracing::{debug, error, info, warn};
 
 use super::app::QuincyGui;
+use super::config_edit;
 use super::error::GuiError;
 use super::types::{
     ConfigEntry, ConfigState, ConfirmAction, ConfirmMsg, ConfirmationState, EditorState,
@@ -15,16 +16,18 @@ use super::types::{
 };
 use crate::ipc::{ConnectionMetrics, ConnectionStatus, IpcMessage};
 use crate::validation;
+use quincy::QuincyError;
 use quincy::error::Result;
 
 /// Helper function to parse and validate a config file.
-/// Returns a tuple of (Option<ClientConfig>, Option<String>) where:
-/// - The first element is Some(config) if parsing succeeded, None otherwise
-/// - The second element is Some(error_string) if parsing or validation failed, None otherwise
+///
+/// Errors are returned as-is so that the view can point at the offending field
+/// (see `validation::config_error_field`).
 fn try_parse_config(path: &Path) -> Result<ClientConfig> {
     // Parse the TOML configuration
     let cfg = ClientConfig::from_path(path, "QUINCY_")?;
-    // Validate the config by building the quinn client config
+    // Validate the config values and build the quinn client config
+    cfg.validate()?;
     let _ = cfg.quinn_client_config()?;
 
     Ok(cfg)
@@ -65,7 +68,7 @@ impl QuincyGui {
                 Err(e) => {
                     error!("Failed to parse config file {}: {}", entry.config.name, e);
                     entry.parsed = None;
-                    entry.parse_error = Some(e.to_string());
+                    entry.parse_error = Some(e);
                 }
             }
         }
@@ -166,6 +169,67 @@ impl QuincyGui {
         Task::none()
     }
 
+    /// Handles edits to the routes in the configuration view.
+    /// Note: This only updates the draft; the file is written on RoutesSaved.
+    pub fn handle_config_routes_changed(&mut self, routes: String) -> Task<Message> {
+        if self.editor_state.is_some() {
+            return Task::none();
+        }
+
+        let Some(ref selected_key) = self.selected_config else {
+            error!("No configuration selected");
+            return Task::none();
+        };
+
+        if let Some(entry) = self.configs.get_mut(selected_key) {
+            entry.routes_draft = Some(routes);
+        }
+        Task::none()
+    }
+
+    /// Handles saving of the routes edited in the configuration view.
+    ///
+    /// Only the routes of the configuration file are rewritten, keeping its comments
+    /// and other keys intact.
+    pub fn handle_config_routes_saved(&mut self) -> Task<Message> {
+        if self.editor_state.is_some() {
+            return Task::none();
+        }
+
+        let Some(config_name) = self.selected_config.clone() else {
+            error!("No configuration selected");
+            return Task::none();
+        };
+
+        let Some(entry) = self.configs.get_mut(&config_name) else {
+            error!("Configuration not found: {}", config_name);
+            return Task::none();
+        };
+
+        let Some(routes) = entry.routes_draft.take() else {
+            return Task::none();
+        };
+
+        let edited = fs::read_to_string(&entry.config.path)
+            .map_err(QuincyError::from)
+            .and_then(|content| config_edit::set_routes(&content, &routes));
+        let config_content = match edited {
+            Ok(content) => content,
+            Err(e) => {
+                error!("Failed to edit config file: {}", e);
+                return Task::none();
+            }
+        };
+
+        if let Err(e) = fs::write(&entry.config.path, &config_content) {
+            error!("Failed to save config file: {}", e);
+            return Task::none();
+        }
+        info!("Config file saved: {}", entry.config.path.display());
+
+        self.apply_saved_config(config_name)
+    }
+
     /// Handles deletion of the current configuration.
     /// Shows a confirmation modal instead of deleting immediately.
     pub fn handle_config_delete(&mut self) -> Task<Message> {
@@ -226,6 +290,7 @@ impl QuincyGui {
             state: ConfigState::default(),
             parsed: None,
             parse_error: None,
+            routes_draft: None,
         };
 
         self.configs.insert(new_config_name.clone(), entry);
@@ -339,12 +404,12 @@ impl QuincyGui {
             }
         };
 
-        let Some(ref config_name) = self.selected_config else {
+        let Some(config_name) = self.selected_config.clone() else {
             error!("No configuration selected");
             return Task::none();
         };
 
-        let Some(entry) = self.configs.get_mut(config_name) else {
+        let Some(entry) = self.configs.get_mut(&config_name) else {
             error!("Configuration not found: {}", config_name);
             return Task::none();
         };
@@ -352,25 +417,56 @@ impl QuincyGui {
         let config_content = editor_state.content.text();
 
         // Save to disk
-        match fs::write(&entry.config.path, &config_content) {
-            Ok(_) => {
-                info!("Config file saved: {}", entry.config.path.display());
+        if let Err(e) = fs::write(&entry.config.path, &config_content) {
+            error!("Failed to save config file: {}", e);
+            return Task::none();
+        }
+        info!("Config file saved: {}", entry.config.path.display());
 
-                // Re-parse the configuration
-                match try_parse_config(&entry.config.path) {
-                    Ok(cfg) => {
-                        entry.parsed = Some(cfg);
-                        entry.parse_error = None;
-                    }
-                    Err(e) => {
-                        error!("Failed to parse config file {}: {}", entry.config.name, e);
-                        entry.parsed = None;
-                        entry.parse_error = Some(e.to_string());
-                    }
+        self.apply_saved_config(config_name)
+    }
+
+    /// Re-parses a saved configuration and applies route and DNS changes to a running
+    /// connection.
+    fn apply_saved_config(&mut self, config_name: String) -> Task<Message> {
+        let Some(entry) = self.configs.get_mut(&config_name) else {
+            error!("Configuration not found: {}", config_name);
+            return Task::none();
+        };
+
+        match try_parse_config(&entry.config.path) {
+            Ok(cfg) => {
+                entry.parsed = Some(cfg);
+                entry.parse_error = None;
+
+                // Apply route and DNS changes to a running connection
+                if let ConfigState::Connected { instance, .. } = &entry.state {
+                    let instance = instance.clone();
+
+                    info!("Reloading configuration of instance: {}", config_name);
+
+                    return Task::future(async move {
+                        match instance.reload_config().await {
+                            Ok(metrics) => Some(Message::Instance(InstanceMsg::StatusUpdated(
+                                config_name,
+                                metrics,
+                            ))),
+                            Err(e) => {
+                                error!(
+                                    "Failed to reload configuration of instance {}: {}",
+                                    config_name, e
+                                );
+                                None
+                            }
+                        }
+                    })
+                    .and_then(Task::done);
                 }
             }
             Err(e) => {
-                error!("Failed to save config file: {}", e);
+                error!("Failed to parse config file {}: {}", entry.config.name, e);
+                entry.parsed = None;
+                entry.parse_error = Some(e);
             }
         }
 
@@ -478,6 +574,40 @@ impl QuincyGui {
         })
     }
 
+    /// Handles a forced reconnection request.
+    /// The daemon re-establishes the connection while the instance stays Connected.
+    pub fn handle_reconnect(&mut self) -> Task<Message> {
+        let Some(ref config_name) = self.selected_config else {
+            error!("No configuration selected");
+            return Task::none();
+        };
+
+        let Some(entry) = self.configs.get(config_name) else {
+            error!("Configuration not found: {}", config_name);
+            return Task::none();
+        };
+
+        let ConfigState::Connected { instance, .. } = &entry.state else {
+            error!("Cannot reconnect: not in Connected state");
+            return Task::none();
+        };
+
+        let instance = instance.clone();
+        let config_name = config_name.clone();
+
+        info!("Reconnecting instance: {}", config_name);
+
+        Task::future(async move {
+            match instance.reconnect().await {
+                Ok(metrics) => Message::Instance(InstanceMsg::StatusUpdated(config_name, metrics)),
+                Err(e) => {
+                    error!("Failed to reconnect instance {}: {}", config_name, e);
+                    Message::Instance(InstanceMsg::DisconnectedWithError(config_name, e.into()))
+                }
+            }
+        })
+    }
+
     /// Handles cancellation of an in-progress connection.
     /// Transitions: Connecting -> Idle
     pub fn handle_cancel_connect(&mut self) -> Task<Message> {
@@ -562,9 +692,13 @@ impl QuincyGui {
                     match conn.recv().await {
                         Ok(IpcMessage::StatusUpdate(status)) => match status.status {
                             ConnectionStatus::Disconnected => {
+                                let reason = status
+                                    .shutdown_reason
+                                    .map(|reason| reason.to_string())
+                                    .unwrap_or_else(|| "Connection lost".to_string());
                                 Message::Instance(InstanceMsg::DisconnectedWithError(
                                     name,
-                                    GuiError::connection_closed("Connection lost"),
+                                    GuiError::connection_closed(reason),
                                 ))
                             }
                             ConnectionStatus::Error(err) => {
@@ -825,3 +959,70 @@ impl QuincyGui {
 async fn exit() -> Message {
     process::exit(0);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use quincy::error::ConfigError;
+    use std::io::Write;
+
+    #[test]
+    fn missing_connection_string_reports_field() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        write!(
+            file,
+            r#"
+            [protocol]
+            mode = "noise"
+            server_public_key = "6axLx6XF+CrzWL6WppAr4R5/FO1NCq6yRuG59iqQCQ8="
+            private_key = "6axLx6XF+CrzWL6WppAr4R5/FO1NCq6yRuG59iqQCQ8="
+
+            [log]
+            level = "info"
+            "#
+        )
+        .unwrap();
+
+        let error = try_parse_config(file.path()).expect_err("config must fail to parse");
+
+        assert!(matches!(
+            &error,
+            QuincyError::Config(ConfigError::MissingField { field }) if field == "connection_string"
+        ));
+        assert_eq!(
+            validation::config_error_field(&error),
+            Some("connection_string")
+        );
+        assert!(
+            validation::config_error_hint(&error)
+                .is_some_and(|hint| hint.contains("connection_string"))
+        );
+    }
+
+    #[test]
+    fn out_of_range_mtu_reports_field() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        write!(
+            file,
+            r#"
+            connection_string = "127.0.0.1:55555"
+
+            [protocol]
+            mode = "noise"
+            server_public_key = "6axLx6XF+CrzWL6WppAr4R5/FO1NCq6yRuG59iqQCQ8="
+            private_key = "6axLx6XF+CrzWL6WppAr4R5/FO1NCq6yRuG59iqQCQ8="
+
+            [connection]
+            mtu = 100
+
+            [log]
+            level = "info"
+            "#
+        )
+        .unwrap();
+
+        let error = try_parse_config(file.path()).expect_err("config must fail validation");
+
+        assert_eq!(validation::config_error_field(&error), Some("mtu"));
+    }
+}
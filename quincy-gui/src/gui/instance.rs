@@ -201,6 +201,52 @@ impl QuincyInstance {
         Ok(())
     }
 
+    /// Asks the daemon to close and re-establish the VPN connection.
+    ///
+    /// # Returns
+    /// * `Ok(metrics)` with the metrics of the new connection
+    /// * `Err` if the daemon failed to reconnect or communication fails
+    pub async fn reconnect(&self) -> Result<Option<ConnectionMetrics>> {
+        let Some(ref ipc_connection) = self.ipc_client else {
+            return Err(QuincyError::system("Not connected to the daemon"));
+        };
+
+        let mut connection = ipc_connection.lock().await;
+
+        connection.send(&IpcMessage::Reconnect).await?;
+
+        match connection.recv().await? {
+            IpcMessage::StatusUpdate(status) => Ok(status.metrics),
+            IpcMessage::Error(err) => Err(QuincyError::system(err.to_string())),
+            other => Err(QuincyError::system(format!(
+                "Unexpected response to reconnect request: {other:?}"
+            ))),
+        }
+    }
+
+    /// Asks the daemon to re-read the configuration file and apply it to the running client.
+    ///
+    /// # Returns
+    /// * `Ok(metrics)` with the metrics of the current connection
+    /// * `Err` if the configuration could not be applied or communication fails
+    pub async fn reload_config(&self) -> Result<Option<ConnectionMetrics>> {
+        let Some(ref ipc_connection) = self.ipc_client else {
+            return Err(QuincyError::system("Not connected to the daemon"));
+        };
+
+        let mut connection = ipc_connection.lock().await;
+
+        connection.send(&IpcMessage::ReloadConfig).await?;
+
+        match connection.recv().await? {
+            IpcMessage::StatusUpdate(status) => Ok(status.metrics),
+            IpcMessage::Error(err) => Err(QuincyError::system(err.to_string())),
+            other => Err(QuincyError::system(format!(
+                "Unexpected response to reload request: {other:?}"
+            ))),
+        }
+    }
+
     /// Sends a shutdown message to the daemon.
     async fn send_shutdown_message(&self) {
         if let Some(ref ipc_connection) = self.ipc_client {
@@ -6,12 +6,14 @@
 //! - `types`: Core data structures and message types
 //! - `instance`: VPN instance management and IPC communication
 //! - `app`: Main application logic and state management
+//! - `config_edit`: Targeted configuration file edits that preserve comments
 //! - `handlers`: Event handlers for user interactions
 //! - `ui_builders`: UI component builders and layout methods
 //! - `styles`: Visual styling and theming
 //! - `utils`: Utility functions for formatting and path handling
 
 mod app;
+mod config_edit;
 mod error;
 mod handlers;
 mod instance;
@@ -1,5 +1,6 @@
 use iced::widget::text_editor;
 use iced::window;
+use quincy::QuincyError;
 use quincy::config::ClientConfig;
 use std::fmt;
 use std::path::PathBuf;
@@ -150,8 +151,10 @@ pub struct ConfigEntry {
     pub state: ConfigState,
     /// Parsed configuration for display (None if parsing failed)
     pub parsed: Option<ClientConfig>,
-    /// Parse error message if configuration failed to parse
-    pub parse_error: Option<String>,
+    /// Structured parse error if configuration failed to parse or validate
+    pub parse_error: Option<QuincyError>,
+    /// Routes being edited in the configuration view, not yet saved
+    pub routes_draft: Option<String>,
 }
 
 /// State for the inline editor modal.
@@ -183,6 +186,8 @@ pub enum ConfigMsg {
     Selected(String),
     NameChanged(String),
     NameSaved,
+    RoutesChanged(String),
+    RoutesSaved,
     Delete,
     New,
 }
@@ -217,6 +222,8 @@ pub enum InstanceMsg {
     ConnectedInstance(QuincyInstance, Option<ConnectionMetrics>),
     /// User requested to disconnect
     Disconnect,
+    /// User requested to re-establish the connection
+    Reconnect,
     /// User requested to cancel an in-progress connection
     CancelConnect,
     /// Disconnection completed
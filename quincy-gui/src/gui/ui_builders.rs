@@ -7,6 +7,7 @@ use iced::widget::{
 use iced::widget::{column, opaque, row, scrollable, stack, text, text_editor};
 use iced::{Alignment, Background, Border, Element, Font, Length, border};
 use quincy::config::ClientProtocolConfig;
+use quincy_client::health::ConnectionQuality;
 
 use super::app::QuincyGui;
 use super::styles::{
@@ -15,8 +16,9 @@ use super::styles::{
 };
 use super::types::Message;
 use super::types::{ConfigEntry, ConfigMsg, ConfigState, ConfirmMsg, EditorMsg, InstanceMsg};
-use super::utils::{format_bytes, format_duration};
+use super::utils::{format_bytes, format_dns_servers, format_duration};
 use crate::ipc::ConnectionMetrics;
+use crate::validation;
 
 impl QuincyGui {
     /// Returns true if the editor modal is currently open.
@@ -386,29 +388,15 @@ impl QuincyGui {
     /// Builds the configuration view section with read-only fields.
     pub fn build_config_view_section(&self, entry: &ConfigEntry) -> Element<'_, Message> {
         let config_info = if let Some(ref config) = entry.parsed {
-            let routes_display = if config.network.routes.is_empty() {
-                "None".to_string()
-            } else {
-                config
-                    .network
-                    .routes
-                    .iter()
-                    .map(|route| route.to_string())
-                    .collect::<Vec<_>>()
-                    .join(", ")
-            };
+            let routes = config
+                .network
+                .routes
+                .iter()
+                .map(|route| route.to_string())
+                .collect::<Vec<_>>()
+                .join(", ");
 
-            let dns_servers_display = if config.network.dns_servers.is_empty() {
-                "None".to_string()
-            } else {
-                config
-                    .network
-                    .dns_servers
-                    .iter()
-                    .map(|dns| dns.to_string())
-                    .collect::<Vec<_>>()
-                    .join(", ")
-            };
+            let dns_servers_display = format_dns_servers(&config.network.dns_servers);
 
             let (protocol_name, crypto_type) = match &config.protocol {
                 ClientProtocolConfig::Tls(tls_config) => {
@@ -422,30 +410,60 @@ impl QuincyGui {
             column![
                 self.build_owned_config_field(
                     "Connection String".to_string(),
-                    config.connection_string.clone()
+                    config.server_endpoints().join(", ")
                 ),
                 self.build_owned_config_field(
                     "Encryption Type".to_string(),
                     format!("{protocol_name} ({crypto_type})")
                 ),
-                self.build_owned_config_field("Routes".to_string(), routes_display),
+                self.build_routes_input(entry, routes),
                 self.build_owned_config_field("DNS Servers".to_string(), dns_servers_display),
             ]
             .spacing(Spacing::MD)
         } else {
             let error_msg = entry
                 .parse_error
-                .clone()
+                .as_ref()
+                .map(|e| e.to_string())
                 .unwrap_or_else(|| "Unknown error".to_string());
-            column![
+            let field = entry
+                .parse_error
+                .as_ref()
+                .and_then(validation::config_error_field);
+            let hint = entry
+                .parse_error
+                .as_ref()
+                .and_then(validation::config_error_hint);
+
+            let mut error_column = column![
                 text("Configuration parsing failed")
                     .size(Typography::BODY)
                     .color(ColorPalette::ERROR),
+            ]
+            .spacing(Spacing::SM);
+
+            if let Some(field) = field {
+                error_column = error_column.push(
+                    text(format!("Setting: {field}"))
+                        .size(Typography::BODY)
+                        .font(Font::MONOSPACE)
+                        .color(ColorPalette::TEXT_PRIMARY),
+                );
+            }
+
+            if let Some(hint) = hint {
+                error_column = error_column.push(
+                    text(hint)
+                        .size(Typography::CAPTION)
+                        .color(ColorPalette::TEXT_PRIMARY),
+                );
+            }
+
+            error_column.push(
                 text(error_msg)
                     .size(Typography::CAPTION)
                     .color(ColorPalette::TEXT_SECONDARY),
-            ]
-            .spacing(Spacing::SM)
+            )
         };
 
         container_widget(
@@ -473,6 +491,32 @@ impl QuincyGui {
         .into()
     }
 
+    /// Builds the routes field of the configuration view, editable unless the editor is open.
+    ///
+    /// Submitting the field rewrites only the routes of the configuration file.
+    pub fn build_routes_input(&self, entry: &ConfigEntry, routes: String) -> Element<'_, Message> {
+        let value = entry.routes_draft.clone().unwrap_or(routes);
+
+        let mut input = text_input_widget("None", &value)
+            .padding([Spacing::BUTTON_V, Spacing::MD])
+            .size(Typography::BODY);
+
+        if !self.is_editor_open() {
+            input = input
+                .on_input(|s| Message::Config(ConfigMsg::RoutesChanged(s)))
+                .on_submit(Message::Config(ConfigMsg::RoutesSaved));
+        }
+
+        column![
+            text("Routes")
+                .size(Typography::CAPTION)
+                .color(ColorPalette::TEXT_SECONDARY),
+            input.style(CustomTextInputStyle::default_fn())
+        ]
+        .spacing(Spacing::XS)
+        .into()
+    }
+
     /// Builds a single configuration field display with owned strings.
     pub fn build_owned_config_field(&self, label: String, value: String) -> Element<'_, Message> {
         column![
@@ -533,6 +577,20 @@ impl QuincyGui {
                 .into(),
         ];
 
+        if let Some(quality) = metrics.and_then(|metrics| metrics.connection_quality) {
+            let quality_color = match quality {
+                ConnectionQuality::Good => ColorPalette::SUCCESS,
+                ConnectionQuality::Degraded => ColorPalette::WARNING,
+                ConnectionQuality::Poor => ColorPalette::ERROR,
+            };
+            content.push(
+                text(format!("Connection quality: {quality}"))
+                    .size(Typography::CAPTION)
+                    .color(quality_color)
+                    .into(),
+            );
+        }
+
         if let Some(metrics) = metrics {
             content.extend([
                 container_widget(text(""))
@@ -588,6 +646,51 @@ impl QuincyGui {
             );
         }
 
+        if let Some(server_endpoint) = &metrics.server_endpoint {
+            ip_info.push(
+                column![
+                    text("Server Endpoint")
+                        .size(Typography::CAPTION)
+                        .color(ColorPalette::TEXT_SECONDARY),
+                    text(server_endpoint.clone())
+                        .size(Typography::BODY)
+                        .color(ColorPalette::TEXT_PRIMARY),
+                ]
+                .spacing(Spacing::XS)
+                .into(),
+            );
+        }
+
+        if let Some(tunnel_mtu) = metrics.tunnel_mtu {
+            ip_info.push(
+                column![
+                    text("MTU")
+                        .size(Typography::CAPTION)
+                        .color(ColorPalette::TEXT_SECONDARY),
+                    text(tunnel_mtu.to_string())
+                        .size(Typography::BODY)
+                        .color(ColorPalette::TEXT_PRIMARY),
+                ]
+                .spacing(Spacing::XS)
+                .into(),
+            );
+        }
+
+        if let Some(app_rtt_ms) = metrics.app_rtt_ms {
+            ip_info.push(
+                column![
+                    text("Round trip")
+                        .size(Typography::CAPTION)
+                        .color(ColorPalette::TEXT_SECONDARY),
+                    text(format!("{app_rtt_ms} ms"))
+                        .size(Typography::BODY)
+                        .color(ColorPalette::TEXT_PRIMARY),
+                ]
+                .spacing(Spacing::XS)
+                .into(),
+            );
+        }
+
         ip_info.push(
             column![
                 text("Connected for")
@@ -710,7 +813,27 @@ impl QuincyGui {
             )
         };
 
-        row![connection_button, edit_button, delete_button]
+        let mut buttons = row![connection_button];
+
+        // Reconnect button - only while connected, disabled when editor open
+        if is_connected {
+            let reconnect_button = if is_editor_open {
+                Self::styled_button("Reconnect", None, |_theme, _status| {
+                    CustomButtonStyles::disabled()
+                })
+            } else {
+                Self::styled_button(
+                    "Reconnect",
+                    Some(Message::Instance(InstanceMsg::Reconnect)),
+                    |theme, status| CustomButtonStyles::secondary_fn()(theme, status),
+                )
+            };
+            buttons = buttons.push(reconnect_button);
+        }
+
+        buttons
+            .push(edit_button)
+            .push(delete_button)
             .spacing(Spacing::MD)
             .width(Length::Fill)
             .into()
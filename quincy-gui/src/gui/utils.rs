@@ -1,4 +1,5 @@
 use std::env;
+use std::net::IpAddr;
 use std::path::{Path, PathBuf};
 use std::time::Duration;
 
@@ -25,6 +26,25 @@ pub fn format_bytes(bytes: u64) -> String {
     format!("{:.1} {}", size, UNITS[unit_index])
 }
 
+/// Formats a list of DNS servers for display, e.g. "10.0.0.1, 2606:4700:4700::1111".
+///
+/// # Arguments
+/// * `dns_servers` - DNS servers to format
+///
+/// # Returns
+/// The DNS servers separated by commas, or "None" if there are none
+pub fn format_dns_servers(dns_servers: &[IpAddr]) -> String {
+    if dns_servers.is_empty() {
+        return "None".to_string();
+    }
+
+    dns_servers
+        .iter()
+        .map(|server| server.to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
 /// Formats a duration into a human-readable string showing days, hours, minutes,
 /// and seconds. Units with zero values are omitted.
 ///
@@ -89,3 +109,22 @@ pub fn expand_path(path: &Path) -> PathBuf {
 
     path.to_path_buf()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dns_servers_of_mixed_families_are_formatted() {
+        let dns_servers: Vec<IpAddr> = ["10.0.0.1", "2606:4700:4700::1111", "fd00::53"]
+            .iter()
+            .map(|address| address.parse().unwrap())
+            .collect();
+
+        assert_eq!(
+            format_dns_servers(&dns_servers),
+            "10.0.0.1, 2606:4700:4700::1111, fd00::53"
+        );
+        assert_eq!(format_dns_servers(&[]), "None");
+    }
+}
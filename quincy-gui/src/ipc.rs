@@ -1,5 +1,8 @@
 use ipnet::IpNet;
 use quincy::{QuincyError, Result};
+use quincy_client::client::ShutdownReason;
+use quincy_client::health::ConnectionQuality;
+use quincy_client::stats::ConnectionStats;
 use serde::{Deserialize, Serialize};
 use std::env;
 use std::path::{Path, PathBuf};
@@ -26,15 +29,48 @@ use tokio::net::windows::named_pipe::{
     ClientOptions, NamedPipeClient, NamedPipeServer, ServerOptions,
 };
 
+/// The connection statistics sent to the GUI, projected from [`ConnectionStats`].
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ConnectionMetrics {
     pub bytes_sent: u64,
     pub bytes_received: u64,
     pub packets_sent: u64,
     pub packets_received: u64,
+    /// Payload bytes received through the tunnel and written to the TUN interface
+    pub tun_bytes_in: u64,
+    /// Payload bytes read from the TUN interface and sent through the tunnel
+    pub tun_bytes_out: u64,
     pub connection_duration: Duration,
     pub client_address: Option<IpNet>,
     pub server_address: Option<IpNet>,
+    /// Connection string of the server endpoint the tunnel is connected to
+    pub server_endpoint: Option<String>,
+    /// MTU of the tunnel interface, negotiated with the server
+    pub tunnel_mtu: Option<u16>,
+    /// Round-trip time of the last application keep-alive ping echoed by the server
+    pub app_rtt_ms: Option<u64>,
+    /// Quality graded from the sampled round-trip time, packet loss and congestion window
+    pub connection_quality: Option<ConnectionQuality>,
+}
+
+impl From<ConnectionStats> for ConnectionMetrics {
+    fn from(stats: ConnectionStats) -> Self {
+        Self {
+            bytes_sent: stats.bytes_sent,
+            bytes_received: stats.bytes_received,
+            packets_sent: stats.packets_sent,
+            packets_received: stats.packets_received,
+            tun_bytes_in: stats.tun_bytes_in,
+            tun_bytes_out: stats.tun_bytes_out,
+            connection_duration: stats.connection_duration,
+            client_address: stats.client_address,
+            server_address: stats.server_address,
+            server_endpoint: stats.server_endpoint,
+            tunnel_mtu: stats.tunnel_mtu,
+            app_rtt_ms: stats.app_rtt.map(|rtt| rtt.as_millis() as u64),
+            connection_quality: stats.quality,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -49,12 +85,16 @@ pub enum ConnectionStatus {
 pub struct ClientStatus {
     pub status: ConnectionStatus,
     pub metrics: Option<ConnectionMetrics>,
+    /// Why the tunnel ended, once it has ended
+    pub shutdown_reason: Option<ShutdownReason>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum IpcMessage {
     StartClient { config_path: PathBuf },
     StopClient,
+    Reconnect,
+    ReloadConfig,
     GetStatus,
     StatusUpdate(ClientStatus),
     Error(GuiError),
@@ -45,3 +45,27 @@ pub fn validate_instance_name(name: &str) -> Result<()> {
         })
     })
 }
+
+/// Returns the configuration field path a parse error refers to, if any.
+///
+/// Nested fields are dot-separated (e.g. `protocol.server_public_key`).
+pub fn config_error_field(error: &QuincyError) -> Option<&str> {
+    match error {
+        QuincyError::Config(ConfigError::MissingField { field })
+        | QuincyError::Config(ConfigError::InvalidValue { field, .. }) => Some(field),
+        _ => None,
+    }
+}
+
+/// Returns a short hint on how to fix the field a parse error refers to, if any.
+pub fn config_error_hint(error: &QuincyError) -> Option<String> {
+    match error {
+        QuincyError::Config(ConfigError::MissingField { field }) => Some(format!(
+            "Add the required setting '{field}' to the configuration"
+        )),
+        QuincyError::Config(ConfigError::InvalidValue { field, reason }) => {
+            Some(format!("Check the value of '{field}': {reason}"))
+        }
+        _ => None,
+    }
+}
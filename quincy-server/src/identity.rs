@@ -1,7 +1,7 @@
 //! Client identity resolution after QUIC handshake.
 //!
 //! Extracts the peer identity from the completed connection and resolves it
-//! to a username using the users file.
+//! to a username and device identifier using the users file.
 
 use std::any::Any;
 
@@ -12,6 +12,17 @@ use quinn::Connection;
 use reishi_quinn::PeerIdentity;
 use rustls::pki_types::CertificateDer;
 
+/// The identity of an authenticated client.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ClientIdentity {
+    /// The username the client's credential belongs to
+    pub username: String,
+    /// A stable identifier of the client device, derived from its credential
+    ///
+    /// Users may own several credentials (one per device), so this tells their devices apart.
+    pub device_id: String,
+}
+
 /// Extracts the peer identity from the connection and resolves the username.
 ///
 /// For Noise connections, extracts the `PeerIdentity` and looks up the public
@@ -24,7 +35,7 @@ use rustls::pki_types::CertificateDer;
 /// - `users` - the parsed users file for username lookup
 ///
 /// ### Returns
-/// The resolved username on success.
+/// The resolved client identity on success.
 ///
 /// ### Errors
 /// Returns `AuthError::HandshakeRejected` if the peer identity cannot be
@@ -34,7 +45,7 @@ pub fn identify_peer(
     connection: &Connection,
     protocol: &ServerProtocolConfig,
     users: &UsersFile,
-) -> Result<String> {
+) -> Result<ClientIdentity> {
     let peer_identity = connection
         .peer_identity()
         .ok_or(AuthError::HandshakeRejected)?;
@@ -49,29 +60,37 @@ pub fn identify_peer(
 ///
 /// Downcasts the peer identity to `PeerIdentity` and looks up the public key
 /// (standard X25519 or hybrid PQ) in the users file.
-fn identify_noise_peer(peer_identity: Box<dyn Any>, users: &UsersFile) -> Result<String> {
+fn identify_noise_peer(peer_identity: Box<dyn Any>, users: &UsersFile) -> Result<ClientIdentity> {
     let noise_identity = peer_identity
         .downcast_ref::<PeerIdentity>()
         .ok_or(AuthError::HandshakeRejected)?;
 
     if let Some(pq_pubkey) = &noise_identity.pq_public_key {
-        return users
+        let username = users
             .find_user_by_noise_pq_pubkey(pq_pubkey)
-            .map(|user| user.to_string())
-            .ok_or(AuthError::UserUnknown.into());
+            .ok_or(AuthError::UserUnknown)?;
+
+        return Ok(ClientIdentity {
+            username: username.to_string(),
+            device_id: format!("noise-pq:{}", hex_encode(&pq_pubkey.to_bytes())),
+        });
     }
 
-    users
+    let username = users
         .find_user_by_noise_pubkey(&noise_identity.public_key)
-        .map(|user| user.to_string())
-        .ok_or(AuthError::UserUnknown.into())
+        .ok_or(AuthError::UserUnknown)?;
+
+    Ok(ClientIdentity {
+        username: username.to_string(),
+        device_id: format!("noise:{}", hex_encode(noise_identity.public_key.as_bytes())),
+    })
 }
 
 /// Resolves a TLS peer identity to a username.
 ///
 /// Downcasts the peer identity to a certificate chain, computes the SHA-256
 /// fingerprint of the end-entity certificate, and looks it up in the users file.
-fn identify_tls_peer(peer_identity: Box<dyn Any>, users: &UsersFile) -> Result<String> {
+fn identify_tls_peer(peer_identity: Box<dyn Any>, users: &UsersFile) -> Result<ClientIdentity> {
     let certs = peer_identity
         .downcast_ref::<Vec<CertificateDer<'static>>>()
         .ok_or(AuthError::HandshakeRejected)?;
@@ -80,8 +99,17 @@ fn identify_tls_peer(peer_identity: Box<dyn Any>, users: &UsersFile) -> Result<S
 
     let fingerprint = quincy::certificates::compute_cert_fingerprint(end_entity);
 
-    users
+    let username = users
         .find_user_by_cert_fingerprint(&fingerprint)
-        .map(|s| s.to_string())
-        .ok_or(AuthError::UserUnknown.into())
+        .ok_or(AuthError::UserUnknown)?;
+
+    Ok(ClientIdentity {
+        username: username.to_string(),
+        device_id: format!("cert:{fingerprint}"),
+    })
+}
+
+/// Encodes bytes as a lowercase hex string.
+fn hex_encode(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
 }
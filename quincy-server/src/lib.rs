@@ -1,3 +1,4 @@
 pub mod identity;
+pub mod policy;
 pub mod server;
 pub mod users;
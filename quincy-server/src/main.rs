@@ -3,7 +3,7 @@ use std::process::exit;
 
 use clap::Parser;
 use quincy::Result;
-use quincy::config::{FromPath, ServerConfig};
+use quincy::config::{FromEnv, FromPath, ServerConfig};
 use quincy::network::interface::tun_rs::TunRsInterface;
 use quincy::utils::tracing::log_subscriber;
 use quincy_server::server::QuincyServer;
@@ -16,6 +16,9 @@ pub struct Args {
     pub config_path: PathBuf,
     #[arg(long, default_value = "QUINCY_")]
     pub env_prefix: String,
+    /// Build the configuration from environment variables only, ignoring the config file
+    #[arg(long)]
+    pub env_only: bool,
 }
 
 #[tokio::main]
@@ -35,7 +38,12 @@ async fn main() {
 /// Runs the Quincy server.
 async fn run_server() -> Result<()> {
     let args = Args::parse();
-    let config = ServerConfig::from_path(&args.config_path, &args.env_prefix)?;
+    let config = if args.env_only {
+        ServerConfig::from_env(&args.env_prefix)?
+    } else {
+        ServerConfig::from_path(&args.config_path, &args.env_prefix)?
+    };
+    config.validate()?;
     // Enable tracing with the log level from the configuration.
     tracing::subscriber::set_global_default(log_subscriber(&config.log.level))?;
 
@@ -0,0 +1,240 @@
+//! Authorization policy resolution for Quincy VPN users.
+//!
+//! Combines a user's own settings from the users file with the policies of the
+//! groups they belong to (defined in the server configuration) into the effective
+//! policy applied to their connections.
+
+use std::collections::HashMap;
+
+use ipnet::IpNet;
+
+use quincy::config::{Bandwidth, GroupConfig, ServerConfig};
+use quincy::error::{AuthError, Result};
+
+use crate::users::UserEntry;
+
+/// Name of the group that users without any groups belong to.
+pub const DEFAULT_GROUP: &str = "default";
+
+/// The effective authorization policy of a user.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct UserPolicy {
+    /// Destination networks the user may send traffic to (empty = all).
+    pub allowed_destinations: Vec<IpNet>,
+    /// Bandwidth limit of the user (`None` = unlimited).
+    pub bandwidth_limit: Option<Bandwidth>,
+}
+
+impl UserPolicy {
+    /// Resolves the effective policy of a user.
+    ///
+    /// Without any groups configured, the user's own settings apply, falling back
+    /// to the server's default bandwidth limit. Once groups are configured, the user
+    /// may reach the union of the destinations granted by their groups (or the
+    /// `default` group if they have none) and their own `allowed_destinations`.
+    /// The bandwidth limit is the user's own, else the highest of their groups',
+    /// else the server default.
+    ///
+    /// ### Arguments
+    /// - `username` - the name of the user
+    /// - `entry` - the user's entry in the users file
+    /// - `config` - the server configuration defining the groups
+    ///
+    /// ### Errors
+    /// Returns `AuthError::PermissionDenied` if groups are configured but grant the
+    /// user no destinations at all.
+    pub fn resolve(username: &str, entry: &UserEntry, config: &ServerConfig) -> Result<Self> {
+        if config.groups.is_empty() {
+            return Ok(Self {
+                allowed_destinations: entry.allowed_destinations.clone(),
+                bandwidth_limit: entry.bandwidth_limit.or(config.default_bandwidth_limit),
+            });
+        }
+
+        let groups = user_groups(entry, &config.groups);
+
+        let mut allowed_destinations = entry.allowed_destinations.clone();
+        for network in groups.iter().flat_map(|group| &group.allowed_destinations) {
+            if !allowed_destinations.contains(network) {
+                allowed_destinations.push(*network);
+            }
+        }
+
+        if allowed_destinations.is_empty() {
+            return Err(AuthError::PermissionDenied {
+                username: username.to_string(),
+            }
+            .into());
+        }
+
+        let group_bandwidth_limit = groups
+            .iter()
+            .filter_map(|group| group.bandwidth_limit)
+            .max_by_key(|limit| limit.bytes_per_second());
+
+        Ok(Self {
+            allowed_destinations,
+            bandwidth_limit: entry
+                .bandwidth_limit
+                .or(group_bandwidth_limit)
+                .or(config.default_bandwidth_limit),
+        })
+    }
+}
+
+/// Returns the defined groups of a user, or the default group if the user has none.
+fn user_groups<'a>(
+    entry: &UserEntry,
+    groups: &'a HashMap<String, GroupConfig>,
+) -> Vec<&'a GroupConfig> {
+    if entry.groups.is_empty() {
+        return groups.get(DEFAULT_GROUP).into_iter().collect();
+    }
+
+    entry
+        .groups
+        .iter()
+        .filter_map(|name| groups.get(name))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::users::UsersFile;
+    use figment::{
+        Figment,
+        providers::{Format, Toml},
+    };
+
+    const SERVER_CONFIG: &str = r#"
+        name = "test"
+        tunnel_network = "10.0.0.1/24"
+        users_file = "users.toml"
+        default_bandwidth_limit = "1 mbps"
+
+        [protocol]
+        mode = "noise"
+        private_key = "AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA="
+
+        [log]
+        level = "info"
+    "#;
+
+    const USERS: &str = r#"
+        [users.alice]
+        groups = ["engineering", "ops"]
+
+        [users.bob]
+        allowed_destinations = ["192.168.1.0/24"]
+        bandwidth_limit = "5 mbps"
+
+        [users.carol]
+        groups = ["engineering"]
+        allowed_destinations = ["10.1.0.0/16", "10.3.0.0/16"]
+    "#;
+
+    const GROUPS: &str = r#"
+        [groups.engineering]
+        allowed_destinations = ["10.1.0.0/16"]
+        bandwidth_limit = "10 mbps"
+
+        [groups.ops]
+        allowed_destinations = ["10.2.0.0/16", "10.1.0.0/16"]
+        bandwidth_limit = "50 mbps"
+
+        [groups.default]
+        allowed_destinations = ["10.9.0.0/24"]
+    "#;
+
+    fn config(groups: &str) -> ServerConfig {
+        Figment::new()
+            .merge(Toml::string(SERVER_CONFIG))
+            .merge(Toml::string(groups))
+            .extract()
+            .expect("valid server config")
+    }
+
+    fn resolve(username: &str, config: &ServerConfig) -> Result<UserPolicy> {
+        let users = UsersFile::parse(USERS).expect("valid users file");
+        UserPolicy::resolve(username, &users.users[username], config)
+    }
+
+    fn networks(networks: &[&str]) -> Vec<IpNet> {
+        networks.iter().map(|n| n.parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn user_settings_apply_without_groups() {
+        let config = config("");
+
+        assert_eq!(
+            resolve("bob", &config).unwrap(),
+            UserPolicy {
+                allowed_destinations: networks(&["192.168.1.0/24"]),
+                bandwidth_limit: Some("5 mbps".parse().unwrap()),
+            }
+        );
+        assert_eq!(
+            resolve("alice", &config).unwrap(),
+            UserPolicy {
+                allowed_destinations: Vec::new(),
+                bandwidth_limit: Some("1 mbps".parse().unwrap()),
+            }
+        );
+    }
+
+    #[test]
+    fn multiple_groups_are_combined() {
+        let policy = resolve("alice", &config(GROUPS)).unwrap();
+
+        assert_eq!(
+            policy,
+            UserPolicy {
+                allowed_destinations: networks(&["10.1.0.0/16", "10.2.0.0/16"]),
+                bandwidth_limit: Some("50 mbps".parse().unwrap()),
+            }
+        );
+    }
+
+    #[test]
+    fn user_settings_extend_group_policy() {
+        let policy = resolve("carol", &config(GROUPS)).unwrap();
+
+        assert_eq!(
+            policy.allowed_destinations,
+            networks(&["10.1.0.0/16", "10.3.0.0/16"])
+        );
+        assert_eq!(policy.bandwidth_limit, Some("10 mbps".parse().unwrap()));
+    }
+
+    #[test]
+    fn user_without_groups_falls_back_to_default_group() {
+        let policy = resolve("bob", &config(GROUPS)).unwrap();
+
+        assert_eq!(
+            policy,
+            UserPolicy {
+                allowed_destinations: networks(&["192.168.1.0/24", "10.9.0.0/24"]),
+                bandwidth_limit: Some("5 mbps".parse().unwrap()),
+            }
+        );
+    }
+
+    #[test]
+    fn no_granted_access_is_denied() {
+        let config = config(
+            r#"
+            [groups.engineering]
+            bandwidth_limit = "10 mbps"
+            "#,
+        );
+
+        assert!(matches!(
+            resolve("alice", &config),
+            Err(quincy::QuincyError::Auth(
+                AuthError::PermissionDenied { .. }
+            ))
+        ));
+    }
+}
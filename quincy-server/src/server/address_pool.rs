@@ -1,11 +1,13 @@
 use std::collections::HashMap;
 use std::net::IpAddr;
+use std::time::{Duration, Instant};
 
-use dashmap::DashSet;
+use dashmap::{DashMap, DashSet};
 use ipnet::IpNet;
 
 use quincy::config::AddressRange;
 use quincy::error::{AuthError, Result};
+use quincy::ip_assignment::generate_resumption_token;
 
 /// A pool of IP addresses from which addresses can be allocated and released.
 ///
@@ -50,6 +52,17 @@ impl AddressPool {
         self.used_addresses.remove(address);
     }
 
+    /// Claims a specific address if it is not in use.
+    ///
+    /// ### Arguments
+    /// - `address` - the address to claim
+    ///
+    /// ### Returns
+    /// - `bool` - whether the address was available and is now claimed
+    pub fn claim_address(&self, address: IpAddr) -> bool {
+        self.used_addresses.insert(address)
+    }
+
     /// Marks a set of addresses as used, preventing them from being allocated.
     ///
     /// ### Arguments
@@ -61,6 +74,33 @@ impl AddressPool {
     }
 }
 
+/// Identifies a single client device of a user.
+///
+/// Users may connect several devices at once, so sticky addresses are keyed
+/// on the device rather than the username alone.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct DeviceKey {
+    /// The authenticated username
+    pub username: String,
+    /// A stable identifier of the device (derived from its authenticated credential)
+    pub device_id: String,
+}
+
+/// An address held for a disconnected device until its grace period ends.
+struct StickyAddress {
+    address: IpAddr,
+    expires_at: Instant,
+}
+
+/// An assigned address, resumable with the token issued along with it.
+struct ResumptionLease {
+    /// The device the address was assigned to
+    device: DeviceKey,
+    address: IpAddr,
+    /// When the token expires, counted from the release of the address (`None` while assigned)
+    expires_at: Option<Instant>,
+}
+
 /// Manages IP address allocation across a global pool and optional per-user
 /// reserved pools.
 ///
@@ -68,6 +108,15 @@ impl AddressPool {
 /// Users without a per-user pool get addresses from the global (unreserved) pool.
 /// Reserved addresses are pre-inserted into the global pool's used set at
 /// construction time so they are never handed out to unrestricted users.
+///
+/// With a non-zero grace period, released addresses stay reserved for the
+/// device that held them, so a device reconnecting within the grace period
+/// gets its previous address back.
+///
+/// With resumption tokens enabled, each allocated address is leased to the
+/// token issued along with it. A device presenting the token within the token
+/// lifetime after releasing the address gets it back, unless it was allocated
+/// to another device in the meantime.
 pub struct AddressPoolManager {
     /// The tunnel network (carries server IP + netmask for wrapping allocations).
     network: IpNet,
@@ -75,6 +124,14 @@ pub struct AddressPoolManager {
     global_pool: AddressPool,
     /// Per-user reserved pools, keyed by username.
     user_pools: HashMap<String, AddressPool>,
+    /// How long released addresses are held for their device.
+    grace_period: Duration,
+    /// Addresses held for recently disconnected devices.
+    sticky_addresses: DashMap<DeviceKey, StickyAddress>,
+    /// How long a released address can be resumed with its token (zero disables tokens).
+    resumption_ttl: Duration,
+    /// Addresses leased to resumption tokens, keyed by token.
+    resumption_leases: DashMap<String, ResumptionLease>,
 }
 
 impl AddressPoolManager {
@@ -94,12 +151,17 @@ impl AddressPoolManager {
     /// ### Arguments
     /// - `network` - the tunnel network (server IP + netmask)
     /// - `user_pools` - per-user address ranges, keyed by username
+    /// - `grace_period` - how long a released address is held for its device (zero disables this)
     ///
     /// ### Errors
     /// Returns `AuthError::InvalidUserStore` if any user pool address falls
     /// outside the tunnel network or is a reserved tunnel address (network,
     /// server, or broadcast).
-    pub fn new(network: IpNet, user_pools: HashMap<String, Vec<AddressRange>>) -> Result<Self> {
+    pub fn new(
+        network: IpNet,
+        user_pools: HashMap<String, Vec<AddressRange>>,
+        grace_period: Duration,
+    ) -> Result<Self> {
         // Build the global pool covering the entire tunnel network
         let global_pool = AddressPool::new(vec![AddressRange::from(network)]);
 
@@ -146,21 +208,42 @@ impl AddressPoolManager {
             network,
             global_pool,
             user_pools: built_user_pools,
+            grace_period,
+            sticky_addresses: DashMap::new(),
+            resumption_ttl: Duration::ZERO,
+            resumption_leases: DashMap::new(),
         })
     }
 
-    /// Allocates an address for the given user.
+    /// Enables resumption tokens.
+    ///
+    /// ### Arguments
+    /// - `ttl` - how long a released address can be resumed with its token (zero disables tokens)
+    pub fn with_resumption_ttl(mut self, ttl: Duration) -> Self {
+        self.resumption_ttl = ttl;
+        self
+    }
+
+    /// Returns the tunnel network (server IP + netmask) the pool allocates from.
+    pub fn network(&self) -> IpNet {
+        self.network
+    }
+
+    /// Allocates an address for the given device.
     ///
-    /// If the user has a per-user pool, allocates from that pool. Otherwise
-    /// allocates from the global pool. Returns the address wrapped in an
+    /// If the device disconnected within the grace period, its previous address
+    /// is returned. Otherwise, if the user has a per-user pool, allocates from
+    /// that pool, else from the global pool. Returns the address wrapped in an
     /// [`IpNet`] with the tunnel network's netmask.
     ///
     /// ### Arguments
-    /// - `username` - the authenticated username
-    pub fn allocate_address(&self, username: &str) -> Option<IpNet> {
-        let address = match self.user_pools.get(username) {
-            Some(user_pool) => user_pool.next_available_address()?,
-            None => self.global_pool.next_available_address()?,
+    /// - `device` - the authenticated user's device
+    pub fn allocate_address(&self, device: &DeviceKey) -> Option<IpNet> {
+        self.expire_sticky_addresses();
+
+        let address = match self.sticky_addresses.remove(device) {
+            Some((_, sticky)) => sticky.address,
+            None => self.pool_for(&device.username).next_available_address()?,
         };
 
         Some(
@@ -169,20 +252,142 @@ impl AddressPoolManager {
         )
     }
 
+    /// Issues a resumption token for an allocated address.
+    ///
+    /// ### Arguments
+    /// - `device` - the device the address is allocated to
+    /// - `address` - the allocated address
+    ///
+    /// ### Returns
+    /// - `Option<String>` - the token, or `None` if resumption tokens are disabled
+    pub fn issue_resumption_token(&self, device: &DeviceKey, address: IpAddr) -> Option<String> {
+        if self.resumption_ttl.is_zero() {
+            return None;
+        }
+
+        let token = generate_resumption_token();
+        self.resumption_leases.insert(
+            token.clone(),
+            ResumptionLease {
+                device: device.clone(),
+                address,
+                expires_at: None,
+            },
+        );
+
+        Some(token)
+    }
+
+    /// Returns whether a resumption token was issued to the device, which it may
+    /// present when reconnecting.
+    ///
+    /// ### Arguments
+    /// - `device` - the authenticated user's device
+    pub fn has_resumption_lease(&self, device: &DeviceKey) -> bool {
+        self.expire_resumption_leases();
+
+        self.resumption_leases
+            .iter()
+            .any(|lease| lease.device == *device)
+    }
+
+    /// Allocates the address leased to a resumption token to the device presenting it.
+    ///
+    /// The token is used up. If the device disconnected within the grace period and
+    /// its held address is the leased one, that address is returned as well.
+    ///
+    /// ### Arguments
+    /// - `device` - the authenticated user's device
+    /// - `token` - the resumption token presented by the device
+    ///
+    /// ### Returns
+    /// - `Option<IpNet>` - the leased address, or `None` if the token is unknown, expired,
+    ///   issued to another device or its address is still assigned, or the address was
+    ///   allocated to another device in the meantime
+    pub fn resume_address(&self, device: &DeviceKey, token: &str) -> Option<IpNet> {
+        self.expire_resumption_leases();
+
+        let (_, lease) = self.resumption_leases.remove_if(token, |_, lease| {
+            lease.device == *device && lease.expires_at.is_some()
+        })?;
+
+        let held = self
+            .sticky_addresses
+            .remove_if(device, |_, sticky| sticky.address == lease.address)
+            .is_some();
+        if !held && !self.pool_for(&device.username).claim_address(lease.address) {
+            return None;
+        }
+
+        Some(
+            IpNet::with_netmask(lease.address, self.network.netmask())
+                .expect("Netmask is always valid for addresses within the tunnel network"),
+        )
+    }
+
     /// Releases an address back to the appropriate pool.
     ///
     /// If the user has a per-user pool, releases to that pool. Otherwise
-    /// releases to the global pool.
+    /// releases to the global pool. With a non-zero grace period, the address
+    /// is held for the device until the grace period ends.
     ///
     /// ### Arguments
-    /// - `username` - the authenticated username
+    /// - `device` - the authenticated user's device
     /// - `address` - the address to release
-    pub fn release_address(&self, username: &str, address: &IpAddr) {
-        match self.user_pools.get(username) {
-            Some(user_pool) => user_pool.release_address(address),
-            None => self.global_pool.release_address(address),
+    pub fn release_address(&self, device: &DeviceKey, address: &IpAddr) {
+        if !self.resumption_ttl.is_zero() {
+            let expires_at = Instant::now() + self.resumption_ttl;
+            for mut lease in self.resumption_leases.iter_mut() {
+                if lease.device == *device && lease.address == *address {
+                    lease.expires_at = Some(expires_at);
+                }
+            }
+        }
+
+        if self.grace_period.is_zero() {
+            self.pool_for(&device.username).release_address(address);
+            return;
+        }
+
+        let sticky = StickyAddress {
+            address: *address,
+            expires_at: Instant::now() + self.grace_period,
+        };
+
+        if let Some(previous) = self.sticky_addresses.insert(device.clone(), sticky) {
+            if previous.address != *address {
+                self.pool_for(&device.username)
+                    .release_address(&previous.address);
+            }
         }
     }
+
+    /// Returns the pool addresses of the given user are allocated from.
+    fn pool_for(&self, username: &str) -> &AddressPool {
+        self.user_pools.get(username).unwrap_or(&self.global_pool)
+    }
+
+    /// Returns addresses whose grace period has ended to their pools.
+    fn expire_sticky_addresses(&self) {
+        let now = Instant::now();
+
+        self.sticky_addresses.retain(|device, sticky| {
+            let active = sticky.expires_at > now;
+            if !active {
+                self.pool_for(&device.username)
+                    .release_address(&sticky.address);
+            }
+            active
+        });
+    }
+
+    /// Drops resumption leases whose token has expired.
+    fn expire_resumption_leases(&self) {
+        let now = Instant::now();
+
+        self.resumption_leases
+            .retain(|_, lease| lease.expires_at.is_none_or(|expires_at| expires_at > now));
+    }
 }
 
 #[cfg(test)]
@@ -193,6 +398,17 @@ mod tests {
     use std::collections::HashMap;
     use std::net::Ipv4Addr;
 
+    fn device(username: &str) -> DeviceKey {
+        device_of(username, "default")
+    }
+
+    fn device_of(username: &str, device_id: &str) -> DeviceKey {
+        DeviceKey {
+            username: username.to_string(),
+            device_id: device_id.to_string(),
+        }
+    }
+
     /// 10.0.0.0/29 = 8 addresses: .0 (network), .1 (server), .2-.6 (usable), .7 (broadcast)
     fn test_network() -> IpNet {
         IpNet::V4(
@@ -264,11 +480,11 @@ mod tests {
             "alice".to_string(),
             vec!["10.0.0.2/32".parse::<AddressRange>().unwrap()],
         )]);
-        let manager = AddressPoolManager::new(test_network(), user_pools).unwrap();
+        let manager = AddressPoolManager::new(test_network(), user_pools, Duration::ZERO).unwrap();
 
         // Global pool should skip .0 (network), .1 (server), .2 (reserved), .7 (broadcast)
         // First global allocation is .3
-        let addr = manager.allocate_address("bob").unwrap();
+        let addr = manager.allocate_address(&device("bob")).unwrap();
         assert_eq!(addr.addr(), IpAddr::from(Ipv4Addr::new(10, 0, 0, 3)));
     }
 
@@ -278,9 +494,9 @@ mod tests {
             "alice".to_string(),
             vec!["10.0.0.5 - 10.0.0.6".parse::<AddressRange>().unwrap()],
         )]);
-        let manager = AddressPoolManager::new(test_network(), user_pools).unwrap();
+        let manager = AddressPoolManager::new(test_network(), user_pools, Duration::ZERO).unwrap();
 
-        let addr = manager.allocate_address("alice").unwrap();
+        let addr = manager.allocate_address(&device("alice")).unwrap();
         assert_eq!(addr.addr(), IpAddr::from(Ipv4Addr::new(10, 0, 0, 5)));
     }
 
@@ -290,12 +506,12 @@ mod tests {
             "alice".to_string(),
             vec!["10.0.0.5/32".parse::<AddressRange>().unwrap()],
         )]);
-        let manager = AddressPoolManager::new(test_network(), user_pools).unwrap();
+        let manager = AddressPoolManager::new(test_network(), user_pools, Duration::ZERO).unwrap();
 
-        assert!(manager.allocate_address("alice").is_some());
-        assert!(manager.allocate_address("alice").is_none());
+        assert!(manager.allocate_address(&device("alice")).is_some());
+        assert!(manager.allocate_address(&device("alice")).is_none());
         // Global pool still works for other users
-        assert!(manager.allocate_address("bob").is_some());
+        assert!(manager.allocate_address(&device("bob")).is_some());
     }
 
     #[test]
@@ -304,23 +520,24 @@ mod tests {
             "alice".to_string(),
             vec!["10.0.0.5/32".parse::<AddressRange>().unwrap()],
         )]);
-        let manager = AddressPoolManager::new(test_network(), user_pools).unwrap();
+        let manager = AddressPoolManager::new(test_network(), user_pools, Duration::ZERO).unwrap();
 
-        let addr = manager.allocate_address("alice").unwrap();
-        assert!(manager.allocate_address("alice").is_none());
+        let addr = manager.allocate_address(&device("alice")).unwrap();
+        assert!(manager.allocate_address(&device("alice")).is_none());
 
-        manager.release_address("alice", &addr.addr());
-        assert!(manager.allocate_address("alice").is_some());
+        manager.release_address(&device("alice"), &addr.addr());
+        assert!(manager.allocate_address(&device("alice")).is_some());
     }
 
     #[test]
     fn manager_release_global_and_reallocate() {
-        let manager = AddressPoolManager::new(test_network(), HashMap::new()).unwrap();
+        let manager =
+            AddressPoolManager::new(test_network(), HashMap::new(), Duration::ZERO).unwrap();
 
-        let addr = manager.allocate_address("bob").unwrap();
-        manager.release_address("bob", &addr.addr());
+        let addr = manager.allocate_address(&device("bob")).unwrap();
+        manager.release_address(&device("bob"), &addr.addr());
 
-        let addr2 = manager.allocate_address("bob").unwrap();
+        let addr2 = manager.allocate_address(&device("bob")).unwrap();
         assert_eq!(addr, addr2);
     }
 
@@ -330,20 +547,21 @@ mod tests {
             "alice".to_string(),
             vec!["192.168.1.1/32".parse::<AddressRange>().unwrap()],
         )]);
-        let result = AddressPoolManager::new(test_network(), user_pools);
+        let result = AddressPoolManager::new(test_network(), user_pools, Duration::ZERO);
         assert!(result.is_err());
     }
 
     #[test]
     fn manager_no_user_pools() {
-        let manager = AddressPoolManager::new(test_network(), HashMap::new()).unwrap();
+        let manager =
+            AddressPoolManager::new(test_network(), HashMap::new(), Duration::ZERO).unwrap();
 
         // Should get .2 through .6 (5 usable addresses)
         for expected in 2..=6u8 {
-            let addr = manager.allocate_address("anyone").unwrap();
+            let addr = manager.allocate_address(&device("anyone")).unwrap();
             assert_eq!(addr.addr(), IpAddr::from(Ipv4Addr::new(10, 0, 0, expected)));
         }
-        assert!(manager.allocate_address("anyone").is_none());
+        assert!(manager.allocate_address(&device("anyone")).is_none());
     }
 
     #[test]
@@ -352,7 +570,7 @@ mod tests {
             "alice".to_string(),
             vec!["10.0.0.0/32".parse::<AddressRange>().unwrap()],
         )]);
-        let result = AddressPoolManager::new(test_network(), user_pools);
+        let result = AddressPoolManager::new(test_network(), user_pools, Duration::ZERO);
         assert!(result.is_err());
         let err = result.err().unwrap().to_string();
         assert!(err.contains("reserved tunnel address"), "error: {err}");
@@ -364,7 +582,7 @@ mod tests {
             "alice".to_string(),
             vec!["10.0.0.1/32".parse::<AddressRange>().unwrap()],
         )]);
-        let result = AddressPoolManager::new(test_network(), user_pools);
+        let result = AddressPoolManager::new(test_network(), user_pools, Duration::ZERO);
         assert!(result.is_err());
         let err = result.err().unwrap().to_string();
         assert!(err.contains("reserved tunnel address"), "error: {err}");
@@ -376,7 +594,7 @@ mod tests {
             "alice".to_string(),
             vec!["10.0.0.7/32".parse::<AddressRange>().unwrap()],
         )]);
-        let result = AddressPoolManager::new(test_network(), user_pools);
+        let result = AddressPoolManager::new(test_network(), user_pools, Duration::ZERO);
         assert!(result.is_err());
         let err = result.err().unwrap().to_string();
         assert!(err.contains("reserved tunnel address"), "error: {err}");
@@ -384,8 +602,168 @@ mod tests {
 
     #[test]
     fn manager_netmask_preserved() {
-        let manager = AddressPoolManager::new(test_network(), HashMap::new()).unwrap();
-        let addr = manager.allocate_address("bob").unwrap();
+        let manager =
+            AddressPoolManager::new(test_network(), HashMap::new(), Duration::ZERO).unwrap();
+        let addr = manager.allocate_address(&device("bob")).unwrap();
         assert_eq!(addr.netmask(), test_network().netmask());
     }
+
+    // --- Sticky address tests ---
+
+    #[test]
+    fn manager_sticky_address_per_device() {
+        let manager =
+            AddressPoolManager::new(test_network(), HashMap::new(), Duration::from_secs(60))
+                .unwrap();
+        let laptop = device_of("alice", "laptop");
+        let phone = device_of("alice", "phone");
+
+        let laptop_addr = manager.allocate_address(&laptop).unwrap();
+        let phone_addr = manager.allocate_address(&phone).unwrap();
+        assert_ne!(laptop_addr, phone_addr);
+
+        manager.release_address(&laptop, &laptop_addr.addr());
+        manager.release_address(&phone, &phone_addr.addr());
+
+        // Held addresses are not handed out to anyone else
+        let other_addr = manager.allocate_address(&device("bob")).unwrap();
+        assert_ne!(other_addr, laptop_addr);
+        assert_ne!(other_addr, phone_addr);
+
+        // Each device gets its own address back, regardless of reconnect order
+        assert_eq!(manager.allocate_address(&phone), Some(phone_addr));
+        assert_eq!(manager.allocate_address(&laptop), Some(laptop_addr));
+    }
+
+    #[test]
+    fn manager_sticky_address_expires() {
+        let manager =
+            AddressPoolManager::new(test_network(), HashMap::new(), Duration::from_millis(1))
+                .unwrap();
+        let laptop = device_of("alice", "laptop");
+
+        let addr = manager.allocate_address(&laptop).unwrap();
+        manager.release_address(&laptop, &addr.addr());
+        std::thread::sleep(Duration::from_millis(5));
+
+        // The expired address returns to the pool and is allocated to the next device
+        assert_eq!(manager.allocate_address(&device("bob")), Some(addr));
+    }
+
+    // --- Resumption token tests ---
+
+    fn resuming_manager(ttl: Duration) -> AddressPoolManager {
+        AddressPoolManager::new(test_network(), HashMap::new(), Duration::ZERO)
+            .unwrap()
+            .with_resumption_ttl(ttl)
+    }
+
+    #[test]
+    fn manager_issues_no_tokens_when_disabled() {
+        let manager =
+            AddressPoolManager::new(test_network(), HashMap::new(), Duration::ZERO).unwrap();
+        let laptop = device_of("alice", "laptop");
+
+        let addr = manager.allocate_address(&laptop).unwrap();
+
+        assert_eq!(manager.issue_resumption_token(&laptop, addr.addr()), None);
+        assert!(!manager.has_resumption_lease(&laptop));
+    }
+
+    #[test]
+    fn manager_honors_resumption_token() {
+        let manager = resuming_manager(Duration::from_secs(60));
+        let laptop = device_of("alice", "laptop");
+
+        // Move the first free address to another device, so the laptop's is not the next one
+        let _ = manager.allocate_address(&device("bob")).unwrap();
+        let addr = manager.allocate_address(&laptop).unwrap();
+        let token = manager
+            .issue_resumption_token(&laptop, addr.addr())
+            .unwrap();
+        assert!(manager.has_resumption_lease(&laptop));
+
+        // The address cannot be resumed while it is still assigned
+        assert_eq!(manager.resume_address(&laptop, &token), None);
+
+        manager.release_address(&laptop, &addr.addr());
+
+        assert_eq!(manager.resume_address(&laptop, &token), Some(addr));
+        // The token is used up
+        assert!(!manager.has_resumption_lease(&laptop));
+        assert_eq!(manager.resume_address(&laptop, &token), None);
+    }
+
+    #[test]
+    fn manager_rejects_expired_resumption_token() {
+        let manager = resuming_manager(Duration::from_millis(1));
+        let laptop = device_of("alice", "laptop");
+
+        let addr = manager.allocate_address(&laptop).unwrap();
+        let token = manager
+            .issue_resumption_token(&laptop, addr.addr())
+            .unwrap();
+        manager.release_address(&laptop, &addr.addr());
+        std::thread::sleep(Duration::from_millis(5));
+
+        assert!(!manager.has_resumption_lease(&laptop));
+        assert_eq!(manager.resume_address(&laptop, &token), None);
+    }
+
+    #[test]
+    fn manager_rejects_resumption_token_of_reassigned_address() {
+        let manager = resuming_manager(Duration::from_secs(60));
+        let laptop = device_of("alice", "laptop");
+
+        let addr = manager.allocate_address(&laptop).unwrap();
+        let token = manager
+            .issue_resumption_token(&laptop, addr.addr())
+            .unwrap();
+        manager.release_address(&laptop, &addr.addr());
+
+        // Without a grace period, the released address goes to the next device
+        assert_eq!(manager.allocate_address(&device("bob")), Some(addr));
+
+        assert_eq!(manager.resume_address(&laptop, &token), None);
+        // A fresh address is allocated instead
+        let fresh = manager.allocate_address(&laptop).unwrap();
+        assert_ne!(fresh, addr);
+    }
+
+    #[test]
+    fn manager_rejects_resumption_token_of_other_device() {
+        let manager = resuming_manager(Duration::from_secs(60));
+        let laptop = device_of("alice", "laptop");
+        let phone = device_of("alice", "phone");
+
+        let addr = manager.allocate_address(&laptop).unwrap();
+        let token = manager
+            .issue_resumption_token(&laptop, addr.addr())
+            .unwrap();
+        manager.release_address(&laptop, &addr.addr());
+
+        assert_eq!(manager.resume_address(&phone, &token), None);
+        assert_eq!(manager.resume_address(&laptop, "unknown-token"), None);
+        // The token stays valid for the device it was issued to
+        assert_eq!(manager.resume_address(&laptop, &token), Some(addr));
+    }
+
+    #[test]
+    fn manager_resumes_address_held_in_grace_period() {
+        let manager =
+            AddressPoolManager::new(test_network(), HashMap::new(), Duration::from_secs(60))
+                .unwrap()
+                .with_resumption_ttl(Duration::from_secs(60));
+        let laptop = device_of("alice", "laptop");
+
+        let addr = manager.allocate_address(&laptop).unwrap();
+        let token = manager
+            .issue_resumption_token(&laptop, addr.addr())
+            .unwrap();
+        manager.release_address(&laptop, &addr.addr());
+
+        assert_eq!(manager.resume_address(&laptop, &token), Some(addr));
+        // The held address is not handed out a second time
+        assert_ne!(manager.allocate_address(&laptop), Some(addr));
+    }
 }
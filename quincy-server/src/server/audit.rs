@@ -0,0 +1,354 @@
+//! Audit log of authentication attempts.
+//!
+//! Appends one JSON object per line for every authentication attempt, recording
+//! when it happened, who attempted it from where, and its outcome. Lines are written
+//! by a background thread, so that a slow disk does not hold up accepting connections.
+//! Refusals of locked out source addresses are coalesced, recording at most one line
+//! per address and window.
+
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::net::IpAddr;
+use std::path::Path;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{Receiver, SyncSender, TrySendError, sync_channel};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+use tracing::warn;
+
+use quincy::Result;
+
+/// Number of records waiting to be written before further records are dropped.
+const AUDIT_QUEUE_SIZE: usize = 1024;
+
+/// Window within which repeated refusals of a source address are recorded once.
+const REFUSAL_COALESCE_WINDOW: Duration = Duration::from_secs(60);
+
+/// Outcome of an authentication attempt.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum AuthOutcome {
+    Success,
+    Failure,
+}
+
+/// A single line of the audit log.
+#[derive(Debug, Serialize)]
+struct AuthAuditRecord<'a> {
+    /// Milliseconds since the Unix epoch.
+    timestamp_ms: u64,
+    username: Option<&'a str>,
+    source_ip: IpAddr,
+    result: AuthOutcome,
+    reason: Option<String>,
+    assigned_ip: Option<IpAddr>,
+    /// Refusals of the source address left out since its previous record.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    suppressed: Option<u64>,
+}
+
+/// Refusals of a source address within the current coalescing window.
+struct RefusalWindow {
+    started: Instant,
+    suppressed: u64,
+}
+
+/// Writer of the authentication audit log. Records nothing when no log file is configured.
+///
+/// Records are handed to a background thread through a bounded queue. When the queue is
+/// full, records are dropped and counted rather than blocking the caller. Dropping the
+/// logger writes the queued records before returning.
+pub struct AuthAuditLogger {
+    writer: Option<(SyncSender<Vec<u8>>, JoinHandle<()>)>,
+    dropped: AtomicU64,
+    refusals: Mutex<HashMap<IpAddr, RefusalWindow>>,
+}
+
+impl AuthAuditLogger {
+    /// Opens the audit log, appending to the file if it already exists.
+    ///
+    /// ### Arguments
+    /// - `path` - path to the audit log file, or `None` to disable audit logging
+    ///
+    /// ### Errors
+    /// Returns `QuincyError::Io` if the file cannot be opened for appending or the
+    /// writer thread cannot be started.
+    pub fn new(path: Option<&Path>) -> Result<Self> {
+        let writer = match path {
+            Some(path) => {
+                let file = OpenOptions::new().create(true).append(true).open(path)?;
+                let (sender, receiver) = sync_channel(AUDIT_QUEUE_SIZE);
+                let thread = std::thread::Builder::new()
+                    .name("quincy-audit".to_string())
+                    .spawn(move || write_records(file, receiver))?;
+                Some((sender, thread))
+            }
+            None => None,
+        };
+
+        Ok(Self {
+            writer,
+            dropped: AtomicU64::new(0),
+            refusals: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Records a successful authentication.
+    ///
+    /// ### Arguments
+    /// - `username` - the authenticated user
+    /// - `source_ip` - the source address of the connection
+    /// - `assigned_ip` - the tunnel address assigned to the client
+    pub fn record_success(&self, username: &str, source_ip: IpAddr, assigned_ip: IpAddr) {
+        self.record(AuthAuditRecord {
+            timestamp_ms: now_ms(),
+            username: Some(username),
+            source_ip,
+            result: AuthOutcome::Success,
+            reason: None,
+            assigned_ip: Some(assigned_ip),
+            suppressed: None,
+        });
+    }
+
+    /// Records a failed authentication.
+    ///
+    /// ### Arguments
+    /// - `username` - the user, if identified before the attempt failed
+    /// - `source_ip` - the source address of the connection
+    /// - `reason` - why the attempt failed
+    pub fn record_failure(&self, username: Option<&str>, source_ip: IpAddr, reason: impl Display) {
+        self.record(AuthAuditRecord {
+            timestamp_ms: now_ms(),
+            username,
+            source_ip,
+            result: AuthOutcome::Failure,
+            reason: Some(reason.to_string()),
+            assigned_ip: None,
+            suppressed: None,
+        });
+    }
+
+    /// Records the refusal of a connection from a locked out source address.
+    ///
+    /// Only the first refusal of an address within [`REFUSAL_COALESCE_WINDOW`] is recorded;
+    /// the next record of the address carries the number of refusals left out.
+    ///
+    /// ### Arguments
+    /// - `source_ip` - the source address of the connection
+    pub fn record_refusal(&self, source_ip: IpAddr) {
+        if self.writer.is_none() {
+            return;
+        }
+
+        let suppressed = {
+            let mut refusals = self.refusals.lock().unwrap_or_else(|e| e.into_inner());
+            let now = Instant::now();
+
+            match refusals.get_mut(&source_ip) {
+                Some(window) if now.duration_since(window.started) < REFUSAL_COALESCE_WINDOW => {
+                    window.suppressed += 1;
+                    return;
+                }
+                Some(window) => {
+                    let suppressed = window.suppressed;
+                    *window = RefusalWindow {
+                        started: now,
+                        suppressed: 0,
+                    };
+                    suppressed
+                }
+                None => {
+                    // Forget addresses whose window ended, keeping the map bounded by the
+                    // addresses refused recently
+                    refusals.retain(|_, window| {
+                        now.duration_since(window.started) < REFUSAL_COALESCE_WINDOW
+                    });
+                    refusals.insert(
+                        source_ip,
+                        RefusalWindow {
+                            started: now,
+                            suppressed: 0,
+                        },
+                    );
+                    0
+                }
+            }
+        };
+
+        self.record(AuthAuditRecord {
+            timestamp_ms: now_ms(),
+            username: None,
+            source_ip,
+            result: AuthOutcome::Failure,
+            reason: Some("Source address locked out".to_string()),
+            assigned_ip: None,
+            suppressed: (suppressed > 0).then_some(suppressed),
+        });
+    }
+
+    fn record(&self, record: AuthAuditRecord) {
+        let Some((sender, _)) = &self.writer else {
+            return;
+        };
+
+        let mut line = match serde_json::to_vec(&record) {
+            Ok(line) => line,
+            Err(e) => {
+                warn!("Failed to serialize authentication audit record: {e}");
+                return;
+            }
+        };
+        line.push(b'\n');
+
+        match sender.try_send(line) {
+            Ok(()) => {
+                let dropped = self.dropped.swap(0, Ordering::Relaxed);
+                if dropped > 0 {
+                    warn!(
+                        "Dropped {dropped} authentication audit records while the log was falling behind"
+                    );
+                }
+            }
+            Err(TrySendError::Full(_)) => {
+                self.dropped.fetch_add(1, Ordering::Relaxed);
+            }
+            Err(TrySendError::Disconnected(_)) => {
+                warn!("Authentication audit log writer stopped, dropping record");
+            }
+        }
+    }
+}
+
+impl Drop for AuthAuditLogger {
+    fn drop(&mut self) {
+        if let Some((sender, thread)) = self.writer.take() {
+            // Closing the queue lets the writer finish the queued records and exit
+            drop(sender);
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Writes the queued records to the audit log until the queue is closed.
+///
+/// ### Arguments
+/// - `file` - the audit log file, opened for appending
+/// - `receiver` - the queue of serialized records
+fn write_records(mut file: File, receiver: Receiver<Vec<u8>>) {
+    for line in receiver {
+        if let Err(e) = file.write_all(&line).and_then(|_| file.flush()) {
+            warn!("Failed to write authentication audit record: {e}");
+        }
+    }
+}
+
+/// Returns the current time in milliseconds since the Unix epoch.
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+
+    fn addr(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn writes_one_json_line_per_attempt() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("audit.log");
+        let audit_log = AuthAuditLogger::new(Some(&path)).unwrap();
+
+        audit_log.record_success("alice", addr("192.0.2.1"), addr("10.0.0.2"));
+        audit_log.record_failure(None, addr("192.0.2.2"), "User unknown");
+        drop(audit_log);
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<Value> = content
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["username"], "alice");
+        assert_eq!(lines[0]["source_ip"], "192.0.2.1");
+        assert_eq!(lines[0]["result"], "success");
+        assert_eq!(lines[0]["assigned_ip"], "10.0.0.2");
+        assert!(lines[0]["timestamp_ms"].as_u64().unwrap() > 0);
+
+        assert_eq!(lines[1]["username"], Value::Null);
+        assert_eq!(lines[1]["result"], "failure");
+        assert_eq!(lines[1]["reason"], "User unknown");
+        assert_eq!(lines[1]["assigned_ip"], Value::Null);
+    }
+
+    #[test]
+    fn appends_to_existing_log() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("audit.log");
+
+        AuthAuditLogger::new(Some(&path)).unwrap().record_failure(
+            Some("bob"),
+            addr("192.0.2.1"),
+            "Address pool exhausted",
+        );
+        AuthAuditLogger::new(Some(&path)).unwrap().record_success(
+            "bob",
+            addr("192.0.2.1"),
+            addr("10.0.0.3"),
+        );
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(content.lines().count(), 2);
+    }
+
+    #[test]
+    fn coalesces_refusals_per_source_address() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("audit.log");
+        let audit_log = AuthAuditLogger::new(Some(&path)).unwrap();
+
+        for _ in 0..3 {
+            audit_log.record_refusal(addr("192.0.2.1"));
+        }
+        audit_log.record_refusal(addr("192.0.2.2"));
+
+        // End the window of the first address, as if it had passed
+        if let Some(window) = audit_log
+            .refusals
+            .lock()
+            .unwrap()
+            .get_mut(&addr("192.0.2.1"))
+        {
+            window.started -= REFUSAL_COALESCE_WINDOW;
+        }
+        audit_log.record_refusal(addr("192.0.2.1"));
+        drop(audit_log);
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<Value> = content
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0]["source_ip"], "192.0.2.1");
+        assert_eq!(lines[0]["reason"], "Source address locked out");
+        assert_eq!(lines[0].get("suppressed"), None);
+        assert_eq!(lines[1]["source_ip"], "192.0.2.2");
+        assert_eq!(lines[2]["source_ip"], "192.0.2.1");
+        assert_eq!(lines[2]["suppressed"], 2);
+    }
+}
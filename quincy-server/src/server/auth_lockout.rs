@@ -0,0 +1,359 @@
+//! Lockout of source addresses repeatedly failing authentication.
+//!
+//! Tracks failed handshakes and identifications per source address and refuses
+//! further connection attempts from an address while it is locked out. The tracker
+//! is only consulted on connection setup -- it is NOT in the packet forwarding hot path.
+//!
+//! Only failures from validated source addresses are counted: QUIC Initial packets are
+//! protected with public keys, so anyone can forge a handshake aborting from someone
+//! else's address and would otherwise be able to lock that address out.
+
+use std::net::IpAddr;
+use std::time::{Duration, Instant};
+
+use dashmap::DashMap;
+use quinn::ConnectionError;
+use tracing::{debug, warn};
+
+use quincy::config::AuthLockoutConfig;
+
+/// Failed authentication state of a single source address.
+#[derive(Clone, Copy, Debug)]
+struct FailureRecord {
+    /// Failures counted in the current window.
+    failures: u32,
+    /// Start of the current counting window.
+    window_start: Instant,
+    /// Number of consecutive lockouts, used for the exponential backoff.
+    lockouts: u32,
+    /// End of the active lockout, if any.
+    locked_until: Option<Instant>,
+    /// Time of the most recent failure.
+    last_failure: Instant,
+}
+
+impl FailureRecord {
+    fn new(now: Instant) -> Self {
+        Self {
+            failures: 0,
+            window_start: now,
+            lockouts: 0,
+            locked_until: None,
+            last_failure: now,
+        }
+    }
+
+    fn is_locked_out(&self, now: Instant) -> bool {
+        self.locked_until.is_some_and(|until| now < until)
+    }
+}
+
+/// Thread-safe tracker of failed authentications per source address.
+pub struct AuthLockout {
+    config: AuthLockoutConfig,
+    records: DashMap<IpAddr, FailureRecord>,
+}
+
+impl AuthLockout {
+    /// Creates a new tracker with the given configuration.
+    ///
+    /// ### Arguments
+    /// - `config` - the lockout configuration
+    pub fn new(config: AuthLockoutConfig) -> Self {
+        Self {
+            config,
+            records: DashMap::new(),
+        }
+    }
+
+    /// Returns whether connection attempts from `address` are currently refused.
+    ///
+    /// ### Arguments
+    /// - `address` - the source address of the connection attempt
+    pub fn is_locked_out(&self, address: &IpAddr) -> bool {
+        self.is_locked_out_at(address, Instant::now())
+    }
+
+    /// Records a failed authentication from `address`, locking it out once the
+    /// configured number of failures is reached within the window.
+    ///
+    /// ### Arguments
+    /// - `address` - the source address of the failed attempt
+    pub fn record_failure(&self, address: IpAddr) {
+        self.record_failure_at(address, Instant::now());
+    }
+
+    /// Records a failed connection handshake from `address`.
+    ///
+    /// Rejected credentials surface as transport or peer-closed errors, unlike timeouts
+    /// and resets caused by the network, and are only counted if the source address was
+    /// validated before the handshake failed.
+    ///
+    /// ### Arguments
+    /// - `address` - the source address of the failed handshake
+    /// - `address_validated` - whether the source address was validated, e.g. by a Retry
+    /// - `error` - the error the handshake failed with
+    pub fn record_handshake_failure(
+        &self,
+        address: IpAddr,
+        address_validated: bool,
+        error: &ConnectionError,
+    ) {
+        if !matches!(
+            error,
+            ConnectionError::TransportError(_) | ConnectionError::ConnectionClosed(_)
+        ) {
+            return;
+        }
+
+        if !address_validated {
+            debug!("Not counting failed handshake from unvalidated address {address}");
+            return;
+        }
+
+        self.record_failure(address);
+    }
+
+    /// Forgets all failures of `address` after a successful authentication.
+    ///
+    /// ### Arguments
+    /// - `address` - the source address of the successful attempt
+    pub fn record_success(&self, address: &IpAddr) {
+        self.records.remove(address);
+    }
+
+    fn is_locked_out_at(&self, address: &IpAddr, now: Instant) -> bool {
+        self.config.is_enabled()
+            && self
+                .records
+                .get(address)
+                .is_some_and(|record| record.is_locked_out(now))
+    }
+
+    fn record_failure_at(&self, address: IpAddr, now: Instant) {
+        if !self.config.is_enabled() {
+            return;
+        }
+
+        if !self.records.contains_key(&address)
+            && self.records.len() >= self.config.max_tracked_addresses
+        {
+            self.evict(now);
+        }
+
+        let window = Duration::from_secs(self.config.window_s);
+        let mut record = self
+            .records
+            .entry(address)
+            .or_insert_with(|| FailureRecord::new(now));
+
+        if record.is_locked_out(now) {
+            return;
+        }
+
+        if now.duration_since(record.window_start) >= window {
+            record.failures = 0;
+            record.window_start = now;
+        }
+
+        record.failures += 1;
+        record.last_failure = now;
+
+        if record.failures >= self.config.max_failures {
+            let lockout = self.lockout_duration(record.lockouts);
+            record.locked_until = Some(now + lockout);
+            record.lockouts = record.lockouts.saturating_add(1);
+            record.failures = 0;
+
+            warn!(
+                "Locking out {address} for {}s after {} failed authentication attempts",
+                lockout.as_secs(),
+                self.config.max_failures
+            );
+        }
+    }
+
+    /// Returns the duration of a lockout following `previous_lockouts` consecutive lockouts.
+    fn lockout_duration(&self, previous_lockouts: u32) -> Duration {
+        let lockout_s = self
+            .config
+            .lockout_s
+            .saturating_mul(1u64.checked_shl(previous_lockouts).unwrap_or(u64::MAX));
+
+        Duration::from_secs(lockout_s.min(self.config.max_lockout_s))
+    }
+
+    /// Frees room for a new address by dropping stale records, and if none are stale,
+    /// the least recently failing address that is not locked out.
+    fn evict(&self, now: Instant) {
+        let stale_after = Duration::from_secs(self.config.window_s.max(self.config.max_lockout_s));
+        self.records.retain(|_, record| {
+            record.is_locked_out(now) || now.duration_since(record.last_failure) < stale_after
+        });
+
+        if self.records.len() < self.config.max_tracked_addresses {
+            return;
+        }
+
+        let oldest = self
+            .records
+            .iter()
+            .filter(|entry| !entry.is_locked_out(now))
+            .min_by_key(|entry| entry.last_failure)
+            .map(|entry| *entry.key());
+
+        if let Some(address) = oldest {
+            self.records.remove(&address);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lockout() -> AuthLockout {
+        AuthLockout::new(AuthLockoutConfig {
+            max_failures: 3,
+            window_s: 60,
+            lockout_s: 10,
+            max_lockout_s: 25,
+            max_tracked_addresses: 2,
+        })
+    }
+
+    fn addr(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn locks_out_after_max_failures_and_recovers() {
+        let lockout = lockout();
+        let client = addr("192.0.2.1");
+        let start = Instant::now();
+
+        lockout.record_failure_at(client, start);
+        lockout.record_failure_at(client, start);
+        assert!(!lockout.is_locked_out_at(&client, start));
+
+        lockout.record_failure_at(client, start);
+        assert!(lockout.is_locked_out_at(&client, start));
+        assert!(lockout.is_locked_out_at(&client, start + Duration::from_secs(9)));
+        assert!(!lockout.is_locked_out_at(&client, start + Duration::from_secs(10)));
+    }
+
+    #[test]
+    fn failures_outside_window_are_not_counted() {
+        let lockout = lockout();
+        let client = addr("192.0.2.1");
+        let start = Instant::now();
+
+        lockout.record_failure_at(client, start);
+        lockout.record_failure_at(client, start);
+        lockout.record_failure_at(client, start + Duration::from_secs(61));
+
+        assert!(!lockout.is_locked_out_at(&client, start + Duration::from_secs(61)));
+    }
+
+    #[test]
+    fn consecutive_lockouts_back_off_exponentially() {
+        let lockout = lockout();
+        let client = addr("192.0.2.1");
+        let mut now = Instant::now();
+
+        for expected in [10, 20, 25] {
+            for _ in 0..3 {
+                lockout.record_failure_at(client, now);
+            }
+            assert!(lockout.is_locked_out_at(&client, now + Duration::from_secs(expected - 1)));
+            assert!(!lockout.is_locked_out_at(&client, now + Duration::from_secs(expected)));
+            now += Duration::from_secs(expected);
+        }
+    }
+
+    #[test]
+    fn success_resets_failures() {
+        let lockout = lockout();
+        let client = addr("192.0.2.1");
+        let start = Instant::now();
+
+        lockout.record_failure_at(client, start);
+        lockout.record_failure_at(client, start);
+        lockout.record_success(&client);
+        lockout.record_failure_at(client, start);
+
+        assert!(!lockout.is_locked_out_at(&client, start));
+    }
+
+    #[test]
+    fn tracked_addresses_are_bounded() {
+        let lockout = lockout();
+        let start = Instant::now();
+
+        for _ in 0..3 {
+            lockout.record_failure_at(addr("192.0.2.1"), start);
+        }
+        lockout.record_failure_at(addr("192.0.2.2"), start);
+        lockout.record_failure_at(addr("192.0.2.3"), start + Duration::from_secs(1));
+
+        assert_eq!(lockout.records.len(), 2);
+        // The locked-out address is kept, the least recently failing one is dropped
+        assert!(lockout.is_locked_out_at(&addr("192.0.2.1"), start));
+        assert!(!lockout.records.contains_key(&addr("192.0.2.2")));
+    }
+
+    fn rejected_handshake() -> ConnectionError {
+        ConnectionError::TransportError(quinn::TransportError {
+            code: quinn::TransportErrorCode::crypto(42),
+            frame: None,
+            reason: "bad certificate".to_string(),
+        })
+    }
+
+    #[test]
+    fn handshake_aborts_from_unvalidated_addresses_are_not_counted() {
+        let lockout = lockout();
+        let client = addr("192.0.2.1");
+
+        for _ in 0..10 {
+            lockout.record_handshake_failure(client, false, &rejected_handshake());
+        }
+
+        assert!(!lockout.is_locked_out(&client));
+        assert!(lockout.records.is_empty());
+    }
+
+    #[test]
+    fn rejected_handshakes_from_validated_addresses_are_counted() {
+        let lockout = lockout();
+        let client = addr("192.0.2.1");
+
+        // Network failures are not counted
+        lockout.record_handshake_failure(client, true, &ConnectionError::TimedOut);
+        lockout.record_handshake_failure(client, true, &ConnectionError::Reset);
+        assert!(lockout.records.is_empty());
+
+        for _ in 0..3 {
+            lockout.record_handshake_failure(client, true, &rejected_handshake());
+        }
+
+        assert!(lockout.is_locked_out(&client));
+    }
+
+    #[test]
+    fn disabled_lockout_never_locks_out() {
+        let lockout = AuthLockout::new(AuthLockoutConfig {
+            max_failures: 0,
+            ..AuthLockoutConfig::default()
+        });
+        let client = addr("192.0.2.1");
+        let start = Instant::now();
+
+        for _ in 0..100 {
+            lockout.record_failure_at(client, start);
+        }
+
+        assert!(!lockout.is_locked_out_at(&client, start));
+        assert!(lockout.records.is_empty());
+    }
+}
@@ -1,41 +1,53 @@
 use std::net::IpAddr;
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, SystemTime};
 
 use bytes::Bytes;
 use futures::StreamExt;
 use futures::stream::FuturesUnordered;
 use governor::Jitter;
 use ipnet::IpNet;
-use quinn::Connection;
+use quinn::{Connection, SendStream, VarInt};
 use tokio::sync::mpsc::{Receiver, Sender};
+use tokio::sync::oneshot;
 use tracing::{debug, info};
 
 use crate::identity;
-use crate::server::address_pool::AddressPoolManager;
+use crate::policy::UserPolicy;
+use crate::server::address_pool::{AddressPoolManager, DeviceKey};
 use crate::server::session::BandwidthLimiter;
-use crate::users::UsersFile;
-use quincy::config::ServerProtocolConfig;
-use quincy::ip_assignment::{self, IpAssignment};
+use crate::users::{AccountExpiry, UsersFile};
+use quincy::config::ServerConfig;
+use quincy::constants::AUTH_FAILED_ERROR_CODE;
+use quincy::error::AuthError;
+use quincy::ip_assignment::{self, IpAssignment, PushedNetworkConfig};
 use quincy::network::packet::Packet;
+use quincy::network::transport::{PacketReceiver, PacketSender, decode_ping};
 use quincy::utils::tasks::abort_all;
 use quincy::{QuincyError, Result};
 
 /// Default timeout for IP assignment exchange.
 const IP_ASSIGNMENT_TIMEOUT: Duration = Duration::from_secs(10);
+/// How long to wait for a reconnecting client to present its resumption token.
+const RESUMPTION_REQUEST_TIMEOUT: Duration = Duration::from_secs(1);
 
 /// Initial state: connection established but client not yet identified.
 pub struct New;
 
 /// Client identified via handshake peer identity.
 pub struct Identified {
-    pub username: String,
+    pub device: DeviceKey,
+    pub policy: UserPolicy,
+    pub valid_until: Option<AccountExpiry>,
 }
 
 /// Client identified and tunnel IP assigned.
 pub struct Assigned {
-    pub username: String,
+    pub device: DeviceKey,
+    pub policy: UserPolicy,
     pub client_address: IpNet,
+    pub secondary_client_address: Option<IpNet>,
+    pub valid_until: Option<AccountExpiry>,
 }
 
 /// Represents a Quincy connection whose lifecycle phase is tracked at the type level.
@@ -67,78 +79,171 @@ impl QuincyConnection<New> {
     /// Identifies the client from the handshake.
     ///
     /// Uses the peer identity from the completed QUIC handshake (Noise public key
-    /// or TLS client certificate) to look up the username.
+    /// or TLS client certificate) to look up the username, rejects users whose
+    /// account has expired and resolves the user's authorization policy.
     ///
     /// ### Arguments
-    /// - `protocol` - the server protocol configuration
+    /// - `config` - the server configuration
     /// - `users` - the parsed users file
+    ///
+    /// ### Errors
+    /// Returns `AuthError::UserUnknown` if the peer is not in the users file,
+    /// `AuthError::AccountExpired` if the user's `valid_until` has passed, or
+    /// `AuthError::PermissionDenied` if the user's groups grant no access.
     pub fn identify(
         self,
-        protocol: &ServerProtocolConfig,
+        config: &ServerConfig,
         users: &UsersFile,
     ) -> Result<QuincyConnection<Identified>> {
-        let username = identity::identify_peer(&self.connection, protocol, users)?;
+        let identity = identity::identify_peer(&self.connection, &config.protocol, users)?;
+        let user_entry = users
+            .users
+            .get(&identity.username)
+            .ok_or(AuthError::UserUnknown)?;
+        if user_entry.is_expired_at(SystemTime::now()) {
+            return Err(AuthError::AccountExpired {
+                username: identity.username,
+            }
+            .into());
+        }
+
+        let policy = UserPolicy::resolve(&identity.username, user_entry, config)?;
+        let valid_until = user_entry.valid_until;
+        let device = DeviceKey {
+            username: identity.username,
+            device_id: identity.device_id,
+        };
 
         Ok(QuincyConnection {
             connection: self.connection,
             ingress_queue: self.ingress_queue,
-            state: Identified { username },
+            state: Identified {
+                device,
+                policy,
+                valid_until,
+            },
         })
     }
 }
 
 impl QuincyConnection<Identified> {
     /// Returns the username resolved during identification.
-    #[allow(dead_code)]
     pub fn username(&self) -> &str {
-        &self.state.username
+        &self.state.device.username
     }
 
     /// Assigns an IP address and sends the assignment to the client.
     ///
-    /// Allocates an IP from the address pool manager (using the user's
-    /// reserved pool if configured, otherwise the global pool) and sends
-    /// the assignment to the client over a uni-stream. On send failure,
-    /// the address is released back to the appropriate pool.
+    /// Allocates an IP from the address pool manager (the address of the
+    /// resumption token presented by a reconnecting device if it can be resumed,
+    /// else the device's sticky address if it reconnects within the grace period,
+    /// else the user's reserved pool if configured, otherwise the global pool) and sends
+    /// the assignment to the client over a uni-stream. With a dual-stack tunnel,
+    /// an address of the other IP family is allocated from the secondary pool as
+    /// well. The assignment advertises the server's tunnel MTU, to which the client
+    /// clamps its own, and carries the network settings pushed to clients. On failure,
+    /// the addresses are released back to the appropriate pools.
     ///
     /// ### Arguments
     /// - `address_pool` - the address pool manager
     /// - `server_address` - the server's tunnel address
+    /// - `secondary_address_pool` - the address pool manager of the secondary tunnel network, if any
+    /// - `tunnel_mtu` - the MTU of the server's tunnel interface
+    /// - `pushed` - the network settings pushed to clients
     pub async fn assign_ip(
         self,
         address_pool: &AddressPoolManager,
         server_address: IpNet,
+        secondary_address_pool: Option<&AddressPoolManager>,
+        tunnel_mtu: u16,
+        pushed: PushedNetworkConfig,
     ) -> Result<QuincyConnection<Assigned>> {
-        let client_address = address_pool
-            .allocate_address(&self.state.username)
-            .ok_or(quincy::error::AuthError::AddressPoolExhausted)?;
+        // Only devices that were issued a token may present one, sparing other clients the wait
+        let resumption_token = if address_pool.has_resumption_lease(&self.state.device) {
+            ip_assignment::recv_resumption_token(&self.connection, RESUMPTION_REQUEST_TIMEOUT).await
+        } else {
+            None
+        };
+        let resumed_address = resumption_token
+            .and_then(|token| address_pool.resume_address(&self.state.device, &token));
+        let resumed = resumed_address.is_some();
+
+        let client_address = match resumed_address {
+            Some(address) => {
+                debug!(
+                    "Resumed address {} for user {}",
+                    address.addr(),
+                    self.state.device.username
+                );
+                address
+            }
+            None => address_pool
+                .allocate_address(&self.state.device)
+                .ok_or(AuthError::AddressPoolExhausted)?,
+        };
+
+        let secondary_client_address = match secondary_address_pool {
+            Some(secondary_pool) => match secondary_pool.allocate_address(&self.state.device) {
+                Some(address) => Some(address),
+                None => {
+                    address_pool.release_address(&self.state.device, &client_address.addr());
+                    return Err(AuthError::AddressPoolExhausted.into());
+                }
+            },
+            None => None,
+        };
 
         let assignment = IpAssignment {
             client_address,
             server_address,
+            secondary_client_address,
+            secondary_server_address: secondary_address_pool.map(|pool| pool.network()),
+            tunnel_mtu: Some(tunnel_mtu),
+            account_expires_at: self.state.valid_until.map(|expiry| expiry.as_unix_secs()),
+            pushed,
+            resumption_token: address_pool
+                .issue_resumption_token(&self.state.device, client_address.addr()),
+            resumed,
         };
 
         if let Err(e) =
             ip_assignment::send_ip_assignment(&self.connection, &assignment, IP_ASSIGNMENT_TIMEOUT)
                 .await
         {
-            address_pool.release_address(&self.state.username, &client_address.addr());
+            address_pool.release_address(&self.state.device, &client_address.addr());
+            if let (Some(secondary_pool), Some(secondary_address)) =
+                (secondary_address_pool, secondary_client_address)
+            {
+                secondary_pool.release_address(&self.state.device, &secondary_address.addr());
+            }
             return Err(e);
         }
 
-        info!(
-            "Connection established: user = {}, client address = {}, remote address = {}",
-            self.state.username,
-            client_address.addr(),
-            self.connection.remote_address().ip(),
-        );
+        match secondary_client_address {
+            Some(secondary_address) => info!(
+                "Connection established: user = {}, client addresses = {}, {}, remote address = {}",
+                self.state.device.username,
+                client_address.addr(),
+                secondary_address.addr(),
+                self.connection.remote_address().ip(),
+            ),
+            None => info!(
+                "Connection established: user = {}, client address = {}, remote address = {}",
+                self.state.device.username,
+                client_address.addr(),
+                self.connection.remote_address().ip(),
+            ),
+        }
 
         Ok(QuincyConnection {
             connection: self.connection,
             ingress_queue: self.ingress_queue,
             state: Assigned {
-                username: self.state.username,
+                device: self.state.device,
+                policy: self.state.policy,
                 client_address,
+                secondary_client_address,
+                valid_until: self.state.valid_until,
             },
         })
     }
@@ -147,7 +252,12 @@ impl QuincyConnection<Identified> {
 impl QuincyConnection<Assigned> {
     /// Returns the username resolved during identification.
     pub fn username(&self) -> &str {
-        &self.state.username
+        &self.state.device.username
+    }
+
+    /// Returns the device resolved during identification.
+    pub fn device(&self) -> &DeviceKey {
+        &self.state.device
     }
 
     /// Returns the client's assigned tunnel address.
@@ -155,20 +265,39 @@ impl QuincyConnection<Assigned> {
         self.state.client_address
     }
 
+    /// Returns the client's assigned tunnel address of the secondary IP family, if any.
+    pub fn secondary_client_address(&self) -> Option<IpNet> {
+        self.state.secondary_client_address
+    }
+
+    /// Returns the authorization policy resolved during identification.
+    pub fn policy(&self) -> &UserPolicy {
+        &self.state.policy
+    }
+
     /// Starts the IO and metrics tasks for this connection.
     ///
+    /// Packets are relayed as datagrams until the client opens a packet stream,
+    /// after which they are relayed over the stream in both directions.
+    ///
     /// ### Arguments
     /// - `egress_queue` - channel carrying packets destined for this client
     /// - `rate_limiter` - optional shared bandwidth limiter for the user
+    /// - `allowed_destinations` - destination networks the client may reach (empty = all)
     /// - `metrics_interval` - how often to report per-connection metrics
     pub async fn run(
         self,
         egress_queue: Receiver<Bytes>,
         rate_limiter: Option<Arc<BandwidthLimiter>>,
+        allowed_destinations: Vec<IpNet>,
         #[cfg(feature = "metrics")] metrics_interval: Duration,
     ) -> (Self, QuincyError) {
-        let client_address = self.state.client_address.addr();
+        let client_addresses: Vec<IpAddr> = std::iter::once(self.state.client_address)
+            .chain(self.state.secondary_client_address)
+            .map(|address| address.addr())
+            .collect();
 
+        let (send_stream_tx, send_stream_rx) = oneshot::channel();
         let mut tasks = FuturesUnordered::new();
 
         tasks.extend([
@@ -176,20 +305,39 @@ impl QuincyConnection<Assigned> {
                 self.connection.clone(),
                 egress_queue,
                 rate_limiter.clone(),
+                send_stream_rx,
             )),
             tokio::spawn(Self::process_incoming_data(
                 self.connection.clone(),
+                PacketReceiver::Datagram(self.connection.clone()),
                 self.ingress_queue.clone(),
-                client_address,
+                client_addresses.clone(),
+                allowed_destinations.clone(),
+                rate_limiter.clone(),
+            )),
+            tokio::spawn(Self::accept_packet_stream(
+                self.connection.clone(),
+                self.ingress_queue.clone(),
+                client_addresses,
+                allowed_destinations,
                 rate_limiter,
+                send_stream_tx,
             )),
         ]);
 
+        if let Some(expiry) = self.state.valid_until {
+            tasks.push(tokio::spawn(Self::close_on_expiry(
+                self.connection.clone(),
+                self.state.device.username.clone(),
+                expiry,
+            )));
+        }
+
         #[cfg(feature = "metrics")]
         tasks.push(tokio::spawn(Self::report_metrics(
             self.connection.clone(),
             metrics_interval,
-            self.state.username.clone(),
+            self.state.device.username.clone(),
             self.state.client_address.addr(),
         )));
 
@@ -210,17 +358,44 @@ impl QuincyConnection<Assigned> {
         }
     }
 
+    /// Closes the connection once the user's account expires.
+    ///
+    /// The client is told that authentication failed, so that it does not reconnect.
+    ///
+    /// ### Arguments
+    /// - `connection` - the QUIC connection to close
+    /// - `username` - the user the connection belongs to
+    /// - `expiry` - when the user's account expires
+    async fn close_on_expiry(
+        connection: Connection,
+        username: String,
+        expiry: AccountExpiry,
+    ) -> Result<()> {
+        tokio::time::sleep(expiry.remaining_at(SystemTime::now())).await;
+
+        connection.close(
+            VarInt::from_u32(AUTH_FAILED_ERROR_CODE),
+            "Account expired".as_bytes(),
+        );
+
+        Err(AuthError::AccountExpired { username }.into())
+    }
+
     /// Processes outgoing data and sends it to the QUIC connection.
     ///
     /// ### Arguments
     /// - `connection` - the QUIC connection to send datagrams on
     /// - `egress_queue` - the queue to receive data from the TUN interface
     /// - `rate_limiter` - optional shared bandwidth limiter for the user
+    /// - `send_stream_rx` - receives the packet stream once the client opens one
     async fn process_outgoing_data(
         connection: Connection,
         mut egress_queue: Receiver<Bytes>,
         rate_limiter: Option<Arc<BandwidthLimiter>>,
+        mut send_stream_rx: oneshot::Receiver<SendStream>,
     ) -> Result<()> {
+        let mut sender = PacketSender::Datagram(connection);
+
         loop {
             let data = egress_queue
                 .recv()
@@ -238,29 +413,79 @@ impl QuincyConnection<Assigned> {
                     .await;
             }
 
-            connection.send_datagram(data)?;
+            if let Ok(send_stream) = send_stream_rx.try_recv() {
+                debug!("Relaying packets to the client over a QUIC stream");
+                sender = PacketSender::Stream(send_stream);
+            }
+
+            sender.send(data).await?;
         }
     }
 
+    /// Waits for the client to open a packet stream and relays the packets received on it.
+    ///
+    /// Clients relaying over datagrams never open a stream, in which case this task
+    /// runs until the connection is closed.
+    ///
+    /// ### Arguments
+    /// - `connection` - the QUIC connection to accept the stream on
+    /// - `ingress_queue` - the queue to send validated packets to the TUN interface
+    /// - `client_addresses` - the client's assigned tunnel IP addresses
+    /// - `allowed_destinations` - destination networks the client may reach (empty = all)
+    /// - `rate_limiter` - optional shared bandwidth limiter for the user
+    /// - `send_stream_tx` - hands the sending half of the stream to the outgoing task
+    async fn accept_packet_stream(
+        connection: Connection,
+        ingress_queue: Sender<Packet>,
+        client_addresses: Vec<IpAddr>,
+        allowed_destinations: Vec<IpNet>,
+        rate_limiter: Option<Arc<BandwidthLimiter>>,
+        send_stream_tx: oneshot::Sender<SendStream>,
+    ) -> Result<()> {
+        let (send_stream, recv_stream) = connection.accept_bi().await?;
+        let _ = send_stream_tx.send(send_stream);
+
+        Self::process_incoming_data(
+            connection,
+            PacketReceiver::Stream(recv_stream),
+            ingress_queue,
+            client_addresses,
+            allowed_destinations,
+            rate_limiter,
+        )
+        .await
+    }
+
     /// Processes incoming data and sends it to the TUN interface queue.
     ///
-    /// Validates that the source IP of each incoming datagram matches the client's
-    /// assigned tunnel address, dropping packets with mismatched or unparseable
-    /// source IPs to prevent IP spoofing between authenticated clients.
+    /// Validates that the source IP of each incoming packet matches the client's
+    /// assigned tunnel addresses, dropping packets with mismatched or unparseable
+    /// source IPs to prevent IP spoofing between authenticated clients. Packets
+    /// to destinations outside `allowed_destinations` are dropped as well.
+    /// Application keep-alive pings are echoed back to the client.
     ///
     /// ### Arguments
-    /// - `connection` - the QUIC connection to read datagrams from
+    /// - `connection` - the QUIC connection to echo pings on
+    /// - `receiver` - receives packets from the client
     /// - `ingress_queue` - the queue to send validated packets to the TUN interface
-    /// - `client_address` - the client's assigned tunnel IP address
+    /// - `client_addresses` - the client's assigned tunnel IP addresses
+    /// - `allowed_destinations` - destination networks the client may reach (empty = all)
     /// - `rate_limiter` - optional shared bandwidth limiter for the user
     async fn process_incoming_data(
         connection: Connection,
+        mut receiver: PacketReceiver,
         ingress_queue: Sender<Packet>,
-        client_address: IpAddr,
+        client_addresses: Vec<IpAddr>,
+        allowed_destinations: Vec<IpNet>,
         rate_limiter: Option<Arc<BandwidthLimiter>>,
     ) -> Result<()> {
         loop {
-            let packet: Packet = connection.read_datagram().await?.into();
+            let packet = receiver.recv().await?;
+            if decode_ping(&packet).is_some() {
+                connection.send_datagram(packet.into())?;
+                continue;
+            }
+
             let source_address = match packet.source() {
                 Ok(source) => source,
                 Err(err) => {
@@ -269,13 +494,17 @@ impl QuincyConnection<Assigned> {
                 }
             };
 
-            if source_address != client_address {
+            if !client_addresses.contains(&source_address) {
                 debug!(
-                    "Dropping packet: source IP {source_address} does not match assigned address {client_address}"
+                    "Dropping packet: source IP {source_address} does not match assigned addresses {client_addresses:?}"
                 );
                 continue;
             }
 
+            if !is_destination_allowed(&packet, &allowed_destinations) {
+                continue;
+            }
+
             if let Some(ref limiter) = rate_limiter {
                 let tokens = (packet.len() as u32 / 1024)
                     .max(1)
@@ -332,3 +561,69 @@ impl QuincyConnection<Assigned> {
         }
     }
 }
+
+/// Returns whether a client may send `packet` to its destination.
+///
+/// ### Arguments
+/// - `packet` - the packet received from the client
+/// - `allowed_destinations` - destination networks the client may reach (empty = all)
+fn is_destination_allowed(packet: &Packet, allowed_destinations: &[IpNet]) -> bool {
+    if allowed_destinations.is_empty() {
+        return true;
+    }
+
+    match packet.destination() {
+        Ok(destination)
+            if allowed_destinations
+                .iter()
+                .any(|net| net.contains(&destination)) =>
+        {
+            true
+        }
+        Ok(destination) => {
+            debug!("Dropping packet: destination {destination} is not allowed for this client");
+            false
+        }
+        Err(err) => {
+            debug!("Dropping packet: unable to parse destination IP from header due to {err}");
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::Ipv4Addr;
+
+    /// Builds a minimal IPv4 header from `source` to `destination`.
+    fn ipv4_packet(source: Ipv4Addr, destination: Ipv4Addr) -> Packet {
+        let mut header = [0u8; 20];
+        header[0] = 0x45;
+        header[3] = 20;
+        header[8] = 64;
+        header[12..16].copy_from_slice(&source.octets());
+        header[16..20].copy_from_slice(&destination.octets());
+
+        Packet::new(Bytes::copy_from_slice(&header))
+    }
+
+    #[test]
+    fn packets_to_disallowed_destinations_are_dropped() {
+        let allowed = vec!["10.1.0.0/16".parse::<IpNet>().unwrap()];
+        let client = Ipv4Addr::new(10, 0, 0, 2);
+
+        let permitted = ipv4_packet(client, Ipv4Addr::new(10, 1, 2, 3));
+        let denied = ipv4_packet(client, Ipv4Addr::new(192, 168, 1, 1));
+
+        assert!(is_destination_allowed(&permitted, &allowed));
+        assert!(!is_destination_allowed(&denied, &allowed));
+    }
+
+    #[test]
+    fn empty_allowed_destinations_allow_everything() {
+        let packet = ipv4_packet(Ipv4Addr::new(10, 0, 0, 2), Ipv4Addr::new(192, 168, 1, 1));
+
+        assert!(is_destination_allowed(&packet, &[]));
+    }
+}
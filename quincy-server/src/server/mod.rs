@@ -1,4 +1,6 @@
 pub mod address_pool;
+pub mod audit;
+pub mod auth_lockout;
 mod connection;
 pub mod session;
 
@@ -8,33 +10,43 @@ mod metrics;
 use std::collections::HashMap;
 use std::net::{IpAddr, SocketAddr};
 use std::sync::Arc;
-#[cfg(feature = "metrics")]
-use std::time::Duration;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
 use bytes::Bytes;
 use dashmap::DashMap;
 use futures::StreamExt;
 use futures::stream::FuturesUnordered;
+use ipnet::{IpAddrRange, IpNet};
 use quinn::{Endpoint, VarInt};
 use tokio::signal;
 use tokio::sync::mpsc::error::TrySendError;
 use tokio::sync::mpsc::{Receiver, Sender, channel};
+use tokio::time::MissedTickBehavior;
 use tracing::{debug, info, warn};
 
 use crate::server::address_pool::AddressPoolManager;
+use crate::server::audit::AuthAuditLogger;
+use crate::server::auth_lockout::AuthLockout;
 use crate::server::connection::{Assigned, QuincyConnection};
 use crate::server::session::{ConnectionSession, UserSessionRegistry};
-use crate::users::UsersFile;
-use quincy::Result;
+use crate::users::{UsersFile, UsersFileWatcher};
 use quincy::config::{
     AddressRange, AllowedNoiseKeys, NoiseKeyExchange, ServerConfig, ServerProtocolConfig,
 };
-use quincy::constants::{PACKET_BUFFER_SIZE, PACKET_CHANNEL_SIZE, QUINN_RUNTIME};
-use quincy::network::interface::{ActiveInterface, Interface, InterfaceIO};
+use quincy::constants::{
+    AUTH_FAILED_ERROR_CODE, PACKET_BUFFER_SIZE, PACKET_CHANNEL_SIZE, QUINN_RUNTIME,
+};
+use quincy::error::AuthError;
+use quincy::network::dns::DnsOptions;
+use quincy::network::interface::{ActiveInterface, Interface, InterfaceAddress, InterfaceIO};
 use quincy::network::packet::Packet;
-use quincy::network::socket::bind_socket;
+use quincy::network::route::RouteOptions;
+use quincy::network::socket::{SocketOptions, bind_socket, check_udp_offload};
 use quincy::utils::tasks::abort_all;
+use quincy::{QuincyError, Result};
+
+/// How often the users file is checked for changes.
+const USERS_FILE_POLL_INTERVAL: Duration = Duration::from_secs(5);
 
 /// Map of connection addresses to their TX channel.
 type ConnectionQueues = Arc<DashMap<IpAddr, Sender<Bytes>>>;
@@ -43,6 +55,7 @@ type ConnectionQueues = Arc<DashMap<IpAddr, Sender<Bytes>>>;
 struct AssignmentResult {
     result: Result<QuincyConnection<Assigned>>,
     quic_connection: quinn::Connection,
+    username: String,
 }
 
 /// Represents a Quincy server encapsulating Quincy connections and TUN interface IO.
@@ -50,49 +63,89 @@ pub struct QuincyServer {
     config: ServerConfig,
     connection_queues: ConnectionQueues,
     address_pool: Arc<AddressPoolManager>,
+    secondary_address_pool: Option<Arc<AddressPoolManager>>,
     users: Arc<UsersFile>,
     session_registry: Arc<UserSessionRegistry>,
+    auth_lockout: AuthLockout,
+    audit_log: AuthAuditLogger,
 }
 
 impl QuincyServer {
     /// Creates a new instance of the Quincy tunnel.
     ///
-    /// Loads the users file and initializes the address pool from the tunnel network.
+    /// Loads the users file and initializes the address pools from the tunnel networks.
     ///
     /// ### Arguments
     /// - `config` - the server configuration
     pub fn new(config: ServerConfig) -> Result<Self> {
         let users = UsersFile::load(&config.users_file)?;
+        users.validate_groups(&config.groups)?;
+
+        let grace_period = Duration::from_secs(config.address_grace_period_s);
+        let resumption_ttl = Duration::from_secs(config.resumption_token_ttl_s);
+        let pools = user_pools(&users);
+        let (address_pool, secondary_address_pool) = match config.secondary_tunnel_network {
+            // Per-user pools are split by IP family between the two networks
+            Some(secondary_network) => (
+                AddressPoolManager::new(
+                    config.tunnel_network,
+                    family_pools(&pools, config.tunnel_network),
+                    grace_period,
+                )?
+                .with_resumption_ttl(resumption_ttl),
+                Some(Arc::new(AddressPoolManager::new(
+                    secondary_network,
+                    family_pools(&pools, secondary_network),
+                    grace_period,
+                )?)),
+            ),
+            None => (
+                AddressPoolManager::new(config.tunnel_network, pools, grace_period)?
+                    .with_resumption_ttl(resumption_ttl),
+                None,
+            ),
+        };
 
-        let user_pools: HashMap<String, Vec<AddressRange>> = users
-            .users
-            .iter()
-            .filter(|(_, entry)| !entry.address_pool.is_empty())
-            .map(|(name, entry)| (name.clone(), entry.address_pool.clone()))
-            .collect();
-
-        let address_pool = AddressPoolManager::new(config.tunnel_network, user_pools)?;
+        let auth_lockout = AuthLockout::new(config.auth_lockout.clone());
+        let audit_log = AuthAuditLogger::new(config.auth_audit_log.as_deref())?;
 
         Ok(Self {
             config,
             connection_queues: Arc::new(DashMap::new()),
             address_pool: Arc::new(address_pool),
+            secondary_address_pool,
             users: Arc::new(users),
             session_registry: Arc::new(UserSessionRegistry::new()),
+            auth_lockout,
+            audit_log,
         })
     }
 
     /// Starts the tasks for this instance of Quincy tunnel and listens for incoming connections.
     pub async fn run<I: InterfaceIO>(&self) -> Result<()> {
+        let addresses: Vec<InterfaceAddress> = std::iter::once(self.config.tunnel_network)
+            .chain(self.config.secondary_tunnel_network)
+            .map(|network| InterfaceAddress {
+                address: network,
+                gateway: Some(network.network()),
+            })
+            .collect();
+
         let interface: Interface<I> = Interface::create(
-            self.config.tunnel_network,
+            &addresses,
             self.config.connection.mtu,
-            Some(self.config.tunnel_network.network()),
             self.config.interface_name.clone(),
+            self.config.connection.offload,
             None,
+            RouteOptions::default(),
             None,
+            DnsOptions::default(),
             None,
         )?;
+        interface.capture_packets(
+            self.config.log.capture_file.as_deref(),
+            self.config.log.capture_max_bytes,
+        )?;
         let interface = Arc::new(interface.configure()?);
 
         #[cfg(feature = "metrics")]
@@ -143,15 +196,19 @@ impl QuincyServer {
             endpoint.local_addr().expect("Endpoint has a local address")
         );
 
-        let protocol = Arc::new(self.config.protocol.clone());
         let server_address = self.config.tunnel_network;
-        let users = self.users.clone();
+        let mut users = self.users.clone();
         let address_pool = self.address_pool.clone();
+        let secondary_address_pool = self.secondary_address_pool.clone();
         let session_registry = self.session_registry.clone();
 
         let mut assignment_tasks = FuturesUnordered::new();
         let mut connection_tasks = FuturesUnordered::new();
 
+        let mut users_watcher = UsersFileWatcher::new(&self.config.users_file);
+        let mut users_poll = tokio::time::interval(USERS_FILE_POLL_INTERVAL);
+        users_poll.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
         let shutdown = shutdown_signal();
         tokio::pin!(shutdown);
 
@@ -160,15 +217,28 @@ impl QuincyServer {
                 // New connections
                 Some(handshake) = endpoint.accept() => {
                     let client_ip = handshake.remote_address().ip();
+                    let address_validated = handshake.remote_address_validated();
 
                     debug!(
                         "Received incoming connection from '{}'",
                         client_ip
                     );
 
+                    if self.auth_lockout.is_locked_out(&client_ip) {
+                        debug!("Refusing connection from locked out client '{client_ip}'");
+                        self.audit_log.record_refusal(client_ip);
+                        handshake.refuse();
+                        continue;
+                    }
+
+                    // Waiting for the handshake to complete also holds back any 0-RTT early
+                    // data, which is never delivered for replayed handshakes
                     let quic_connection = match handshake.await {
                         Ok(connection) => connection,
                         Err(e) => {
+                            self.auth_lockout
+                                .record_handshake_failure(client_ip, address_validated, &e);
+                            self.audit_log.record_failure(None, client_ip, &e);
                             warn!("Connection handshake with client '{client_ip}' failed: {e}");
                             continue;
                         }
@@ -180,24 +250,46 @@ impl QuincyServer {
                         ingress_queue.clone(),
                     );
 
-                    // Identify synchronously (reads peer_identity + HashMap lookup)
-                    let connection = match connection.identify(&protocol, &users) {
-                        Ok(conn) => conn,
+                    // Identify synchronously (reads peer_identity + HashMap lookup). The
+                    // completed handshake has validated the source address.
+                    let connection = match connection.identify(&self.config, &users) {
+                        Ok(conn) => {
+                            self.auth_lockout.record_success(&client_ip);
+                            conn
+                        }
                         Err(e) => {
+                            self.auth_lockout.record_failure(client_ip);
+                            self.audit_log.record_failure(identified_username(&e), client_ip, &e);
                             warn!("Failed to identify client: {e}");
-                            quic_connection_clone.close(VarInt::from_u32(0x02), "Session establishment failed".as_bytes());
+                            quic_connection_clone.close(
+                                VarInt::from_u32(AUTH_FAILED_ERROR_CODE),
+                                "Authentication failed".as_bytes(),
+                            );
                             continue;
                         }
                     };
 
                     let address_pool = address_pool.clone();
+                    let secondary_address_pool = secondary_address_pool.clone();
                     let server_addr = server_address;
+                    let tunnel_mtu = self.config.connection.mtu;
+                    let pushed = self.config.push.clone();
+                    let username = connection.username().to_string();
 
                     assignment_tasks.push(async move {
-                        let result = connection.assign_ip(&address_pool, server_addr).await;
+                        let result = connection
+                            .assign_ip(
+                                &address_pool,
+                                server_addr,
+                                secondary_address_pool.as_deref(),
+                                tunnel_mtu,
+                                pushed,
+                            )
+                            .await;
                         AssignmentResult {
                             result,
                             quic_connection: quic_connection_clone,
+                            username,
                         }
                     });
                 }
@@ -207,6 +299,11 @@ impl QuincyServer {
                     let connection = match assignment.result {
                         Ok(connection) => connection,
                         Err(e) => {
+                            self.audit_log.record_failure(
+                                Some(&assignment.username),
+                                assignment.quic_connection.remote_address().ip(),
+                                &e,
+                            );
                             warn!("Failed to assign IP to client: {e}");
                             assignment.quic_connection.close(
                                 VarInt::from_u32(0x02),
@@ -217,16 +314,16 @@ impl QuincyServer {
                     };
 
                     let client_address = connection.client_address();
+                    let secondary_client_address = connection.secondary_client_address();
                     let username = connection.username().to_string();
+                    self.audit_log.record_success(
+                        &username,
+                        assignment.quic_connection.remote_address().ip(),
+                        client_address.addr(),
+                    );
 
-                    // Resolve effective bandwidth limit:
-                    // per-user override > server default > None (unlimited)
-                    let bandwidth_limit = self
-                        .users
-                        .users
-                        .get(&username)
-                        .and_then(|entry| entry.bandwidth_limit)
-                        .or(self.config.default_bandwidth_limit);
+                    let bandwidth_limit = connection.policy().bandwidth_limit;
+                    let allowed_destinations = connection.policy().allowed_destinations.clone();
 
                     // Register session and obtain the shared rate limiter
                     let rate_limiter = session_registry.add_connection(
@@ -243,9 +340,14 @@ impl QuincyServer {
                     connection_tasks.push(tokio::spawn(connection.run(
                         connection_receiver,
                         rate_limiter,
+                        allowed_destinations,
                         #[cfg(feature = "metrics")]
                         Duration::from_secs(self.config.metrics.reporting_interval_s),
                     )));
+                    if let Some(secondary_address) = secondary_client_address {
+                        self.connection_queues
+                            .insert(secondary_address.addr(), connection_sender.clone());
+                    }
                     self.connection_queues
                         .insert(client_address.addr(), connection_sender);
                 }
@@ -257,7 +359,13 @@ impl QuincyServer {
                     let client_address = connection.client_address();
 
                     self.connection_queues.remove(&client_address.addr());
-                    self.address_pool.release_address(username, &client_address.addr());
+                    self.address_pool.release_address(connection.device(), &client_address.addr());
+                    if let (Some(secondary_address), Some(secondary_pool)) =
+                        (connection.secondary_client_address(), &self.secondary_address_pool)
+                    {
+                        self.connection_queues.remove(&secondary_address.addr());
+                        secondary_pool.release_address(connection.device(), &secondary_address.addr());
+                    }
                     session_registry.remove_connection(username, &client_address);
 
                     warn!(
@@ -266,6 +374,32 @@ impl QuincyServer {
                     );
                 }
 
+                // Users file changes
+                _ = users_poll.tick() => {
+                    let Some(reloaded) = users_watcher.poll() else {
+                        continue;
+                    };
+
+                    // New handshakes are checked against the reloaded keys and fingerprints,
+                    // established connections are left untouched.
+                    let server_config = reloaded
+                        .validate_groups(&self.config.groups)
+                        .and_then(|_| self.quinn_server_config(&reloaded));
+                    match server_config {
+                        Ok(server_config) => {
+                            endpoint.set_server_config(Some(server_config));
+
+                            if user_pools(&reloaded) != user_pools(&users) {
+                                warn!("Changes to per-user address pools take effect after a restart");
+                            }
+
+                            info!("Reloaded users file with {} users", reloaded.users.len());
+                            users = Arc::new(reloaded);
+                        }
+                        Err(e) => warn!("Failed to apply reloaded users file: {e}"),
+                    }
+                }
+
                 // Shutdown
                 shutdown_result = &mut shutdown => {
                     shutdown_result?;
@@ -281,34 +415,45 @@ impl QuincyServer {
         }
     }
 
-    /// Creates a Quinn QUIC endpoint that clients can connect to.
-    fn create_quinn_endpoint(&self) -> Result<Endpoint> {
-        // Build allowed keys/fingerprints from the users file
+    /// Creates the Quinn server configuration accepting the credentials in `users`.
+    ///
+    /// ### Arguments
+    /// - `users` - the users file providing the allowed keys or certificate fingerprints
+    fn quinn_server_config(&self, users: &UsersFile) -> Result<quinn::ServerConfig> {
         let (allowed_keys, allowed_fingerprints) = match &self.config.protocol {
             ServerProtocolConfig::Noise(noise) => {
                 let keys = match noise.key_exchange {
                     NoiseKeyExchange::Standard => Some(AllowedNoiseKeys::Standard(
-                        self.users.collect_noise_public_keys(),
+                        users.collect_noise_public_keys(),
                     )),
                     NoiseKeyExchange::Hybrid => Some(AllowedNoiseKeys::Hybrid(
-                        self.users.collect_noise_pq_public_keys(),
+                        users.collect_noise_pq_public_keys(),
                     )),
                 };
                 (keys, None)
             }
-            ServerProtocolConfig::Tls(_) => (None, Some(self.users.collect_cert_fingerprints())),
+            ServerProtocolConfig::Tls(_) => (None, Some(users.collect_cert_fingerprints())),
         };
 
-        let quinn_config = self
-            .config
-            .as_quinn_server_config(allowed_keys, allowed_fingerprints)?;
+        self.config
+            .as_quinn_server_config(allowed_keys, allowed_fingerprints)
+    }
+
+    /// Creates a Quinn QUIC endpoint that clients can connect to.
+    fn create_quinn_endpoint(&self) -> Result<Endpoint> {
+        let quinn_config = self.quinn_server_config(&self.users)?;
 
         let socket = bind_socket(
             SocketAddr::new(self.config.bind_address, self.config.bind_port),
             self.config.connection.send_buffer_size as usize,
             self.config.connection.recv_buffer_size as usize,
             self.config.reuse_socket,
+            &SocketOptions {
+                dscp: self.config.connection.dscp,
+                ..SocketOptions::default()
+            },
         )?;
+        check_udp_offload(&socket, self.config.connection.gso_segment_size)?;
 
         let endpoint_config = self
             .config
@@ -389,6 +534,52 @@ impl QuincyServer {
     }
 }
 
+/// Returns the per-user address ranges within the IP family of `network`.
+///
+/// ### Arguments
+/// - `pools` - per-user address ranges, keyed by username
+/// - `network` - the tunnel network whose family to keep
+fn family_pools(
+    pools: &HashMap<String, Vec<AddressRange>>,
+    network: IpNet,
+) -> HashMap<String, Vec<AddressRange>> {
+    pools
+        .iter()
+        .filter_map(|(name, ranges)| {
+            let ranges: Vec<AddressRange> = ranges
+                .iter()
+                .filter(|range| {
+                    matches!(range.into_inner(), IpAddrRange::V4(_)) == network.addr().is_ipv4()
+                })
+                .copied()
+                .collect();
+
+            (!ranges.is_empty()).then(|| (name.clone(), ranges))
+        })
+        .collect()
+}
+
+/// Collects the per-user address pools of the users that have one.
+fn user_pools(users: &UsersFile) -> HashMap<String, Vec<AddressRange>> {
+    users
+        .users
+        .iter()
+        .filter(|(_, entry)| !entry.address_pool.is_empty())
+        .map(|(name, entry)| (name.clone(), entry.address_pool.clone()))
+        .collect()
+}
+
+/// Returns the user named by an identification error, if the user was identified before
+/// being rejected.
+fn identified_username(error: &QuincyError) -> Option<&str> {
+    match error {
+        QuincyError::Auth(
+            AuthError::AccountExpired { username } | AuthError::PermissionDenied { username },
+        ) => Some(username),
+        _ => None,
+    }
+}
+
 #[cfg(unix)]
 async fn shutdown_signal() -> Result<()> {
     let mut interrupt = signal::unix::signal(signal::unix::SignalKind::interrupt())?;
@@ -5,19 +5,24 @@
 //! lookup indices for O(1) key and fingerprint resolution.
 
 use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::hash::{DefaultHasher, Hash, Hasher};
 use std::net::IpAddr;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use figment::{
     Figment,
     providers::{Format, Toml},
 };
+use ipnet::IpNet;
 use reishi_quinn::{PqPublicKey, PublicKey};
 use serde::Deserialize;
 use tracing::warn;
 
-use quincy::config::{AddressRange, Bandwidth, decode_base64_key};
-use quincy::error::{AuthError, Result};
+use quincy::config::{AddressRange, Bandwidth, GroupConfig, decode_base64_key};
+use quincy::error::{AuthError, ConfigError, Result};
 
 /// A parsed users file mapping usernames to their authentication credentials.
 ///
@@ -73,8 +78,221 @@ pub struct UserEntry {
     ///
     /// Keep ranges small (a `/24` or narrower is typical) — overlap validation
     /// iterates every address eagerly at startup.
+    ///
+    /// Changes to the pools are not applied when the users file is reloaded,
+    /// they take effect after a server restart.
     #[serde(default)]
     pub address_pool: Vec<AddressRange>,
+    /// Destination networks this user may send traffic to through the tunnel.
+    /// Packets to other destinations are dropped. When empty, all destinations
+    /// are allowed.
+    #[serde(default)]
+    pub allowed_destinations: Vec<IpNet>,
+    /// Optional account expiry. Once it has passed, the user's connections are
+    /// rejected even though their handshake succeeds, and established connections
+    /// are closed.
+    /// Format: `"YYYY-MM-DD"` (expires at the end of that day, UTC) or
+    /// `"YYYY-MM-DDTHH:MM:SSZ"`.
+    #[serde(default)]
+    pub valid_until: Option<AccountExpiry>,
+    /// Authorization groups this user belongs to, defined in the server configuration.
+    #[serde(default)]
+    pub groups: Vec<String>,
+}
+
+impl UserEntry {
+    /// Returns whether the user's account has expired at the given time.
+    ///
+    /// ### Arguments
+    /// - `now` - the time to check against
+    pub fn is_expired_at(&self, now: SystemTime) -> bool {
+        self.valid_until
+            .is_some_and(|expiry| expiry.is_expired_at(now))
+    }
+}
+
+/// Point in time at which a user account expires, stored as seconds since the Unix epoch.
+///
+/// Parsed from either a date (`"2025-12-31"`), which expires at the end of that day (UTC),
+/// or a UTC timestamp (`"2025-12-31T18:00:00Z"`).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct AccountExpiry(u64);
+
+impl AccountExpiry {
+    /// Creates an expiry at the given time in seconds since the Unix epoch.
+    ///
+    /// ### Arguments
+    /// - `secs` - the expiry time in seconds since the Unix epoch
+    pub fn from_unix_secs(secs: u64) -> Self {
+        Self(secs)
+    }
+
+    /// Returns the expiry time in seconds since the Unix epoch.
+    pub fn as_unix_secs(&self) -> u64 {
+        self.0
+    }
+
+    /// Returns how long the account remains valid from the given time, zero once expired.
+    ///
+    /// ### Arguments
+    /// - `now` - the time to measure from
+    pub fn remaining_at(&self, now: SystemTime) -> Duration {
+        let elapsed = now.duration_since(UNIX_EPOCH).unwrap_or_default();
+
+        Duration::from_secs(self.0).saturating_sub(elapsed)
+    }
+
+    /// Returns whether the expiry time has been reached at the given time.
+    ///
+    /// ### Arguments
+    /// - `now` - the time to check against
+    pub fn is_expired_at(&self, now: SystemTime) -> bool {
+        now.duration_since(UNIX_EPOCH)
+            .is_ok_and(|elapsed| elapsed.as_secs() >= self.0)
+    }
+}
+
+impl fmt::Display for AccountExpiry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let days = (self.0 / SECONDS_PER_DAY) as i64;
+        let seconds = self.0 % SECONDS_PER_DAY;
+        let (year, month, day) = civil_from_days(days);
+
+        write!(
+            f,
+            "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}Z",
+            seconds / 3600,
+            seconds / 60 % 60,
+            seconds % 60
+        )
+    }
+}
+
+impl FromStr for AccountExpiry {
+    type Err = ConfigError;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let invalid = || ConfigError::InvalidValue {
+            field: "valid_until".to_string(),
+            reason: format!("invalid expiry '{s}', expected YYYY-MM-DD or YYYY-MM-DDTHH:MM:SSZ"),
+        };
+
+        let trimmed = s.trim();
+        let (date, time) = match trimmed.split_once('T') {
+            Some((date, time)) => (date, Some(time.strip_suffix('Z').ok_or_else(invalid)?)),
+            None => (trimmed, None),
+        };
+
+        let date_parts = parse_fields(date, '-').ok_or_else(invalid)?;
+        let [year, month, day] = date_parts[..] else {
+            return Err(invalid());
+        };
+        if year < 1970 || !(1..=12).contains(&month) || day < 1 || day > days_in_month(year, month)
+        {
+            return Err(invalid());
+        }
+        let days = days_from_civil(year as i64, month, day) as u64;
+
+        let seconds = match time {
+            Some(time) => {
+                let time_parts = parse_fields(time, ':').ok_or_else(invalid)?;
+                let [hour, minute, second] = time_parts[..] else {
+                    return Err(invalid());
+                };
+                if hour > 23 || minute > 59 || second > 59 {
+                    return Err(invalid());
+                }
+                days * SECONDS_PER_DAY + (hour * 3600 + minute * 60 + second) as u64
+            }
+            // A plain date is valid through the end of that day
+            None => (days + 1) * SECONDS_PER_DAY,
+        };
+
+        Ok(AccountExpiry(seconds))
+    }
+}
+
+impl<'de> Deserialize<'de> for AccountExpiry {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        use serde::de::{self, Visitor};
+
+        struct AccountExpiryVisitor;
+
+        impl<'de> Visitor<'de> for AccountExpiryVisitor {
+            type Value = AccountExpiry;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a date string like \"2025-12-31\" or \"2025-12-31T18:00:00Z\"")
+            }
+
+            fn visit_str<E>(self, v: &str) -> std::result::Result<AccountExpiry, E>
+            where
+                E: de::Error,
+            {
+                v.parse::<AccountExpiry>().map_err(de::Error::custom)
+            }
+        }
+
+        deserializer.deserialize_str(AccountExpiryVisitor)
+    }
+}
+
+const SECONDS_PER_DAY: u64 = 86_400;
+
+/// Splits `s` on `separator` into unsigned decimal fields.
+fn parse_fields(s: &str, separator: char) -> Option<Vec<u32>> {
+    s.split(separator)
+        .map(|field| {
+            if field.is_empty() || !field.chars().all(|c| c.is_ascii_digit()) {
+                return None;
+            }
+            field.parse().ok()
+        })
+        .collect()
+}
+
+fn days_in_month(year: u32, month: u32) -> u32 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Returns the number of days since the Unix epoch of the given proleptic Gregorian date.
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month_from_march = (month as i64 + 9) % 12;
+    let day_of_year = (153 * month_from_march + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// Returns the proleptic Gregorian date of the given number of days since the Unix epoch.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_from_march = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month_from_march + 2) / 5 + 1) as u32;
+    let month = if month_from_march < 10 {
+        month_from_march + 3
+    } else {
+        month_from_march - 9
+    } as u32;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    (year, month, day)
 }
 
 impl UsersFile {
@@ -290,6 +508,26 @@ impl UsersFile {
             .map(|s| s.as_str())
     }
 
+    /// Validates that every group referenced by a user is defined.
+    ///
+    /// ### Arguments
+    /// - `groups` - the groups defined in the server configuration
+    ///
+    /// ### Errors
+    /// Returns `AuthError::InvalidUserStore` if a user references an unknown group.
+    pub fn validate_groups(&self, groups: &HashMap<String, GroupConfig>) -> Result<()> {
+        for (username, entry) in &self.users {
+            if let Some(group) = entry.groups.iter().find(|g| !groups.contains_key(*g)) {
+                return Err(AuthError::InvalidUserStore {
+                    reason: format!("user '{username}': unknown group '{group}'"),
+                }
+                .into());
+            }
+        }
+
+        Ok(())
+    }
+
     /// Collects all authorized X25519 public keys from all users.
     ///
     /// Keys that fail to decode (wrong length, invalid base64) are silently skipped.
@@ -317,9 +555,84 @@ impl UsersFile {
     }
 }
 
+/// Watches a users file for changes by polling its modification time, size and content.
+///
+/// Comparing the content as well catches writes within the file system's timestamp
+/// resolution and files atomically replaced by one with an older modification time.
+pub struct UsersFileWatcher {
+    path: PathBuf,
+    version: Option<FileVersion>,
+}
+
+impl UsersFileWatcher {
+    /// Creates a watcher treating the current state of the file as already loaded.
+    ///
+    /// ### Arguments
+    /// - `path` - path to the TOML users file
+    pub fn new(path: &Path) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            version: FileVersion::read(path),
+        }
+    }
+
+    /// Reloads the users file if it was changed since the last check.
+    ///
+    /// A file that fails to load is logged and skipped until it is changed again,
+    /// so the previously loaded users stay in effect.
+    ///
+    /// ### Returns
+    /// The reloaded users file, or `None` if the file is unchanged or invalid.
+    pub fn poll(&mut self) -> Option<UsersFile> {
+        let version = FileVersion::read(&self.path);
+        if version == self.version {
+            return None;
+        }
+        self.version = version;
+
+        match UsersFile::load(&self.path) {
+            Ok(users) => Some(users),
+            Err(e) => {
+                warn!(
+                    "Failed to reload users file '{}', keeping the previous users: {e}",
+                    self.path.display()
+                );
+                None
+            }
+        }
+    }
+}
+
+/// A version of a watched file, identified by its modification time, size and content hash.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct FileVersion {
+    modified: Option<SystemTime>,
+    len: u64,
+    hash: u64,
+}
+
+impl FileVersion {
+    /// Reads the version of the file at `path`, if it is readable.
+    fn read(path: &Path) -> Option<Self> {
+        let content = std::fs::read(path).ok()?;
+        let modified = std::fs::metadata(path)
+            .and_then(|metadata| metadata.modified())
+            .ok();
+        let mut hasher = DefaultHasher::new();
+        content.hash(&mut hasher);
+
+        Some(Self {
+            modified,
+            len: content.len() as u64,
+            hash: hasher.finish(),
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::fs::File;
 
     const SAMPLE_USERS_TOML: &str = r#"
         [users.alice]
@@ -648,6 +961,133 @@ mod tests {
         assert!(alice.address_pool.is_empty());
     }
 
+    #[test]
+    fn parse_user_entry_with_allowed_destinations() {
+        let toml = r#"
+            [users.alice]
+            authorized_keys = ["AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA="]
+            allowed_destinations = ["10.1.0.0/16", "fd00::/64"]
+
+            [users.bob]
+            authorized_keys = ["AQAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA="]
+        "#;
+        let users = UsersFile::parse(toml).expect("valid TOML");
+
+        assert_eq!(
+            users.users["alice"].allowed_destinations,
+            vec![
+                "10.1.0.0/16".parse::<IpNet>().unwrap(),
+                "fd00::/64".parse::<IpNet>().unwrap()
+            ]
+        );
+        assert!(users.users["bob"].allowed_destinations.is_empty());
+    }
+
+    #[test]
+    fn parse_user_entry_with_valid_until() {
+        let toml = r#"
+            [users.alice]
+            authorized_keys = ["AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA="]
+            valid_until = "2025-12-31"
+
+            [users.bob]
+            authorized_keys = ["AQAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA="]
+            valid_until = "2025-06-30T18:30:00Z"
+
+            [users.carol]
+            authorized_keys = ["AgAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA="]
+        "#;
+        let users = UsersFile::parse(toml).expect("valid TOML");
+
+        // 2026-01-01T00:00:00Z
+        assert_eq!(
+            users.users["alice"].valid_until.map(|e| e.as_unix_secs()),
+            Some(1_767_225_600)
+        );
+        assert_eq!(
+            users.users["bob"].valid_until.map(|e| e.as_unix_secs()),
+            Some(1_751_308_200)
+        );
+        assert!(users.users["carol"].valid_until.is_none());
+    }
+
+    #[test]
+    fn unknown_groups_are_rejected() {
+        let toml = r#"
+            [users.alice]
+            groups = ["engineering", "ops"]
+        "#;
+        let users = UsersFile::parse(toml).expect("valid TOML");
+        let mut groups = HashMap::from([("engineering".to_string(), GroupConfig::default())]);
+
+        assert!(matches!(
+            users.validate_groups(&groups),
+            Err(quincy::QuincyError::Auth(
+                AuthError::InvalidUserStore { .. }
+            ))
+        ));
+
+        groups.insert("ops".to_string(), GroupConfig::default());
+        assert!(users.validate_groups(&groups).is_ok());
+    }
+
+    #[test]
+    fn account_expiry_rejects_invalid_dates() {
+        for invalid in [
+            "",
+            "2025-13-01",
+            "2025-02-29",
+            "2025-12-31T24:00:00Z",
+            "2025-12-31T18:00:00",
+            "1969-12-31",
+            "31/12/2025",
+            "+2025-12-31",
+        ] {
+            assert!(
+                invalid.parse::<AccountExpiry>().is_err(),
+                "'{invalid}' should be rejected"
+            );
+        }
+        assert!("2024-02-29".parse::<AccountExpiry>().is_ok());
+    }
+
+    #[test]
+    fn account_expiry_display_round_trips() {
+        let expiry: AccountExpiry = "2024-02-29T07:08:09Z".parse().unwrap();
+        assert_eq!(expiry.to_string(), "2024-02-29T07:08:09Z");
+        assert_eq!(expiry.to_string().parse::<AccountExpiry>().unwrap(), expiry);
+    }
+
+    #[test]
+    fn user_entry_expires_at_end_of_day() {
+        let toml = r#"
+            [users.alice]
+            valid_until = "2025-12-31"
+        "#;
+        let users = UsersFile::parse(toml).expect("valid TOML");
+        let alice = &users.users["alice"];
+        let end_of_day = UNIX_EPOCH + Duration::from_secs(1_767_225_600);
+
+        assert!(!alice.is_expired_at(end_of_day - Duration::from_secs(1)));
+        assert!(alice.is_expired_at(end_of_day));
+    }
+
+    #[test]
+    fn account_expiry_remaining_time() {
+        let expiry = AccountExpiry::from_unix_secs(1_767_225_600);
+        let expires_at = UNIX_EPOCH + Duration::from_secs(1_767_225_600);
+
+        assert_eq!(
+            expiry.remaining_at(expires_at - Duration::from_millis(1500)),
+            Duration::from_millis(1500)
+        );
+        assert_eq!(expiry.remaining_at(expires_at), Duration::ZERO);
+        assert_eq!(
+            expiry.remaining_at(expires_at + Duration::from_secs(60)),
+            Duration::ZERO
+        );
+    }
+
     #[test]
     fn overlapping_address_pools_between_users_rejected() {
         let toml = r#"
@@ -677,4 +1117,76 @@ mod tests {
         let err = result.unwrap_err().to_string();
         assert!(err.contains("duplicate address"), "error: {err}");
     }
+
+    /// Rewrites the file and moves its modification time forward, so the change is
+    /// visible regardless of the file system's timestamp resolution.
+    fn rewrite(path: &Path, content: &str) {
+        std::fs::write(path, content).unwrap();
+        let modified = std::fs::metadata(path).unwrap().modified().unwrap();
+        File::options()
+            .write(true)
+            .open(path)
+            .unwrap()
+            .set_modified(modified + Duration::from_secs(1))
+            .unwrap();
+    }
+
+    #[test]
+    fn watcher_reloads_modified_users_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("users.toml");
+        std::fs::write(&path, "[users.alice]").unwrap();
+
+        let mut watcher = UsersFileWatcher::new(&path);
+        assert!(watcher.poll().is_none());
+
+        rewrite(&path, SAMPLE_USERS_TOML);
+
+        let users = watcher.poll().expect("modified users file is reloaded");
+        let bob_key = PublicKey::from_bytes({
+            let mut bytes = [0u8; 32];
+            bytes[0] = 1;
+            bytes
+        });
+        assert_eq!(users.find_user_by_noise_pubkey(&bob_key), Some("bob"));
+        assert!(watcher.poll().is_none());
+    }
+
+    #[test]
+    fn watcher_reloads_users_file_changed_within_same_mtime() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("users.toml");
+        std::fs::write(&path, SAMPLE_USERS_TOML).unwrap();
+        let modified = std::fs::metadata(&path).unwrap().modified().unwrap();
+
+        let mut watcher = UsersFileWatcher::new(&path);
+        std::fs::write(&path, SAMPLE_USERS_TOML.replace("10 mbps", "20 mbps")).unwrap();
+        File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(modified)
+            .unwrap();
+
+        let users = watcher
+            .poll()
+            .expect("users file changed within the same mtime is reloaded");
+        assert_eq!(
+            users.users["alice"].bandwidth_limit,
+            Some(Bandwidth::from_bytes_per_second(2_500_000))
+        );
+        assert!(watcher.poll().is_none());
+    }
+
+    #[test]
+    fn watcher_skips_invalid_users_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("users.toml");
+        std::fs::write(&path, SAMPLE_USERS_TOML).unwrap();
+
+        let mut watcher = UsersFileWatcher::new(&path);
+        rewrite(&path, "[users.alice\n");
+
+        assert!(watcher.poll().is_none());
+    }
 }
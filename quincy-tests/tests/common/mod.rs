@@ -1,7 +1,11 @@
 use bytes::{BufMut, Bytes, BytesMut};
 use etherparse::PacketBuilder;
-use ipnet::IpNet;
-use quincy::network::{interface::InterfaceIO, packet::Packet, route::InstalledExclusionRoute};
+use quincy::network::{
+    dns::DnsOptions,
+    interface::{InterfaceAddress, InterfaceIO},
+    packet::Packet,
+    route::{InstalledExclusionRoute, RouteSpec},
+};
 use std::any::TypeId;
 use std::cell::RefCell;
 use std::collections::HashMap;
@@ -21,6 +25,7 @@ pub type TestReceiver = Arc<Mutex<UnboundedReceiver<Bytes>>>;
 thread_local! {
     static CHANNEL_REGISTRY: RefCell<HashMap<TypeId, (TestSender, TestReceiver)>> =
         RefCell::new(HashMap::new());
+    static ROUTE_MUTATIONS: RefCell<HashMap<TypeId, usize>> = RefCell::new(HashMap::new());
 }
 
 /// Test-side channel endpoints for a component.
@@ -79,6 +84,24 @@ pub fn setup_interface<T: 'static>() -> TestChannels {
     }
 }
 
+/// Returns the number of routes added or removed through test interfaces of type `T`.
+#[allow(unused)]
+pub fn route_mutations<T: 'static>() -> usize {
+    ROUTE_MUTATIONS.with(|mutations| {
+        mutations
+            .borrow()
+            .get(&TypeId::of::<T>())
+            .copied()
+            .unwrap_or_default()
+    })
+}
+
+fn record_route_mutations<T: 'static>(routes: &[RouteSpec]) {
+    ROUTE_MUTATIONS.with(|mutations| {
+        *mutations.borrow_mut().entry(TypeId::of::<T>()).or_default() += routes.len();
+    });
+}
+
 /// Builds a dummy ICMP echo-request packet with the given source and destination.
 #[allow(unused)]
 pub fn dummy_packet(src: Ipv4Addr, dest: Ipv4Addr) -> Bytes {
@@ -94,10 +117,10 @@ pub fn dummy_packet(src: Ipv4Addr, dest: Ipv4Addr) -> Bytes {
 impl<T: 'static + Send + Sync> InterfaceIO for TestInterface<T> {
     /// Creates a new interface by retrieving channels from the thread-local registry.
     fn create_interface(
-        _interface_address: IpNet,
+        _addresses: &[InterfaceAddress],
         _mtu: u16,
-        _tunnel_gateway: Option<IpAddr>,
         _interface_name: Option<&str>,
+        _offload: bool,
     ) -> quincy::Result<Self> {
         let (tx, rx) = CHANNEL_REGISTRY.with(|registry| {
             registry
@@ -109,21 +132,33 @@ impl<T: 'static + Send + Sync> InterfaceIO for TestInterface<T> {
     }
 
     /// No-op for test interfaces.
+    fn set_addresses(&self, _addresses: &[InterfaceAddress]) -> quincy::Result<()> {
+        Ok(())
+    }
+
+    /// Only counts the added routes for test interfaces.
     fn configure_routes(
         &self,
-        _routes: &[IpNet],
+        routes: &[RouteSpec],
         _remote_address: Option<IpAddr>,
     ) -> quincy::Result<Option<InstalledExclusionRoute>> {
+        record_route_mutations::<T>(routes);
         Ok(None)
     }
 
+    /// Only counts the removed routes for test interfaces.
+    fn remove_routes(&self, routes: &[RouteSpec]) -> quincy::Result<()> {
+        record_route_mutations::<T>(routes);
+        Ok(())
+    }
+
     /// No-op for test interfaces.
-    fn configure_dns(&self, _dns_servers: &[IpAddr]) -> quincy::Result<()> {
+    fn configure_dns(&self, _dns_servers: &[IpAddr], _options: &DnsOptions) -> quincy::Result<()> {
         Ok(())
     }
 
     /// No-op for test interfaces.
-    fn cleanup_dns(&self, _dns_servers: &[IpAddr]) -> quincy::Result<()> {
+    fn cleanup_dns(&self, _dns_servers: &[IpAddr], _options: &DnsOptions) -> quincy::Result<()> {
         Ok(())
     }
 
@@ -0,0 +1,66 @@
+mod common;
+
+use common::{TestInterface, setup_interface};
+use quincy::config::{ClientConfig, FromPath, ServerConfig};
+use quincy::constants::AUTH_FAILED_ERROR_CODE;
+use quincy_client::client::QuincyClient;
+use quincy_server::server::QuincyServer;
+use quincy_server::users::AccountExpiry;
+use quinn::{ConnectionError, VarInt};
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::time::timeout;
+
+#[tokio::test]
+async fn test_established_connection_closed_on_account_expiry() {
+    struct Client;
+    struct Server;
+
+    let _client_ch = setup_interface::<Client>();
+    let _server_ch = setup_interface::<Server>();
+
+    let config_dir = Path::new("tests/static/configs/tls_standard");
+    let client_config =
+        ClientConfig::from_path(&config_dir.join("client.toml"), "QUINCY_").unwrap();
+    let mut server_config =
+        ServerConfig::from_path(&config_dir.join("server.toml"), "QUINCY_").unwrap();
+
+    // The account of the test user expires shortly after the client connects
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+    let expiry = AccountExpiry::from_unix_secs(now.as_secs() + 3);
+    let users = std::fs::read_to_string(&server_config.users_file).unwrap();
+    let users_file = std::env::temp_dir().join("quincy_test_users_account_expiry.toml");
+    std::fs::write(
+        &users_file,
+        format!("{}\nvalid_until = \"{expiry}\"\n", users.trim_end()),
+    )
+    .unwrap();
+    server_config.users_file = users_file.clone();
+
+    let mut client = QuincyClient::new(client_config);
+    let server = QuincyServer::new(server_config).unwrap();
+
+    tokio::spawn(async move { server.run::<TestInterface<Server>>().await.unwrap() });
+    client.start::<TestInterface<Client>>().await.unwrap();
+    assert_eq!(
+        client.account_expires_at(),
+        Some(UNIX_EPOCH + Duration::from_secs(expiry.as_unix_secs()))
+    );
+
+    let connection = client.relayer().unwrap().connection().clone();
+    let _ = timeout(
+        Duration::from_secs(15),
+        client.run::<TestInterface<Client>>(),
+    )
+    .await
+    .expect("connection outlived the account");
+
+    match connection.close_reason() {
+        Some(ConnectionError::ApplicationClosed(close)) => {
+            assert_eq!(close.error_code, VarInt::from_u32(AUTH_FAILED_ERROR_CODE));
+        }
+        other => panic!("expected the server to close the connection, got {other:?}"),
+    }
+
+    std::fs::remove_file(users_file).unwrap();
+}
@@ -0,0 +1,102 @@
+mod common;
+
+use common::{TestInterface, setup_interface};
+use quincy::QuincyError;
+use quincy::certificates::{load_certificates_from_file, load_private_key_from_file};
+use quincy::config::{ClientConfig, FromPath};
+use quincy::constants::{QUINN_RUNTIME, TLS_INITIAL_CIPHER_SUITE, TLS_PROTOCOL_VERSIONS};
+use quincy::error::QuicError;
+use quincy::network::socket::{SocketOptions, bind_socket};
+use quincy_client::client::QuincyClient;
+use quinn::crypto::rustls::QuicServerConfig;
+use quinn::{Endpoint, EndpointConfig};
+use std::net::SocketAddr;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::timeout;
+
+/// Port used by the non-Quincy QUIC server in this test.
+const FOREIGN_SERVER_PORT: u16 = 55160;
+
+/// Starts a plain QUIC server that only speaks HTTP/3 (`h3`) ALPN.
+fn foreign_quic_server() -> Endpoint {
+    let certs = load_certificates_from_file(Path::new("tests/static/server_cert_pkcs8.pem"))
+        .expect("server certificate is valid");
+    let key = load_private_key_from_file(Path::new("tests/static/server_key_pkcs8.pem"))
+        .expect("server key is valid");
+
+    let provider = Arc::new(rustls::crypto::aws_lc_rs::default_provider());
+    let mut rustls_config = rustls::ServerConfig::builder_with_provider(provider)
+        .with_protocol_versions(TLS_PROTOCOL_VERSIONS)
+        .unwrap()
+        .with_no_client_auth()
+        .with_single_cert(certs, key)
+        .unwrap();
+    rustls_config.alpn_protocols = vec![b"h3".to_vec()];
+
+    let quic_config = QuicServerConfig::with_initial(
+        rustls_config.into(),
+        TLS_INITIAL_CIPHER_SUITE
+            .tls13()
+            .unwrap()
+            .quic_suite()
+            .unwrap(),
+    )
+    .unwrap();
+    let server_config = quinn::ServerConfig::with_crypto(Arc::new(quic_config));
+
+    let socket = bind_socket(
+        SocketAddr::new("::".parse().unwrap(), FOREIGN_SERVER_PORT),
+        2097152,
+        2097152,
+        false,
+        &SocketOptions::default(),
+    )
+    .unwrap();
+
+    Endpoint::new(
+        EndpointConfig::default(),
+        Some(server_config),
+        socket,
+        QUINN_RUNTIME.clone(),
+    )
+    .unwrap()
+}
+
+#[tokio::test]
+async fn test_client_rejects_non_quincy_server() {
+    struct Client;
+
+    let _client_ch = setup_interface::<Client>();
+
+    let mut client_config = ClientConfig::from_path(
+        Path::new("tests/static/configs/tls_standard/client.toml"),
+        "QUINCY_",
+    )
+    .unwrap();
+    client_config.connection_string = format!("localhost:{FOREIGN_SERVER_PORT}");
+
+    let endpoint = foreign_quic_server();
+    tokio::spawn(async move {
+        while let Some(incoming) = endpoint.accept().await {
+            let _ = incoming.await;
+        }
+    });
+
+    let mut client = QuincyClient::new(client_config);
+
+    let result = timeout(
+        Duration::from_secs(5),
+        client.start::<TestInterface<Client>>(),
+    )
+    .await
+    .expect("client should fail fast against a non-Quincy server");
+
+    match result {
+        Err(QuincyError::Quic(QuicError::ConnectionFailed { reason })) => {
+            assert_eq!(reason, "not a Quincy server");
+        }
+        other => panic!("expected 'not a Quincy server' error, got {other:?}"),
+    }
+}
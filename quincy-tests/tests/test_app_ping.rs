@@ -0,0 +1,49 @@
+mod common;
+
+use common::{TestInterface, setup_interface};
+use quincy::config::{ClientConfig, FromPath, ServerConfig};
+use quincy_client::client::{ClientState, QuincyClient};
+use quincy_server::server::QuincyServer;
+use std::path::Path;
+use std::time::Duration;
+
+#[tokio::test]
+async fn test_app_ping_is_echoed() {
+    struct Client;
+    struct Server;
+
+    let _client_ch = setup_interface::<Client>();
+    let _server_ch = setup_interface::<Server>();
+
+    let config_dir = Path::new("tests/static/configs/tls_standard");
+    let mut client_config =
+        ClientConfig::from_path(&config_dir.join("client.toml"), "QUINCY_").unwrap();
+    client_config.connection.app_ping_interval_s = Some(1);
+    client_config.connection.app_ping_timeout_s = 2;
+    let server_config =
+        ServerConfig::from_path(&config_dir.join("server.toml"), "QUINCY_").unwrap();
+
+    let mut client = QuincyClient::new(client_config);
+    let server = QuincyServer::new(server_config).unwrap();
+
+    tokio::spawn(async move { server.run::<TestInterface<Server>>().await.unwrap() });
+    client.start::<TestInterface<Client>>().await.unwrap();
+
+    // The first ping is sent right away
+    let mut app_rtt = None;
+    for _ in 0..50 {
+        app_rtt = client.relayer().and_then(|relayer| relayer.app_rtt());
+        if app_rtt.is_some() {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    assert!(app_rtt.is_some());
+
+    // Outlive several ping deadlines
+    tokio::time::sleep(Duration::from_secs(3)).await;
+    assert_eq!(client.state(), ClientState::Connected);
+
+    client.stop().await.unwrap();
+    client.wait_for_shutdown().await.unwrap();
+}
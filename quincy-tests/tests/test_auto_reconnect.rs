@@ -0,0 +1,237 @@
+mod common;
+
+use common::{TestInterface, dummy_packet, setup_interface};
+use quincy::QuincyError;
+use quincy::config::{ClientConfig, FromPath, ServerConfig};
+use quincy::error::AuthError;
+use quincy_client::client::{ClientState, QuincyClient};
+use quincy_server::server::QuincyServer;
+use std::collections::HashMap;
+use std::net::{Ipv4Addr, SocketAddr};
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use tokio::net::UdpSocket;
+use tokio::time::{sleep, timeout};
+
+/// How long the proxy drops all packets, longer than the idle timeout of both peers.
+const OUTAGE: Duration = Duration::from_secs(5);
+
+/// How long the server takes to pick up a modified users file, longer than its poll interval.
+const USERS_FILE_RELOAD: Duration = Duration::from_secs(6);
+
+/// Fingerprint of the test client certificate in the users file.
+const CLIENT_CERT_FINGERPRINT: &str =
+    "sha256:2dba01529210e4e828265d56329df1b85a8f9aedccdd3fef67ab502b57cb0029";
+
+/// A UDP proxy between the clients and the server that can drop all packets,
+/// simulating a transient network outage.
+struct OutageProxy {
+    blocked: Arc<AtomicBool>,
+}
+
+impl OutageProxy {
+    /// Starts relaying packets between clients connecting to `port` and the server at `server_port`.
+    ///
+    /// Each client address is relayed through its own upstream socket, so that the
+    /// server's replies reach the client socket they are meant for.
+    async fn start(port: u16, server_port: u16) -> Self {
+        let front = Arc::new(UdpSocket::bind(("::", port)).await.unwrap());
+        let blocked = Arc::new(AtomicBool::new(false));
+        let proxy_blocked = blocked.clone();
+
+        tokio::spawn(async move {
+            let mut upstreams: HashMap<SocketAddr, Arc<UdpSocket>> = HashMap::new();
+            let mut buf = [0u8; u16::MAX as usize];
+
+            while let Ok((len, client_addr)) = front.recv_from(&mut buf).await {
+                if proxy_blocked.load(Ordering::Relaxed) {
+                    continue;
+                }
+
+                let upstream = match upstreams.get(&client_addr) {
+                    Some(upstream) => upstream.clone(),
+                    None => {
+                        let upstream = Arc::new(UdpSocket::bind(("::", 0)).await.unwrap());
+                        upstream.connect(("::1", server_port)).await.unwrap();
+                        tokio::spawn(Self::relay_replies(
+                            upstream.clone(),
+                            front.clone(),
+                            client_addr,
+                            proxy_blocked.clone(),
+                        ));
+                        upstreams.insert(client_addr, upstream.clone());
+                        upstream
+                    }
+                };
+                let _ = upstream.send(&buf[..len]).await;
+            }
+        });
+
+        Self { blocked }
+    }
+
+    /// Relays the server's replies on an upstream socket to the client.
+    async fn relay_replies(
+        upstream: Arc<UdpSocket>,
+        front: Arc<UdpSocket>,
+        client_addr: SocketAddr,
+        blocked: Arc<AtomicBool>,
+    ) {
+        let mut buf = [0u8; u16::MAX as usize];
+
+        while let Ok(len) = upstream.recv(&mut buf).await {
+            if !blocked.load(Ordering::Relaxed) {
+                let _ = front.send_to(&buf[..len], client_addr).await;
+            }
+        }
+    }
+
+    /// Drops all packets for the outage period.
+    async fn interrupt(&self) {
+        self.blocked.store(true, Ordering::Relaxed);
+        sleep(OUTAGE).await;
+        self.blocked.store(false, Ordering::Relaxed);
+    }
+}
+
+/// Loads the TLS test configurations with the client connecting to the server through a proxy.
+///
+/// Both peers time out idle connections within the proxy outage, and the client
+/// retries lost connections quickly.
+fn configs(server_port: u16, proxy_port: u16) -> (ClientConfig, ServerConfig) {
+    let config_dir = Path::new("tests/static/configs/tls_standard");
+
+    let mut client_config =
+        ClientConfig::from_path(&config_dir.join("client.toml"), "QUINCY_").unwrap();
+    client_config.connection_string = format!("localhost:{proxy_port}");
+    client_config.connection.connection_timeout_s = 2;
+    client_config.connection.keep_alive_interval_s = 1;
+    client_config.connection.auto_reconnect = true;
+    client_config.connection.reconnect_max_attempts = 0;
+    client_config.connection.reconnect_backoff_ms = 100;
+    // The test interface cannot be recreated, so keep it even if the address changes
+    client_config.network.persistent = true;
+
+    let mut server_config =
+        ServerConfig::from_path(&config_dir.join("server.toml"), "QUINCY_").unwrap();
+    server_config.bind_port = server_port;
+    server_config.connection.connection_timeout_s = 2;
+    // Hand the same address back to the reconnecting device
+    server_config.address_grace_period_s = 60;
+
+    (client_config, server_config)
+}
+
+#[tokio::test]
+async fn test_auto_reconnect_after_transient_outage() {
+    struct Client;
+    struct Server;
+
+    let client_ch = setup_interface::<Client>();
+    let server_ch = setup_interface::<Server>();
+
+    let (client_config, server_config) = configs(55170, 55171);
+    let proxy = OutageProxy::start(55171, 55170).await;
+
+    let mut client = QuincyClient::new(client_config);
+    let server = QuincyServer::new(server_config).unwrap();
+
+    tokio::spawn(async move { server.run::<TestInterface<Server>>().await.unwrap() });
+    client.start::<TestInterface<Client>>().await.unwrap();
+
+    let first_connection = client.relayer().unwrap().connection().clone();
+    let mut state_rx = client.subscribe_state();
+
+    let ip_server = Ipv4Addr::new(10, 0, 0, 1);
+    let ip_client = Ipv4Addr::new(10, 0, 0, 2);
+    let test_packet = dummy_packet(ip_client, ip_server);
+
+    let checks = async {
+        let reconnect = async {
+            // The connection times out during the outage and is re-established afterwards
+            timeout(
+                Duration::from_secs(10),
+                state_rx.wait_for(|state| *state == ClientState::Reconnecting),
+            )
+            .await
+            .expect("connection lost")
+            .unwrap();
+            timeout(
+                Duration::from_secs(15),
+                state_rx.wait_for(|state| *state == ClientState::Connected),
+            )
+            .await
+            .expect("tunnel reconnected")
+            .unwrap();
+        };
+        tokio::join!(proxy.interrupt(), reconnect);
+        assert!(first_connection.close_reason().is_some());
+
+        // Packets flow through the preserved interface
+        client_ch.tx.lock().await.send(test_packet.clone()).unwrap();
+
+        let recv_packet = timeout(Duration::from_secs(5), server_ch.rx.lock().await.recv())
+            .await
+            .expect("packet relayed after reconnecting")
+            .unwrap();
+        assert_eq!(recv_packet, test_packet);
+    };
+
+    tokio::select! {
+        result = client.run::<TestInterface<Client>>() => panic!("client stopped: {result:?}"),
+        _ = checks => {}
+    }
+
+    client.stop().await.unwrap();
+    client.wait_for_shutdown().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_auto_reconnect_stops_on_rejected_credentials() {
+    struct Client;
+    struct Server;
+
+    let _client_ch = setup_interface::<Client>();
+    let _server_ch = setup_interface::<Server>();
+
+    let (client_config, mut server_config) = configs(55172, 55173);
+    let users = std::fs::read_to_string(&server_config.users_file).unwrap();
+    let users_file = std::env::temp_dir().join("quincy_test_users_rejected_credentials.toml");
+    std::fs::write(&users_file, &users).unwrap();
+    server_config.users_file = users_file.clone();
+    let proxy = OutageProxy::start(55173, 55172).await;
+
+    let mut client = QuincyClient::new(client_config);
+    let server = QuincyServer::new(server_config).unwrap();
+
+    tokio::spawn(async move { server.run::<TestInterface<Server>>().await.unwrap() });
+    client.start::<TestInterface<Client>>().await.unwrap();
+
+    // Revoke the client certificate, leaving the established connection untouched
+    let revoked = users.replace(
+        CLIENT_CERT_FINGERPRINT,
+        &format!("sha256:{}", "0".repeat(64)),
+    );
+    std::fs::write(&users_file, revoked).unwrap();
+    sleep(USERS_FILE_RELOAD).await;
+
+    let (result, _) = tokio::join!(
+        timeout(
+            Duration::from_secs(20),
+            client.run::<TestInterface<Client>>()
+        ),
+        proxy.interrupt(),
+    );
+
+    // Retries are unlimited, so only the rejected credentials end them
+    match result.expect("client gave up reconnecting") {
+        Err(QuincyError::Auth(AuthError::InvalidCredentials)) => {}
+        other => panic!("expected rejected credentials, got {other:?}"),
+    }
+    assert!(matches!(client.state(), ClientState::Error { .. }));
+    assert!(client.relayer().is_none());
+
+    std::fs::remove_file(users_file).unwrap();
+}
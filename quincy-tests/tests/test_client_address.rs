@@ -0,0 +1,61 @@
+mod common;
+
+use common::{TestInterface, setup_interface};
+use quincy::config::{ClientConfig, FromPath, ServerConfig};
+use quincy_client::client::QuincyClient;
+use quincy_server::server::QuincyServer;
+use std::path::Path;
+use std::time::Duration;
+use tokio::time::timeout;
+
+#[tokio::test]
+async fn test_interface_address_notifications_across_reconnect() {
+    struct Client;
+    struct Server;
+
+    let _client_ch = setup_interface::<Client>();
+    let _server_ch = setup_interface::<Server>();
+
+    let config_dir = Path::new("tests/static/configs/tls_standard");
+    let client_config =
+        ClientConfig::from_path(&config_dir.join("client.toml"), "QUINCY_").unwrap();
+    let server_config =
+        ServerConfig::from_path(&config_dir.join("server.toml"), "QUINCY_").unwrap();
+
+    let mut client = QuincyClient::new(client_config);
+    let server = QuincyServer::new(server_config).unwrap();
+
+    tokio::spawn(async move { server.run::<TestInterface<Server>>().await.unwrap() });
+
+    assert_eq!(client.interface_address(), None);
+
+    let mut address_rx = client.subscribe_interface_address();
+
+    // First connection
+    client.start::<TestInterface<Client>>().await.unwrap();
+    let first_address = client
+        .interface_address()
+        .expect("interface address is assigned after connecting");
+    assert_eq!(client.client_address(), Some(first_address));
+    assert!(address_rx.has_changed().unwrap());
+    assert_eq!(*address_rx.borrow_and_update(), Some(first_address));
+
+    client.stop().await.unwrap();
+    client.wait_for_shutdown().await.unwrap();
+    assert!(address_rx.has_changed().unwrap());
+    assert_eq!(*address_rx.borrow_and_update(), None);
+
+    // Reconnect with the same client instance (the test interface hands its channels over on creation)
+    let _client_ch = setup_interface::<Client>();
+    client.start::<TestInterface<Client>>().await.unwrap();
+    timeout(Duration::from_secs(5), address_rx.changed())
+        .await
+        .expect("address notification timed out")
+        .unwrap();
+    let second_address = *address_rx.borrow_and_update();
+    assert!(second_address.is_some());
+    assert_eq!(second_address, client.interface_address());
+
+    client.stop().await.unwrap();
+    client.wait_for_shutdown().await.unwrap();
+}
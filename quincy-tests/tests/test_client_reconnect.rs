@@ -0,0 +1,68 @@
+mod common;
+
+use common::{TestInterface, dummy_packet, route_mutations, setup_interface};
+use quincy::config::{ClientConfig, FromPath, ServerConfig};
+use quincy_client::client::{ClientState, QuincyClient};
+use quincy_server::server::QuincyServer;
+use std::net::Ipv4Addr;
+use std::path::Path;
+use std::time::Duration;
+use tokio::time::timeout;
+
+#[tokio::test]
+async fn test_reconnect_preserves_interface() {
+    struct Client;
+    struct Server;
+
+    let client_ch = setup_interface::<Client>();
+    let server_ch = setup_interface::<Server>();
+
+    let config_dir = Path::new("tests/static/configs/tls_standard");
+    let mut client_config =
+        ClientConfig::from_path(&config_dir.join("client.toml"), "QUINCY_").unwrap();
+    client_config.network.routes = vec!["10.0.1.0/24".parse().unwrap()];
+    let mut server_config =
+        ServerConfig::from_path(&config_dir.join("server.toml"), "QUINCY_").unwrap();
+    // Hand the same address back to the reconnecting device
+    server_config.address_grace_period_s = 60;
+
+    let mut client = QuincyClient::new(client_config);
+    let server = QuincyServer::new(server_config).unwrap();
+
+    tokio::spawn(async move { server.run::<TestInterface<Server>>().await.unwrap() });
+
+    client.start::<TestInterface<Client>>().await.unwrap();
+    let address = client.interface_address();
+    let first_connection = client.relayer().unwrap().connection().clone();
+    let mutations = route_mutations::<Client>();
+    assert_eq!(mutations, 1);
+
+    // The test interface takes its channels from the registry on creation, so
+    // recreating the interface here would panic.
+    client.reconnect::<TestInterface<Client>>().await.unwrap();
+
+    let second_connection = client.relayer().unwrap().connection().clone();
+    assert_ne!(first_connection.stable_id(), second_connection.stable_id());
+    assert!(first_connection.close_reason().is_some());
+    assert!(second_connection.close_reason().is_none());
+    assert_eq!(client.interface_address(), address);
+    assert_eq!(client.state(), ClientState::Connected);
+    // Reconnecting with the same address and configuration leaves the routes in place
+    assert_eq!(route_mutations::<Client>(), mutations);
+
+    // Packets still flow through the preserved interface
+    let ip_server = Ipv4Addr::new(10, 0, 0, 1);
+    let ip_client = Ipv4Addr::new(10, 0, 0, 2);
+    let test_packet = dummy_packet(ip_client, ip_server);
+
+    client_ch.tx.lock().await.send(test_packet.clone()).unwrap();
+
+    let recv_packet = timeout(Duration::from_secs(5), server_ch.rx.lock().await.recv())
+        .await
+        .expect("packet was not relayed after reconnecting")
+        .unwrap();
+    assert_eq!(test_packet, recv_packet);
+
+    client.stop().await.unwrap();
+    client.wait_for_shutdown().await.unwrap();
+}
@@ -0,0 +1,82 @@
+mod common;
+
+use common::{TestInterface, setup_interface};
+use quincy::config::{ClientConfig, FromPath, ServerConfig};
+use quincy_client::client::{ClientState, QuincyClient, ShutdownReason};
+use quincy_server::server::QuincyServer;
+use std::path::Path;
+use std::time::Duration;
+use tokio::time::timeout;
+
+#[tokio::test]
+async fn test_client_state_transitions() {
+    struct Client;
+    struct Server;
+
+    let _client_ch = setup_interface::<Client>();
+    let _server_ch = setup_interface::<Server>();
+
+    let config_dir = Path::new("tests/static/configs/tls_standard");
+    let client_config =
+        ClientConfig::from_path(&config_dir.join("client.toml"), "QUINCY_").unwrap();
+    let server_config =
+        ServerConfig::from_path(&config_dir.join("server.toml"), "QUINCY_").unwrap();
+
+    let mut client = QuincyClient::new(client_config);
+    let server = QuincyServer::new(server_config).unwrap();
+
+    tokio::spawn(async move { server.run::<TestInterface<Server>>().await.unwrap() });
+
+    assert_eq!(client.state(), ClientState::Idle);
+    assert!(!client.is_running());
+
+    // Record every transition made during the connect/stop cycle
+    let mut state_rx = client.subscribe_state();
+    let observer = tokio::spawn(async move {
+        let mut states = Vec::new();
+        while state_rx.changed().await.is_ok() {
+            let state = state_rx.borrow_and_update().clone();
+            let done = state == ClientState::Idle;
+            states.push(state);
+            if done {
+                break;
+            }
+        }
+        states
+    });
+
+    client.start::<TestInterface<Client>>().await.unwrap();
+    assert_eq!(client.state(), ClientState::Connected);
+    assert!(client.is_running());
+
+    client.stop().await.unwrap();
+    assert_eq!(
+        client.wait_for_shutdown().await.unwrap(),
+        ShutdownReason::UserRequest
+    );
+    assert_eq!(client.shutdown_reason(), Some(ShutdownReason::UserRequest));
+    assert_eq!(client.state(), ClientState::Idle);
+    assert!(!client.is_running());
+
+    let states = timeout(Duration::from_secs(5), observer)
+        .await
+        .expect("state observer timed out")
+        .unwrap();
+
+    // The watch channel may coalesce rapid transitions, but the order is preserved
+    let expected = [
+        ClientState::Connecting,
+        ClientState::Authenticating,
+        ClientState::Connected,
+        ClientState::Disconnecting,
+        ClientState::Idle,
+    ];
+    let mut expected_iter = expected.iter();
+    for state in &states {
+        assert!(
+            expected_iter.any(|expected| expected == state),
+            "unexpected state transition order: {states:?}"
+        );
+    }
+    assert_eq!(states.last(), Some(&ClientState::Idle));
+}
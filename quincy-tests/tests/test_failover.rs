@@ -0,0 +1,64 @@
+mod common;
+
+use common::{TestInterface, dummy_packet, setup_interface};
+use quincy::config::{ClientConfig, FromPath, ServerConfig};
+use quincy_client::client::{ClientState, QuincyClient};
+use quincy_server::server::QuincyServer;
+use std::net::Ipv4Addr;
+use std::path::Path;
+use std::time::Duration;
+use tokio::time::timeout;
+
+#[tokio::test]
+async fn test_failover_to_secondary_endpoint() {
+    struct Client;
+    struct Server;
+
+    let client_ch = setup_interface::<Client>();
+    let server_ch = setup_interface::<Server>();
+
+    let config_dir = Path::new("tests/static/configs/tls_standard");
+    let mut client_config =
+        ClientConfig::from_path(&config_dir.join("client.toml"), "QUINCY_").unwrap();
+    let mut server_config =
+        ServerConfig::from_path(&config_dir.join("server.toml"), "QUINCY_").unwrap();
+
+    // Nothing listens on the primary endpoint, so connecting to it times out
+    client_config.connection_string = "127.0.0.1:55175".to_string();
+    client_config.connection_strings = vec!["localhost:55174".to_string()];
+    client_config.connection.connection_timeout_s = 2;
+    server_config.bind_port = 55174;
+    client_config.validate().unwrap();
+
+    let mut client = QuincyClient::new(client_config);
+    let server = QuincyServer::new(server_config).unwrap();
+
+    tokio::spawn(async move { server.run::<TestInterface<Server>>().await.unwrap() });
+
+    timeout(
+        Duration::from_secs(10),
+        client.start::<TestInterface<Client>>(),
+    )
+    .await
+    .expect("client connected within the timeout")
+    .unwrap();
+
+    assert_eq!(client.state(), ClientState::Connected);
+    assert_eq!(client.active_endpoint(), Some("localhost:55174"));
+
+    // Packets flow through the secondary endpoint
+    let ip_server = Ipv4Addr::new(10, 0, 0, 1);
+    let ip_client = Ipv4Addr::new(10, 0, 0, 2);
+    let test_packet = dummy_packet(ip_client, ip_server);
+    client_ch.tx.lock().await.send(test_packet.clone()).unwrap();
+
+    let recv_packet = timeout(Duration::from_secs(5), server_ch.rx.lock().await.recv())
+        .await
+        .expect("packet relayed through the secondary endpoint")
+        .unwrap();
+    assert_eq!(recv_packet, test_packet);
+
+    client.stop().await.unwrap();
+    client.wait_for_shutdown().await.unwrap();
+    assert_eq!(client.active_endpoint(), None);
+}
@@ -0,0 +1,78 @@
+mod common;
+
+use common::{TestInterface, dummy_packet, setup_interface};
+use quincy::config::{ClientConfig, FromPath, ServerConfig};
+use quincy_client::client::{ClientState, QuincyClient};
+use quincy_server::server::QuincyServer;
+use std::net::Ipv4Addr;
+use std::path::Path;
+use std::time::Duration;
+use tokio::time::timeout;
+
+#[tokio::test]
+async fn test_idle_disconnect_reconnects_on_outbound_traffic() {
+    struct Client;
+    struct Server;
+
+    let client_ch = setup_interface::<Client>();
+    let server_ch = setup_interface::<Server>();
+
+    let config_dir = Path::new("tests/static/configs/tls_standard");
+    let mut client_config =
+        ClientConfig::from_path(&config_dir.join("client.toml"), "QUINCY_").unwrap();
+    client_config.connection.idle_disconnect_s = Some(1);
+    let mut server_config =
+        ServerConfig::from_path(&config_dir.join("server.toml"), "QUINCY_").unwrap();
+    // Hand the same address back so that the interface is kept on reconnect
+    server_config.address_grace_period_s = 60;
+
+    let mut client = QuincyClient::new(client_config);
+    let server = QuincyServer::new(server_config).unwrap();
+
+    tokio::spawn(async move { server.run::<TestInterface<Server>>().await.unwrap() });
+    client.start::<TestInterface<Client>>().await.unwrap();
+
+    let first_connection = client.relayer().unwrap().connection().clone();
+    let mut state_rx = client.subscribe_state();
+
+    let ip_server = Ipv4Addr::new(10, 0, 0, 1);
+    let ip_client = Ipv4Addr::new(10, 0, 0, 2);
+    let test_packet = dummy_packet(ip_client, ip_server);
+
+    let checks = async {
+        // No packets are relayed, so the connection is closed after the idle period
+        timeout(
+            Duration::from_secs(5),
+            state_rx.wait_for(|state| *state == ClientState::Dormant),
+        )
+        .await
+        .expect("tunnel became idle")
+        .unwrap();
+        assert!(first_connection.close_reason().is_some());
+
+        // The next outbound packet re-establishes the connection and is relayed over it
+        client_ch.tx.lock().await.send(test_packet.clone()).unwrap();
+
+        let recv_packet = timeout(Duration::from_secs(10), server_ch.rx.lock().await.recv())
+            .await
+            .expect("packet relayed after reconnecting")
+            .unwrap();
+        assert_eq!(recv_packet, test_packet);
+
+        timeout(
+            Duration::from_secs(5),
+            state_rx.wait_for(|state| *state == ClientState::Connected),
+        )
+        .await
+        .expect("tunnel reconnected")
+        .unwrap();
+    };
+
+    tokio::select! {
+        result = client.run::<TestInterface<Client>>() => panic!("client stopped: {result:?}"),
+        _ = checks => {}
+    }
+
+    client.stop().await.unwrap();
+    client.wait_for_shutdown().await.unwrap();
+}
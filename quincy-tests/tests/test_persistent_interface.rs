@@ -0,0 +1,49 @@
+mod common;
+
+use common::{TestInterface, route_mutations, setup_interface};
+use quincy::config::{ClientConfig, FromPath, ServerConfig};
+use quincy_client::client::QuincyClient;
+use quincy_server::server::QuincyServer;
+use std::path::Path;
+
+#[tokio::test]
+async fn test_persistent_interface_is_reused_across_restarts() {
+    struct Client;
+    struct Server;
+
+    let _client_ch = setup_interface::<Client>();
+    let _server_ch = setup_interface::<Server>();
+
+    let config_dir = Path::new("tests/static/configs/tls_standard");
+    let mut client_config =
+        ClientConfig::from_path(&config_dir.join("client.toml"), "QUINCY_").unwrap();
+    client_config.network.persistent = true;
+    client_config.network.routes = vec!["10.0.1.0/24".parse().unwrap()];
+    let mut server_config =
+        ServerConfig::from_path(&config_dir.join("server.toml"), "QUINCY_").unwrap();
+    // Hand the same address back to the restarted client
+    server_config.address_grace_period_s = 60;
+
+    let mut client = QuincyClient::new(client_config);
+    let server = QuincyServer::new(server_config).unwrap();
+
+    tokio::spawn(async move { server.run::<TestInterface<Server>>().await.unwrap() });
+
+    client.start::<TestInterface<Client>>().await.unwrap();
+    assert!(client.interface_address().is_some());
+    assert_eq!(route_mutations::<Client>(), 1);
+
+    client.stop().await.unwrap();
+    client.wait_for_shutdown().await.unwrap();
+
+    // The test interface hands its channels over on creation, so a second
+    // interface could not be created without registering new channels
+    client.start::<TestInterface<Client>>().await.unwrap();
+    assert!(client.interface_address().is_some());
+    // The routes of the unchanged configuration stay installed
+    assert_eq!(route_mutations::<Client>(), 1);
+
+    client.stop().await.unwrap();
+    client.wait_for_shutdown().await.unwrap();
+    client.close_interface();
+}
@@ -0,0 +1,77 @@
+mod common;
+
+use common::{TestInterface, dummy_packet, setup_interface};
+use quincy::config::{ClientConfig, FromPath, ServerConfig};
+use quincy_client::client::QuincyClient;
+use quincy_server::server::QuincyServer;
+use rstest::rstest;
+use std::net::Ipv4Addr;
+use std::path::Path;
+use std::time::Instant;
+
+/// Number of packets sent from the server to the client.
+///
+/// Large enough for a meaningful packet rate, small enough to stay within the
+/// QUIC datagram buffers so that no packets are dropped.
+const PACKET_COUNT: usize = 2000;
+
+#[rstest]
+#[case(1)]
+#[case(4)]
+#[tokio::test]
+async fn test_receive_tasks(#[case] receive_tasks: u32) {
+    struct Client;
+    struct Server;
+
+    let config_dir = Path::new("tests/static/configs/tls_standard");
+
+    let client_ch = setup_interface::<Client>();
+    let server_ch = setup_interface::<Server>();
+
+    let mut client_config =
+        ClientConfig::from_path(&config_dir.join("client.toml"), "QUINCY_").unwrap();
+    client_config.connection.receive_tasks = receive_tasks;
+    let server_config =
+        ServerConfig::from_path(&config_dir.join("server.toml"), "QUINCY_").unwrap();
+
+    let mut client = QuincyClient::new(client_config);
+    let server = QuincyServer::new(server_config).unwrap();
+
+    let ip_server = Ipv4Addr::new(10, 0, 0, 1);
+    let ip_client = Ipv4Addr::new(10, 0, 0, 2);
+
+    tokio::spawn(async move { server.run::<TestInterface<Server>>().await.unwrap() });
+    client.start::<TestInterface<Client>>().await.unwrap();
+
+    // Make sure the server relays to the client before measuring
+    let warm_up_packet = dummy_packet(ip_client, ip_server);
+    client_ch
+        .tx
+        .lock()
+        .await
+        .send(warm_up_packet.clone())
+        .unwrap();
+    assert_eq!(
+        server_ch.rx.lock().await.recv().await.unwrap(),
+        warm_up_packet
+    );
+
+    let test_packet = dummy_packet(ip_server, ip_client);
+
+    let start = Instant::now();
+
+    for _ in 0..PACKET_COUNT {
+        server_ch.tx.lock().await.send(test_packet.clone()).unwrap();
+    }
+
+    let mut client_rx = client_ch.rx.lock().await;
+    for _ in 0..PACKET_COUNT {
+        assert_eq!(client_rx.recv().await.unwrap(), test_packet);
+    }
+
+    let elapsed = start.elapsed();
+    println!(
+        "{receive_tasks} receive task(s): {PACKET_COUNT} packets in {elapsed:?} ({:.0} packets/s)",
+        PACKET_COUNT as f64 / elapsed.as_secs_f64()
+    );
+}
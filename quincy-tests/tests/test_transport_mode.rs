@@ -0,0 +1,66 @@
+mod common;
+
+use common::{TestInterface, dummy_packet, setup_interface};
+use quincy::config::{ClientConfig, FromPath, ServerConfig, TransportMode};
+use quincy_client::client::QuincyClient;
+use quincy_server::server::QuincyServer;
+use rstest::rstest;
+use std::net::Ipv4Addr;
+use std::path::Path;
+
+/// Number of packets sent in each direction.
+const PACKET_COUNT: usize = 500;
+
+#[rstest]
+#[case(TransportMode::Datagram)]
+#[case(TransportMode::Stream)]
+#[tokio::test]
+async fn test_transport_mode(#[case] transport_mode: TransportMode) {
+    struct Client;
+    struct Server;
+
+    let config_dir = Path::new("tests/static/configs/tls_standard");
+
+    let client_ch = setup_interface::<Client>();
+    let server_ch = setup_interface::<Server>();
+
+    let mut client_config =
+        ClientConfig::from_path(&config_dir.join("client.toml"), "QUINCY_").unwrap();
+    client_config.connection.transport_mode = transport_mode;
+    let server_config =
+        ServerConfig::from_path(&config_dir.join("server.toml"), "QUINCY_").unwrap();
+
+    let mut client = QuincyClient::new(client_config);
+    let server = QuincyServer::new(server_config).unwrap();
+
+    let ip_server = Ipv4Addr::new(10, 0, 0, 1);
+    let ip_client = Ipv4Addr::new(10, 0, 0, 2);
+
+    tokio::spawn(async move { server.run::<TestInterface<Server>>().await.unwrap() });
+    client.start::<TestInterface<Client>>().await.unwrap();
+
+    // Client -> Server; in stream mode, this also makes the server switch to the stream
+    let test_packet = dummy_packet(ip_client, ip_server);
+    for _ in 0..PACKET_COUNT {
+        client_ch.tx.lock().await.send(test_packet.clone()).unwrap();
+    }
+
+    let mut server_rx = server_ch.rx.lock().await;
+    for _ in 0..PACKET_COUNT {
+        assert_eq!(server_rx.recv().await.unwrap(), test_packet);
+    }
+
+    // Server -> Client
+    let test_packet = dummy_packet(ip_server, ip_client);
+    for _ in 0..PACKET_COUNT {
+        server_ch.tx.lock().await.send(test_packet.clone()).unwrap();
+    }
+
+    let mut client_rx = client_ch.rx.lock().await;
+    for _ in 0..PACKET_COUNT {
+        assert_eq!(client_rx.recv().await.unwrap(), test_packet);
+    }
+
+    client.stop().await.unwrap();
+    client.wait_for_shutdown().await.unwrap();
+}
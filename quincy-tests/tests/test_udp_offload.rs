@@ -0,0 +1,162 @@
+use quincy::network::socket::{SocketOptions, bind_socket, check_udp_offload};
+use quinn::udp::{BATCH_SIZE, RecvMeta, Transmit, UdpSocketState};
+use rstest::rstest;
+use std::io::IoSliceMut;
+use std::net::{Ipv4Addr, SocketAddr, UdpSocket};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Number of datagrams sent in each run.
+const PACKET_COUNT: usize = 2000;
+/// Size of each datagram, matching a typical QUIC packet.
+const DATAGRAM_SIZE: usize = 1200;
+/// Number of datagrams sent per tick.
+const PACKETS_PER_TICK: usize = 20;
+/// Interval between ticks, giving a fixed rate of 20 000 packets/s.
+const TICK_INTERVAL: Duration = Duration::from_millis(1);
+/// Size of each receive buffer, large enough to hold a GRO-coalesced batch.
+const RECV_BUFFER_SIZE: usize = u16::MAX as usize;
+/// Socket buffer size, large enough to hold every datagram of a run.
+const SOCKET_BUFFER_SIZE: usize = 8 * 1024 * 1024;
+
+/// Number of system calls used to relay `PACKET_COUNT` datagrams.
+#[derive(Debug)]
+struct SyscallCount {
+    sends: usize,
+    recvs: usize,
+}
+
+fn bind_loopback() -> UdpSocket {
+    let socket = bind_socket(
+        SocketAddr::new(Ipv4Addr::LOCALHOST.into(), 0),
+        SOCKET_BUFFER_SIZE,
+        SOCKET_BUFFER_SIZE,
+        false,
+        &SocketOptions::default(),
+    )
+    .unwrap();
+    socket.set_nonblocking(false).unwrap();
+    socket
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .unwrap();
+
+    socket
+}
+
+/// Receives `PACKET_COUNT` datagrams one `recv_from` call at a time.
+fn recv_unbatched(socket: UdpSocket) -> usize {
+    let mut buf = [0u8; RECV_BUFFER_SIZE];
+    let mut recvs = 0;
+
+    for _ in 0..PACKET_COUNT {
+        socket.recv_from(&mut buf).unwrap();
+        recvs += 1;
+    }
+
+    recvs
+}
+
+/// Receives `PACKET_COUNT` datagrams the way Quinn does, with `recvmmsg` and UDP GRO.
+fn recv_batched(socket: UdpSocket, state: UdpSocketState) -> usize {
+    let mut storage = vec![[0u8; RECV_BUFFER_SIZE]; BATCH_SIZE];
+    let mut meta = vec![RecvMeta::default(); BATCH_SIZE];
+    let mut received = 0;
+    let mut recvs = 0;
+
+    while received < PACKET_COUNT {
+        let mut bufs = storage
+            .iter_mut()
+            .map(|buf| IoSliceMut::new(buf))
+            .collect::<Vec<_>>();
+        let messages = state.recv((&socket).into(), &mut bufs, &mut meta).unwrap();
+        recvs += 1;
+
+        received += meta[..messages]
+            .iter()
+            .map(|meta| meta.len.div_ceil(meta.stride))
+            .sum::<usize>();
+    }
+
+    recvs
+}
+
+/// Sends `PACKET_COUNT` datagrams at a fixed rate and counts the system calls on both ends.
+///
+/// Without batching, every datagram takes one `sendmsg` and one `recvmsg` call. With
+/// batching, the datagrams of each tick are sent with UDP GSO and received with
+/// `recvmmsg` and UDP GRO, as Quinn does on its endpoint socket.
+fn relay_at_fixed_rate(batched: bool) -> SyscallCount {
+    let sender = bind_loopback();
+    let receiver = bind_loopback();
+    let destination = receiver.local_addr().unwrap();
+
+    let sender_state = UdpSocketState::new((&sender).into()).unwrap();
+    let max_segments = if batched {
+        check_udp_offload(&sender, None)
+            .unwrap()
+            .max_gso_segments
+            .min(PACKETS_PER_TICK)
+    } else {
+        1
+    };
+
+    let receive_task = if batched {
+        let receiver_state = UdpSocketState::new((&receiver).into()).unwrap();
+        thread::spawn(move || recv_batched(receiver, receiver_state))
+    } else {
+        thread::spawn(move || recv_unbatched(receiver))
+    };
+
+    let payload = vec![0xab; DATAGRAM_SIZE * max_segments];
+    let mut next_tick = Instant::now();
+    let mut sent = 0;
+    let mut sends = 0;
+
+    while sent < PACKET_COUNT {
+        let tick_end = (sent + PACKETS_PER_TICK).min(PACKET_COUNT);
+
+        while sent < tick_end {
+            let segments = max_segments.min(tick_end - sent);
+            let transmit = Transmit {
+                destination,
+                ecn: None,
+                contents: &payload[..DATAGRAM_SIZE * segments],
+                segment_size: (segments > 1).then_some(DATAGRAM_SIZE),
+                src_ip: None,
+            };
+            sender_state.send((&sender).into(), &transmit).unwrap();
+            sends += 1;
+            sent += segments;
+        }
+
+        next_tick += TICK_INTERVAL;
+        thread::sleep(next_tick.saturating_duration_since(Instant::now()));
+    }
+
+    SyscallCount {
+        sends,
+        recvs: receive_task.join().unwrap(),
+    }
+}
+
+#[rstest]
+#[case(false)]
+#[case(true)]
+fn test_udp_offload_syscall_count(#[case] batched: bool) {
+    let count = relay_at_fixed_rate(batched);
+
+    println!(
+        "{}: {PACKET_COUNT} datagrams with {} send and {} receive system calls",
+        if batched { "batched" } else { "unbatched" },
+        count.sends,
+        count.recvs
+    );
+
+    if batched {
+        assert!(count.sends <= PACKET_COUNT);
+        assert!(count.recvs <= PACKET_COUNT);
+    } else {
+        assert_eq!(count.sends, PACKET_COUNT);
+        assert_eq!(count.recvs, PACKET_COUNT);
+    }
+}
@@ -0,0 +1,51 @@
+mod common;
+
+use common::{TestInterface, setup_interface};
+use quincy::config::{ClientConfig, FromPath, ServerConfig};
+use quincy_client::client::{ClientState, QuincyClient};
+use quincy_server::server::QuincyServer;
+use rstest::rstest;
+use std::path::Path;
+
+#[rstest]
+#[case(true, true, true)]
+#[case(true, false, false)]
+#[case(false, true, false)]
+#[tokio::test]
+async fn test_zero_rtt_on_reconnect(
+    #[case] client_0rtt: bool,
+    #[case] server_0rtt: bool,
+    #[case] expect_accepted: bool,
+) {
+    struct Client;
+    struct Server;
+
+    let _client_ch = setup_interface::<Client>();
+    let _server_ch = setup_interface::<Server>();
+
+    let config_dir = Path::new("tests/static/configs/tls_standard");
+    let mut client_config =
+        ClientConfig::from_path(&config_dir.join("client.toml"), "QUINCY_").unwrap();
+    client_config.enable_0rtt = client_0rtt;
+    let mut server_config =
+        ServerConfig::from_path(&config_dir.join("server.toml"), "QUINCY_").unwrap();
+    server_config.enable_0rtt = server_0rtt;
+    // Hand the same address back so that the interface is kept on reconnect
+    server_config.address_grace_period_s = 60;
+
+    let mut client = QuincyClient::new(client_config);
+    let server = QuincyServer::new(server_config).unwrap();
+
+    tokio::spawn(async move { server.run::<TestInterface<Server>>().await.unwrap() });
+
+    // The first connection has no session to resume
+    client.start::<TestInterface<Client>>().await.unwrap();
+    assert!(!client.zero_rtt_accepted());
+
+    client.reconnect::<TestInterface<Client>>().await.unwrap();
+    assert_eq!(client.state(), ClientState::Connected);
+    assert_eq!(client.zero_rtt_accepted(), expect_accepted);
+
+    client.stop().await.unwrap();
+    client.wait_for_shutdown().await.unwrap();
+}
@@ -2,7 +2,7 @@ use std::collections::HashSet;
 use std::fmt::Debug;
 use std::path::Path;
 use std::{
-    fs::File,
+    fs::{self, File},
     io::{BufReader, Cursor},
 };
 
@@ -12,6 +12,7 @@ use rustls::pki_types::pem::PemObject;
 use rustls::pki_types::{CertificateDer, PrivateKeyDer, UnixTime};
 use rustls::server::danger::{ClientCertVerified, ClientCertVerifier};
 use rustls::{DigitallySignedStruct, DistinguishedName, Error, SignatureScheme};
+use tracing::warn;
 
 use crate::error::{CertificateError, Result};
 
@@ -45,6 +46,50 @@ pub fn load_certificates_from_file(path: &Path) -> Result<Vec<CertificateDer<'st
     Ok(certs)
 }
 
+/// Loads certificates from all `*.pem` and `*.crt` files in a directory.
+///
+/// Files that cannot be loaded are skipped with a warning.
+///
+/// ### Arguments
+/// - `path` - Path to the directory containing the certificate files.
+///
+/// ### Returns
+/// - `Vec<CertificateDer>` - A list of loaded certificates.
+///
+/// ### Errors
+/// - `CertificateError::LoadFailed` - the directory does not exist or cannot be read
+pub fn load_certificates_from_dir(path: &Path) -> Result<Vec<CertificateDer<'static>>> {
+    let load_failed = || CertificateError::LoadFailed {
+        path: path.to_path_buf(),
+    };
+
+    let mut cert_paths = fs::read_dir(path)
+        .map_err(|_| load_failed())?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<std::io::Result<Vec<_>>>()
+        .map_err(|_| load_failed())?;
+    cert_paths.retain(|cert_path| {
+        cert_path.is_file()
+            && cert_path
+                .extension()
+                .and_then(|extension| extension.to_str())
+                .is_some_and(|extension| {
+                    extension.eq_ignore_ascii_case("pem") || extension.eq_ignore_ascii_case("crt")
+                })
+    });
+    cert_paths.sort();
+
+    let mut certs = Vec::new();
+    for cert_path in cert_paths {
+        match load_certificates_from_file(&cert_path) {
+            Ok(file_certs) => certs.extend(file_certs),
+            Err(e) => warn!("Skipping trusted certificate file: {e}"),
+        }
+    }
+
+    Ok(certs)
+}
+
 /// Loads certificates from a PEM string.
 ///
 /// ### Arguments
@@ -323,6 +368,42 @@ mod tests {
         assert!(result.is_err());
     }
 
+    // ========== load_certificates_from_dir tests ==========
+
+    #[test]
+    fn load_certificates_from_dir_loads_pem_and_crt_files() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("server.pem"), VALID_CERT_PEM_PKCS8).unwrap();
+        fs::write(dir.path().join("client.crt"), CLIENT_CERT_PEM).unwrap();
+        fs::write(dir.path().join("notes.txt"), VALID_CERT_PEM_PKCS8).unwrap();
+
+        let certs = load_certificates_from_dir(dir.path()).unwrap();
+        assert_eq!(certs.len(), 2);
+    }
+
+    #[test]
+    fn load_certificates_from_dir_skips_invalid_files() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("empty.pem"), "").unwrap();
+        fs::write(dir.path().join("key.pem"), VALID_KEY_PEM_PKCS8).unwrap();
+        fs::write(dir.path().join("server.pem"), VALID_CERT_PEM_PKCS8).unwrap();
+
+        let certs = load_certificates_from_dir(dir.path()).unwrap();
+        assert_eq!(certs.len(), 1);
+    }
+
+    #[test]
+    fn load_certificates_from_dir_nonexistent() {
+        let path = Path::new("/nonexistent/path/certs");
+        let result = load_certificates_from_dir(path);
+
+        assert!(matches!(
+            result,
+            Err(crate::QuincyError::Certificate(CertificateError::LoadFailed { path: failed }))
+                if failed == path
+        ));
+    }
+
     // ========== load_private_key_from_file tests ==========
 
     #[test]
@@ -1,23 +1,34 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::fmt;
 use std::str::FromStr;
 
 use crate::certificates::{
-    load_certificates_from_file, load_certificates_from_pem, load_private_key_from_file,
-    load_private_key_from_pem,
+    load_certificates_from_dir, load_certificates_from_file, load_certificates_from_pem,
+    load_private_key_from_file, load_private_key_from_pem,
 };
 use crate::constants::{
-    QUIC_MTU_OVERHEAD, TLS_ALPN_PROTOCOLS, TLS_INITIAL_CIPHER_SUITE, TLS_PROTOCOL_VERSIONS,
+    MAX_DSCP, QUIC_MIN_UDP_PAYLOAD_SIZE, QUIC_MTU_OVERHEAD, SRV_CONNECTION_SCHEME,
+    TLS_ALPN_PROTOCOLS, TLS_INITIAL_CIPHER_SUITE, TLS_PROTOCOL_VERSIONS,
 };
-use crate::error::{ConfigError, NoiseError, Result};
+use crate::error::{ConfigError, NoiseError, Result, SocketError};
+use crate::ip_assignment::PushedNetworkConfig;
+use crate::kx_hint_cache::KxHintCache;
+use crate::network::IpFamily;
+use crate::network::dns::{
+    DnsOptions, DnsProtocol, LeakProtection, SplitDnsDomain, is_valid_domain,
+};
+use crate::network::firewall::{KillSwitch, load_pinned_endpoints, pinned_endpoints_path};
+use crate::network::route::{RouteOptions, RouteSpec, SplitTunnel, bypass_networks};
+use crate::network::socket::SocketOptions;
 use base64::{DecodeSliceError, prelude::*};
 use figment::{
     Figment,
-    providers::{Env, Format, Toml},
+    providers::{Env, Format, Json, Serialized, Toml, Yaml},
+    value::{Dict, Value},
 };
 use ipnet::{IpAddrRange, IpNet, Ipv4AddrRange, Ipv6AddrRange};
 use quinn::{
-    EndpointConfig, TransportConfig,
+    EndpointConfig, MtuDiscoveryConfig, TransportConfig, VarInt,
     crypto::rustls::{QuicClientConfig, QuicServerConfig},
 };
 use reishi_quinn::{
@@ -25,6 +36,7 @@ use reishi_quinn::{
     PublicKey, REISHI_PQ_V1_QUIC_V1, REISHI_V1_QUIC_V1, StaticSecret, noise_handshake_token_key,
     noise_hmac_key,
 };
+use rustls::client::Resumption;
 use rustls::crypto::aws_lc_rs::kx_group::{MLKEM768, X25519MLKEM768};
 use rustls::crypto::{CryptoProvider, aws_lc_rs};
 use rustls::pki_types::{CertificateDer, PrivateKeyDer};
@@ -32,15 +44,42 @@ use rustls::{CipherSuite, RootCertStore};
 use secrecy::{ExposeSecret, SecretString};
 use serde::Deserialize;
 use serde::de::DeserializeOwned;
-use std::net::IpAddr;
+use std::net::{IpAddr, ToSocketAddrs};
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::time::Duration;
+use tracing::warn;
 use zeroize::Zeroizing;
 
+/// Current version of the configuration file layout.
+///
+/// Files declaring an older `config_version` (or none) are migrated on load.
+pub const CONFIG_VERSION: u32 = 1;
+
+/// Migrations between configuration layouts, indexed by the version they upgrade from.
+///
+/// Each migration returns whether it changed the configuration.
+const CONFIG_MIGRATIONS: [fn(&mut Dict) -> bool; CONFIG_VERSION as usize] =
+    [migrate_v0_connection_timeout];
+
+/// Range of MTUs accepted by configuration validation.
+const MTU_RANGE: std::ops::RangeInclusive<u16> = 576..=9000;
+
+/// Range of initial RTT estimates in milliseconds accepted by configuration validation.
+const INITIAL_RTT_MS_RANGE: std::ops::RangeInclusive<u64> = 1..=10000;
+
+/// TLS 1.3 cipher suites offered when none are configured.
+const DEFAULT_TLS_CIPHER_SUITES: [CipherSuite; 2] = [
+    CipherSuite::TLS13_AES_256_GCM_SHA384,
+    CipherSuite::TLS13_CHACHA20_POLY1305_SHA256,
+];
+
 /// Quincy server configuration.
 #[derive(Clone, Debug, Deserialize)]
 pub struct ServerConfig {
+    /// Version of the configuration layout (default = 0, i.e. before versioning)
+    #[serde(default)]
+    pub config_version: u32,
     /// The name of the tunnel
     pub name: String,
     /// Optional interface name to request for the tunnel device
@@ -55,16 +94,46 @@ pub struct ServerConfig {
     ///
     /// This is useful when running multiple Quincy instances on the same port for load balancing.
     ///
-    /// Unsupported on Windows.
+    /// Unsupported on Windows, where the socket is bound exclusively with a warning.
     #[serde(default = "default_false_fn")]
     pub reuse_socket: bool,
     /// The network address of this tunnel (address + mask)
     pub tunnel_network: IpNet,
+    /// Optional network of the other IP family (address + mask)
+    ///
+    /// When set, clients are assigned an address from both networks and their
+    /// tunnel interfaces come up dual-stack, e.g. `"fd00::1/64"` next to an
+    /// IPv4 `tunnel_network`.
+    pub secondary_tunnel_network: Option<IpNet>,
     /// Path to the TOML users file for authentication
     pub users_file: PathBuf,
     /// Whether to isolate clients from each other (default = true)
     #[serde(default = "default_true_fn")]
     pub isolate_clients: bool,
+    /// How long a disconnected device's tunnel address is held for it, in seconds (default = 0)
+    ///
+    /// Devices reconnecting within this period get their previous address back.
+    /// Devices are told apart by their credential, so several devices of one user
+    /// each keep their own address.
+    #[serde(default)]
+    pub address_grace_period_s: u64,
+    /// How long a disconnected client may resume its tunnel address with a resumption token,
+    /// in seconds (default = 0, i.e. disabled)
+    ///
+    /// Every client is handed an opaque token along with its address. A client presenting
+    /// the token when reconnecting within this period gets the same address back, unless it
+    /// was assigned to another client in the meantime. Unlike the grace period, the address
+    /// is not held. Reconnecting clients that predate resumption tokens are assigned an
+    /// address after a delay of up to a second.
+    #[serde(default)]
+    pub resumption_token_ttl_s: u64,
+    /// Whether clients may resume sessions with 0-RTT early data (default = false, TLS mode only)
+    ///
+    /// Early data can be replayed by an attacker. Clients are authenticated during the
+    /// handshake and tunnel packets are only relayed once it has completed, so replayed
+    /// early data is never acted on.
+    #[serde(default = "default_false_fn")]
+    pub enable_0rtt: bool,
     /// Default bandwidth limit applied to users without a per-user limit.
     /// If not set, users without a per-user limit have unlimited bandwidth.
     #[serde(default)]
@@ -79,6 +148,27 @@ pub struct ServerConfig {
     /// Prometheus metrics configuration.
     #[serde(default)]
     pub metrics: MetricsConfig,
+    /// Lockout of source addresses repeatedly failing authentication.
+    #[serde(default)]
+    pub auth_lockout: AuthLockoutConfig,
+    /// Optional path of the authentication audit log.
+    ///
+    /// Every authentication attempt is appended to it as a line of JSON, except that
+    /// repeated refusals of a locked out source address are recorded once a minute.
+    /// Audit logging is disabled if not set.
+    pub auth_audit_log: Option<PathBuf>,
+    /// Authorization groups users can be assigned to, keyed by group name.
+    ///
+    /// Once any group is defined, a user's access is the union of the policies of
+    /// their groups. Users without groups belong to the group named `default`.
+    #[serde(default)]
+    pub groups: HashMap<String, GroupConfig>,
+    /// Network settings pushed to clients along with their tunnel address.
+    ///
+    /// Clients merge them into their own network configuration unless they disable
+    /// `network.accept_pushed_config`.
+    #[serde(default)]
+    pub push: PushedNetworkConfig,
 }
 
 /// Server protocol configuration.
@@ -99,6 +189,17 @@ pub struct ServerTlsConfig {
     /// The key exchange algorithm to use (default = Hybrid)
     #[serde(default = "default_tls_key_exchange")]
     pub key_exchange: TlsKeyExchange,
+    /// ALPN protocol identifiers to negotiate (default = ["quincy"])
+    ///
+    /// Must match between the client and the server. Each entry must be 1 to 255 bytes long.
+    #[serde(default)]
+    pub alpn_protocols: Vec<String>,
+    /// TLS 1.3 cipher suites to offer (default = AES-256-GCM and ChaCha20-Poly1305)
+    ///
+    /// One of `TLS13_AES_256_GCM_SHA384`, `TLS13_AES_128_GCM_SHA256` or
+    /// `TLS13_CHACHA20_POLY1305_SHA256`. Does not affect the QUIC initial packet protection.
+    #[serde(default)]
+    pub cipher_suites: Vec<String>,
     /// The certificate to use for the tunnel
     #[serde(default)]
     pub certificate_file: Option<PathBuf>,
@@ -137,8 +238,23 @@ pub enum AllowedNoiseKeys {
 /// Quincy client configuration.
 #[derive(Clone, Debug, Deserialize)]
 pub struct ClientConfig {
+    /// Version of the configuration layout (default = 0, i.e. before versioning)
+    #[serde(default)]
+    pub config_version: u32,
     /// Connection string to be used to connect to a Quincy server
+    ///
+    /// Tried before the endpoints in `connection_strings`. Either may be left out, but at
+    /// least one endpoint must be configured. A connection string of the form
+    /// `srv://example.com` discovers the endpoints through the DNS SRV records of
+    /// `_quincy._udp.example.com`.
+    #[serde(default)]
     pub connection_string: String,
+    /// Connection strings of further endpoints of the Quincy server (default = none)
+    ///
+    /// The endpoints are tried in order until one accepts the client. When a lost connection
+    /// is re-established, the endpoint after the lost one is tried first.
+    #[serde(default)]
+    pub connection_strings: Vec<String>,
     /// Protocol configuration (TLS or Noise)
     pub protocol: ClientProtocolConfig,
     /// QUIC connection configuration
@@ -147,6 +263,18 @@ pub struct ClientConfig {
     /// Network configuration
     #[serde(default)]
     pub network: NetworkConfig,
+    /// Optional file used to remember the key-exchange group each server negotiated across
+    /// client restarts, saving a `HelloRetryRequest` round trip on the first handshake (TLS
+    /// mode only). Session tickets are kept in memory and are not persisted.
+    #[serde(default)]
+    pub kx_hint_cache_path: Option<PathBuf>,
+    /// Whether to send 0-RTT early data when resuming a session (default = false, TLS mode only)
+    ///
+    /// Saves the resumed handshake a round trip on reconnects if the server enables 0-RTT as
+    /// well. Quincy sends no credentials as early data, as authentication is part of the
+    /// handshake, so replaying the early data does not authenticate an attacker.
+    #[serde(default)]
+    pub enable_0rtt: bool,
     /// Logging configuration
     pub log: LogConfig,
 }
@@ -169,9 +297,23 @@ pub struct ClientTlsConfig {
     /// The key exchange algorithm to use (default = Hybrid)
     #[serde(default = "default_tls_key_exchange")]
     pub key_exchange: TlsKeyExchange,
+    /// ALPN protocol identifiers to negotiate (default = ["quincy"])
+    ///
+    /// Must match between the client and the server. Each entry must be 1 to 255 bytes long.
+    #[serde(default)]
+    pub alpn_protocols: Vec<String>,
+    /// TLS 1.3 cipher suites to offer (default = AES-256-GCM and ChaCha20-Poly1305)
+    ///
+    /// One of `TLS13_AES_256_GCM_SHA384`, `TLS13_AES_128_GCM_SHA256` or
+    /// `TLS13_CHACHA20_POLY1305_SHA256`. Does not affect the QUIC initial packet protection.
+    #[serde(default)]
+    pub cipher_suites: Vec<String>,
     /// A list of trusted certificate file paths
     #[serde(default)]
     pub trusted_certificate_paths: Vec<PathBuf>,
+    /// A list of directories whose `*.pem` and `*.crt` files are trusted
+    #[serde(default)]
+    pub trusted_certificate_dirs: Vec<PathBuf>,
     /// A list of trusted certificates as PEM strings
     #[serde(default)]
     pub trusted_certificates: Vec<String>,
@@ -198,7 +340,47 @@ pub struct ClientNoiseConfig {
     /// Base64-encoded server public key (32 bytes for Standard, 1216 bytes for Hybrid)
     pub server_public_key: String,
     /// Base64-encoded client private key for persistent identity
-    pub private_key: SecretString,
+    #[serde(default)]
+    pub private_key: Option<SecretString>,
+    /// Path to a file containing the Base64-encoded client private key
+    ///
+    /// Keeps the key out of the configuration file. Mutually exclusive with `private_key`.
+    #[serde(default)]
+    pub private_key_file: Option<PathBuf>,
+}
+
+impl ClientNoiseConfig {
+    /// Returns the client private key from either the inline value or the key file.
+    ///
+    /// Trailing whitespace (e.g. a final newline) is trimmed from the key file.
+    ///
+    /// ### Errors
+    /// - `ConfigError::Conflict` - both `private_key` and `private_key_file` are set
+    /// - `ConfigError::MissingField` - neither is set
+    /// - `ConfigError::FileNotReadable` - the key file cannot be read
+    pub fn private_key(&self) -> Result<SecretString> {
+        match (&self.private_key, &self.private_key_file) {
+            (Some(_), Some(_)) => Err(ConfigError::Conflict {
+                conflict: "Specify only one of protocol.private_key or protocol.private_key_file"
+                    .to_string(),
+            }
+            .into()),
+            (Some(private_key), None) => Ok(private_key.clone()),
+            (None, Some(path)) => {
+                let contents = Zeroizing::new(std::fs::read_to_string(path).map_err(|_| {
+                    ConfigError::FileNotReadable {
+                        path: path.to_path_buf(),
+                    }
+                })?);
+
+                Ok(SecretString::from(contents.trim_end()))
+            }
+            (None, None) => Err(ConfigError::MissingField {
+                field: "protocol.private_key or protocol.private_key_file".to_string(),
+            }
+            .into()),
+        }
+    }
 }
 
 /// TLS key exchange algorithm.
@@ -235,18 +417,183 @@ pub struct ConnectionConfig {
     /// The congestion control algorithm to use (default = Cubic)
     #[serde(default = "default_congestion_controller")]
     pub congestion_controller: CongestionController,
-    /// The time after which a connection is considered timed out in seconds (default = 30)
+    /// BBR tuning, only allowed with the BBR congestion controller (default = Quinn defaults)
+    #[serde(default)]
+    pub bbr: Option<BbrConfig>,
+    /// How tunnel packets are relayed to the server (default = datagram)
+    ///
+    /// Datagrams may be lost, like packets on any IP link. Stream mode relays packets
+    /// reliably and in order over a QUIC stream instead, for workloads that cannot tolerate
+    /// loss, at the cost of head-of-line blocking. Ignored by the server, which follows the
+    /// mode of each client.
+    #[serde(default)]
+    pub transport_mode: TransportMode,
+    /// The deadline for establishing a connection in seconds (default = 30)
+    ///
+    /// Also used as the idle timeout of established connections unless
+    /// `max_idle_timeout_s` is set.
     #[serde(default = "default_timeout_s")]
     pub connection_timeout_s: u64,
+    /// The deadline for establishing a connection in milliseconds (optional)
+    ///
+    /// Takes precedence over `connection_timeout_s` for a sub-second connection deadline.
+    /// Clients sending keep-alives need `max_idle_timeout_s` as well, since the idle timeout
+    /// must be greater than `keep_alive_interval_s`.
+    /// Setting both (i.e. changing `connection_timeout_s` from its default) is a conflict.
+    #[serde(default)]
+    pub connection_timeout_ms: Option<u64>,
+    /// The time after which an idle connection is closed in seconds (default = connection timeout)
+    ///
+    /// Must be greater than `keep_alive_interval_s` on clients.
+    #[serde(default)]
+    pub max_idle_timeout_s: Option<u64>,
     /// Keep alive interval for connections in seconds (default = 25)
+    ///
+    /// Set to 0 to disable keep-alives, e.g. to let metered mobile connections idle.
     #[serde(default = "default_keep_alive_interval_s")]
     pub keep_alive_interval_s: u64,
+    /// Interval of the application keep-alive pings in seconds (default = disabled)
+    ///
+    /// QUIC keep-alives keep the connection open, but do not prove that the server still
+    /// relays packets. When set, the client sends a ping datagram that the server echoes
+    /// back, and ends the tunnel with an error if the echo does not arrive in time, e.g.
+    /// because a relay task of the server is stuck. Must be nonzero. Ignored by the server.
+    #[serde(default)]
+    pub app_ping_interval_s: Option<u64>,
+    /// The deadline for the echo of an application keep-alive ping in seconds (default = 10)
+    ///
+    /// Must be nonzero. Ignored by the server.
+    #[serde(default = "default_app_ping_timeout_s")]
+    pub app_ping_timeout_s: u64,
+    /// The period without tunnel traffic in seconds after which the connection is closed
+    /// (default = disabled)
+    ///
+    /// Saves battery and data on metered connections: the TUN interface stays up, and the
+    /// next outbound packet re-establishes the connection. Keep-alives and application pings
+    /// do not count as traffic. Must be nonzero. Ignored by the server.
+    #[serde(default)]
+    pub idle_disconnect_s: Option<u64>,
+    /// Whether to re-establish the connection when it is lost (default = false)
+    ///
+    /// The TUN interface stays up while the connection is retried with exponential backoff.
+    /// Retries end early if the server rejects the client's credentials. A connection closed
+    /// by stopping the client is never re-established. Ignored by the server.
+    #[serde(default)]
+    pub auto_reconnect: bool,
+    /// The number of connection attempts after a lost connection before giving up
+    /// (default = 10)
+    ///
+    /// Set to 0 to retry indefinitely. Ignored by the server.
+    #[serde(default = "default_reconnect_max_attempts")]
+    pub reconnect_max_attempts: u32,
+    /// The delay in milliseconds before retrying a failed connection attempt (default = 1000)
+    ///
+    /// Doubles with every failed attempt, up to one minute. Must be nonzero. Ignored by
+    /// the server.
+    #[serde(default = "default_reconnect_backoff_ms")]
+    pub reconnect_backoff_ms: u64,
+    /// Thresholds of the connection quality shown by the GUI (`[connection.quality]`)
+    ///
+    /// Ignored by the server.
+    #[serde(default)]
+    pub quality: QualityConfig,
+    /// Initial round-trip time estimate in milliseconds (default = Quinn default)
+    ///
+    /// Raising it avoids spurious early retransmits on high-latency links such as satellite.
+    /// Must be between 1 and 10000.
+    #[serde(default)]
+    pub initial_rtt_ms: Option<u64>,
     /// The size of the send buffer of the socket and Quinn endpoint (default = 2097152)
     #[serde(default = "default_buffer_size")]
     pub send_buffer_size: u64,
     /// The size of the receive buffer of the socket and Quinn endpoint (default = 2097152)
     #[serde(default = "default_buffer_size")]
     pub recv_buffer_size: u64,
+    /// The number of tasks relaying packets from the server to the TUN interface (default = 1)
+    ///
+    /// Quinn receives all packets of a connection on a single socket: the kernel steers them
+    /// to one socket by their address 4-tuple, so additional sockets would stay idle. On
+    /// multi-core hosts, several tasks can instead write the decrypted packets to the TUN
+    /// interface in parallel, at the cost of possibly reordering them. Must be nonzero.
+    /// Ignored by the server.
+    #[serde(default = "default_receive_tasks")]
+    pub receive_tasks: u32,
+    /// The local UDP port to bind the client QUIC socket to (default = ephemeral)
+    ///
+    /// Useful behind firewalls that only permit a fixed outbound source port.
+    /// Ignored by the server, which binds to `bind_port`.
+    #[serde(default)]
+    pub local_port: Option<u16>,
+    /// The local address to bind the client QUIC socket to (default = unspecified)
+    ///
+    /// Pins the tunnel to the network interface owning the address on multi-homed hosts.
+    /// Only server addresses of the same address family are connected to; without a local
+    /// address, a dual-stack socket reaches server addresses of both families.
+    /// Ignored by the server, which binds to `bind_address`.
+    #[serde(default)]
+    pub local_address: Option<IpAddr>,
+    /// The firewall mark (`SO_MARK`) set on the packets of the client QUIC socket, Linux only
+    ///
+    /// Lets policy routing rules (e.g. `ip rule add fwmark 0x51 lookup main`) route the tunnel's
+    /// own packets outside of the tunnel, preventing routing loops in full-tunnel mode. With
+    /// split tunneling, such a rule is installed automatically. Requires `CAP_NET_ADMIN`.
+    /// Ignored by the server.
+    #[serde(default)]
+    pub socket_mark: Option<u32>,
+    /// The DSCP value (0-63) set on the QUIC packets, e.g. 46 (Expedited Forwarding)
+    ///
+    /// Lets QoS-aware networks prioritize the tunnel over other traffic. Sets the IPv4 ToS
+    /// field and, except on Windows, the IPv6 traffic class.
+    #[serde(default)]
+    pub dscp: Option<u8>,
+    /// Maximum number of concurrent bidirectional streams the peer may open (default = 100)
+    ///
+    /// Tunnel packets are relayed over QUIC datagrams, or a single stream in stream
+    /// `transport_mode`, so other streams only carry control traffic (e.g. the IP
+    /// assignment exchange). Must be nonzero.
+    #[serde(default = "default_max_concurrent_streams")]
+    pub max_concurrent_bidi_streams: u32,
+    /// Maximum number of concurrent unidirectional streams the peer may open (default = 100)
+    ///
+    /// The server delivers the IP assignment over a unidirectional stream,
+    /// so clients must accept at least one. Must be nonzero.
+    #[serde(default = "default_max_concurrent_streams")]
+    pub max_concurrent_uni_streams: u32,
+    /// Maximum number of bytes the peer may send on a single stream before
+    /// acknowledgement (default = Quinn default)
+    ///
+    /// Must be nonzero and at most 4294967295.
+    #[serde(default)]
+    pub stream_receive_window: Option<u64>,
+    /// Maximum number of bytes the peer may send across all streams of a connection
+    /// before acknowledgement (default = Quinn default)
+    #[serde(default)]
+    pub receive_window: Option<u64>,
+    /// Maximum number of bytes to transmit to the peer without acknowledgement
+    /// (default = Quinn default)
+    #[serde(default)]
+    pub send_window: Option<u64>,
+    /// Whether to probe for a larger path MTU using DPLPMTUD (default = false)
+    ///
+    /// When enabled, the QUIC MTU starts at `mtu` (plus overhead) but may fall back to
+    /// the QUIC minimum instead of being pinned. The TUN interface still uses `mtu`.
+    #[serde(default)]
+    pub pmtud: bool,
+    /// Whether to use GSO/GRO segmentation offload on the TUN interface (default = true)
+    ///
+    /// Only takes effect on Linux builds with the `offload` feature; elsewhere packets are
+    /// always relayed one at a time. Disabling it works around NICs with broken GSO checksums.
+    #[serde(default = "default_true_fn")]
+    pub offload: bool,
+    /// The largest UDP payload of the QUIC packets sent with segmentation offload (default = auto)
+    ///
+    /// Quinn sends batches of QUIC packets with UDP generic segmentation offload (GSO) where
+    /// supported, each segment being one QUIC packet. Lowering the segment size helps with
+    /// middleboxes dropping large UDP packets; tunnel packets that no longer fit are dropped,
+    /// so `mtu` should be lowered to match. Set to 0 to disable GSO entirely. Must be between
+    /// 1200 and `mtu` plus the QUIC overhead.
+    #[serde(default)]
+    pub gso_segment_size: Option<u16>,
 }
 
 /// Network configuration.
@@ -254,15 +601,63 @@ pub struct ConnectionConfig {
 pub struct NetworkConfig {
     /// Routes/networks to be routed through the tunnel
     ///
-    /// In the format of `address/mask`, e.g.:
+    /// In the format of `address/mask`, optionally with a route metric, e.g.:
     /// ```toml
     /// routes = [
     ///     "10.0.1.0/24",
-    ///     "10.11.12.0/24"
+    ///     { net = "10.11.12.0/24", metric = 50 }
     /// ]
     /// ```
     #[serde(default = "default_routes")]
-    pub routes: Vec<IpNet>,
+    pub routes: Vec<RouteSpec>,
+    /// Networks excluded from the routes through the tunnel
+    ///
+    /// Each excluded network is routed through the gateway it was reached through before
+    /// connecting, and must be more specific than a route it is carved out of, e.g.:
+    /// ```toml
+    /// routes = ["0.0.0.0/0"]
+    /// exclude_routes = ["192.168.0.0/16"]
+    /// ```
+    #[serde(default)]
+    pub exclude_routes: Vec<IpNet>,
+    /// Whether to block all traffic outside of the tunnel while connected (default = false)
+    ///
+    /// Only takes effect when the routes contain a default route (`0.0.0.0/0` or `::/0`).
+    /// Installs firewall rules (nftables or iptables on Linux, pf on macOS, Windows Firewall on
+    /// Windows) rejecting outbound traffic that does not go through the tunnel, except to the
+    /// server, to the `exclude_routes` networks and on the loopback interface. The rules are
+    /// removed on disconnect. Not supported on FreeBSD.
+    #[serde(default)]
+    pub kill_switch: bool,
+    /// Whether the kill switch only logs the firewall changes instead of making them (default = false)
+    #[serde(default)]
+    pub kill_switch_dry_run: bool,
+    /// Whether to block all traffic outside of the tunnel from the moment the client starts
+    /// connecting until it is stopped cleanly, regardless of the routes (default = false)
+    ///
+    /// Uses the same firewall rules as `kill_switch`, allowing the server endpoints, the
+    /// `exclude_routes` networks and the loopback interface before the tunnel is up, so that
+    /// nothing leaks during the handshake. The rules are only removed when the client is
+    /// stopped; a client that fails, or a daemon that crashes, leaves them in place by design,
+    /// until they are lifted with `quincy-client --lift-kill-switch`. Meanwhile, a restarted
+    /// client connects to the server addresses pinned by the engaged kill switch instead of
+    /// resolving the endpoints. Takes precedence over `kill_switch`. Not supported on FreeBSD.
+    #[serde(default)]
+    pub kill_switch_strict: bool,
+    /// User IDs whose traffic is sent through the tunnel routes, Linux only (default = all users)
+    ///
+    /// When set, the routes are installed into the routing table `split_tunnel_table` instead
+    /// of the main table, and policy routing rules (`ip rule add uidrange ...`) only look it up
+    /// for the traffic of these users. Requires `CAP_NET_ADMIN`.
+    #[serde(default)]
+    pub split_tunnel_uids: Vec<u32>,
+    /// Routing table holding the tunnel routes while split tunneling (default = 51900)
+    ///
+    /// Must not be a table reserved by the kernel (0 and 253-255) and should not be used by
+    /// other software; the default stays clear of both and of WireGuard's customary 51820.
+    /// The rules selecting the table use priorities 30999 and 31000, before the main table.
+    #[serde(default = "default_split_tunnel_table")]
+    pub split_tunnel_table: u32,
     /// DNS servers to use for the tunnel
     ///
     /// In the format of `address`, e.g.:
@@ -273,8 +668,431 @@ pub struct NetworkConfig {
     /// ```
     #[serde(default = "default_dns_servers")]
     pub dns_servers: Vec<IpAddr>,
+    /// Maximum number of DNS servers configured on the tunnel interface (default = 8)
+    ///
+    /// Longer DNS server lists are rejected before the system resolver is touched.
+    #[serde(default = "default_max_dns_servers")]
+    pub max_dns_servers: usize,
+    /// Domains resolved through the tunnel's DNS servers (split DNS)
+    ///
+    /// When set, only queries for these domains and their subdomains go through the tunnel,
+    /// while all other queries keep using the system resolver. Domains without their own
+    /// `dns_servers` are resolved through the `dns_servers` above, e.g.:
+    /// ```toml
+    /// dns_split_domains = [
+    ///     { domain = "corp.example.com", dns_servers = ["10.0.1.1"] },
+    ///     { domain = "internal" },
+    /// ]
+    /// ```
+    #[serde(default)]
+    pub dns_split_domains: Vec<SplitDnsDomain>,
+    /// Protocol used to reach the DNS servers (default = "plain")
+    ///
+    /// With `dot` (DNS-over-TLS) or `doh` (DNS-over-HTTPS), the system resolver is pointed at a
+    /// local stub resolver on 127.0.0.1:53, which forwards the queries to the DNS servers over
+    /// TLS. The DNS servers must present a certificate valid for their IP address, or for
+    /// `dns_tls_name` if set.
+    #[serde(default)]
+    pub dns_protocol: DnsProtocol,
+    /// Optional name the certificates of `dns_servers` are verified against with `dot` or `doh`
+    ///
+    /// Also sent as the TLS server name and the DoH `Host` header. The own DNS servers of
+    /// `dns_split_domains` are still verified against their IP address, e.g.:
+    /// ```toml
+    /// dns_tls_name = "dns.corp.example.com"
+    /// ```
+    #[serde(default)]
+    pub dns_tls_name: Option<String>,
+    /// Certificates trusted to sign the DNS servers' certificates with `dot` or `doh`
+    ///
+    /// When empty, the platform's trust store is used.
+    #[serde(default)]
+    pub dns_trusted_certificate_paths: Vec<PathBuf>,
+    /// Path of the DNS-over-HTTPS endpoint of the DNS servers (default = "/dns-query")
+    #[serde(default)]
+    pub dns_doh_path: Option<String>,
+    /// Whether to block DNS queries to servers other than `dns_servers` while the tunnel is up (default = false)
+    ///
+    /// Installs firewall rules (nftables or iptables on Linux, pf on macOS, Windows Firewall on
+    /// Windows) rejecting outbound traffic to ports 53 and 853, except to the DNS servers and
+    /// on the loopback interface. Not supported together with `dns_split_domains` or on FreeBSD.
+    #[serde(default)]
+    pub dns_leak_protection: bool,
+    /// Whether DNS leak protection only logs the firewall changes instead of making them (default = false)
+    #[serde(default)]
+    pub dns_leak_protection_dry_run: bool,
+    /// Domains appended to unqualified host names while the tunnel is up
+    ///
+    /// Ignored on Windows and together with `dns_split_domains` on macOS, e.g.:
+    /// ```toml
+    /// dns_search_domains = ["corp.example.com"]
+    /// ```
+    #[serde(default)]
+    pub dns_search_domains: Vec<String>,
+    /// Whether to apply the routes, DNS servers and search domains pushed by the server (default = true)
+    ///
+    /// Pushed routes are added to `routes` and pushed search domains are searched before
+    /// `dns_search_domains`, while pushed DNS servers replace `dns_servers`.
+    #[serde(default = "default_true_fn")]
+    pub accept_pushed_config: bool,
     /// Optional interface name to request for the tunnel device
+    ///
+    /// Limited to 15 characters on Linux and FreeBSD and of the form `utun<N>` on macOS.
+    /// When unset, the operating system picks a name.
     pub interface_name: Option<String>,
+    /// IP families routed through the tunnel (default = ["ipv4", "ipv6"])
+    ///
+    /// Routes, DNS servers and tunnel addresses of disabled families are not configured, e.g.:
+    /// ```toml
+    /// enabled_families = ["ipv4"]
+    /// ```
+    #[serde(default = "default_enabled_families")]
+    pub enabled_families: Vec<IpFamily>,
+    /// Whether Quincy installs the configured routes (default = true)
+    ///
+    /// When disabled, the tunnel interface only receives its address and routing is left to the user.
+    #[serde(default = "default_true_fn")]
+    pub manage_routes: bool,
+    /// Whether Quincy configures the system resolver with the configured DNS servers (default = true)
+    #[serde(default = "default_true_fn")]
+    pub manage_dns: bool,
+    /// Whether the tunnel interface, with its routes and DNS servers, outlives the connection (default = false)
+    ///
+    /// When enabled, the interface is created on the first connection and reused by later
+    /// ones instead of being recreated, and only torn down when the client is shut down.
+    #[serde(default)]
+    pub persistent: bool,
+    /// Whether to clamp the MSS of TCP connections through the tunnel to fit its MTU (default = false)
+    ///
+    /// When enabled, the MSS option of TCP SYN packets passing the tunnel is lowered to the
+    /// tunnel MTU minus the IP and TCP headers (40 bytes for IPv4, 60 bytes for IPv6).
+    #[serde(default)]
+    pub clamp_mss: bool,
+}
+
+impl NetworkConfig {
+    /// Returns whether the family of the given address is routed through the tunnel.
+    ///
+    /// ### Arguments
+    /// - `address` - the address to check
+    pub fn is_family_enabled(&self, address: &IpAddr) -> bool {
+        self.enabled_families.contains(&IpFamily::of(address))
+    }
+
+    /// Returns the configured routes belonging to an enabled IP family.
+    pub fn enabled_routes(&self) -> Vec<RouteSpec> {
+        self.routes
+            .iter()
+            .filter(|route| self.is_family_enabled(&route.net.addr()))
+            .copied()
+            .collect()
+    }
+
+    /// Returns the configured DNS servers belonging to an enabled IP family.
+    pub fn enabled_dns_servers(&self) -> Vec<IpAddr> {
+        self.dns_servers
+            .iter()
+            .filter(|server| self.is_family_enabled(server))
+            .copied()
+            .collect()
+    }
+
+    /// Returns the routes Quincy should install, or `None` if route management is disabled.
+    pub fn managed_routes(&self) -> Option<Vec<RouteSpec>> {
+        self.manage_routes.then(|| self.enabled_routes())
+    }
+
+    /// Returns the excluded networks Quincy should route around the tunnel, or none if route
+    /// management is disabled.
+    ///
+    /// Excluded networks of disabled IP families, and those not carved out of an enabled route,
+    /// are dropped.
+    pub fn managed_bypass_networks(&self) -> Vec<IpNet> {
+        if !self.manage_routes {
+            return Vec::new();
+        }
+
+        let exclude_routes: Vec<IpNet> = self
+            .exclude_routes
+            .iter()
+            .filter(|net| self.is_family_enabled(&net.addr()))
+            .copied()
+            .collect();
+
+        bypass_networks(&self.enabled_routes(), &exclude_routes)
+    }
+
+    /// Returns the route options Quincy should apply together with the managed routes.
+    pub fn managed_route_options(&self) -> RouteOptions {
+        RouteOptions {
+            bypass_networks: self.managed_bypass_networks(),
+            // The strict kill switch is managed by the client instead
+            kill_switch: match (
+                self.manage_routes && self.kill_switch && !self.kill_switch_strict,
+                self.kill_switch_dry_run,
+            ) {
+                (false, _) => KillSwitch::Disabled,
+                (true, false) => KillSwitch::Enabled,
+                (true, true) => KillSwitch::DryRun,
+            },
+            split_tunnel: (self.manage_routes && !self.split_tunnel_uids.is_empty()).then(|| {
+                SplitTunnel {
+                    uids: self.split_tunnel_uids.clone(),
+                    table: self.split_tunnel_table,
+                    fwmark: None,
+                }
+            }),
+        }
+    }
+
+    /// Returns the mode of the strict kill switch, engaged for as long as the client runs.
+    pub fn strict_kill_switch(&self) -> KillSwitch {
+        match (self.kill_switch_strict, self.kill_switch_dry_run) {
+            (false, _) => KillSwitch::Disabled,
+            (true, false) => KillSwitch::Enabled,
+            (true, true) => KillSwitch::DryRun,
+        }
+    }
+
+    /// Returns the DNS servers Quincy should configure, or `None` if DNS management is disabled.
+    pub fn managed_dns_servers(&self) -> Option<Vec<IpAddr>> {
+        self.manage_dns.then(|| self.enabled_dns_servers())
+    }
+
+    /// Returns the split DNS domains Quincy should configure, or none if DNS management is disabled.
+    ///
+    /// Domains without their own DNS servers are given the enabled `dns_servers`, and DNS
+    /// servers of disabled IP families are dropped.
+    pub fn managed_dns_split_domains(&self) -> Vec<SplitDnsDomain> {
+        if !self.manage_dns {
+            return Vec::new();
+        }
+
+        self.dns_split_domains
+            .iter()
+            .map(|split| SplitDnsDomain {
+                domain: split.domain.clone(),
+                dns_servers: if split.dns_servers.is_empty() {
+                    self.enabled_dns_servers()
+                } else {
+                    split
+                        .dns_servers
+                        .iter()
+                        .filter(|server| self.is_family_enabled(server))
+                        .copied()
+                        .collect()
+                },
+            })
+            .collect()
+    }
+
+    /// Merges the network settings pushed by the server into this configuration.
+    ///
+    /// Pushed routes are added to the configured ones and pushed search domains are searched
+    /// first. Pushed DNS servers replace the configured ones, as the server knows which
+    /// resolvers are reachable through the tunnel. Nothing is merged if `accept_pushed_config`
+    /// is disabled.
+    ///
+    /// ### Arguments
+    /// - `pushed` - the network settings pushed by the server
+    ///
+    /// ### Returns
+    /// - `NetworkConfig` - the network configuration to apply to the tunnel
+    pub fn merge_pushed(&self, pushed: &PushedNetworkConfig) -> NetworkConfig {
+        let mut network = self.clone();
+        if !self.accept_pushed_config {
+            return network;
+        }
+
+        for net in &pushed.routes {
+            if !network.routes.iter().any(|route| route.net == *net) {
+                network.routes.push(RouteSpec::from(*net));
+            }
+        }
+
+        if !pushed.dns_servers.is_empty() {
+            network.dns_servers = pushed.dns_servers.clone();
+        }
+
+        let mut search_domains = Vec::new();
+        for domain in pushed
+            .dns_search_domains
+            .iter()
+            .chain(&self.dns_search_domains)
+        {
+            if !is_valid_domain(domain) {
+                warn!("Ignoring pushed DNS search domain '{domain}': not a valid domain name");
+            } else if !search_domains.contains(domain) {
+                search_domains.push(domain.clone());
+            }
+        }
+        network.dns_search_domains = search_domains;
+
+        network
+    }
+
+    /// Returns the DNS options Quincy should apply together with the managed DNS servers.
+    pub fn managed_dns_options(&self) -> DnsOptions {
+        DnsOptions {
+            split_domains: self.managed_dns_split_domains(),
+            search_domains: if self.manage_dns {
+                self.dns_search_domains.clone()
+            } else {
+                Vec::new()
+            },
+            protocol: self.dns_protocol,
+            tls_name: self.dns_tls_name.clone(),
+            trusted_certificate_paths: self.dns_trusted_certificate_paths.clone(),
+            doh_path: self.dns_doh_path.clone(),
+            leak_protection: match (self.dns_leak_protection, self.dns_leak_protection_dry_run) {
+                (false, _) => LeakProtection::Disabled,
+                (true, false) => LeakProtection::Enabled,
+                (true, true) => LeakProtection::DryRun,
+            },
+        }
+    }
+
+    /// Validates the split DNS domains.
+    ///
+    /// ### Errors
+    /// Returns `ConfigError::InvalidValue` if a domain is not a valid domain name or
+    /// has no DNS servers to resolve it.
+    fn validate_dns_split_domains(&self) -> Result<()> {
+        for split in &self.dns_split_domains {
+            let reason = if !is_valid_domain(&split.domain) {
+                format!("'{}' is not a valid domain name", split.domain)
+            } else if split.dns_servers.is_empty() && self.dns_servers.is_empty() {
+                format!(
+                    "'{}' has no DNS servers and network.dns_servers is empty",
+                    split.domain
+                )
+            } else {
+                continue;
+            };
+
+            return Err(ConfigError::InvalidValue {
+                field: "network.dns_split_domains".to_string(),
+                reason,
+            }
+            .into());
+        }
+
+        Ok(())
+    }
+
+    /// Validates the settings of the encrypted DNS protocols.
+    ///
+    /// ### Errors
+    /// Returns `ConfigError::InvalidValue` if the TLS name is not a valid domain name or the
+    /// DoH path is not an absolute path.
+    fn validate_dns_protocol(&self) -> Result<()> {
+        if let Some(tls_name) = &self.dns_tls_name {
+            if !is_valid_domain(tls_name) {
+                return Err(ConfigError::InvalidValue {
+                    field: "network.dns_tls_name".to_string(),
+                    reason: format!("'{tls_name}' is not a valid domain name"),
+                }
+                .into());
+            }
+        }
+
+        if let Some(doh_path) = &self.dns_doh_path {
+            let is_valid_path = doh_path.starts_with('/')
+                && doh_path.chars().all(|c| c.is_ascii_graphic() && c != '#');
+            if !is_valid_path {
+                return Err(ConfigError::InvalidValue {
+                    field: "network.dns_doh_path".to_string(),
+                    reason: format!("'{doh_path}' is not an absolute URL path"),
+                }
+                .into());
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Validates the DNS search domains.
+    ///
+    /// ### Errors
+    /// Returns `ConfigError::InvalidValue` if a search domain is not a valid domain name.
+    fn validate_dns_search_domains(&self) -> Result<()> {
+        validate_search_domains("network.dns_search_domains", &self.dns_search_domains)
+    }
+
+    /// Validates the kill switch.
+    ///
+    /// ### Errors
+    /// Returns `ConfigError::InvalidValue` if the kill switch is enabled without route
+    /// management, as it relies on the default route through the tunnel.
+    fn validate_kill_switch(&self) -> Result<()> {
+        if !self.kill_switch || self.manage_routes {
+            return Ok(());
+        }
+
+        Err(ConfigError::InvalidValue {
+            field: "network.kill_switch".to_string(),
+            reason: "requires network.manage_routes".to_string(),
+        }
+        .into())
+    }
+
+    /// Validates split tunneling.
+    ///
+    /// ### Errors
+    /// Returns `ConfigError::InvalidValue` if split tunneling is enabled on a platform other than
+    /// Linux, without route management, together with either kill switch, or with a routing table
+    /// reserved by the kernel.
+    fn validate_split_tunnel(&self) -> Result<()> {
+        if self.split_tunnel_uids.is_empty() {
+            return Ok(());
+        }
+
+        let reason = if !cfg!(target_os = "linux") {
+            "is only supported on Linux".to_string()
+        } else if !self.manage_routes {
+            "requires network.manage_routes".to_string()
+        } else if self.kill_switch || self.kill_switch_strict {
+            "cannot be combined with network.kill_switch".to_string()
+        } else if matches!(self.split_tunnel_table, 0 | 253..=255) {
+            format!(
+                "network.split_tunnel_table {} is reserved by the kernel",
+                self.split_tunnel_table
+            )
+        } else {
+            return Ok(());
+        };
+
+        Err(ConfigError::InvalidValue {
+            field: "network.split_tunnel_uids".to_string(),
+            reason,
+        }
+        .into())
+    }
+
+    /// Validates DNS leak protection.
+    ///
+    /// ### Errors
+    /// Returns `ConfigError::InvalidValue` if leak protection is enabled together with split
+    /// DNS domains or without any DNS servers, which would block all DNS queries.
+    fn validate_dns_leak_protection(&self) -> Result<()> {
+        if !self.dns_leak_protection {
+            return Ok(());
+        }
+
+        let reason = if !self.dns_split_domains.is_empty() {
+            "cannot be combined with network.dns_split_domains"
+        } else if self.enabled_dns_servers().is_empty() {
+            "requires at least one DNS server in network.dns_servers"
+        } else {
+            return Ok(());
+        };
+
+        Err(ConfigError::InvalidValue {
+            field: "network.dns_leak_protection".to_string(),
+            reason: reason.to_string(),
+        }
+        .into())
+    }
 }
 
 /// Logging configuration.
@@ -283,6 +1101,13 @@ pub struct LogConfig {
     /// The log level to use (default = info)
     #[serde(default = "default_log_level")]
     pub level: String,
+    /// Optional pcap file capturing every packet read from and written to the tunnel interface
+    ///
+    /// Requires the `capture` feature. Meant for debugging, as packets are written unencrypted.
+    pub capture_file: Option<PathBuf>,
+    /// Size of the capture file in bytes after which capturing stops (default = 100 MiB)
+    #[serde(default = "default_capture_max_bytes")]
+    pub capture_max_bytes: u64,
 }
 
 /// Prometheus metrics endpoint configuration.
@@ -319,7 +1144,107 @@ impl Default for MetricsConfig {
     }
 }
 
-/// Bandwidth value stored as bytes per second.
+/// Authorization policy shared by the members of a group.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+pub struct GroupConfig {
+    /// Destination networks members may send traffic to through the tunnel.
+    ///
+    /// Unlike the per-user setting, an empty list grants no destinations.
+    #[serde(default)]
+    pub allowed_destinations: Vec<IpNet>,
+    /// Optional bandwidth limit for members of this group.
+    /// A per-user limit takes precedence.
+    #[serde(default)]
+    pub bandwidth_limit: Option<Bandwidth>,
+}
+
+/// Lockout of source addresses repeatedly failing authentication.
+///
+/// A source address failing the handshake or identification `max_failures` times within
+/// `window_s` is refused for `lockout_s`. Each consecutive lockout doubles the duration,
+/// up to `max_lockout_s`. A successful authentication resets the address. Failed handshakes
+/// only count if the source address was validated, as they can be forged otherwise.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+pub struct AuthLockoutConfig {
+    /// Failed attempts after which an address is locked out (default = 10, 0 = disabled)
+    #[serde(default = "default_auth_lockout_max_failures")]
+    pub max_failures: u32,
+    /// Window in seconds in which failed attempts are counted (default = 60)
+    #[serde(default = "default_auth_lockout_window_s")]
+    pub window_s: u64,
+    /// Duration of the first lockout in seconds (default = 60)
+    #[serde(default = "default_auth_lockout_lockout_s")]
+    pub lockout_s: u64,
+    /// Maximum lockout duration in seconds (default = 3600)
+    #[serde(default = "default_auth_lockout_max_lockout_s")]
+    pub max_lockout_s: u64,
+    /// Maximum number of source addresses tracked at once (default = 10000)
+    ///
+    /// When full, the least recently seen address that is not locked out is forgotten.
+    #[serde(default = "default_auth_lockout_max_tracked_addresses")]
+    pub max_tracked_addresses: usize,
+}
+
+impl Default for AuthLockoutConfig {
+    fn default() -> Self {
+        Self {
+            max_failures: default_auth_lockout_max_failures(),
+            window_s: default_auth_lockout_window_s(),
+            lockout_s: default_auth_lockout_lockout_s(),
+            max_lockout_s: default_auth_lockout_max_lockout_s(),
+            max_tracked_addresses: default_auth_lockout_max_tracked_addresses(),
+        }
+    }
+}
+
+impl AuthLockoutConfig {
+    /// Returns whether failed authentications lead to lockouts.
+    pub fn is_enabled(&self) -> bool {
+        self.max_failures > 0
+    }
+
+    /// Validates the lockout configuration.
+    ///
+    /// ### Errors
+    /// - `ConfigError::InvalidValue` - a duration or the address limit is zero while enabled
+    /// - `ConfigError::Conflict` - the first lockout is longer than the maximum lockout
+    fn validate(&self) -> Result<()> {
+        if !self.is_enabled() {
+            return Ok(());
+        }
+
+        for (field, value) in [
+            ("auth_lockout.window_s", self.window_s),
+            ("auth_lockout.lockout_s", self.lockout_s),
+            (
+                "auth_lockout.max_tracked_addresses",
+                self.max_tracked_addresses as u64,
+            ),
+        ] {
+            if value == 0 {
+                return Err(ConfigError::InvalidValue {
+                    field: field.to_string(),
+                    reason: "must be greater than 0 while the lockout is enabled".to_string(),
+                }
+                .into());
+            }
+        }
+
+        if self.lockout_s > self.max_lockout_s {
+            return Err(ConfigError::Conflict {
+                conflict: format!(
+                    "auth_lockout.lockout_s ({}) is greater than auth_lockout.max_lockout_s ({})",
+                    self.lockout_s, self.max_lockout_s
+                ),
+            }
+            .into());
+        }
+
+        Ok(())
+    }
+}
+
+/// Bandwidth value stored as bytes per second.
 ///
 /// Parsed from human-readable strings like "10 mbps", "500 kbps", "1 gbps".
 /// Supported units (case-insensitive): bps, kbps, mbps, gbps.
@@ -688,19 +1613,171 @@ pub enum CongestionController {
     NewReno,
 }
 
+/// How tunnel packets are relayed over the QUIC connection.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+pub enum TransportMode {
+    /// QUIC datagrams - unreliable, like the IP packets they carry
+    #[default]
+    #[serde(alias = "datagram")]
+    Datagram,
+    /// A length-framed bidirectional QUIC stream - reliable and ordered
+    #[serde(alias = "stream")]
+    Stream,
+}
+
+/// Tuning of the BBR congestion controller (`[connection.bbr]`).
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+pub struct BbrConfig {
+    /// The initial congestion window in bytes (default = Quinn default)
+    ///
+    /// A larger window lets new connections ramp up faster on high-bandwidth links,
+    /// a smaller one is gentler on shared links. Must be nonzero.
+    #[serde(default)]
+    pub initial_window: Option<u64>,
+}
+
+/// Thresholds grading the quality of the connection (`[connection.quality]`).
+///
+/// The path statistics of the connection are sampled every `sample_interval_s`, and the
+/// quality is graded from the round-trip time and the share of packets lost since the
+/// previous sample.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+pub struct QualityConfig {
+    /// The interval in seconds between samples of the path statistics (default = 5)
+    #[serde(default = "default_quality_sample_interval_s")]
+    pub sample_interval_s: u64,
+    /// The round-trip time in milliseconds from which the connection is degraded (default = 150)
+    #[serde(default = "default_degraded_rtt_ms")]
+    pub degraded_rtt_ms: u64,
+    /// The round-trip time in milliseconds from which the connection is poor (default = 400)
+    #[serde(default = "default_poor_rtt_ms")]
+    pub poor_rtt_ms: u64,
+    /// The percentage of lost packets from which the connection is degraded (default = 1)
+    #[serde(default = "default_degraded_loss_percent")]
+    pub degraded_loss_percent: f64,
+    /// The percentage of lost packets from which the connection is poor (default = 5)
+    #[serde(default = "default_poor_loss_percent")]
+    pub poor_loss_percent: f64,
+}
+
+impl Default for QualityConfig {
+    fn default() -> Self {
+        Self {
+            sample_interval_s: default_quality_sample_interval_s(),
+            degraded_rtt_ms: default_degraded_rtt_ms(),
+            poor_rtt_ms: default_poor_rtt_ms(),
+            degraded_loss_percent: default_degraded_loss_percent(),
+            poor_loss_percent: default_poor_loss_percent(),
+        }
+    }
+}
+
 pub trait ConfigInit<T: DeserializeOwned> {
     /// Initializes the configuration object from the given Figment.
     ///
+    /// Configurations of older layouts are migrated to the current one first.
+    ///
     /// ### Arguments
     /// - `figment` - the Figment to use for initialization
     fn init(figment: Figment, _env_prefix: &str) -> Result<T> {
-        Ok(figment.extract()?)
+        Ok(migrate_config(figment)?.extract()?)
+    }
+}
+
+/// Upgrades a configuration of an older layout to the current [`CONFIG_VERSION`].
+///
+/// The Figment is returned unchanged if no migration applies. Otherwise only the migrated
+/// values are merged over it, so the remaining values keep their metadata for error reporting.
+///
+/// ### Arguments
+/// - `figment` - the Figment holding the configuration
+///
+/// ### Errors
+/// - `ConfigError::InvalidValue` - the configuration is of a newer layout than supported
+fn migrate_config(figment: Figment) -> Result<Figment> {
+    let version = match figment.find_value("config_version") {
+        Ok(value) => value.deserialize::<u32>()?,
+        Err(_) => 0,
+    };
+
+    if version > CONFIG_VERSION {
+        return Err(ConfigError::InvalidValue {
+            field: "config_version".to_string(),
+            reason: format!(
+                "version {version} is newer than the supported version {CONFIG_VERSION}, please upgrade Quincy"
+            ),
+        }
+        .into());
+    }
+
+    if version == CONFIG_VERSION {
+        return Ok(figment);
     }
+
+    let original: Dict = figment.extract()?;
+    let mut config = original.clone();
+    let mut migrated = false;
+    for migration in &CONFIG_MIGRATIONS[version as usize..] {
+        migrated |= migration(&mut config);
+    }
+
+    if !migrated {
+        return Ok(figment);
+    }
+
+    warn!(
+        "Migrated configuration from version {version} to {CONFIG_VERSION}, consider updating the configuration file"
+    );
+    let mut changes = config_changes(&original, &config);
+    changes.insert("config_version".to_string(), Value::from(CONFIG_VERSION));
+
+    Ok(figment.merge(Serialized::defaults(changes)))
+}
+
+/// Collects the values of `migrated` that are new or differ from `original`.
+///
+/// Only these are merged over the original Figment, so untouched values keep their
+/// provider metadata.
+///
+/// ### Arguments
+/// - `original` - the configuration before migration
+/// - `migrated` - the configuration after migration
+fn config_changes(original: &Dict, migrated: &Dict) -> Dict {
+    migrated
+        .iter()
+        .filter_map(|(key, value)| match (original.get(key), value) {
+            (Some(Value::Dict(_, original)), Value::Dict(tag, migrated)) => {
+                let changes = config_changes(original, migrated);
+                (!changes.is_empty()).then(|| (key.clone(), Value::Dict(*tag, changes)))
+            }
+            (Some(original), value) if original == value => None,
+            _ => Some((key.clone(), value.clone())),
+        })
+        .collect()
+}
+
+/// Renames the legacy `connection.timeout` key to `connection.connection_timeout_s`.
+fn migrate_v0_connection_timeout(config: &mut Dict) -> bool {
+    let Some(Value::Dict(_, connection)) = config.get_mut("connection") else {
+        return false;
+    };
+    let Some(timeout) = connection.remove("timeout") else {
+        return false;
+    };
+
+    connection
+        .entry("connection_timeout_s".to_string())
+        .or_insert(timeout);
+
+    true
 }
 
 pub trait FromPath<T: DeserializeOwned + ConfigInit<T>> {
     /// Creates a configuration object from the given path and ENV prefix.
     ///
+    /// The file format is detected from the extension (`.toml`, `.json`, `.yaml`/`.yml`),
+    /// falling back to TOML for unknown extensions.
+    ///
     /// ### Arguments
     /// - `path` - a path to the configuration file
     /// - `env_prefix` - the ENV prefix to use for overrides
@@ -712,9 +1789,33 @@ pub trait FromPath<T: DeserializeOwned + ConfigInit<T>> {
             .into());
         }
 
-        let figment = Figment::new()
-            .merge(Toml::file(path))
-            .merge(Env::prefixed(env_prefix).split("__"));
+        let figment = match path.extension().and_then(|extension| extension.to_str()) {
+            Some("json") => Figment::new().merge(Json::file(path)),
+            Some("yaml" | "yml") => Figment::new().merge(Yaml::file(path)),
+            Some("toml") => Figment::new().merge(Toml::file(path)),
+            _ => {
+                warn!(
+                    "Unknown configuration file extension of {}, parsing as TOML",
+                    path.display()
+                );
+                Figment::new().merge(Toml::file(path))
+            }
+        }
+        .merge(Env::prefixed(env_prefix).split("__"));
+
+        T::init(figment, env_prefix)
+    }
+}
+
+pub trait FromEnv<T: DeserializeOwned + ConfigInit<T>> {
+    /// Creates a configuration object purely from environment variables.
+    ///
+    /// Nested fields are separated by `__`, e.g. `QUINCY_CONNECTION__MTU`.
+    ///
+    /// ### Arguments
+    /// - `env_prefix` - the ENV prefix of the configuration variables
+    fn from_env(env_prefix: &str) -> Result<T> {
+        let figment = Figment::new().merge(Env::prefixed(env_prefix).split("__"));
 
         T::init(figment, env_prefix)
     }
@@ -726,15 +1827,43 @@ impl ConfigInit<ClientConfig> for ClientConfig {}
 impl FromPath<ServerConfig> for ServerConfig {}
 impl FromPath<ClientConfig> for ClientConfig {}
 
+impl FromEnv<ServerConfig> for ServerConfig {}
+impl FromEnv<ClientConfig> for ClientConfig {}
+
 impl Default for ConnectionConfig {
     fn default() -> Self {
         Self {
             mtu: default_mtu(),
             congestion_controller: default_congestion_controller(),
+            bbr: None,
+            transport_mode: TransportMode::default(),
             connection_timeout_s: default_timeout_s(),
+            connection_timeout_ms: None,
+            max_idle_timeout_s: None,
             keep_alive_interval_s: default_keep_alive_interval_s(),
+            app_ping_interval_s: None,
+            app_ping_timeout_s: default_app_ping_timeout_s(),
+            idle_disconnect_s: None,
+            auto_reconnect: false,
+            reconnect_max_attempts: default_reconnect_max_attempts(),
+            reconnect_backoff_ms: default_reconnect_backoff_ms(),
+            quality: QualityConfig::default(),
+            initial_rtt_ms: None,
             send_buffer_size: default_buffer_size(),
             recv_buffer_size: default_buffer_size(),
+            local_port: None,
+            local_address: None,
+            receive_tasks: default_receive_tasks(),
+            socket_mark: None,
+            dscp: None,
+            max_concurrent_bidi_streams: default_max_concurrent_streams(),
+            max_concurrent_uni_streams: default_max_concurrent_streams(),
+            stream_receive_window: None,
+            receive_window: None,
+            send_window: None,
+            pmtud: false,
+            offload: true,
+            gso_segment_size: None,
         }
     }
 }
@@ -743,9 +1872,52 @@ impl Default for NetworkConfig {
     fn default() -> Self {
         Self {
             routes: default_routes(),
+            exclude_routes: Vec::new(),
+            kill_switch: false,
+            kill_switch_dry_run: false,
+            kill_switch_strict: false,
+            split_tunnel_uids: Vec::new(),
+            split_tunnel_table: default_split_tunnel_table(),
             dns_servers: default_dns_servers(),
+            max_dns_servers: default_max_dns_servers(),
+            dns_split_domains: Vec::new(),
+            dns_protocol: DnsProtocol::default(),
+            dns_tls_name: None,
+            dns_trusted_certificate_paths: Vec::new(),
+            dns_doh_path: None,
+            dns_leak_protection: false,
+            dns_leak_protection_dry_run: false,
+            dns_search_domains: Vec::new(),
+            accept_pushed_config: true,
             interface_name: None,
+            enabled_families: default_enabled_families(),
+            manage_routes: true,
+            manage_dns: true,
+            persistent: false,
+            clamp_mss: false,
+        }
+    }
+}
+
+/// Validates a list of DNS search domains.
+///
+/// ### Arguments
+/// - `field` - the configuration field holding the search domains
+/// - `search_domains` - the search domains to validate
+///
+/// ### Errors
+/// Returns `ConfigError::InvalidValue` if a search domain is not a valid domain name.
+fn validate_search_domains(field: &str, search_domains: &[String]) -> Result<()> {
+    match search_domains
+        .iter()
+        .find(|domain| !is_valid_domain(domain))
+    {
+        Some(domain) => Err(ConfigError::InvalidValue {
+            field: field.to_string(),
+            reason: format!("'{domain}' is not a valid domain name"),
         }
+        .into()),
+        None => Ok(()),
     }
 }
 
@@ -753,6 +1925,10 @@ fn default_log_level() -> String {
     "info".to_string()
 }
 
+fn default_capture_max_bytes() -> u64 {
+    100 * 1024 * 1024
+}
+
 fn default_bind_address() -> IpAddr {
     "0.0.0.0".parse().expect("Default address is valid")
 }
@@ -781,7 +1957,47 @@ fn default_keep_alive_interval_s() -> u64 {
     25
 }
 
-fn default_routes() -> Vec<IpNet> {
+fn default_app_ping_timeout_s() -> u64 {
+    10
+}
+
+fn default_reconnect_max_attempts() -> u32 {
+    10
+}
+
+fn default_reconnect_backoff_ms() -> u64 {
+    1000
+}
+
+fn default_quality_sample_interval_s() -> u64 {
+    5
+}
+
+fn default_degraded_rtt_ms() -> u64 {
+    150
+}
+
+fn default_poor_rtt_ms() -> u64 {
+    400
+}
+
+fn default_degraded_loss_percent() -> f64 {
+    1.0
+}
+
+fn default_poor_loss_percent() -> f64 {
+    5.0
+}
+
+fn default_max_concurrent_streams() -> u32 {
+    100
+}
+
+fn default_receive_tasks() -> u32 {
+    1
+}
+
+fn default_routes() -> Vec<RouteSpec> {
     Vec::new()
 }
 
@@ -789,6 +2005,18 @@ fn default_dns_servers() -> Vec<IpAddr> {
     Vec::new()
 }
 
+fn default_max_dns_servers() -> usize {
+    8
+}
+
+fn default_split_tunnel_table() -> u32 {
+    51900
+}
+
+fn default_enabled_families() -> Vec<IpFamily> {
+    vec![IpFamily::V4, IpFamily::V6]
+}
+
 fn default_true_fn() -> bool {
     true
 }
@@ -813,6 +2041,26 @@ fn default_metrics_idle_timeout_s() -> u64 {
     300
 }
 
+fn default_auth_lockout_max_failures() -> u32 {
+    10
+}
+
+fn default_auth_lockout_window_s() -> u64 {
+    60
+}
+
+fn default_auth_lockout_lockout_s() -> u64 {
+    60
+}
+
+fn default_auth_lockout_max_lockout_s() -> u64 {
+    3600
+}
+
+fn default_auth_lockout_max_tracked_addresses() -> usize {
+    10_000
+}
+
 fn default_tls_key_exchange() -> TlsKeyExchange {
     TlsKeyExchange::Hybrid
 }
@@ -824,17 +2072,75 @@ fn default_noise_key_exchange() -> NoiseKeyExchange {
 // --- TLS crypto provider ---
 
 /// Builds a rustls CryptoProvider configured for the given TLS key exchange mode.
-fn tls_crypto_provider(key_exchange: &TlsKeyExchange) -> CryptoProvider {
+/// Returns the ALPN protocol identifiers to negotiate over TLS.
+///
+/// ### Arguments
+/// - `protocols` - the configured identifiers, `TLS_ALPN_PROTOCOLS` is used if empty
+///
+/// ### Errors
+/// - `ConfigError::InvalidValue` - an identifier is empty or longer than 255 bytes
+pub fn alpn_protocol_ids(protocols: &[String]) -> Result<Vec<Vec<u8>>> {
+    if protocols.is_empty() {
+        return Ok(TLS_ALPN_PROTOCOLS.clone());
+    }
+
+    protocols
+        .iter()
+        .map(|protocol| {
+            if protocol.is_empty() || protocol.len() > u8::MAX as usize {
+                return Err(ConfigError::InvalidValue {
+                    field: "protocol.alpn_protocols".to_string(),
+                    reason: format!(
+                        "ALPN protocol '{protocol}' must be between 1 and 255 bytes long"
+                    ),
+                }
+                .into());
+            }
+
+            Ok(protocol.as_bytes().to_vec())
+        })
+        .collect()
+}
+
+/// Maps configured TLS 1.3 cipher suite names to rustls cipher suites.
+///
+/// ### Arguments
+/// - `names` - the configured cipher suite names, `DEFAULT_TLS_CIPHER_SUITES` is used if empty
+///
+/// ### Errors
+/// - `ConfigError::InvalidValue` - a name is not a supported TLS 1.3 cipher suite
+fn tls_cipher_suites(names: &[String]) -> Result<Vec<CipherSuite>> {
+    if names.is_empty() {
+        return Ok(DEFAULT_TLS_CIPHER_SUITES.to_vec());
+    }
+
+    names
+        .iter()
+        .map(|name| match name.to_ascii_uppercase().as_str() {
+            "TLS13_AES_256_GCM_SHA384" => Ok(CipherSuite::TLS13_AES_256_GCM_SHA384),
+            "TLS13_AES_128_GCM_SHA256" => Ok(CipherSuite::TLS13_AES_128_GCM_SHA256),
+            "TLS13_CHACHA20_POLY1305_SHA256" => Ok(CipherSuite::TLS13_CHACHA20_POLY1305_SHA256),
+            _ => Err(ConfigError::InvalidValue {
+                field: "protocol.cipher_suites".to_string(),
+                reason: format!("unknown TLS 1.3 cipher suite '{name}'"),
+            }
+            .into()),
+        })
+        .collect()
+}
+
+fn tls_crypto_provider(
+    key_exchange: &TlsKeyExchange,
+    cipher_suites: &[String],
+) -> Result<CryptoProvider> {
     let mut custom_provider = aws_lc_rs::default_provider();
+    let cipher_suites = tls_cipher_suites(cipher_suites)?;
 
-    custom_provider.cipher_suites.retain(|suite| {
-        matches!(
-            suite.suite(),
-            CipherSuite::TLS13_AES_256_GCM_SHA384 | CipherSuite::TLS13_CHACHA20_POLY1305_SHA256
-        )
-    });
+    custom_provider
+        .cipher_suites
+        .retain(|suite| cipher_suites.contains(&suite.suite()));
 
-    match key_exchange {
+    Ok(match key_exchange {
         TlsKeyExchange::Standard => custom_provider,
         TlsKeyExchange::Hybrid => CryptoProvider {
             kx_groups: vec![X25519MLKEM768],
@@ -844,7 +2150,7 @@ fn tls_crypto_provider(key_exchange: &TlsKeyExchange) -> CryptoProvider {
             kx_groups: vec![MLKEM768],
             ..custom_provider
         },
-    }
+    })
 }
 
 fn load_identity_certificates(
@@ -896,77 +2202,608 @@ fn load_identity_private_key(
 // --- Client config builders ---
 
 impl ClientConfig {
-    /// Creates Quinn client configuration from this Quincy client configuration.
+    /// Validates the configuration, reporting misconfiguration before connecting.
     ///
-    /// ### Returns
-    /// - `quinn::ClientConfig` - the Quinn client configuration
-    pub fn quinn_client_config(&self) -> Result<quinn::ClientConfig> {
-        match &self.protocol {
-            ClientProtocolConfig::Tls(tls) => self.build_tls_client_config(tls),
-            ClientProtocolConfig::Noise(noise) => self.build_noise_client_config(noise),
+    /// Note that resolving the connection strings may perform DNS lookups.
+    ///
+    /// ### Errors
+    /// - `ConfigError::InvalidValue` - no connection string resolves to an address, the MTU
+    ///   is out of range, or a split DNS domain is invalid
+    /// - `ConfigError::Conflict` - the keep-alive interval is not below the idle timeout,
+    ///   or 0-RTT is enabled in Noise mode
+    /// - `ConfigError::MissingField` - no connection string is configured, or no trusted
+    ///   certificate source is configured (TLS mode)
+    pub fn validate(&self) -> Result<()> {
+        self.validate_server_endpoints()?;
+
+        self.connection.validate(true)?;
+        self.network.validate_dns_split_domains()?;
+        self.network.validate_dns_protocol()?;
+        self.network.validate_dns_search_domains()?;
+        self.network.validate_dns_leak_protection()?;
+        self.network.validate_kill_switch()?;
+        self.network.validate_split_tunnel()?;
+
+        if let ClientProtocolConfig::Noise(noise) = &self.protocol {
+            noise.private_key()?;
+            if self.enable_0rtt {
+                return Err(ConfigError::Conflict {
+                    conflict: "enable_0rtt is only supported in TLS mode".to_string(),
+                }
+                .into());
+            }
+        }
+
+        if let ClientProtocolConfig::Tls(tls) = &self.protocol {
+            let has_trusted_certificates = !tls.trusted_certificate_paths.is_empty()
+                || !tls.trusted_certificate_dirs.is_empty()
+                || !tls.trusted_certificates.is_empty();
+            if !has_trusted_certificates {
+                return Err(ConfigError::MissingField {
+                    field: "protocol.trusted_certificate_paths".to_string(),
+                }
+                .into());
+            }
         }
+
+        Ok(())
     }
 
-    /// Builds a TLS-based Quinn client configuration with mutual authentication.
-    fn build_tls_client_config(&self, tls: &ClientTlsConfig) -> Result<quinn::ClientConfig> {
-        let mut cert_store = RootCertStore::empty();
+    /// Returns the connection strings of all endpoints of the Quincy server, in the order
+    /// they are tried.
+    ///
+    /// ### Returns
+    /// - `Vec<&str>` - `connection_string`, if set, followed by `connection_strings`
+    pub fn server_endpoints(&self) -> Vec<&str> {
+        Some(self.connection_string.as_str())
+            .filter(|connection_string| !connection_string.is_empty())
+            .into_iter()
+            .chain(self.connection_strings.iter().map(String::as_str))
+            .collect()
+    }
 
-        for cert_path in &tls.trusted_certificate_paths {
-            let certs = load_certificates_from_file(cert_path)?;
-            cert_store.add_parsable_certificates(certs);
+    /// Validates that at least one endpoint of the Quincy server resolves to an address.
+    ///
+    /// Other endpoints may not resolve yet, e.g. if their DNS records are only published
+    /// when failing over to them. Endpoints discovered through SRV records are only looked
+    /// up when connecting, so a well-formed `srv://` connection string counts as resolvable.
+    /// While a strict kill switch left engaged by an earlier run blocks DNS, the endpoints are
+    /// not resolved, as the client connects to the server addresses pinned by the kill switch.
+    ///
+    /// ### Errors
+    /// - `ConfigError::MissingField` - no connection string is configured
+    /// - `ConfigError::InvalidValue` - no connection string resolves to an address
+    fn validate_server_endpoints(&self) -> Result<()> {
+        let field = if self.connection_strings.is_empty() {
+            "connection_string"
+        } else {
+            "connection_strings"
+        };
+        let endpoints = self.server_endpoints();
+        if endpoints.is_empty() {
+            return Err(ConfigError::MissingField {
+                field: "connection_string".to_string(),
+            }
+            .into());
         }
 
-        for pem_data in &tls.trusted_certificates {
-            let certs = load_certificates_from_pem(pem_data)?;
-            cert_store.add_parsable_certificates(certs);
+        if self.network.kill_switch_strict
+            && load_pinned_endpoints(&pinned_endpoints_path()).is_some()
+        {
+            return Ok(());
         }
 
-        let client_certs = load_identity_certificates(
-            tls.client_certificate_file.as_ref(),
-            tls.client_certificate.as_deref(),
-            "protocol.client_certificate_file",
-            "protocol.client_certificate",
-        )?;
-        let client_key = load_identity_private_key(
-            tls.client_certificate_key_file.as_ref(),
-            tls.client_certificate_key
-                .as_ref()
-                .map(|key| key.expose_secret()),
-            "protocol.client_certificate_key_file",
-            "protocol.client_certificate_key",
-        )?;
-
-        let crypto_provider = Arc::from(tls_crypto_provider(&tls.key_exchange));
-
-        let mut rustls_config = rustls::ClientConfig::builder_with_provider(crypto_provider)
-            .with_protocol_versions(TLS_PROTOCOL_VERSIONS)?
-            .with_root_certificates(cert_store)
-            .with_client_auth_cert(client_certs, client_key)?;
+        let mut reasons = Vec::new();
+        for endpoint in endpoints {
+            if let Some(domain) = endpoint.strip_prefix(SRV_CONNECTION_SCHEME) {
+                if !domain.is_empty() && !domain.contains([':', '/']) {
+                    return Ok(());
+                }
+                reasons.push(format!("'{endpoint}' does not name a domain"));
+                continue;
+            }
 
-        rustls_config.alpn_protocols.clone_from(&TLS_ALPN_PROTOCOLS);
+            match endpoint.to_socket_addrs() {
+                Ok(mut addrs) if addrs.next().is_some() => return Ok(()),
+                Ok(_) => reasons.push(format!("'{endpoint}' resolves to no addresses")),
+                Err(e) => reasons.push(format!("cannot resolve '{endpoint}': {e}")),
+            }
+        }
 
-        let quic_client_config = QuicClientConfig::with_initial(
-            rustls_config.into(),
-            TLS_INITIAL_CIPHER_SUITE
-                .tls13()
-                .expect("QUIC initial suite is a valid TLS 1.3 suite")
-                .quic_suite()
-                .expect("QUIC initial suite is a valid QUIC suite"),
-        )
-        .map_err(|e| ConfigError::InvalidValue {
-            field: "quic_client_config".to_string(),
-            reason: format!("QUIC configuration creation failed: {e}"),
-        })?;
+        Err(ConfigError::InvalidValue {
+            field: field.to_string(),
+            reason: reasons.join(", "),
+        }
+        .into())
+    }
 
-        let transport_config = self.connection.as_transport_config(true)?;
-        let mut quinn_config = quinn::ClientConfig::new(Arc::new(quic_client_config));
-        quinn_config.transport_config(Arc::new(transport_config));
+    /// Creates a client configuration template with every setting at its default value.
+    ///
+    /// The connection string and the TLS trust and client credentials have no defaults
+    /// and are left empty for the user to fill in.
+    ///
+    /// ### Returns
+    /// - `String` - the configuration as commented TOML
+    pub fn default_template() -> String {
+        let config = ClientConfig {
+            config_version: CONFIG_VERSION,
+            connection_string: String::new(),
+            connection_strings: Vec::new(),
+            protocol: ClientProtocolConfig::Tls(ClientTlsConfig {
+                key_exchange: default_tls_key_exchange(),
+                alpn_protocols: Vec::new(),
+                cipher_suites: Vec::new(),
+                trusted_certificate_paths: Vec::new(),
+                trusted_certificate_dirs: Vec::new(),
+                trusted_certificates: Vec::new(),
+                client_certificate_file: None,
+                client_certificate: None,
+                client_certificate_key_file: None,
+                client_certificate_key: None,
+            }),
+            connection: ConnectionConfig::default(),
+            network: NetworkConfig::default(),
+            kx_hint_cache_path: None,
+            enable_0rtt: false,
+            log: LogConfig {
+                level: default_log_level(),
+                capture_file: None,
+                capture_max_bytes: default_capture_max_bytes(),
+            },
+        };
 
-        Ok(quinn_config)
+        config.to_commented_toml()
     }
 
-    /// Builds a Noise IK-based Quinn client configuration using the client's persistent keypair.
+    /// Renders this configuration as commented TOML.
+    ///
+    /// Secrets are never written; private keys are emitted as empty placeholders.
+    fn to_commented_toml(&self) -> String {
+        let protocol = match &self.protocol {
+            ClientProtocolConfig::Tls(tls) => format!(
+                r#"mode = "tls"
+# The TLS key exchange algorithm (standard, hybrid or post_quantum)
+key_exchange = "{key_exchange}"
+# ALPN protocol identifiers to negotiate; must match the server (default = ["quincy"])
+alpn_protocols = {alpn_protocols}
+# TLS 1.3 cipher suites to offer (default = AES-256-GCM and ChaCha20-Poly1305)
+cipher_suites = {cipher_suites}
+# Trusted server (CA) certificate files
+trusted_certificate_paths = {trusted_certificate_paths}
+# Directories whose *.pem and *.crt files are trusted
+trusted_certificate_dirs = {trusted_certificate_dirs}
+# Client certificate and private key files for mutual TLS authentication
+client_certificate_file = {client_certificate_file}
+client_certificate_key_file = {client_certificate_key_file}
+# Or the PEM-encoded client certificate and private key inline, instead of the files
+# client_certificate = ""
+# client_certificate_key = """#,
+                key_exchange = tls_key_exchange_name(&tls.key_exchange),
+                alpn_protocols = toml_array(&tls.alpn_protocols),
+                cipher_suites = toml_array(&tls.cipher_suites),
+                trusted_certificate_paths = toml_array(
+                    tls.trusted_certificate_paths
+                        .iter()
+                        .map(|path| path.display())
+                ),
+                trusted_certificate_dirs = toml_array(
+                    tls.trusted_certificate_dirs
+                        .iter()
+                        .map(|path| path.display())
+                ),
+                client_certificate_file = toml_path(tls.client_certificate_file.as_deref()),
+                client_certificate_key_file = toml_path(tls.client_certificate_key_file.as_deref()),
+            ),
+            ClientProtocolConfig::Noise(noise) => format!(
+                r#"mode = "noise"
+# The Noise key exchange algorithm (standard or hybrid)
+key_exchange = "{key_exchange}"
+# Base64-encoded server Noise public key
+server_public_key = "{server_public_key}"
+# Base64-encoded client Noise private key
+private_key = ""
+# Or a file containing the private key, instead of the inline key
+# private_key_file = """#,
+                key_exchange = noise_key_exchange_name(&noise.key_exchange),
+                server_public_key = noise.server_public_key,
+            ),
+        };
+
+        let connection = &self.connection;
+        let network = &self.network;
+
+        format!(
+            r#"# Version of the configuration layout
+config_version = {config_version}
+# The address and port the Quincy server is available at
+connection_string = "{connection_string}"
+# Further endpoints of the server, tried in order if the ones before are unavailable
+# connection_strings = ["quincy-backup:55555"]
+# Endpoints can also be discovered through the DNS SRV records of _quincy._udp.<domain>
+# connection_string = "srv://example.com"
+# Optional file remembering the key-exchange group of each server across restarts, saving a
+# round trip on the first handshake (TLS mode only). Session tickets are not persisted.
+# kx_hint_cache_path = "/var/cache/quincy/kx-hints.json"
+# Resume sessions with 0-RTT early data if the server allows it (TLS mode only)
+# enable_0rtt = true
+
+[protocol]
+{protocol}
+
+[connection]
+# The MTU used by the QUIC tunnel and the spawned TUN interface
+mtu = {mtu}
+# The congestion control algorithm (cubic, bbr or new_reno)
+congestion_controller = "{congestion_controller}"
+# BBR tuning, requires congestion_controller = "bbr"
+# bbr = {{ initial_window = 131072 }}
+# How tunnel packets are relayed (datagram or stream)
+transport_mode = "{transport_mode}"
+# Connection timeout in seconds
+connection_timeout_s = {connection_timeout_s}
+# Connection timeout in milliseconds; takes precedence over connection_timeout_s.
+# Keep-alives need an idle timeout above their interval, so pair it with max_idle_timeout_s
+# connection_timeout_ms = 750
+# Idle timeout of the established connection in seconds (default = connection timeout)
+# max_idle_timeout_s = 120
+# Keep alive interval in seconds (0 disables keep-alives)
+keep_alive_interval_s = {keep_alive_interval_s}
+# Interval in seconds of pings echoed by the server, proving that it still relays packets
+# app_ping_interval_s = 10
+# Deadline in seconds for the echo of a ping
+app_ping_timeout_s = {app_ping_timeout_s}
+# Close the connection after this many seconds without tunnel traffic, reconnecting on demand
+# idle_disconnect_s = 300
+# Re-establish a lost connection, retrying with exponential backoff
+auto_reconnect = {auto_reconnect}
+# Connection attempts after a lost connection before giving up (0 retries indefinitely)
+reconnect_max_attempts = {reconnect_max_attempts}
+# Delay in milliseconds before retrying a failed attempt, doubling up to one minute
+reconnect_backoff_ms = {reconnect_backoff_ms}
+# Round-trip time (ms) and packet loss (%) thresholds of the connection quality shown by the GUI
+# quality = {{ degraded_rtt_ms = 150, poor_rtt_ms = 400, degraded_loss_percent = 1.0, poor_loss_percent = 5.0 }}
+# Initial round-trip time estimate in milliseconds for high-latency links (Quinn default if unset)
+# initial_rtt_ms = 600
+# Socket send and receive buffer sizes in bytes
+send_buffer_size = {send_buffer_size}
+recv_buffer_size = {recv_buffer_size}
+# Number of tasks writing packets from the server to the TUN interface
+# receive_tasks = 4
+# Optional fixed local UDP port for the QUIC socket (ephemeral if unset)
+# local_port = 40000
+# Optional local address for the QUIC socket
+# local_address = "192.168.1.10"
+# Optional firewall mark of the QUIC socket's packets, for policy routing rules (Linux only)
+# socket_mark = 0x51
+# Optional DSCP value of the QUIC packets for QoS-aware networks, e.g. 46
+# dscp = 46
+# Maximum number of concurrent streams the server may open
+max_concurrent_bidi_streams = {max_concurrent_bidi_streams}
+max_concurrent_uni_streams = {max_concurrent_uni_streams}
+# QUIC flow control windows in bytes (Quinn defaults if unset)
+# stream_receive_window = 8388608
+# receive_window = 67108864
+# send_window = 33554432
+# Probe for a larger path MTU instead of pinning the QUIC MTU
+pmtud = {pmtud}
+# Use GSO/GRO segmentation offload on the TUN interface (Linux builds with the `offload` feature)
+offload = {offload}
+# Optional cap of the QUIC packet size sent with UDP segmentation offload, 0 disables it
+# gso_segment_size = 1280
+
+[network]
+# Routes to send through the VPN tunnel, e.g. ["0.0.0.0/0", "::/0"] for full-tunnel mode
+routes = {routes}
+# Networks routed around the tunnel, more specific than the routes above, e.g. ["192.168.0.0/16"]
+exclude_routes = {exclude_routes}
+# Whether all traffic outside of the tunnel is blocked while a default route is sent through it
+kill_switch = {kill_switch}
+# Only log the firewall changes of the kill switch instead of making them
+# kill_switch_dry_run = false
+# Block all traffic outside of the tunnel from connecting until the client is stopped, regardless
+# of the routes; a failed or crashed client leaves it engaged (lift it with
+# `quincy-client --lift-kill-switch`)
+# kill_switch_strict = false
+# User IDs whose traffic alone is sent through the tunnel routes (Linux only), e.g. [1000]
+split_tunnel_uids = {split_tunnel_uids}
+# Routing table holding the tunnel routes while split tunneling
+split_tunnel_table = {split_tunnel_table}
+# DNS servers to use for the tunnel
+dns_servers = {dns_servers}
+# Maximum number of DNS servers configured on the tunnel interface
+max_dns_servers = {max_dns_servers}
+# Optional domains resolved through the tunnel (split DNS), all other queries use the system resolver
+# dns_split_domains = [{{ domain = "corp.example.com", dns_servers = ["10.0.1.1"] }}]
+# Protocol used to reach the DNS servers: "plain", "dot" (DNS-over-TLS) or "doh" (DNS-over-HTTPS)
+dns_protocol = "{dns_protocol}"
+# Optional name the certificates of the DNS servers are verified against instead of their address
+# dns_tls_name = "dns.corp.example.com"
+# Optional certificates trusted to sign the certificates of the DNS servers
+# dns_trusted_certificate_paths = ["/etc/quincy/dns-ca.pem"]
+# Path of the DNS-over-HTTPS endpoint of the DNS servers
+# dns_doh_path = "/dns-query"
+# Whether DNS queries to servers other than the DNS servers above are blocked while connected
+dns_leak_protection = {dns_leak_protection}
+# Only log the firewall changes of DNS leak protection instead of making them
+# dns_leak_protection_dry_run = false
+# Domains appended to unqualified host names while connected
+dns_search_domains = {dns_search_domains}
+# Whether the routes, DNS servers and search domains pushed by the server are applied
+accept_pushed_config = {accept_pushed_config}
+# Optional name of the tunnel interface
+# interface_name = "quincy0"
+# IP families routed through the tunnel
+enabled_families = {enabled_families}
+# Whether Quincy installs the routes above
+manage_routes = {manage_routes}
+# Whether Quincy configures the system resolver with the DNS servers above
+manage_dns = {manage_dns}
+# Whether the tunnel interface is kept up across reconnects
+persistent = {persistent}
+# Whether the MSS of TCP connections through the tunnel is clamped to fit its MTU
+clamp_mss = {clamp_mss}
+
+[log]
+# The log level
+level = "{level}"
+# Optional pcap file capturing the tunnel packets (requires the `capture` feature)
+# capture_file = "quincy.pcap"
+"#,
+            config_version = self.config_version,
+            connection_string = self.connection_string,
+            mtu = connection.mtu,
+            congestion_controller = congestion_controller_name(&connection.congestion_controller),
+            transport_mode = transport_mode_name(connection.transport_mode),
+            connection_timeout_s = connection.connection_timeout_s,
+            keep_alive_interval_s = connection.keep_alive_interval_s,
+            app_ping_timeout_s = connection.app_ping_timeout_s,
+            auto_reconnect = connection.auto_reconnect,
+            reconnect_max_attempts = connection.reconnect_max_attempts,
+            reconnect_backoff_ms = connection.reconnect_backoff_ms,
+            send_buffer_size = connection.send_buffer_size,
+            recv_buffer_size = connection.recv_buffer_size,
+            max_concurrent_bidi_streams = connection.max_concurrent_bidi_streams,
+            max_concurrent_uni_streams = connection.max_concurrent_uni_streams,
+            pmtud = connection.pmtud,
+            offload = connection.offload,
+            routes = toml_routes(&network.routes),
+            exclude_routes = toml_array(&network.exclude_routes),
+            kill_switch = network.kill_switch,
+            split_tunnel_uids = format!("{:?}", network.split_tunnel_uids),
+            split_tunnel_table = network.split_tunnel_table,
+            dns_servers = toml_array(&network.dns_servers),
+            max_dns_servers = network.max_dns_servers,
+            dns_protocol = match network.dns_protocol {
+                DnsProtocol::Plain => "plain",
+                DnsProtocol::Dot => "dot",
+                DnsProtocol::Doh => "doh",
+            },
+            dns_leak_protection = network.dns_leak_protection,
+            dns_search_domains = toml_array(&network.dns_search_domains),
+            accept_pushed_config = network.accept_pushed_config,
+            enabled_families =
+                toml_array(network.enabled_families.iter().map(|family| match family {
+                    IpFamily::V4 => "ipv4",
+                    IpFamily::V6 => "ipv6",
+                })),
+            manage_routes = network.manage_routes,
+            manage_dns = network.manage_dns,
+            persistent = network.persistent,
+            clamp_mss = network.clamp_mss,
+            level = self.log.level,
+        )
+    }
+
+    /// Lists the settings that differ from `other` and only take effect after reconnecting.
+    ///
+    /// Routes and DNS servers are not included, as they can be applied to a live tunnel.
+    ///
+    /// ### Arguments
+    /// - `other` - the configuration to compare against
+    ///
+    /// ### Returns
+    /// - `Vec<&'static str>` - the names of the changed settings
+    pub fn restart_required_changes(&self, other: &ClientConfig) -> Vec<&'static str> {
+        let network = &self.network;
+        let other_network = &other.network;
+
+        [
+            (
+                "connection_string",
+                self.connection_string != other.connection_string,
+            ),
+            (
+                "connection_strings",
+                self.connection_strings != other.connection_strings,
+            ),
+            (
+                "protocol",
+                protocol_fingerprint(&self.protocol) != protocol_fingerprint(&other.protocol),
+            ),
+            ("connection", self.connection != other.connection),
+            (
+                "kx_hint_cache_path",
+                self.kx_hint_cache_path != other.kx_hint_cache_path,
+            ),
+            ("enable_0rtt", self.enable_0rtt != other.enable_0rtt),
+            (
+                "network.interface_name",
+                network.interface_name != other_network.interface_name,
+            ),
+            (
+                "network.manage_routes",
+                network.manage_routes != other_network.manage_routes,
+            ),
+            (
+                "network.exclude_routes",
+                network.exclude_routes != other_network.exclude_routes,
+            ),
+            (
+                "network.kill_switch",
+                network.kill_switch != other_network.kill_switch
+                    || network.kill_switch_dry_run != other_network.kill_switch_dry_run
+                    || network.kill_switch_strict != other_network.kill_switch_strict,
+            ),
+            (
+                "network.split_tunnel_uids",
+                network.split_tunnel_uids != other_network.split_tunnel_uids
+                    || network.split_tunnel_table != other_network.split_tunnel_table,
+            ),
+            (
+                "network.manage_dns",
+                network.manage_dns != other_network.manage_dns,
+            ),
+            (
+                "network.persistent",
+                network.persistent != other_network.persistent,
+            ),
+            (
+                "network.dns_split_domains",
+                network.dns_split_domains != other_network.dns_split_domains,
+            ),
+            (
+                "network.dns_protocol",
+                network.dns_protocol != other_network.dns_protocol,
+            ),
+            (
+                "network.dns_tls_name",
+                network.dns_tls_name != other_network.dns_tls_name,
+            ),
+            (
+                "network.dns_trusted_certificate_paths",
+                network.dns_trusted_certificate_paths
+                    != other_network.dns_trusted_certificate_paths,
+            ),
+            (
+                "network.dns_doh_path",
+                network.dns_doh_path != other_network.dns_doh_path,
+            ),
+            (
+                "network.dns_search_domains",
+                network.dns_search_domains != other_network.dns_search_domains,
+            ),
+            (
+                "network.accept_pushed_config",
+                network.accept_pushed_config != other_network.accept_pushed_config,
+            ),
+            (
+                "network.dns_leak_protection",
+                network.dns_leak_protection != other_network.dns_leak_protection
+                    || network.dns_leak_protection_dry_run
+                        != other_network.dns_leak_protection_dry_run,
+            ),
+            (
+                "network.clamp_mss",
+                network.clamp_mss != other_network.clamp_mss,
+            ),
+        ]
+        .into_iter()
+        .filter_map(|(field, changed)| changed.then_some(field))
+        .collect()
+    }
+
+    /// Returns this configuration with the settings of `other` that can be applied to a live
+    /// tunnel, i.e. all settings not listed by [`ClientConfig::restart_required_changes`].
+    ///
+    /// ### Arguments
+    /// - `other` - the configuration to take the live settings from
+    pub fn with_reloadable_settings(&self, other: &ClientConfig) -> ClientConfig {
+        let mut config = self.clone();
+        config.config_version = other.config_version;
+        config.network.routes = other.network.routes.clone();
+        config.network.dns_servers = other.network.dns_servers.clone();
+        config.network.max_dns_servers = other.network.max_dns_servers;
+        config.network.enabled_families = other.network.enabled_families.clone();
+        config.log = other.log.clone();
+
+        config
+    }
+
+    /// Creates Quinn client configuration from this Quincy client configuration.
+    ///
+    /// ### Returns
+    /// - `quinn::ClientConfig` - the Quinn client configuration
+    pub fn quinn_client_config(&self) -> Result<quinn::ClientConfig> {
+        match &self.protocol {
+            ClientProtocolConfig::Tls(tls) => self.build_tls_client_config(tls),
+            ClientProtocolConfig::Noise(noise) => self.build_noise_client_config(noise),
+        }
+    }
+
+    /// Builds a TLS-based Quinn client configuration with mutual authentication.
+    fn build_tls_client_config(&self, tls: &ClientTlsConfig) -> Result<quinn::ClientConfig> {
+        let mut cert_store = RootCertStore::empty();
+
+        for cert_path in &tls.trusted_certificate_paths {
+            let certs = load_certificates_from_file(cert_path)?;
+            cert_store.add_parsable_certificates(certs);
+        }
+
+        for cert_dir in &tls.trusted_certificate_dirs {
+            let certs = load_certificates_from_dir(cert_dir)?;
+            cert_store.add_parsable_certificates(certs);
+        }
+
+        for pem_data in &tls.trusted_certificates {
+            let certs = load_certificates_from_pem(pem_data)?;
+            cert_store.add_parsable_certificates(certs);
+        }
+
+        let client_certs = load_identity_certificates(
+            tls.client_certificate_file.as_ref(),
+            tls.client_certificate.as_deref(),
+            "protocol.client_certificate_file",
+            "protocol.client_certificate",
+        )?;
+        let client_key = load_identity_private_key(
+            tls.client_certificate_key_file.as_ref(),
+            tls.client_certificate_key
+                .as_ref()
+                .map(|key| key.expose_secret()),
+            "protocol.client_certificate_key_file",
+            "protocol.client_certificate_key",
+        )?;
+
+        let crypto_provider =
+            Arc::from(tls_crypto_provider(&tls.key_exchange, &tls.cipher_suites)?);
+
+        let mut rustls_config = rustls::ClientConfig::builder_with_provider(crypto_provider)
+            .with_protocol_versions(TLS_PROTOCOL_VERSIONS)?
+            .with_root_certificates(cert_store)
+            .with_client_auth_cert(client_certs, client_key)?;
+
+        rustls_config.alpn_protocols = alpn_protocol_ids(&tls.alpn_protocols)?;
+        rustls_config.enable_early_data = self.enable_0rtt;
+
+        if let Some(kx_hint_cache_path) = &self.kx_hint_cache_path {
+            rustls_config.resumption =
+                Resumption::store(Arc::new(KxHintCache::load(kx_hint_cache_path)));
+        }
+
+        let quic_client_config = QuicClientConfig::with_initial(
+            rustls_config.into(),
+            TLS_INITIAL_CIPHER_SUITE
+                .tls13()
+                .expect("QUIC initial suite is a valid TLS 1.3 suite")
+                .quic_suite()
+                .expect("QUIC initial suite is a valid QUIC suite"),
+        )
+        .map_err(|e| ConfigError::InvalidValue {
+            field: "quic_client_config".to_string(),
+            reason: format!("QUIC configuration creation failed: {e}"),
+        })?;
+
+        let transport_config = self.connection.as_transport_config(true)?;
+        let mut quinn_config = quinn::ClientConfig::new(Arc::new(quic_client_config));
+        quinn_config.transport_config(Arc::new(transport_config));
+
+        Ok(quinn_config)
+    }
+
+    /// Builds a Noise IK-based Quinn client configuration using the client's persistent keypair.
     fn build_noise_client_config(&self, noise: &ClientNoiseConfig) -> Result<quinn::ClientConfig> {
+        let private_key = noise.private_key()?;
+
         let mut quinn_config = match noise.key_exchange {
             NoiseKeyExchange::Standard => {
                 let server_pub_bytes =
@@ -974,7 +2811,7 @@ impl ClientConfig {
                 let server_public = PublicKey::from_bytes(*server_pub_bytes);
 
                 let secret_bytes =
-                    decode_base64_key::<{ StaticSecret::LEN }>(noise.private_key.expose_secret())?;
+                    decode_base64_key::<{ StaticSecret::LEN }>(private_key.expose_secret())?;
                 let local_keypair = KeyPair::from_secret_bytes(&secret_bytes);
 
                 let client_config = NoiseConfigBuilder::new(local_keypair)
@@ -993,9 +2830,8 @@ impl ClientConfig {
                     decode_base64_key::<{ PqPublicKey::LEN }>(&noise.server_public_key)?;
                 let server_public = PqPublicKey::from_bytes(*server_pub_bytes);
 
-                let secret_bytes = decode_base64_key::<{ PqStaticSecret::LEN }>(
-                    noise.private_key.expose_secret(),
-                )?;
+                let secret_bytes =
+                    decode_base64_key::<{ PqStaticSecret::LEN }>(private_key.expose_secret())?;
                 let local_keypair = PqKeyPair::from_secret_bytes(&secret_bytes);
 
                 let client_config = PqNoiseConfigBuilder::new(local_keypair)
@@ -1028,7 +2864,139 @@ impl ClientConfig {
 
 // --- Server config builders ---
 
+/// Renders values as a TOML array of strings.
+fn toml_array<T: fmt::Display>(values: impl IntoIterator<Item = T>) -> String {
+    let values: Vec<String> = values
+        .into_iter()
+        .map(|value| format!("{:?}", value.to_string()))
+        .collect();
+
+    format!("[{}]", values.join(", "))
+}
+
+/// Renders routes as a TOML array, using inline tables for routes with a metric.
+fn toml_routes(routes: &[RouteSpec]) -> String {
+    let routes: Vec<String> = routes
+        .iter()
+        .map(|route| match route.metric {
+            Some(metric) => format!("{{ net = {:?}, metric = {metric} }}", route.net.to_string()),
+            None => format!("{:?}", route.net.to_string()),
+        })
+        .collect();
+
+    format!("[{}]", routes.join(", "))
+}
+
+/// Renders an optional path as a TOML string, using an empty string when unset.
+fn toml_path(path: Option<&Path>) -> String {
+    format!(
+        "{:?}",
+        path.map(|path| path.display().to_string())
+            .unwrap_or_default()
+    )
+}
+
+/// Returns the configuration name of a TLS key exchange algorithm.
+fn tls_key_exchange_name(key_exchange: &TlsKeyExchange) -> &'static str {
+    match key_exchange {
+        TlsKeyExchange::Standard => "standard",
+        TlsKeyExchange::Hybrid => "hybrid",
+        TlsKeyExchange::PostQuantum => "post_quantum",
+    }
+}
+
+/// Returns the configuration name of a Noise key exchange algorithm.
+fn noise_key_exchange_name(key_exchange: &NoiseKeyExchange) -> &'static str {
+    match key_exchange {
+        NoiseKeyExchange::Standard => "standard",
+        NoiseKeyExchange::Hybrid => "hybrid",
+    }
+}
+
+/// Returns the configuration name of a transport mode.
+fn transport_mode_name(transport_mode: TransportMode) -> &'static str {
+    match transport_mode {
+        TransportMode::Datagram => "datagram",
+        TransportMode::Stream => "stream",
+    }
+}
+
+/// Returns the configuration name of a congestion control algorithm.
+fn congestion_controller_name(congestion_controller: &CongestionController) -> &'static str {
+    match congestion_controller {
+        CongestionController::Cubic => "cubic",
+        CongestionController::Bbr => "bbr",
+        CongestionController::NewReno => "new_reno",
+    }
+}
+
+/// Renders a client protocol configuration for comparison, including its secrets.
+fn protocol_fingerprint(protocol: &ClientProtocolConfig) -> String {
+    let secret = match protocol {
+        ClientProtocolConfig::Tls(tls) => tls
+            .client_certificate_key
+            .as_ref()
+            .map(|key| key.expose_secret().to_string()),
+        ClientProtocolConfig::Noise(noise) => noise
+            .private_key
+            .as_ref()
+            .map(|key| key.expose_secret().to_string()),
+    };
+
+    format!("{protocol:?}{secret:?}")
+}
+
 impl ServerConfig {
+    /// Validates the configuration, reporting misconfiguration before starting the server.
+    ///
+    /// ### Errors
+    /// - `ConfigError::InvalidValue` - the MTU or an authentication lockout setting is out of range
+    /// - `ConfigError::Conflict` - conflicting timeouts or lockout durations are configured,
+    ///   both tunnel networks belong to the same IP family, or 0-RTT is enabled in Noise mode
+    /// - `ConfigError::MissingField` - no certificate or private key is configured (TLS mode)
+    pub fn validate(&self) -> Result<()> {
+        self.connection.validate(false)?;
+        self.auth_lockout.validate()?;
+
+        if self.enable_0rtt && matches!(self.protocol, ServerProtocolConfig::Noise(_)) {
+            return Err(ConfigError::Conflict {
+                conflict: "enable_0rtt is only supported in TLS mode".to_string(),
+            }
+            .into());
+        }
+        validate_search_domains("push.dns_search_domains", &self.push.dns_search_domains)?;
+
+        if let Some(secondary) = self.secondary_tunnel_network {
+            if IpFamily::of(&secondary.addr()) == IpFamily::of(&self.tunnel_network.addr()) {
+                return Err(ConfigError::Conflict {
+                    conflict: format!(
+                        "secondary_tunnel_network {secondary} must be of a different IP family \
+                         than tunnel_network {}",
+                        self.tunnel_network
+                    ),
+                }
+                .into());
+            }
+        }
+
+        if let ServerProtocolConfig::Tls(tls) = &self.protocol {
+            if tls.certificate_file.is_none() && tls.certificate.is_none() {
+                return Err(ConfigError::MissingField {
+                    field: "protocol.certificate_file".to_string(),
+                }
+                .into());
+            }
+            if tls.certificate_key_file.is_none() && tls.certificate_key.is_none() {
+                return Err(ConfigError::MissingField {
+                    field: "protocol.certificate_key_file".to_string(),
+                }
+                .into());
+            }
+        }
+
+        Ok(())
+    }
+
     /// Creates Quinn server configuration from this Quincy tunnel configuration.
     ///
     /// ### Arguments
@@ -1077,7 +3045,8 @@ impl ServerConfig {
             "protocol.certificate",
         )?;
 
-        let crypto_provider = Arc::from(tls_crypto_provider(&tls.key_exchange));
+        let crypto_provider =
+            Arc::from(tls_crypto_provider(&tls.key_exchange, &tls.cipher_suites)?);
 
         let verifier = Arc::new(crate::certificates::QuincyCertVerifier::new(
             allowed_fingerprints,
@@ -1089,8 +3058,9 @@ impl ServerConfig {
             .with_client_cert_verifier(verifier)
             .with_single_cert(certs, key)?;
 
-        rustls_config.alpn_protocols.clone_from(&TLS_ALPN_PROTOCOLS);
-        rustls_config.max_early_data_size = 0;
+        rustls_config.alpn_protocols = alpn_protocol_ids(&tls.alpn_protocols)?;
+        // QUIC requires early data to be either disabled or unlimited (RFC 9001 §4.6.1)
+        rustls_config.max_early_data_size = if self.enable_0rtt { u32::MAX } else { 0 };
 
         let quic_server_config = QuicServerConfig::with_initial(
             rustls_config.into(),
@@ -1197,7 +3167,94 @@ impl ServerConfig {
 
 // --- Endpoint config ---
 
+/// Flow control windows of a connection, `None` keeping the QUIC default.
+#[derive(Debug, PartialEq, Eq)]
+struct FlowControlWindows {
+    stream_receive: Option<VarInt>,
+    receive: Option<VarInt>,
+    send: Option<u64>,
+}
+
 impl ConnectionConfig {
+    /// Validates the connection configuration.
+    ///
+    /// ### Arguments
+    /// - `set_keep_alive` - whether keep-alives are sent (typically true for clients)
+    ///
+    /// ### Errors
+    /// - `ConfigError::InvalidValue` - the MTU, application ping interval or timeout, idle
+    ///   disconnect period, reconnect backoff, number of receive tasks or DSCP value is out
+    ///   of range
+    /// - `ConfigError::Conflict` - conflicting timeouts are configured, the keep-alive
+    ///   interval is not below the idle timeout, or BBR tuning is set for another
+    ///   congestion controller
+    pub fn validate(&self, set_keep_alive: bool) -> Result<()> {
+        if !MTU_RANGE.contains(&self.mtu) {
+            return Err(ConfigError::InvalidValue {
+                field: "mtu".to_string(),
+                reason: format!(
+                    "MTU must be between {} and {}",
+                    MTU_RANGE.start(),
+                    MTU_RANGE.end()
+                ),
+            }
+            .into());
+        }
+
+        let (timeout_field, idle_timeout) = self.idle_timeout()?;
+        if set_keep_alive {
+            self.keep_alive_interval(timeout_field, idle_timeout)?;
+        }
+
+        self.initial_rtt()?;
+        self.validate_gso_segment_size()?;
+        self.validate_bbr()?;
+        self.validate_quality()?;
+        self.app_ping()?;
+
+        if self.receive_tasks == 0 {
+            return Err(ConfigError::InvalidValue {
+                field: "receive_tasks".to_string(),
+                reason: "number of receive tasks must be nonzero".to_string(),
+            }
+            .into());
+        }
+
+        if self.idle_disconnect_s == Some(0) {
+            return Err(ConfigError::InvalidValue {
+                field: "idle_disconnect_s".to_string(),
+                reason: "idle disconnect period must be nonzero".to_string(),
+            }
+            .into());
+        }
+
+        if self.reconnect_backoff_ms == 0 {
+            return Err(ConfigError::InvalidValue {
+                field: "reconnect_backoff_ms".to_string(),
+                reason: "reconnect backoff must be nonzero".to_string(),
+            }
+            .into());
+        }
+
+        if let Some(dscp) = self.dscp.filter(|dscp| *dscp > MAX_DSCP) {
+            return Err(ConfigError::InvalidValue {
+                field: "dscp".to_string(),
+                reason: format!("DSCP value {dscp} must be between 0 and {MAX_DSCP}"),
+            }
+            .into());
+        }
+
+        Ok(())
+    }
+
+    /// Returns the options applied to the client QUIC socket.
+    pub fn socket_options(&self) -> SocketOptions {
+        SocketOptions {
+            mark: self.socket_mark,
+            dscp: self.dscp,
+        }
+    }
+
     /// Creates a Quinn endpoint configuration.
     ///
     /// For Noise protocol mode, the endpoint uses Noise-specific HMAC keys and
@@ -1240,61 +3297,429 @@ impl ConnectionConfig {
     pub fn as_transport_config(&self, set_keep_alive: bool) -> Result<TransportConfig> {
         let mut transport_config = TransportConfig::default();
 
-        transport_config.max_idle_timeout(Some(
-            Duration::from_secs(self.connection_timeout_s)
-                .try_into()
-                .map_err(|e| ConfigError::InvalidValue {
-                    field: "connection_timeout_s".to_string(),
-                    reason: format!("timeout value out of bounds: {e}"),
-                })?,
-        ));
+        let (timeout_field, idle_timeout) = self.idle_timeout()?;
+        transport_config.max_idle_timeout(Some(idle_timeout.try_into().map_err(|e| {
+            ConfigError::InvalidValue {
+                field: timeout_field.to_string(),
+                reason: format!("timeout value out of bounds: {e}"),
+            }
+        })?));
         if set_keep_alive {
             transport_config
-                .keep_alive_interval(Some(Duration::from_secs(self.keep_alive_interval_s)));
+                .keep_alive_interval(self.keep_alive_interval(timeout_field, idle_timeout)?);
         }
-        let mtu = self.mtu_with_overhead()?;
+        if let Some(initial_rtt) = self.initial_rtt()? {
+            transport_config.initial_rtt(initial_rtt);
+        }
+        let mtu = self.quic_mtu()?;
         transport_config.initial_mtu(mtu);
-        transport_config.min_mtu(mtu);
+        if self.pmtud {
+            let mut mtu_discovery = MtuDiscoveryConfig::default();
+            if let Some(segment_size) = self.gso_segment_size.filter(|size| *size > 0) {
+                mtu_discovery.upper_bound(segment_size);
+            }
+            transport_config.mtu_discovery_config(Some(mtu_discovery));
+        } else {
+            transport_config.min_mtu(mtu);
+        }
+        transport_config.enable_segmentation_offload(self.gso_segment_size != Some(0));
         transport_config.congestion_controller_factory(self.congestion_controller_factory());
 
+        let (bidi_streams, uni_streams) = self.stream_limits()?;
+        transport_config.max_concurrent_bidi_streams(bidi_streams);
+        transport_config.max_concurrent_uni_streams(uni_streams);
+
+        let windows = self.flow_control_windows()?;
+        if let Some(window) = windows.stream_receive {
+            transport_config.stream_receive_window(window);
+        }
+        if let Some(window) = windows.receive {
+            transport_config.receive_window(window);
+        }
+        if let Some(window) = windows.send {
+            transport_config.send_window(window);
+        }
+
         Ok(transport_config)
     }
 
-    /// Returns the MTU with QUIC overhead added.
-    pub fn mtu_with_overhead(&self) -> Result<u16> {
-        self.mtu.checked_add(QUIC_MTU_OVERHEAD).ok_or_else(|| {
-            ConfigError::InvalidValue {
-                field: "mtu".to_string(),
-                reason: format!(
-                    "MTU value {} overflows when adding QUIC overhead of {}",
-                    self.mtu, QUIC_MTU_OVERHEAD
-                ),
-            }
-            .into()
-        })
+    /// Returns the limits of concurrent bidirectional and unidirectional streams.
+    ///
+    /// ### Errors
+    /// - `ConfigError::InvalidValue` - a limit is zero
+    fn stream_limits(&self) -> Result<(VarInt, VarInt)> {
+        Ok((
+            Self::stream_limit(
+                "max_concurrent_bidi_streams",
+                self.max_concurrent_bidi_streams,
+            )?,
+            Self::stream_limit(
+                "max_concurrent_uni_streams",
+                self.max_concurrent_uni_streams,
+            )?,
+        ))
     }
 
-    /// Returns the congestion controller factory for this configuration.
-    pub fn congestion_controller_factory(
-        &self,
-    ) -> Arc<dyn quinn::congestion::ControllerFactory + Send + Sync> {
-        let config: Box<dyn quinn::congestion::ControllerFactory + Send + Sync> = match self
-            .congestion_controller
-        {
-            CongestionController::Cubic => Box::new(quinn::congestion::CubicConfig::default()),
-            CongestionController::Bbr => Box::new(quinn::congestion::BbrConfig::default()),
-            CongestionController::NewReno => Box::new(quinn::congestion::NewRenoConfig::default()),
+    /// Returns the configured flow control windows.
+    ///
+    /// ### Errors
+    /// - `ConfigError::InvalidValue` - a window is zero or too large
+    fn flow_control_windows(&self) -> Result<FlowControlWindows> {
+        let stream_receive = self
+            .stream_receive_window
+            .map(|window| {
+                Self::flow_control_window("stream_receive_window", window, u32::MAX.into())
+            })
+            .transpose()?;
+        let receive = self
+            .receive_window
+            .map(|window| {
+                Self::flow_control_window("receive_window", window, VarInt::MAX.into_inner())
+            })
+            .transpose()?;
+        let send = match self.send_window {
+            Some(0) => {
+                return Err(ConfigError::InvalidValue {
+                    field: "send_window".to_string(),
+                    reason: "flow control window must be nonzero".to_string(),
+                }
+                .into());
+            }
+            window => window,
         };
 
-        Arc::from(config)
+        Ok(FlowControlWindows {
+            stream_receive,
+            receive,
+            send,
+        })
     }
-}
 
-// --- Helpers ---
+    /// Validates a flow control window and converts it to a QUIC variable-length integer.
+    ///
+    /// ### Arguments
+    /// - `field` - the name of the configuration field
+    /// - `window` - the configured window size in bytes
+    /// - `max_window` - the largest accepted window size in bytes
+    fn flow_control_window(field: &str, window: u64, max_window: u64) -> Result<VarInt> {
+        if window == 0 || window > max_window {
+            return Err(ConfigError::InvalidValue {
+                field: field.to_string(),
+                reason: format!("flow control window must be between 1 and {max_window}"),
+            }
+            .into());
+        }
 
-/// Decodes a base64-encoded key and validates its length.
-pub fn decode_base64_key<const KEY_LEN: usize>(encoded: &str) -> Result<Zeroizing<[u8; KEY_LEN]>> {
-    let mut key_bytes = Zeroizing::new([0u8; KEY_LEN]);
+        VarInt::from_u64(window).map_err(|e| {
+            ConfigError::InvalidValue {
+                field: field.to_string(),
+                reason: format!("flow control window out of bounds: {e}"),
+            }
+            .into()
+        })
+    }
+
+    /// Returns the keep-alive interval, checking that it is below the idle timeout.
+    ///
+    /// ### Arguments
+    /// - `timeout_field` - the name of the field the idle timeout was taken from
+    /// - `idle_timeout` - the idle timeout of connections
+    ///
+    /// ### Returns
+    /// - `Option<Duration>` - the keep-alive interval, or `None` if keep-alives are disabled
+    fn keep_alive_interval(
+        &self,
+        timeout_field: &str,
+        idle_timeout: Duration,
+    ) -> Result<Option<Duration>> {
+        if self.keep_alive_interval_s == 0 {
+            return Ok(None);
+        }
+
+        let keep_alive_interval = Duration::from_secs(self.keep_alive_interval_s);
+        if keep_alive_interval >= idle_timeout {
+            return Err(ConfigError::Conflict {
+                conflict: format!("keep_alive_interval_s must be less than {timeout_field}"),
+            }
+            .into());
+        }
+
+        Ok(Some(keep_alive_interval))
+    }
+
+    /// Returns the interval and deadline of the application keep-alive pings, if enabled.
+    ///
+    /// ### Returns
+    /// - `Option<(Duration, Duration)>` - the ping interval and echo deadline
+    ///
+    /// ### Errors
+    /// - `ConfigError::InvalidValue` - the interval or deadline is zero
+    pub fn app_ping(&self) -> Result<Option<(Duration, Duration)>> {
+        let Some(interval_s) = self.app_ping_interval_s else {
+            return Ok(None);
+        };
+
+        for (field, value) in [
+            ("app_ping_interval_s", interval_s),
+            ("app_ping_timeout_s", self.app_ping_timeout_s),
+        ] {
+            if value == 0 {
+                return Err(ConfigError::InvalidValue {
+                    field: field.to_string(),
+                    reason: "application ping interval and timeout must be nonzero".to_string(),
+                }
+                .into());
+            }
+        }
+
+        Ok(Some((
+            Duration::from_secs(interval_s),
+            Duration::from_secs(self.app_ping_timeout_s),
+        )))
+    }
+
+    /// Returns the configured initial RTT estimate, if any.
+    ///
+    /// ### Errors
+    /// - `ConfigError::InvalidValue` - the estimate is outside of 1 to 10000 ms
+    fn initial_rtt(&self) -> Result<Option<Duration>> {
+        let Some(initial_rtt_ms) = self.initial_rtt_ms else {
+            return Ok(None);
+        };
+
+        if !INITIAL_RTT_MS_RANGE.contains(&initial_rtt_ms) {
+            return Err(ConfigError::InvalidValue {
+                field: "initial_rtt_ms".to_string(),
+                reason: format!(
+                    "initial RTT must be between {} and {} ms",
+                    INITIAL_RTT_MS_RANGE.start(),
+                    INITIAL_RTT_MS_RANGE.end()
+                ),
+            }
+            .into());
+        }
+
+        Ok(Some(Duration::from_millis(initial_rtt_ms)))
+    }
+
+    /// Returns the deadline for establishing a connection.
+    ///
+    /// `connection_timeout_ms` takes precedence over `connection_timeout_s` when set.
+    ///
+    /// ### Errors
+    /// - `ConfigError::Conflict` - both `connection_timeout_ms` and a non-default
+    ///   `connection_timeout_s` are set
+    pub fn connection_timeout(&self) -> Result<Duration> {
+        self.connection_timeout_setting()
+            .map(|(_, timeout)| timeout)
+    }
+
+    /// Returns the idle timeout along with the name of the field it was taken from.
+    ///
+    /// Falls back to the connection timeout when `max_idle_timeout_s` is not set.
+    fn idle_timeout(&self) -> Result<(&'static str, Duration)> {
+        let connection_timeout = self.connection_timeout_setting()?;
+
+        Ok(match self.max_idle_timeout_s {
+            Some(timeout_s) => ("max_idle_timeout_s", Duration::from_secs(timeout_s)),
+            None => connection_timeout,
+        })
+    }
+
+    /// Returns the connection timeout along with the name of the field it was taken from.
+    fn connection_timeout_setting(&self) -> Result<(&'static str, Duration)> {
+        match self.connection_timeout_ms {
+            Some(_) if self.connection_timeout_s != default_timeout_s() => {
+                Err(ConfigError::Conflict {
+                    conflict: "connection_timeout_s and connection_timeout_ms are both set"
+                        .to_string(),
+                }
+                .into())
+            }
+            Some(timeout_ms) => Ok(("connection_timeout_ms", Duration::from_millis(timeout_ms))),
+            None => Ok((
+                "connection_timeout_s",
+                Duration::from_secs(self.connection_timeout_s),
+            )),
+        }
+    }
+
+    /// Validates a concurrent stream limit and converts it to a QUIC variable-length integer.
+    fn stream_limit(field: &str, limit: u32) -> Result<VarInt> {
+        if limit == 0 {
+            return Err(ConfigError::InvalidValue {
+                field: field.to_string(),
+                reason: "stream limit must be nonzero".to_string(),
+            }
+            .into());
+        }
+
+        Ok(VarInt::from_u32(limit))
+    }
+
+    /// Returns the size of the QUIC packets, the MTU with overhead capped by the GSO segment size.
+    fn quic_mtu(&self) -> Result<u16> {
+        let mtu = self.mtu_with_overhead()?;
+
+        Ok(match self.gso_segment_size {
+            Some(segment_size) if segment_size > 0 => mtu.min(segment_size),
+            _ => mtu,
+        })
+    }
+
+    /// Validates the BBR tuning.
+    ///
+    /// ### Errors
+    /// - `ConfigError::Conflict` - BBR tuning is set for another congestion controller
+    /// - `ConfigError::InvalidValue` - the initial window is zero
+    fn validate_bbr(&self) -> Result<()> {
+        let Some(bbr) = &self.bbr else {
+            return Ok(());
+        };
+
+        if self.congestion_controller != CongestionController::Bbr {
+            return Err(ConfigError::Conflict {
+                conflict: format!(
+                    "[connection.bbr] requires congestion_controller = \"bbr\", got \"{}\"",
+                    congestion_controller_name(&self.congestion_controller)
+                ),
+            }
+            .into());
+        }
+
+        if bbr.initial_window == Some(0) {
+            return Err(ConfigError::InvalidValue {
+                field: "bbr.initial_window".to_string(),
+                reason: "initial window must be nonzero".to_string(),
+            }
+            .into());
+        }
+
+        Ok(())
+    }
+
+    /// Validates the connection quality thresholds.
+    ///
+    /// ### Errors
+    /// Returns `ConfigError::InvalidValue` if the sample interval is zero, a loss threshold
+    /// is not a percentage, or a degraded threshold exceeds the poor one.
+    fn validate_quality(&self) -> Result<()> {
+        let quality = &self.quality;
+
+        if quality.sample_interval_s == 0 {
+            return Err(ConfigError::InvalidValue {
+                field: "quality.sample_interval_s".to_string(),
+                reason: "sample interval must be nonzero".to_string(),
+            }
+            .into());
+        }
+
+        for (field, loss_percent) in [
+            (
+                "quality.degraded_loss_percent",
+                quality.degraded_loss_percent,
+            ),
+            ("quality.poor_loss_percent", quality.poor_loss_percent),
+        ] {
+            if !(0.0..=100.0).contains(&loss_percent) {
+                return Err(ConfigError::InvalidValue {
+                    field: field.to_string(),
+                    reason: format!("loss threshold {loss_percent} must be between 0 and 100"),
+                }
+                .into());
+            }
+        }
+
+        if quality.degraded_rtt_ms > quality.poor_rtt_ms {
+            return Err(ConfigError::InvalidValue {
+                field: "quality.degraded_rtt_ms".to_string(),
+                reason: format!(
+                    "degraded threshold {} ms exceeds the poor threshold {} ms",
+                    quality.degraded_rtt_ms, quality.poor_rtt_ms
+                ),
+            }
+            .into());
+        }
+
+        if quality.degraded_loss_percent > quality.poor_loss_percent {
+            return Err(ConfigError::InvalidValue {
+                field: "quality.degraded_loss_percent".to_string(),
+                reason: format!(
+                    "degraded threshold {}% exceeds the poor threshold {}%",
+                    quality.degraded_loss_percent, quality.poor_loss_percent
+                ),
+            }
+            .into());
+        }
+
+        Ok(())
+    }
+
+    /// Validates the GSO segment size.
+    ///
+    /// ### Errors
+    /// Returns `SocketError::ConfigFailed` if the segment size is below the QUIC minimum or
+    /// exceeds the MTU plus the QUIC overhead.
+    fn validate_gso_segment_size(&self) -> Result<()> {
+        let Some(segment_size) = self.gso_segment_size.filter(|size| *size > 0) else {
+            return Ok(());
+        };
+
+        let max_segment_size = self.mtu_with_overhead()?;
+        if (QUIC_MIN_UDP_PAYLOAD_SIZE..=max_segment_size).contains(&segment_size) {
+            return Ok(());
+        }
+
+        Err(SocketError::ConfigFailed {
+            option: format!(
+                "gso_segment_size {segment_size} must be 0 or between \
+                 {QUIC_MIN_UDP_PAYLOAD_SIZE} and {max_segment_size}"
+            ),
+        }
+        .into())
+    }
+
+    /// Returns the MTU with QUIC overhead added.
+    pub fn mtu_with_overhead(&self) -> Result<u16> {
+        self.mtu.checked_add(QUIC_MTU_OVERHEAD).ok_or_else(|| {
+            ConfigError::InvalidValue {
+                field: "mtu".to_string(),
+                reason: format!(
+                    "MTU value {} overflows when adding QUIC overhead of {}",
+                    self.mtu, QUIC_MTU_OVERHEAD
+                ),
+            }
+            .into()
+        })
+    }
+
+    /// Returns the congestion controller factory for this configuration.
+    pub fn congestion_controller_factory(
+        &self,
+    ) -> Arc<dyn quinn::congestion::ControllerFactory + Send + Sync> {
+        let config: Box<dyn quinn::congestion::ControllerFactory + Send + Sync> = match self
+            .congestion_controller
+        {
+            CongestionController::Cubic => Box::new(quinn::congestion::CubicConfig::default()),
+            CongestionController::Bbr => {
+                let mut bbr_config = quinn::congestion::BbrConfig::default();
+                if let Some(initial_window) = self.bbr.as_ref().and_then(|bbr| bbr.initial_window) {
+                    bbr_config.initial_window(initial_window);
+                }
+                Box::new(bbr_config)
+            }
+            CongestionController::NewReno => Box::new(quinn::congestion::NewRenoConfig::default()),
+        };
+
+        Arc::from(config)
+    }
+}
+
+// --- Helpers ---
+
+/// Decodes a base64-encoded key and validates its length.
+pub fn decode_base64_key<const KEY_LEN: usize>(encoded: &str) -> Result<Zeroizing<[u8; KEY_LEN]>> {
+    let mut key_bytes = Zeroizing::new([0u8; KEY_LEN]);
 
     let decoded_bytes = BASE64_STANDARD
         .decode_slice(encoded.trim(), &mut *key_bytes)
@@ -1329,40 +3754,58 @@ mod tests {
     const CLIENT_CERT_PEM: &str = include_str!("../../quincy-tests/tests/static/client_cert.pem");
     const CLIENT_KEY_PEM: &str = include_str!("../../quincy-tests/tests/static/client_key.pem");
 
-    #[test]
-    fn parse_server_config_tls() {
-        let toml = r#"
-            name = "quincy-server"
-            bind_address = "192.168.1.1"
-            bind_port = 12345
-            reuse_socket = true
-            tunnel_network = "10.0.0.1/24"
-            isolate_clients = false
-            users_file = "/path/to/users.toml"
-
-            [protocol]
-            mode = "tls"
-            certificate_file = "/path/to/cert.pem"
-            certificate_key_file = "/path/to/key.pem"
-            key_exchange = "PostQuantum"
-
-            [connection]
-            mtu = 1500
-            congestion_controller = "Bbr"
-            connection_timeout_s = 45
-            keep_alive_interval_s = 20
-            send_buffer_size = 4194304
-            recv_buffer_size = 4194304
-
-            [log]
-            level = "debug"
-        "#;
-
-        let config: ServerConfig = Figment::new()
-            .merge(Toml::string(toml))
-            .extract()
-            .expect("Failed to parse server config");
-
+    const SERVER_CONFIG_TLS_TOML: &str = r#"
+        name = "quincy-server"
+        bind_address = "192.168.1.1"
+        bind_port = 12345
+        reuse_socket = true
+        tunnel_network = "10.0.0.1/24"
+        isolate_clients = false
+        users_file = "/path/to/users.toml"
+
+        [protocol]
+        mode = "tls"
+        certificate_file = "/path/to/cert.pem"
+        certificate_key_file = "/path/to/key.pem"
+        key_exchange = "PostQuantum"
+
+        [connection]
+        mtu = 1500
+        congestion_controller = "Bbr"
+        connection_timeout_s = 45
+        keep_alive_interval_s = 20
+        send_buffer_size = 4194304
+        recv_buffer_size = 4194304
+
+        [log]
+        level = "debug"
+    "#;
+
+    const SERVER_CONFIG_TLS_YAML: &str = r#"
+        name: quincy-server
+        bind_address: 192.168.1.1
+        bind_port: 12345
+        reuse_socket: true
+        tunnel_network: 10.0.0.1/24
+        isolate_clients: false
+        users_file: /path/to/users.toml
+        protocol:
+          mode: tls
+          certificate_file: /path/to/cert.pem
+          certificate_key_file: /path/to/key.pem
+          key_exchange: PostQuantum
+        connection:
+          mtu: 1500
+          congestion_controller: Bbr
+          connection_timeout_s: 45
+          keep_alive_interval_s: 20
+          send_buffer_size: 4194304
+          recv_buffer_size: 4194304
+        log:
+          level: debug
+    "#;
+
+    fn assert_server_config_tls(config: &ServerConfig) {
         assert_eq!(config.name, "quincy-server");
         assert_eq!(
             config.bind_address,
@@ -1385,6 +3828,7 @@ mod tests {
         assert_eq!(config.connection.keep_alive_interval_s, 20);
         assert_eq!(config.connection.send_buffer_size, 4194304);
         assert_eq!(config.connection.recv_buffer_size, 4194304);
+        assert_eq!(config.connection.local_port, None);
 
         match &config.protocol {
             ServerProtocolConfig::Tls(tls) => {
@@ -1406,6 +3850,71 @@ mod tests {
         assert_eq!(config.log.level, "debug");
     }
 
+    #[test]
+    fn parse_server_config_tls() {
+        let config: ServerConfig = Figment::new()
+            .merge(Toml::string(SERVER_CONFIG_TLS_TOML))
+            .extract()
+            .expect("Failed to parse server config");
+
+        assert_server_config_tls(&config);
+    }
+
+    #[test]
+    fn parse_server_config_tls_yaml() {
+        let config: ServerConfig = Figment::new()
+            .merge(Yaml::string(&unindent_yaml(SERVER_CONFIG_TLS_YAML)))
+            .extract()
+            .expect("Failed to parse server config");
+
+        assert_server_config_tls(&config);
+    }
+
+    #[test]
+    fn from_path_detects_config_format() {
+        let dir = tempfile::tempdir().unwrap();
+        let toml_path = dir.path().join("server.toml");
+        let yaml_path = dir.path().join("server.yml");
+        std::fs::write(&toml_path, SERVER_CONFIG_TLS_TOML).unwrap();
+        std::fs::write(&yaml_path, unindent_yaml(SERVER_CONFIG_TLS_YAML)).unwrap();
+
+        let from_toml = ServerConfig::from_path(&toml_path, "QUINCY_FROM_PATH_TEST_").unwrap();
+        let from_yaml = ServerConfig::from_path(&yaml_path, "QUINCY_FROM_PATH_TEST_").unwrap();
+
+        assert_server_config_tls(&from_toml);
+        assert_server_config_tls(&from_yaml);
+        assert_eq!(format!("{from_toml:?}"), format!("{from_yaml:?}"));
+    }
+
+    #[test]
+    fn from_path_reads_json_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let json_path = dir.path().join("client.json");
+        std::fs::write(
+            &json_path,
+            r#"{
+                "connection_string": "127.0.0.1:55555",
+                "protocol": { "mode": "tls", "trusted_certificates": ["cert"] },
+                "connection": { "mtu": 1300 },
+                "log": { "level": "info" }
+            }"#,
+        )
+        .unwrap();
+
+        let config = ClientConfig::from_path(&json_path, "QUINCY_FROM_PATH_TEST_").unwrap();
+
+        assert_eq!(config.connection_string, "127.0.0.1:55555");
+        assert_eq!(config.connection.mtu, 1300);
+    }
+
+    /// Strips the common indentation of the YAML fixtures, which YAML is sensitive to.
+    fn unindent_yaml(yaml: &str) -> String {
+        yaml.lines()
+            .map(|line| line.strip_prefix("        ").unwrap_or(line))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
     #[test]
     fn parse_server_config_noise() {
         let toml = r#"
@@ -1478,6 +3987,7 @@ mod tests {
     fn parse_client_config_tls() {
         let toml = r#"
             connection_string = "example.com:55555"
+            kx_hint_cache_path = "/var/cache/quincy/kx-hints.json"
 
             [protocol]
             mode = "tls"
@@ -1494,6 +4004,10 @@ mod tests {
             keep_alive_interval_s = 20
             send_buffer_size = 1048576
             recv_buffer_size = 1048576
+            local_port = 40000
+            local_address = "192.168.1.10"
+            socket_mark = 0x51
+            dscp = 46
 
             [network]
             routes = ["10.0.1.0/24", "192.168.0.0/16"]
@@ -1547,11 +4061,22 @@ mod tests {
         assert_eq!(config.connection.keep_alive_interval_s, 20);
         assert_eq!(config.connection.send_buffer_size, 1048576);
         assert_eq!(config.connection.recv_buffer_size, 1048576);
+        assert_eq!(config.connection.local_port, Some(40000));
+        assert_eq!(
+            config.connection.local_address,
+            Some("192.168.1.10".parse::<IpAddr>().unwrap())
+        );
+        assert_eq!(config.connection.socket_mark, Some(0x51));
+        assert_eq!(config.connection.dscp, Some(46));
+        assert_eq!(
+            config.kx_hint_cache_path,
+            Some(PathBuf::from("/var/cache/quincy/kx-hints.json"))
+        );
         assert_eq!(
             config.network.routes,
             vec![
-                "10.0.1.0/24".parse::<IpNet>().unwrap(),
-                "192.168.0.0/16".parse::<IpNet>().unwrap()
+                "10.0.1.0/24".parse::<RouteSpec>().unwrap(),
+                "192.168.0.0/16".parse::<RouteSpec>().unwrap()
             ]
         );
         assert_eq!(
@@ -1636,7 +4161,7 @@ mod tests {
                     "AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA="
                 );
                 assert_eq!(
-                    noise.private_key.expose_secret(),
+                    noise.private_key().unwrap().expose_secret(),
                     "AQAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA="
                 );
             }
@@ -1644,56 +4169,1690 @@ mod tests {
         }
     }
 
+    fn noise_config_with_key_sources(
+        private_key: Option<&str>,
+        private_key_file: Option<PathBuf>,
+    ) -> ClientNoiseConfig {
+        ClientNoiseConfig {
+            key_exchange: NoiseKeyExchange::Standard,
+            server_public_key: "AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA=".to_string(),
+            private_key: private_key.map(SecretString::from),
+            private_key_file,
+        }
+    }
+
     #[test]
-    fn build_server_tls_config_with_inline_certificate_and_key() {
-        let config = ServerConfig {
-            name: "quincy-server".to_string(),
-            interface_name: None,
-            bind_address: "127.0.0.1".parse().unwrap(),
-            bind_port: 55555,
-            reuse_socket: false,
-            tunnel_network: "10.0.0.1/24".parse().unwrap(),
-            users_file: PathBuf::from("users.toml"),
-            isolate_clients: true,
-            default_bandwidth_limit: None,
-            protocol: ServerProtocolConfig::Tls(ServerTlsConfig {
-                key_exchange: TlsKeyExchange::Standard,
-                certificate_file: None,
-                certificate: Some(SERVER_CERT_PEM.to_string()),
-                certificate_key_file: None,
-                certificate_key: Some(SecretString::from(SERVER_KEY_PEM)),
-            }),
-            connection: ConnectionConfig::default(),
-            log: LogConfig {
-                level: "info".to_string(),
-            },
-            metrics: MetricsConfig::default(),
-        };
+    fn noise_private_key_file_is_trimmed() {
+        let dir = tempfile::tempdir().unwrap();
+        let key_path = dir.path().join("client.key");
+        std::fs::write(&key_path, "AQAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA=\n").unwrap();
 
-        assert!(config.as_quinn_server_config(None, None).is_ok());
+        let noise = noise_config_with_key_sources(None, Some(key_path));
+
+        assert_eq!(
+            noise.private_key().unwrap().expose_secret(),
+            "AQAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA="
+        );
     }
 
     #[test]
-    fn build_client_tls_config_with_inline_certificate_and_key() {
-        let config = ClientConfig {
-            connection_string: "example.com:55555".to_string(),
-            protocol: ClientProtocolConfig::Tls(ClientTlsConfig {
-                key_exchange: TlsKeyExchange::Standard,
-                trusted_certificate_paths: Vec::new(),
-                trusted_certificates: vec![SERVER_CERT_PEM.to_string()],
-                client_certificate_file: None,
-                client_certificate: Some(CLIENT_CERT_PEM.to_string()),
-                client_certificate_key_file: None,
-                client_certificate_key: Some(SecretString::from(CLIENT_KEY_PEM)),
+    fn noise_private_key_sources_are_mutually_exclusive() {
+        let noise = noise_config_with_key_sources(
+            Some("AQAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA="),
+            Some(PathBuf::from("/etc/quincy/client.key")),
+        );
+
+        assert!(matches!(
+            noise.private_key(),
+            Err(crate::QuincyError::Config(ConfigError::Conflict { .. }))
+        ));
+    }
+
+    #[test]
+    fn noise_private_key_file_must_be_readable() {
+        let noise = noise_config_with_key_sources(
+            None,
+            Some(PathBuf::from("/nonexistent/quincy/client.key")),
+        );
+
+        assert!(matches!(
+            noise.private_key(),
+            Err(crate::QuincyError::Config(
+                ConfigError::FileNotReadable { .. }
+            ))
+        ));
+    }
+
+    const CLIENT_CONFIG_V0_TOML: &str = r#"
+        connection_string = "example.com:55555"
+
+        [protocol]
+        mode = "noise"
+        server_public_key = "AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA="
+        private_key = "AQAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA="
+
+        [connection]
+        timeout = 10
+
+        [log]
+        level = "info"
+    "#;
+
+    #[test]
+    fn migrate_v0_client_config_to_current_layout() {
+        let config = ClientConfig::init(
+            Figment::from(Toml::string(CLIENT_CONFIG_V0_TOML)),
+            "QUINCY_",
+        )
+        .expect("v0 configuration must be migrated");
+
+        assert_eq!(config.config_version, CONFIG_VERSION);
+        assert_eq!(config.connection.connection_timeout_s, 10);
+        assert_eq!(config.connection_string, "example.com:55555");
+    }
+
+    #[test]
+    fn current_client_config_is_not_migrated() {
+        let toml = format!(
+            "config_version = {CONFIG_VERSION}\n{}",
+            CLIENT_CONFIG_V0_TOML.replace("timeout = 10", "connection_timeout_s = 12")
+        );
+
+        let config = ClientConfig::init(Figment::from(Toml::string(&toml)), "QUINCY_").unwrap();
+
+        assert_eq!(config.config_version, CONFIG_VERSION);
+        assert_eq!(config.connection.connection_timeout_s, 12);
+    }
+
+    #[test]
+    fn migrated_config_errors_name_the_source_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("client.toml");
+        std::fs::write(
+            &path,
+            CLIENT_CONFIG_V0_TOML.replace("timeout = 10", "timeout = 10\nmtu = \"large\""),
+        )
+        .unwrap();
+
+        let result = ClientConfig::from_path(&path, "QUINCY_MIGRATION_TEST_");
+
+        assert!(matches!(
+            result,
+            Err(crate::QuincyError::Config(ConfigError::InvalidValue { field, reason }))
+                if field == "connection.mtu" && reason.contains(&path.display().to_string())
+        ));
+    }
+
+    #[test]
+    fn future_config_version_is_rejected() {
+        let toml = format!(
+            "config_version = {}\n{CLIENT_CONFIG_V0_TOML}",
+            CONFIG_VERSION + 1
+        );
+
+        let result = ClientConfig::init(Figment::from(Toml::string(&toml)), "QUINCY_");
+
+        assert!(matches!(
+            result,
+            Err(crate::QuincyError::Config(ConfigError::InvalidValue { field, reason }))
+                if field == "config_version" && reason.contains("upgrade Quincy")
+        ));
+    }
+
+    #[test]
+    fn client_config_from_env_only() {
+        // The jail restores the environment, and the prefix keeps other tests from seeing it
+        figment::Jail::expect_with(|jail| {
+            jail.set_env(
+                "QUINCY_FROM_ENV_TEST_CONNECTION_STRING",
+                "example.com:55555",
+            );
+            jail.set_env("QUINCY_FROM_ENV_TEST_PROTOCOL__MODE", "tls");
+            jail.set_env("QUINCY_FROM_ENV_TEST_CONNECTION__MTU", "1300");
+            jail.set_env("QUINCY_FROM_ENV_TEST_LOG__LEVEL", "debug");
+
+            let config = ClientConfig::from_env("QUINCY_FROM_ENV_TEST_")
+                .expect("Failed to build client config from the environment");
+
+            assert_eq!(config.connection_string, "example.com:55555");
+            assert!(matches!(config.protocol, ClientProtocolConfig::Tls(_)));
+            assert_eq!(config.connection.mtu, 1300);
+            assert_eq!(config.log.level, "debug");
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn build_server_tls_config_with_inline_certificate_and_key() {
+        let config = ServerConfig {
+            config_version: CONFIG_VERSION,
+            name: "quincy-server".to_string(),
+            interface_name: None,
+            bind_address: "127.0.0.1".parse().unwrap(),
+            bind_port: 55555,
+            reuse_socket: false,
+            tunnel_network: "10.0.0.1/24".parse().unwrap(),
+            secondary_tunnel_network: None,
+            users_file: PathBuf::from("users.toml"),
+            isolate_clients: true,
+            address_grace_period_s: 0,
+            resumption_token_ttl_s: 0,
+            enable_0rtt: false,
+            default_bandwidth_limit: None,
+            protocol: ServerProtocolConfig::Tls(ServerTlsConfig {
+                key_exchange: TlsKeyExchange::Standard,
+                alpn_protocols: Vec::new(),
+                cipher_suites: Vec::new(),
+                certificate_file: None,
+                certificate: Some(SERVER_CERT_PEM.to_string()),
+                certificate_key_file: None,
+                certificate_key: Some(SecretString::from(SERVER_KEY_PEM)),
+            }),
+            connection: ConnectionConfig::default(),
+            log: LogConfig {
+                level: "info".to_string(),
+                capture_file: None,
+                capture_max_bytes: default_capture_max_bytes(),
+            },
+            metrics: MetricsConfig::default(),
+            auth_lockout: AuthLockoutConfig::default(),
+            auth_audit_log: None,
+            groups: HashMap::new(),
+            push: PushedNetworkConfig::default(),
+        };
+
+        assert!(config.as_quinn_server_config(None, None).is_ok());
+    }
+
+    #[test]
+    fn routes_accept_networks_and_tables_with_metrics() {
+        let toml = r#"
+            routes = ["10.0.1.0/24", { net = "10.0.2.0/24", metric = 50 }, { net = "fd00::/64" }]
+        "#;
+
+        let network: NetworkConfig = Figment::new()
+            .merge(Toml::string(toml))
+            .extract()
+            .expect("Failed to parse network config");
+
+        assert_eq!(
+            network.routes,
+            vec![
+                RouteSpec {
+                    net: "10.0.1.0/24".parse().unwrap(),
+                    metric: None,
+                },
+                RouteSpec {
+                    net: "10.0.2.0/24".parse().unwrap(),
+                    metric: Some(50),
+                },
+                RouteSpec {
+                    net: "fd00::/64".parse().unwrap(),
+                    metric: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn route_with_invalid_metric_is_rejected() {
+        let toml = r#"
+            routes = [{ net = "10.0.2.0/24", metric = -1 }]
+        "#;
+
+        let result: std::result::Result<NetworkConfig, _> =
+            Figment::new().merge(Toml::string(toml)).extract();
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn mixed_family_dns_servers_are_parsed_and_rendered() {
+        let toml = r#"
+            dns_servers = ["1.1.1.1", "2606:4700:4700::1111", "fe80::1"]
+        "#;
+
+        let network: NetworkConfig = Figment::new()
+            .merge(Toml::string(toml))
+            .extract()
+            .expect("Failed to parse network config");
+        let dns_servers: Vec<IpAddr> = ["1.1.1.1", "2606:4700:4700::1111", "fe80::1"]
+            .iter()
+            .map(|address| address.parse().unwrap())
+            .collect();
+
+        assert_eq!(network.enabled_dns_servers(), dns_servers);
+        assert_eq!(
+            toml_array(&network.dns_servers),
+            r#"["1.1.1.1", "2606:4700:4700::1111", "fe80::1"]"#
+        );
+    }
+
+    #[test]
+    fn disabled_ipv6_family_filters_network_config() {
+        let toml = r#"
+            routes = ["10.0.1.0/24", "fd00::/64"]
+            dns_servers = ["10.0.1.1", "fd00::1"]
+            enabled_families = ["ipv4"]
+        "#;
+
+        let network: NetworkConfig = Figment::new()
+            .merge(Toml::string(toml))
+            .extract()
+            .expect("Failed to parse network config");
+
+        assert_eq!(network.enabled_families, vec![IpFamily::V4]);
+        assert_eq!(
+            network.enabled_routes(),
+            vec!["10.0.1.0/24".parse::<RouteSpec>().unwrap()]
+        );
+        assert_eq!(
+            network.enabled_dns_servers(),
+            vec!["10.0.1.1".parse::<IpAddr>().unwrap()]
+        );
+        assert!(network.is_family_enabled(&"10.0.0.2".parse().unwrap()));
+        assert!(!network.is_family_enabled(&"fd00::2".parse().unwrap()));
+    }
+
+    #[test]
+    fn exclude_routes_are_carved_out_of_enabled_routes() {
+        let toml = r#"
+            routes = ["0.0.0.0/0", "fd00::/8"]
+            exclude_routes = ["192.168.0.0/16", "fd00:1::/64", "10.0.0.0/8"]
+            enabled_families = ["ipv4"]
+        "#;
+
+        let mut network: NetworkConfig = Figment::new()
+            .merge(Toml::string(toml))
+            .extract()
+            .expect("Failed to parse network config");
+
+        assert_eq!(
+            network.managed_bypass_networks(),
+            vec![
+                "192.168.0.0/16".parse::<IpNet>().unwrap(),
+                "10.0.0.0/8".parse().unwrap()
+            ]
+        );
+
+        network.manage_routes = false;
+        assert!(network.managed_bypass_networks().is_empty());
+    }
+
+    #[test]
+    fn kill_switch_requires_managed_routes() {
+        let network = NetworkConfig {
+            kill_switch: true,
+            ..NetworkConfig::default()
+        };
+        assert!(network.validate_kill_switch().is_ok());
+        assert_eq!(
+            network.managed_route_options().kill_switch,
+            KillSwitch::Enabled
+        );
+
+        let dry_run = NetworkConfig {
+            kill_switch_dry_run: true,
+            ..network.clone()
+        };
+        assert_eq!(
+            dry_run.managed_route_options().kill_switch,
+            KillSwitch::DryRun
+        );
+
+        let unmanaged = NetworkConfig {
+            manage_routes: false,
+            ..network
+        };
+        assert_eq!(
+            unmanaged.managed_route_options().kill_switch,
+            KillSwitch::Disabled
+        );
+        assert!(matches!(
+            unmanaged.validate_kill_switch(),
+            Err(crate::QuincyError::Config(ConfigError::InvalidValue { field, .. }))
+                if field == "network.kill_switch"
+        ));
+    }
+
+    #[test]
+    fn strict_kill_switch_is_independent_of_routes() {
+        let network = NetworkConfig {
+            kill_switch_strict: true,
+            manage_routes: false,
+            routes: vec![],
+            ..NetworkConfig::default()
+        };
+        assert_eq!(network.strict_kill_switch(), KillSwitch::Enabled);
+        assert!(network.validate_kill_switch().is_ok());
+
+        let both = NetworkConfig {
+            kill_switch: true,
+            kill_switch_strict: true,
+            ..NetworkConfig::default()
+        };
+        // The route-bound kill switch would lift the rules of the strict one
+        assert_eq!(
+            both.managed_route_options().kill_switch,
+            KillSwitch::Disabled
+        );
+
+        let dry_run = NetworkConfig {
+            kill_switch_dry_run: true,
+            ..network
+        };
+        assert_eq!(dry_run.strict_kill_switch(), KillSwitch::DryRun);
+        assert_eq!(
+            NetworkConfig::default().strict_kill_switch(),
+            KillSwitch::Disabled
+        );
+    }
+
+    #[test]
+    fn split_tunnel_selects_users_and_table() {
+        let toml = r#"
+            routes = ["0.0.0.0/0"]
+            split_tunnel_uids = [1000, 1001]
+        "#;
+
+        let network: NetworkConfig = Figment::new()
+            .merge(Toml::string(toml))
+            .extract()
+            .expect("Failed to parse network config");
+
+        assert_eq!(
+            network.managed_route_options().split_tunnel,
+            Some(SplitTunnel {
+                uids: vec![1000, 1001],
+                table: 51900,
+                fwmark: None,
+            })
+        );
+        assert_eq!(
+            network.validate_split_tunnel().is_ok(),
+            cfg!(target_os = "linux")
+        );
+
+        let reserved = NetworkConfig {
+            split_tunnel_table: 254,
+            ..network.clone()
+        };
+        let with_kill_switch = NetworkConfig {
+            kill_switch: true,
+            ..network.clone()
+        };
+        let with_strict_kill_switch = NetworkConfig {
+            kill_switch_strict: true,
+            ..network.clone()
+        };
+        let unmanaged = NetworkConfig {
+            manage_routes: false,
+            ..network
+        };
+        assert_eq!(unmanaged.managed_route_options().split_tunnel, None);
+        for invalid in [
+            reserved,
+            with_kill_switch,
+            with_strict_kill_switch,
+            unmanaged,
+        ] {
+            assert!(matches!(
+                invalid.validate_split_tunnel(),
+                Err(crate::QuincyError::Config(ConfigError::InvalidValue { field, .. }))
+                    if field == "network.split_tunnel_uids"
+            ));
+        }
+    }
+
+    #[test]
+    fn network_config_enables_both_families_by_default() {
+        let network = NetworkConfig::default();
+
+        assert_eq!(network.enabled_families, vec![IpFamily::V4, IpFamily::V6]);
+        assert!(network.is_family_enabled(&"fd00::2".parse().unwrap()));
+    }
+
+    #[test]
+    fn disabled_route_and_dns_management_yields_no_configuration() {
+        let toml = r#"
+            routes = ["10.0.1.0/24"]
+            dns_servers = ["10.0.1.1"]
+            manage_routes = false
+            manage_dns = false
+        "#;
+
+        let network: NetworkConfig = Figment::new()
+            .merge(Toml::string(toml))
+            .extract()
+            .expect("Failed to parse network config");
+
+        assert_eq!(network.managed_routes(), None);
+        assert_eq!(network.managed_dns_servers(), None);
+
+        let default = NetworkConfig::default();
+        assert_eq!(default.managed_routes(), Some(Vec::new()));
+        assert_eq!(default.managed_dns_servers(), Some(Vec::new()));
+    }
+
+    #[test]
+    fn split_dns_domains_are_parsed() {
+        let toml = r#"
+            dns_servers = ["10.0.1.1", "fd00::1"]
+            enabled_families = ["ipv4"]
+            dns_split_domains = [
+                { domain = "corp.example.com", dns_servers = ["10.0.2.53", "fd00::53"] },
+                { domain = "internal" },
+            ]
+        "#;
+
+        let network: NetworkConfig = Figment::new()
+            .merge(Toml::string(toml))
+            .extract()
+            .expect("Failed to parse network config");
+
+        assert_eq!(network.dns_split_domains.len(), 2);
+        assert_eq!(
+            network.dns_split_domains[1].dns_servers,
+            Vec::<IpAddr>::new()
+        );
+        assert!(network.validate_dns_split_domains().is_ok());
+        assert_eq!(
+            network.managed_dns_split_domains(),
+            vec![
+                SplitDnsDomain {
+                    domain: "corp.example.com".to_string(),
+                    dns_servers: vec!["10.0.2.53".parse().unwrap()],
+                },
+                SplitDnsDomain {
+                    domain: "internal".to_string(),
+                    dns_servers: vec!["10.0.1.1".parse().unwrap()],
+                },
+            ]
+        );
+
+        let unmanaged = NetworkConfig {
+            manage_dns: false,
+            ..network
+        };
+        assert!(unmanaged.managed_dns_split_domains().is_empty());
+    }
+
+    #[test]
+    fn invalid_split_dns_domains_are_rejected() {
+        for (domain, dns_servers) in [
+            ("../../etc/hosts", vec!["10.0.1.1".parse().unwrap()]),
+            ("internal", Vec::new()),
+        ] {
+            let network = NetworkConfig {
+                dns_split_domains: vec![SplitDnsDomain {
+                    domain: domain.to_string(),
+                    dns_servers,
+                }],
+                ..NetworkConfig::default()
+            };
+
+            assert!(matches!(
+                network.validate_dns_split_domains(),
+                Err(crate::QuincyError::Config(ConfigError::InvalidValue { field, .. }))
+                    if field == "network.dns_split_domains"
+            ));
+        }
+    }
+
+    fn pushed_network_config() -> PushedNetworkConfig {
+        PushedNetworkConfig {
+            routes: vec![
+                "10.0.1.0/24".parse().unwrap(),
+                "10.0.2.0/24".parse().unwrap(),
+            ],
+            dns_servers: vec!["10.0.2.53".parse().unwrap()],
+            dns_search_domains: vec![
+                "corp.example.com".to_string(),
+                "bad domain".to_string(),
+                "lab.example.com".to_string(),
+            ],
+        }
+    }
+
+    #[test]
+    fn pushed_config_is_merged_into_network_config() {
+        let toml = r#"
+            routes = [{ net = "10.0.1.0/24", metric = 50 }]
+            dns_servers = ["10.0.1.1"]
+            dns_search_domains = ["lab.example.com", "home.arpa"]
+        "#;
+        let network: NetworkConfig = Figment::new()
+            .merge(Toml::string(toml))
+            .extract()
+            .expect("Failed to parse network config");
+
+        let merged = network.merge_pushed(&pushed_network_config());
+
+        // Local routes win over pushed routes of the same network
+        assert_eq!(
+            merged.routes,
+            vec![
+                RouteSpec {
+                    net: "10.0.1.0/24".parse().unwrap(),
+                    metric: Some(50),
+                },
+                "10.0.2.0/24".parse().unwrap(),
+            ]
+        );
+        // Pushed DNS servers replace the local ones
+        assert_eq!(
+            merged.dns_servers,
+            vec!["10.0.2.53".parse::<IpAddr>().unwrap()]
+        );
+        // Pushed search domains are searched first, invalid ones are dropped
+        assert_eq!(
+            merged.dns_search_domains,
+            vec!["corp.example.com", "lab.example.com", "home.arpa"]
+        );
+    }
+
+    #[test]
+    fn local_dns_servers_are_kept_without_pushed_ones() {
+        let network = NetworkConfig {
+            dns_servers: vec!["10.0.1.1".parse().unwrap()],
+            ..Default::default()
+        };
+        let pushed = PushedNetworkConfig {
+            dns_servers: Vec::new(),
+            ..pushed_network_config()
+        };
+
+        let merged = network.merge_pushed(&pushed);
+
+        assert_eq!(merged.dns_servers, network.dns_servers);
+    }
+
+    #[test]
+    fn pushed_config_is_ignored_when_not_accepted() {
+        let network = NetworkConfig {
+            accept_pushed_config: false,
+            ..Default::default()
+        };
+
+        assert_eq!(network.merge_pushed(&pushed_network_config()), network);
+    }
+
+    #[test]
+    fn dns_search_domains_are_validated() {
+        let network = NetworkConfig {
+            dns_search_domains: vec!["corp.example.com".to_string()],
+            ..Default::default()
+        };
+        assert!(network.validate_dns_search_domains().is_ok());
+
+        let network = NetworkConfig {
+            dns_search_domains: vec!["corp.example.com".to_string(), "bad/domain".to_string()],
+            ..Default::default()
+        };
+        assert!(matches!(
+            network.validate_dns_search_domains(),
+            Err(crate::QuincyError::Config(ConfigError::InvalidValue { field, .. }))
+                if field == "network.dns_search_domains"
+        ));
+    }
+
+    #[test]
+    fn dns_leak_protection_is_validated() {
+        let network = NetworkConfig {
+            dns_servers: vec!["10.0.1.1".parse().unwrap()],
+            dns_leak_protection: true,
+            ..NetworkConfig::default()
+        };
+        assert!(network.validate_dns_leak_protection().is_ok());
+        assert_eq!(
+            network.managed_dns_options().leak_protection,
+            LeakProtection::Enabled
+        );
+
+        let dry_run = NetworkConfig {
+            dns_leak_protection_dry_run: true,
+            ..network.clone()
+        };
+        assert_eq!(
+            dry_run.managed_dns_options().leak_protection,
+            LeakProtection::DryRun
+        );
+
+        let invalid = [
+            NetworkConfig {
+                dns_servers: Vec::new(),
+                ..network.clone()
+            },
+            NetworkConfig {
+                dns_split_domains: vec![SplitDnsDomain {
+                    domain: "corp.example.com".to_string(),
+                    dns_servers: Vec::new(),
+                }],
+                ..network
+            },
+        ];
+        for network in invalid {
+            assert!(matches!(
+                network.validate_dns_leak_protection(),
+                Err(crate::QuincyError::Config(ConfigError::InvalidValue { field, .. }))
+                    if field == "network.dns_leak_protection"
+            ));
+        }
+    }
+
+    #[test]
+    fn dns_protocol_is_parsed() {
+        let network: NetworkConfig = Figment::new()
+            .merge(Toml::string(r#"dns_protocol = "doh""#))
+            .extract()
+            .expect("Failed to parse network config");
+
+        assert_eq!(network.managed_dns_options().protocol, DnsProtocol::Doh);
+        assert_eq!(NetworkConfig::default().dns_protocol, DnsProtocol::Plain);
+
+        let unknown = Figment::new()
+            .merge(Toml::string(r#"dns_protocol = "dnscrypt""#))
+            .extract::<NetworkConfig>();
+        assert!(unknown.is_err());
+    }
+
+    #[test]
+    fn dns_tls_options_are_validated() {
+        let network: NetworkConfig = Figment::new()
+            .merge(Toml::string(
+                r#"
+                dns_protocol = "doh"
+                dns_tls_name = "dns.corp.example.com"
+                dns_trusted_certificate_paths = ["/etc/quincy/dns-ca.pem"]
+                dns_doh_path = "/resolve"
+                "#,
+            ))
+            .extract()
+            .expect("Failed to parse network config");
+        assert!(network.validate_dns_protocol().is_ok());
+
+        let options = network.managed_dns_options();
+        assert_eq!(options.tls_name.as_deref(), Some("dns.corp.example.com"));
+        assert_eq!(
+            options.trusted_certificate_paths,
+            vec![PathBuf::from("/etc/quincy/dns-ca.pem")]
+        );
+        assert_eq!(options.doh_path.as_deref(), Some("/resolve"));
+
+        for (field, invalid) in [
+            ("network.dns_tls_name", r#"dns_tls_name = "not a name""#),
+            ("network.dns_doh_path", r#"dns_doh_path = "dns-query""#),
+            ("network.dns_doh_path", r#"dns_doh_path = "/dns query""#),
+        ] {
+            let network: NetworkConfig = Figment::new()
+                .merge(Toml::string(invalid))
+                .extract()
+                .expect("Failed to parse network config");
+
+            assert!(matches!(
+                network.validate_dns_protocol(),
+                Err(crate::QuincyError::Config(ConfigError::InvalidValue { field: f, .. })) if f == field
+            ));
+        }
+    }
+
+    #[test]
+    fn build_client_tls_config_with_inline_certificate_and_key() {
+        let config = ClientConfig {
+            config_version: CONFIG_VERSION,
+            connection_string: "example.com:55555".to_string(),
+            connection_strings: Vec::new(),
+            protocol: ClientProtocolConfig::Tls(ClientTlsConfig {
+                key_exchange: TlsKeyExchange::Standard,
+                alpn_protocols: Vec::new(),
+                cipher_suites: Vec::new(),
+                trusted_certificate_paths: Vec::new(),
+                trusted_certificate_dirs: Vec::new(),
+                trusted_certificates: vec![SERVER_CERT_PEM.to_string()],
+                client_certificate_file: None,
+                client_certificate: Some(CLIENT_CERT_PEM.to_string()),
+                client_certificate_key_file: None,
+                client_certificate_key: Some(SecretString::from(CLIENT_KEY_PEM)),
+            }),
+            connection: ConnectionConfig::default(),
+            network: NetworkConfig::default(),
+            kx_hint_cache_path: None,
+            log: LogConfig {
+                level: "info".to_string(),
+                capture_file: None,
+                capture_max_bytes: default_capture_max_bytes(),
+            },
+        };
+
+        assert!(config.quinn_client_config().is_ok());
+    }
+
+    #[test]
+    fn parse_tls_alpn_protocols() {
+        let toml = r#"
+            connection_string = "example.com:443"
+
+            [protocol]
+            mode = "tls"
+            alpn_protocols = ["h3", "h3-29"]
+
+            [log]
+            level = "info"
+        "#;
+
+        let config: ClientConfig = Figment::new()
+            .merge(Toml::string(toml))
+            .extract()
+            .expect("Failed to parse client config");
+
+        let ClientProtocolConfig::Tls(tls) = &config.protocol else {
+            panic!("Expected TLS protocol config");
+        };
+        assert_eq!(tls.alpn_protocols, vec!["h3", "h3-29"]);
+        assert_eq!(
+            alpn_protocol_ids(&tls.alpn_protocols).unwrap(),
+            vec![b"h3".to_vec(), b"h3-29".to_vec()]
+        );
+    }
+
+    #[test]
+    fn alpn_protocols_default_to_quincy() {
+        let toml = r#"
+            mode = "tls"
+            certificate_file = "/path/to/cert.pem"
+            certificate_key_file = "/path/to/key.pem"
+        "#;
+
+        let protocol: ServerProtocolConfig = Figment::new()
+            .merge(Toml::string(toml))
+            .extract()
+            .expect("Failed to parse protocol config");
+
+        let ServerProtocolConfig::Tls(tls) = &protocol else {
+            panic!("Expected TLS protocol config");
+        };
+        assert!(tls.alpn_protocols.is_empty());
+        assert_eq!(
+            alpn_protocol_ids(&tls.alpn_protocols).unwrap(),
+            *TLS_ALPN_PROTOCOLS
+        );
+    }
+
+    #[test]
+    fn alpn_protocols_reject_invalid_lengths() {
+        for protocol in [String::new(), "x".repeat(256)] {
+            let result = alpn_protocol_ids(&["h3".to_string(), protocol.clone()]);
+
+            assert!(matches!(
+                result,
+                Err(crate::QuincyError::Config(ConfigError::InvalidValue { reason, .. }))
+                    if reason.contains(&format!("'{protocol}'"))
+            ));
+        }
+        assert!(alpn_protocol_ids(&["x".repeat(255)]).is_ok());
+    }
+
+    #[test]
+    fn parse_tls_cipher_suites() {
+        let toml = r#"
+            mode = "tls"
+            certificate_file = "/path/to/cert.pem"
+            certificate_key_file = "/path/to/key.pem"
+            cipher_suites = ["TLS13_CHACHA20_POLY1305_SHA256"]
+        "#;
+
+        let protocol: ServerProtocolConfig = Figment::new()
+            .merge(Toml::string(toml))
+            .extract()
+            .expect("Failed to parse protocol config");
+
+        let ServerProtocolConfig::Tls(tls) = &protocol else {
+            panic!("Expected TLS protocol config");
+        };
+        let provider = tls_crypto_provider(&tls.key_exchange, &tls.cipher_suites).unwrap();
+        let suites = provider
+            .cipher_suites
+            .iter()
+            .map(|suite| suite.suite())
+            .collect::<Vec<_>>();
+
+        assert_eq!(suites, vec![CipherSuite::TLS13_CHACHA20_POLY1305_SHA256]);
+    }
+
+    #[test]
+    fn tls_cipher_suites_default_when_empty() {
+        let provider = tls_crypto_provider(&TlsKeyExchange::Standard, &[]).unwrap();
+
+        assert_eq!(
+            provider.cipher_suites.len(),
+            DEFAULT_TLS_CIPHER_SUITES.len()
+        );
+        assert!(
+            provider
+                .cipher_suites
+                .iter()
+                .all(|suite| DEFAULT_TLS_CIPHER_SUITES.contains(&suite.suite()))
+        );
+    }
+
+    #[test]
+    fn tls_cipher_suites_reject_unknown_names() {
+        let result = tls_cipher_suites(&["TLS13_AES_128_CCM_SHA256".to_string()]);
+
+        assert!(matches!(
+            result,
+            Err(crate::QuincyError::Config(ConfigError::InvalidValue { field, .. }))
+                if field == "protocol.cipher_suites"
+        ));
+    }
+
+    fn validated_client_config() -> ClientConfig {
+        ClientConfig {
+            config_version: CONFIG_VERSION,
+            connection_string: "127.0.0.1:55555".to_string(),
+            connection_strings: Vec::new(),
+            protocol: ClientProtocolConfig::Tls(ClientTlsConfig {
+                key_exchange: TlsKeyExchange::Standard,
+                alpn_protocols: Vec::new(),
+                cipher_suites: Vec::new(),
+                trusted_certificate_paths: Vec::new(),
+                trusted_certificate_dirs: Vec::new(),
+                trusted_certificates: vec![SERVER_CERT_PEM.to_string()],
+                client_certificate_file: None,
+                client_certificate: Some(CLIENT_CERT_PEM.to_string()),
+                client_certificate_key_file: None,
+                client_certificate_key: Some(SecretString::from(CLIENT_KEY_PEM)),
+            }),
+            connection: ConnectionConfig::default(),
+            network: NetworkConfig::default(),
+            kx_hint_cache_path: None,
+            log: LogConfig {
+                level: "info".to_string(),
+                capture_file: None,
+                capture_max_bytes: default_capture_max_bytes(),
+            },
+        }
+    }
+
+    #[test]
+    fn validate_accepts_valid_client_config() {
+        assert!(validated_client_config().validate().is_ok());
+    }
+
+    #[test]
+    fn validate_rejects_unresolvable_connection_string() {
+        let config = ClientConfig {
+            connection_string: "127.0.0.1".to_string(),
+            ..validated_client_config()
+        };
+
+        assert!(matches!(
+            config.validate(),
+            Err(crate::QuincyError::Config(ConfigError::InvalidValue { field, .. }))
+                if field == "connection_string"
+        ));
+    }
+
+    #[test]
+    fn validate_accepts_any_resolvable_connection_string() {
+        let config = ClientConfig {
+            connection_string: "127.0.0.1".to_string(),
+            connection_strings: vec!["127.0.0.1:55556".to_string()],
+            ..validated_client_config()
+        };
+
+        assert!(config.validate().is_ok());
+        assert_eq!(
+            config.server_endpoints(),
+            vec!["127.0.0.1", "127.0.0.1:55556"]
+        );
+    }
+
+    #[test]
+    fn validate_accepts_srv_connection_string() {
+        let config = ClientConfig {
+            connection_string: "srv://quincy.invalid".to_string(),
+            ..validated_client_config()
+        };
+
+        assert!(config.validate().is_ok());
+
+        for connection_string in ["srv://", "srv://quincy.invalid:55555", "srv://quincy/path"] {
+            let config = ClientConfig {
+                connection_string: connection_string.to_string(),
+                ..validated_client_config()
+            };
+
+            assert!(matches!(
+                config.validate(),
+                Err(crate::QuincyError::Config(ConfigError::InvalidValue { field, .. }))
+                    if field == "connection_string"
+            ));
+        }
+    }
+
+    #[test]
+    fn validate_rejects_unresolvable_connection_strings() {
+        let config = ClientConfig {
+            connection_string: String::new(),
+            connection_strings: vec!["127.0.0.1".to_string(), "::1".to_string()],
+            ..validated_client_config()
+        };
+
+        assert!(matches!(
+            config.validate(),
+            Err(crate::QuincyError::Config(ConfigError::InvalidValue { field, .. }))
+                if field == "connection_strings"
+        ));
+
+        let config = ClientConfig {
+            connection_string: String::new(),
+            ..validated_client_config()
+        };
+
+        assert!(matches!(
+            config.validate(),
+            Err(crate::QuincyError::Config(ConfigError::MissingField { field }))
+                if field == "connection_string"
+        ));
+    }
+
+    #[test]
+    fn validate_rejects_out_of_range_mtu() {
+        for mtu in [575, 9001] {
+            let config = ClientConfig {
+                connection: ConnectionConfig {
+                    mtu,
+                    ..ConnectionConfig::default()
+                },
+                ..validated_client_config()
+            };
+
+            assert!(matches!(
+                config.validate(),
+                Err(crate::QuincyError::Config(ConfigError::InvalidValue { field, .. }))
+                    if field == "mtu"
+            ));
+        }
+    }
+
+    #[test]
+    fn validate_bounds_gso_segment_size() {
+        let connection = |gso_segment_size| ConnectionConfig {
+            mtu: 1400,
+            gso_segment_size: Some(gso_segment_size),
+            ..ConnectionConfig::default()
+        };
+
+        for valid in [0, 1200, 1450] {
+            assert!(connection(valid).validate(true).is_ok(), "{valid}");
+        }
+        for invalid in [1199, 1451] {
+            assert!(matches!(
+                connection(invalid).validate(true),
+                Err(crate::QuincyError::Socket(SocketError::ConfigFailed { .. }))
+            ));
+        }
+
+        assert_eq!(connection(1300).quic_mtu().unwrap(), 1300);
+        assert_eq!(connection(0).quic_mtu().unwrap(), 1450);
+    }
+
+    #[test]
+    fn enable_0rtt_conflicts_with_noise_mode() {
+        let toml = r#"
+            connection_string = "127.0.0.1:55555"
+            enable_0rtt = true
+
+            [protocol]
+            mode = "noise"
+            server_public_key = "AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA="
+            private_key = "AQAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA="
+
+            [log]
+            level = "info"
+        "#;
+        let config: ClientConfig = Figment::new()
+            .merge(Toml::string(toml))
+            .extract()
+            .expect("Failed to parse client config");
+
+        assert!(config.enable_0rtt);
+        assert!(matches!(
+            config.validate(),
+            Err(crate::QuincyError::Config(ConfigError::Conflict { .. }))
+        ));
+    }
+
+    #[test]
+    fn parse_transport_mode() {
+        let connection: ConnectionConfig = Figment::new()
+            .merge(Toml::string(r#"transport_mode = "stream""#))
+            .extract()
+            .expect("Failed to parse connection config");
+
+        assert_eq!(connection.transport_mode, TransportMode::Stream);
+        assert_eq!(
+            ConnectionConfig::default().transport_mode,
+            TransportMode::Datagram
+        );
+    }
+
+    #[test]
+    fn parse_bbr_sub_table() {
+        let toml = r#"
+            congestion_controller = "bbr"
+
+            [bbr]
+            initial_window = 131072
+        "#;
+
+        let connection: ConnectionConfig = Figment::new()
+            .merge(Toml::string(toml))
+            .extract()
+            .expect("Failed to parse connection config");
+
+        assert_eq!(
+            connection.bbr,
+            Some(BbrConfig {
+                initial_window: Some(131072)
+            })
+        );
+        assert!(connection.validate(true).is_ok());
+    }
+
+    #[test]
+    fn bbr_sub_table_defaults_to_quinn_defaults() {
+        let connection: ConnectionConfig = Figment::new()
+            .merge(Toml::string(r#"congestion_controller = "bbr""#))
+            .extract()
+            .expect("Failed to parse connection config");
+
+        assert_eq!(connection.bbr, None);
+
+        let connection: ConnectionConfig = Figment::new()
+            .merge(Toml::string("congestion_controller = \"bbr\"\nbbr = {}"))
+            .extract()
+            .expect("Failed to parse connection config");
+
+        assert_eq!(connection.bbr, Some(BbrConfig::default()));
+    }
+
+    #[test]
+    fn bbr_sub_table_conflicts_with_other_congestion_controllers() {
+        for congestion_controller in [CongestionController::Cubic, CongestionController::NewReno] {
+            let connection = ConnectionConfig {
+                congestion_controller,
+                bbr: Some(BbrConfig::default()),
+                ..ConnectionConfig::default()
+            };
+
+            assert!(matches!(
+                connection.validate(true),
+                Err(crate::QuincyError::Config(ConfigError::Conflict { .. }))
+            ));
+        }
+    }
+
+    #[test]
+    fn validate_rejects_zero_bbr_initial_window() {
+        let connection = ConnectionConfig {
+            congestion_controller: CongestionController::Bbr,
+            bbr: Some(BbrConfig {
+                initial_window: Some(0),
+            }),
+            ..ConnectionConfig::default()
+        };
+
+        assert!(matches!(
+            connection.validate(true),
+            Err(crate::QuincyError::Config(ConfigError::InvalidValue { field, .. }))
+                if field == "bbr.initial_window"
+        ));
+    }
+
+    #[test]
+    fn validate_rejects_zero_receive_tasks() {
+        let connection = ConnectionConfig {
+            receive_tasks: 0,
+            ..ConnectionConfig::default()
+        };
+
+        assert!(matches!(
+            connection.validate(true),
+            Err(crate::QuincyError::Config(ConfigError::InvalidValue { field, .. }))
+                if field == "receive_tasks"
+        ));
+    }
+
+    #[test]
+    fn validate_rejects_zero_app_ping_timeout() {
+        let connection = ConnectionConfig {
+            app_ping_interval_s: Some(10),
+            app_ping_timeout_s: 0,
+            ..ConnectionConfig::default()
+        };
+
+        assert!(matches!(
+            connection.validate(true),
+            Err(crate::QuincyError::Config(ConfigError::InvalidValue { field, .. }))
+                if field == "app_ping_timeout_s"
+        ));
+    }
+
+    #[test]
+    fn validate_rejects_zero_idle_disconnect() {
+        let connection = ConnectionConfig {
+            idle_disconnect_s: Some(0),
+            ..ConnectionConfig::default()
+        };
+
+        assert!(matches!(
+            connection.validate(true),
+            Err(crate::QuincyError::Config(ConfigError::InvalidValue { field, .. }))
+                if field == "idle_disconnect_s"
+        ));
+    }
+
+    #[test]
+    fn validate_rejects_zero_reconnect_backoff() {
+        let connection = ConnectionConfig {
+            reconnect_backoff_ms: 0,
+            ..ConnectionConfig::default()
+        };
+
+        assert!(matches!(
+            connection.validate(true),
+            Err(crate::QuincyError::Config(ConfigError::InvalidValue { field, .. }))
+                if field == "reconnect_backoff_ms"
+        ));
+    }
+
+    #[test]
+    fn parse_quality_sub_table() {
+        let toml = r#"
+            [quality]
+            poor_rtt_ms = 600
+            degraded_loss_percent = 0.5
+        "#;
+
+        let connection: ConnectionConfig = Figment::new()
+            .merge(Toml::string(toml))
+            .extract()
+            .expect("Failed to parse connection config");
+
+        assert_eq!(
+            connection.quality,
+            QualityConfig {
+                poor_rtt_ms: 600,
+                degraded_loss_percent: 0.5,
+                ..QualityConfig::default()
+            }
+        );
+        assert!(connection.validate(true).is_ok());
+    }
+
+    #[test]
+    fn validate_rejects_inverted_quality_thresholds() {
+        let connection = ConnectionConfig {
+            quality: QualityConfig {
+                degraded_rtt_ms: 500,
+                poor_rtt_ms: 400,
+                ..QualityConfig::default()
+            },
+            ..ConnectionConfig::default()
+        };
+
+        assert!(matches!(
+            connection.validate(true),
+            Err(crate::QuincyError::Config(ConfigError::InvalidValue { field, .. }))
+                if field == "quality.degraded_rtt_ms"
+        ));
+
+        let connection = ConnectionConfig {
+            quality: QualityConfig {
+                poor_loss_percent: 120.0,
+                ..QualityConfig::default()
+            },
+            ..ConnectionConfig::default()
+        };
+
+        assert!(matches!(
+            connection.validate(true),
+            Err(crate::QuincyError::Config(ConfigError::InvalidValue { field, .. }))
+                if field == "quality.poor_loss_percent"
+        ));
+    }
+
+    #[test]
+    fn app_ping_is_disabled_by_default() {
+        assert_eq!(ConnectionConfig::default().app_ping().unwrap(), None);
+    }
+
+    #[test]
+    fn validate_rejects_dscp_wider_than_six_bits() {
+        let connection = ConnectionConfig {
+            dscp: Some(64),
+            ..ConnectionConfig::default()
+        };
+
+        assert!(matches!(
+            connection.validate(true),
+            Err(crate::QuincyError::Config(ConfigError::InvalidValue { field, .. }))
+                if field == "dscp"
+        ));
+        assert!(
+            ConnectionConfig {
+                dscp: Some(63),
+                ..connection
+            }
+            .validate(true)
+            .is_ok()
+        );
+    }
+
+    #[test]
+    fn validate_rejects_keep_alive_not_below_connection_timeout() {
+        let config = ClientConfig {
+            connection: ConnectionConfig {
+                keep_alive_interval_s: 30,
+                ..ConnectionConfig::default()
+            },
+            ..validated_client_config()
+        };
+
+        assert!(matches!(
+            config.validate(),
+            Err(crate::QuincyError::Config(ConfigError::Conflict { conflict }))
+                if conflict.contains("keep_alive_interval_s")
+        ));
+    }
+
+    #[test]
+    fn validate_requires_trusted_certificate_source() {
+        let mut config = validated_client_config();
+        let ClientProtocolConfig::Tls(tls) = &mut config.protocol else {
+            unreachable!();
+        };
+        tls.trusted_certificates.clear();
+
+        assert!(matches!(
+            config.validate(),
+            Err(crate::QuincyError::Config(ConfigError::MissingField { field }))
+                if field == "protocol.trusted_certificate_paths"
+        ));
+    }
+
+    #[test]
+    fn default_template_parses_to_defaults() {
+        let template = ClientConfig::default_template();
+        let config: ClientConfig = Figment::new()
+            .merge(Toml::string(&template))
+            .extract()
+            .expect("template is valid TOML");
+
+        assert_eq!(config.connection, ConnectionConfig::default());
+        assert_eq!(config.network, NetworkConfig::default());
+        assert_eq!(config.log.level, default_log_level());
+        let ClientProtocolConfig::Tls(tls) = &config.protocol else {
+            panic!("template must use TLS mode");
+        };
+        assert_eq!(tls.key_exchange, default_tls_key_exchange());
+        assert!(tls.client_certificate_key.is_none());
+    }
+
+    #[test]
+    fn commented_toml_leaves_secrets_empty() {
+        let config = ClientConfig {
+            protocol: ClientProtocolConfig::Noise(ClientNoiseConfig {
+                key_exchange: NoiseKeyExchange::Hybrid,
+                server_public_key: "server-key".to_string(),
+                private_key: Some(SecretString::from("secret-key")),
+                private_key_file: None,
             }),
-            connection: ConnectionConfig::default(),
-            network: NetworkConfig::default(),
-            log: LogConfig {
-                level: "info".to_string(),
+            ..validated_client_config()
+        };
+
+        let template = config.to_commented_toml();
+
+        assert!(template.contains("key_exchange = \"hybrid\""));
+        assert!(template.contains("private_key = \"\""));
+        assert!(!template.contains("secret-key"));
+    }
+
+    #[test]
+    fn commented_toml_keeps_route_metrics() {
+        let mut config = validated_client_config();
+        config.network.routes = vec![
+            "10.0.1.0/24".parse().unwrap(),
+            RouteSpec {
+                net: "10.0.2.0/24".parse().unwrap(),
+                metric: Some(50),
             },
+        ];
+
+        let template = config.to_commented_toml();
+        let network = Figment::new()
+            .merge(Toml::string(&template))
+            .extract_inner::<NetworkConfig>("network")
+            .expect("template is valid TOML");
+
+        assert_eq!(network.routes, config.network.routes);
+    }
+
+    #[test]
+    fn restart_required_changes_ignore_routes_and_dns() {
+        let config = validated_client_config();
+        let mut reloaded = validated_client_config();
+        reloaded.network.routes = vec!["10.0.0.0/8".parse().unwrap()];
+        reloaded.network.dns_servers = vec!["9.9.9.9".parse().unwrap()];
+
+        assert!(config.restart_required_changes(&reloaded).is_empty());
+    }
+
+    #[test]
+    fn restart_required_changes_detect_secret_changes() {
+        let config = validated_client_config();
+        let mut reloaded = validated_client_config();
+        reloaded.connection_string = "127.0.0.1:55556".to_string();
+        let ClientProtocolConfig::Tls(tls) = &mut reloaded.protocol else {
+            unreachable!();
         };
+        tls.client_certificate_key = Some(SecretString::from("other"));
 
-        assert!(config.quinn_client_config().is_ok());
+        assert_eq!(
+            config.restart_required_changes(&reloaded),
+            vec!["connection_string", "protocol"]
+        );
+    }
+
+    #[test]
+    fn reloadable_settings_keep_restart_required_ones() {
+        let config = validated_client_config();
+        let mut reloaded = validated_client_config();
+        reloaded.connection_string = "127.0.0.1:55556".to_string();
+        reloaded.network.kill_switch = true;
+        reloaded.network.routes = vec!["10.0.0.0/8".parse().unwrap()];
+        reloaded.network.dns_servers = vec!["9.9.9.9".parse().unwrap()];
+
+        let live = config.with_reloadable_settings(&reloaded);
+
+        assert!(config.restart_required_changes(&live).is_empty());
+        assert_eq!(live.connection_string, config.connection_string);
+        assert!(!live.network.kill_switch);
+        assert_eq!(live.network.routes, reloaded.network.routes);
+        assert_eq!(live.network.dns_servers, reloaded.network.dns_servers);
+        assert_eq!(
+            live.restart_required_changes(&reloaded),
+            vec!["connection_string", "network.kill_switch"]
+        );
+    }
+
+    #[test]
+    fn parse_initial_rtt_accepts_range_bounds() {
+        for (initial_rtt_ms, expected) in [
+            (1, Duration::from_millis(1)),
+            (10000, Duration::from_secs(10)),
+        ] {
+            let connection: ConnectionConfig = Figment::new()
+                .merge(Toml::string(&format!("initial_rtt_ms = {initial_rtt_ms}")))
+                .extract()
+                .expect("Failed to parse connection config");
+
+            assert_eq!(connection.initial_rtt_ms, Some(initial_rtt_ms));
+            assert_eq!(connection.initial_rtt().unwrap(), Some(expected));
+            assert!(connection.as_transport_config(true).is_ok());
+        }
+    }
+
+    #[test]
+    fn parse_initial_rtt_rejects_out_of_range_values() {
+        for initial_rtt_ms in [0, 10001] {
+            let connection: ConnectionConfig = Figment::new()
+                .merge(Toml::string(&format!("initial_rtt_ms = {initial_rtt_ms}")))
+                .extract()
+                .expect("Failed to parse connection config");
+
+            assert!(matches!(
+                connection.validate(false),
+                Err(crate::QuincyError::Config(ConfigError::InvalidValue { field, .. }))
+                    if field == "initial_rtt_ms"
+            ));
+            assert!(connection.as_transport_config(false).is_err());
+        }
+    }
+
+    #[test]
+    fn transport_config_applies_stream_limits() {
+        let connection = ConnectionConfig {
+            max_concurrent_bidi_streams: 7,
+            max_concurrent_uni_streams: 3,
+            ..ConnectionConfig::default()
+        };
+
+        assert_eq!(
+            connection.stream_limits().unwrap(),
+            (VarInt::from_u32(7), VarInt::from_u32(3))
+        );
+        assert!(connection.as_transport_config(true).is_ok());
+    }
+
+    #[test]
+    fn transport_config_applies_flow_control_windows() {
+        let connection = ConnectionConfig {
+            stream_receive_window: Some(8_388_608),
+            receive_window: Some(67_108_864),
+            send_window: Some(33_554_432),
+            ..ConnectionConfig::default()
+        };
+
+        assert_eq!(
+            connection.flow_control_windows().unwrap(),
+            FlowControlWindows {
+                stream_receive: Some(VarInt::from_u32(8_388_608)),
+                receive: Some(VarInt::from_u32(67_108_864)),
+                send: Some(33_554_432),
+            }
+        );
+        assert!(connection.as_transport_config(true).is_ok());
+    }
+
+    #[test]
+    fn transport_config_rejects_oversized_stream_receive_window() {
+        let connection = ConnectionConfig {
+            stream_receive_window: Some(u64::from(u32::MAX) + 1),
+            ..ConnectionConfig::default()
+        };
+
+        assert!(matches!(
+            connection.as_transport_config(true),
+            Err(crate::QuincyError::Config(ConfigError::InvalidValue { field, .. }))
+                if field == "stream_receive_window"
+        ));
+    }
+
+    #[test]
+    fn transport_config_rejects_oversized_receive_window() {
+        let connection = ConnectionConfig {
+            receive_window: Some(u64::MAX),
+            ..ConnectionConfig::default()
+        };
+
+        assert!(matches!(
+            connection.as_transport_config(false),
+            Err(crate::QuincyError::Config(ConfigError::InvalidValue { field, .. }))
+                if field == "receive_window"
+        ));
+    }
+
+    #[test]
+    fn transport_config_rejects_zero_stream_limits() {
+        let connection = ConnectionConfig {
+            max_concurrent_uni_streams: 0,
+            ..ConnectionConfig::default()
+        };
+
+        assert!(matches!(
+            connection.as_transport_config(false),
+            Err(crate::QuincyError::Config(ConfigError::InvalidValue { field, .. }))
+                if field == "max_concurrent_uni_streams"
+        ));
+    }
+
+    #[test]
+    fn transport_config_with_pmtud_does_not_pin_mtu() {
+        let toml = r#"
+            mtu = 1400
+            pmtud = true
+        "#;
+
+        let connection: ConnectionConfig = Figment::new()
+            .merge(Toml::string(toml))
+            .extract()
+            .expect("Failed to parse connection config");
+        let pinned = ConnectionConfig {
+            pmtud: false,
+            ..connection.clone()
+        };
+
+        assert!(connection.pmtud);
+        assert!(!pinned.pmtud);
+        // The MTU with overhead that a config without PMTUD pins as its minimum
+        assert_eq!(pinned.quic_mtu().unwrap(), 1450);
+        assert!(connection.as_transport_config(true).is_ok());
+        assert!(pinned.as_transport_config(true).is_ok());
+    }
+
+    #[test]
+    fn transport_config_prefers_millisecond_timeout() {
+        let connection = ConnectionConfig {
+            connection_timeout_ms: Some(750),
+            // Keep-alives need an idle timeout above their interval
+            max_idle_timeout_s: Some(60),
+            ..ConnectionConfig::default()
+        };
+
+        assert_eq!(
+            connection.connection_timeout().unwrap(),
+            Duration::from_millis(750)
+        );
+        assert!(connection.as_transport_config(true).is_ok());
+
+        let without_idle_timeout = ConnectionConfig {
+            max_idle_timeout_s: None,
+            ..connection
+        };
+        let (field, timeout) = without_idle_timeout
+            .idle_timeout()
+            .expect("valid idle timeout");
+
+        assert_eq!(field, "connection_timeout_ms");
+        assert_eq!(timeout, Duration::from_millis(750));
+        // The default keep-alive interval is not below a sub-second idle timeout
+        assert!(matches!(
+            without_idle_timeout.as_transport_config(true),
+            Err(crate::QuincyError::Config(ConfigError::Conflict { .. }))
+        ));
+    }
+
+    #[test]
+    fn idle_timeout_falls_back_to_connection_timeout() {
+        let connection = ConnectionConfig::default();
+
+        let (field, timeout) = connection.idle_timeout().expect("valid idle timeout");
+
+        assert_eq!(field, "connection_timeout_s");
+        assert_eq!(timeout, Duration::from_secs(default_timeout_s()));
+    }
+
+    #[test]
+    fn idle_timeout_is_independent_of_connection_timeout() {
+        let connection = ConnectionConfig {
+            connection_timeout_s: 5,
+            max_idle_timeout_s: Some(120),
+            keep_alive_interval_s: 60,
+            ..ConnectionConfig::default()
+        };
+
+        let (field, timeout) = connection.idle_timeout().expect("valid idle timeout");
+
+        assert_eq!(field, "max_idle_timeout_s");
+        assert_eq!(timeout, Duration::from_secs(120));
+        assert_eq!(
+            connection.connection_timeout().unwrap(),
+            Duration::from_secs(5)
+        );
+        assert!(connection.as_transport_config(true).is_ok());
+    }
+
+    #[test]
+    fn transport_config_rejects_keep_alive_not_below_idle_timeout() {
+        let connection = ConnectionConfig {
+            max_idle_timeout_s: Some(20),
+            keep_alive_interval_s: 20,
+            ..ConnectionConfig::default()
+        };
+
+        assert!(matches!(
+            connection.as_transport_config(true),
+            Err(crate::QuincyError::Config(ConfigError::Conflict { .. }))
+        ));
+        // Servers do not send keep-alives
+        assert!(connection.as_transport_config(false).is_ok());
+    }
+
+    #[test]
+    fn transport_config_disables_keep_alive_when_zero() {
+        let disabled = ConnectionConfig {
+            keep_alive_interval_s: 0,
+            ..ConnectionConfig::default()
+        };
+        let enabled = ConnectionConfig {
+            keep_alive_interval_s: 20,
+            ..ConnectionConfig::default()
+        };
+
+        let (field, idle_timeout) = disabled.idle_timeout().unwrap();
+        assert_eq!(
+            disabled.keep_alive_interval(field, idle_timeout).unwrap(),
+            None
+        );
+        let (field, idle_timeout) = enabled.idle_timeout().unwrap();
+        assert_eq!(
+            enabled.keep_alive_interval(field, idle_timeout).unwrap(),
+            Some(Duration::from_secs(20))
+        );
+        assert!(disabled.as_transport_config(true).is_ok());
+        assert!(enabled.as_transport_config(true).is_ok());
+    }
+
+    #[test]
+    fn transport_config_rejects_both_timeouts() {
+        let connection = ConnectionConfig {
+            connection_timeout_s: 10,
+            connection_timeout_ms: Some(750),
+            ..ConnectionConfig::default()
+        };
+
+        assert!(matches!(
+            connection.as_transport_config(true),
+            Err(crate::QuincyError::Config(ConfigError::Conflict { .. }))
+        ));
+    }
+
+    #[test]
+    fn transport_config_rejects_out_of_bounds_millisecond_timeout() {
+        let connection = ConnectionConfig {
+            connection_timeout_ms: Some(u64::MAX),
+            ..ConnectionConfig::default()
+        };
+
+        assert!(matches!(
+            connection.as_transport_config(true),
+            Err(crate::QuincyError::Config(ConfigError::InvalidValue { field, .. }))
+                if field == "connection_timeout_ms"
+        ));
     }
 
     #[test]
@@ -1869,6 +6028,142 @@ mod tests {
         assert_eq!(config.metrics.idle_timeout_s, 300);
     }
 
+    #[test]
+    fn auth_lockout_defaults_and_overrides() {
+        let toml = r#"
+            name = "quincy-server"
+            tunnel_network = "10.0.0.1/24"
+            users_file = "/path/to/users.toml"
+
+            [protocol]
+            mode = "noise"
+            key_exchange = "Standard"
+            private_key = "AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA="
+
+            [auth_lockout]
+            max_failures = 3
+            lockout_s = 30
+
+            [log]
+            level = "info"
+        "#;
+
+        let config: ServerConfig = Figment::new()
+            .merge(Toml::string(toml))
+            .extract()
+            .expect("Failed to parse server config");
+
+        assert_eq!(
+            config.auth_lockout,
+            AuthLockoutConfig {
+                max_failures: 3,
+                lockout_s: 30,
+                ..AuthLockoutConfig::default()
+            }
+        );
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn auth_lockout_longer_than_maximum_is_rejected() {
+        let lockout = AuthLockoutConfig {
+            lockout_s: 7200,
+            ..AuthLockoutConfig::default()
+        };
+
+        assert!(matches!(
+            lockout.validate(),
+            Err(crate::QuincyError::Config(ConfigError::Conflict { .. }))
+        ));
+    }
+
+    #[test]
+    fn disabled_auth_lockout_skips_validation() {
+        let lockout = AuthLockoutConfig {
+            max_failures: 0,
+            window_s: 0,
+            ..AuthLockoutConfig::default()
+        };
+
+        assert!(lockout.validate().is_ok());
+    }
+
+    #[test]
+    fn secondary_tunnel_network_must_differ_in_family() {
+        let toml = r#"
+            name = "test"
+            tunnel_network = "10.0.0.1/24"
+            users_file = "users.toml"
+
+            [protocol]
+            mode = "noise"
+            private_key = "AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA="
+
+            [log]
+            level = "info"
+        "#;
+
+        let parse = |secondary: &str| -> ServerConfig {
+            Figment::new()
+                .merge(Toml::string(toml))
+                .merge(Toml::string(&format!(
+                    "secondary_tunnel_network = \"{secondary}\""
+                )))
+                .extract()
+                .expect("Failed to parse server config")
+        };
+
+        let dual_stack = parse("fd00::1/64");
+        assert_eq!(
+            dual_stack.secondary_tunnel_network,
+            Some("fd00::1/64".parse().unwrap())
+        );
+        assert!(dual_stack.validate().is_ok());
+
+        assert!(matches!(
+            parse("10.1.0.1/24").validate(),
+            Err(crate::QuincyError::Config(ConfigError::Conflict { .. }))
+        ));
+    }
+
+    #[test]
+    fn groups_are_parsed() {
+        let toml = r#"
+            name = "test"
+            tunnel_network = "10.0.0.1/24"
+            users_file = "users.toml"
+
+            [protocol]
+            mode = "noise"
+            private_key = "AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA="
+
+            [groups.engineering]
+            allowed_destinations = ["10.1.0.0/16"]
+            bandwidth_limit = "100 mbps"
+
+            [groups.default]
+            allowed_destinations = ["10.2.0.0/24"]
+
+            [log]
+            level = "info"
+        "#;
+
+        let config: ServerConfig = Figment::new()
+            .merge(Toml::string(toml))
+            .extract()
+            .expect("Failed to parse server config");
+
+        assert_eq!(config.groups.len(), 2);
+        assert_eq!(
+            config.groups["engineering"],
+            GroupConfig {
+                allowed_destinations: vec!["10.1.0.0/16".parse().unwrap()],
+                bandwidth_limit: Some("100 mbps".parse().unwrap()),
+            }
+        );
+        assert_eq!(config.groups["default"].bandwidth_limit, None);
+    }
+
     // --- AddressRange tests ---
 
     #[test]
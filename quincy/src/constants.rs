@@ -7,6 +7,9 @@ use rustls::crypto::aws_lc_rs::cipher_suite::TLS13_AES_128_GCM_SHA256;
 /// Represents the maximum MTU overhead for QUIC, since the QUIC header is variable in size.
 pub const QUIC_MTU_OVERHEAD: u16 = 50;
 
+/// Smallest UDP payload every QUIC path must support (RFC 9000, section 14).
+pub const QUIC_MIN_UDP_PAYLOAD_SIZE: u16 = 1200;
+
 /// Packet buffer size for operations on the TUN interface.
 pub const PACKET_BUFFER_SIZE: usize = 4;
 
@@ -23,6 +26,21 @@ pub const PACKET_CHANNEL_SIZE: usize = 1024 * 1024;
 /// floor.
 pub const MIN_SOCKET_BUFFER_SIZE: usize = 128 * 1024;
 
+/// Percentage of the requested socket buffer size below which a buffer clamped
+/// by the OS is reported.
+pub const SOCKET_BUFFER_WARNING_THRESHOLD_PERCENT: usize = 90;
+
+/// Largest DSCP value, which occupies the upper six bits of the IPv4 ToS and IPv6
+/// traffic class fields.
+pub const MAX_DSCP: u8 = 63;
+
+/// Scheme of connection strings naming a domain whose DNS SRV records list the server endpoints.
+pub const SRV_CONNECTION_SCHEME: &str = "srv://";
+
+/// Application error code with which the server closes the connections of clients
+/// whose credentials it does not accept.
+pub const AUTH_FAILED_ERROR_CODE: u32 = 0x04;
+
 /// Represents the supported TLS protocol versions for Quincy.
 pub static TLS_PROTOCOL_VERSIONS: &[&rustls::SupportedProtocolVersion] = &[&rustls::version::TLS13];
 
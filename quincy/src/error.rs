@@ -80,10 +80,22 @@ pub enum AuthError {
     #[error("Handshake rejected")]
     HandshakeRejected,
 
+    /// The server rejected the client's credentials (key or certificate)
+    #[error("Invalid credentials")]
+    InvalidCredentials,
+
     /// Peer identity not found in the users file after successful handshake
     #[error("User unknown")]
     UserUnknown,
 
+    /// The user's account is past its expiry date
+    #[error("Account of user '{username}' expired")]
+    AccountExpired { username: String },
+
+    /// The user's groups grant no access
+    #[error("Permission denied for user '{username}': no group grants access")]
+    PermissionDenied { username: String },
+
     /// Authentication timeout
     #[error("Authentication timeout")]
     Timeout,
@@ -583,58 +595,77 @@ impl From<tracing::subscriber::SetGlobalDefaultError> for QuincyError {
 
 impl From<figment::Error> for QuincyError {
     fn from(err: figment::Error) -> Self {
-        let config_error = if err.path.is_empty() {
+        let config_error = if let figment::error::Kind::MissingField(field) = &err.kind {
+            // The error path points at the parent of the missing field (empty for top-level fields)
+            let field = err
+                .path
+                .iter()
+                .map(String::as_str)
+                .chain(std::iter::once(field.as_ref()))
+                .collect::<Vec<_>>()
+                .join(".");
+            ConfigError::MissingField { field }
+        } else if err.path.is_empty() {
             ConfigError::ParseError {
                 message: err.to_string(),
             }
         } else {
             let path = PathBuf::from(err.path.join("."));
+            // Name the provider of the offending value, e.g. the configuration file
+            let source = err
+                .metadata
+                .as_ref()
+                .map(|metadata| match &metadata.source {
+                    Some(source) => format!(" in {source}"),
+                    None => format!(" in {}", metadata.name),
+                })
+                .unwrap_or_default();
             match err.kind {
                 figment::error::Kind::MissingField(field) => ConfigError::MissingField {
                     field: field.to_string(),
                 },
                 figment::error::Kind::InvalidType(_, _) => ConfigError::InvalidValue {
                     field: path.to_string_lossy().to_string(),
-                    reason: "invalid type".to_string(),
+                    reason: format!("invalid type{source}"),
                 },
                 figment::error::Kind::InvalidLength(_, _) => ConfigError::InvalidValue {
                     field: path.to_string_lossy().to_string(),
-                    reason: "invalid length".to_string(),
+                    reason: format!("invalid length{source}"),
                 },
                 figment::error::Kind::UnknownVariant(_, _) => ConfigError::InvalidValue {
                     field: path.to_string_lossy().to_string(),
-                    reason: "unknown variant".to_string(),
+                    reason: format!("unknown variant{source}"),
                 },
                 figment::error::Kind::UnknownField(..) => ConfigError::InvalidValue {
                     field: path.to_string_lossy().to_string(),
-                    reason: "unknown field".to_string(),
+                    reason: format!("unknown field{source}"),
                 },
                 figment::error::Kind::UnsupportedKey(..) => ConfigError::InvalidValue {
                     field: path.to_string_lossy().to_string(),
-                    reason: "unsupported key".to_string(),
+                    reason: format!("unsupported key{source}"),
                 },
                 figment::error::Kind::ISizeOutOfRange(_) => ConfigError::InvalidValue {
                     field: path.to_string_lossy().to_string(),
-                    reason: "integer out of range".to_string(),
+                    reason: format!("integer out of range{source}"),
                 },
                 figment::error::Kind::Unsupported(_) => ConfigError::InvalidValue {
                     field: path.to_string_lossy().to_string(),
-                    reason: "unsupported value".to_string(),
+                    reason: format!("unsupported value{source}"),
                 },
                 figment::error::Kind::Message(_) => ConfigError::ParseError {
                     message: err.to_string(),
                 },
                 figment::error::Kind::InvalidValue(_, _) => ConfigError::InvalidValue {
                     field: path.to_string_lossy().to_string(),
-                    reason: "invalid value".to_string(),
+                    reason: format!("invalid value{source}"),
                 },
                 figment::error::Kind::DuplicateField(_) => ConfigError::InvalidValue {
                     field: path.to_string_lossy().to_string(),
-                    reason: "duplicate field".to_string(),
+                    reason: format!("duplicate field{source}"),
                 },
                 figment::error::Kind::USizeOutOfRange(_) => ConfigError::InvalidValue {
                     field: path.to_string_lossy().to_string(),
-                    reason: "integer out of range".to_string(),
+                    reason: format!("integer out of range{source}"),
                 },
             }
         };
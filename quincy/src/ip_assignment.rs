@@ -2,24 +2,119 @@
 //!
 //! After the QUIC handshake completes (which includes authentication via Noise
 //! allowed-keys or TLS mTLS), the server opens a uni-directional stream to send
-//! the client its assigned IP address and the server's tunnel address.
+//! the client its assigned IP address and the server's tunnel address, along with
+//! the network settings it recommends to clients.
+//!
+//! A reconnecting client may first send the resumption token of its previous
+//! assignment over a uni-directional stream of its own, asking for the same address.
 
 use std::{net::IpAddr, time::Duration};
 
+use base64::prelude::*;
 use ipnet::IpNet;
 use quinn::Connection;
+use rand_core::{OsRng, RngCore};
 use serde::{Deserialize, Serialize};
 use tokio::time::timeout;
 
 use crate::error::{AuthError, Result};
 
+/// The smallest tunnel MTU a server may advertise (the IPv4 minimum reassembly size).
+const MIN_TUNNEL_MTU: u16 = 576;
+/// The largest IP assignment payload accepted from a server, in bytes.
+const MAX_ASSIGNMENT_SIZE: usize = 64 * 1024;
+/// The largest resumption request payload accepted from a client, in bytes.
+const MAX_RESUMPTION_REQUEST_SIZE: usize = 1024;
+/// The number of random bytes in a resumption token.
+const RESUMPTION_TOKEN_LEN: usize = 32;
+
 /// IP assignment payload sent from server to client after authentication.
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub struct IpAssignment {
     /// The IP address assigned to the client (with network mask).
     pub client_address: IpNet,
     /// The server's tunnel address (with network mask).
     pub server_address: IpNet,
+    /// When the client's account expires, in seconds since the Unix epoch.
+    ///
+    /// Omitted for accounts without an expiry date and by servers predating it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub account_expires_at: Option<u64>,
+    /// The client's address of the other IP family on dual-stack servers (with network mask).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub secondary_client_address: Option<IpNet>,
+    /// The server's tunnel address of the other IP family on dual-stack servers (with network mask).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub secondary_server_address: Option<IpNet>,
+    /// The tunnel MTU supported by the server.
+    ///
+    /// Omitted by servers predating MTU negotiation.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tunnel_mtu: Option<u16>,
+    /// Network settings recommended by the server.
+    ///
+    /// Omitted if the server pushes none and by servers predating pushed settings.
+    #[serde(default, skip_serializing_if = "PushedNetworkConfig::is_empty")]
+    pub pushed: PushedNetworkConfig,
+    /// Opaque token the client presents when reconnecting to get the same address back.
+    ///
+    /// Omitted by servers that do not issue resumption tokens.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resumption_token: Option<String>,
+    /// Whether the address was assigned for a resumption token presented by the client.
+    ///
+    /// False if the token was invalid or expired, or its address was assigned to another client.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub resumed: bool,
+}
+
+/// Request for the address of a previous assignment, sent by a reconnecting client.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ResumptionRequest {
+    /// The resumption token of the previous assignment, if the client holds one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resumption_token: Option<String>,
+}
+
+/// Network settings a server pushes to its clients, similar to OpenVPN's pushed options.
+///
+/// Configured in the `[push]` section of the server configuration, e.g.:
+/// ```toml
+/// [push]
+/// routes = ["10.0.1.0/24"]
+/// dns_servers = ["10.0.1.1"]
+/// dns_search_domains = ["corp.example.com"]
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PushedNetworkConfig {
+    /// Routes/networks to be routed through the tunnel
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub routes: Vec<IpNet>,
+    /// DNS servers to use for the tunnel
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub dns_servers: Vec<IpAddr>,
+    /// Domains appended to unqualified host names
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub dns_search_domains: Vec<String>,
+}
+
+impl PushedNetworkConfig {
+    /// Returns whether no network settings are pushed.
+    pub fn is_empty(&self) -> bool {
+        self.routes.is_empty() && self.dns_servers.is_empty() && self.dns_search_domains.is_empty()
+    }
+}
+
+/// Negotiates the MTU of the client's tunnel interface.
+///
+/// ### Arguments
+/// - `configured` - the MTU configured on the client
+/// - `advertised` - the tunnel MTU advertised by the server, if any
+///
+/// ### Returns
+/// The smaller of both MTUs, or the configured MTU if the server advertised none.
+pub fn negotiate_mtu(configured: u16, advertised: Option<u16>) -> u16 {
+    advertised.map_or(configured, |advertised| configured.min(advertised))
 }
 
 /// Sends an IP assignment to the client over a QUIC uni-directional stream.
@@ -83,7 +178,7 @@ pub async fn recv_ip_assignment(
             .map_err(|_| AuthError::IpAssignmentFailed)?;
 
         let payload = recv_stream
-            .read_to_end(4096)
+            .read_to_end(MAX_ASSIGNMENT_SIZE)
             .await
             .map_err(|_| AuthError::IpAssignmentFailed)?;
 
@@ -97,6 +192,80 @@ pub async fn recv_ip_assignment(
     .map_err(|_| AuthError::Timeout)?
 }
 
+/// Generates a new random resumption token.
+///
+/// ### Returns
+/// - `String` - 32 random bytes, encoded as URL-safe base64
+pub fn generate_resumption_token() -> String {
+    let mut token = [0u8; RESUMPTION_TOKEN_LEN];
+    OsRng.fill_bytes(&mut token);
+
+    BASE64_URL_SAFE_NO_PAD.encode(token)
+}
+
+/// Sends a resumption request to the server over a QUIC uni-directional stream.
+///
+/// Servers that do not issue resumption tokens never accept the stream, so the
+/// request is only written to the stream's buffer.
+///
+/// ### Arguments
+/// - `connection` - the established QUIC connection
+/// - `request` - the resumption request to send
+/// - `duration` - timeout for the entire operation
+///
+/// ### Errors
+/// Returns an error if the stream cannot be opened, the write fails, or the
+/// operation times out.
+pub async fn send_resumption_request(
+    connection: &Connection,
+    request: &ResumptionRequest,
+    duration: Duration,
+) -> Result<()> {
+    timeout(duration, async {
+        let mut send_stream = connection
+            .open_uni()
+            .await
+            .map_err(|_| AuthError::IpAssignmentFailed)?;
+
+        let payload = serde_json::to_vec(request)?;
+        send_stream.write_all(&payload).await?;
+        send_stream
+            .finish()
+            .map_err(|_| AuthError::IpAssignmentFailed)?;
+
+        Ok::<(), crate::error::QuincyError>(())
+    })
+    .await
+    .map_err(|_| AuthError::Timeout)?
+}
+
+/// Receives the resumption token of a reconnecting client over a QUIC uni-directional stream.
+///
+/// Clients predating resumption tokens send no request, and malformed requests are
+/// ignored, so that the client is assigned a fresh address instead.
+///
+/// ### Arguments
+/// - `connection` - the established QUIC connection
+/// - `duration` - how long to wait for the request
+///
+/// ### Returns
+/// - `Option<String>` - the presented token, or `None` if the client presented none
+pub async fn recv_resumption_token(connection: &Connection, duration: Duration) -> Option<String> {
+    let request = timeout(duration, async {
+        let mut recv_stream = connection.accept_uni().await.ok()?;
+        let payload = recv_stream
+            .read_to_end(MAX_RESUMPTION_REQUEST_SIZE)
+            .await
+            .ok()?;
+
+        serde_json::from_slice::<ResumptionRequest>(&payload).ok()
+    })
+    .await
+    .ok()??;
+
+    request.resumption_token
+}
+
 /// Validates that an IP assignment address is safe for use as a tunnel endpoint.
 ///
 /// Rejects loopback, unspecified, multicast, and broadcast addresses,
@@ -131,7 +300,9 @@ fn validate_assignment_address(ipnet: IpNet) -> Result<()> {
 ///
 /// Rejects assignments where either address is loopback, unspecified,
 /// multicast, or broadcast. Also validates that addresses share the same
-/// subnet and have non-zero prefix lengths.
+/// subnet and have non-zero prefix lengths. Secondary addresses must be
+/// given together and belong to the other IP family. An advertised tunnel
+/// MTU must be at least the IPv4 minimum of 576 bytes.
 ///
 /// ### Arguments
 /// - `assignment` - the IP assignment to validate
@@ -139,9 +310,40 @@ fn validate_assignment_address(ipnet: IpNet) -> Result<()> {
 /// ### Errors
 /// Returns an error if the assignment contains unsafe addresses.
 fn validate_assignment(assignment: &IpAssignment) -> Result<()> {
-    let client = assignment.client_address;
-    let server = assignment.server_address;
+    validate_address_pair(assignment.client_address, assignment.server_address)?;
+
+    if assignment
+        .tunnel_mtu
+        .is_some_and(|mtu| mtu < MIN_TUNNEL_MTU)
+    {
+        return Err(AuthError::IpAssignmentFailed.into());
+    }
+
+    match (
+        assignment.secondary_client_address,
+        assignment.secondary_server_address,
+    ) {
+        (None, None) => Ok(()),
+        (Some(client), Some(server)) => {
+            if client.addr().is_ipv4() == assignment.client_address.addr().is_ipv4() {
+                return Err(AuthError::IpAssignmentFailed.into());
+            }
+
+            validate_address_pair(client, server)
+        }
+        _ => Err(AuthError::IpAssignmentFailed.into()),
+    }
+}
 
+/// Validates a client address and the matching server address.
+///
+/// ### Arguments
+/// - `client` - the client's tunnel address
+/// - `server` - the server's tunnel address
+///
+/// ### Errors
+/// Returns an error if either address is unsafe or they are in different subnets.
+fn validate_address_pair(client: IpNet, server: IpNet) -> Result<()> {
     validate_assignment_address(client)?;
     validate_assignment_address(server)?;
 
@@ -262,6 +464,7 @@ mod tests {
             let assignment = IpAssignment {
                 client_address: make_ipv4("10.0.0.2", 24),
                 server_address: make_ipv4("10.0.0.1", 24),
+                ..Default::default()
             };
             assert!(validate_assignment(&assignment).is_ok());
         }
@@ -271,6 +474,7 @@ mod tests {
             let assignment = IpAssignment {
                 client_address: make_ipv6("fd00::2", 64),
                 server_address: make_ipv6("fd00::1", 64),
+                ..Default::default()
             };
             assert!(validate_assignment(&assignment).is_ok());
         }
@@ -280,6 +484,7 @@ mod tests {
             let assignment = IpAssignment {
                 client_address: make_ipv4("10.0.0.2", 24),
                 server_address: make_ipv4("192.168.1.1", 24),
+                ..Default::default()
             };
             let result = validate_assignment(&assignment);
             assert!(result.is_err());
@@ -294,6 +499,7 @@ mod tests {
             let assignment = IpAssignment {
                 client_address: make_ipv6("fd00::2", 64),
                 server_address: make_ipv6("fd01::1", 64),
+                ..Default::default()
             };
             let result = validate_assignment(&assignment);
             assert!(result.is_err());
@@ -304,6 +510,7 @@ mod tests {
             let assignment = IpAssignment {
                 client_address: make_ipv4("10.0.0.2", 24),
                 server_address: make_ipv4("10.0.1.1", 24),
+                ..Default::default()
             };
             let result = validate_assignment(&assignment);
             assert!(result.is_err());
@@ -314,6 +521,7 @@ mod tests {
             let assignment = IpAssignment {
                 client_address: make_ipv4("10.0.1.2", 24),
                 server_address: make_ipv4("10.0.2.1", 24),
+                ..Default::default()
             };
             let result = validate_assignment(&assignment);
             assert!(result.is_err());
@@ -324,6 +532,7 @@ mod tests {
             let assignment = IpAssignment {
                 client_address: make_ipv4("127.0.0.1", 8),
                 server_address: make_ipv4("10.0.0.1", 24),
+                ..Default::default()
             };
             let result = validate_assignment(&assignment);
             assert!(result.is_err());
@@ -334,6 +543,7 @@ mod tests {
             let assignment = IpAssignment {
                 client_address: make_ipv4("10.0.0.2", 24),
                 server_address: make_ipv4("127.0.0.1", 8),
+                ..Default::default()
             };
             let result = validate_assignment(&assignment);
             assert!(result.is_err());
@@ -344,6 +554,7 @@ mod tests {
             let assignment = IpAssignment {
                 client_address: make_ipv4("0.0.0.0", 24),
                 server_address: make_ipv4("10.0.0.1", 24),
+                ..Default::default()
             };
             let result = validate_assignment(&assignment);
             assert!(result.is_err());
@@ -354,6 +565,7 @@ mod tests {
             let assignment = IpAssignment {
                 client_address: make_ipv4("224.0.0.1", 24),
                 server_address: make_ipv4("10.0.0.1", 24),
+                ..Default::default()
             };
             let result = validate_assignment(&assignment);
             assert!(result.is_err());
@@ -364,6 +576,7 @@ mod tests {
             let assignment = IpAssignment {
                 client_address: make_ipv4("10.0.0.2", 24),
                 server_address: make_ipv4("255.255.255.255", 32),
+                ..Default::default()
             };
             let result = validate_assignment(&assignment);
             assert!(result.is_err());
@@ -374,9 +587,231 @@ mod tests {
             let assignment = IpAssignment {
                 client_address: make_ipv4("10.0.0.2", 0),
                 server_address: make_ipv4("10.0.0.1", 0),
+                ..Default::default()
             };
             let result = validate_assignment(&assignment);
             assert!(result.is_err());
         }
     }
+
+    mod serialization {
+        use super::*;
+
+        #[test]
+        fn assignment_without_expiry_omits_field() {
+            let assignment = IpAssignment {
+                client_address: make_ipv4("10.0.0.2", 24),
+                server_address: make_ipv4("10.0.0.1", 24),
+                ..Default::default()
+            };
+            let json = serde_json::to_string(&assignment).unwrap();
+            assert!(!json.contains("account_expires_at"));
+
+            let parsed: IpAssignment = serde_json::from_str(&json).unwrap();
+            assert_eq!(parsed.account_expires_at, None);
+        }
+
+        #[test]
+        fn assignment_with_expiry_round_trips() {
+            let assignment = IpAssignment {
+                client_address: make_ipv4("10.0.0.2", 24),
+                server_address: make_ipv4("10.0.0.1", 24),
+                account_expires_at: Some(1_767_225_600),
+                ..Default::default()
+            };
+            let json = serde_json::to_string(&assignment).unwrap();
+
+            let parsed: IpAssignment = serde_json::from_str(&json).unwrap();
+            assert_eq!(parsed.account_expires_at, Some(1_767_225_600));
+        }
+
+        #[test]
+        fn assignment_with_resumption_token_round_trips() {
+            let assignment = IpAssignment {
+                resumption_token: Some(generate_resumption_token()),
+                resumed: true,
+                ..with_expiry(None)
+            };
+            let json = serde_json::to_string(&assignment).unwrap();
+
+            let parsed: IpAssignment = serde_json::from_str(&json).unwrap();
+            assert_eq!(parsed, assignment);
+        }
+
+        #[test]
+        fn assignment_without_resumption_omits_fields() {
+            let json = serde_json::to_string(&with_expiry(None)).unwrap();
+
+            assert!(!json.contains("resumption_token"));
+            assert!(!json.contains("resumed"));
+        }
+
+        #[test]
+        fn resumption_tokens_are_unique() {
+            let token = generate_resumption_token();
+
+            assert_eq!(token.len(), 43);
+            assert_ne!(token, generate_resumption_token());
+        }
+
+        fn with_expiry(account_expires_at: Option<u64>) -> IpAssignment {
+            IpAssignment {
+                client_address: make_ipv4("10.0.0.2", 24),
+                server_address: make_ipv4("10.0.0.1", 24),
+                account_expires_at,
+                ..Default::default()
+            }
+        }
+
+        #[test]
+        fn assignment_with_secondary_addresses_round_trips() {
+            let assignment = IpAssignment {
+                client_address: make_ipv4("10.0.0.2", 24),
+                server_address: make_ipv4("10.0.0.1", 24),
+                secondary_client_address: Some(make_ipv6("fd00::2", 64)),
+                secondary_server_address: Some(make_ipv6("fd00::1", 64)),
+                ..Default::default()
+            };
+            let json = serde_json::to_string(&assignment).unwrap();
+
+            let parsed: IpAssignment = serde_json::from_str(&json).unwrap();
+            assert_eq!(
+                parsed.secondary_client_address,
+                Some(make_ipv6("fd00::2", 64))
+            );
+            assert_eq!(
+                parsed.secondary_server_address,
+                Some(make_ipv6("fd00::1", 64))
+            );
+        }
+    }
+
+    mod validate_secondary_addresses {
+        use super::*;
+
+        fn dual_stack(
+            secondary_client: Option<IpNet>,
+            secondary_server: Option<IpNet>,
+        ) -> IpAssignment {
+            IpAssignment {
+                client_address: make_ipv4("10.0.0.2", 24),
+                server_address: make_ipv4("10.0.0.1", 24),
+                secondary_client_address: secondary_client,
+                secondary_server_address: secondary_server,
+                ..Default::default()
+            }
+        }
+
+        #[test]
+        fn accepts_dual_stack_assignment() {
+            let assignment = dual_stack(
+                Some(make_ipv6("fd00::2", 64)),
+                Some(make_ipv6("fd00::1", 64)),
+            );
+            assert!(validate_assignment(&assignment).is_ok());
+        }
+
+        #[test]
+        fn rejects_secondary_client_without_server() {
+            let assignment = dual_stack(Some(make_ipv6("fd00::2", 64)), None);
+            assert!(validate_assignment(&assignment).is_err());
+        }
+
+        #[test]
+        fn rejects_secondary_of_same_family() {
+            let assignment = dual_stack(
+                Some(make_ipv4("10.1.0.2", 24)),
+                Some(make_ipv4("10.1.0.1", 24)),
+            );
+            assert!(validate_assignment(&assignment).is_err());
+        }
+
+        #[test]
+        fn rejects_secondary_in_different_subnets() {
+            let assignment = dual_stack(
+                Some(make_ipv6("fd00::2", 64)),
+                Some(make_ipv6("fd01::1", 64)),
+            );
+            assert!(validate_assignment(&assignment).is_err());
+        }
+    }
+
+    mod tunnel_mtu {
+        use super::*;
+
+        fn with_mtu(tunnel_mtu: Option<u16>) -> IpAssignment {
+            IpAssignment {
+                client_address: make_ipv4("10.0.0.2", 24),
+                server_address: make_ipv4("10.0.0.1", 24),
+                tunnel_mtu,
+                ..Default::default()
+            }
+        }
+
+        #[test]
+        fn negotiates_smaller_mtu() {
+            assert_eq!(negotiate_mtu(1400, Some(1280)), 1280);
+            assert_eq!(negotiate_mtu(1280, Some(1400)), 1280);
+            assert_eq!(negotiate_mtu(1400, Some(1400)), 1400);
+        }
+
+        #[test]
+        fn keeps_configured_mtu_without_advertisement() {
+            assert_eq!(negotiate_mtu(1400, None), 1400);
+        }
+
+        #[test]
+        fn rejects_mtu_below_minimum() {
+            assert!(validate_assignment(&with_mtu(Some(1280))).is_ok());
+            assert!(validate_assignment(&with_mtu(None)).is_ok());
+            assert!(validate_assignment(&with_mtu(Some(575))).is_err());
+        }
+    }
+
+    mod pushed_network_config {
+        use super::*;
+
+        #[test]
+        fn assignment_without_pushed_settings_is_parsed() {
+            let payload = r#"{"client_address":"10.0.0.2/24","server_address":"10.0.0.1/24"}"#;
+
+            let assignment: IpAssignment = serde_json::from_str(payload).unwrap();
+
+            assert!(assignment.pushed.is_empty());
+        }
+
+        #[test]
+        fn pushed_settings_round_trip() {
+            let assignment = with_pushed(PushedNetworkConfig {
+                routes: vec![make_ipv4("10.0.1.0", 24)],
+                dns_servers: vec!["10.0.1.1".parse().unwrap(), "fd00::53".parse().unwrap()],
+                dns_search_domains: vec!["corp.example.com".to_string()],
+            });
+
+            let payload = serde_json::to_vec(&assignment).unwrap();
+
+            assert_eq!(
+                serde_json::from_slice::<IpAssignment>(&payload).unwrap(),
+                assignment
+            );
+        }
+
+        #[test]
+        fn empty_pushed_settings_are_omitted() {
+            let assignment = with_pushed(PushedNetworkConfig::default());
+
+            let payload = serde_json::to_string(&assignment).unwrap();
+
+            assert!(!payload.contains("pushed"));
+        }
+
+        fn with_pushed(pushed: PushedNetworkConfig) -> IpAssignment {
+            IpAssignment {
+                client_address: make_ipv4("10.0.0.2", 24),
+                server_address: make_ipv4("10.0.0.1", 24),
+                pushed,
+                ..Default::default()
+            }
+        }
+    }
 }
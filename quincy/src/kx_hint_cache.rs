@@ -0,0 +1,450 @@
+//! Persistent TLS key-exchange hint cache.
+//!
+//! Remembers the key-exchange group each server negotiated in a file, so that a
+//! restarted client sends the correct key share immediately instead of paying for
+//! a `HelloRetryRequest` round trip on its first handshake.
+//!
+//! Session tickets are not persisted: rustls does not expose a serialisation
+//! format for TLS 1.3 session values, so tickets are kept in memory for the
+//! lifetime of the process and the first handshake after a restart is always
+//! a full one.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use rustls::NamedGroup;
+use rustls::client::{
+    ClientSessionMemoryCache, ClientSessionStore, Tls12ClientSessionValue, Tls13ClientSessionValue,
+};
+use rustls::pki_types::ServerName;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, warn};
+
+/// Version of the on-disk cache format.
+const CACHE_FORMAT_VERSION: u32 = 1;
+/// Number of servers kept in the in-memory ticket cache.
+const MEMORY_CACHE_SIZE: usize = 32;
+/// Maximum age of a persisted entry, after which the server may have changed its groups.
+const MAX_ENTRY_AGE: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// On-disk representation of the hint cache.
+#[derive(Debug, Default, Deserialize, Serialize)]
+struct CacheFile {
+    version: u32,
+    servers: HashMap<String, CachedServer>,
+}
+
+/// Persisted key-exchange hint for a single server.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+struct CachedServer {
+    /// The key-exchange group the server last negotiated
+    kx_group: u16,
+    /// Seconds since the Unix epoch when this entry was last written
+    updated_at: u64,
+}
+
+/// The persisted hints, along with a counter of the changes made to them.
+#[derive(Debug, Default)]
+struct CachedServers {
+    generation: u64,
+    servers: HashMap<String, CachedServer>,
+}
+
+/// A rustls client session store that persists key-exchange hints to a file.
+///
+/// Session tickets are delegated to an in-memory cache and do not survive a restart.
+#[derive(Debug)]
+pub struct KxHintCache {
+    path: PathBuf,
+    memory: ClientSessionMemoryCache,
+    servers: Mutex<CachedServers>,
+    /// Generation of the hints last written to the file, locked while writing
+    written_generation: Mutex<u64>,
+}
+
+impl KxHintCache {
+    /// Creates a hint cache backed by the given file, loading any previously persisted hints.
+    ///
+    /// Missing, corrupt, outdated or expired cache contents are ignored and the
+    /// cache starts fresh.
+    ///
+    /// ### Arguments
+    /// - `path` - the path of the cache file
+    pub fn load(path: &Path) -> Self {
+        let servers: HashMap<_, _> = match read_cache_file(path) {
+            Some(cache) => {
+                let now = unix_now();
+                cache
+                    .servers
+                    .into_iter()
+                    .filter(|(_, entry)| !is_expired(entry, now))
+                    .collect()
+            }
+            None => HashMap::new(),
+        };
+
+        debug!(
+            "Loaded {} cached TLS key-exchange hints from {}",
+            servers.len(),
+            path.display()
+        );
+
+        Self {
+            path: path.to_path_buf(),
+            memory: ClientSessionMemoryCache::new(MEMORY_CACHE_SIZE),
+            servers: Mutex::new(CachedServers {
+                generation: 0,
+                servers,
+            }),
+            written_generation: Mutex::new(0),
+        }
+    }
+
+    /// Writes a snapshot of the hints to the cache file.
+    ///
+    /// Snapshots older than the last written one are skipped, so that concurrent
+    /// handshakes cannot overwrite newer hints with stale ones. Failures are logged
+    /// and otherwise ignored, as the cache is only an optimisation.
+    ///
+    /// ### Arguments
+    /// - `generation` - the generation of the snapshot
+    /// - `servers` - the hints to write
+    fn persist(&self, generation: u64, servers: HashMap<String, CachedServer>) {
+        let mut written_generation = self
+            .written_generation
+            .lock()
+            .expect("hint cache write lock is not poisoned");
+        if *written_generation >= generation {
+            return;
+        }
+
+        let cache = CacheFile {
+            version: CACHE_FORMAT_VERSION,
+            servers,
+        };
+
+        let result = serde_json::to_vec(&cache)
+            .map_err(|e| e.to_string())
+            .and_then(|data| write_atomically(&self.path, &data).map_err(|e| e.to_string()));
+
+        match result {
+            Ok(()) => *written_generation = generation,
+            Err(e) => warn!(
+                "Failed to write TLS key-exchange hint cache to {}: {e}",
+                self.path.display()
+            ),
+        }
+    }
+}
+
+impl ClientSessionStore for KxHintCache {
+    fn set_kx_hint(&self, server_name: ServerName<'static>, group: NamedGroup) {
+        self.memory.set_kx_hint(server_name.clone(), group);
+
+        // Take a snapshot under the lock and write it after releasing the lock, so that
+        // concurrent handshakes looking up hints do not wait for the file system
+        let (generation, snapshot) = {
+            let mut cached = self
+                .servers
+                .lock()
+                .expect("hint cache lock is not poisoned");
+            cached.servers.insert(
+                server_name.to_str().into_owned(),
+                CachedServer {
+                    kx_group: u16::from(group),
+                    updated_at: unix_now(),
+                },
+            );
+            cached.generation += 1;
+
+            (cached.generation, cached.servers.clone())
+        };
+
+        self.persist(generation, snapshot);
+    }
+
+    fn kx_hint(&self, server_name: &ServerName<'_>) -> Option<NamedGroup> {
+        self.memory.kx_hint(server_name).or_else(|| {
+            let cached = self
+                .servers
+                .lock()
+                .expect("hint cache lock is not poisoned");
+            cached
+                .servers
+                .get(server_name.to_str().as_ref())
+                .map(|entry| NamedGroup::from(entry.kx_group))
+        })
+    }
+
+    fn set_tls12_session(&self, server_name: ServerName<'static>, value: Tls12ClientSessionValue) {
+        self.memory.set_tls12_session(server_name, value);
+    }
+
+    fn tls12_session(&self, server_name: &ServerName<'_>) -> Option<Tls12ClientSessionValue> {
+        self.memory.tls12_session(server_name)
+    }
+
+    fn remove_tls12_session(&self, server_name: &ServerName<'static>) {
+        self.memory.remove_tls12_session(server_name);
+    }
+
+    fn insert_tls13_ticket(
+        &self,
+        server_name: ServerName<'static>,
+        value: Tls13ClientSessionValue,
+    ) {
+        self.memory.insert_tls13_ticket(server_name, value);
+    }
+
+    fn take_tls13_ticket(
+        &self,
+        server_name: &ServerName<'static>,
+    ) -> Option<Tls13ClientSessionValue> {
+        self.memory.take_tls13_ticket(server_name)
+    }
+}
+
+/// Reads and validates the cache file, returning `None` if it is missing or unusable.
+fn read_cache_file(path: &Path) -> Option<CacheFile> {
+    let data = match fs::read(path) {
+        Ok(data) => data,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return None,
+        Err(e) => {
+            warn!(
+                "Failed to read TLS key-exchange hint cache {}: {e}",
+                path.display()
+            );
+            return None;
+        }
+    };
+
+    match serde_json::from_slice::<CacheFile>(&data) {
+        Ok(cache) if cache.version == CACHE_FORMAT_VERSION => Some(cache),
+        Ok(cache) => {
+            debug!(
+                "Ignoring TLS key-exchange hint cache {} with unsupported version {}",
+                path.display(),
+                cache.version
+            );
+            None
+        }
+        Err(e) => {
+            warn!(
+                "Ignoring corrupt TLS key-exchange hint cache {}: {e}",
+                path.display()
+            );
+            None
+        }
+    }
+}
+
+/// Writes data to a temporary file next to `path` and renames it into place.
+fn write_atomically(path: &Path, data: &[u8]) -> std::io::Result<()> {
+    let tmp_path = path.with_extension("tmp");
+    fs::write(&tmp_path, data)?;
+    fs::rename(&tmp_path, path)
+}
+
+fn is_expired(entry: &CachedServer, now: u64) -> bool {
+    now.saturating_sub(entry.updated_at) > MAX_ENTRY_AGE.as_secs()
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::certificates::{load_certificates_from_pem, load_private_key_from_pem};
+    use rustls::crypto::CryptoProvider;
+    use rustls::crypto::aws_lc_rs::{self, kx_group};
+    use rustls::{
+        ClientConfig, ClientConnection, HandshakeKind, RootCertStore, ServerConfig,
+        ServerConnection,
+    };
+    use std::sync::Arc;
+    use tempfile::TempDir;
+
+    const SERVER_CERT_PEM: &str =
+        include_str!("../../quincy-tests/tests/static/server_cert_pkcs8.pem");
+    const SERVER_KEY_PEM: &str =
+        include_str!("../../quincy-tests/tests/static/server_key_pkcs8.pem");
+
+    fn server_name() -> ServerName<'static> {
+        ServerName::try_from("quincy.example.com").expect("valid server name")
+    }
+
+    /// Runs an in-memory TLS 1.3 handshake with a server that only supports X25519,
+    /// while the client prefers P-256, and returns the kind of handshake performed.
+    fn handshake(store: Arc<KxHintCache>) -> HandshakeKind {
+        let certs = load_certificates_from_pem(SERVER_CERT_PEM).unwrap();
+        let key = load_private_key_from_pem(SERVER_KEY_PEM).unwrap();
+
+        let server_provider = CryptoProvider {
+            kx_groups: vec![kx_group::X25519],
+            ..aws_lc_rs::default_provider()
+        };
+        let server_config = ServerConfig::builder_with_provider(server_provider.into())
+            .with_protocol_versions(&[&rustls::version::TLS13])
+            .unwrap()
+            .with_no_client_auth()
+            .with_single_cert(certs.clone(), key)
+            .unwrap();
+
+        let mut roots = RootCertStore::empty();
+        roots.add(certs[0].clone()).unwrap();
+        let client_provider = CryptoProvider {
+            kx_groups: vec![kx_group::SECP256R1, kx_group::X25519],
+            ..aws_lc_rs::default_provider()
+        };
+        let mut client_config = ClientConfig::builder_with_provider(client_provider.into())
+            .with_protocol_versions(&[&rustls::version::TLS13])
+            .unwrap()
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+        client_config.resumption = rustls::client::Resumption::store(store);
+
+        let mut client = ClientConnection::new(
+            Arc::new(client_config),
+            ServerName::try_from("localhost").unwrap(),
+        )
+        .unwrap();
+        let mut server = ServerConnection::new(Arc::new(server_config)).unwrap();
+
+        for _ in 0..10 {
+            if !client.is_handshaking() && !server.is_handshaking() {
+                break;
+            }
+
+            let mut buffer = Vec::new();
+            while client.wants_write() {
+                client.write_tls(&mut buffer).unwrap();
+            }
+            let mut data = buffer.as_slice();
+            while !data.is_empty() {
+                server.read_tls(&mut data).unwrap();
+                server.process_new_packets().unwrap();
+            }
+
+            let mut buffer = Vec::new();
+            while server.wants_write() {
+                server.write_tls(&mut buffer).unwrap();
+            }
+            let mut data = buffer.as_slice();
+            while !data.is_empty() {
+                client.read_tls(&mut data).unwrap();
+                client.process_new_packets().unwrap();
+            }
+        }
+
+        assert!(!client.is_handshaking() && !server.is_handshaking());
+        client.handshake_kind().expect("handshake completed")
+    }
+
+    #[test]
+    fn warm_start_loads_persisted_entry() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("kx-hints.json");
+
+        let cold = KxHintCache::load(&path);
+        assert_eq!(cold.kx_hint(&server_name()), None);
+        cold.set_kx_hint(server_name(), NamedGroup::X25519MLKEM768);
+        drop(cold);
+
+        let warm = KxHintCache::load(&path);
+        assert_eq!(
+            warm.kx_hint(&server_name()),
+            Some(NamedGroup::X25519MLKEM768)
+        );
+    }
+
+    #[test]
+    fn warm_start_skips_hello_retry_request() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("kx-hints.json");
+
+        // The cold client guesses P-256 first and is asked by the server to retry with X25519
+        let cold = Arc::new(KxHintCache::load(&path));
+        assert_eq!(
+            handshake(cold.clone()),
+            HandshakeKind::FullWithHelloRetryRequest
+        );
+        drop(cold);
+
+        // A separate cache loaded from the same file sends the X25519 key share right away.
+        // Its ticket cache starts empty, so the handshake is a full one
+        let warm = Arc::new(KxHintCache::load(&path));
+        assert_eq!(handshake(warm), HandshakeKind::Full);
+    }
+
+    #[test]
+    fn corrupt_cache_starts_fresh() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("kx-hints.json");
+        fs::write(&path, b"{not json").unwrap();
+
+        let store = KxHintCache::load(&path);
+        assert_eq!(store.kx_hint(&server_name()), None);
+
+        // The corrupt file is replaced on the next write
+        store.set_kx_hint(server_name(), NamedGroup::X25519);
+        let reloaded = KxHintCache::load(&path);
+        assert_eq!(reloaded.kx_hint(&server_name()), Some(NamedGroup::X25519));
+    }
+
+    #[test]
+    fn stale_snapshots_are_not_written() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("kx-hints.json");
+
+        let store = KxHintCache::load(&path);
+        store.set_kx_hint(server_name(), NamedGroup::X25519);
+
+        // A snapshot taken before the last write must not replace the newer hints
+        store.persist(1, HashMap::new());
+        let reloaded = KxHintCache::load(&path);
+        assert_eq!(reloaded.kx_hint(&server_name()), Some(NamedGroup::X25519));
+    }
+
+    #[test]
+    fn expired_entries_are_ignored() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("kx-hints.json");
+
+        let cache = CacheFile {
+            version: CACHE_FORMAT_VERSION,
+            servers: HashMap::from([(
+                "quincy.example.com".to_string(),
+                CachedServer {
+                    kx_group: u16::from(NamedGroup::X25519),
+                    updated_at: unix_now() - MAX_ENTRY_AGE.as_secs() - 1,
+                },
+            )]),
+        };
+        fs::write(&path, serde_json::to_vec(&cache).unwrap()).unwrap();
+
+        let store = KxHintCache::load(&path);
+        assert_eq!(store.kx_hint(&server_name()), None);
+    }
+
+    #[test]
+    fn unsupported_version_is_ignored() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("kx-hints.json");
+        fs::write(
+            &path,
+            r#"{"version":999,"servers":{"quincy.example.com":{"kx_group":29,"updated_at":0}}}"#,
+        )
+        .unwrap();
+
+        let store = KxHintCache::load(&path);
+        assert_eq!(store.kx_hint(&server_name()), None);
+    }
+}
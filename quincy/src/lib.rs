@@ -10,6 +10,7 @@ pub mod config;
 pub mod constants;
 pub mod error;
 pub mod ip_assignment;
+pub mod kx_hint_cache;
 pub mod network;
 pub mod utils;
 
@@ -0,0 +1,200 @@
+//! Packet capture of tunnel interface traffic.
+//!
+//! Writes every packet read from and written to the tunnel interface into a pcap
+//! file. Packets are wrapped in Linux cooked capture (SLL) headers, whose packet
+//! type marks the direction, so captures open in Wireshark or tcpdump with
+//! incoming and outgoing packets told apart.
+
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use tracing::{info, warn};
+
+use crate::Result;
+
+/// Magic number of pcap files with microsecond timestamps.
+const PCAP_MAGIC: u32 = 0xa1b2_c3d4;
+/// Link type of Linux cooked capture (SLL) headers.
+const LINKTYPE_LINUX_SLL: u32 = 113;
+/// Maximum captured length of a packet.
+const SNAPLEN: u32 = u16::MAX as u32;
+/// Length of the pcap file header.
+const FILE_HEADER_LEN: usize = 24;
+/// Length of a pcap record header.
+const RECORD_HEADER_LEN: usize = 16;
+/// Length of a Linux cooked capture header.
+const SLL_HEADER_LEN: usize = 16;
+/// ARP hardware type of devices without a link-layer header.
+const ARPHRD_NONE: u16 = 0xfffe;
+
+/// Direction of a captured packet, relative to the tunnel interface.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CaptureDirection {
+    /// Written to the interface (received through the tunnel)
+    Inbound,
+    /// Read from the interface (sent through the tunnel)
+    Outbound,
+}
+
+impl CaptureDirection {
+    /// Returns the SLL packet type of the direction.
+    fn packet_type(self) -> u16 {
+        match self {
+            // PACKET_HOST
+            CaptureDirection::Inbound => 0,
+            // PACKET_OUTGOING
+            CaptureDirection::Outbound => 4,
+        }
+    }
+}
+
+/// Writer of a pcap capture file with a size limit.
+pub struct PacketCapture {
+    state: Mutex<CaptureState>,
+    max_bytes: u64,
+}
+
+struct CaptureState {
+    /// The capture file, `None` once capturing has stopped
+    file: Option<File>,
+    written: u64,
+}
+
+impl PacketCapture {
+    /// Creates the capture file, truncating it if it exists.
+    ///
+    /// ### Arguments
+    /// - `path` - path to the pcap file
+    /// - `max_bytes` - size of the file after which capturing stops
+    ///
+    /// ### Errors
+    /// Returns `QuincyError::Io` if the file cannot be created or written to.
+    pub fn create(path: &Path, max_bytes: u64) -> Result<Self> {
+        let mut file = File::create(path)?;
+
+        let mut header = Vec::with_capacity(FILE_HEADER_LEN);
+        header.extend_from_slice(&PCAP_MAGIC.to_ne_bytes());
+        header.extend_from_slice(&2u16.to_ne_bytes());
+        header.extend_from_slice(&4u16.to_ne_bytes());
+        // Time zone offset and timestamp accuracy
+        header.extend_from_slice(&0i32.to_ne_bytes());
+        header.extend_from_slice(&0u32.to_ne_bytes());
+        header.extend_from_slice(&SNAPLEN.to_ne_bytes());
+        header.extend_from_slice(&LINKTYPE_LINUX_SLL.to_ne_bytes());
+        file.write_all(&header)?;
+
+        info!("Capturing tunnel packets to {}", path.display());
+
+        Ok(Self {
+            state: Mutex::new(CaptureState {
+                file: Some(file),
+                written: header.len() as u64,
+            }),
+            max_bytes,
+        })
+    }
+
+    /// Appends a packet to the capture.
+    ///
+    /// Capturing stops once the file would grow beyond its size limit or a write fails.
+    ///
+    /// ### Arguments
+    /// - `direction` - whether the packet was read from or written to the interface
+    /// - `packet` - the IP packet
+    pub fn record(&self, direction: CaptureDirection, packet: &[u8]) {
+        let mut guard = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let state = &mut *guard;
+        let Some(file) = state.file.as_mut() else {
+            return;
+        };
+
+        let record = pcap_record(direction, packet, SystemTime::now());
+        if state.written + record.len() as u64 > self.max_bytes {
+            info!(
+                "Packet capture reached its limit of {} bytes, capture stopped",
+                self.max_bytes
+            );
+            state.file = None;
+            return;
+        }
+
+        if let Err(e) = file.write_all(&record) {
+            warn!("Failed to write packet capture, capture stopped: {e}");
+            state.file = None;
+            return;
+        }
+        state.written += record.len() as u64;
+    }
+}
+
+/// Encodes a packet as a pcap record with a Linux cooked capture header.
+fn pcap_record(direction: CaptureDirection, packet: &[u8], timestamp: SystemTime) -> Vec<u8> {
+    let timestamp = timestamp.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let captured = &packet[..packet.len().min(SNAPLEN as usize - SLL_HEADER_LEN)];
+    let protocol: u16 = match packet.first().map(|byte| byte >> 4) {
+        Some(6) => 0x86dd,
+        _ => 0x0800,
+    };
+
+    let mut record = Vec::with_capacity(RECORD_HEADER_LEN + SLL_HEADER_LEN + captured.len());
+    record.extend_from_slice(&(timestamp.as_secs() as u32).to_ne_bytes());
+    record.extend_from_slice(&timestamp.subsec_micros().to_ne_bytes());
+    record.extend_from_slice(&((SLL_HEADER_LEN + captured.len()) as u32).to_ne_bytes());
+    record.extend_from_slice(&((SLL_HEADER_LEN + packet.len()) as u32).to_ne_bytes());
+
+    record.extend_from_slice(&direction.packet_type().to_be_bytes());
+    record.extend_from_slice(&ARPHRD_NONE.to_be_bytes());
+    // No link-layer address
+    record.extend_from_slice(&[0; 10]);
+    record.extend_from_slice(&protocol.to_be_bytes());
+
+    record.extend_from_slice(captured);
+
+    record
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    const IPV4_PACKET: [u8; 4] = [0x45, 0x00, 0x00, 0x04];
+
+    #[test]
+    fn record_carries_direction_and_protocol() {
+        let timestamp = UNIX_EPOCH + Duration::from_micros(1_500_000);
+        let record = pcap_record(CaptureDirection::Outbound, &IPV4_PACKET, timestamp);
+
+        assert_eq!(record.len(), RECORD_HEADER_LEN + SLL_HEADER_LEN + 4);
+        assert_eq!(record[0..4], 1u32.to_ne_bytes());
+        assert_eq!(record[4..8], 500_000u32.to_ne_bytes());
+        assert_eq!(record[8..12], 20u32.to_ne_bytes());
+        assert_eq!(record[16..18], [0, 4]);
+        assert_eq!(record[30..32], [0x08, 0x00]);
+        assert_eq!(record[32..], IPV4_PACKET);
+
+        let record = pcap_record(CaptureDirection::Inbound, &[0x60], timestamp);
+        assert_eq!(record[16..18], [0, 0]);
+        assert_eq!(record[30..32], [0x86, 0xdd]);
+    }
+
+    #[test]
+    fn capture_stops_at_size_limit() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("capture.pcap");
+        let record_len = (RECORD_HEADER_LEN + SLL_HEADER_LEN + IPV4_PACKET.len()) as u64;
+
+        let capture =
+            PacketCapture::create(&path, FILE_HEADER_LEN as u64 + 2 * record_len).unwrap();
+        for _ in 0..3 {
+            capture.record(CaptureDirection::Inbound, &IPV4_PACKET);
+        }
+        drop(capture);
+
+        let written = std::fs::metadata(&path).unwrap().len();
+        assert_eq!(written, FILE_HEADER_LEN as u64 + 2 * record_len);
+    }
+}
@@ -0,0 +1,336 @@
+//! Snapshots of the system resolver configuration, restored after a crash.
+//!
+//! The resolver state replaced by the tunnel's DNS configuration is written to a sidecar
+//! file before it is changed and removed once it has been cleaned up. A snapshot that is
+//! still present when the client starts was left behind by a run that did not clean up,
+//! e.g. because the process was killed, and is restored before the tunnel is brought up.
+
+use crate::Result;
+use crate::error::DnsError;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+/// File name of the DNS configuration snapshot.
+const BACKUP_FILE_NAME: &str = "dns-backup.json";
+
+/// Resolver state of the endpoint before the tunnel's DNS configuration was applied.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DnsBackup {
+    /// The ID of the process that applied the DNS configuration
+    pub pid: u32,
+    /// The name of the tunnel interface the DNS configuration was applied to
+    pub interface_name: String,
+    /// Files replaced or created by the DNS configuration
+    #[serde(default)]
+    pub files: Vec<FileBackup>,
+    /// DNS servers of each network service before they were replaced (macOS)
+    #[serde(default)]
+    pub service_dns_servers: Vec<(String, Vec<IpAddr>)>,
+    /// Search domains of each network service before they were replaced (macOS)
+    #[serde(default)]
+    pub service_search_domains: Vec<(String, Vec<String>)>,
+}
+
+/// A file replaced or created by the DNS configuration.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileBackup {
+    /// The path of the file
+    pub path: PathBuf,
+    /// The prior contents of the file, or none if the file did not exist
+    pub contents: Option<String>,
+}
+
+impl DnsBackup {
+    /// Creates an empty snapshot of the resolver state of the given interface.
+    ///
+    /// ### Arguments
+    /// - `interface_name` - the name of the tunnel interface
+    pub fn new(interface_name: &str) -> Self {
+        Self {
+            pid: std::process::id(),
+            interface_name: interface_name.to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// Records the current contents of a file, which are restored if the snapshot is stale.
+    ///
+    /// ### Arguments
+    /// - `path` - the path of the file
+    ///
+    /// ### Errors
+    /// Returns `DnsError::BackupFailed` if the file exists but cannot be read.
+    pub fn add_file(&mut self, path: &Path) -> Result<()> {
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => Some(contents),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+            Err(_) => return Err(DnsError::BackupFailed.into()),
+        };
+
+        self.files.push(FileBackup {
+            path: path.to_path_buf(),
+            contents,
+        });
+
+        Ok(())
+    }
+
+    /// Writes the snapshot to the given path.
+    ///
+    /// The snapshot is written to a temporary file first, so that a crash while saving never
+    /// leaves a truncated snapshot behind.
+    ///
+    /// ### Arguments
+    /// - `path` - the path of the snapshot file
+    ///
+    /// ### Errors
+    /// Returns `DnsError::BackupFailed` if the snapshot cannot be written.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|_| DnsError::BackupFailed)?;
+        }
+
+        let contents = serde_json::to_vec_pretty(self).map_err(|_| DnsError::BackupFailed)?;
+        let temp_path = path.with_extension("tmp");
+        fs::write(&temp_path, contents).map_err(|_| DnsError::BackupFailed)?;
+        fs::rename(&temp_path, path).map_err(|_| DnsError::BackupFailed)?;
+
+        Ok(())
+    }
+
+    /// Reads the snapshot at the given path.
+    ///
+    /// ### Arguments
+    /// - `path` - the path of the snapshot file
+    ///
+    /// ### Returns
+    /// The snapshot, or none if there is no snapshot at the path.
+    ///
+    /// ### Errors
+    /// Returns `DnsError::RestoreFailed` if the snapshot cannot be read or parsed.
+    pub fn load(path: &Path) -> Result<Option<Self>> {
+        let contents = match fs::read(path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(_) => return Err(DnsError::RestoreFailed.into()),
+        };
+
+        serde_json::from_slice(&contents)
+            .map(Some)
+            .map_err(|_| DnsError::RestoreFailed.into())
+    }
+
+    /// Removes the snapshot at the given path, if any.
+    ///
+    /// ### Arguments
+    /// - `path` - the path of the snapshot file
+    ///
+    /// ### Errors
+    /// Returns `DnsError::BackupFailed` if the snapshot exists but cannot be removed.
+    pub fn discard(path: &Path) -> Result<()> {
+        match fs::remove_file(path) {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+            Err(_) => Err(DnsError::BackupFailed.into()),
+        }
+    }
+
+    /// Writes back the recorded file contents, removing files that did not exist.
+    ///
+    /// ### Errors
+    /// Returns `DnsError::RestoreFailed` if a file cannot be restored.
+    fn restore_files(&self) -> Result<()> {
+        for file in &self.files {
+            let restored = match &file.contents {
+                Some(contents) => fs::write(&file.path, contents),
+                None => match fs::remove_file(&file.path) {
+                    Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+                    result => result,
+                },
+            };
+            restored.map_err(|_| DnsError::RestoreFailed)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns the path of the DNS configuration snapshot.
+///
+/// The snapshot is kept in a runtime directory where the resolver state is reset on reboot
+/// as well, except on macOS and Windows, where the network service settings persist.
+pub fn dns_backup_path() -> PathBuf {
+    #[cfg(any(target_os = "linux", target_os = "freebsd"))]
+    let directory = PathBuf::from("/var/run/quincy");
+    #[cfg(target_os = "macos")]
+    let directory = PathBuf::from("/var/db/quincy");
+    #[cfg(target_os = "windows")]
+    let directory = std::env::var_os("ProgramData")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(r"C:\ProgramData"))
+        .join("Quincy");
+
+    directory.join(BACKUP_FILE_NAME)
+}
+
+/// Restores the DNS configuration snapshot left behind by an earlier run, if any.
+///
+/// A snapshot is stale once the process that saved it has exited. Must be called before the
+/// tunnel's DNS configuration is applied, as the snapshot is replaced when it is.
+///
+/// ### Arguments
+/// - `path` - the path of the snapshot file
+///
+/// ### Returns
+/// Whether a stale snapshot was restored.
+///
+/// ### Errors
+/// Returns `DnsError::RestoreFailed` if the snapshot cannot be read or restored.
+pub fn restore_stale_dns_backup(path: &Path) -> Result<bool> {
+    restore_stale_backup(path, is_process_running, super::restore_dns_state)
+}
+
+fn restore_stale_backup(
+    path: &Path,
+    is_running: impl FnOnce(u32) -> bool,
+    restore_state: impl FnOnce(&DnsBackup) -> Result<()>,
+) -> Result<bool> {
+    let Some(backup) = DnsBackup::load(path)? else {
+        return Ok(false);
+    };
+    if backup.pid != std::process::id() && is_running(backup.pid) {
+        info!(
+            "DNS configuration of interface {} is owned by running process {}, leaving it",
+            backup.interface_name, backup.pid
+        );
+        return Ok(false);
+    }
+
+    warn!(
+        "Found DNS configuration of interface {} left behind by an earlier run, restoring it",
+        backup.interface_name
+    );
+    restore_state(&backup)?;
+    backup.restore_files()?;
+    DnsBackup::discard(path).map_err(|_| DnsError::RestoreFailed)?;
+    info!("Restored the DNS configuration from {}", path.display());
+
+    Ok(true)
+}
+
+/// Returns whether a process with the given ID is running.
+#[cfg(unix)]
+fn is_process_running(pid: u32) -> bool {
+    let Ok(pid) = libc::pid_t::try_from(pid) else {
+        return false;
+    };
+
+    // SAFETY: signal 0 only checks whether the process exists and may be signalled
+    let result = unsafe { libc::kill(pid, 0) };
+    result == 0 || io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+/// Returns whether a process with the given ID is running.
+///
+/// Always false on Windows, where a stale snapshot holds no state besides the adapter's.
+#[cfg(windows)]
+fn is_process_running(_pid: u32) -> bool {
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stale_backup_is_restored() {
+        let dir = tempfile::tempdir().unwrap();
+        let backup_path = dir.path().join(BACKUP_FILE_NAME);
+        let resolv_conf = dir.path().join("resolv.conf");
+        let resolver_file = dir.path().join("corp.example.com");
+
+        // Resolver state before the crashed run configured the tunnel's DNS servers
+        fs::write(&resolv_conf, "nameserver 192.0.2.53\n").unwrap();
+        let mut backup = DnsBackup::new("quincy0");
+        backup.add_file(&resolv_conf).unwrap();
+        backup.add_file(&resolver_file).unwrap();
+        backup.save(&backup_path).unwrap();
+
+        // Left behind by the crashed run
+        fs::write(&resolv_conf, "nameserver 10.0.0.1\n").unwrap();
+        fs::write(&resolver_file, "nameserver 10.0.1.1\n").unwrap();
+
+        let mut restored_state = None;
+        let restored = restore_stale_backup(
+            &backup_path,
+            |_| false,
+            |backup| {
+                restored_state = Some(backup.clone());
+                Ok(())
+            },
+        )
+        .unwrap();
+
+        assert!(restored);
+        assert_eq!(restored_state, Some(backup));
+        assert_eq!(
+            fs::read_to_string(&resolv_conf).unwrap(),
+            "nameserver 192.0.2.53\n"
+        );
+        assert!(!resolver_file.exists());
+        assert!(!backup_path.exists());
+    }
+
+    #[test]
+    fn backup_of_running_process_is_not_restored() {
+        let dir = tempfile::tempdir().unwrap();
+        let backup_path = dir.path().join(BACKUP_FILE_NAME);
+        let mut backup = DnsBackup::new("quincy0");
+        backup.pid = std::process::id() + 1;
+        backup.save(&backup_path).unwrap();
+
+        let restored = restore_stale_backup(
+            &backup_path,
+            |_| true,
+            |_| panic!("the owning process is still running"),
+        )
+        .unwrap();
+
+        assert!(!restored);
+        assert!(backup_path.exists());
+    }
+
+    #[test]
+    fn missing_backup_is_not_restored() {
+        let dir = tempfile::tempdir().unwrap();
+        let backup_path = dir.path().join(BACKUP_FILE_NAME);
+
+        let restored = restore_stale_backup(
+            &backup_path,
+            |_| false,
+            |_| panic!("no resolver state to restore"),
+        )
+        .unwrap();
+
+        assert!(!restored);
+    }
+
+    #[test]
+    fn corrupt_backup_fails_to_restore() {
+        let dir = tempfile::tempdir().unwrap();
+        let backup_path = dir.path().join(BACKUP_FILE_NAME);
+        fs::write(&backup_path, "{ not json").unwrap();
+
+        let result = restore_stale_backup(&backup_path, |_| false, |_| Ok(()));
+
+        assert!(matches!(
+            result,
+            Err(crate::QuincyError::Dns(DnsError::RestoreFailed))
+        ));
+        assert!(backup_path.exists());
+    }
+}
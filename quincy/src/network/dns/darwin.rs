@@ -1,18 +1,40 @@
 use crate::Result;
 use crate::error::DnsError;
+use crate::network::dns::backup::DnsBackup;
+use crate::network::dns::leak_protection::pf_rules;
+use crate::network::dns::{SplitDnsDomain, is_valid_domain, resolver_file_contents};
+use crate::network::firewall::{FirewallCommand, FirewallFeature, PFCTL_COMMAND};
 use crate::utils::command::run_command;
-use dashmap::DashMap;
+use dashmap::{DashMap, DashSet};
+use std::fs;
 use std::net::IpAddr;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
-use std::sync::LazyLock;
+use std::sync::{LazyLock, Mutex};
+use tracing::warn;
 
 /// Command name for the macOS `networksetup` utility.
 const NETWORK_SETUP_COMMAND: &str = "networksetup";
 const DNS_GET_ARG: &str = "-getdnsservers";
 const DNS_SET_ARG: &str = "-setdnsservers";
 const SERVICES_GET_ARG: &str = "-listallnetworkservices";
+const SEARCH_DOMAINS_GET_ARG: &str = "-getsearchdomains";
+const SEARCH_DOMAINS_SET_ARG: &str = "-setsearchdomains";
+
+/// Directory of the per-domain resolver files read by the macOS resolver.
+const RESOLVER_DIR: &str = "/etc/resolver";
 
 static SERVICE_DNS_SERVERS: LazyLock<DashMap<String, Vec<IpAddr>>> = LazyLock::new(DashMap::new);
+/// Search domains of the network services before they were replaced, restored when the DNS
+/// servers are deleted.
+static SERVICE_SEARCH_DOMAINS: LazyLock<DashMap<String, Vec<String>>> = LazyLock::new(DashMap::new);
+/// Resolver files written for split domains, removed when the DNS servers are deleted.
+static RESOLVER_FILES: LazyLock<DashSet<PathBuf>> = LazyLock::new(DashSet::new);
+
+/// pf anchor holding the DNS leak protection rules, evaluated by the default macOS ruleset.
+const LEAK_PROTECTION_ANCHOR: &str = "com.apple/quincy-dns";
+/// Reference token of pf being enabled for DNS leak protection, released when the rules are removed.
+static PF_TOKEN: Mutex<Option<String>> = Mutex::new(None);
 
 /// Gets the names of all network services on the endpoint.
 ///
@@ -51,60 +73,43 @@ fn get_service_names() -> Result<Vec<String>> {
 
 /// Adds a list of DNS servers to all network services on the endpoint.
 ///
+/// With split domains, the network services are left untouched and a resolver file
+/// is written to `/etc/resolver/` for each domain instead. The search domains then
+/// are not applied.
+///
 /// ### Arguments
 /// - `dns_servers` - the DNS servers to be added
-/// - `interface_name` - the name of the interface to add the DNS servers to (unused)
-pub fn add_dns_servers(dns_servers: &[IpAddr], _interface_name: &str) -> Result<()> {
+/// - `split_domains` - the domains resolved through the tunnel, or none for all domains
+/// - `search_domains` - the domains appended to unqualified host names
+/// - `interface_name` - the name of the interface, scoping link-local DNS servers of split domains
+pub fn add_dns_servers(
+    dns_servers: &[IpAddr],
+    split_domains: &[SplitDnsDomain],
+    search_domains: &[String],
+    interface_name: &str,
+) -> Result<()> {
+    if !split_domains.is_empty() {
+        if !search_domains.is_empty() {
+            warn!("DNS search domains are not supported together with split DNS on macOS");
+        }
+        return add_resolver_files(split_domains, interface_name, Path::new(RESOLVER_DIR));
+    }
+
     let service_names = get_service_names()?;
-    let dns_servers_args = dns_servers
-        .iter()
-        .map(|ip| ip.to_string())
-        .collect::<Vec<_>>();
 
     for service_name in service_names {
-        // networksetup -getdnsservers <service_name>
-        let get_args = [DNS_GET_ARG, &service_name];
-        let get_output = run_command(NETWORK_SETUP_COMMAND, get_args)
-            .map_err(|e| DnsError::PlatformError {
-                message: format!("failed to execute command: {e}"),
-            })?
-            .wait_with_output()
-            .map_err(|e| DnsError::PlatformError {
-                message: format!("failed to wait for command: {e}"),
-            })?;
-
-        if !get_output.status.success() {
-            return Err(DnsError::ConfigurationFailed.into());
-        }
+        let original_dns_servers = get_service_dns_servers(&service_name)?;
+        SERVICE_DNS_SERVERS.insert(service_name.clone(), original_dns_servers);
 
-        let original_dns_servers = String::from_utf8(get_output.stdout)
-            .map_err(|e| DnsError::PlatformError {
-                message: format!("Failed to parse DNS servers output: {e}"),
-            })?
-            .lines()
-            .filter_map(|dns_str| IpAddr::from_str(dns_str).ok())
-            .collect();
+        set_service_dns_servers(&service_name, dns_servers)
+            .map_err(|_| DnsError::ConfigurationFailed)?;
 
-        SERVICE_DNS_SERVERS.insert(service_name.clone(), original_dns_servers);
+        if !search_domains.is_empty() {
+            let original_search_domains = get_service_search_domains(&service_name)?;
+            SERVICE_SEARCH_DOMAINS.insert(service_name.clone(), original_search_domains);
 
-        // networksetup -setdnsservers <service_name> <dns_servers...>
-        let set_args = [DNS_SET_ARG, &service_name].into_iter().chain(
-            dns_servers_args
-                .iter()
-                .map(|addr_string| addr_string.as_str()),
-        );
-
-        let set_output = run_command(NETWORK_SETUP_COMMAND, set_args)
-            .map_err(|e| DnsError::PlatformError {
-                message: format!("failed to execute command: {e}"),
-            })?
-            .wait_with_output()
-            .map_err(|e| DnsError::PlatformError {
-                message: format!("failed to wait for command: {e}"),
-            })?;
-
-        if !set_output.status.success() {
-            return Err(DnsError::ConfigurationFailed.into());
+            set_service_setting(SEARCH_DOMAINS_SET_ARG, &service_name, search_domains)
+                .map_err(|_| DnsError::ConfigurationFailed)?;
         }
     }
 
@@ -112,40 +117,340 @@ pub fn add_dns_servers(dns_servers: &[IpAddr], _interface_name: &str) -> Result<
 }
 
 /// Deletes all DNS servers from all network services on the endpoint.
-pub fn delete_dns_servers() -> Result<()> {
+///
+/// ### Arguments
+/// - `split_domains` - the split domains configured (unused, written resolver files are tracked)
+/// - `interface_name` - the name of the interface to remove the DNS servers from (unused)
+pub fn delete_dns_servers(_split_domains: &[SplitDnsDomain], _interface_name: &str) -> Result<()> {
+    delete_resolver_files()?;
+
     for service_entry in SERVICE_DNS_SERVERS.iter() {
-        let service_name = service_entry.key();
-        let original_dns_servers = service_entry.value();
-
-        // If the original DNS servers are empty (set by the DHCP, ...), we need to pass "Empty"
-        let dns_servers_args = if original_dns_servers.is_empty() {
-            vec!["Empty".to_owned()]
-        } else {
-            original_dns_servers
-                .iter()
-                .map(|ip| ip.to_string())
-                .collect::<Vec<_>>()
-        };
-
-        // networksetup -setdnsservers <service_name> <original_dns_servers...>
-        let set_args = [DNS_SET_ARG, service_name].into_iter().chain(
-            dns_servers_args
-                .iter()
-                .map(|addr_string| addr_string.as_str()),
-        );
-
-        let set_output = run_command(NETWORK_SETUP_COMMAND, set_args)
-            .map_err(|e| DnsError::PlatformError {
-                message: format!("failed to execute command: {e}"),
-            })?
-            .wait_with_output()
-            .map_err(|e| DnsError::PlatformError {
-                message: format!("failed to wait for command: {e}"),
-            })?;
-
-        if !set_output.status.success() {
-            return Err(DnsError::RestoreFailed.into());
+        set_service_dns_servers(service_entry.key(), service_entry.value())
+            .map_err(|_| DnsError::RestoreFailed)?;
+    }
+
+    for service_entry in SERVICE_SEARCH_DOMAINS.iter() {
+        set_service_setting(
+            SEARCH_DOMAINS_SET_ARG,
+            service_entry.key(),
+            service_entry.value(),
+        )
+        .map_err(|_| DnsError::RestoreFailed)?;
+    }
+
+    Ok(())
+}
+
+/// Captures the resolver state replaced by the DNS configuration.
+///
+/// With split domains, the resolver files to be written are recorded, otherwise the DNS
+/// servers and search domains of all network services.
+///
+/// ### Arguments
+/// - `split_domains` - the domains resolved through the tunnel, or none for all domains
+/// - `interface_name` - the name of the interface the DNS servers are added to
+///
+/// ### Errors
+/// Returns `DnsError::BackupFailed` if the resolver state cannot be captured.
+pub fn backup_dns_state(
+    split_domains: &[SplitDnsDomain],
+    interface_name: &str,
+) -> Result<DnsBackup> {
+    let mut backup = DnsBackup::new(interface_name);
+
+    if !split_domains.is_empty() {
+        // Invalid domains are rejected when the resolver files are written
+        for split in split_domains
+            .iter()
+            .filter(|split| is_valid_domain(&split.domain))
+        {
+            backup.add_file(&Path::new(RESOLVER_DIR).join(&split.domain))?;
+        }
+        return Ok(backup);
+    }
+
+    for service_name in get_service_names().map_err(|_| DnsError::BackupFailed)? {
+        let dns_servers =
+            get_service_dns_servers(&service_name).map_err(|_| DnsError::BackupFailed)?;
+        let search_domains =
+            get_service_search_domains(&service_name).map_err(|_| DnsError::BackupFailed)?;
+        backup
+            .service_dns_servers
+            .push((service_name.clone(), dns_servers));
+        backup
+            .service_search_domains
+            .push((service_name, search_domains));
+    }
+
+    Ok(backup)
+}
+
+/// Restores the DNS servers and search domains of the network services recorded in a stale snapshot.
+///
+/// Services that no longer exist are skipped.
+///
+/// ### Arguments
+/// - `backup` - the snapshot left behind by an earlier run
+///
+/// ### Errors
+/// Returns `DnsError::RestoreFailed` if the DNS servers of a service cannot be restored.
+pub fn restore_dns_state(backup: &DnsBackup) -> Result<()> {
+    if backup.service_dns_servers.is_empty() && backup.service_search_domains.is_empty() {
+        return Ok(());
+    }
+
+    let service_names = get_service_names().map_err(|_| DnsError::RestoreFailed)?;
+    for (service_name, dns_servers) in &backup.service_dns_servers {
+        if service_names.contains(service_name) {
+            set_service_dns_servers(service_name, dns_servers)
+                .map_err(|_| DnsError::RestoreFailed)?;
+        }
+    }
+    for (service_name, search_domains) in &backup.service_search_domains {
+        if service_names.contains(service_name) {
+            set_service_setting(SEARCH_DOMAINS_SET_ARG, service_name, search_domains)
+                .map_err(|_| DnsError::RestoreFailed)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Gets the DNS servers of a network service.
+///
+/// ### Arguments
+/// - `service_name` - the name of the network service
+///
+/// ### Returns
+/// The DNS servers of the service, empty if they are set by DHCP.
+fn get_service_dns_servers(service_name: &str) -> Result<Vec<IpAddr>> {
+    let dns_servers = get_service_setting(DNS_GET_ARG, service_name)?
+        .iter()
+        .filter_map(|dns_str| IpAddr::from_str(dns_str).ok())
+        .collect();
+
+    Ok(dns_servers)
+}
+
+/// Sets the DNS servers of a network service.
+///
+/// ### Arguments
+/// - `service_name` - the name of the network service
+/// - `dns_servers` - the DNS servers, or none to use the ones set by DHCP
+fn set_service_dns_servers(service_name: &str, dns_servers: &[IpAddr]) -> Result<()> {
+    let dns_servers = dns_servers
+        .iter()
+        .map(|ip| ip.to_string())
+        .collect::<Vec<_>>();
+
+    set_service_setting(DNS_SET_ARG, service_name, &dns_servers)
+}
+
+/// Gets the search domains of a network service.
+///
+/// ### Arguments
+/// - `service_name` - the name of the network service
+///
+/// ### Returns
+/// The search domains of the service, empty if they are set by DHCP.
+fn get_service_search_domains(service_name: &str) -> Result<Vec<String>> {
+    // Without search domains, a sentence saying so is printed instead
+    let search_domains = get_service_setting(SEARCH_DOMAINS_GET_ARG, service_name)?
+        .into_iter()
+        .filter(|domain| is_valid_domain(domain))
+        .collect();
+
+    Ok(search_domains)
+}
+
+/// Gets a list setting of a network service, one value per line.
+///
+/// ### Arguments
+/// - `get_arg` - the `networksetup` option getting the setting
+/// - `service_name` - the name of the network service
+fn get_service_setting(get_arg: &str, service_name: &str) -> Result<Vec<String>> {
+    // networksetup <get_arg> <service_name>
+    let output = run_command(NETWORK_SETUP_COMMAND, [get_arg, service_name])
+        .map_err(|e| DnsError::PlatformError {
+            message: format!("failed to execute command: {e}"),
+        })?
+        .wait_with_output()
+        .map_err(|e| DnsError::PlatformError {
+            message: format!("failed to wait for command: {e}"),
+        })?;
+
+    if !output.status.success() {
+        return Err(DnsError::ConfigurationFailed.into());
+    }
+
+    let values = String::from_utf8(output.stdout)
+        .map_err(|e| DnsError::PlatformError {
+            message: format!("Failed to parse {get_arg} output: {e}"),
+        })?
+        .lines()
+        .map(|line| line.trim().to_string())
+        .collect();
+
+    Ok(values)
+}
+
+/// Sets a list setting of a network service.
+///
+/// ### Arguments
+/// - `set_arg` - the `networksetup` option setting the setting
+/// - `service_name` - the name of the network service
+/// - `values` - the values, or none to use the ones set by DHCP
+fn set_service_setting(set_arg: &str, service_name: &str, values: &[String]) -> Result<()> {
+    // If the values are empty (set by the DHCP, ...), we need to pass "Empty"
+    let empty = ["Empty".to_owned()];
+    let values = if values.is_empty() {
+        &empty[..]
+    } else {
+        values
+    };
+
+    // networksetup <set_arg> <service_name> <values...>
+    let set_args = [set_arg, service_name]
+        .into_iter()
+        .chain(values.iter().map(String::as_str));
+
+    let output = run_command(NETWORK_SETUP_COMMAND, set_args)
+        .map_err(|e| DnsError::PlatformError {
+            message: format!("failed to execute command: {e}"),
+        })?
+        .wait_with_output()
+        .map_err(|e| DnsError::PlatformError {
+            message: format!("failed to wait for command: {e}"),
+        })?;
+
+    if !output.status.success() {
+        return Err(DnsError::PlatformError {
+            message: format!(
+                "failed to set {set_arg} of {service_name}: {}",
+                String::from_utf8_lossy(&output.stdout)
+            ),
+        }
+        .into());
+    }
+
+    Ok(())
+}
+
+/// Installs pf rules blocking DNS queries to servers other than `dns_servers`.
+///
+/// The rules are loaded into their own anchor, replacing rules left behind by an earlier run,
+/// and pf is enabled with a reference token so that it is only disabled again if Quincy
+/// enabled it.
+///
+/// ### Arguments
+/// - `dns_servers` - the DNS servers queries are allowed to
+/// - `dry_run` - whether to only log the firewall changes
+pub fn block_dns_leaks(dns_servers: &[IpAddr], dry_run: bool) -> Result<()> {
+    // pfctl -a <anchor> -f - <<< <rules>
+    FirewallCommand::new(
+        FirewallFeature::DnsLeakProtection,
+        PFCTL_COMMAND,
+        &["-a", LEAK_PROTECTION_ANCHOR, "-f", "-"],
+    )
+    .with_stdin(pf_rules(dns_servers))
+    .run(dry_run)?;
+
+    // pfctl -E prints "Token : <token>"
+    let output = FirewallCommand::new(FirewallFeature::DnsLeakProtection, PFCTL_COMMAND, &["-E"])
+        .run(dry_run)?;
+    let token = output
+        .lines()
+        .find_map(|line| line.strip_prefix("Token : "))
+        .map(|token| token.trim().to_string());
+    *PF_TOKEN.lock().unwrap_or_else(|e| e.into_inner()) = token;
+
+    Ok(())
+}
+
+/// Removes the pf rules installed by [`block_dns_leaks`].
+///
+/// ### Arguments
+/// - `dry_run` - whether to only log the firewall changes
+pub fn unblock_dns_leaks(dry_run: bool) -> Result<()> {
+    // pfctl -a <anchor> -F all
+    FirewallCommand::new(
+        FirewallFeature::DnsLeakProtection,
+        PFCTL_COMMAND,
+        &["-a", LEAK_PROTECTION_ANCHOR, "-F", "all"],
+    )
+    .run(dry_run)?;
+
+    let token = PF_TOKEN.lock().unwrap_or_else(|e| e.into_inner()).take();
+    if let Some(token) = token {
+        // pfctl -X <token>
+        FirewallCommand::new(
+            FirewallFeature::DnsLeakProtection,
+            PFCTL_COMMAND,
+            &["-X", token.as_str()],
+        )
+        .run(dry_run)?;
+    }
+
+    Ok(())
+}
+
+/// Writes a resolver file for each split domain.
+///
+/// Existing resolver files are not overwritten, as they belong to other software or the user.
+///
+/// ### Arguments
+/// - `split_domains` - the domains resolved through the tunnel
+/// - `interface_name` - the name of the tunnel interface
+/// - `resolver_dir` - the directory of the resolver files
+fn add_resolver_files(
+    split_domains: &[SplitDnsDomain],
+    interface_name: &str,
+    resolver_dir: &Path,
+) -> Result<()> {
+    fs::create_dir_all(resolver_dir).map_err(|e| DnsError::PlatformError {
+        message: format!("failed to create {}: {e}", resolver_dir.display()),
+    })?;
+
+    for split in split_domains {
+        // The domain names the file, so it must not contain path separators
+        if !is_valid_domain(&split.domain) {
+            return Err(DnsError::InvalidConfiguration {
+                reason: format!("invalid split DNS domain '{}'", split.domain),
+            }
+            .into());
+        }
+
+        let path = resolver_dir.join(&split.domain);
+        if path.exists() && !RESOLVER_FILES.contains(&path) {
+            return Err(DnsError::PlatformError {
+                message: format!("resolver file {} already exists", path.display()),
+            }
+            .into());
         }
+
+        fs::write(
+            &path,
+            resolver_file_contents(&split.dns_servers, interface_name),
+        )
+        .map_err(|e| DnsError::PlatformError {
+            message: format!("failed to write {}: {e}", path.display()),
+        })?;
+        RESOLVER_FILES.insert(path);
+    }
+
+    Ok(())
+}
+
+/// Removes the resolver files written for split domains.
+fn delete_resolver_files() -> Result<()> {
+    let paths: Vec<PathBuf> = RESOLVER_FILES
+        .iter()
+        .map(|path| path.key().clone())
+        .collect();
+
+    for path in paths {
+        fs::remove_file(&path).map_err(|_| DnsError::RestoreFailed)?;
+        RESOLVER_FILES.remove(&path);
     }
 
     Ok(())
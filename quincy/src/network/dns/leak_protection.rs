@@ -0,0 +1,365 @@
+//! Firewall rules blocking DNS queries that bypass the tunnel's DNS servers.
+//!
+//! While the tunnel is up, outbound traffic to the DNS (53) and DNS-over-TLS (853) ports is
+//! rejected unless it goes to one of the tunnel's DNS servers or stays on the loopback
+//! interface, so the system cannot fall back to other resolvers. The platform modules
+//! install the rules generated here with nftables or iptables (Linux), pf (macOS) or the
+//! Windows Firewall.
+
+use crate::Result;
+use crate::network::dns::{block_dns_leaks, unblock_dns_leaks};
+use crate::network::firewall::join;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use tracing::{error, info};
+
+/// Ports blocked by DNS leak protection.
+pub(crate) const DNS_LEAK_PORTS: [u16; 2] = [53, 853];
+/// Name of the nftables table and iptables chain holding the rules on Linux.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+pub(crate) const LINUX_LEAK_PROTECTION_TABLE: &str = "quincy_dns";
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+pub(crate) const LINUX_LEAK_PROTECTION_CHAIN: &str = "QUINCY_DNS";
+
+/// How DNS queries bypassing the tunnel's DNS servers are handled.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LeakProtection {
+    /// DNS queries are not restricted
+    #[default]
+    Disabled,
+    /// Firewall rules block DNS queries to other servers
+    Enabled,
+    /// The firewall changes are only logged, without being made
+    DryRun,
+}
+
+/// Installed DNS leak protection rules, removed when dropped.
+///
+/// Owned by the tunnel interface, so that the rules are removed with it even when the
+/// DNS configuration is not cleaned up, e.g. when unwinding from a panic.
+pub struct LeakProtectionGuard {
+    dry_run: bool,
+    installed: bool,
+}
+
+impl LeakProtectionGuard {
+    /// Installs rules blocking DNS queries to servers other than `dns_servers`.
+    ///
+    /// ### Arguments
+    /// - `dns_servers` - the DNS servers queries are allowed to
+    /// - `mode` - the leak protection mode
+    ///
+    /// ### Returns
+    /// - `Option<LeakProtectionGuard>` - the installed rules, or `None` if leak protection is disabled
+    ///
+    /// ### Errors
+    /// Returns `DnsError::PlatformError` if the firewall rules cannot be installed, in which
+    /// case partially installed rules are removed again.
+    pub fn install(dns_servers: &[IpAddr], mode: LeakProtection) -> Result<Option<Self>> {
+        let dry_run = match mode {
+            LeakProtection::Disabled => return Ok(None),
+            LeakProtection::Enabled => false,
+            LeakProtection::DryRun => true,
+        };
+
+        if let Err(e) = block_dns_leaks(dns_servers, dry_run) {
+            if let Err(cleanup_error) = unblock_dns_leaks(dry_run) {
+                error!("Failed to roll back DNS leak protection: {cleanup_error}");
+            }
+            return Err(e);
+        }
+        info!("Enabled DNS leak protection for DNS servers {dns_servers:?}");
+
+        Ok(Some(Self {
+            dry_run,
+            installed: true,
+        }))
+    }
+
+    /// Removes the installed rules.
+    ///
+    /// ### Errors
+    /// Returns `DnsError::PlatformError` if the firewall rules cannot be removed.
+    pub fn remove(mut self) -> Result<()> {
+        self.installed = false;
+        unblock_dns_leaks(self.dry_run)?;
+        info!("Disabled DNS leak protection");
+
+        Ok(())
+    }
+}
+
+impl Drop for LeakProtectionGuard {
+    fn drop(&mut self) {
+        if self.installed {
+            if let Err(e) = unblock_dns_leaks(self.dry_run) {
+                error!("Failed to remove DNS leak protection: {e}");
+            }
+        }
+    }
+}
+
+/// Generates the nftables ruleset of DNS leak protection.
+///
+/// The ruleset replaces a table left behind by an earlier run.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+pub(crate) fn nft_ruleset(dns_servers: &[IpAddr]) -> String {
+    let table = LINUX_LEAK_PROTECTION_TABLE;
+    let ports = join(&DNS_LEAK_PORTS, ", ");
+    let (ipv4_servers, ipv6_servers) = split_families(dns_servers);
+
+    let mut rules = vec![
+        "type filter hook output priority 0; policy accept;".to_string(),
+        "oifname \"lo\" accept".to_string(),
+    ];
+    for (family, servers) in [
+        ("ip", join(&ipv4_servers, ", ")),
+        ("ip6", join(&ipv6_servers, ", ")),
+    ] {
+        if !servers.is_empty() {
+            rules.push(format!(
+                "{family} daddr {{ {servers} }} meta l4proto {{ tcp, udp }} th dport {{ {ports} }} accept"
+            ));
+        }
+    }
+    rules.push(format!(
+        "meta l4proto {{ tcp, udp }} th dport {{ {ports} }} reject"
+    ));
+
+    let mut ruleset = format!(
+        "table inet {table}\ndelete table inet {table}\ntable inet {table} {{\n\tchain output {{\n"
+    );
+    for rule in rules {
+        ruleset.push_str(&format!("\t\t{rule}\n"));
+    }
+    ruleset.push_str("\t}\n}\n");
+
+    ruleset
+}
+
+/// Generates the arguments of the `iptables` or `ip6tables` commands installing DNS leak protection.
+///
+/// ### Arguments
+/// - `dns_servers` - the DNS servers of the family handled by the command
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+pub(crate) fn iptables_block_args(dns_servers: &[IpAddr]) -> Vec<Vec<String>> {
+    let chain = LINUX_LEAK_PROTECTION_CHAIN;
+    let ports = join(&DNS_LEAK_PORTS, ",");
+    let args = |args: &str| args.split(' ').map(str::to_string).collect::<Vec<_>>();
+
+    let mut commands = vec![
+        args(&format!("-N {chain}")),
+        args(&format!("-A {chain} -o lo -j RETURN")),
+    ];
+    for protocol in ["udp", "tcp"] {
+        for server in dns_servers {
+            commands.push(args(&format!(
+                "-A {chain} -d {server} -p {protocol} -m multiport --dports {ports} -j RETURN"
+            )));
+        }
+        commands.push(args(&format!(
+            "-A {chain} -p {protocol} -m multiport --dports {ports} -j REJECT"
+        )));
+    }
+    commands.push(args(&format!("-I OUTPUT -j {chain}")));
+
+    commands
+}
+
+/// Generates the arguments of the `iptables` or `ip6tables` commands removing DNS leak protection.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+pub(crate) fn iptables_unblock_args() -> Vec<Vec<String>> {
+    let chain = LINUX_LEAK_PROTECTION_CHAIN;
+
+    [
+        vec!["-D", "OUTPUT", "-j", chain],
+        vec!["-F", chain],
+        vec!["-X", chain],
+    ]
+    .into_iter()
+    .map(|args| args.into_iter().map(str::to_string).collect())
+    .collect()
+}
+
+/// Generates the pf rules of DNS leak protection, loaded into their own anchor.
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+pub(crate) fn pf_rules(dns_servers: &[IpAddr]) -> String {
+    let ports = join(&DNS_LEAK_PORTS, " ");
+
+    let mut rules = String::from("pass out quick on lo0 all\n");
+    if !dns_servers.is_empty() {
+        rules.push_str(&format!(
+            "pass out quick proto {{ tcp udp }} from any to {{ {} }} port {{ {ports} }}\n",
+            join(dns_servers, ", ")
+        ));
+    }
+    rules.push_str(&format!(
+        "block return out quick proto {{ tcp udp }} from any to any port {{ {ports} }}\n"
+    ));
+
+    rules
+}
+
+/// Generates the remote addresses blocked by the Windows Firewall rules of DNS leak protection.
+///
+/// Windows Firewall block rules take precedence over allow rules, so the blocked addresses are
+/// all addresses except the DNS servers, as a comma-separated list of address ranges.
+#[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+pub(crate) fn windows_blocked_addresses(dns_servers: &[IpAddr]) -> String {
+    let (ipv4_servers, ipv6_servers) = split_families(dns_servers);
+
+    let ipv4_servers = ipv4_servers
+        .iter()
+        .map(|server| u32::from(*server) as u128)
+        .collect::<Vec<_>>();
+    let ipv4_ranges = complement(ipv4_servers, u32::MAX as u128)
+        .into_iter()
+        .map(|(start, end)| (Ipv4Addr::from(start as u32), Ipv4Addr::from(end as u32)))
+        .map(|(start, end)| format!("{start}-{end}"));
+
+    let ipv6_servers = ipv6_servers
+        .iter()
+        .map(|server| u128::from(*server))
+        .collect();
+    let ipv6_ranges = complement(ipv6_servers, u128::MAX)
+        .into_iter()
+        .map(|(start, end)| format!("{}-{}", Ipv6Addr::from(start), Ipv6Addr::from(end)));
+
+    ipv4_ranges.chain(ipv6_ranges).collect::<Vec<_>>().join(",")
+}
+
+/// Returns the ranges of `0..=max` not containing any of the excluded values.
+fn complement(mut excluded: Vec<u128>, max: u128) -> Vec<(u128, u128)> {
+    excluded.sort_unstable();
+    excluded.dedup();
+
+    let mut ranges = Vec::new();
+    let mut start = 0;
+    for value in excluded {
+        if value > start {
+            ranges.push((start, value - 1));
+        }
+        match value.checked_add(1) {
+            Some(next) if next <= max => start = next,
+            _ => return ranges,
+        }
+    }
+    ranges.push((start, max));
+
+    ranges
+}
+
+/// Splits DNS servers into IPv4 and IPv6 servers.
+fn split_families(dns_servers: &[IpAddr]) -> (Vec<Ipv4Addr>, Vec<Ipv6Addr>) {
+    let mut ipv4_servers = Vec::new();
+    let mut ipv6_servers = Vec::new();
+    for server in dns_servers {
+        match server {
+            IpAddr::V4(server) => ipv4_servers.push(*server),
+            IpAddr::V6(server) => ipv6_servers.push(*server),
+        }
+    }
+
+    (ipv4_servers, ipv6_servers)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn servers(addresses: &[&str]) -> Vec<IpAddr> {
+        addresses
+            .iter()
+            .map(|address| address.parse().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn nft_ruleset_allows_only_tunnel_dns_servers() {
+        let ruleset = nft_ruleset(&servers(&["10.0.0.1", "10.0.0.2", "fd00::53"]));
+
+        assert!(ruleset.starts_with("table inet quincy_dns\ndelete table inet quincy_dns\n"));
+        assert!(ruleset.contains(
+            "ip daddr { 10.0.0.1, 10.0.0.2 } meta l4proto { tcp, udp } th dport { 53, 853 } accept"
+        ));
+        assert!(ruleset.contains(
+            "ip6 daddr { fd00::53 } meta l4proto { tcp, udp } th dport { 53, 853 } accept"
+        ));
+        assert!(ruleset.contains("oifname \"lo\" accept"));
+        assert!(
+            ruleset
+                .trim_end()
+                .lines()
+                .rev()
+                .nth(2)
+                .unwrap()
+                .ends_with("th dport { 53, 853 } reject")
+        );
+
+        let ipv4_only = nft_ruleset(&servers(&["10.0.0.1"]));
+        assert!(!ipv4_only.contains("ip6 daddr"));
+    }
+
+    #[test]
+    fn iptables_rules_return_before_rejecting() {
+        let commands = iptables_block_args(&servers(&["10.0.0.1"]))
+            .into_iter()
+            .map(|args| args.join(" "))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            commands,
+            vec![
+                "-N QUINCY_DNS",
+                "-A QUINCY_DNS -o lo -j RETURN",
+                "-A QUINCY_DNS -d 10.0.0.1 -p udp -m multiport --dports 53,853 -j RETURN",
+                "-A QUINCY_DNS -p udp -m multiport --dports 53,853 -j REJECT",
+                "-A QUINCY_DNS -d 10.0.0.1 -p tcp -m multiport --dports 53,853 -j RETURN",
+                "-A QUINCY_DNS -p tcp -m multiport --dports 53,853 -j REJECT",
+                "-I OUTPUT -j QUINCY_DNS",
+            ]
+        );
+        assert_eq!(
+            iptables_unblock_args()[0],
+            ["-D", "OUTPUT", "-j", "QUINCY_DNS"]
+        );
+    }
+
+    #[test]
+    fn pf_rules_pass_tunnel_dns_servers() {
+        let rules = pf_rules(&servers(&["10.0.0.1", "fd00::53"]));
+
+        assert_eq!(
+            rules.lines().collect::<Vec<_>>(),
+            vec![
+                "pass out quick on lo0 all",
+                "pass out quick proto { tcp udp } from any to { 10.0.0.1, fd00::53 } port { 53 853 }",
+                "block return out quick proto { tcp udp } from any to any port { 53 853 }",
+            ]
+        );
+    }
+
+    #[test]
+    fn windows_blocked_addresses_exclude_dns_servers() {
+        let blocked = windows_blocked_addresses(&servers(&["10.0.0.1", "0.0.0.0", "::1"]));
+
+        assert_eq!(
+            blocked.split(',').collect::<Vec<_>>(),
+            vec![
+                "0.0.0.1-10.0.0.0",
+                "10.0.0.2-255.255.255.255",
+                "::-::",
+                "::2-ffff:ffff:ffff:ffff:ffff:ffff:ffff:ffff",
+            ]
+        );
+    }
+
+    #[test]
+    fn complement_handles_range_edges() {
+        assert_eq!(complement(vec![], 10), vec![(0, 10)]);
+        assert_eq!(complement(vec![10, 0, 5, 5], 10), vec![(1, 4), (6, 9)]);
+        assert_eq!(
+            complement(vec![u128::MAX], u128::MAX),
+            vec![(0, u128::MAX - 1)]
+        );
+    }
+}
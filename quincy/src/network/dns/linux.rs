@@ -1,24 +1,68 @@
 use crate::Result;
 use crate::error::DnsError;
+#[cfg(target_os = "linux")]
+use crate::network::IpFamily;
+use crate::network::dns::backup::DnsBackup;
+#[cfg(target_os = "linux")]
+use crate::network::dns::leak_protection::{
+    LINUX_LEAK_PROTECTION_TABLE, iptables_block_args, iptables_unblock_args, nft_ruleset,
+};
+#[cfg(all(target_os = "linux", feature = "resolved"))]
+use crate::network::dns::resolved;
+use crate::network::dns::{SplitDnsDomain, nameserver_line};
+#[cfg(target_os = "linux")]
+use crate::network::firewall::{FirewallCommand, FirewallFeature, IPTABLES_COMMANDS, NFT_COMMAND};
+use crate::utils::command::command_exists;
 use crate::utils::command::run_command;
+use std::fs;
 use std::io::Write;
 use std::net::IpAddr;
+use std::path::Path;
 
 /// Command name for the `resolvconf` utility.
 const RESOLVCONF_COMMAND: &str = "resolvconf";
+/// Path of the system resolver configuration.
+const RESOLV_CONF_PATH: &str = "/etc/resolv.conf";
+/// Command name for the systemd-resolved `resolvectl` utility.
+#[cfg(target_os = "linux")]
+const RESOLVECTL_COMMAND: &str = "resolvectl";
 
 /// Adds a list of DNS servers to the given interface.
 ///
+/// With split domains, only queries for those domains are resolved through the interface,
+/// using systemd-resolved per-link routing domains. systemd-resolved keeps one set of DNS
+/// servers per link, so all split domains are resolved through the union of their servers.
+///
+/// With the `resolved` feature and systemd-resolved running, the link is configured over
+/// its D-Bus API instead of `resolvconf` and `resolvectl`.
+///
 /// ### Arguments
 /// - `dns_servers` - the DNS servers to be added
+/// - `split_domains` - the domains resolved through the interface, or none for all domains
+/// - `search_domains` - the domains appended to unqualified host names
 /// - `interface_name` - the name of the interface to add the DNS servers to
-pub fn add_dns_servers(dns_servers: &[IpAddr], interface_name: &str) -> Result<()> {
+pub fn add_dns_servers(
+    dns_servers: &[IpAddr],
+    split_domains: &[SplitDnsDomain],
+    search_domains: &[String],
+    interface_name: &str,
+) -> Result<()> {
+    #[cfg(all(target_os = "linux", feature = "resolved"))]
+    if resolved::is_running() {
+        return resolved::configure_link(
+            dns_servers,
+            split_domains,
+            search_domains,
+            interface_name,
+        );
+    }
+
+    if !split_domains.is_empty() {
+        return add_split_dns(split_domains, search_domains, interface_name);
+    }
+
     let set_args = ["-a", interface_name, "-x"];
-    let input = dns_servers
-        .iter()
-        .map(|ip| format!("nameserver {ip}"))
-        .collect::<Vec<_>>()
-        .join("\n");
+    let input = resolvconf_input(dns_servers, search_domains, interface_name);
 
     let mut process =
         run_command(RESOLVCONF_COMMAND, set_args).map_err(|e| DnsError::PlatformError {
@@ -53,9 +97,307 @@ pub fn add_dns_servers(dns_servers: &[IpAddr], interface_name: &str) -> Result<(
 
 /// Deletes all DNS servers from the given interface.
 ///
-/// No-op on Linux/FreeBSD.
-pub fn delete_dns_servers() -> Result<()> {
+/// Reverts the systemd-resolved configuration of the interface if it was configured over
+/// D-Bus or with split domains, otherwise a no-op on Linux/FreeBSD.
+///
+/// ### Arguments
+/// - `split_domains` - the split domains configured on the interface
+/// - `interface_name` - the name of the interface to remove the DNS servers from
+pub fn delete_dns_servers(split_domains: &[SplitDnsDomain], interface_name: &str) -> Result<()> {
+    #[cfg(all(target_os = "linux", feature = "resolved"))]
+    if resolved::is_running() {
+        return resolved::revert_link(interface_name);
+    }
+
+    if !split_domains.is_empty() {
+        return delete_split_dns(interface_name);
+    }
+
     // This is a no-op on Linux and FreeBSD as the interface is deleted when the process exits
     // along with its routes and DNS servers
     Ok(())
 }
+
+/// Captures the resolver state replaced by the DNS configuration of the interface.
+///
+/// `/etc/resolv.conf` is recorded if it is a regular file. A symlinked one is generated by
+/// systemd-resolved or `resolvconf` from per-interface state, which is restored separately.
+///
+/// ### Arguments
+/// - `split_domains` - the domains resolved through the interface (unused)
+/// - `interface_name` - the name of the interface the DNS servers are added to
+///
+/// ### Errors
+/// Returns `DnsError::BackupFailed` if `/etc/resolv.conf` cannot be read.
+pub fn backup_dns_state(
+    _split_domains: &[SplitDnsDomain],
+    interface_name: &str,
+) -> Result<DnsBackup> {
+    let mut backup = DnsBackup::new(interface_name);
+
+    let resolv_conf = Path::new(RESOLV_CONF_PATH);
+    if fs::symlink_metadata(resolv_conf).is_ok_and(|metadata| metadata.is_file()) {
+        backup.add_file(resolv_conf)?;
+    }
+
+    Ok(backup)
+}
+
+/// Deletes the `resolvconf` record of the interface recorded in a stale snapshot.
+///
+/// The record outlives the interface, so `/etc/resolv.conf` keeps listing the tunnel's DNS
+/// servers until it is deleted. systemd-resolved drops the configuration of a link by itself
+/// when its interface is removed.
+///
+/// ### Arguments
+/// - `backup` - the snapshot left behind by an earlier run
+///
+/// ### Errors
+/// Returns `DnsError::RestoreFailed` if the record cannot be deleted.
+pub fn restore_dns_state(backup: &DnsBackup) -> Result<()> {
+    if !command_exists(RESOLVCONF_COMMAND) {
+        return Ok(());
+    }
+
+    // resolvconf -d <interface> -f
+    let output = run_command(
+        RESOLVCONF_COMMAND,
+        ["-d", backup.interface_name.as_str(), "-f"],
+    )
+    .map_err(|_| DnsError::RestoreFailed)?
+    .wait_with_output()
+    .map_err(|_| DnsError::RestoreFailed)?;
+
+    if !output.status.success() {
+        return Err(DnsError::RestoreFailed.into());
+    }
+
+    Ok(())
+}
+
+/// Builds the `resolv.conf` entries of the interface passed to `resolvconf`.
+///
+/// ### Arguments
+/// - `dns_servers` - the DNS servers of the interface
+/// - `search_domains` - the domains appended to unqualified host names
+/// - `interface_name` - the name of the interface
+fn resolvconf_input(
+    dns_servers: &[IpAddr],
+    search_domains: &[String],
+    interface_name: &str,
+) -> String {
+    let mut lines = dns_servers
+        .iter()
+        .map(|ip| nameserver_line(ip, interface_name))
+        .collect::<Vec<_>>();
+    if !search_domains.is_empty() {
+        lines.push(format!("search {}", search_domains.join(" ")));
+    }
+
+    lines.join("\n")
+}
+
+/// Configures systemd-resolved to resolve only the split domains through the interface.
+///
+/// The search domains are added to the interface as regular domains, so that they are
+/// searched and resolved through it as well.
+#[cfg(target_os = "linux")]
+fn add_split_dns(
+    split_domains: &[SplitDnsDomain],
+    search_domains: &[String],
+    interface_name: &str,
+) -> Result<()> {
+    let mut dns_servers: Vec<String> = Vec::new();
+    for server in split_domains.iter().flat_map(|split| &split.dns_servers) {
+        let server = server.to_string();
+        if !dns_servers.contains(&server) {
+            dns_servers.push(server);
+        }
+    }
+    let domains = split_domains
+        .iter()
+        .map(|split| format!("~{}", split.domain))
+        .chain(search_domains.iter().cloned())
+        .collect::<Vec<_>>();
+
+    // resolvectl dns <interface> <dns_servers...>
+    run_resolvectl(
+        ["dns", interface_name]
+            .into_iter()
+            .chain(dns_servers.iter().map(String::as_str)),
+    )?;
+    // resolvectl domain <interface> ~<domain>... <search_domain>...
+    run_resolvectl(
+        ["domain", interface_name]
+            .into_iter()
+            .chain(domains.iter().map(String::as_str)),
+    )?;
+    // Keep queries for other domains off the interface
+    run_resolvectl(["default-route", interface_name, "false"])
+}
+
+/// Reverts the systemd-resolved configuration of the interface.
+#[cfg(target_os = "linux")]
+fn delete_split_dns(interface_name: &str) -> Result<()> {
+    // resolvectl revert <interface>
+    run_resolvectl(["revert", interface_name]).map_err(|_| DnsError::RestoreFailed.into())
+}
+
+/// Runs `resolvectl` with the given arguments.
+#[cfg(target_os = "linux")]
+fn run_resolvectl<'a>(args: impl IntoIterator<Item = &'a str>) -> Result<()> {
+    let output = run_command(RESOLVECTL_COMMAND, args)
+        .map_err(|e| DnsError::PlatformError {
+            message: format!("failed to execute command: {e}"),
+        })?
+        .wait_with_output()
+        .map_err(|e| DnsError::PlatformError {
+            message: format!("failed to wait for command: {e}"),
+        })?;
+
+    if !output.status.success() {
+        return Err(DnsError::PlatformError {
+            message: format!(
+                "resolvectl failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            ),
+        }
+        .into());
+    }
+
+    Ok(())
+}
+
+/// Installs firewall rules blocking DNS queries to servers other than `dns_servers`.
+///
+/// Uses nftables when available and falls back to iptables, replacing rules left
+/// behind by an earlier run.
+///
+/// ### Arguments
+/// - `dns_servers` - the DNS servers queries are allowed to
+/// - `dry_run` - whether to only log the firewall changes
+#[cfg(target_os = "linux")]
+pub fn block_dns_leaks(dns_servers: &[IpAddr], dry_run: bool) -> Result<()> {
+    if command_exists(NFT_COMMAND) {
+        // nft -f - <<< <ruleset>
+        FirewallCommand::new(
+            FirewallFeature::DnsLeakProtection,
+            NFT_COMMAND,
+            &["-f", "-"],
+        )
+        .with_stdin(nft_ruleset(dns_servers))
+        .run(dry_run)?;
+        return Ok(());
+    }
+
+    for (program, family) in IPTABLES_COMMANDS {
+        let family_servers = dns_servers
+            .iter()
+            .filter(|server| IpFamily::of(server) == family)
+            .copied()
+            .collect::<Vec<_>>();
+
+        for args in iptables_unblock_args() {
+            // Only succeeds if a chain was left behind by an earlier run
+            let _ = FirewallCommand::new(FirewallFeature::DnsLeakProtection, program, &args)
+                .run(dry_run);
+        }
+        for args in iptables_block_args(&family_servers) {
+            FirewallCommand::new(FirewallFeature::DnsLeakProtection, program, &args)
+                .run(dry_run)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Removes the firewall rules installed by [`block_dns_leaks`].
+///
+/// ### Arguments
+/// - `dry_run` - whether to only log the firewall changes
+#[cfg(target_os = "linux")]
+pub fn unblock_dns_leaks(dry_run: bool) -> Result<()> {
+    if command_exists(NFT_COMMAND) {
+        // nft delete table inet <table>
+        FirewallCommand::new(
+            FirewallFeature::DnsLeakProtection,
+            NFT_COMMAND,
+            &["delete", "table", "inet", LINUX_LEAK_PROTECTION_TABLE],
+        )
+        .run(dry_run)?;
+        return Ok(());
+    }
+
+    let mut result = Ok(());
+    for (program, _) in IPTABLES_COMMANDS {
+        for args in iptables_unblock_args() {
+            if let Err(e) = FirewallCommand::new(FirewallFeature::DnsLeakProtection, program, &args)
+                .run(dry_run)
+            {
+                if result.is_ok() {
+                    result = Err(e);
+                }
+            }
+        }
+    }
+
+    result
+}
+
+#[cfg(target_os = "freebsd")]
+pub fn block_dns_leaks(_dns_servers: &[IpAddr], _dry_run: bool) -> Result<()> {
+    Err(DnsError::InvalidConfiguration {
+        reason: "DNS leak protection is not supported on FreeBSD".to_string(),
+    }
+    .into())
+}
+
+#[cfg(target_os = "freebsd")]
+pub fn unblock_dns_leaks(_dry_run: bool) -> Result<()> {
+    Ok(())
+}
+
+#[cfg(target_os = "freebsd")]
+fn add_split_dns(
+    _split_domains: &[SplitDnsDomain],
+    _search_domains: &[String],
+    _interface_name: &str,
+) -> Result<()> {
+    Err(DnsError::InvalidConfiguration {
+        reason: "split DNS is not supported on FreeBSD".to_string(),
+    }
+    .into())
+}
+
+#[cfg(target_os = "freebsd")]
+fn delete_split_dns(_interface_name: &str) -> Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolvconf_input_lists_nameservers_and_search_domains() {
+        let dns_servers: Vec<IpAddr> = ["10.0.0.1", "2606:4700:4700::1111"]
+            .iter()
+            .map(|address| address.parse().unwrap())
+            .collect();
+        let search_domains = [
+            "corp.example.com".to_string(),
+            "lab.example.com".to_string(),
+        ];
+
+        assert_eq!(
+            resolvconf_input(&dns_servers, &search_domains, "quincy0"),
+            "nameserver 10.0.0.1\n\
+             nameserver 2606:4700:4700::1111\n\
+             search corp.example.com lab.example.com"
+        );
+        assert_eq!(
+            resolvconf_input(&dns_servers[..1], &[], "quincy0"),
+            "nameserver 10.0.0.1"
+        );
+    }
+}
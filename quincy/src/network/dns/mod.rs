@@ -1,14 +1,369 @@
+use std::net::IpAddr;
+use std::path::PathBuf;
+
+use serde::Deserialize;
+use tracing::warn;
+
+use crate::Result;
+use crate::error::DnsError;
+use crate::network::IpFamily;
+
+mod backup;
+mod leak_protection;
+#[cfg(all(target_os = "linux", feature = "resolved"))]
+mod resolved;
+mod stub;
+pub use backup::{DnsBackup, FileBackup, dns_backup_path, restore_stale_dns_backup};
+pub use leak_protection::{LeakProtection, LeakProtectionGuard};
+pub use stub::{DnsStub, STUB_ADDRESS};
+
 #[cfg(target_os = "macos")]
 mod darwin;
 #[cfg(target_os = "macos")]
-pub use darwin::{add_dns_servers, delete_dns_servers};
+pub use darwin::{
+    add_dns_servers, backup_dns_state, block_dns_leaks, delete_dns_servers, restore_dns_state,
+    unblock_dns_leaks,
+};
 
 #[cfg(any(target_os = "linux", target_os = "freebsd"))]
 mod linux;
 #[cfg(any(target_os = "linux", target_os = "freebsd"))]
-pub use linux::{add_dns_servers, delete_dns_servers};
+pub use linux::{
+    add_dns_servers, backup_dns_state, block_dns_leaks, delete_dns_servers, restore_dns_state,
+    unblock_dns_leaks,
+};
 
 #[cfg(target_os = "windows")]
 mod windows;
 #[cfg(target_os = "windows")]
-pub use windows::{add_dns_servers, delete_dns_servers};
+pub use windows::{
+    add_dns_servers, backup_dns_state, block_dns_leaks, delete_dns_servers, restore_dns_state,
+    unblock_dns_leaks,
+};
+
+/// A domain resolved through the tunnel's DNS servers (split DNS).
+///
+/// Queries for the domain and its subdomains go to `dns_servers`, while all other
+/// queries keep using the system resolver, e.g.:
+/// ```toml
+/// dns_split_domains = [
+///     { domain = "corp.example.com", dns_servers = ["10.0.1.1"] },
+///     { domain = "internal" },
+/// ]
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+pub struct SplitDnsDomain {
+    /// The domain, including all of its subdomains
+    pub domain: String,
+    /// DNS servers resolving the domain (default = the tunnel's `dns_servers`)
+    #[serde(default)]
+    pub dns_servers: Vec<IpAddr>,
+}
+
+/// The protocol used to reach the tunnel's DNS servers.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+pub enum DnsProtocol {
+    /// Plain DNS over UDP/TCP port 53, handled by the system resolver
+    #[default]
+    #[serde(rename = "plain")]
+    Plain,
+    /// DNS-over-TLS (RFC 7858) on port 853
+    #[serde(rename = "dot")]
+    Dot,
+    /// DNS-over-HTTPS (RFC 8484) on port 443
+    #[serde(rename = "doh")]
+    Doh,
+}
+
+/// Path of the DNS-over-HTTPS endpoint used unless another one is configured (RFC 8484).
+pub const DEFAULT_DOH_PATH: &str = "/dns-query";
+
+/// DNS settings applied together with the tunnel's DNS servers.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DnsOptions {
+    /// Domains resolved through the tunnel, or empty to resolve all domains through it
+    pub split_domains: Vec<SplitDnsDomain>,
+    /// Domains appended to unqualified host names
+    pub search_domains: Vec<String>,
+    /// The protocol used to reach the DNS servers
+    ///
+    /// With an encrypted protocol, the system resolver is pointed at a local [`DnsStub`]
+    /// forwarding the queries to the DNS servers.
+    pub protocol: DnsProtocol,
+    /// Name the certificates of the DNS servers are verified against with an encrypted
+    /// protocol, or `None` to verify them against their IP address
+    pub tls_name: Option<String>,
+    /// Certificates trusted to sign the certificates of the DNS servers with an encrypted
+    /// protocol, or empty to use the platform's trust store
+    pub trusted_certificate_paths: Vec<PathBuf>,
+    /// Path of the DNS-over-HTTPS endpoint, or `None` for [`DEFAULT_DOH_PATH`]
+    pub doh_path: Option<String>,
+    /// Whether DNS queries to other servers are blocked while the DNS servers are configured
+    pub leak_protection: LeakProtection,
+}
+
+/// Returns whether `domain` is a valid DNS domain name, e.g. `corp.example.com`.
+///
+/// ### Arguments
+/// - `domain` - the domain to check, without a trailing dot
+pub fn is_valid_domain(domain: &str) -> bool {
+    !domain.is_empty()
+        && domain.len() <= 253
+        && domain.split('.').all(|label| {
+            !label.is_empty()
+                && label.len() <= 63
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+                && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        })
+}
+
+/// Generates the contents of a macOS resolver file (`/etc/resolver/<domain>`).
+///
+/// ### Arguments
+/// - `dns_servers` - the DNS servers resolving the domain
+/// - `interface_name` - the name of the tunnel interface
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+fn resolver_file_contents(dns_servers: &[IpAddr], interface_name: &str) -> String {
+    let mut contents = String::from("# Generated by Quincy, removed when the tunnel closes\n");
+    for server in dns_servers {
+        contents.push_str(&nameserver_line(server, interface_name));
+        contents.push('\n');
+    }
+
+    contents
+}
+
+/// Formats a `nameserver` line of `resolv.conf` or a macOS resolver file.
+///
+/// Link-local IPv6 addresses are only unique per interface, so they are scoped to the
+/// tunnel interface (`fe80::1%tun0`).
+///
+/// ### Arguments
+/// - `server` - the DNS server
+/// - `interface_name` - the name of the tunnel interface
+#[cfg_attr(target_os = "windows", allow(dead_code))]
+fn nameserver_line(server: &IpAddr, interface_name: &str) -> String {
+    match server {
+        IpAddr::V6(address) if address.is_unicast_link_local() => {
+            format!("nameserver {address}%{interface_name}")
+        }
+        _ => format!("nameserver {server}"),
+    }
+}
+
+/// Builds the `netsh` arguments setting the DNS servers of an interface on Windows.
+///
+/// The DNS servers of each IP family are set with their own `netsh interface ipv4` or
+/// `netsh interface ipv6` context: the first server replaces the existing ones and the
+/// others are appended in order.
+///
+/// ### Arguments
+/// - `dns_servers` - the DNS servers of the interface
+/// - `interface_name` - the name of the interface
+///
+/// ### Returns
+/// The arguments of each `netsh` invocation, in order.
+#[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+fn netsh_dns_args(dns_servers: &[IpAddr], interface_name: &str) -> Vec<Vec<String>> {
+    let mut commands = Vec::new();
+
+    for (context, family) in [("ipv4", IpFamily::V4), ("ipv6", IpFamily::V6)] {
+        let family_servers = dns_servers
+            .iter()
+            .filter(|server| IpFamily::of(server) == family);
+
+        for (index, server) in family_servers.enumerate() {
+            let mut args = vec!["interface".to_string(), context.to_string()];
+            if index == 0 {
+                // netsh interface <context> set dnsservers name=<interface> source=static address=<server> ...
+                args.extend(["set".to_string(), "dnsservers".to_string()]);
+                args.push(format!("name={interface_name}"));
+                args.push("source=static".to_string());
+                args.push(format!("address={server}"));
+                args.push("register=none".to_string());
+            } else {
+                // netsh interface <context> add dnsservers name=<interface> address=<server> index=<n> ...
+                args.extend(["add".to_string(), "dnsservers".to_string()]);
+                args.push(format!("name={interface_name}"));
+                args.push(format!("address={server}"));
+                args.push(format!("index={}", index + 1));
+            }
+            args.push("validate=no".to_string());
+            commands.push(args);
+        }
+    }
+
+    commands
+}
+
+/// Validates the DNS servers about to be configured on the tunnel interface.
+///
+/// Addresses that can never act as a unicast DNS server (unspecified, loopback,
+/// multicast or broadcast) are dropped with a warning, so that a misconfigured
+/// server list cannot break name resolution on the client.
+///
+/// ### Arguments
+/// - `dns_servers` - the DNS servers to validate
+/// - `max_servers` - the maximum number of DNS servers accepted
+///
+/// ### Returns
+/// - `Vec<IpAddr>` - the usable DNS servers, in their original order
+///
+/// ### Errors
+/// Returns `DnsError::InvalidConfiguration` if more than `max_servers` DNS servers are given.
+pub fn validate_dns_servers(dns_servers: &[IpAddr], max_servers: usize) -> Result<Vec<IpAddr>> {
+    if dns_servers.len() > max_servers {
+        return Err(DnsError::InvalidConfiguration {
+            reason: format!(
+                "{} DNS servers exceed the limit of {max_servers}",
+                dns_servers.len()
+            ),
+        }
+        .into());
+    }
+
+    let valid_servers = dns_servers
+        .iter()
+        .filter(|server| {
+            let usable = is_unicast_dns_server(server);
+            if !usable {
+                warn!("Ignoring DNS server {server}: not a routable unicast address");
+            }
+            usable
+        })
+        .copied()
+        .collect();
+
+    Ok(valid_servers)
+}
+
+fn is_unicast_dns_server(address: &IpAddr) -> bool {
+    let broadcast = match address {
+        IpAddr::V4(address) => address.is_broadcast(),
+        IpAddr::V6(_) => false,
+    };
+
+    !(address.is_unspecified() || address.is_loopback() || address.is_multicast() || broadcast)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::QuincyError;
+
+    #[test]
+    fn over_limit_dns_server_list_is_rejected() {
+        let dns_servers: Vec<IpAddr> = (1..=4)
+            .map(|host| format!("10.0.0.{host}").parse().unwrap())
+            .collect();
+
+        let result = validate_dns_servers(&dns_servers, 3);
+
+        assert!(matches!(
+            result,
+            Err(QuincyError::Dns(DnsError::InvalidConfiguration { .. }))
+        ));
+        assert_eq!(validate_dns_servers(&dns_servers, 4).unwrap(), dns_servers);
+    }
+
+    #[test]
+    fn multicast_and_loopback_dns_servers_are_dropped() {
+        let dns_servers: Vec<IpAddr> = [
+            "10.0.0.1",
+            "224.0.0.251",
+            "127.0.0.1",
+            "ff02::fb",
+            "fd00::1",
+        ]
+        .iter()
+        .map(|address| address.parse().unwrap())
+        .collect();
+
+        let valid_servers = validate_dns_servers(&dns_servers, 8).unwrap();
+
+        assert_eq!(
+            valid_servers,
+            vec![
+                "10.0.0.1".parse::<IpAddr>().unwrap(),
+                "fd00::1".parse::<IpAddr>().unwrap()
+            ]
+        );
+    }
+
+    #[test]
+    fn domain_names_are_validated() {
+        assert!(is_valid_domain("corp.example.com"));
+        assert!(is_valid_domain("internal"));
+        assert!(is_valid_domain("xn--bcher-kva.example"));
+
+        assert!(!is_valid_domain(""));
+        assert!(!is_valid_domain(".example.com"));
+        assert!(!is_valid_domain("example..com"));
+        assert!(!is_valid_domain("-corp.example.com"));
+        assert!(!is_valid_domain("../../etc/hosts"));
+        assert!(!is_valid_domain("corp example.com"));
+    }
+
+    #[test]
+    fn resolver_file_lists_nameservers() {
+        let dns_servers: Vec<IpAddr> = ["10.0.1.1", "fd00::53"]
+            .iter()
+            .map(|address| address.parse().unwrap())
+            .collect();
+
+        let contents = resolver_file_contents(&dns_servers, "utun4");
+
+        assert_eq!(
+            contents.lines().skip(1).collect::<Vec<_>>(),
+            vec!["nameserver 10.0.1.1", "nameserver fd00::53"]
+        );
+        assert!(contents.starts_with('#'));
+        assert!(contents.ends_with('\n'));
+    }
+
+    #[test]
+    fn nameserver_lines_format_mixed_families() {
+        let lines = [
+            "10.0.0.1",
+            "2606:4700:4700::1111",
+            "fe80::1",
+            "::ffff:10.0.0.2",
+        ]
+        .iter()
+        .map(|address| nameserver_line(&address.parse().unwrap(), "quincy0"))
+        .collect::<Vec<_>>();
+
+        assert_eq!(
+            lines,
+            vec![
+                "nameserver 10.0.0.1",
+                "nameserver 2606:4700:4700::1111",
+                "nameserver fe80::1%quincy0",
+                "nameserver ::ffff:10.0.0.2",
+            ]
+        );
+    }
+
+    #[test]
+    fn netsh_sets_dns_servers_per_family() {
+        let dns_servers: Vec<IpAddr> = ["10.0.0.1", "2606:4700:4700::1111", "10.0.0.2"]
+            .iter()
+            .map(|address| address.parse().unwrap())
+            .collect();
+
+        let commands = netsh_dns_args(&dns_servers, "quincy")
+            .into_iter()
+            .map(|args| args.join(" "))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            commands,
+            vec![
+                "interface ipv4 set dnsservers name=quincy source=static address=10.0.0.1 register=none validate=no",
+                "interface ipv4 add dnsservers name=quincy address=10.0.0.2 index=2 validate=no",
+                "interface ipv6 set dnsservers name=quincy source=static address=2606:4700:4700::1111 register=none validate=no",
+            ]
+        );
+    }
+}
@@ -0,0 +1,241 @@
+//! systemd-resolved backend configuring the DNS servers of the tunnel interface over D-Bus.
+//!
+//! systemd-resolved keeps DNS servers and domains per link, so the tunnel's configuration
+//! does not touch `/etc/resolv.conf` and is not reverted by other software rewriting it.
+
+use crate::Result;
+use crate::error::DnsError;
+use crate::network::dns::SplitDnsDomain;
+use std::ffi::CString;
+use std::io;
+use std::net::IpAddr;
+use std::path::Path;
+use zbus::blocking::{Connection, Proxy};
+
+/// Runtime directory of systemd-resolved, present while it is running.
+const RESOLVED_RUNTIME_DIR: &str = "/run/systemd/resolve";
+const RESOLVED_DESTINATION: &str = "org.freedesktop.resolve1";
+const RESOLVED_PATH: &str = "/org/freedesktop/resolve1";
+const RESOLVED_MANAGER_INTERFACE: &str = "org.freedesktop.resolve1.Manager";
+
+/// A DNS server as passed to `SetLinkDNS`: the address family and the address bytes.
+type LinkDnsServer = (i32, Vec<u8>);
+/// A domain as passed to `SetLinkDomains`: the domain and whether it is routing-only.
+type LinkDomain = (String, bool);
+
+/// Returns whether systemd-resolved is running.
+pub fn is_running() -> bool {
+    Path::new(RESOLVED_RUNTIME_DIR).is_dir()
+}
+
+/// Configures the DNS servers and domains of the interface in systemd-resolved.
+///
+/// Without split domains, the interface resolves all domains (routing domain `~.`) and is
+/// used as the default route for DNS. With split domains, it only resolves those domains,
+/// through the union of their DNS servers. Search domains are added as regular domains,
+/// which are both searched and resolved through the interface.
+///
+/// ### Arguments
+/// - `dns_servers` - the DNS servers of the interface
+/// - `split_domains` - the domains resolved through the interface, or none for all domains
+/// - `search_domains` - the domains appended to unqualified host names
+/// - `interface_name` - the name of the interface
+///
+/// ### Errors
+/// Returns `DnsError::PlatformError` if the interface does not exist or systemd-resolved
+/// rejects the configuration.
+pub fn configure_link(
+    dns_servers: &[IpAddr],
+    split_domains: &[SplitDnsDomain],
+    search_domains: &[String],
+    interface_name: &str,
+) -> Result<()> {
+    let link = link_index(interface_name)?;
+    let (servers, domains) = link_settings(dns_servers, split_domains, search_domains);
+    let connection = system_bus()?;
+    let manager = manager(&connection)?;
+
+    call(&manager, "SetLinkDNS", &(link, servers))?;
+    call(&manager, "SetLinkDomains", &(link, domains))?;
+    call(
+        &manager,
+        "SetLinkDefaultRoute",
+        &(link, split_domains.is_empty()),
+    )
+}
+
+/// Reverts the systemd-resolved configuration of the interface.
+///
+/// ### Arguments
+/// - `interface_name` - the name of the interface
+///
+/// ### Errors
+/// Returns `DnsError::RestoreFailed` if the configuration cannot be reverted.
+pub fn revert_link(interface_name: &str) -> Result<()> {
+    let link = link_index(interface_name).map_err(|_| DnsError::RestoreFailed)?;
+    let connection = system_bus().map_err(|_| DnsError::RestoreFailed)?;
+    let manager = manager(&connection).map_err(|_| DnsError::RestoreFailed)?;
+
+    call(&manager, "RevertLink", &link).map_err(|_| DnsError::RestoreFailed.into())
+}
+
+/// Builds the DNS servers and domains of the interface.
+fn link_settings(
+    dns_servers: &[IpAddr],
+    split_domains: &[SplitDnsDomain],
+    search_domains: &[String],
+) -> (Vec<LinkDnsServer>, Vec<LinkDomain>) {
+    let search_domains = search_domains.iter().map(|domain| (domain.clone(), false));
+
+    if split_domains.is_empty() {
+        let servers = dns_servers.iter().map(link_dns_server).collect();
+        let domains = [(".".to_string(), true)]
+            .into_iter()
+            .chain(search_domains)
+            .collect();
+        return (servers, domains);
+    }
+
+    let mut servers = Vec::new();
+    for server in split_domains.iter().flat_map(|split| &split.dns_servers) {
+        let server = link_dns_server(server);
+        if !servers.contains(&server) {
+            servers.push(server);
+        }
+    }
+    let domains = split_domains
+        .iter()
+        .map(|split| (split.domain.clone(), true))
+        .chain(search_domains)
+        .collect();
+
+    (servers, domains)
+}
+
+fn link_dns_server(server: &IpAddr) -> LinkDnsServer {
+    match server {
+        IpAddr::V4(address) => (libc::AF_INET, address.octets().to_vec()),
+        IpAddr::V6(address) => (libc::AF_INET6, address.octets().to_vec()),
+    }
+}
+
+/// Returns the index of the interface, which identifies its link in systemd-resolved.
+fn link_index(interface_name: &str) -> Result<i32> {
+    let name = CString::new(interface_name).map_err(|_| DnsError::InvalidConfiguration {
+        reason: format!("invalid interface name: {interface_name}"),
+    })?;
+
+    // SAFETY: `name` is a valid NUL-terminated string that outlives the call
+    let index = unsafe { libc::if_nametoindex(name.as_ptr()) };
+    if index == 0 {
+        return Err(DnsError::PlatformError {
+            message: format!(
+                "failed to find interface {interface_name}: {}",
+                io::Error::last_os_error()
+            ),
+        }
+        .into());
+    }
+
+    Ok(index as i32)
+}
+
+fn system_bus() -> Result<Connection> {
+    Connection::system().map_err(|e| {
+        DnsError::PlatformError {
+            message: format!("failed to connect to the system D-Bus: {e}"),
+        }
+        .into()
+    })
+}
+
+fn manager(connection: &Connection) -> Result<Proxy<'static>> {
+    Proxy::new(
+        connection,
+        RESOLVED_DESTINATION,
+        RESOLVED_PATH,
+        RESOLVED_MANAGER_INTERFACE,
+    )
+    .map_err(|e| {
+        DnsError::PlatformError {
+            message: format!("failed to access systemd-resolved: {e}"),
+        }
+        .into()
+    })
+}
+
+/// Calls a method of the systemd-resolved manager.
+fn call<B>(manager: &Proxy<'_>, method: &str, body: &B) -> Result<()>
+where
+    B: serde::Serialize + zbus::zvariant::DynamicType,
+{
+    manager
+        .call_method(method, body)
+        .map_err(|e| DnsError::PlatformError {
+            message: format!("systemd-resolved {method} failed: {e}"),
+        })?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn link_resolves_all_domains_without_split_dns() {
+        let dns_servers = ["10.0.0.1".parse().unwrap(), "fd00::1".parse().unwrap()];
+
+        let (servers, domains) =
+            link_settings(&dns_servers, &[], &["corp.example.com".to_string()]);
+
+        assert_eq!(
+            servers,
+            vec![
+                (libc::AF_INET, vec![10, 0, 0, 1]),
+                (
+                    libc::AF_INET6,
+                    vec![0xfd, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1]
+                ),
+            ]
+        );
+        assert_eq!(
+            domains,
+            vec![
+                (".".to_string(), true),
+                ("corp.example.com".to_string(), false)
+            ]
+        );
+    }
+
+    #[test]
+    fn link_resolves_only_split_domains() {
+        let split_domains = [
+            SplitDnsDomain {
+                domain: "corp.example.com".to_string(),
+                dns_servers: vec!["10.0.1.1".parse().unwrap()],
+            },
+            SplitDnsDomain {
+                domain: "internal".to_string(),
+                dns_servers: vec!["10.0.1.1".parse().unwrap(), "10.0.1.2".parse().unwrap()],
+            },
+        ];
+
+        let (servers, domains) = link_settings(&["10.0.0.1".parse().unwrap()], &split_domains, &[]);
+
+        assert_eq!(
+            servers,
+            vec![
+                (libc::AF_INET, vec![10, 0, 1, 1]),
+                (libc::AF_INET, vec![10, 0, 1, 2])
+            ]
+        );
+        assert_eq!(
+            domains,
+            vec![
+                ("corp.example.com".to_string(), true),
+                ("internal".to_string(), true)
+            ]
+        );
+    }
+}
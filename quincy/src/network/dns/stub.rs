@@ -0,0 +1,849 @@
+//! Local stub resolver forwarding DNS queries over DNS-over-TLS or DNS-over-HTTPS.
+//!
+//! The system resolver is pointed at [`STUB_ADDRESS`], which keeps speaking plain DNS,
+//! while the stub forwards every query to the tunnel's DNS servers over an encrypted
+//! connection, so that DNS traffic is not exposed even inside the tunnel's networks.
+
+use crate::Result;
+use crate::certificates::load_certificates_from_file;
+use crate::error::DnsError;
+use crate::network::dns::{DEFAULT_DOH_PATH, DnsOptions, DnsProtocol};
+use bytes::Bytes;
+use http_body_util::{BodyExt, Full, Limited};
+use hyper::client::conn::http1::{self, SendRequest};
+use hyper::{Request, StatusCode, header};
+use hyper_util::rt::TokioIo;
+use rustls::crypto::aws_lc_rs;
+use rustls::pki_types::ServerName;
+use rustls::{ClientConfig, RootCertStore};
+use rustls_platform_verifier::BuilderVerifierExt;
+use std::collections::HashMap;
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::{TcpStream, UdpSocket};
+use tokio::task::{JoinHandle, JoinSet};
+use tokio::time::timeout;
+use tokio_rustls::TlsConnector;
+use tokio_rustls::client::TlsStream;
+use tracing::{debug, warn};
+
+/// The address the stub resolver listens on.
+pub const STUB_ADDRESS: IpAddr = IpAddr::V4(Ipv4Addr::LOCALHOST);
+
+const DNS_PORT: u16 = 53;
+const DOT_PORT: u16 = 853;
+const DOH_PORT: u16 = 443;
+const DNS_HEADER_LEN: usize = 12;
+const MAX_DNS_MESSAGE_SIZE: usize = 65535;
+const DNS_MESSAGE_CONTENT_TYPE: &str = "application/dns-message";
+/// Time given to a single DNS server to answer a query
+const UPSTREAM_TIMEOUT: Duration = Duration::from_secs(5);
+/// Maximum number of queries forwarded at the same time
+const MAX_PENDING_QUERIES: usize = 256;
+/// Maximum number of idle connections kept open to a single DNS server
+const MAX_IDLE_CONNECTIONS: usize = 4;
+/// Attempts to bind the stub's socket while a previously stopped stub releases it
+const BIND_ATTEMPTS: u32 = 10;
+const BIND_RETRY_INTERVAL: Duration = Duration::from_millis(50);
+
+/// A DNS-over-HTTPS connection, sending one request at a time.
+type DohSender = SendRequest<Full<Bytes>>;
+
+/// A running stub resolver, stopped when dropped.
+pub struct DnsStub {
+    task: JoinHandle<()>,
+}
+
+impl DnsStub {
+    /// Starts a stub resolver on [`STUB_ADDRESS`] forwarding queries to `dns_servers`.
+    ///
+    /// The certificates of `dns_servers` are verified against `options.tls_name` if set,
+    /// while those of other DNS servers of the split domains are verified against their
+    /// IP address.
+    ///
+    /// ### Arguments
+    /// - `dns_servers` - the DNS servers queries are forwarded to
+    /// - `options` - the protocol, split domains and TLS settings of the stub
+    ///
+    /// ### Errors
+    /// Returns `DnsError::InvalidConfiguration` for plain DNS or an invalid TLS name, an error
+    /// if a trusted certificate cannot be loaded, or `DnsError::PlatformError` if the stub
+    /// cannot listen on [`STUB_ADDRESS`] or is started outside of a Tokio runtime.
+    pub fn start(dns_servers: &[IpAddr], options: &DnsOptions) -> Result<Self> {
+        if options.protocol == DnsProtocol::Plain {
+            return Err(DnsError::InvalidConfiguration {
+                reason: "plain DNS is resolved without a stub resolver".to_string(),
+            }
+            .into());
+        }
+
+        let runtime =
+            tokio::runtime::Handle::try_current().map_err(|e| DnsError::PlatformError {
+                message: format!("the DNS stub resolver requires a Tokio runtime: {e}"),
+            })?;
+
+        // Split domains without DNS servers of their own are resolved through `dns_servers`
+        let upstream = |address: IpAddr| match &options.tls_name {
+            Some(tls_name) if dns_servers.contains(&address) => {
+                Upstream::with_tls_name(address, tls_name)
+            }
+            _ => Ok(Upstream::new(address)),
+        };
+        let upstreams = Upstreams {
+            protocol: options.protocol,
+            tls: tls_config(options.protocol, &options.trusted_certificate_paths)?,
+            doh_path: options
+                .doh_path
+                .clone()
+                .unwrap_or_else(|| DEFAULT_DOH_PATH.to_string()),
+            dns_servers: dns_servers
+                .iter()
+                .map(|&address| upstream(address))
+                .collect::<Result<_>>()?,
+            split_domains: options
+                .split_domains
+                .iter()
+                .map(|split| {
+                    let servers = split
+                        .dns_servers
+                        .iter()
+                        .map(|&address| upstream(address))
+                        .collect::<Result<_>>()?;
+                    Ok((split.domain.clone(), servers))
+                })
+                .collect::<Result<_>>()?,
+            dot_connections: ConnectionPool::default(),
+            doh_connections: ConnectionPool::default(),
+        };
+
+        let bind_address = SocketAddr::new(STUB_ADDRESS, DNS_PORT);
+        let socket = bind_socket(bind_address)
+            .and_then(|socket| {
+                socket.set_nonblocking(true)?;
+                let _runtime = runtime.enter();
+                UdpSocket::from_std(socket)
+            })
+            .map_err(|e| DnsError::PlatformError {
+                message: format!("failed to start the DNS stub resolver on {bind_address}: {e}"),
+            })?;
+
+        let task = runtime.spawn(serve(socket, Arc::new(upstreams)));
+
+        Ok(Self { task })
+    }
+}
+
+impl Drop for DnsStub {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// A DNS server the stub forwards queries to.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+struct Upstream {
+    address: IpAddr,
+    /// The name the server's certificate is verified against
+    server_name: ServerName<'static>,
+    /// The `Host` header of DoH requests
+    host: String,
+}
+
+impl Upstream {
+    /// Creates an upstream verified against its IP address.
+    fn new(address: IpAddr) -> Self {
+        let host = match address {
+            IpAddr::V4(_) => address.to_string(),
+            IpAddr::V6(_) => format!("[{address}]"),
+        };
+
+        Self {
+            address,
+            server_name: ServerName::from(address),
+            host,
+        }
+    }
+
+    /// Creates an upstream verified against the given name.
+    ///
+    /// ### Errors
+    /// Returns `DnsError::InvalidConfiguration` if the name is not a valid DNS name.
+    fn with_tls_name(address: IpAddr, tls_name: &str) -> Result<Self> {
+        let server_name = ServerName::try_from(tls_name.to_string()).map_err(|e| {
+            DnsError::InvalidConfiguration {
+                reason: format!("invalid DNS TLS name '{tls_name}': {e}"),
+            }
+        })?;
+
+        Ok(Self {
+            address,
+            server_name,
+            host: tls_name.to_string(),
+        })
+    }
+}
+
+/// Idle connections to the DNS servers, reused by later queries.
+struct ConnectionPool<C> {
+    idle: Mutex<HashMap<Upstream, Vec<C>>>,
+}
+
+impl<C> Default for ConnectionPool<C> {
+    fn default() -> Self {
+        Self {
+            idle: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl<C> ConnectionPool<C> {
+    /// Takes the most recently used idle connection to a DNS server.
+    fn take(&self, upstream: &Upstream) -> Option<C> {
+        self.idle
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get_mut(upstream)?
+            .pop()
+    }
+
+    /// Returns a connection to the pool, closing it if enough connections are idle.
+    fn put(&self, upstream: &Upstream, connection: C) {
+        let mut idle = self.idle.lock().unwrap_or_else(|e| e.into_inner());
+        let connections = idle.entry(upstream.clone()).or_default();
+        if connections.len() < MAX_IDLE_CONNECTIONS {
+            connections.push(connection);
+        }
+    }
+}
+
+/// The DNS servers the stub forwards queries to.
+struct Upstreams {
+    protocol: DnsProtocol,
+    tls: Arc<ClientConfig>,
+    doh_path: String,
+    dns_servers: Vec<Upstream>,
+    split_domains: Vec<(String, Vec<Upstream>)>,
+    dot_connections: ConnectionPool<TlsStream<TcpStream>>,
+    doh_connections: ConnectionPool<DohSender>,
+}
+
+impl Upstreams {
+    /// Resolves a query received by the stub.
+    ///
+    /// ### Returns
+    /// - `Option<Vec<u8>>` - the response, a SERVFAIL response if no DNS server answered,
+    ///   or `None` if the query is malformed
+    async fn resolve(&self, query: &[u8]) -> Option<Vec<u8>> {
+        let (name, question_end) = parse_question(query)?;
+        let servers = servers_for(&name, &self.dns_servers, &self.split_domains);
+
+        match self.forward(servers, query).await {
+            Ok(response) => Some(response),
+            Err(e) => {
+                warn!("Failed to resolve {name}: {e}");
+                Some(servfail_response(query, question_end))
+            }
+        }
+    }
+
+    /// Forwards a query to the given DNS servers in order, until one of them answers.
+    ///
+    /// ### Errors
+    /// Returns `DnsError::ServerUnreachable` with the last DNS server tried if none answered.
+    async fn forward(&self, servers: &[Upstream], query: &[u8]) -> Result<Vec<u8>> {
+        let mut last_error = DnsError::InvalidConfiguration {
+            reason: "no DNS servers to forward the query to".to_string(),
+        };
+
+        for server in servers {
+            let address = server.address;
+            match timeout(UPSTREAM_TIMEOUT, self.exchange(server, query)).await {
+                Ok(Ok(response)) => return Ok(response),
+                Ok(Err(e)) => debug!("DNS server {address} failed to answer: {e}"),
+                Err(_) => debug!("DNS server {address} did not answer in time"),
+            }
+
+            last_error = DnsError::ServerUnreachable { server: address };
+        }
+
+        Err(last_error.into())
+    }
+
+    /// Sends a query to a DNS server and receives its response.
+    async fn exchange(&self, server: &Upstream, query: &[u8]) -> io::Result<Vec<u8>> {
+        match self.protocol {
+            DnsProtocol::Plain => Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "plain DNS is not forwarded",
+            )),
+            DnsProtocol::Dot => self.exchange_dot(server, query).await,
+            DnsProtocol::Doh => self.exchange_doh(server, query).await,
+        }
+    }
+
+    /// Sends a query over DNS-over-TLS, reusing an idle connection to the server if possible.
+    async fn exchange_dot(&self, server: &Upstream, query: &[u8]) -> io::Result<Vec<u8>> {
+        if let Some(mut stream) = self.dot_connections.take(server) {
+            // The server may have closed the idle connection in the meantime
+            match dot_query(&mut stream, query).await {
+                Ok(response) => {
+                    self.dot_connections.put(server, stream);
+                    return Ok(response);
+                }
+                Err(e) => debug!(
+                    "Idle connection to DNS server {} failed: {e}",
+                    server.address
+                ),
+            }
+        }
+
+        let mut stream = self.connect(server, DOT_PORT).await?;
+        let response = dot_query(&mut stream, query).await?;
+        self.dot_connections.put(server, stream);
+
+        Ok(response)
+    }
+
+    /// Sends a query over DNS-over-HTTPS, reusing an idle connection to the server if possible.
+    async fn exchange_doh(&self, server: &Upstream, query: &[u8]) -> io::Result<Vec<u8>> {
+        let idle = self
+            .doh_connections
+            .take(server)
+            .filter(|sender| !sender.is_closed());
+        if let Some(mut sender) = idle {
+            // The server may have closed the idle connection in the meantime
+            match doh_query(&mut sender, &server.host, &self.doh_path, query).await {
+                Ok(response) => {
+                    self.doh_connections.put(server, sender);
+                    return Ok(response);
+                }
+                Err(e) => debug!(
+                    "Idle connection to DNS server {} failed: {e}",
+                    server.address
+                ),
+            }
+        }
+
+        let stream = self.connect(server, DOH_PORT).await?;
+        let mut sender = doh_handshake(stream).await?;
+        let response = doh_query(&mut sender, &server.host, &self.doh_path, query).await?;
+        self.doh_connections.put(server, sender);
+
+        Ok(response)
+    }
+
+    /// Opens a TLS connection to a DNS server, verifying its certificate against its server name.
+    async fn connect(&self, server: &Upstream, port: u16) -> io::Result<TlsStream<TcpStream>> {
+        let stream = TcpStream::connect(SocketAddr::new(server.address, port)).await?;
+
+        TlsConnector::from(self.tls.clone())
+            .connect(server.server_name.clone(), stream)
+            .await
+    }
+}
+
+/// Binds the stub's socket.
+///
+/// A stub stopped just before, e.g. when the DNS servers are replaced, releases the
+/// socket only once its task has been cancelled, so binding is retried for a short while.
+fn bind_socket(address: SocketAddr) -> io::Result<std::net::UdpSocket> {
+    let mut attempts = 1;
+
+    loop {
+        match std::net::UdpSocket::bind(address) {
+            Err(e) if e.kind() == io::ErrorKind::AddrInUse && attempts < BIND_ATTEMPTS => {
+                attempts += 1;
+                std::thread::sleep(BIND_RETRY_INTERVAL);
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Receives queries on the stub's socket and answers them once forwarded.
+///
+/// Pending queries live in a `JoinSet` owned by this task, so aborting it
+/// also cancels them and releases the socket.
+async fn serve(socket: UdpSocket, upstreams: Arc<Upstreams>) {
+    let mut buf = vec![0; MAX_DNS_MESSAGE_SIZE];
+    let mut pending = JoinSet::new();
+
+    loop {
+        tokio::select! {
+            received = socket.recv_from(&mut buf) => {
+                let (len, client) = match received {
+                    Ok(received) => received,
+                    Err(e) => {
+                        debug!("Failed to receive a DNS query: {e}");
+                        continue;
+                    }
+                };
+
+                if pending.len() >= MAX_PENDING_QUERIES {
+                    debug!("Dropping DNS query from {client}: too many pending queries");
+                    continue;
+                }
+
+                let query = buf[..len].to_vec();
+                let upstreams = upstreams.clone();
+                pending.spawn(async move { (upstreams.resolve(&query).await, client) });
+            }
+            Some(Ok((response, client))) = pending.join_next() => {
+                let Some(response) = response else {
+                    continue;
+                };
+
+                if let Err(e) = socket.send_to(&response, client).await {
+                    debug!("Failed to send a DNS response to {client}: {e}");
+                }
+            }
+        }
+    }
+}
+
+/// Creates the TLS configuration used to connect to the DNS servers.
+///
+/// Certificates are verified by the platform verifier, like those of the Quincy server,
+/// unless trusted certificates are configured for the DNS servers.
+///
+/// ### Arguments
+/// - `protocol` - the encrypted protocol used to reach the DNS servers
+/// - `trusted_certificate_paths` - certificates trusted instead of the platform's trust store
+fn tls_config(
+    protocol: DnsProtocol,
+    trusted_certificate_paths: &[PathBuf],
+) -> Result<Arc<ClientConfig>> {
+    let tls_error = |e: rustls::Error| DnsError::PlatformError {
+        message: format!("failed to set up TLS for the DNS stub resolver: {e}"),
+    };
+
+    let builder = ClientConfig::builder_with_provider(Arc::new(aws_lc_rs::default_provider()))
+        .with_safe_default_protocol_versions()
+        .map_err(tls_error)?;
+
+    let builder = if trusted_certificate_paths.is_empty() {
+        builder.with_platform_verifier().map_err(tls_error)?
+    } else {
+        let mut roots = RootCertStore::empty();
+        for path in trusted_certificate_paths {
+            for certificate in load_certificates_from_file(path)? {
+                roots.add(certificate).map_err(tls_error)?;
+            }
+        }
+
+        builder.with_root_certificates(roots)
+    };
+
+    let mut config = builder.with_no_client_auth();
+    if protocol == DnsProtocol::Doh {
+        config.alpn_protocols = vec![b"http/1.1".to_vec()];
+    }
+
+    Ok(Arc::new(config))
+}
+
+/// Parses the first question of a DNS query.
+///
+/// ### Returns
+/// - `Option<(String, usize)>` - the lowercase name asked for and the offset just past the
+///   question, or `None` if the message is not a well-formed query
+fn parse_question(query: &[u8]) -> Option<(String, usize)> {
+    let is_response = query.get(2)? & 0x80 != 0;
+    let question_count = u16::from_be_bytes([*query.get(4)?, *query.get(5)?]);
+    if is_response || question_count == 0 {
+        return None;
+    }
+
+    let mut labels = Vec::new();
+    let mut offset = DNS_HEADER_LEN;
+    loop {
+        let len = *query.get(offset)? as usize;
+        offset += 1;
+        if len == 0 {
+            break;
+        }
+        // Questions carry neither compression pointers nor extended label types
+        if len > 63 {
+            return None;
+        }
+
+        let label = query.get(offset..offset + len)?;
+        labels.push(String::from_utf8_lossy(label).to_ascii_lowercase());
+        offset += len;
+    }
+
+    // QTYPE and QCLASS
+    offset += 4;
+
+    (offset <= query.len()).then(|| (labels.join("."), offset))
+}
+
+/// Returns the DNS servers responsible for `name`.
+///
+/// The most specific split domain containing the name wins, and names outside of
+/// all split domains go to the tunnel's DNS servers.
+fn servers_for<'a, T>(
+    name: &str,
+    dns_servers: &'a [T],
+    split_domains: &'a [(String, Vec<T>)],
+) -> &'a [T] {
+    split_domains
+        .iter()
+        .filter(|(domain, _)| is_within_domain(name, domain))
+        .max_by_key(|(domain, _)| domain.len())
+        .map(|(_, servers)| servers.as_slice())
+        .unwrap_or(dns_servers)
+}
+
+/// Returns whether `name` is `domain` or one of its subdomains.
+fn is_within_domain(name: &str, domain: &str) -> bool {
+    let (name, domain) = (name.as_bytes(), domain.as_bytes());
+    let Some(prefix_len) = name.len().checked_sub(domain.len()) else {
+        return false;
+    };
+
+    name[prefix_len..].eq_ignore_ascii_case(domain)
+        && (prefix_len == 0 || name[prefix_len - 1] == b'.')
+}
+
+/// Builds a SERVFAIL response to a query, echoing its ID and first question.
+fn servfail_response(query: &[u8], question_end: usize) -> Vec<u8> {
+    let mut response = query[..question_end].to_vec();
+    // QR with the query's opcode and RD, then RA with RCODE 2 (SERVFAIL)
+    response[2] = 0x80 | (query[2] & 0x79);
+    response[3] = 0x82;
+    // A single question and no answer, authority or additional records
+    response[4..DNS_HEADER_LEN].copy_from_slice(&[0, 1, 0, 0, 0, 0, 0, 0]);
+
+    response
+}
+
+/// Frames a query for DNS-over-TLS, prefixing it with its length (RFC 7858).
+fn dot_frame(query: &[u8]) -> Vec<u8> {
+    let mut frame = Vec::with_capacity(query.len() + 2);
+    frame.extend_from_slice(&(query.len() as u16).to_be_bytes());
+    frame.extend_from_slice(query);
+
+    frame
+}
+
+/// Sends a query over a DNS-over-TLS connection and receives its response.
+///
+/// ### Errors
+/// Returns `io::ErrorKind::InvalidData` if the response does not match the query.
+async fn dot_query<S>(stream: &mut S, query: &[u8]) -> io::Result<Vec<u8>>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    stream.write_all(&dot_frame(query)).await?;
+
+    let len = stream.read_u16().await?;
+    let mut response = vec![0; len as usize];
+    stream.read_exact(&mut response).await?;
+
+    check_response(query, response)
+}
+
+/// Starts an HTTP/1.1 connection for DNS-over-HTTPS over the given stream.
+///
+/// The connection is driven by a task of its own, which ends once the returned sender
+/// is dropped or the server closes the connection.
+async fn doh_handshake<S>(stream: S) -> io::Result<DohSender>
+where
+    S: AsyncRead + AsyncWrite + Send + Unpin + 'static,
+{
+    let (sender, connection) = http1::handshake(TokioIo::new(stream))
+        .await
+        .map_err(io::Error::other)?;
+
+    tokio::spawn(async move {
+        if let Err(e) = connection.await {
+            debug!("DNS-over-HTTPS connection failed: {e}");
+        }
+    });
+
+    Ok(sender)
+}
+
+/// Sends a query over a DNS-over-HTTPS connection and receives its response (RFC 8484).
+///
+/// ### Arguments
+/// - `sender` - the connection to the DNS server
+/// - `host` - the `Host` header of the request
+/// - `path` - the path of the DoH endpoint, e.g. `/dns-query`
+/// - `query` - the DNS query
+///
+/// ### Errors
+/// Returns `io::ErrorKind::InvalidData` if the response is not successful, is too large
+/// or does not match the query, or another error if the connection fails.
+async fn doh_query(
+    sender: &mut DohSender,
+    host: &str,
+    path: &str,
+    query: &[u8],
+) -> io::Result<Vec<u8>> {
+    let request = Request::post(path)
+        .header(header::HOST, host)
+        .header(header::CONTENT_TYPE, DNS_MESSAGE_CONTENT_TYPE)
+        .header(header::ACCEPT, DNS_MESSAGE_CONTENT_TYPE)
+        .body(Full::new(Bytes::copy_from_slice(query)))
+        .map_err(|e| invalid_data(format!("invalid DoH request: {e}")))?;
+
+    sender.ready().await.map_err(io::Error::other)?;
+    let response = sender
+        .send_request(request)
+        .await
+        .map_err(io::Error::other)?;
+
+    let status = response.status();
+    if status != StatusCode::OK {
+        return Err(invalid_data(format!("unexpected DoH response: {status}")));
+    }
+
+    // Content-Length and chunked bodies alike are decoded by the HTTP client
+    let body = Limited::new(response.into_body(), MAX_DNS_MESSAGE_SIZE)
+        .collect()
+        .await
+        .map_err(|e| invalid_data(format!("failed to receive the DoH response: {e}")))?
+        .to_bytes();
+
+    check_response(query, body.to_vec())
+}
+
+/// Checks that a response answers the query, returning the response.
+fn check_response(query: &[u8], response: Vec<u8>) -> io::Result<Vec<u8>> {
+    if response.len() < DNS_HEADER_LEN || response[..2] != query[..2] {
+        return Err(invalid_data(
+            "response does not match the query".to_string(),
+        ));
+    }
+
+    Ok(response)
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::DuplexStream;
+
+    /// A query for `Corp.Example.com` (type A, class IN) with an EDNS OPT record.
+    const QUERY: &[u8] = &[
+        0x12, 0x34, 0x01, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, // header
+        4, b'C', b'o', b'r', b'p', 7, b'E', b'x', b'a', b'm', b'p', b'l', b'e', 3, b'c', b'o',
+        b'm', 0, 0x00, 0x01, 0x00, 0x01, // question
+        0x00, 0x00, 0x29, 0x10, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // OPT record
+    ];
+    const QUESTION_END: usize = 34;
+
+    #[test]
+    fn parses_question_name() {
+        assert_eq!(
+            parse_question(QUERY),
+            Some(("corp.example.com".to_string(), QUESTION_END))
+        );
+
+        let mut response = QUERY.to_vec();
+        response[2] |= 0x80;
+        assert_eq!(parse_question(&response), None);
+        assert_eq!(parse_question(&QUERY[..20]), None);
+    }
+
+    #[test]
+    fn servfail_echoes_id_and_question() {
+        let response = servfail_response(QUERY, QUESTION_END);
+
+        assert_eq!(&response[..2], &QUERY[..2]);
+        assert_eq!(response[2], 0x81);
+        assert_eq!(response[3] & 0x0f, 2);
+        assert_eq!(&response[4..12], &[0, 1, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(&response[12..], &QUERY[12..QUESTION_END]);
+    }
+
+    #[test]
+    fn split_domains_pick_most_specific_servers() {
+        let server = |address: &str| address.parse::<IpAddr>().unwrap();
+        let dns_servers = [server("10.0.0.1")];
+        let split_domains = [
+            ("example.com".to_string(), vec![server("10.0.0.2")]),
+            ("corp.example.com".to_string(), vec![server("10.0.0.3")]),
+        ];
+        let servers = |name| servers_for(name, &dns_servers, &split_domains);
+
+        assert_eq!(servers("corp.example.com"), [server("10.0.0.3")]);
+        assert_eq!(servers("host.corp.example.com"), [server("10.0.0.3")]);
+        assert_eq!(servers("www.example.com"), [server("10.0.0.2")]);
+        assert_eq!(servers("notexample.com"), [server("10.0.0.1")]);
+    }
+
+    #[test]
+    fn upstreams_are_verified_against_tls_name_or_address() {
+        let address = "2606:4700:4700::1111".parse::<IpAddr>().unwrap();
+
+        let by_address = Upstream::new(address);
+        assert_eq!(by_address.server_name, ServerName::from(address));
+        assert_eq!(by_address.host, "[2606:4700:4700::1111]");
+
+        let by_name = Upstream::with_tls_name(address, "dns.example.com").unwrap();
+        assert_eq!(
+            by_name.server_name,
+            ServerName::try_from("dns.example.com").unwrap()
+        );
+        assert_eq!(by_name.host, "dns.example.com");
+
+        assert!(Upstream::with_tls_name(address, "not a name").is_err());
+    }
+
+    #[test]
+    fn dot_frame_is_length_prefixed() {
+        let frame = dot_frame(QUERY);
+
+        assert_eq!(&frame[..2], &(QUERY.len() as u16).to_be_bytes());
+        assert_eq!(&frame[2..], QUERY);
+    }
+
+    /// Answers a query by echoing it with the QR bit set.
+    fn answer(query: &[u8]) -> Vec<u8> {
+        let mut response = query.to_vec();
+        response[2] |= 0x80;
+
+        response
+    }
+
+    /// Reads an HTTP/1.1 request with a `Content-Length` body, returning its head and body.
+    async fn read_request(stream: &mut DuplexStream) -> (String, Vec<u8>) {
+        let mut head = Vec::new();
+        while !head.ends_with(b"\r\n\r\n") {
+            head.push(stream.read_u8().await.unwrap());
+        }
+        let head = String::from_utf8(head).unwrap();
+
+        let content_length = head
+            .lines()
+            .find_map(|line| {
+                let (name, value) = line.split_once(':')?;
+                name.eq_ignore_ascii_case("content-length")
+                    .then(|| value.trim().parse::<usize>().unwrap())
+            })
+            .unwrap();
+        let mut body = vec![0; content_length];
+        stream.read_exact(&mut body).await.unwrap();
+
+        (head, body)
+    }
+
+    #[tokio::test]
+    async fn dot_connection_is_reused() {
+        let (mut client, mut server) = tokio::io::duplex(4096);
+
+        let responder = tokio::spawn(async move {
+            for _ in 0..2 {
+                let len = server.read_u16().await.unwrap();
+                let mut query = vec![0; len as usize];
+                server.read_exact(&mut query).await.unwrap();
+                server.write_all(&dot_frame(&answer(&query))).await.unwrap();
+            }
+        });
+
+        for _ in 0..2 {
+            assert_eq!(dot_query(&mut client, QUERY).await.unwrap(), answer(QUERY));
+        }
+        responder.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn dot_mismatched_response_is_rejected() {
+        let (mut client, mut server) = tokio::io::duplex(4096);
+
+        let mut other = answer(QUERY);
+        other[0] ^= 0xff;
+        server.write_all(&dot_frame(&other)).await.unwrap();
+
+        assert_eq!(
+            dot_query(&mut client, QUERY).await.unwrap_err().kind(),
+            io::ErrorKind::InvalidData
+        );
+    }
+
+    #[tokio::test]
+    async fn doh_decodes_chunked_and_sized_responses_on_one_connection() {
+        let (client, mut server) = tokio::io::duplex(4096);
+
+        let responder = tokio::spawn(async move {
+            // A chunked response, split in the middle of the DNS message
+            let (head, query) = read_request(&mut server).await;
+            assert!(head.starts_with("POST /resolve HTTP/1.1\r\n"));
+            assert!(
+                head.to_ascii_lowercase()
+                    .contains("host: dns.example.com\r\n")
+            );
+            assert!(
+                head.to_ascii_lowercase()
+                    .contains("content-type: application/dns-message\r\n")
+            );
+
+            let response = answer(&query);
+            let (first, second) = response.split_at(10);
+            let mut chunked = b"HTTP/1.1 200 OK\r\n\
+                content-type: application/dns-message\r\n\
+                transfer-encoding: chunked\r\n\r\n"
+                .to_vec();
+            for chunk in [first, second] {
+                chunked.extend_from_slice(format!("{:x}\r\n", chunk.len()).as_bytes());
+                chunked.extend_from_slice(chunk);
+                chunked.extend_from_slice(b"\r\n");
+            }
+            chunked.extend_from_slice(b"0\r\n\r\n");
+            server.write_all(&chunked).await.unwrap();
+
+            // A response with a Content-Length on the same connection
+            let (_, query) = read_request(&mut server).await;
+            let response = answer(&query);
+            let mut sized = format!(
+                "HTTP/1.1 200 OK\r\n\
+                 content-type: application/dns-message\r\n\
+                 content-length: {}\r\n\r\n",
+                response.len()
+            )
+            .into_bytes();
+            sized.extend_from_slice(&response);
+            server.write_all(&sized).await.unwrap();
+        });
+
+        let mut sender = doh_handshake(client).await.unwrap();
+        for _ in 0..2 {
+            let response = doh_query(&mut sender, "dns.example.com", "/resolve", QUERY)
+                .await
+                .unwrap();
+            assert_eq!(response, answer(QUERY));
+        }
+        responder.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn doh_error_responses_are_rejected() {
+        let (client, mut server) = tokio::io::duplex(4096);
+
+        let responder = tokio::spawn(async move {
+            read_request(&mut server).await;
+            server
+                .write_all(b"HTTP/1.1 404 Not Found\r\ncontent-length: 0\r\n\r\n")
+                .await
+                .unwrap();
+        });
+
+        let mut sender = doh_handshake(client).await.unwrap();
+        let error = doh_query(&mut sender, "10.0.0.1", DEFAULT_DOH_PATH, QUERY)
+            .await
+            .unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        responder.await.unwrap();
+    }
+}
@@ -1,13 +1,45 @@
 use crate::Result;
+use crate::network::dns::backup::DnsBackup;
+use crate::network::dns::leak_protection::{DNS_LEAK_PORTS, windows_blocked_addresses};
+use crate::network::dns::{SplitDnsDomain, netsh_dns_args};
+use crate::network::firewall::{FirewallCommand, FirewallFeature};
+use crate::utils::command::run_command;
 use std::net::IpAddr;
+use tracing::warn;
 use wintun_bindings::Adapter;
 
-/// Adds a list of DNS servers to all network services on the endpoint.
+const NETSH_COMMAND: &str = "netsh";
+/// Name of the Windows Firewall rules of DNS leak protection.
+const LEAK_PROTECTION_RULE: &str = "QuincyDnsLeakProtection";
+
+/// Adds a list of DNS servers to the WinTun adapter of the given interface.
+///
+/// The adapter is looked up through WinTun, and its IPv4 and IPv6 DNS servers are then set
+/// with `netsh interface ipv4` and `netsh interface ipv6` respectively, each family
+/// replacing the previous servers of that family. Search domains are not applied, as they
+/// are global on Windows.
 ///
 /// ### Arguments
 /// - `dns_servers` - the DNS servers to be added
-/// - `interface_name` - the name of the interface to add the DNS servers to (unused)
-pub fn add_dns_servers(dns_servers: &[IpAddr], interface_name: &str) -> Result<()> {
+/// - `split_domains` - the domains resolved through the tunnel, which must be empty on Windows
+/// - `search_domains` - the domains appended to unqualified host names, ignored on Windows
+/// - `interface_name` - the name of the interface to add the DNS servers to
+pub fn add_dns_servers(
+    dns_servers: &[IpAddr],
+    split_domains: &[SplitDnsDomain],
+    search_domains: &[String],
+    interface_name: &str,
+) -> Result<()> {
+    if !split_domains.is_empty() {
+        return Err(crate::error::DnsError::InvalidConfiguration {
+            reason: "split DNS is not supported on Windows".to_string(),
+        }
+        .into());
+    }
+    if !search_domains.is_empty() {
+        warn!("DNS search domains are not supported on Windows");
+    }
+
     let wintun = unsafe {
         // SAFETY: signature verification is enabled in the WinTun library
         wintun_bindings::load().map_err(|e| crate::error::DnsError::PlatformError {
@@ -15,14 +47,25 @@ pub fn add_dns_servers(dns_servers: &[IpAddr], interface_name: &str) -> Result<(
         })?
     };
 
-    let adapter = Adapter::open(&wintun, interface_name).map_err(|e| {
-        crate::error::DnsError::PlatformError {
-            message: format!("failed to open adapter: {e}"),
-        }
+    // Fail early if the tunnel adapter cannot be found, rather than on the first netsh call
+    Adapter::open(&wintun, interface_name).map_err(|e| crate::error::DnsError::PlatformError {
+        message: format!("failed to open adapter: {e}"),
     })?;
-    adapter
-        .set_dns_servers(dns_servers)
-        .map_err(|_e| crate::error::DnsError::ConfigurationFailed)?;
+
+    for args in netsh_dns_args(dns_servers, interface_name) {
+        let output = run_command(NETSH_COMMAND, &args)
+            .map_err(|e| crate::error::DnsError::PlatformError {
+                message: format!("failed to execute command: {e}"),
+            })?
+            .wait_with_output()
+            .map_err(|e| crate::error::DnsError::PlatformError {
+                message: format!("failed to wait for command: {e}"),
+            })?;
+
+        if !output.status.success() {
+            return Err(crate::error::DnsError::ConfigurationFailed.into());
+        }
+    }
 
     Ok(())
 }
@@ -30,8 +73,90 @@ pub fn add_dns_servers(dns_servers: &[IpAddr], interface_name: &str) -> Result<(
 /// Deletes all DNS servers from the given interface.
 ///
 /// No-op on Windows.
-pub fn delete_dns_servers() -> Result<()> {
+pub fn delete_dns_servers(_split_domains: &[SplitDnsDomain], _interface_name: &str) -> Result<()> {
     // This is a no-op on Windows as the interface is deleted when the process exits
     // along with its routes and DNS servers
     Ok(())
 }
+
+/// Captures the resolver state replaced by the DNS configuration of the interface.
+///
+/// The DNS servers are only set on the WinTun adapter, so there is no other state to record.
+///
+/// ### Arguments
+/// - `split_domains` - the domains resolved through the tunnel (unused)
+/// - `interface_name` - the name of the interface the DNS servers are added to
+pub fn backup_dns_state(
+    _split_domains: &[SplitDnsDomain],
+    interface_name: &str,
+) -> Result<DnsBackup> {
+    Ok(DnsBackup::new(interface_name))
+}
+
+/// Restores resolver state recorded in a stale snapshot.
+///
+/// No-op on Windows, as the DNS servers were removed along with the adapter.
+pub fn restore_dns_state(_backup: &DnsBackup) -> Result<()> {
+    Ok(())
+}
+
+/// Installs Windows Firewall rules blocking DNS queries to servers other than `dns_servers`.
+///
+/// Rules left behind by an earlier run are replaced. The rules only take effect while the
+/// Windows Firewall is enabled.
+///
+/// ### Arguments
+/// - `dns_servers` - the DNS servers queries are allowed to
+/// - `dry_run` - whether to only log the firewall changes
+pub fn block_dns_leaks(dns_servers: &[IpAddr], dry_run: bool) -> Result<()> {
+    // Only succeeds if rules were left behind by an earlier run
+    let _ = unblock_dns_leaks(dry_run);
+
+    let name = format!("name={LEAK_PROTECTION_RULE}");
+    let remote_ports = format!(
+        "remoteport={}",
+        DNS_LEAK_PORTS.map(|port| port.to_string()).join(",")
+    );
+    let remote_addresses = format!("remoteip={}", windows_blocked_addresses(dns_servers));
+
+    for protocol in ["protocol=UDP", "protocol=TCP"] {
+        // netsh advfirewall firewall add rule name=<rule> dir=out action=block ...
+        FirewallCommand::new(
+            FirewallFeature::DnsLeakProtection,
+            NETSH_COMMAND,
+            &[
+                "advfirewall",
+                "firewall",
+                "add",
+                "rule",
+                name.as_str(),
+                "dir=out",
+                "action=block",
+                protocol,
+                remote_ports.as_str(),
+                remote_addresses.as_str(),
+            ],
+        )
+        .run(dry_run)?;
+    }
+
+    Ok(())
+}
+
+/// Removes the Windows Firewall rules installed by [`block_dns_leaks`].
+///
+/// ### Arguments
+/// - `dry_run` - whether to only log the firewall changes
+pub fn unblock_dns_leaks(dry_run: bool) -> Result<()> {
+    let name = format!("name={LEAK_PROTECTION_RULE}");
+
+    // netsh advfirewall firewall delete rule name=<rule>
+    FirewallCommand::new(
+        FirewallFeature::DnsLeakProtection,
+        NETSH_COMMAND,
+        &["advfirewall", "firewall", "delete", "rule", name.as_str()],
+    )
+    .run(dry_run)?;
+
+    Ok(())
+}
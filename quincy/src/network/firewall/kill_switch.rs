@@ -0,0 +1,884 @@
+//! Firewall rules blocking all traffic that bypasses the tunnel.
+//!
+//! While the tunnel's default route is installed, outbound traffic is rejected unless it goes
+//! through the tunnel interface, stays on the loopback interface or goes to an allowed network,
+//! i.e. the server endpoint and the networks excluded from the tunnel routes. DHCP and IPv6
+//! neighbor discovery stay allowed so that the physical link keeps working. The rules are
+//! installed with nftables or iptables (Linux), pf (macOS) or the Windows Firewall.
+//!
+//! The strict kill switch installs the same rules independently of the routes, before the
+//! tunnel interface exists, and is only removed by [`lift_kill_switch`]. A client that fails or
+//! crashes leaves it engaged, so that nothing leaks until the user decides otherwise. The server
+//! endpoints it allows are saved alongside, so that a restarted client can reconnect without
+//! resolving them through the blocked DNS.
+
+use crate::Result;
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+use crate::error::RouteError;
+use crate::network::IpFamily;
+#[cfg(target_os = "macos")]
+use crate::network::firewall::PFCTL_COMMAND;
+use crate::network::firewall::join;
+#[cfg(any(target_os = "linux", target_os = "macos", target_os = "windows"))]
+use crate::network::firewall::{FirewallCommand, FirewallFeature};
+#[cfg(target_os = "linux")]
+use crate::network::firewall::{IPTABLES_COMMANDS, NFT_COMMAND};
+#[cfg(target_os = "linux")]
+use crate::utils::command::command_exists;
+use ipnet::IpNet;
+use std::fs;
+use std::io;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use tracing::{error, info, warn};
+
+/// Name of the nftables table holding the rules on Linux.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+pub(crate) const LINUX_KILL_SWITCH_TABLE: &str = "quincy_kill_switch";
+/// Name of the iptables chain holding the rules on Linux.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+pub(crate) const LINUX_KILL_SWITCH_CHAIN: &str = "QUINCY_KILL_SWITCH";
+/// pf anchor holding the rules, evaluated by the default macOS ruleset.
+#[cfg(target_os = "macos")]
+const KILL_SWITCH_ANCHOR: &str = "com.apple/quincy-kill-switch";
+/// Prefix of the names of the Windows Firewall rules.
+#[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+pub(crate) const WINDOWS_KILL_SWITCH_RULE: &str = "QuincyKillSwitch";
+/// File name of the server endpoints pinned by the engaged strict kill switch.
+const PINNED_ENDPOINTS_FILE_NAME: &str = "kill-switch-endpoints.json";
+/// Registry key saving the default outbound actions of the Windows Firewall profiles while
+/// the kill switch is engaged.
+#[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+pub(crate) const WINDOWS_KILL_SWITCH_STATE_KEY: &str = r"HKLM:\SOFTWARE\Quincy\KillSwitch";
+#[cfg(target_os = "windows")]
+const POWERSHELL_COMMAND: &str = "powershell.exe";
+
+/// Reference token of pf being enabled for the kill switch, released when the rules are removed.
+#[cfg(target_os = "macos")]
+static PF_TOKEN: std::sync::Mutex<Option<String>> = std::sync::Mutex::new(None);
+
+/// How traffic bypassing the tunnel is handled while the tunnel's default route is installed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum KillSwitch {
+    /// Traffic is not restricted
+    #[default]
+    Disabled,
+    /// Firewall rules block all traffic outside of the tunnel
+    Enabled,
+    /// The firewall changes are only logged, without being made
+    DryRun,
+}
+
+/// Installed kill switch rules, removed when dropped.
+///
+/// Owned by the route configuration of the tunnel interface, so that the rules are removed
+/// before the interface is brought down.
+pub struct KillSwitchGuard {
+    dry_run: bool,
+    installed: bool,
+}
+
+impl KillSwitchGuard {
+    /// Installs rules blocking all outbound traffic outside of the tunnel.
+    ///
+    /// ### Arguments
+    /// - `tunnel_interface` - the name of the tunnel interface
+    /// - `allowed_networks` - the networks reached outside of the tunnel, e.g. the server endpoint
+    /// - `mode` - the kill switch mode
+    ///
+    /// ### Returns
+    /// - `Option<KillSwitchGuard>` - the installed rules, or `None` if the kill switch is disabled
+    ///
+    /// ### Errors
+    /// Returns `RouteError::PlatformError` if the firewall rules cannot be installed, in which
+    /// case partially installed rules are removed again.
+    pub fn install(
+        tunnel_interface: &str,
+        allowed_networks: &[IpNet],
+        mode: KillSwitch,
+    ) -> Result<Option<Self>> {
+        let dry_run = match mode {
+            KillSwitch::Disabled => return Ok(None),
+            KillSwitch::Enabled => false,
+            KillSwitch::DryRun => true,
+        };
+
+        if let Err(e) = enable_kill_switch(Some(tunnel_interface), allowed_networks, dry_run) {
+            if let Err(cleanup_error) = disable_kill_switch(dry_run) {
+                error!("Failed to roll back kill switch: {cleanup_error}");
+            }
+            return Err(e);
+        }
+        info!(
+            "Enabled kill switch for interface {tunnel_interface}, allowing networks {allowed_networks:?}"
+        );
+
+        Ok(Some(Self {
+            dry_run,
+            installed: true,
+        }))
+    }
+
+    /// Removes the installed rules.
+    ///
+    /// ### Errors
+    /// Returns `RouteError::PlatformError` if the firewall rules cannot be removed.
+    pub fn remove(mut self) -> Result<()> {
+        self.installed = false;
+        disable_kill_switch(self.dry_run)?;
+        info!("Disabled kill switch");
+
+        Ok(())
+    }
+}
+
+impl Drop for KillSwitchGuard {
+    fn drop(&mut self) {
+        if self.installed {
+            if let Err(e) = disable_kill_switch(self.dry_run) {
+                error!("Failed to remove kill switch: {e}");
+            }
+        }
+    }
+}
+
+/// Engages the strict kill switch, blocking all outbound traffic outside of the tunnel
+/// regardless of the routes.
+///
+/// Unlike [`KillSwitchGuard`], nothing removes the rules again but [`lift_kill_switch`], so a
+/// process that exits without lifting them leaves the traffic blocked. Engaging the kill switch
+/// again replaces the rules, e.g. to allow the tunnel interface once it is up.
+///
+/// ### Arguments
+/// - `tunnel_interface` - the name of the tunnel interface, if it is up yet
+/// - `allowed_networks` - the networks reached outside of the tunnel, e.g. the server endpoints
+/// - `dry_run` - whether to only log the firewall changes
+///
+/// ### Errors
+/// Returns `RouteError::PlatformError` if the firewall rules cannot be installed, in which
+/// case partially installed rules are removed again.
+pub fn engage_kill_switch(
+    tunnel_interface: Option<&str>,
+    allowed_networks: &[IpNet],
+    dry_run: bool,
+) -> Result<()> {
+    if let Err(e) = enable_kill_switch(tunnel_interface, allowed_networks, dry_run) {
+        if let Err(cleanup_error) = disable_kill_switch(dry_run) {
+            error!("Failed to roll back kill switch: {cleanup_error}");
+        }
+        return Err(e);
+    }
+    info!(
+        "Engaged strict kill switch for interface {}, allowing networks {allowed_networks:?}",
+        tunnel_interface.unwrap_or("(none)")
+    );
+
+    Ok(())
+}
+
+/// Lifts the kill switch, removing its rules.
+///
+/// Also removes rules left behind by a client that failed or crashed, for recovery tooling.
+///
+/// ### Arguments
+/// - `dry_run` - whether to only log the firewall changes
+///
+/// ### Errors
+/// Returns `RouteError::PlatformError` if the firewall rules cannot be removed.
+pub fn lift_kill_switch(dry_run: bool) -> Result<()> {
+    disable_kill_switch(dry_run)?;
+    if !dry_run {
+        let path = pinned_endpoints_path();
+        match fs::remove_file(&path) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => {
+                warn!("Failed to remove {}: {e}", path.display());
+            }
+            _ => {}
+        }
+    }
+    info!("Lifted kill switch");
+
+    Ok(())
+}
+
+/// Returns the path of the file saving the server endpoints pinned by the strict kill switch.
+///
+/// The file is kept in a runtime directory that is cleared on reboot along with the firewall
+/// rules, except on Windows, where the Windows Firewall rules persist as well.
+pub fn pinned_endpoints_path() -> PathBuf {
+    #[cfg(not(target_os = "windows"))]
+    let directory = PathBuf::from("/var/run/quincy");
+    #[cfg(target_os = "windows")]
+    let directory = std::env::var_os("ProgramData")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(r"C:\ProgramData"))
+        .join("Quincy");
+
+    directory.join(PINNED_ENDPOINTS_FILE_NAME)
+}
+
+/// Saves the server endpoints allowed by the engaged strict kill switch, removed again when
+/// the kill switch is lifted.
+///
+/// ### Arguments
+/// - `path` - the path of the file, see [`pinned_endpoints_path`]
+/// - `endpoints` - the connection strings of the endpoints and the addresses they resolved to
+///
+/// ### Errors
+/// Returns `QuincyError::Io` if the file cannot be written.
+pub fn save_pinned_endpoints(path: &Path, endpoints: &[(String, Vec<SocketAddr>)]) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, serde_json::to_vec(endpoints)?)?;
+
+    Ok(())
+}
+
+/// Loads the server endpoints saved by a strict kill switch that is still engaged.
+///
+/// ### Arguments
+/// - `path` - the path of the file, see [`pinned_endpoints_path`]
+///
+/// ### Returns
+/// - `Option<Vec<(String, Vec<SocketAddr>)>>` - the connection strings of the endpoints and
+///   their addresses, or `None` if no endpoints are saved
+pub fn load_pinned_endpoints(path: &Path) -> Option<Vec<(String, Vec<SocketAddr>)>> {
+    let content = match fs::read(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return None,
+        Err(e) => {
+            warn!("Failed to read {}: {e}", path.display());
+            return None;
+        }
+    };
+
+    match serde_json::from_slice::<Vec<(String, Vec<SocketAddr>)>>(&content) {
+        Ok(endpoints) if !endpoints.is_empty() => Some(endpoints),
+        Ok(_) => None,
+        Err(e) => {
+            warn!("Ignoring corrupt pinned endpoints {}: {e}", path.display());
+            None
+        }
+    }
+}
+
+/// Installs the kill switch rules with nftables when available, falling back to iptables.
+///
+/// Rules left behind by an earlier run are replaced.
+///
+/// ### Arguments
+/// - `tunnel_interface` - the name of the tunnel interface, if it is up yet
+/// - `allowed_networks` - the networks reached outside of the tunnel
+/// - `dry_run` - whether to only log the firewall changes
+#[cfg(target_os = "linux")]
+fn enable_kill_switch(
+    tunnel_interface: Option<&str>,
+    allowed_networks: &[IpNet],
+    dry_run: bool,
+) -> Result<()> {
+    if command_exists(NFT_COMMAND) {
+        // nft -f - <<< <ruleset>
+        FirewallCommand::new(FirewallFeature::KillSwitch, NFT_COMMAND, &["-f", "-"])
+            .with_stdin(nft_ruleset(tunnel_interface, allowed_networks))
+            .run(dry_run)?;
+        return Ok(());
+    }
+
+    for (program, family) in IPTABLES_COMMANDS {
+        for args in iptables_unblock_args() {
+            // Only succeeds if a chain was left behind by an earlier run
+            let _ = FirewallCommand::new(FirewallFeature::KillSwitch, program, &args).run(dry_run);
+        }
+        for args in iptables_block_args(family, tunnel_interface, allowed_networks) {
+            FirewallCommand::new(FirewallFeature::KillSwitch, program, &args).run(dry_run)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Removes the rules installed by [`enable_kill_switch`].
+///
+/// ### Arguments
+/// - `dry_run` - whether to only log the firewall changes
+#[cfg(target_os = "linux")]
+fn disable_kill_switch(dry_run: bool) -> Result<()> {
+    if command_exists(NFT_COMMAND) {
+        // nft -f - <<< <ruleset>
+        FirewallCommand::new(FirewallFeature::KillSwitch, NFT_COMMAND, &["-f", "-"])
+            .with_stdin(nft_delete_ruleset())
+            .run(dry_run)?;
+        return Ok(());
+    }
+
+    let mut result = Ok(());
+    for (program, _) in IPTABLES_COMMANDS {
+        for args in iptables_unblock_args() {
+            if let Err(e) =
+                FirewallCommand::new(FirewallFeature::KillSwitch, program, &args).run(dry_run)
+            {
+                if result.is_ok() {
+                    result = Err(e);
+                }
+            }
+        }
+    }
+
+    result
+}
+
+/// Installs the kill switch rules into their own pf anchor.
+///
+/// pf is enabled with a reference token so that it is only disabled again if Quincy enabled it.
+/// Replacing the rules keeps the token taken when they were first installed.
+///
+/// ### Arguments
+/// - `tunnel_interface` - the name of the tunnel interface, if it is up yet
+/// - `allowed_networks` - the networks reached outside of the tunnel
+/// - `dry_run` - whether to only log the firewall changes
+#[cfg(target_os = "macos")]
+fn enable_kill_switch(
+    tunnel_interface: Option<&str>,
+    allowed_networks: &[IpNet],
+    dry_run: bool,
+) -> Result<()> {
+    // pfctl -a <anchor> -f - <<< <rules>
+    FirewallCommand::new(
+        FirewallFeature::KillSwitch,
+        PFCTL_COMMAND,
+        &["-a", KILL_SWITCH_ANCHOR, "-f", "-"],
+    )
+    .with_stdin(pf_rules(tunnel_interface, allowed_networks))
+    .run(dry_run)?;
+
+    let mut pf_token = PF_TOKEN.lock().unwrap_or_else(|e| e.into_inner());
+    if pf_token.is_none() {
+        // pfctl -E prints "Token : <token>"
+        let output = FirewallCommand::new(FirewallFeature::KillSwitch, PFCTL_COMMAND, &["-E"])
+            .run(dry_run)?;
+        *pf_token = output
+            .lines()
+            .find_map(|line| line.strip_prefix("Token : "))
+            .map(|token| token.trim().to_string());
+    }
+
+    Ok(())
+}
+
+/// Removes the rules installed by [`enable_kill_switch`].
+///
+/// ### Arguments
+/// - `dry_run` - whether to only log the firewall changes
+#[cfg(target_os = "macos")]
+fn disable_kill_switch(dry_run: bool) -> Result<()> {
+    // pfctl -a <anchor> -F all
+    FirewallCommand::new(
+        FirewallFeature::KillSwitch,
+        PFCTL_COMMAND,
+        &["-a", KILL_SWITCH_ANCHOR, "-F", "all"],
+    )
+    .run(dry_run)?;
+
+    let token = PF_TOKEN.lock().unwrap_or_else(|e| e.into_inner()).take();
+    if let Some(token) = token {
+        // pfctl -X <token>
+        FirewallCommand::new(
+            FirewallFeature::KillSwitch,
+            PFCTL_COMMAND,
+            &["-X", token.as_str()],
+        )
+        .run(dry_run)?;
+    }
+
+    Ok(())
+}
+
+/// Blocks outbound traffic by default in all Windows Firewall profiles and allows the tunnel
+/// interface and the allowed networks.
+///
+/// Loopback traffic is not filtered by the Windows Firewall. The default outbound action of
+/// each profile is saved in the registry when the kill switch is first enabled, and restored
+/// when it is disabled, also by a later process if the client crashed.
+///
+/// ### Arguments
+/// - `tunnel_interface` - the name of the tunnel interface, if it is up yet
+/// - `allowed_networks` - the networks reached outside of the tunnel
+/// - `dry_run` - whether to only log the firewall changes
+#[cfg(target_os = "windows")]
+fn enable_kill_switch(
+    tunnel_interface: Option<&str>,
+    allowed_networks: &[IpNet],
+    dry_run: bool,
+) -> Result<()> {
+    let script = windows_block_script(tunnel_interface, allowed_networks);
+
+    FirewallCommand::new(
+        FirewallFeature::KillSwitch,
+        POWERSHELL_COMMAND,
+        &["-NoProfile", "-NonInteractive", "-Command", script.as_str()],
+    )
+    .run(dry_run)?;
+
+    Ok(())
+}
+
+/// Removes the rules installed by [`enable_kill_switch`].
+///
+/// ### Arguments
+/// - `dry_run` - whether to only log the firewall changes
+#[cfg(target_os = "windows")]
+fn disable_kill_switch(dry_run: bool) -> Result<()> {
+    let script = windows_unblock_script();
+
+    FirewallCommand::new(
+        FirewallFeature::KillSwitch,
+        POWERSHELL_COMMAND,
+        &["-NoProfile", "-NonInteractive", "-Command", script.as_str()],
+    )
+    .run(dry_run)?;
+
+    Ok(())
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+fn enable_kill_switch(
+    _tunnel_interface: Option<&str>,
+    _allowed_networks: &[IpNet],
+    _dry_run: bool,
+) -> Result<()> {
+    Err(RouteError::PlatformError {
+        message: "the kill switch is not supported on this platform".to_string(),
+    }
+    .into())
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+fn disable_kill_switch(_dry_run: bool) -> Result<()> {
+    Ok(())
+}
+
+/// Generates the nftables ruleset of the kill switch.
+///
+/// The ruleset replaces a table left behind by an earlier run.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+pub(crate) fn nft_ruleset(tunnel_interface: Option<&str>, allowed_networks: &[IpNet]) -> String {
+    let table = LINUX_KILL_SWITCH_TABLE;
+    let (ipv4_networks, ipv6_networks) = split_families(allowed_networks);
+
+    let mut rules = vec![
+        "type filter hook output priority 0; policy accept;".to_string(),
+        "oifname \"lo\" accept".to_string(),
+    ];
+    if let Some(tunnel_interface) = tunnel_interface {
+        rules.push(format!("oifname \"{tunnel_interface}\" accept"));
+    }
+    rules.extend([
+        "udp sport 68 udp dport 67 accept".to_string(),
+        "udp sport 546 udp dport 547 accept".to_string(),
+        "icmpv6 type { nd-router-solicit, nd-neighbor-solicit, nd-neighbor-advert } accept"
+            .to_string(),
+    ]);
+    for (family, networks) in [
+        ("ip", join(&ipv4_networks, ", ")),
+        ("ip6", join(&ipv6_networks, ", ")),
+    ] {
+        if !networks.is_empty() {
+            rules.push(format!("{family} daddr {{ {networks} }} accept"));
+        }
+    }
+    rules.push("reject".to_string());
+
+    let mut ruleset = format!(
+        "table inet {table}\ndelete table inet {table}\ntable inet {table} {{\n\tchain output {{\n"
+    );
+    for rule in rules {
+        ruleset.push_str(&format!("\t\t{rule}\n"));
+    }
+    ruleset.push_str("\t}\n}\n");
+
+    ruleset
+}
+
+/// Generates the nftables ruleset removing the kill switch table.
+///
+/// The table is declared before it is deleted, so that removing a table that does not exist
+/// succeeds as well.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+pub(crate) fn nft_delete_ruleset() -> String {
+    let table = LINUX_KILL_SWITCH_TABLE;
+
+    format!("table inet {table}\ndelete table inet {table}\n")
+}
+
+/// Generates the arguments of the `iptables` or `ip6tables` commands installing the kill switch.
+///
+/// ### Arguments
+/// - `family` - the IP family handled by the command
+/// - `tunnel_interface` - the name of the tunnel interface, if it is up yet
+/// - `allowed_networks` - the networks reached outside of the tunnel, of any family
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+pub(crate) fn iptables_block_args(
+    family: IpFamily,
+    tunnel_interface: Option<&str>,
+    allowed_networks: &[IpNet],
+) -> Vec<Vec<String>> {
+    let chain = LINUX_KILL_SWITCH_CHAIN;
+    let args = |args: &str| args.split(' ').map(str::to_string).collect::<Vec<_>>();
+
+    let mut commands = vec![
+        args(&format!("-N {chain}")),
+        args(&format!("-A {chain} -o lo -j RETURN")),
+    ];
+    if let Some(tunnel_interface) = tunnel_interface {
+        commands.push(args(&format!("-A {chain} -o {tunnel_interface} -j RETURN")));
+    }
+    match family {
+        IpFamily::V4 => commands.push(args(&format!(
+            "-A {chain} -p udp --sport 68 --dport 67 -j RETURN"
+        ))),
+        IpFamily::V6 => {
+            commands.push(args(&format!(
+                "-A {chain} -p udp --sport 546 --dport 547 -j RETURN"
+            )));
+            for icmpv6_type in [
+                "router-solicitation",
+                "neighbour-solicitation",
+                "neighbour-advertisement",
+            ] {
+                commands.push(args(&format!(
+                    "-A {chain} -p ipv6-icmp --icmpv6-type {icmpv6_type} -j RETURN"
+                )));
+            }
+        }
+    }
+    for network in allowed_networks
+        .iter()
+        .filter(|network| IpFamily::of(&network.addr()) == family)
+    {
+        commands.push(args(&format!("-A {chain} -d {network} -j RETURN")));
+    }
+    commands.push(args(&format!("-A {chain} -j REJECT")));
+    commands.push(args(&format!("-I OUTPUT -j {chain}")));
+
+    commands
+}
+
+/// Generates the arguments of the `iptables` or `ip6tables` commands removing the kill switch.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+pub(crate) fn iptables_unblock_args() -> Vec<Vec<String>> {
+    let chain = LINUX_KILL_SWITCH_CHAIN;
+
+    [
+        vec!["-D", "OUTPUT", "-j", chain],
+        vec!["-F", chain],
+        vec!["-X", chain],
+    ]
+    .into_iter()
+    .map(|args| args.into_iter().map(str::to_string).collect())
+    .collect()
+}
+
+/// Generates the pf rules of the kill switch, loaded into their own anchor.
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+pub(crate) fn pf_rules(tunnel_interface: Option<&str>, allowed_networks: &[IpNet]) -> String {
+    let mut rules = "pass out quick on lo0 all\n".to_string();
+    if let Some(tunnel_interface) = tunnel_interface {
+        rules.push_str(&format!("pass out quick on {tunnel_interface} all\n"));
+    }
+    rules.push_str(
+        "pass out quick inet proto udp from any port 68 to any port 67\n\
+         pass out quick inet6 proto udp from any port 546 to any port 547\n\
+         pass out quick inet6 proto icmp6 all icmp6-type { routersol, neighbrsol, neighbradv }\n",
+    );
+    if !allowed_networks.is_empty() {
+        rules.push_str(&format!(
+            "pass out quick from any to {{ {} }}\n",
+            join(allowed_networks, ", ")
+        ));
+    }
+    rules.push_str("block return out quick all\n");
+
+    rules
+}
+
+/// Generates the PowerShell script installing the Windows Firewall rules of the kill switch.
+///
+/// Windows Firewall block rules take precedence over allow rules, so traffic is blocked through
+/// the default outbound action instead, with allow rules for the tunnel interface, DHCP, IPv6
+/// neighbor discovery and the allowed networks. Rules left behind by an earlier run are replaced,
+/// keeping the default outbound actions it saved.
+#[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+pub(crate) fn windows_block_script(
+    tunnel_interface: Option<&str>,
+    allowed_networks: &[IpNet],
+) -> String {
+    let rule = WINDOWS_KILL_SWITCH_RULE;
+    let state = WINDOWS_KILL_SWITCH_STATE_KEY;
+
+    let mut script = format!(
+        "$ErrorActionPreference = 'Stop'; \
+         if (-not (Test-Path '{state}')) {{ \
+         New-Item -Path '{state}' -Force | Out-Null; \
+         Get-NetFirewallProfile | ForEach-Object {{ New-ItemProperty -Path '{state}' -Name $_.Name -Value ([string]$_.DefaultOutboundAction) | Out-Null }} }}; \
+         Remove-NetFirewallRule -Name '{rule}*' -ErrorAction SilentlyContinue; "
+    );
+    if let Some(tunnel_interface) = tunnel_interface {
+        script.push_str(&format!(
+            "New-NetFirewallRule -Name '{rule}Tunnel' -DisplayName '{rule}Tunnel' -Direction Outbound -Action Allow -InterfaceAlias '{tunnel_interface}' | Out-Null; "
+        ));
+    }
+    script.push_str(&format!(
+        "New-NetFirewallRule -Name '{rule}Dhcp' -DisplayName '{rule}Dhcp' -Direction Outbound -Action Allow -Protocol UDP -LocalPort 68 -RemotePort 67 | Out-Null; \
+         New-NetFirewallRule -Name '{rule}Dhcp6' -DisplayName '{rule}Dhcp6' -Direction Outbound -Action Allow -Protocol UDP -LocalPort 546 -RemotePort 547 | Out-Null; \
+         New-NetFirewallRule -Name '{rule}NeighborDiscovery' -DisplayName '{rule}NeighborDiscovery' -Direction Outbound -Action Allow -Protocol ICMPv6 -IcmpType 133,135,136 | Out-Null; "
+    ));
+    if !allowed_networks.is_empty() {
+        let addresses = allowed_networks
+            .iter()
+            .map(|network| format!("'{network}'"))
+            .collect::<Vec<_>>()
+            .join(",");
+        script.push_str(&format!(
+            "New-NetFirewallRule -Name '{rule}Allowed' -DisplayName '{rule}Allowed' -Direction Outbound -Action Allow -RemoteAddress {addresses} | Out-Null; "
+        ));
+    }
+    script.push_str("Set-NetFirewallProfile -All -DefaultOutboundAction Block");
+
+    script
+}
+
+/// Generates the PowerShell script removing the Windows Firewall rules of the kill switch.
+///
+/// The default outbound actions saved when the kill switch was engaged are restored. Without
+/// saved actions, e.g. if the kill switch is not engaged, the profiles are left unchanged.
+#[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+pub(crate) fn windows_unblock_script() -> String {
+    let rule = WINDOWS_KILL_SWITCH_RULE;
+    let state = WINDOWS_KILL_SWITCH_STATE_KEY;
+
+    format!(
+        "$ErrorActionPreference = 'Stop'; \
+         if (Test-Path '{state}') {{ \
+         $saved = Get-ItemProperty -Path '{state}'; \
+         Get-NetFirewallProfile | ForEach-Object {{ $action = $saved.($_.Name); if ($action) {{ Set-NetFirewallProfile -Name $_.Name -DefaultOutboundAction $action }} }}; \
+         Remove-Item -Path '{state}' -Recurse }}; \
+         Remove-NetFirewallRule -Name '{rule}*' -ErrorAction SilentlyContinue"
+    )
+}
+
+/// Splits networks into IPv4 and IPv6 networks.
+fn split_families(networks: &[IpNet]) -> (Vec<IpNet>, Vec<IpNet>) {
+    networks
+        .iter()
+        .partition(|network| matches!(network, IpNet::V4(_)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn networks(networks: &[&str]) -> Vec<IpNet> {
+        networks
+            .iter()
+            .map(|network| network.parse().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn nft_ruleset_allows_only_tunnel_and_allowed_networks() {
+        let ruleset = nft_ruleset(
+            Some("tun0"),
+            &networks(&["203.0.113.1/32", "192.168.0.0/16", "2001:db8::1/128"]),
+        );
+
+        assert!(
+            ruleset.starts_with(
+                "table inet quincy_kill_switch\ndelete table inet quincy_kill_switch\n"
+            )
+        );
+        assert!(ruleset.contains("oifname \"lo\" accept"));
+        assert!(ruleset.contains("oifname \"tun0\" accept"));
+        assert!(ruleset.contains("ip daddr { 203.0.113.1/32, 192.168.0.0/16 } accept"));
+        assert!(ruleset.contains("ip6 daddr { 2001:db8::1/128 } accept"));
+        assert_eq!(
+            ruleset.trim_end().lines().rev().nth(2).unwrap().trim(),
+            "reject"
+        );
+
+        let ipv4_only = nft_ruleset(Some("tun0"), &networks(&["203.0.113.1/32"]));
+        assert!(!ipv4_only.contains("ip6 daddr"));
+    }
+
+    #[test]
+    fn iptables_rules_return_before_rejecting() {
+        let allowed = networks(&["203.0.113.1/32", "2001:db8::1/128"]);
+        let commands = iptables_block_args(IpFamily::V4, Some("tun0"), &allowed)
+            .into_iter()
+            .map(|args| args.join(" "))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            commands,
+            vec![
+                "-N QUINCY_KILL_SWITCH",
+                "-A QUINCY_KILL_SWITCH -o lo -j RETURN",
+                "-A QUINCY_KILL_SWITCH -o tun0 -j RETURN",
+                "-A QUINCY_KILL_SWITCH -p udp --sport 68 --dport 67 -j RETURN",
+                "-A QUINCY_KILL_SWITCH -d 203.0.113.1/32 -j RETURN",
+                "-A QUINCY_KILL_SWITCH -j REJECT",
+                "-I OUTPUT -j QUINCY_KILL_SWITCH",
+            ]
+        );
+
+        let ipv6_commands = iptables_block_args(IpFamily::V6, Some("tun0"), &allowed)
+            .into_iter()
+            .map(|args| args.join(" "))
+            .collect::<Vec<_>>();
+        assert!(
+            ipv6_commands
+                .contains(&"-A QUINCY_KILL_SWITCH -d 2001:db8::1/128 -j RETURN".to_string())
+        );
+        assert!(
+            !ipv6_commands
+                .iter()
+                .any(|command| command.contains("203.0.113.1"))
+        );
+        assert_eq!(
+            iptables_unblock_args()[0],
+            ["-D", "OUTPUT", "-j", "QUINCY_KILL_SWITCH"]
+        );
+    }
+
+    #[test]
+    fn pf_rules_pass_tunnel_and_allowed_networks() {
+        let rules = pf_rules(
+            Some("utun5"),
+            &networks(&["203.0.113.1/32", "192.168.0.0/16"]),
+        );
+        let lines = rules.lines().collect::<Vec<_>>();
+
+        assert_eq!(lines[0], "pass out quick on lo0 all");
+        assert_eq!(lines[1], "pass out quick on utun5 all");
+        assert!(lines.contains(&"pass out quick from any to { 203.0.113.1/32, 192.168.0.0/16 }"));
+        assert_eq!(lines.last(), Some(&"block return out quick all"));
+    }
+
+    #[test]
+    fn windows_scripts_block_outbound_traffic_by_default() {
+        let script = windows_block_script(Some("quincy"), &networks(&["203.0.113.1/32"]));
+
+        assert!(script.contains("-InterfaceAlias 'quincy'"));
+        assert!(script.contains("-RemoteAddress '203.0.113.1/32'"));
+        assert!(script.contains("-Protocol UDP -LocalPort 68 -RemotePort 67"));
+        assert!(script.ends_with("Set-NetFirewallProfile -All -DefaultOutboundAction Block"));
+        assert!(!windows_block_script(Some("quincy"), &[]).contains("-RemoteAddress"));
+    }
+
+    #[test]
+    fn windows_scripts_restore_saved_outbound_actions() {
+        let block_script = windows_block_script(Some("quincy"), &[]);
+        let unblock_script = windows_unblock_script();
+
+        // The actions are saved before blocking, unless saved by an earlier run
+        let save = block_script
+            .find("New-ItemProperty")
+            .expect("block script saves the outbound actions");
+        assert!(save < block_script.find("-DefaultOutboundAction Block").unwrap());
+        assert!(block_script.contains(&format!(
+            "if (-not (Test-Path '{WINDOWS_KILL_SWITCH_STATE_KEY}'))"
+        )));
+
+        assert!(unblock_script.contains("-DefaultOutboundAction $action"));
+        assert!(unblock_script.contains(&format!(
+            "Remove-Item -Path '{WINDOWS_KILL_SWITCH_STATE_KEY}'"
+        )));
+        assert!(!unblock_script.contains("NotConfigured"));
+    }
+
+    #[test]
+    fn nft_delete_ruleset_succeeds_without_table() {
+        assert_eq!(
+            nft_delete_ruleset(),
+            "table inet quincy_kill_switch\ndelete table inet quincy_kill_switch\n"
+        );
+    }
+
+    #[test]
+    fn rules_without_tunnel_interface_block_everything_but_allowed_networks() {
+        let allowed = networks(&["203.0.113.1/32"]);
+
+        let ruleset = nft_ruleset(None, &allowed);
+        assert!(ruleset.contains("oifname \"lo\" accept"));
+        assert_eq!(ruleset.matches("oifname").count(), 1);
+        assert!(ruleset.contains("ip daddr { 203.0.113.1/32 } accept"));
+
+        let commands = iptables_block_args(IpFamily::V4, None, &allowed);
+        assert!(
+            !commands
+                .iter()
+                .any(|args| args.join(" ").contains("-o tun"))
+        );
+        assert_eq!(commands.len(), 6);
+
+        let rules = pf_rules(None, &allowed);
+        assert_eq!(
+            rules.lines().filter(|line| line.contains(" on ")).count(),
+            1
+        );
+        assert!(!windows_block_script(None, &allowed).contains("-InterfaceAlias"));
+    }
+
+    #[test]
+    fn dry_run_installs_and_removes_without_changes() {
+        let guard =
+            KillSwitchGuard::install("tun0", &networks(&["203.0.113.1/32"]), KillSwitch::DryRun);
+
+        #[cfg(any(target_os = "linux", target_os = "macos", target_os = "windows"))]
+        guard.unwrap().unwrap().remove().unwrap();
+        #[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+        assert!(guard.is_err());
+
+        assert!(
+            KillSwitchGuard::install("tun0", &[], KillSwitch::Disabled)
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn pinned_endpoints_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("state").join(PINNED_ENDPOINTS_FILE_NAME);
+        let endpoints = vec![(
+            "vpn.example.com:55555".to_string(),
+            vec![
+                "203.0.113.1:55555".parse().unwrap(),
+                "[2001:db8::1]:55555".parse().unwrap(),
+            ],
+        )];
+
+        assert_eq!(load_pinned_endpoints(&path), None);
+
+        save_pinned_endpoints(&path, &endpoints).unwrap();
+        assert_eq!(load_pinned_endpoints(&path), Some(endpoints));
+
+        fs::write(&path, "not json").unwrap();
+        assert_eq!(load_pinned_endpoints(&path), None);
+    }
+
+    #[test]
+    fn dry_run_engages_and_lifts_without_changes() {
+        let result = engage_kill_switch(None, &networks(&["203.0.113.1/32"]), true);
+
+        #[cfg(any(target_os = "linux", target_os = "macos", target_os = "windows"))]
+        {
+            result.unwrap();
+            engage_kill_switch(Some("tun0"), &networks(&["203.0.113.1/32"]), true).unwrap();
+        }
+        #[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+        assert!(result.is_err());
+
+        lift_kill_switch(true).unwrap();
+    }
+}
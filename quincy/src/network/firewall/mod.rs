@@ -0,0 +1,194 @@
+//! Firewall commands shared by the features that install packet filter rules.
+//!
+//! DNS leak protection and the kill switch both install their rules with nftables or iptables
+//! (Linux), pf (macOS) or the Windows Firewall, and support a dry-run mode in which the
+//! commands are only logged.
+
+pub mod kill_switch;
+
+pub use kill_switch::{
+    KillSwitch, KillSwitchGuard, engage_kill_switch, lift_kill_switch, load_pinned_endpoints,
+    pinned_endpoints_path, save_pinned_endpoints,
+};
+
+use crate::error::{DnsError, RouteError};
+#[cfg(target_os = "linux")]
+use crate::network::IpFamily;
+use crate::utils::command::run_command;
+use crate::{QuincyError, Result};
+use std::fmt;
+use std::io::Write;
+use tracing::info;
+
+/// Command name for the nftables utility.
+#[cfg(target_os = "linux")]
+pub(crate) const NFT_COMMAND: &str = "nft";
+/// Command names of the iptables utilities of each IP family.
+#[cfg(target_os = "linux")]
+pub(crate) const IPTABLES_COMMANDS: [(&str, IpFamily); 2] =
+    [("iptables", IpFamily::V4), ("ip6tables", IpFamily::V6)];
+/// Command name for the pf control utility.
+#[cfg(target_os = "macos")]
+pub(crate) const PFCTL_COMMAND: &str = "pfctl";
+
+/// The feature a firewall command belongs to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum FirewallFeature {
+    /// DNS leak protection, failing with `DnsError::PlatformError`
+    DnsLeakProtection,
+    /// The kill switch, failing with `RouteError::PlatformError`
+    KillSwitch,
+}
+
+impl FirewallFeature {
+    /// Returns the platform error of the feature with the given message.
+    fn error(self, message: String) -> QuincyError {
+        match self {
+            FirewallFeature::DnsLeakProtection => DnsError::PlatformError { message }.into(),
+            FirewallFeature::KillSwitch => RouteError::PlatformError { message }.into(),
+        }
+    }
+}
+
+impl fmt::Display for FirewallFeature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FirewallFeature::DnsLeakProtection => write!(f, "DNS leak protection"),
+            FirewallFeature::KillSwitch => write!(f, "Kill switch"),
+        }
+    }
+}
+
+/// A firewall command installing or removing the rules of a feature.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct FirewallCommand {
+    feature: FirewallFeature,
+    program: &'static str,
+    args: Vec<String>,
+    stdin: Option<String>,
+}
+
+impl FirewallCommand {
+    pub(crate) fn new<S: ToString>(
+        feature: FirewallFeature,
+        program: &'static str,
+        args: &[S],
+    ) -> Self {
+        Self {
+            feature,
+            program,
+            args: args.iter().map(ToString::to_string).collect(),
+            stdin: None,
+        }
+    }
+
+    /// Sets the input written to the command, e.g. a ruleset.
+    pub(crate) fn with_stdin(mut self, stdin: String) -> Self {
+        self.stdin = Some(stdin);
+        self
+    }
+
+    /// Runs the command, or only logs it in dry-run mode.
+    ///
+    /// ### Returns
+    /// - `String` - the standard output and error of the command, empty in dry-run mode
+    ///
+    /// ### Errors
+    /// Returns the platform error of the command's feature if the command cannot be run or fails.
+    pub(crate) fn run(&self, dry_run: bool) -> Result<String> {
+        if dry_run {
+            info!("{} dry run: {self}", self.feature);
+            return Ok(String::new());
+        }
+
+        let mut process = run_command(self.program, &self.args).map_err(|e| {
+            self.feature
+                .error(format!("failed to execute command: {e}"))
+        })?;
+
+        if let Some(stdin) = &self.stdin {
+            process
+                .stdin
+                .take()
+                .ok_or_else(|| self.feature.error("failed to open stdin".to_string()))?
+                .write_all(stdin.as_bytes())
+                .map_err(|e| self.feature.error(format!("failed to write to stdin: {e}")))?;
+        }
+
+        let output = process.wait_with_output().map_err(|e| {
+            self.feature
+                .error(format!("failed to wait for command: {e}"))
+        })?;
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        if !output.status.success() {
+            return Err(self.feature.error(format!(
+                "'{}' failed: {}",
+                self.program,
+                stderr.trim()
+            )));
+        }
+
+        Ok(format!("{stdout}{stderr}"))
+    }
+}
+
+impl fmt::Display for FirewallCommand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.program, self.args.join(" "))?;
+        if let Some(stdin) = &self.stdin {
+            write!(f, " <<EOF\n{stdin}EOF")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Joins the string representations of `items` with `separator`.
+pub(crate) fn join<T: ToString>(items: &[T], separator: &str) -> String {
+    items
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(separator)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dry_run_commands_are_not_executed() {
+        let command = FirewallCommand::new(
+            FirewallFeature::DnsLeakProtection,
+            "quincy-nonexistent-firewall",
+            &["-f", "-"],
+        )
+        .with_stdin("rules\n".to_string());
+
+        assert_eq!(command.run(true).unwrap(), "");
+        assert!(matches!(
+            command.run(false),
+            Err(QuincyError::Dns(DnsError::PlatformError { .. }))
+        ));
+        assert_eq!(
+            command.to_string(),
+            "quincy-nonexistent-firewall -f - <<EOF\nrules\nEOF"
+        );
+    }
+
+    #[test]
+    fn failures_are_reported_as_errors_of_the_feature() {
+        let command = FirewallCommand::new(
+            FirewallFeature::KillSwitch,
+            "quincy-nonexistent-firewall",
+            &["-F"],
+        );
+
+        assert!(matches!(
+            command.run(false),
+            Err(QuincyError::Route(RouteError::PlatformError { .. }))
+        ));
+    }
+}
@@ -0,0 +1,289 @@
+//! Default gateway detection.
+//!
+//! Looks up the system's current default gateway so that traffic which must
+//! bypass the tunnel (the VPN server itself, excluded networks) can be routed
+//! around it.
+
+use std::net::IpAddr;
+use std::process::Output;
+#[cfg(any(target_os = "linux", target_os = "windows"))]
+use std::str::FromStr;
+
+use crate::Result;
+use crate::error::RouteError;
+use crate::network::IpFamily;
+use crate::utils::command::run_command;
+
+/// Command name for the Linux `ip` utility.
+#[cfg(target_os = "linux")]
+const IP_COMMAND: &str = "ip";
+
+/// Command name for the BSD `route` utility.
+#[cfg(any(target_os = "macos", target_os = "freebsd"))]
+const ROUTE_COMMAND: &str = "route";
+
+/// Command name for Windows PowerShell.
+#[cfg(target_os = "windows")]
+const POWERSHELL_COMMAND: &str = "powershell.exe";
+
+/// Returns the default route prefix for the given family.
+fn default_prefix(family: IpFamily) -> &'static str {
+    match family {
+        IpFamily::V4 => "0.0.0.0/0",
+        IpFamily::V6 => "::/0",
+    }
+}
+
+/// Returns the gateway address of the system's current default route for the given family.
+///
+/// Uses `ip route show default` on Linux, `route -n get default` on macOS/FreeBSD
+/// and `Get-NetRoute` (backed by `GetBestRoute2`) on Windows.
+///
+/// ### Arguments
+/// - `family` - the address family of the default route to look up
+///
+/// ### Errors
+/// Returns `RouteError::NotFound` if there is no default route with a gateway
+/// for the given family.
+pub fn default_gateway(family: IpFamily) -> Result<IpAddr> {
+    let output = run_default_route_command(family)?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    #[cfg(target_os = "linux")]
+    {
+        parse_linux_default_route(&stdout, family)
+    }
+    #[cfg(any(target_os = "macos", target_os = "freebsd"))]
+    {
+        parse_bsd_default_route(&stdout, family)
+    }
+    #[cfg(target_os = "windows")]
+    {
+        parse_windows_default_route(&stdout, family)
+    }
+}
+
+fn run_default_route_command(family: IpFamily) -> Result<Output> {
+    #[cfg(target_os = "linux")]
+    let (program, args): (&str, Vec<String>) = match family {
+        IpFamily::V4 => (
+            IP_COMMAND,
+            vec!["-4".into(), "route".into(), "show".into(), "default".into()],
+        ),
+        IpFamily::V6 => (
+            IP_COMMAND,
+            vec!["-6".into(), "route".into(), "show".into(), "default".into()],
+        ),
+    };
+
+    #[cfg(any(target_os = "macos", target_os = "freebsd"))]
+    let (program, args): (&str, Vec<String>) = match family {
+        IpFamily::V4 => (
+            ROUTE_COMMAND,
+            vec!["-n".into(), "get".into(), "default".into()],
+        ),
+        IpFamily::V6 => (
+            ROUTE_COMMAND,
+            vec!["-n".into(), "get".into(), "-inet6".into(), "default".into()],
+        ),
+    };
+
+    #[cfg(target_os = "windows")]
+    let (program, args): (&str, Vec<String>) = (
+        POWERSHELL_COMMAND,
+        vec![
+            "-NoProfile".into(),
+            "-NonInteractive".into(),
+            "-Command".into(),
+            format!(
+                "Get-NetRoute -DestinationPrefix '{}' -ErrorAction SilentlyContinue | \
+                 Sort-Object -Property RouteMetric | Select-Object -ExpandProperty NextHop",
+                default_prefix(family)
+            ),
+        ],
+    );
+
+    let output = run_command(program, &args)
+        .map_err(|e| RouteError::PlatformError {
+            message: format!("failed to execute default route command: {e}"),
+        })?
+        .wait_with_output()
+        .map_err(|e| RouteError::PlatformError {
+            message: format!("failed to wait for default route command: {e}"),
+        })?;
+
+    if !output.status.success() {
+        return Err(not_found(family).into());
+    }
+
+    Ok(output)
+}
+
+/// Parses the output of `ip route show default` on Linux.
+///
+/// Example output:
+/// ```text
+/// default via 192.168.1.1 dev eth0 proto dhcp src 192.168.1.100 metric 100
+/// default via 10.0.0.1 dev wlan0 proto dhcp metric 600
+/// ```
+///
+/// The kernel lists routes in preference order, so the first entry with a
+/// `via` gateway of the requested family is returned. Device-only default
+/// routes (e.g. `default dev ppp0`) have no gateway and are skipped.
+#[cfg(target_os = "linux")]
+fn parse_linux_default_route(output: &str, family: IpFamily) -> Result<IpAddr> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let tokens: Vec<&str> = line.split_whitespace().collect();
+            let raw = tokens
+                .windows(2)
+                .find(|pair| pair[0] == "via")
+                .map(|pair| pair[1])?;
+            let without_scope = raw.split('%').next().unwrap_or(raw);
+
+            IpAddr::from_str(without_scope).ok()
+        })
+        .find(|gateway| IpFamily::of(gateway) == family)
+        .ok_or_else(|| not_found(family).into())
+}
+
+/// Parses the output of `route -n get default` on macOS and FreeBSD.
+///
+/// Example output:
+/// ```text
+///    route to: default
+/// destination: default
+///        mask: default
+///     gateway: 192.168.1.1
+///   interface: en0
+/// ```
+#[cfg(any(target_os = "macos", target_os = "freebsd"))]
+fn parse_bsd_default_route(output: &str, family: IpFamily) -> Result<IpAddr> {
+    output
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix("gateway:"))
+        .filter_map(|value| {
+            let raw = value.trim();
+            // Strip scope-ids such as "%en0" from link-local IPv6 gateways.
+            let without_scope = raw.split('%').next().unwrap_or(raw);
+
+            without_scope.parse::<IpAddr>().ok()
+        })
+        .find(|gateway| !gateway.is_unspecified() && IpFamily::of(gateway) == family)
+        .ok_or_else(|| not_found(family).into())
+}
+
+/// Parses the output of `Get-NetRoute … | Select-Object -ExpandProperty NextHop`.
+///
+/// Each line holds one next-hop, ordered by route metric. Unspecified
+/// next-hops (`0.0.0.0` / `::`) belong to on-link default routes, typically
+/// other tunnel adapters, and are skipped.
+#[cfg(target_os = "windows")]
+fn parse_windows_default_route(output: &str, family: IpFamily) -> Result<IpAddr> {
+    output
+        .lines()
+        .filter_map(|line| IpAddr::from_str(line.trim()).ok())
+        .find(|gateway| !gateway.is_unspecified() && IpFamily::of(gateway) == family)
+        .ok_or_else(|| not_found(family).into())
+}
+
+fn not_found(family: IpFamily) -> RouteError {
+    RouteError::NotFound {
+        destination: default_prefix(family).to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::QuincyError;
+    use std::net::{Ipv4Addr, Ipv6Addr};
+
+    #[cfg(target_os = "linux")]
+    mod linux_parser {
+        use super::*;
+
+        #[test]
+        fn ipv4_default_route() {
+            let output =
+                "default via 192.168.1.1 dev eth0 proto dhcp src 192.168.1.100 metric 100\n";
+            let gateway = parse_linux_default_route(output, IpFamily::V4).unwrap();
+            assert_eq!(gateway, IpAddr::V4(Ipv4Addr::new(192, 168, 1, 1)));
+        }
+
+        #[test]
+        fn multiple_default_routes_picks_first() {
+            let output = "\
+default via 192.168.1.1 dev eth0 proto dhcp metric 100
+default via 10.0.0.1 dev wlan0 proto dhcp metric 600
+";
+            let gateway = parse_linux_default_route(output, IpFamily::V4).unwrap();
+            assert_eq!(gateway, IpAddr::V4(Ipv4Addr::new(192, 168, 1, 1)));
+        }
+
+        #[test]
+        fn device_only_route_skipped() {
+            let output = "\
+default dev ppp0 scope link
+default via 10.0.0.1 dev wlan0 proto dhcp metric 600
+";
+            let gateway = parse_linux_default_route(output, IpFamily::V4).unwrap();
+            assert_eq!(gateway, IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)));
+        }
+
+        #[test]
+        fn ipv6_link_local_gateway() {
+            let output =
+                "default via fe80::1 dev eth0 proto ra metric 100 expires 1798sec pref medium\n";
+            let gateway = parse_linux_default_route(output, IpFamily::V6).unwrap();
+            assert_eq!(
+                gateway,
+                IpAddr::V6(Ipv6Addr::new(0xfe80, 0, 0, 0, 0, 0, 0, 1))
+            );
+        }
+
+        #[test]
+        fn empty_output_returns_not_found() {
+            let result = parse_linux_default_route("", IpFamily::V4);
+            assert!(matches!(
+                result,
+                Err(QuincyError::Route(RouteError::NotFound { .. }))
+            ));
+        }
+
+        #[test]
+        fn family_mismatch_returns_not_found() {
+            let output = "default via 192.168.1.1 dev eth0 proto dhcp metric 100\n";
+            let result = parse_linux_default_route(output, IpFamily::V6);
+            assert!(matches!(
+                result,
+                Err(QuincyError::Route(RouteError::NotFound { .. }))
+            ));
+        }
+    }
+
+    #[cfg(any(target_os = "macos", target_os = "freebsd"))]
+    mod bsd_parser {
+        use super::*;
+
+        #[test]
+        fn ipv4_default_route() {
+            let output = "\
+   route to: default
+destination: default
+       mask: default
+    gateway: 192.168.1.1
+  interface: en0
+";
+            let gateway = parse_bsd_default_route(output, IpFamily::V4).unwrap();
+            assert_eq!(gateway, IpAddr::V4(Ipv4Addr::new(192, 168, 1, 1)));
+        }
+
+        #[test]
+        fn missing_gateway_returns_not_found() {
+            let output = "route: route has not been found\n";
+            assert!(parse_bsd_default_route(output, IpFamily::V4).is_err());
+        }
+    }
+}
@@ -3,53 +3,202 @@
 pub mod tun_rs;
 
 use crate::Result;
+#[cfg(feature = "capture")]
+use crate::error::InterfaceError;
+use crate::error::RouteError;
+#[cfg(feature = "capture")]
+use crate::network::capture::PacketCapture;
+use crate::network::dns::DnsOptions;
+use crate::network::firewall::{KillSwitch, KillSwitchGuard};
 use crate::network::packet::Packet;
-use crate::network::route::{InstalledExclusionRoute, remove_exclusion_route};
+use crate::network::route::{
+    InstalledBypassRoute, InstalledExclusionRoute, InstalledSplitTunnel, RouteOptions, RouteSpec,
+    SplitTunnel, add_bypass_routes, remove_bypass_routes, remove_exclusion_route,
+    routes_without_server_conflicts,
+};
 use ipnet::IpNet;
 use std::future::Future;
 use std::net::IpAddr;
-use std::sync::Arc;
-use tracing::error;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use tracing::{error, warn};
+
+/// An address of a tunnel interface.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct InterfaceAddress {
+    /// The address (with network prefix) of the interface
+    pub address: IpNet,
+    /// The address of the other end of the tunnel in the same family, used as the gateway of routes
+    pub gateway: Option<IpAddr>,
+}
+
+/// Traffic counters of a tunnel interface.
+///
+/// Counts the packets actually read from and written to the device, i.e. the
+/// tunneled payload without any transport overhead.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct InterfaceStats {
+    /// Bytes read from the interface (sent through the tunnel)
+    pub bytes_read: u64,
+    /// Packets read from the interface (sent through the tunnel)
+    pub packets_read: u64,
+    /// Bytes written to the interface (received through the tunnel)
+    pub bytes_written: u64,
+    /// Packets written to the interface (received through the tunnel)
+    pub packets_written: u64,
+}
 
-/// RAII guard that removes an installed exclusion host-route on drop.
+/// RAII guard that removes the installed tunnel routes, exclusion host-route,
+/// bypass routes of excluded networks and split tunneling rules, and lifts
+/// the kill switch, on drop.
 ///
 /// Cleanup is best-effort: failures are logged at `error` level but not
 /// propagated. The guard is armed when constructed with a `Some` exclusion
 /// token and disarmed (no-op on drop) when constructed with `None`.
 struct RouteGuard<I: InterfaceIO> {
     inner: Arc<I>,
-    #[allow(unused)]
-    routes: Option<Vec<IpNet>>,
+    routes: Option<Vec<RouteSpec>>,
+    remote_address: Option<IpAddr>,
     exclusion: Option<InstalledExclusionRoute>,
+    bypass: Vec<InstalledBypassRoute>,
+    split_tunnel: Option<InstalledSplitTunnel>,
+    kill_switch: Option<KillSwitchGuard>,
 }
 
 impl<I: InterfaceIO> RouteGuard<I> {
-    /// Installs the tunnel routes and arms the guard with any resulting
-    /// exclusion host-route token.
+    /// Installs the bypass routes of the excluded networks, then the tunnel
+    /// routes and finally the kill switch, and arms the guard with the
+    /// resulting route tokens.
+    ///
+    /// Bypass routes are installed first, while the excluded networks are
+    /// still reached through their original next-hop, and only together with
+    /// tunnel routes they could be carved out of.  The kill switch is only
+    /// engaged with a default route through the tunnel, allowing the server
+    /// endpoint and the bypass networks outside of it.
+    ///
+    /// With split tunneling, the policy routing rules are installed instead
+    /// of the bypass routes, and the routes only apply to the selected users'
+    /// traffic, so no exclusion host-route is needed.
     fn configure(
         inner: Arc<I>,
-        routes: Option<Vec<IpNet>>,
+        routes: Option<Vec<RouteSpec>>,
+        options: &RouteOptions,
         remote_address: Option<IpAddr>,
     ) -> Result<Self> {
         let mut guard = Self {
             inner,
             routes,
+            remote_address,
             exclusion: None,
+            bypass: Vec::new(),
+            split_tunnel: None,
+            kill_switch: None,
         };
 
-        guard.exclusion = match &guard.routes {
-            Some(routes) if !routes.is_empty() => {
-                guard.inner.configure_routes(routes, remote_address)?
+        // Only routes that were actually installed are recorded in the guard
+        let routes = match guard.routes.as_mut() {
+            Some(routes) => {
+                routes_without_server_conflicts(&std::mem::take(routes), remote_address)
             }
-            _ => None,
+            None => return Ok(guard),
         };
+        if routes.is_empty() {
+            return Ok(guard);
+        }
+
+        if let Some(split_tunnel) = &options.split_tunnel {
+            guard.split_tunnel = Some(guard.inner.configure_split_tunnel(
+                split_tunnel,
+                &options.bypass_networks,
+                remote_address,
+            )?);
+            guard.remote_address = None;
+        } else if !options.bypass_networks.is_empty() {
+            guard.bypass = guard
+                .inner
+                .configure_bypass_routes(&options.bypass_networks)?;
+        }
+        // On failure, dropping the guard removes the bypass routes again
+        guard.exclusion = guard
+            .inner
+            .configure_routes(&routes, guard.remote_address)?;
+        let default_route = routes.iter().any(|route| route.net.prefix_len() == 0);
+        guard.routes = Some(routes);
+
+        if options.kill_switch != KillSwitch::Disabled {
+            if default_route {
+                let allowed_networks: Vec<IpNet> = remote_address
+                    .map(IpNet::from)
+                    .into_iter()
+                    .chain(options.bypass_networks.iter().copied())
+                    .collect();
+
+                guard.kill_switch = guard
+                    .inner
+                    .configure_kill_switch(&allowed_networks, options.kill_switch)?;
+            } else {
+                warn!("Not enabling the kill switch: no default route is sent through the tunnel");
+            }
+        }
 
         Ok(guard)
     }
+
+    /// Applies the difference between the installed routes and `routes`.
+    ///
+    /// Routes that are no longer requested are removed and new ones are added,
+    /// except routes conflicting with the route to the server.  An exclusion
+    /// host-route is only requested if none is installed yet.  Does nothing
+    /// when route management is disabled.
+    fn update(&mut self, routes: &[RouteSpec]) -> Result<()> {
+        let Some(current) = self.routes.as_mut() else {
+            return Ok(());
+        };
+        let routes = &routes_without_server_conflicts(routes, self.remote_address);
+
+        let removed: Vec<RouteSpec> = current
+            .iter()
+            .filter(|route| !routes.contains(route))
+            .copied()
+            .collect();
+        let added: Vec<RouteSpec> = routes
+            .iter()
+            .filter(|route| !current.contains(route))
+            .copied()
+            .collect();
+
+        if !removed.is_empty() {
+            self.inner.remove_routes(&removed)?;
+            current.retain(|route| !removed.contains(route));
+        }
+
+        if !added.is_empty() {
+            let remote_address = match self.exclusion {
+                Some(_) => None,
+                None => self.remote_address,
+            };
+
+            if let Some(exclusion) = self.inner.configure_routes(&added, remote_address)? {
+                self.exclusion = Some(exclusion);
+            }
+            current.extend(added.iter().copied());
+        }
+
+        Ok(())
+    }
 }
 
 impl<I: InterfaceIO> Drop for RouteGuard<I> {
     fn drop(&mut self) {
+        // Lifted first, while the tunnel routes still keep traffic from leaking
+        drop(self.kill_switch.take());
+
+        if let Some(routes) = self.routes.as_ref().filter(|routes| !routes.is_empty()) {
+            if let Err(e) = self.inner.remove_routes(routes) {
+                error!("Failed to remove routes: {e}");
+            }
+        }
+
         if let Some(exclusion) = &self.exclusion {
             if let Err(e) = self.inner.remove_exclusion_route(exclusion) {
                 error!(
@@ -58,6 +207,18 @@ impl<I: InterfaceIO> Drop for RouteGuard<I> {
                 );
             }
         }
+
+        if !self.bypass.is_empty() {
+            if let Err(e) = self.inner.remove_bypass_routes(&self.bypass) {
+                error!("Failed to remove bypass routes: {e}");
+            }
+        }
+
+        if let Some(split_tunnel) = &self.split_tunnel {
+            if let Err(e) = self.inner.remove_split_tunnel(split_tunnel) {
+                error!("Failed to remove split tunneling rules: {e}");
+            }
+        }
     }
 }
 
@@ -69,29 +230,69 @@ impl<I: InterfaceIO> Drop for RouteGuard<I> {
 struct DnsGuard<I: InterfaceIO> {
     inner: Arc<I>,
     dns_servers: Option<Vec<IpAddr>>,
+    options: DnsOptions,
 }
 
 impl<I: InterfaceIO> DnsGuard<I> {
-    /// Installs DNS configuration for the servers already stored in the guard.
-    fn configure(inner: Arc<I>, dns_servers: Option<Vec<IpAddr>>) -> Result<Self> {
-        let guard = Self { inner, dns_servers };
+    /// Installs DNS configuration for the servers and options already stored in the guard.
+    fn configure(
+        inner: Arc<I>,
+        dns_servers: Option<Vec<IpAddr>>,
+        options: DnsOptions,
+    ) -> Result<Self> {
+        let guard = Self {
+            inner,
+            dns_servers,
+            options,
+        };
 
         let dns_servers = guard.dns_servers.as_deref().unwrap_or_default();
 
-        if !dns_servers.is_empty() {
-            guard.inner.configure_dns(dns_servers)?;
+        if guard.is_configured(dns_servers) {
+            guard.inner.configure_dns(dns_servers, &guard.options)?;
         }
 
         Ok(guard)
     }
+
+    /// Returns whether there is any DNS configuration to install for `dns_servers`.
+    fn is_configured(&self, dns_servers: &[IpAddr]) -> bool {
+        !dns_servers.is_empty() || !self.options.split_domains.is_empty()
+    }
+
+    /// Replaces the configured DNS servers with `dns_servers`.
+    ///
+    /// Does nothing when DNS management is disabled or the server list is
+    /// unchanged.
+    fn update(&mut self, dns_servers: &[IpAddr]) -> Result<()> {
+        let Some(current) = self.dns_servers.as_mut() else {
+            return Ok(());
+        };
+
+        if current.as_slice() == dns_servers {
+            return Ok(());
+        }
+
+        if !current.is_empty() || !self.options.split_domains.is_empty() {
+            self.inner.cleanup_dns(current, &self.options)?;
+            current.clear();
+        }
+
+        if !dns_servers.is_empty() || !self.options.split_domains.is_empty() {
+            self.inner.configure_dns(dns_servers, &self.options)?;
+            current.extend_from_slice(dns_servers);
+        }
+
+        Ok(())
+    }
 }
 
 impl<I: InterfaceIO> Drop for DnsGuard<I> {
     fn drop(&mut self) {
         let dns_servers = self.dns_servers.as_deref().unwrap_or_default();
 
-        if !dns_servers.is_empty() {
-            if let Err(e) = self.inner.cleanup_dns(dns_servers) {
+        if self.is_configured(dns_servers) {
+            if let Err(e) = self.inner.cleanup_dns(dns_servers, &self.options) {
                 error!("Failed to cleanup DNS servers: {e}");
             }
         }
@@ -100,15 +301,26 @@ impl<I: InterfaceIO> Drop for DnsGuard<I> {
 
 pub trait InterfaceIO: Send + Sync + 'static {
     /// Creates a new interface with the specified parameters.
+    ///
+    /// `addresses` holds at least one and at most one address per IP family,
+    /// so a dual-stack interface is created from an IPv4 and an IPv6 address.
+    /// `offload` requests segmentation offload, which implementations without
+    /// support for it ignore.
     fn create_interface(
-        interface_address: IpNet,
+        addresses: &[InterfaceAddress],
         mtu: u16,
-        tunnel_gateway: Option<IpAddr>,
         interface_name: Option<&str>,
+        offload: bool,
     ) -> Result<Self>
     where
         Self: Sized;
 
+    /// Replaces the addresses of the interface.
+    ///
+    /// `addresses` follows the same rules as in [`InterfaceIO::create_interface`].
+    /// The gateways of routes configured afterwards are taken from the new addresses.
+    fn set_addresses(&self, addresses: &[InterfaceAddress]) -> Result<()>;
+
     /// Configures the runtime routes for the interface.
     ///
     /// When `remote_address` is provided and the routes cover the default
@@ -116,12 +328,20 @@ pub trait InterfaceIO: Send + Sync + 'static {
     /// IP so tunnel traffic is not routed back into the tunnel.
     fn configure_routes(
         &self,
-        routes: &[IpNet],
+        routes: &[RouteSpec],
         remote_address: Option<IpAddr>,
     ) -> Result<Option<InstalledExclusionRoute>>;
 
+    /// Removes previously-configured runtime routes from the interface.
+    fn remove_routes(&self, routes: &[RouteSpec]) -> Result<()>;
+
     /// Configures the runtime DNS servers for the interface.
-    fn configure_dns(&self, dns_servers: &[IpAddr]) -> Result<()>;
+    ///
+    /// With split domains in `options`, only queries for those domains are resolved through
+    /// the interface, each by its own DNS servers, and `dns_servers` is not used for other
+    /// queries. With an encrypted protocol in `options`, the system resolver is pointed at a
+    /// local stub resolver forwarding the queries, which runs until [`InterfaceIO::cleanup_dns`].
+    fn configure_dns(&self, dns_servers: &[IpAddr], options: &DnsOptions) -> Result<()>;
 
     /// Removes a previously-installed exclusion host-route.
     ///
@@ -132,8 +352,68 @@ pub trait InterfaceIO: Send + Sync + 'static {
         remove_exclusion_route(exclusion)
     }
 
-    /// Cleans up runtime configuration of DNS servers.
-    fn cleanup_dns(&self, dns_servers: &[IpAddr]) -> Result<()>;
+    /// Installs routes sending the given excluded networks around the tunnel,
+    /// via the next-hop each network is currently reached through.
+    ///
+    /// Default implementation delegates to the platform
+    /// [`add_bypass_routes`] helper. Exists as a trait method so test
+    /// doubles can observe the routes without invoking platform commands.
+    fn configure_bypass_routes(&self, networks: &[IpNet]) -> Result<Vec<InstalledBypassRoute>> {
+        add_bypass_routes(networks, &self.name().unwrap_or_default())
+    }
+
+    /// Removes bypass routes previously installed with
+    /// [`InterfaceIO::configure_bypass_routes`].
+    ///
+    /// Default implementation delegates to the platform
+    /// [`remove_bypass_routes`] helper.
+    fn remove_bypass_routes(&self, routes: &[InstalledBypassRoute]) -> Result<()> {
+        remove_bypass_routes(routes)
+    }
+
+    /// Restricts the routes configured afterwards to the traffic of the users
+    /// in `split_tunnel`, by installing them into its routing table and
+    /// policy routing rules selecting the table for those users.
+    ///
+    /// Traffic to `remote_address` keeps using the main routing table, and
+    /// `bypass_networks` are thrown back to it.  The routes are installed
+    /// into the table until [`InterfaceIO::remove_split_tunnel`].  The
+    /// default implementation does not support split tunneling.
+    fn configure_split_tunnel(
+        &self,
+        _split_tunnel: &SplitTunnel,
+        _bypass_networks: &[IpNet],
+        _remote_address: Option<IpAddr>,
+    ) -> Result<InstalledSplitTunnel> {
+        Err(RouteError::PlatformError {
+            message: "split tunneling is not supported on this platform".to_string(),
+        }
+        .into())
+    }
+
+    /// Removes the rules installed with [`InterfaceIO::configure_split_tunnel`].
+    ///
+    /// The default implementation has nothing to remove.
+    fn remove_split_tunnel(&self, _installed: &InstalledSplitTunnel) -> Result<()> {
+        Ok(())
+    }
+
+    /// Installs the kill switch, blocking all outbound traffic that does not
+    /// go through the interface or to one of `allowed_networks`.
+    ///
+    /// Default implementation delegates to [`KillSwitchGuard::install`].
+    /// Exists as a trait method so test doubles can observe the kill switch
+    /// without invoking platform commands.
+    fn configure_kill_switch(
+        &self,
+        allowed_networks: &[IpNet],
+        mode: KillSwitch,
+    ) -> Result<Option<KillSwitchGuard>> {
+        KillSwitchGuard::install(&self.name().unwrap_or_default(), allowed_networks, mode)
+    }
+
+    /// Cleans up runtime configuration of DNS servers, split domains and the stub resolver.
+    fn cleanup_dns(&self, dns_servers: &[IpAddr], options: &DnsOptions) -> Result<()>;
 
     /// Brings the interface down.
     fn down(&self) -> Result<()>;
@@ -144,6 +424,24 @@ pub trait InterfaceIO: Send + Sync + 'static {
     /// Returns the name of the interface.
     fn name(&self) -> Option<String>;
 
+    /// Returns the traffic counters of the interface.
+    ///
+    /// The default implementation reports no traffic.
+    fn stats(&self) -> InterfaceStats {
+        InterfaceStats::default()
+    }
+
+    /// Starts capturing the packets read from and written to the interface.
+    ///
+    /// The default implementation does not support packet capture.
+    #[cfg(feature = "capture")]
+    fn start_capture(&self, _capture: PacketCapture) -> Result<()> {
+        Err(InterfaceError::ConfigurationFailed {
+            reason: "packet capture is not supported by this interface".to_string(),
+        }
+        .into())
+    }
+
     /// Reads a packet from the interface.
     fn read_packet(&self) -> impl Future<Output = Result<Packet>> + Send;
 
@@ -175,32 +473,34 @@ pub trait InterfaceIO: Send + Sync + 'static {
 /// into an [`ActiveInterface`] that owns packet I/O and cleanup.
 pub struct Interface<I: InterfaceIO> {
     inner: I,
-    routes: Option<Vec<IpNet>>,
+    routes: Option<Vec<RouteSpec>>,
+    route_options: RouteOptions,
     dns_servers: Option<Vec<IpAddr>>,
+    dns_options: DnsOptions,
     remote_address: Option<IpAddr>,
 }
 
 impl<I: InterfaceIO> Interface<I> {
+    #[allow(clippy::too_many_arguments)]
     pub fn create(
-        interface_address: IpNet,
+        addresses: &[InterfaceAddress],
         mtu: u16,
-        tunnel_gateway: Option<IpAddr>,
         interface_name: Option<String>,
-        routes: Option<Vec<IpNet>>,
+        offload: bool,
+        routes: Option<Vec<RouteSpec>>,
+        route_options: RouteOptions,
         dns_servers: Option<Vec<IpAddr>>,
+        dns_options: DnsOptions,
         remote_address: Option<IpAddr>,
     ) -> Result<Self> {
-        let interface = I::create_interface(
-            interface_address,
-            mtu,
-            tunnel_gateway,
-            interface_name.as_deref(),
-        )?;
+        let interface = I::create_interface(addresses, mtu, interface_name.as_deref(), offload)?;
 
         Ok(Interface {
             inner: interface,
             routes,
+            route_options,
             dns_servers,
+            dns_options,
             remote_address,
         })
     }
@@ -218,16 +518,51 @@ impl<I: InterfaceIO> Interface<I> {
     pub fn configure(self) -> Result<ActiveInterface<I>> {
         let inner = Arc::new(self.inner);
 
-        let route_guard = RouteGuard::configure(inner.clone(), self.routes, self.remote_address)?;
-        let dns_guard = DnsGuard::configure(inner.clone(), self.dns_servers)?;
+        let route_guard = RouteGuard::configure(
+            inner.clone(),
+            self.routes,
+            &self.route_options,
+            self.remote_address,
+        )?;
+        let dns_guard = DnsGuard::configure(inner.clone(), self.dns_servers, self.dns_options)?;
 
         Ok(ActiveInterface {
             inner,
-            route_guard: Some(route_guard),
-            dns_guard: Some(dns_guard),
+            route_guard: Mutex::new(Some(route_guard)),
+            dns_guard: Mutex::new(Some(dns_guard)),
         })
     }
 
+    /// Captures the packets of the interface into a pcap file, if one is configured.
+    ///
+    /// Without the `capture` feature, a configured capture file is ignored with a warning.
+    ///
+    /// ### Arguments
+    /// - `capture_file` - path to the pcap file, or `None` to not capture packets
+    /// - `max_bytes` - size of the file after which capturing stops
+    pub fn capture_packets(&self, capture_file: Option<&Path>, max_bytes: u64) -> Result<()> {
+        let Some(capture_file) = capture_file else {
+            return Ok(());
+        };
+
+        #[cfg(feature = "capture")]
+        {
+            self.inner
+                .start_capture(PacketCapture::create(capture_file, max_bytes)?)
+        }
+
+        #[cfg(not(feature = "capture"))]
+        {
+            let _ = max_bytes;
+            warn!(
+                "Ignoring capture file {}: built without the `capture` feature",
+                capture_file.display()
+            );
+
+            Ok(())
+        }
+    }
+
     pub fn mtu(&self) -> u16 {
         self.inner.mtu()
     }
@@ -236,14 +571,65 @@ impl<I: InterfaceIO> Interface<I> {
 /// A configured, active TUN interface that owns packet I/O and cleanup.
 ///
 /// Created by [`Interface::configure`]. On drop, the route guard is dropped
-/// first (removing the exclusion host-route), then the DNS guard (cleaning up
+/// first (lifting the kill switch and removing the tunnel routes, the
+/// exclusion host-route and bypass routes), then the DNS guard (cleaning up
 /// DNS configuration), and finally the underlying device is brought down.
-/// Ordinary tunnel routes are handled by system/interface teardown and are not
-/// explicitly cleaned up here.
+/// Tunnel routes are removed explicitly, as not every platform drops them
+/// together with the device.
 pub struct ActiveInterface<I: InterfaceIO> {
     inner: Arc<I>,
-    route_guard: Option<RouteGuard<I>>,
-    dns_guard: Option<DnsGuard<I>>,
+    route_guard: Mutex<Option<RouteGuard<I>>>,
+    dns_guard: Mutex<Option<DnsGuard<I>>>,
+}
+
+/// Runtime network configuration that can be changed while the interface is up.
+///
+/// Implemented by [`ActiveInterface`] so callers can apply configuration
+/// changes without knowing the concrete [`InterfaceIO`] implementation.
+pub trait NetworkConfiguration: Send + Sync {
+    /// Replaces the routes installed through the interface.
+    ///
+    /// ### Arguments
+    /// - `routes` - the full set of routes that should be installed
+    ///
+    /// ### Errors
+    /// Returns an error if a route could not be removed or added.
+    fn update_routes(&self, routes: &[RouteSpec]) -> Result<()>;
+
+    /// Replaces the DNS servers configured for the interface.
+    ///
+    /// ### Arguments
+    /// - `dns_servers` - the full set of DNS servers that should be configured
+    ///
+    /// ### Errors
+    /// Returns an error if the old servers could not be cleaned up or the new ones configured.
+    fn update_dns(&self, dns_servers: &[IpAddr]) -> Result<()>;
+
+    /// Replaces the addresses of the interface.
+    ///
+    /// Installed routes are reinstalled through the gateways of the new addresses.
+    ///
+    /// ### Arguments
+    /// - `addresses` - the new addresses, at most one per IP family
+    ///
+    /// ### Errors
+    /// Returns an error if the addresses could not be applied or the routes reinstalled.
+    fn update_addresses(&self, addresses: &[InterfaceAddress]) -> Result<()>;
+}
+
+/// Traffic counters of an active interface.
+///
+/// Implemented by [`ActiveInterface`] so callers can read the counters without
+/// knowing the concrete [`InterfaceIO`] implementation.
+pub trait InterfaceStatsProvider: Send + Sync {
+    /// Returns the traffic counters of the interface since it was created.
+    fn stats(&self) -> InterfaceStats;
+}
+
+impl<I: InterfaceIO> InterfaceStatsProvider for ActiveInterface<I> {
+    fn stats(&self) -> InterfaceStats {
+        self.inner.stats()
+    }
 }
 
 impl<I: InterfaceIO> ActiveInterface<I> {
@@ -251,6 +637,11 @@ impl<I: InterfaceIO> ActiveInterface<I> {
         self.inner.mtu()
     }
 
+    /// Returns the name of the interface, if the platform reports one.
+    pub fn name(&self) -> Option<String> {
+        self.inner.name()
+    }
+
     #[inline]
     pub async fn read_packet(&self) -> Result<Packet> {
         self.inner.read_packet().await
@@ -272,12 +663,64 @@ impl<I: InterfaceIO> ActiveInterface<I> {
     }
 }
 
+impl<I: InterfaceIO> NetworkConfiguration for ActiveInterface<I> {
+    fn update_routes(&self, routes: &[RouteSpec]) -> Result<()> {
+        let mut route_guard = self.route_guard.lock().unwrap_or_else(|e| e.into_inner());
+
+        match route_guard.as_mut() {
+            Some(guard) => guard.update(routes),
+            None => Ok(()),
+        }
+    }
+
+    fn update_dns(&self, dns_servers: &[IpAddr]) -> Result<()> {
+        let mut dns_guard = self.dns_guard.lock().unwrap_or_else(|e| e.into_inner());
+
+        match dns_guard.as_mut() {
+            Some(guard) => guard.update(dns_servers),
+            None => Ok(()),
+        }
+    }
+
+    fn update_addresses(&self, addresses: &[InterfaceAddress]) -> Result<()> {
+        let mut route_guard = self.route_guard.lock().unwrap_or_else(|e| e.into_inner());
+
+        // Installed routes point at the old gateways, so they are removed first
+        let routes = match route_guard.as_mut() {
+            Some(guard) => {
+                let routes = guard.routes.clone().unwrap_or_default();
+                guard.update(&[])?;
+                routes
+            }
+            None => Vec::new(),
+        };
+
+        let result = self.inner.set_addresses(addresses);
+
+        if let Some(guard) = route_guard.as_mut() {
+            guard.update(&routes)?;
+        }
+
+        result
+    }
+}
+
 impl<I: InterfaceIO> Drop for ActiveInterface<I> {
     fn drop(&mut self) {
         // Drop guards first to ensure routes and DNS are cleaned up
         // before the interface is brought down.
-        drop(self.route_guard.take());
-        drop(self.dns_guard.take());
+        drop(
+            self.route_guard
+                .get_mut()
+                .unwrap_or_else(|e| e.into_inner())
+                .take(),
+        );
+        drop(
+            self.dns_guard
+                .get_mut()
+                .unwrap_or_else(|e| e.into_inner())
+                .take(),
+        );
 
         if let Err(e) = self.inner.down() {
             error!("Failed to bring down TUN interface: {e}");
@@ -303,8 +746,15 @@ mod tests {
     #[derive(Default)]
     struct MockInterface {
         configure_routes_calls: AtomicUsize,
+        remove_routes_calls: AtomicUsize,
         configure_dns_calls: AtomicUsize,
+        set_addresses_calls: AtomicUsize,
         remove_exclusion_calls: AtomicUsize,
+        configure_bypass_calls: AtomicUsize,
+        removed_bypass_routes: AtomicUsize,
+        configure_kill_switch_calls: AtomicUsize,
+        configure_split_tunnel_calls: AtomicUsize,
+        remove_split_tunnel_calls: AtomicUsize,
         cleanup_dns_calls: AtomicUsize,
         down_calls: AtomicUsize,
 
@@ -315,6 +765,10 @@ mod tests {
 
         /// When set, `configure_routes` returns this exclusion token on success.
         exclusion_token: std::sync::Mutex<Option<InstalledExclusionRoute>>,
+        /// Networks the last installed kill switch allowed.
+        kill_switch_allowed: std::sync::Mutex<Vec<IpNet>>,
+        /// Remote address passed to the last `configure_routes` call.
+        routes_remote_address: std::sync::Mutex<Option<IpAddr>>,
     }
 
     fn configuration_failed(reason: &str) -> crate::QuincyError {
@@ -339,20 +793,27 @@ mod tests {
 
     impl InterfaceIO for SharedMock {
         fn create_interface(
-            _interface_address: IpNet,
+            _addresses: &[InterfaceAddress],
             _mtu: u16,
-            _tunnel_gateway: Option<IpAddr>,
             _interface_name: Option<&str>,
+            _offload: bool,
         ) -> Result<Self> {
             unreachable!("SharedMock is only constructed manually in tests")
         }
 
+        fn set_addresses(&self, _addresses: &[InterfaceAddress]) -> Result<()> {
+            self.0.set_addresses_calls.fetch_add(1, Ordering::SeqCst);
+
+            Ok(())
+        }
+
         fn configure_routes(
             &self,
-            _routes: &[IpNet],
-            _remote_address: Option<IpAddr>,
+            _routes: &[RouteSpec],
+            remote_address: Option<IpAddr>,
         ) -> Result<Option<InstalledExclusionRoute>> {
             self.0.configure_routes_calls.fetch_add(1, Ordering::SeqCst);
+            *self.0.routes_remote_address.lock().unwrap() = remote_address;
 
             if self.0.fail_configure_routes.load(Ordering::SeqCst) {
                 return Err(configuration_failed("forced configure_routes failure"));
@@ -361,7 +822,13 @@ mod tests {
             Ok(self.0.exclusion_token.lock().unwrap().take())
         }
 
-        fn configure_dns(&self, _dns_servers: &[IpAddr]) -> Result<()> {
+        fn remove_routes(&self, _routes: &[RouteSpec]) -> Result<()> {
+            self.0.remove_routes_calls.fetch_add(1, Ordering::SeqCst);
+
+            Ok(())
+        }
+
+        fn configure_dns(&self, _dns_servers: &[IpAddr], _options: &DnsOptions) -> Result<()> {
             self.0.configure_dns_calls.fetch_add(1, Ordering::SeqCst);
 
             if self.0.fail_configure_dns.load(Ordering::SeqCst) {
@@ -383,7 +850,72 @@ mod tests {
             Ok(())
         }
 
-        fn cleanup_dns(&self, _dns_servers: &[IpAddr]) -> Result<()> {
+        fn configure_bypass_routes(&self, networks: &[IpNet]) -> Result<Vec<InstalledBypassRoute>> {
+            self.0.configure_bypass_calls.fetch_add(1, Ordering::SeqCst);
+            // Bypass routes must be in place before the tunnel routes capture the networks
+            assert_eq!(self.0.configure_routes_calls.load(Ordering::SeqCst), 0);
+
+            Ok(networks
+                .iter()
+                .map(|net| InstalledBypassRoute {
+                    net: *net,
+                    next_hop: NextHop::OnLink {
+                        interface: "eth0".to_string(),
+                    },
+                })
+                .collect())
+        }
+
+        fn remove_bypass_routes(&self, routes: &[InstalledBypassRoute]) -> Result<()> {
+            self.0
+                .removed_bypass_routes
+                .fetch_add(routes.len(), Ordering::SeqCst);
+
+            Ok(())
+        }
+
+        fn configure_kill_switch(
+            &self,
+            allowed_networks: &[IpNet],
+            _mode: KillSwitch,
+        ) -> Result<Option<KillSwitchGuard>> {
+            self.0
+                .configure_kill_switch_calls
+                .fetch_add(1, Ordering::SeqCst);
+            *self.0.kill_switch_allowed.lock().unwrap() = allowed_networks.to_vec();
+
+            // A dry-run guard only logs the firewall changes
+            KillSwitchGuard::install("mock", allowed_networks, KillSwitch::DryRun)
+        }
+
+        fn configure_split_tunnel(
+            &self,
+            split_tunnel: &SplitTunnel,
+            bypass_networks: &[IpNet],
+            remote_address: Option<IpAddr>,
+        ) -> Result<InstalledSplitTunnel> {
+            self.0
+                .configure_split_tunnel_calls
+                .fetch_add(1, Ordering::SeqCst);
+            // The rules must be in place before the tunnel routes are installed
+            assert_eq!(self.0.configure_routes_calls.load(Ordering::SeqCst), 0);
+
+            Ok(InstalledSplitTunnel {
+                split_tunnel: split_tunnel.clone(),
+                bypass_networks: bypass_networks.to_vec(),
+                server: remote_address,
+            })
+        }
+
+        fn remove_split_tunnel(&self, _installed: &InstalledSplitTunnel) -> Result<()> {
+            self.0
+                .remove_split_tunnel_calls
+                .fetch_add(1, Ordering::SeqCst);
+
+            Ok(())
+        }
+
+        fn cleanup_dns(&self, _dns_servers: &[IpAddr], _options: &DnsOptions) -> Result<()> {
             self.0.cleanup_dns_calls.fetch_add(1, Ordering::SeqCst);
 
             if self.0.fail_cleanup_dns.load(Ordering::SeqCst) {
@@ -427,7 +959,9 @@ mod tests {
         let interface = Interface {
             inner: SharedMock(mock.clone()),
             routes: Some(vec!["0.0.0.0/0".parse().unwrap()]),
+            route_options: RouteOptions::default(),
             dns_servers: Some(vec![IpAddr::V4(Ipv4Addr::new(1, 1, 1, 1))]),
+            dns_options: DnsOptions::default(),
             remote_address: Some(IpAddr::V4(Ipv4Addr::new(1, 2, 3, 4))),
         };
 
@@ -464,9 +998,199 @@ mod tests {
             "no exclusion token was produced, so nothing to remove"
         );
         assert_eq!(mock.cleanup_dns_calls.load(Ordering::SeqCst), 0);
+        assert_eq!(
+            mock.remove_routes_calls.load(Ordering::SeqCst),
+            0,
+            "routes that failed to install must not be removed"
+        );
         assert_eq!(mock.down_calls.load(Ordering::SeqCst), 0);
     }
 
+    #[test]
+    fn host_routes_to_the_server_are_not_installed() {
+        let mock = Arc::new(MockInterface::default());
+        let mut interface = bypass_interface(&mock);
+        interface.routes = Some(vec!["1.2.3.4/32".parse().unwrap()]);
+
+        let active = interface.configure().expect("configure must succeed");
+        assert_eq!(mock.configure_routes_calls.load(Ordering::SeqCst), 0);
+        assert_eq!(mock.configure_bypass_calls.load(Ordering::SeqCst), 0);
+
+        active
+            .update_routes(&["1.2.3.4/32".parse().unwrap()])
+            .unwrap();
+        assert_eq!(mock.configure_routes_calls.load(Ordering::SeqCst), 0);
+
+        drop(active);
+        assert_eq!(mock.remove_routes_calls.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn routes_are_removed_when_active_interface_is_dropped() {
+        let mock = Arc::new(MockInterface::default());
+
+        let active = bypass_interface(&mock)
+            .configure()
+            .expect("configure must succeed");
+        assert_eq!(mock.remove_routes_calls.load(Ordering::SeqCst), 0);
+
+        drop(active);
+
+        assert_eq!(mock.remove_routes_calls.load(Ordering::SeqCst), 1);
+        assert_eq!(mock.down_calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn routes_are_removed_when_unwinding_from_a_panic() {
+        let mock = Arc::new(MockInterface::default());
+        let interface = bypass_interface(&mock);
+
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(move || {
+            let _active = interface.configure().expect("configure must succeed");
+            panic!("relayer panicked while the routes were installed");
+        }));
+
+        assert!(result.is_err());
+        assert_eq!(mock.remove_routes_calls.load(Ordering::SeqCst), 1);
+        assert_eq!(mock.removed_bypass_routes.load(Ordering::SeqCst), 2);
+        assert_eq!(mock.down_calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn routes_removed_explicitly_are_not_removed_again_on_drop() {
+        let mock = Arc::new(MockInterface::default());
+
+        let active = bypass_interface(&mock)
+            .configure()
+            .expect("configure must succeed");
+        active.update_routes(&[]).expect("update must succeed");
+        assert_eq!(mock.remove_routes_calls.load(Ordering::SeqCst), 1);
+
+        drop(active);
+
+        assert_eq!(mock.remove_routes_calls.load(Ordering::SeqCst), 1);
+        assert_eq!(mock.removed_bypass_routes.load(Ordering::SeqCst), 2);
+    }
+
+    fn bypass_interface(mock: &Arc<MockInterface>) -> Interface<SharedMock> {
+        Interface {
+            inner: SharedMock(mock.clone()),
+            routes: Some(vec!["0.0.0.0/0".parse().unwrap()]),
+            route_options: RouteOptions {
+                bypass_networks: vec![
+                    "192.168.0.0/16".parse().unwrap(),
+                    "fd00:1::/64".parse().unwrap(),
+                ],
+                kill_switch: KillSwitch::Disabled,
+                split_tunnel: None,
+            },
+            dns_servers: None,
+            dns_options: DnsOptions::default(),
+            remote_address: Some(IpAddr::V4(Ipv4Addr::new(1, 2, 3, 4))),
+        }
+    }
+
+    #[test]
+    fn bypass_routes_are_removed_when_active_interface_is_dropped() {
+        let mock = Arc::new(MockInterface::default());
+
+        let active = bypass_interface(&mock)
+            .configure()
+            .expect("configure must succeed");
+
+        assert_eq!(mock.configure_bypass_calls.load(Ordering::SeqCst), 1);
+        assert_eq!(mock.configure_routes_calls.load(Ordering::SeqCst), 1);
+        assert_eq!(mock.removed_bypass_routes.load(Ordering::SeqCst), 0);
+
+        drop(active);
+
+        assert_eq!(mock.removed_bypass_routes.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn configure_routes_failure_removes_bypass_routes() {
+        let mock = Arc::new(MockInterface::default());
+        mock.fail_configure_routes.store(true, Ordering::SeqCst);
+
+        assert!(bypass_interface(&mock).configure().is_err());
+
+        assert_eq!(
+            mock.removed_bypass_routes.load(Ordering::SeqCst),
+            2,
+            "bypass routes must be rolled back when the tunnel routes fail"
+        );
+    }
+
+    #[test]
+    fn bypass_routes_are_not_installed_without_routes() {
+        let mock = Arc::new(MockInterface::default());
+        let mut interface = bypass_interface(&mock);
+        interface.routes = None;
+
+        drop(interface.configure().expect("configure must succeed"));
+
+        assert_eq!(mock.configure_bypass_calls.load(Ordering::SeqCst), 0);
+        assert_eq!(mock.removed_bypass_routes.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn kill_switch_allows_server_and_bypass_networks_with_default_route() {
+        let mock = Arc::new(MockInterface::default());
+        let mut interface = bypass_interface(&mock);
+        interface.route_options.kill_switch = KillSwitch::Enabled;
+
+        drop(interface.configure().expect("configure must succeed"));
+
+        assert_eq!(mock.configure_kill_switch_calls.load(Ordering::SeqCst), 1);
+        assert_eq!(
+            *mock.kill_switch_allowed.lock().unwrap(),
+            vec![
+                "1.2.3.4/32".parse::<IpNet>().unwrap(),
+                "192.168.0.0/16".parse().unwrap(),
+                "fd00:1::/64".parse().unwrap(),
+            ]
+        );
+    }
+
+    #[test]
+    fn kill_switch_requires_default_route() {
+        let mock = Arc::new(MockInterface::default());
+        let mut interface = bypass_interface(&mock);
+        interface.routes = Some(vec!["10.0.0.0/8".parse().unwrap()]);
+        interface.route_options = RouteOptions {
+            bypass_networks: Vec::new(),
+            kill_switch: KillSwitch::Enabled,
+            split_tunnel: None,
+        };
+
+        drop(interface.configure().expect("configure must succeed"));
+
+        assert_eq!(mock.configure_kill_switch_calls.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn split_tunnel_replaces_bypass_and_exclusion_routes() {
+        let mock = Arc::new(MockInterface::default());
+        let mut interface = bypass_interface(&mock);
+        interface.route_options.split_tunnel = Some(SplitTunnel {
+            uids: vec![1000],
+            table: 51900,
+            fwmark: None,
+        });
+
+        let active = interface.configure().expect("configure must succeed");
+
+        assert_eq!(mock.configure_split_tunnel_calls.load(Ordering::SeqCst), 1);
+        assert_eq!(mock.configure_bypass_calls.load(Ordering::SeqCst), 0);
+        assert_eq!(mock.configure_routes_calls.load(Ordering::SeqCst), 1);
+        assert_eq!(*mock.routes_remote_address.lock().unwrap(), None);
+
+        drop(active);
+
+        assert_eq!(mock.remove_routes_calls.load(Ordering::SeqCst), 1);
+        assert_eq!(mock.remove_split_tunnel_calls.load(Ordering::SeqCst), 1);
+    }
+
     #[test]
     fn dns_failure_with_exclusion_removes_exclusion_and_cleans_dns() {
         let (_err, mock) = configure_with_shared_mock(|mock| {
@@ -520,21 +1244,23 @@ mod tests {
 
         let inner = Arc::new(SharedMock(mock.clone()));
         let active = ActiveInterface {
-            route_guard: Some(
+            route_guard: Mutex::new(Some(
                 RouteGuard::configure(
                     inner.clone(),
                     Some(vec!["0.0.0.0/0".parse().unwrap()]),
+                    &RouteOptions::default(),
                     Some("12.13.14.15".parse().unwrap()),
                 )
                 .unwrap(),
-            ),
-            dns_guard: Some(
+            )),
+            dns_guard: Mutex::new(Some(
                 DnsGuard::configure(
                     inner.clone(),
                     Some(vec![IpAddr::V4(Ipv4Addr::new(1, 1, 1, 1))]),
+                    DnsOptions::default(),
                 )
                 .unwrap(),
-            ),
+            )),
             inner,
         };
 
@@ -555,7 +1281,9 @@ mod tests {
         let interface = Interface {
             inner: SharedMock(mock.clone()),
             routes: Some(vec!["0.0.0.0/0".parse().unwrap()]),
+            route_options: RouteOptions::default(),
             dns_servers: Some(vec![IpAddr::V4(Ipv4Addr::new(1, 1, 1, 1))]),
+            dns_options: DnsOptions::default(),
             remote_address: Some(IpAddr::V4(Ipv4Addr::new(1, 2, 3, 4))),
         };
 
@@ -591,4 +1319,132 @@ mod tests {
         );
         assert_eq!(mock.down_calls.load(Ordering::SeqCst), 1);
     }
+
+    #[test]
+    fn unmanaged_routes_and_dns_are_never_configured_or_cleaned_up() {
+        let mock = Arc::new(MockInterface::default());
+
+        let interface = Interface {
+            inner: SharedMock(mock.clone()),
+            routes: None,
+            route_options: RouteOptions::default(),
+            dns_servers: None,
+            dns_options: DnsOptions::default(),
+            remote_address: Some(IpAddr::V4(Ipv4Addr::new(1, 2, 3, 4))),
+        };
+
+        let active = interface.configure().expect("configure must succeed");
+        drop(active);
+
+        assert_eq!(mock.configure_routes_calls.load(Ordering::SeqCst), 0);
+        assert_eq!(mock.configure_dns_calls.load(Ordering::SeqCst), 0);
+        assert_eq!(mock.remove_exclusion_calls.load(Ordering::SeqCst), 0);
+        assert_eq!(mock.cleanup_dns_calls.load(Ordering::SeqCst), 0);
+        assert_eq!(
+            mock.down_calls.load(Ordering::SeqCst),
+            1,
+            "the interface itself is still brought down"
+        );
+    }
+
+    #[test]
+    fn update_applies_only_route_and_dns_deltas() {
+        let mock = Arc::new(MockInterface::default());
+
+        let interface = Interface {
+            inner: SharedMock(mock.clone()),
+            routes: Some(vec!["10.0.0.0/8".parse().unwrap()]),
+            route_options: RouteOptions::default(),
+            dns_servers: Some(vec![IpAddr::V4(Ipv4Addr::new(1, 1, 1, 1))]),
+            dns_options: DnsOptions::default(),
+            remote_address: Some(IpAddr::V4(Ipv4Addr::new(1, 2, 3, 4))),
+        };
+
+        let active = interface.configure().expect("configure must succeed");
+
+        active
+            .update_routes(&["10.0.0.0/8".parse().unwrap()])
+            .unwrap();
+        active
+            .update_dns(&[IpAddr::V4(Ipv4Addr::new(1, 1, 1, 1))])
+            .unwrap();
+
+        assert_eq!(mock.configure_routes_calls.load(Ordering::SeqCst), 1);
+        assert_eq!(mock.remove_routes_calls.load(Ordering::SeqCst), 0);
+        assert_eq!(mock.configure_dns_calls.load(Ordering::SeqCst), 1);
+        assert_eq!(mock.cleanup_dns_calls.load(Ordering::SeqCst), 0);
+
+        active
+            .update_routes(&["192.168.0.0/16".parse().unwrap()])
+            .unwrap();
+        active
+            .update_dns(&[IpAddr::V4(Ipv4Addr::new(9, 9, 9, 9))])
+            .unwrap();
+
+        assert_eq!(mock.configure_routes_calls.load(Ordering::SeqCst), 2);
+        assert_eq!(mock.remove_routes_calls.load(Ordering::SeqCst), 1);
+        assert_eq!(mock.configure_dns_calls.load(Ordering::SeqCst), 2);
+        assert_eq!(mock.cleanup_dns_calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn update_addresses_reinstalls_routes() {
+        let mock = Arc::new(MockInterface::default());
+        *mock.exclusion_token.lock().unwrap() = Some(sample_exclusion());
+
+        let interface = Interface {
+            inner: SharedMock(mock.clone()),
+            routes: Some(vec!["0.0.0.0/0".parse().unwrap()]),
+            route_options: RouteOptions::default(),
+            dns_servers: None,
+            dns_options: DnsOptions::default(),
+            remote_address: Some(IpAddr::V4(Ipv4Addr::new(1, 2, 3, 4))),
+        };
+
+        let active = interface.configure().expect("configure must succeed");
+
+        active
+            .update_addresses(&[InterfaceAddress {
+                address: "10.0.0.3/24".parse().unwrap(),
+                gateway: Some("10.0.0.1".parse().unwrap()),
+            }])
+            .unwrap();
+
+        assert_eq!(mock.set_addresses_calls.load(Ordering::SeqCst), 1);
+        assert_eq!(mock.remove_routes_calls.load(Ordering::SeqCst), 1);
+        assert_eq!(mock.configure_routes_calls.load(Ordering::SeqCst), 2);
+        assert_eq!(
+            mock.remove_exclusion_calls.load(Ordering::SeqCst),
+            0,
+            "the exclusion route does not depend on the interface addresses"
+        );
+    }
+
+    #[test]
+    fn update_is_ignored_when_routes_and_dns_are_unmanaged() {
+        let mock = Arc::new(MockInterface::default());
+
+        let interface = Interface {
+            inner: SharedMock(mock.clone()),
+            routes: None,
+            route_options: RouteOptions::default(),
+            dns_servers: None,
+            dns_options: DnsOptions::default(),
+            remote_address: None,
+        };
+
+        let active = interface.configure().expect("configure must succeed");
+
+        active
+            .update_routes(&["0.0.0.0/0".parse().unwrap()])
+            .unwrap();
+        active
+            .update_dns(&[IpAddr::V4(Ipv4Addr::new(1, 1, 1, 1))])
+            .unwrap();
+
+        assert_eq!(mock.configure_routes_calls.load(Ordering::SeqCst), 0);
+        assert_eq!(mock.remove_routes_calls.load(Ordering::SeqCst), 0);
+        assert_eq!(mock.configure_dns_calls.load(Ordering::SeqCst), 0);
+        assert_eq!(mock.cleanup_dns_calls.load(Ordering::SeqCst), 0);
+    }
 }
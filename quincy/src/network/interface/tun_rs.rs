@@ -1,75 +1,260 @@
 use crate::Result;
 use crate::constants::PACKET_CHANNEL_SIZE;
 use crate::error::InterfaceError;
-use crate::network::dns::{add_dns_servers, delete_dns_servers};
-use crate::network::interface::InterfaceIO;
+use crate::network::IpFamily;
+#[cfg(feature = "capture")]
+use crate::network::capture::{CaptureDirection, PacketCapture};
+use crate::network::dns::{
+    DnsBackup, DnsOptions, DnsProtocol, DnsStub, LeakProtectionGuard, STUB_ADDRESS, SplitDnsDomain,
+    add_dns_servers, backup_dns_state, delete_dns_servers, dns_backup_path,
+};
+use crate::network::interface::{InterfaceAddress, InterfaceIO, InterfaceStats};
 use crate::network::packet::Packet;
-use crate::network::route::{InstalledExclusionRoute, add_routes};
+use crate::network::route::{
+    InstalledExclusionRoute, RouteSpec, add_routes, remove_exclusion_route, remove_routes,
+};
+#[cfg(target_os = "linux")]
+use crate::network::route::{
+    InstalledSplitTunnel, SplitTunnel, add_split_tunnel, add_table_routes, remove_split_tunnel,
+    remove_table_routes,
+};
 use bytes::BytesMut;
 use ipnet::IpNet;
 use std::net::IpAddr;
-use std::sync::Arc;
-use std::sync::atomic::{AtomicBool, Ordering};
+#[cfg(feature = "capture")]
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
 use tokio::sync::Mutex;
 use tokio::sync::mpsc::{Receiver, Sender};
 use tokio::task::JoinHandle;
 use tracing::{debug, error, info, warn};
 use tun_rs::{AsyncDevice, DeviceBuilder, ToIpv4Address};
 
+/// Initial (and smallest) number of packets taken from the reader channel at once.
+const MIN_READ_BATCH_SIZE: usize = 4;
+/// Number of consecutive reads filling the batch after which the batch size is doubled.
+const FULL_READS_TO_GROW: u32 = 2;
+
 pub struct TunRsInterface {
     inner: Arc<AsyncDevice>,
-    reader_channel: Mutex<Receiver<Packet>>,
+    reader_channel: Mutex<PacketReader>,
     writer_channel: Sender<Packet>,
     reader_task: JoinHandle<Result<()>>,
     writer_task: JoinHandle<Result<()>>,
     mtu: u16,
-    gateway: Option<IpAddr>,
+    gateways: RwLock<Vec<IpAddr>>,
+    counters: Arc<TunCounters>,
+    #[cfg(feature = "capture")]
+    capture: Arc<OnceLock<PacketCapture>>,
+    dns_stub: std::sync::Mutex<Option<DnsStub>>,
+    dns_leak_protection: std::sync::Mutex<Option<LeakProtectionGuard>>,
+    /// Routing table the routes are installed into while split tunneling is configured
+    #[cfg(target_os = "linux")]
+    route_table: std::sync::Mutex<Option<u32>>,
     torn_down: AtomicBool,
 }
 
+/// Receiving end of the reader channel, taking packets in adaptively sized batches.
+struct PacketReader {
+    receiver: Receiver<Packet>,
+    batch_size: AdaptiveBatchSize,
+    /// Buffer reused across batched reads
+    buf: Vec<Packet>,
+}
+
+impl PacketReader {
+    /// Creates a new packet reader.
+    ///
+    /// ### Arguments
+    /// - `receiver` - the receiving end of the reader channel
+    /// - `mtu` - the MTU of the interface, bounding the batch size
+    fn new(receiver: Receiver<Packet>, mtu: usize) -> Self {
+        let max_batch_size = (u16::MAX as usize / mtu).max(1);
+
+        Self {
+            receiver,
+            batch_size: AdaptiveBatchSize::new(max_batch_size),
+            buf: Vec::with_capacity(max_batch_size),
+        }
+    }
+
+    /// Waits for packets and takes up to the current batch size of them.
+    ///
+    /// ### Returns
+    /// - `Option<Vec<Packet>>` - the packets, or `None` if the channel is closed
+    async fn read_batch(&mut self) -> Option<Vec<Packet>> {
+        let read_packets = self
+            .receiver
+            .recv_many(&mut self.buf, self.batch_size.get())
+            .await;
+        if read_packets == 0 {
+            return None;
+        }
+
+        self.batch_size.record(read_packets);
+
+        Some(self.buf.drain(..).collect())
+    }
+}
+
+/// Number of packets taken from the reader channel at once.
+///
+/// Starts small and doubles toward the cap while consecutive reads fill the batch, as
+/// during bursts, and halves when a read returns at most a quarter of the batch.
+#[derive(Debug)]
+struct AdaptiveBatchSize {
+    size: usize,
+    max: usize,
+    /// Number of consecutive reads that filled the batch
+    full_reads: u32,
+}
+
+impl AdaptiveBatchSize {
+    /// Creates a new batch size, starting at its minimum.
+    ///
+    /// ### Arguments
+    /// - `max` - the largest batch size
+    fn new(max: usize) -> Self {
+        Self {
+            size: MIN_READ_BATCH_SIZE.min(max),
+            max,
+            full_reads: 0,
+        }
+    }
+
+    /// Returns the current batch size.
+    fn get(&self) -> usize {
+        self.size
+    }
+
+    /// Adapts the batch size to the number of packets a read returned.
+    ///
+    /// ### Arguments
+    /// - `read_packets` - the number of packets returned by the last read
+    fn record(&mut self, read_packets: usize) {
+        if read_packets >= self.size {
+            self.full_reads += 1;
+            if self.full_reads >= FULL_READS_TO_GROW {
+                self.size = (self.size * 2).min(self.max);
+                self.full_reads = 0;
+            }
+            return;
+        }
+
+        self.full_reads = 0;
+        if read_packets <= self.size / 4 {
+            self.size = (self.size / 2).max(MIN_READ_BATCH_SIZE.min(self.max));
+        }
+    }
+}
+
+/// State shared with the reader and writer tasks of a TUN device.
+#[derive(Clone)]
+struct IoTaskContext {
+    interface: Arc<AsyncDevice>,
+    mtu: usize,
+    counters: Arc<TunCounters>,
+    #[cfg(feature = "capture")]
+    capture: Arc<OnceLock<PacketCapture>>,
+}
+
+impl IoTaskContext {
+    /// Accounts for a packet read from the device.
+    fn record_read(&self, packet: &[u8]) {
+        self.counters.record_read(1, packet.len());
+
+        #[cfg(feature = "capture")]
+        if let Some(capture) = self.capture.get() {
+            capture.record(CaptureDirection::Outbound, packet);
+        }
+    }
+
+    /// Accounts for a packet written to the device.
+    fn record_written(&self, packet: &[u8]) {
+        self.counters.record_written(1, packet.len());
+
+        #[cfg(feature = "capture")]
+        if let Some(capture) = self.capture.get() {
+            capture.record(CaptureDirection::Inbound, packet);
+        }
+    }
+}
+
+/// Payload counters of the TUN device, updated by the reader and writer tasks.
+#[derive(Default)]
+struct TunCounters {
+    bytes_read: AtomicU64,
+    packets_read: AtomicU64,
+    bytes_written: AtomicU64,
+    packets_written: AtomicU64,
+}
+
+impl TunCounters {
+    fn record_read(&self, packets: usize, bytes: usize) {
+        self.packets_read
+            .fetch_add(packets as u64, Ordering::Relaxed);
+        self.bytes_read.fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    fn record_written(&self, packets: usize, bytes: usize) {
+        self.packets_written
+            .fetch_add(packets as u64, Ordering::Relaxed);
+        self.bytes_written
+            .fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    fn snapshot(&self) -> InterfaceStats {
+        InterfaceStats {
+            bytes_read: self.bytes_read.load(Ordering::Relaxed),
+            packets_read: self.packets_read.load(Ordering::Relaxed),
+            bytes_written: self.bytes_written.load(Ordering::Relaxed),
+            packets_written: self.packets_written.load(Ordering::Relaxed),
+        }
+    }
+}
+
 impl InterfaceIO for TunRsInterface {
     fn create_interface(
-        interface_address: IpNet,
+        addresses: &[InterfaceAddress],
         mtu: u16,
-        tunnel_gateway: Option<IpAddr>,
         interface_name: Option<&str>,
+        offload: bool,
     ) -> Result<Self>
     where
         Self: Sized,
     {
+        validate_interface_addresses(addresses)?;
+
+        #[cfg(not(all(target_os = "linux", feature = "offload")))]
+        if offload {
+            debug!(
+                "Segmentation offload is not supported by this build, relaying packets one at a time"
+            );
+        }
+
         let mut builder = DeviceBuilder::new().enable(true).mtu(mtu);
         if let Some(interface_name) = interface_name {
+            validate_interface_name(interface_name)?;
             builder = builder.name(interface_name);
         }
 
-        let builder = match interface_address {
-            IpNet::V4(interface_address) => {
-                let addr = interface_address.addr();
-                let netmask = interface_address.netmask();
-
-                let destination = if cfg!(not(target_os = "windows")) {
-                    tunnel_gateway.and_then(|addr| addr.ipv4().ok())
-                } else {
-                    None
-                };
-
-                builder.ipv4(addr, netmask, destination)
-            }
-            IpNet::V6(interface_address) => {
-                let addr = interface_address.addr();
-                let netmask = interface_address.netmask();
-
-                builder.ipv6(addr, netmask)
-            }
-        };
-
-        #[cfg(unix)]
-        let builder = builder.packet_information(false);
+        for interface_address in addresses {
+            builder = match interface_address.address {
+                IpNet::V4(address) => {
+                    let destination = if cfg!(not(target_os = "windows")) {
+                        interface_address.gateway.and_then(|addr| addr.ipv4().ok())
+                    } else {
+                        None
+                    };
 
-        #[cfg(all(target_os = "linux", feature = "offload"))]
-        let builder = builder.offload(true);
+                    builder.ipv4(address.addr(), address.netmask(), destination)
+                }
+                IpNet::V6(address) => builder.ipv6(address.addr(), address.netmask()),
+            };
+        }
 
-        let interface = builder
+        let interface = platform_options(builder, offload)
             .build_async()
             .map_err(|_| InterfaceError::CreationFailed)?;
         let interface = Arc::new(interface);
@@ -84,64 +269,288 @@ impl InterfaceIO for TunRsInterface {
         let (writer_channel_tx, writer_channel_rx) =
             tokio::sync::mpsc::channel::<Packet>(PACKET_CHANNEL_SIZE);
 
-        let reader_handle = reader_task(interface.clone(), reader_channel_tx, mtu as usize);
-        let writer_handle = writer_task(interface.clone(), writer_channel_rx, mtu as usize);
+        let context = IoTaskContext {
+            interface: interface.clone(),
+            mtu: mtu as usize,
+            counters: Arc::new(TunCounters::default()),
+            #[cfg(feature = "capture")]
+            capture: Arc::new(OnceLock::new()),
+        };
+
+        #[cfg(all(target_os = "linux", feature = "offload"))]
+        let (reader_handle, writer_handle) = if offload {
+            (
+                offload_reader_task(context.clone(), reader_channel_tx),
+                offload_writer_task(context.clone(), writer_channel_rx),
+            )
+        } else {
+            (
+                reader_task(context.clone(), reader_channel_tx),
+                writer_task(context.clone(), writer_channel_rx),
+            )
+        };
+
+        #[cfg(not(all(target_os = "linux", feature = "offload")))]
+        let (reader_handle, writer_handle) = (
+            reader_task(context.clone(), reader_channel_tx),
+            writer_task(context.clone(), writer_channel_rx),
+        );
 
         Ok(Self {
             inner: interface,
-            reader_channel: Mutex::new(reader_channel_rx),
+            reader_channel: Mutex::new(PacketReader::new(reader_channel_rx, mtu as usize)),
             writer_channel: writer_channel_tx,
             reader_task: reader_handle,
             writer_task: writer_handle,
             mtu,
-            gateway: tunnel_gateway,
+            gateways: RwLock::new(gateways(addresses)),
+            counters: context.counters,
+            #[cfg(feature = "capture")]
+            capture: context.capture,
+            dns_stub: std::sync::Mutex::new(None),
+            dns_leak_protection: std::sync::Mutex::new(None),
+            #[cfg(target_os = "linux")]
+            route_table: std::sync::Mutex::new(None),
             torn_down: AtomicBool::new(false),
         })
     }
 
+    fn set_addresses(&self, addresses: &[InterfaceAddress]) -> Result<()> {
+        validate_interface_addresses(addresses)?;
+
+        let current = self
+            .inner
+            .addresses()
+            .map_err(|e| InterfaceError::ConfigurationFailed {
+                reason: format!("failed to read interface addresses: {e}"),
+            })?;
+
+        // Link-local IPv6 addresses are managed by the system
+        let stale = current.iter().filter(|address| {
+            !matches!(address, IpAddr::V6(v6) if v6.is_unicast_link_local())
+                && !addresses.iter().any(|new| new.address.addr() == **address)
+        });
+        for address in stale {
+            self.inner.remove_address(*address).map_err(|e| {
+                InterfaceError::ConfigurationFailed {
+                    reason: format!("failed to remove interface address {address}: {e}"),
+                }
+            })?;
+        }
+
+        let added = addresses
+            .iter()
+            .filter(|new| !current.contains(&new.address.addr()));
+        for interface_address in added {
+            match interface_address.address {
+                IpNet::V4(address) => {
+                    let destination = if cfg!(not(target_os = "windows")) {
+                        interface_address.gateway.and_then(|addr| addr.ipv4().ok())
+                    } else {
+                        None
+                    };
+
+                    self.inner
+                        .set_network_address(address.addr(), address.netmask(), destination)
+                }
+                IpNet::V6(address) => self.inner.add_address_v6(address.addr(), address.netmask()),
+            }
+            .map_err(|e| InterfaceError::ConfigurationFailed {
+                reason: format!(
+                    "failed to set interface address {}: {e}",
+                    interface_address.address
+                ),
+            })?;
+        }
+
+        *self.gateways.write().unwrap_or_else(|e| e.into_inner()) = gateways(addresses);
+        info!("Set interface addresses: {addresses:?}");
+
+        Ok(())
+    }
+
     fn configure_routes(
         &self,
-        routes: &[IpNet],
+        routes: &[RouteSpec],
         remote_address: Option<IpAddr>,
     ) -> Result<Option<InstalledExclusionRoute>> {
-        let exclusion_token = add_routes(
-            routes,
-            &self
-                .gateway
-                .ok_or_else(|| InterfaceError::ConfigurationFailed {
-                    reason: "Missing gateway address on client".to_string(),
-                })?,
-            &self
-                .name()
-                .ok_or_else(|| InterfaceError::ConfigurationFailed {
-                    reason: "Missing interface name on client".to_string(),
-                })?,
-            remote_address,
-        )?;
+        #[cfg(target_os = "linux")]
+        if let Some(table) = self.route_table() {
+            self.add_table_routes(routes, table)?;
+            info!("Added routes to routing table {table}: {routes:?}");
+
+            return Ok(None);
+        }
+
+        let interface_name = self.interface_name()?;
+        let mut remote_address = remote_address;
+        let mut exclusion_token = None;
+        let mut added: Vec<(IpAddr, Vec<RouteSpec>)> = Vec::new();
+
+        for (gateway, family_routes) in self.routes_by_gateway(routes) {
+            // The exclusion host-route is only needed once, with the first family
+            match add_routes(
+                &family_routes,
+                &gateway,
+                &interface_name,
+                remote_address.take(),
+            ) {
+                Ok(token) => {
+                    exclusion_token = exclusion_token.or(token);
+                    added.push((gateway, family_routes));
+                }
+                Err(e) => {
+                    for (gateway, routes) in &added {
+                        if let Err(rm_err) = remove_routes(routes, gateway, &interface_name) {
+                            warn!("failed to roll back routes {routes:?}: {rm_err}");
+                        }
+                    }
+                    if let Some(token) = &exclusion_token {
+                        if let Err(rm_err) = remove_exclusion_route(token) {
+                            warn!(
+                                "failed to roll back exclusion route for {}: {rm_err}",
+                                token.destination
+                            );
+                        }
+                    }
+                    return Err(e);
+                }
+            }
+        }
         info!("Added routes: {routes:?}");
 
         Ok(exclusion_token)
     }
 
-    fn configure_dns(&self, dns_servers: &[IpAddr]) -> Result<()> {
-        add_dns_servers(
-            dns_servers,
-            &self
-                .name()
-                .ok_or_else(|| InterfaceError::ConfigurationFailed {
-                    reason: "attempted to configure DNS for interface without name".to_string(),
-                })?,
-        )?;
+    fn remove_routes(&self, routes: &[RouteSpec]) -> Result<()> {
+        let interface_name = self.interface_name()?;
+
+        for (gateway, family_routes) in self.routes_by_gateway(routes) {
+            #[cfg(target_os = "linux")]
+            if let Some(table) = self.route_table() {
+                remove_table_routes(&family_routes, &gateway, &interface_name, table)?;
+                continue;
+            }
+
+            remove_routes(&family_routes, &gateway, &interface_name)?;
+        }
+        info!("Removed routes: {routes:?}");
+
+        Ok(())
+    }
+
+    #[cfg(target_os = "linux")]
+    fn configure_split_tunnel(
+        &self,
+        split_tunnel: &SplitTunnel,
+        bypass_networks: &[IpNet],
+        remote_address: Option<IpAddr>,
+    ) -> Result<InstalledSplitTunnel> {
+        let installed = add_split_tunnel(split_tunnel, bypass_networks, remote_address)?;
+        *self.route_table.lock().unwrap_or_else(|e| e.into_inner()) = Some(split_tunnel.table);
+        info!(
+            "Split tunneling the traffic of users {:?} through routing table {}",
+            split_tunnel.uids, split_tunnel.table
+        );
+
+        Ok(installed)
+    }
 
-        info!("Added DNS servers: {dns_servers:?}");
+    #[cfg(target_os = "linux")]
+    fn remove_split_tunnel(&self, installed: &InstalledSplitTunnel) -> Result<()> {
+        *self.route_table.lock().unwrap_or_else(|e| e.into_inner()) = None;
+        remove_split_tunnel(installed)?;
+        info!(
+            "Removed split tunneling rules of routing table {}",
+            installed.split_tunnel.table
+        );
 
         Ok(())
     }
 
-    fn cleanup_dns(&self, dns_servers: &[IpAddr]) -> Result<()> {
-        delete_dns_servers()?;
+    fn configure_dns(&self, dns_servers: &[IpAddr], options: &DnsOptions) -> Result<()> {
+        let interface_name = self.interface_name()?;
+        let split_domains = &options.split_domains;
 
-        info!("Cleaned up DNS servers: {:?}", dns_servers);
+        // The snapshot of an earlier configuration in this run already holds the original state
+        let backup_path = dns_backup_path();
+        if !backup_path.exists() {
+            backup_dns_state(split_domains, &interface_name)?.save(&backup_path)?;
+        }
+
+        if options.protocol != DnsProtocol::Plain {
+            let stub = DnsStub::start(dns_servers, options)?;
+            // The system resolver only reaches the tunnel's DNS servers through the stub
+            let stub_split_domains = split_domains
+                .iter()
+                .map(|split| SplitDnsDomain {
+                    domain: split.domain.clone(),
+                    dns_servers: vec![STUB_ADDRESS],
+                })
+                .collect::<Vec<_>>();
+            add_dns_servers(
+                &[STUB_ADDRESS],
+                &stub_split_domains,
+                &options.search_domains,
+                &interface_name,
+            )?;
+
+            *self.dns_stub.lock().unwrap_or_else(|e| e.into_inner()) = Some(stub);
+            info!(
+                "Started {:?} stub resolver on {STUB_ADDRESS} for DNS servers {dns_servers:?}",
+                options.protocol
+            );
+        } else {
+            add_dns_servers(
+                dns_servers,
+                split_domains,
+                &options.search_domains,
+                &interface_name,
+            )?;
+        }
+
+        let mut leak_protection = self
+            .dns_leak_protection
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        // Rules of an earlier configuration are removed before installing the new ones
+        drop(leak_protection.take());
+        *leak_protection = LeakProtectionGuard::install(dns_servers, options.leak_protection)?;
+        drop(leak_protection);
+
+        if split_domains.is_empty() {
+            info!("Added DNS servers: {dns_servers:?}");
+        } else {
+            info!("Added split DNS domains: {split_domains:?}");
+        }
+
+        Ok(())
+    }
+
+    fn cleanup_dns(&self, dns_servers: &[IpAddr], options: &DnsOptions) -> Result<()> {
+        let split_domains = &options.split_domains;
+        // Stopped once the system resolver no longer points at it, even if that fails
+        let _dns_stub = self
+            .dns_stub
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .take();
+        let leak_protection_removed = self
+            .dns_leak_protection
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .take()
+            .map_or(Ok(()), LeakProtectionGuard::remove);
+
+        delete_dns_servers(split_domains, &self.interface_name()?)?;
+        DnsBackup::discard(&dns_backup_path())?;
+        leak_protection_removed?;
+
+        if split_domains.is_empty() {
+            info!("Cleaned up DNS servers: {:?}", dns_servers);
+        } else {
+            info!("Cleaned up split DNS domains: {split_domains:?}");
+        }
 
         Ok(())
     }
@@ -154,6 +563,20 @@ impl InterfaceIO for TunRsInterface {
         self.mtu
     }
 
+    fn stats(&self) -> InterfaceStats {
+        self.counters.snapshot()
+    }
+
+    #[cfg(feature = "capture")]
+    fn start_capture(&self, capture: PacketCapture) -> Result<()> {
+        self.capture.set(capture).map_err(|_| {
+            InterfaceError::ConfigurationFailed {
+                reason: "packet capture is already running".to_string(),
+            }
+            .into()
+        })
+    }
+
     fn name(&self) -> Option<String> {
         self.inner
             .name()
@@ -167,6 +590,7 @@ impl InterfaceIO for TunRsInterface {
             .reader_channel
             .lock()
             .await
+            .receiver
             .recv()
             .await
             .ok_or_else(|| InterfaceError::IoError {
@@ -180,29 +604,15 @@ impl InterfaceIO for TunRsInterface {
 
     #[inline]
     async fn read_packets(&self) -> Result<Vec<Packet>> {
-        let mtu = self.mtu() as usize;
-        let batch_size = u16::MAX as usize / mtu;
-
-        let mut packets_buf = Vec::with_capacity(batch_size);
-
-        let read_packets = self
+        let packets = self
             .reader_channel
             .lock()
             .await
-            .recv_many(&mut packets_buf, batch_size)
-            .await;
-
-        if read_packets == 0 {
-            return Err(InterfaceError::IoError {
+            .read_batch()
+            .await
+            .ok_or_else(|| InterfaceError::IoError {
                 operation: "failed to receive packets from reader channel".to_string(),
-            }
-            .into());
-        }
-
-        let packets = packets_buf
-            .into_iter()
-            .take(read_packets)
-            .collect::<Vec<_>>();
+            })?;
 
         debug!("TUN read packets: {}", packets.len());
 
@@ -245,6 +655,73 @@ impl InterfaceIO for TunRsInterface {
 }
 
 impl TunRsInterface {
+    /// Returns the name of the interface, as required for route configuration.
+    fn interface_name(&self) -> Result<String> {
+        self.name().ok_or_else(|| {
+            InterfaceError::ConfigurationFailed {
+                reason: "Missing interface name on client".to_string(),
+            }
+            .into()
+        })
+    }
+
+    /// Groups routes by the tunnel gateway of their IP family.
+    ///
+    /// Routes of a family the tunnel has no address of cannot be sent through it
+    /// and are skipped with a warning.
+    /// Returns the routing table of split tunneling, if configured.
+    #[cfg(target_os = "linux")]
+    fn route_table(&self) -> Option<u32> {
+        *self.route_table.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Adds `routes` to the routing table of split tunneling, rolling back the
+    /// routes of other gateways on failure.
+    #[cfg(target_os = "linux")]
+    fn add_table_routes(&self, routes: &[RouteSpec], table: u32) -> Result<()> {
+        let interface_name = self.interface_name()?;
+        let mut added: Vec<(IpAddr, Vec<RouteSpec>)> = Vec::new();
+
+        for (gateway, family_routes) in self.routes_by_gateway(routes) {
+            if let Err(e) = add_table_routes(&family_routes, &gateway, &interface_name, table) {
+                for (gateway, routes) in &added {
+                    if let Err(rm_err) =
+                        remove_table_routes(routes, gateway, &interface_name, table)
+                    {
+                        warn!("failed to roll back routes {routes:?}: {rm_err}");
+                    }
+                }
+                return Err(e);
+            }
+            added.push((gateway, family_routes));
+        }
+
+        Ok(())
+    }
+
+    fn routes_by_gateway(&self, routes: &[RouteSpec]) -> Vec<(IpAddr, Vec<RouteSpec>)> {
+        let gateways = self.gateways.read().unwrap_or_else(|e| e.into_inner());
+        let mut grouped: Vec<(IpAddr, Vec<RouteSpec>)> = Vec::new();
+
+        for route in routes {
+            let family = IpFamily::of(&route.net.addr());
+            let Some(gateway) = gateways
+                .iter()
+                .find(|gateway| IpFamily::of(gateway) == family)
+            else {
+                warn!("Skipping route {route}: the tunnel has no {family:?} address");
+                continue;
+            };
+
+            match grouped.iter_mut().find(|(g, _)| g == gateway) {
+                Some((_, family_routes)) => family_routes.push(*route),
+                None => grouped.push((*gateway, vec![*route])),
+            }
+        }
+
+        grouped
+    }
+
     /// Idempotent teardown shared by `InterfaceIO::down()` and `Drop`: aborts
     /// the I/O tasks and disables the TUN device. Subsequent calls are no-ops.
     fn teardown(&self) -> Result<()> {
@@ -255,6 +732,14 @@ impl TunRsInterface {
         self.reader_task.abort();
         self.writer_task.abort();
 
+        // DNS leak protection must not outlive the tunnel, even if DNS was not cleaned up
+        drop(
+            self.dns_leak_protection
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .take(),
+        );
+
         self.inner
             .enabled(false)
             .map_err(|e| InterfaceError::ConfigurationFailed {
@@ -278,12 +763,13 @@ impl Drop for TunRsInterface {
     }
 }
 
-#[cfg(any(not(target_os = "linux"), not(feature = "offload")))]
+/// Relays packets read from the device one at a time.
 fn reader_task(
-    interface: Arc<AsyncDevice>,
+    context: IoTaskContext,
     reader_channel_tx: Sender<Packet>,
-    mtu: usize,
 ) -> JoinHandle<Result<()>> {
+    let IoTaskContext { interface, mtu, .. } = context.clone();
+
     tokio::spawn(async move {
         loop {
             let mut packet_buf = unsafe {
@@ -298,6 +784,7 @@ fn reader_task(
                 .inspect_err(|e| error!("failed to receive packet: {}", e))?;
 
             packet_buf.truncate(size);
+            context.record_read(&packet_buf);
             let packet = packet_buf.into();
 
             if reader_channel_tx.is_closed() {
@@ -318,12 +805,13 @@ fn reader_task(
     })
 }
 
-#[cfg(any(not(target_os = "linux"), not(feature = "offload")))]
+/// Writes packets to the device one at a time.
 fn writer_task(
-    interface: Arc<AsyncDevice>,
+    context: IoTaskContext,
     mut writer_channel_rx: Receiver<Packet>,
-    _mtu: usize,
 ) -> JoinHandle<Result<()>> {
+    let interface = context.interface.clone();
+
     tokio::spawn(async move {
         loop {
             if writer_channel_rx.is_closed() {
@@ -339,6 +827,7 @@ fn writer_task(
                 .send(&packet)
                 .await
                 .inspect_err(|e| error!("failed to send packet: {}", e))?;
+            context.record_written(&packet);
         }
 
         info!("writer task exiting - channel closed");
@@ -346,15 +835,17 @@ fn writer_task(
     })
 }
 
+/// Relays packets read from the device in GRO batches.
 #[cfg(all(target_os = "linux", feature = "offload"))]
-fn reader_task(
-    interface: Arc<AsyncDevice>,
+fn offload_reader_task(
+    context: IoTaskContext,
     reader_channel_tx: Sender<Packet>,
-    mtu: usize,
 ) -> JoinHandle<Result<()>> {
     use std::iter;
     use tun_rs::{IDEAL_BATCH_SIZE, VIRTIO_NET_HDR_LEN};
 
+    let IoTaskContext { interface, mtu, .. } = context.clone();
+
     let batch_size = (u16::MAX as usize / mtu).min(IDEAL_BATCH_SIZE);
 
     let mut original_buffer = [0; VIRTIO_NET_HDR_LEN + u16::MAX as usize];
@@ -407,6 +898,7 @@ fn reader_task(
                 });
 
                 buf.truncate(size);
+                context.record_read(&buf);
                 let packet: Packet = buf.into();
 
                 let send_res = reader_channel_tx.send(packet).await;
@@ -422,14 +914,16 @@ fn reader_task(
     })
 }
 
+/// Writes packets to the device in GSO batches.
 #[cfg(all(target_os = "linux", feature = "offload"))]
-fn writer_task(
-    interface: Arc<AsyncDevice>,
+fn offload_writer_task(
+    context: IoTaskContext,
     mut writer_channel_rx: Receiver<Packet>,
-    mtu: usize,
 ) -> JoinHandle<Result<()>> {
     use tun_rs::{GROTable, IDEAL_BATCH_SIZE, VIRTIO_NET_HDR_LEN};
 
+    let IoTaskContext { interface, mtu, .. } = context.clone();
+
     let batch_size = (u16::MAX as usize / mtu).min(IDEAL_BATCH_SIZE);
     let send_buf_size = VIRTIO_NET_HDR_LEN * batch_size + batch_size * mtu;
 
@@ -456,6 +950,7 @@ fn writer_task(
             }
 
             for packet in packet_buf.drain(..) {
+                context.record_written(&packet);
                 send_buf.resize(VIRTIO_NET_HDR_LEN, 0);
                 send_buf.extend_from_slice(&packet);
                 send_bufs.push(send_buf.split());
@@ -472,6 +967,111 @@ fn writer_task(
     })
 }
 
+/// Applies the platform-specific device options.
+///
+/// Each option is gated on exactly the platforms where the pinned `tun-rs` provides it:
+/// `packet_information` does not exist on FreeBSD, and `offload` exists only on Linux.
+/// A `cfg(unix)` gate would break the FreeBSD build, so keep the gates narrow.
+fn platform_options(builder: DeviceBuilder, offload: bool) -> DeviceBuilder {
+    #[cfg(any(target_os = "linux", target_os = "macos", target_os = "ios"))]
+    let builder = builder.packet_information(false);
+
+    // FreeBSD TUN devices never prepend packet information, so there is nothing to disable there
+
+    #[cfg(all(target_os = "linux", feature = "offload"))]
+    let builder = builder.offload(offload);
+    #[cfg(not(all(target_os = "linux", feature = "offload")))]
+    let _ = offload;
+
+    builder
+}
+
+/// Returns the route gateways of the interface addresses.
+fn gateways(addresses: &[InterfaceAddress]) -> Vec<IpAddr> {
+    addresses
+        .iter()
+        .filter_map(|address| address.gateway)
+        .collect()
+}
+
+/// Validates that the interface gets at least one address and at most one per IP family.
+fn validate_interface_addresses(addresses: &[InterfaceAddress]) -> Result<()> {
+    let families: Vec<IpFamily> = addresses
+        .iter()
+        .map(|address| IpFamily::of(&address.address.addr()))
+        .collect();
+
+    if families.is_empty() {
+        return Err(InterfaceError::ConfigurationFailed {
+            reason: "the interface needs at least one address".to_string(),
+        }
+        .into());
+    }
+
+    if families.len() > 2 || families.windows(2).any(|pair| pair[0] == pair[1]) {
+        return Err(InterfaceError::ConfigurationFailed {
+            reason: "the interface accepts at most one address per IP family".to_string(),
+        }
+        .into());
+    }
+
+    Ok(())
+}
+
+/// Maximum interface name length on Linux and FreeBSD (`IFNAMSIZ` without the NUL terminator).
+#[cfg(any(target_os = "linux", target_os = "freebsd"))]
+const MAX_INTERFACE_NAME_LEN: usize = 15;
+
+/// Checks that a requested interface name is accepted by the platform.
+///
+/// ### Arguments
+/// - `name` - the requested interface name
+///
+/// ### Errors
+/// Returns `InterfaceError::ConfigurationFailed` if the platform would reject the name.
+fn validate_interface_name(name: &str) -> Result<()> {
+    match interface_name_violation(name) {
+        Some(reason) => Err(InterfaceError::ConfigurationFailed {
+            reason: format!("invalid interface name '{name}': {reason}"),
+        }
+        .into()),
+        None => Ok(()),
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "freebsd"))]
+fn interface_name_violation(name: &str) -> Option<String> {
+    if name.is_empty() {
+        return Some("the name must not be empty".to_string());
+    }
+
+    if name.len() > MAX_INTERFACE_NAME_LEN {
+        return Some(format!(
+            "the name must be at most {MAX_INTERFACE_NAME_LEN} characters long"
+        ));
+    }
+
+    if name.chars().any(|c| c == '/' || c.is_whitespace()) {
+        return Some("the name must not contain '/' or whitespace".to_string());
+    }
+
+    None
+}
+
+#[cfg(target_os = "macos")]
+fn interface_name_violation(name: &str) -> Option<String> {
+    match name.strip_prefix("utun") {
+        Some(unit) if !unit.is_empty() && unit.chars().all(|c| c.is_ascii_digit()) => None,
+        _ => Some("the name must be 'utun' followed by a unit number, e.g. utun8".to_string()),
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "freebsd", target_os = "macos")))]
+fn interface_name_violation(name: &str) -> Option<String> {
+    name.is_empty()
+        .then(|| "the name must not be empty".to_string())
+}
+
 /// Creates a `BytesMut` of `capacity` uninitialized bytes.
 ///
 /// # Safety
@@ -485,3 +1085,200 @@ unsafe fn uninitialized_bytes_mut(capacity: usize) -> BytesMut {
 
     buf
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Compiles the platform options on every target, so a gate that is too
+    /// broad (e.g. `packet_information` on FreeBSD) fails the test build there.
+    #[test]
+    fn platform_options_apply_to_builder() {
+        let _builder = platform_options(DeviceBuilder::new().mtu(1400), true);
+    }
+
+    #[test]
+    fn counters_accumulate_reads_and_writes() {
+        let counters = TunCounters::default();
+
+        counters.record_read(1, 1400);
+        counters.record_read(2, 200);
+        counters.record_written(3, 900);
+
+        assert_eq!(
+            counters.snapshot(),
+            InterfaceStats {
+                bytes_read: 1600,
+                packets_read: 3,
+                bytes_written: 900,
+                packets_written: 3,
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn read_batch_grows_during_bursts() {
+        let (tx, rx) = tokio::sync::mpsc::channel(1024);
+        // Batches of at most 46 packets at an MTU of 1400
+        let mut reader = PacketReader::new(rx, 1400);
+        assert_eq!(reader.batch_size.get(), MIN_READ_BATCH_SIZE);
+
+        for _ in 0..1000 {
+            tx.send(Packet::new(vec![0x45; 20].into())).await.unwrap();
+        }
+
+        let mut batch_lens = Vec::new();
+        for _ in 0..12 {
+            batch_lens.push(reader.read_batch().await.unwrap().len());
+        }
+
+        // Two full reads at each size before doubling, up to the cap
+        assert_eq!(batch_lens, [4, 4, 8, 8, 16, 16, 32, 32, 46, 46, 46, 46]);
+        assert_eq!(reader.batch_size.get(), 46);
+    }
+
+    #[tokio::test]
+    async fn read_batch_shrinks_on_trickle() {
+        let (tx, rx) = tokio::sync::mpsc::channel(1024);
+        let mut reader = PacketReader::new(rx, 1400);
+        reader.batch_size = AdaptiveBatchSize {
+            size: 46,
+            max: 46,
+            full_reads: 0,
+        };
+
+        for _ in 0..10 {
+            tx.send(Packet::new(vec![0x45; 20].into())).await.unwrap();
+            assert_eq!(reader.read_batch().await.unwrap().len(), 1);
+        }
+
+        assert_eq!(reader.batch_size.get(), MIN_READ_BATCH_SIZE);
+    }
+
+    #[test]
+    fn batch_size_grows_only_after_consecutive_full_reads() {
+        let mut batch_size = AdaptiveBatchSize::new(46);
+
+        batch_size.record(4);
+        batch_size.record(3);
+        batch_size.record(4);
+        assert_eq!(batch_size.get(), 4);
+
+        batch_size.record(4);
+        assert_eq!(batch_size.get(), 8);
+    }
+
+    #[tokio::test]
+    async fn closed_reader_channel_ends_reads() {
+        let (tx, rx) = tokio::sync::mpsc::channel(1);
+        let mut reader = PacketReader::new(rx, 1400);
+        drop(tx);
+
+        assert!(reader.read_batch().await.is_none());
+    }
+
+    #[test]
+    fn empty_interface_name_is_rejected() {
+        assert!(matches!(
+            validate_interface_name(""),
+            Err(crate::QuincyError::Interface(
+                InterfaceError::ConfigurationFailed { .. }
+            ))
+        ));
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "freebsd"))]
+    #[test]
+    fn interface_name_length_is_limited() {
+        assert!(validate_interface_name("quincy0").is_ok());
+        assert!(validate_interface_name("quincy-tunnel-0").is_ok());
+        assert!(validate_interface_name("quincy-tunnel-00").is_err());
+        assert!(validate_interface_name("quincy/0").is_err());
+    }
+
+    #[cfg(target_os = "macos")]
+    #[test]
+    fn interface_name_must_be_utun() {
+        assert!(validate_interface_name("utun8").is_ok());
+        assert!(validate_interface_name("utun").is_err());
+        assert!(validate_interface_name("quincy0").is_err());
+    }
+
+    fn interface_address(address: &str, gateway: Option<&str>) -> InterfaceAddress {
+        InterfaceAddress {
+            address: address.parse().unwrap(),
+            gateway: gateway.map(|gateway| gateway.parse().unwrap()),
+        }
+    }
+
+    #[test]
+    fn interface_addresses_are_limited_to_one_per_family() {
+        let v4 = interface_address("10.0.0.2/24", Some("10.0.0.1"));
+        let v6 = interface_address("fd00::2/64", Some("fd00::1"));
+        let other_v4 = interface_address("10.1.0.2/24", None);
+
+        assert!(validate_interface_addresses(&[v4]).is_ok());
+        assert!(validate_interface_addresses(&[v6, v4]).is_ok());
+        assert!(validate_interface_addresses(&[]).is_err());
+        assert!(validate_interface_addresses(&[v4, other_v4]).is_err());
+        assert!(validate_interface_addresses(&[v4, v6, other_v4]).is_err());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    #[ignore = "requires root privileges to create a TUN interface"]
+    async fn dual_stack_interface_has_both_addresses() {
+        let interface = TunRsInterface::create_interface(
+            &[
+                interface_address("10.213.0.2/24", Some("10.213.0.1")),
+                interface_address("fd00:213::2/64", Some("fd00:213::1")),
+            ],
+            1400,
+            Some("quincy-ds-test"),
+            false,
+        )
+        .expect("interface is created");
+
+        let output = std::process::Command::new("ip")
+            .args(["-o", "address", "show", "dev", "quincy-ds-test"])
+            .output()
+            .expect("ip command runs");
+        let addresses = String::from_utf8_lossy(&output.stdout);
+
+        assert!(addresses.contains("10.213.0.2/24"), "{addresses}");
+        assert!(addresses.contains("fd00:213::2/64"), "{addresses}");
+
+        interface.down().unwrap();
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    #[ignore = "requires root privileges to create a TUN interface"]
+    async fn removed_routes_are_gone_from_the_routing_table() {
+        let interface = TunRsInterface::create_interface(
+            &[interface_address("10.214.0.2/24", Some("10.214.0.1"))],
+            1400,
+            Some("quincy-rt-test"),
+            false,
+        )
+        .expect("interface is created");
+        let routes: Vec<RouteSpec> = vec!["10.214.1.0/24".parse().unwrap()];
+        let route_table = || {
+            let output = std::process::Command::new("ip")
+                .args(["route", "show", "10.214.1.0/24"])
+                .output()
+                .expect("ip command runs");
+            String::from_utf8_lossy(&output.stdout).to_string()
+        };
+
+        interface.configure_routes(&routes, None).unwrap();
+        assert!(route_table().contains("quincy-rt-test"));
+
+        interface.remove_routes(&routes).unwrap();
+        assert!(route_table().trim().is_empty());
+        // Removing routes that are already gone succeeds
+        interface.remove_routes(&routes).unwrap();
+
+        interface.down().unwrap();
+    }
+}
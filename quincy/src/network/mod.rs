@@ -1,5 +1,35 @@
+use std::net::IpAddr;
+
+use serde::Deserialize;
+
+#[cfg(feature = "capture")]
+pub mod capture;
 pub mod dns;
+pub mod firewall;
+pub mod gateway;
 pub mod interface;
 pub mod packet;
 pub mod route;
 pub mod socket;
+pub mod transport;
+
+/// IP address family.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+pub enum IpFamily {
+    /// IPv4
+    #[serde(rename = "ipv4")]
+    V4,
+    /// IPv6
+    #[serde(rename = "ipv6")]
+    V6,
+}
+
+impl IpFamily {
+    /// Returns the address family of the given address.
+    pub fn of(address: &IpAddr) -> Self {
+        match address {
+            IpAddr::V4(_) => IpFamily::V4,
+            IpAddr::V6(_) => IpFamily::V6,
+        }
+    }
+}
@@ -1,14 +1,33 @@
+use crate::error::RouteError;
+use crate::network::firewall::KillSwitch;
+use ipnet::IpNet;
+use serde::{Deserialize, Deserializer};
+use std::fmt;
 use std::net::IpAddr;
+use std::str::FromStr;
+use tracing::warn;
 
 #[cfg(unix)]
 mod posix;
 #[cfg(unix)]
-pub use posix::{add_routes, remove_exclusion_route};
+pub use posix::{
+    add_bypass_routes, add_routes, remove_bypass_routes, remove_exclusion_route, remove_routes,
+};
+
+#[cfg(target_os = "linux")]
+mod split_tunnel;
+#[cfg(target_os = "linux")]
+pub use split_tunnel::{
+    SPLIT_TUNNEL_RULE_PRIORITY, SPLIT_TUNNEL_SERVER_PRIORITY, add_split_tunnel, add_table_routes,
+    remove_split_tunnel, remove_table_routes,
+};
 
 #[cfg(target_os = "windows")]
 mod windows;
 #[cfg(target_os = "windows")]
-pub use windows::{add_routes, remove_exclusion_route};
+pub use windows::{
+    add_bypass_routes, add_routes, remove_bypass_routes, remove_exclusion_route, remove_routes,
+};
 
 /// Represents the next-hop for reaching a destination address: either an IP
 /// gateway or a directly-connected (on-link) interface.
@@ -28,3 +47,251 @@ pub struct InstalledExclusionRoute {
     pub destination: IpAddr,
     pub next_hop: NextHop,
 }
+
+/// Token proving that a route sending an excluded network around the tunnel
+/// was installed.  Carries all information needed to remove the route on
+/// cleanup.
+#[derive(Debug)]
+pub struct InstalledBypassRoute {
+    pub net: IpNet,
+    pub next_hop: NextHop,
+}
+
+/// Token proving that the policy routing rules of split tunneling were
+/// installed.  Carries all information needed to remove them on cleanup.
+#[derive(Debug)]
+pub struct InstalledSplitTunnel {
+    pub split_tunnel: SplitTunnel,
+    pub bypass_networks: Vec<IpNet>,
+    pub server: Option<IpAddr>,
+}
+
+/// Users whose traffic is sent through the tunnel, with the routing table holding the routes.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SplitTunnel {
+    /// The user IDs whose traffic is sent through the tunnel
+    pub uids: Vec<u32>,
+    /// The routing table the tunnel routes are installed into
+    pub table: u32,
+    /// The firewall mark of the tunnel's own packets, which keep using the main routing table
+    pub fwmark: Option<u32>,
+}
+
+/// Route settings applied together with the tunnel's routes.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RouteOptions {
+    /// Networks carved out of the routes, reached around the tunnel (see [`bypass_networks`])
+    pub bypass_networks: Vec<IpNet>,
+    /// Whether traffic outside of the tunnel is blocked while a default route is installed
+    ///
+    /// The server endpoint and the bypass networks stay reachable outside of the tunnel.
+    pub kill_switch: KillSwitch,
+    /// Users whose traffic is sent through the tunnel, or `None` for the traffic of all users
+    ///
+    /// Only supported on Linux, where the routes are installed into a dedicated routing table.
+    pub split_tunnel: Option<SplitTunnel>,
+}
+
+/// Returns the excluded networks that are carved out of the tunnel routes.
+///
+/// An excluded network only bypasses the tunnel if it is more specific than a
+/// route of the tunnel containing it (e.g. `192.168.0.0/16` out of `0.0.0.0/0`),
+/// since the route to the original next-hop then takes precedence.  Excluded
+/// networks that no route carves them out of, or that are routed through the
+/// tunnel themselves, are skipped with a warning.
+///
+/// ### Arguments
+/// - `routes` - the routes sent through the tunnel
+/// - `exclude_routes` - the networks excluded from the tunnel
+pub fn bypass_networks(routes: &[RouteSpec], exclude_routes: &[IpNet]) -> Vec<IpNet> {
+    let mut networks: Vec<IpNet> = Vec::with_capacity(exclude_routes.len());
+
+    for exclude in exclude_routes.iter().map(IpNet::trunc) {
+        let carved_out = routes.iter().any(|route| {
+            route.net.prefix_len() < exclude.prefix_len() && route.net.contains(&exclude)
+        });
+        let routed = routes.iter().any(|route| route.net == exclude);
+
+        if !carved_out || routed {
+            warn!(
+                "Ignoring excluded network {exclude}: not more specific than any route through the tunnel"
+            );
+            continue;
+        }
+        if !networks.contains(&exclude) {
+            networks.push(exclude);
+        }
+    }
+
+    networks
+}
+
+/// Returns the routes that do not conflict with the route to the VPN server.
+///
+/// The exclusion host-route keeps the server reachable outside of the tunnel
+/// only while it is the most specific route to the server.  A host route to
+/// the server through the tunnel would take its place and send the tunnel's
+/// own traffic back into the tunnel, so such routes are skipped with a warning.
+///
+/// ### Arguments
+/// - `routes` - the routes to be sent through the tunnel
+/// - `server` - the address of the VPN server, if known
+pub fn routes_without_server_conflicts(
+    routes: &[RouteSpec],
+    server: Option<IpAddr>,
+) -> Vec<RouteSpec> {
+    let Some(server) = server else {
+        return routes.to_vec();
+    };
+
+    routes
+        .iter()
+        .filter(|route| {
+            let conflict = route.net.prefix_len() == route.net.max_prefix_len()
+                && route.net.contains(&server);
+            if conflict {
+                let error = RouteError::AlreadyExists {
+                    destination: server.to_string(),
+                };
+                warn!("Skipping route {route} to the server, which must stay outside of the tunnel: {error}");
+            }
+
+            !conflict
+        })
+        .copied()
+        .collect()
+}
+
+/// A network routed through the tunnel, with an optional route metric.
+///
+/// In configuration files a route is either a bare network (`"10.0.0.0/8"`) or a table
+/// with a metric (`{ net = "10.0.0.0/8", metric = 50 }`). Routes with a lower metric are
+/// preferred over overlapping routes with a higher one.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct RouteSpec {
+    /// The routed network
+    pub net: IpNet,
+    /// The route metric, or `None` for the platform default
+    pub metric: Option<u32>,
+}
+
+impl From<IpNet> for RouteSpec {
+    fn from(net: IpNet) -> Self {
+        Self { net, metric: None }
+    }
+}
+
+impl FromStr for RouteSpec {
+    type Err = ipnet::AddrParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.parse().map(Self::from)
+    }
+}
+
+impl fmt::Display for RouteSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.metric {
+            Some(metric) => write!(f, "{} (metric {metric})", self.net),
+            None => write!(f, "{}", self.net),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for RouteSpec {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum RawRoute {
+            Net(IpNet),
+            Table {
+                net: IpNet,
+                #[serde(default)]
+                metric: Option<u32>,
+            },
+        }
+
+        Ok(match RawRoute::deserialize(deserializer)? {
+            RawRoute::Net(net) => net.into(),
+            RawRoute::Table { net, metric } => Self { net, metric },
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn routes(networks: &[&str]) -> Vec<RouteSpec> {
+        networks.iter().map(|net| net.parse().unwrap()).collect()
+    }
+
+    fn networks(networks: &[&str]) -> Vec<IpNet> {
+        networks.iter().map(|net| net.parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn excluded_networks_are_carved_out_of_default_routes() {
+        let bypass = bypass_networks(
+            &routes(&["0.0.0.0/0", "::/0"]),
+            &networks(&["192.168.0.0/16", "fd00:1::/64"]),
+        );
+
+        assert_eq!(bypass, networks(&["192.168.0.0/16", "fd00:1::/64"]));
+    }
+
+    #[test]
+    fn excluded_networks_are_carved_out_of_narrower_routes() {
+        let bypass = bypass_networks(
+            &routes(&["10.0.0.0/8"]),
+            &networks(&["10.1.0.0/16", "172.16.0.0/12"]),
+        );
+
+        assert_eq!(bypass, networks(&["10.1.0.0/16"]));
+    }
+
+    #[test]
+    fn excluded_networks_not_more_specific_than_a_route_are_skipped() {
+        let bypass = bypass_networks(
+            &routes(&["10.0.1.0/24", "0.0.0.0/0"]),
+            &networks(&["10.0.1.0/24", "::/0"]),
+        );
+
+        assert!(bypass.is_empty());
+        assert!(bypass_networks(&routes(&["10.0.1.0/24"]), &networks(&["10.0.0.0/8"])).is_empty());
+    }
+
+    #[test]
+    fn excluded_networks_are_normalized_and_deduplicated() {
+        let bypass = bypass_networks(
+            &routes(&["0.0.0.0/0"]),
+            &networks(&["192.168.1.7/24", "192.168.1.0/24"]),
+        );
+
+        assert_eq!(bypass, networks(&["192.168.1.0/24"]));
+    }
+
+    #[test]
+    fn host_routes_to_the_server_are_skipped() {
+        let server = Some("203.0.113.1".parse().unwrap());
+
+        assert_eq!(
+            routes_without_server_conflicts(
+                &routes(&["0.0.0.0/0", "203.0.113.1/32", "203.0.113.0/24", "::/0"]),
+                server
+            ),
+            routes(&["0.0.0.0/0", "203.0.113.0/24", "::/0"])
+        );
+        assert_eq!(
+            routes_without_server_conflicts(
+                &routes(&["2001:db8::1/128"]),
+                Some("2001:db8::1".parse().unwrap())
+            ),
+            Vec::new()
+        );
+        assert_eq!(
+            routes_without_server_conflicts(&routes(&["203.0.113.1/32"]), None),
+            routes(&["203.0.113.1/32"])
+        );
+    }
+}
@@ -1,6 +1,6 @@
 use crate::Result;
 use crate::error::RouteError;
-use crate::network::route::{InstalledExclusionRoute, NextHop};
+use crate::network::route::{InstalledBypassRoute, InstalledExclusionRoute, NextHop, RouteSpec};
 use crate::utils::command::run_command;
 use ipnet::{IpNet, Ipv4Net, Ipv6Net};
 use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
@@ -25,6 +25,9 @@ const ROUTE_COMMAND: &str = "route";
 /// `::/0`) are split into two halves (`/1` pairs) before both the coverage
 /// check and route installation, matching BSD kernel behaviour.
 ///
+/// Route metrics are applied on Linux. The BSD `route` utility has no
+/// per-route metric, so on macOS/FreeBSD metrics are ignored with a warning.
+///
 /// If the exclusion route cannot be installed **and** the (post-split) user
 /// routes would cover `remote_address`, a hard
 /// [`RouteError::ExclusionRequired`] error is returned.  If the routes do
@@ -36,26 +39,25 @@ const ROUTE_COMMAND: &str = "route";
 /// original error is returned.
 ///
 /// ### Arguments
-/// - `networks` - the networks to be routed through the gateway
+/// - `routes` - the routes to be sent through the gateway
 /// - `gateway` - the gateway to be used for the routes
 /// - `tunnel_interface` - the name of the tunnel interface being configured;
 ///   used to reject exclusion next-hops that resolve through the tunnel itself
 /// - `remote_address` - optional VPN server address requiring an exclusion route
 pub fn add_routes(
-    networks: &[IpNet],
+    routes: &[RouteSpec],
     gateway: &IpAddr,
     tunnel_interface: &str,
     remote_address: Option<IpAddr>,
 ) -> Result<Option<InstalledExclusionRoute>> {
-    // On BSD, split exact default routes before coverage checks and route
-    // installation so the kernel sees /1 pairs instead of /0.
-    let split_networks;
-    let effective_networks = if cfg!(any(target_os = "macos", target_os = "freebsd")) {
-        split_networks = bsd_split_default_routes(networks);
-        &split_networks
-    } else {
-        networks
-    };
+    if cfg!(any(target_os = "macos", target_os = "freebsd"))
+        && routes.iter().any(|route| route.metric.is_some())
+    {
+        warn!("route metrics are not supported on this platform; using the default metric");
+    }
+
+    let effective_routes = platform_routes(routes);
+    let effective_networks: Vec<IpNet> = effective_routes.iter().map(|route| route.net).collect();
 
     let exclusion = match remote_address {
         Some(server) => match install_exclusion_for_server(&server, tunnel_interface) {
@@ -64,7 +66,7 @@ pub fn add_routes(
             // to rely on, but we do not claim teardown ownership for it.
             Ok(token) => token,
             Err(err) => {
-                if any_route_covers_address(effective_networks, &server) {
+                if any_route_covers_address(&effective_networks, &server) {
                     return Err(RouteError::ExclusionRequired { server }.into());
                 }
                 warn!(
@@ -78,8 +80,8 @@ pub fn add_routes(
         None => None,
     };
 
-    for network in effective_networks {
-        if let Err(add_err) = add_route(network, gateway) {
+    for route in &effective_routes {
+        if let Err(add_err) = add_route(route, gateway) {
             if let Some(ref token) = exclusion {
                 if let Err(rm_err) = remove_exclusion_route(token) {
                     warn!(
@@ -95,6 +97,34 @@ pub fn add_routes(
     Ok(exclusion)
 }
 
+/// Removes a list of routes previously added with [`add_routes`].
+///
+/// Every route is attempted even if an earlier removal fails; the first
+/// failure is returned.  Routes that are already absent count as removed,
+/// so removal is idempotent.  Exclusion routes are not touched.
+///
+/// ### Arguments
+/// - `routes` - the routes sent through the gateway
+/// - `gateway` - the gateway the routes were added with
+/// - `_tunnel_interface` - unused, routes are matched by their gateway
+pub fn remove_routes(
+    routes: &[RouteSpec],
+    gateway: &IpAddr,
+    _tunnel_interface: &str,
+) -> Result<()> {
+    let mut result = Ok(());
+    for route in &platform_routes(routes) {
+        if let Err(e) = remove_route(route, gateway) {
+            warn!("failed to remove route {}: {e}", route.net);
+            if result.is_ok() {
+                result = Err(e);
+            }
+        }
+    }
+
+    result
+}
+
 /// Attempts to discover the current next-hop for `server` and install an
 /// exclusion host-route via that next-hop.
 ///
@@ -173,12 +203,28 @@ pub(crate) fn bsd_split_default_routes(networks: &[IpNet]) -> Vec<IpNet> {
     out
 }
 
+/// Adapts routes to what the platform's routing table supports.
+///
+/// On BSD, exact default routes are split into `/1` pairs so the kernel sees
+/// them instead of `/0`, and metrics are dropped since `route` cannot set them.
+fn platform_routes(routes: &[RouteSpec]) -> Vec<RouteSpec> {
+    if cfg!(any(target_os = "macos", target_os = "freebsd")) {
+        let networks: Vec<IpNet> = routes.iter().map(|route| route.net).collect();
+        bsd_split_default_routes(&networks)
+            .into_iter()
+            .map(RouteSpec::from)
+            .collect()
+    } else {
+        routes.to_vec()
+    }
+}
+
 pub(crate) fn any_route_covers_address(routes: &[IpNet], address: &IpAddr) -> bool {
     routes.iter().any(|net| net.contains(address))
 }
 
-fn add_route(network: &IpNet, gateway: &IpAddr) -> Result<()> {
-    let args = user_route_add_args(network, gateway);
+fn add_route(route: &RouteSpec, gateway: &IpAddr) -> Result<()> {
+    let args = user_route_add_args(route, gateway);
     let program = &args[0];
     let cmd_args = &args[1..];
 
@@ -194,7 +240,7 @@ fn add_route(network: &IpNet, gateway: &IpAddr) -> Result<()> {
     if !output.status.success() {
         let stderr = String::from_utf8_lossy(&output.stderr);
         return Err(RouteError::AddFailed {
-            destination: network.to_string(),
+            destination: route.net.to_string(),
             message: stderr.trim().to_string(),
         }
         .into());
@@ -203,6 +249,53 @@ fn add_route(network: &IpNet, gateway: &IpAddr) -> Result<()> {
     Ok(())
 }
 
+/// Removes a single route, treating an already absent route as removed.
+fn remove_route(route: &RouteSpec, gateway: &IpAddr) -> Result<()> {
+    let args = user_route_delete_args(route, gateway);
+    let program = &args[0];
+    let cmd_args = &args[1..];
+
+    let output = run_command(program, cmd_args)
+        .map_err(|e| RouteError::PlatformError {
+            message: format!("failed to execute command: {e}"),
+        })?
+        .wait_with_output()
+        .map_err(|e| RouteError::PlatformError {
+            message: format!("failed to create child process: {e}"),
+        })?;
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if output.status.success()
+        || output_indicates_not_found(&stdout)
+        || output_indicates_not_found(&stderr)
+    {
+        return Ok(());
+    }
+
+    Err(RouteError::RemoveFailed {
+        destination: route.net.to_string(),
+    }
+    .into())
+}
+
+/// Builds the argv for a user-route delete command.
+///
+/// All supported platforms take the same arguments for deleting a route as
+/// for adding it, with `delete` in place of `add`.
+fn user_route_delete_args(route: &RouteSpec, gateway: &IpAddr) -> Vec<String> {
+    user_route_add_args(route, gateway)
+        .into_iter()
+        .map(|arg| {
+            if arg == "add" {
+                "delete".to_string()
+            } else {
+                arg
+            }
+        })
+        .collect()
+}
+
 /// Builds the argv for a user-route add command.
 ///
 /// Emitting a `Vec<String>` directly keeps numeric/stringy arguments intact
@@ -210,7 +303,8 @@ fn add_route(network: &IpNet, gateway: &IpAddr) -> Result<()> {
 /// like separator in future formats) and makes the per-platform layout
 /// immediately auditable at the call site.
 #[cfg(target_os = "linux")]
-fn user_route_add_args(network: &IpNet, gateway: &IpAddr) -> Vec<String> {
+fn user_route_add_args(route: &RouteSpec, gateway: &IpAddr) -> Vec<String> {
+    let network = &route.net;
     let prefix = format!("{}/{}", network.addr(), network.prefix_len());
     let mut args = vec![IP_COMMAND.to_string()];
 
@@ -225,12 +319,20 @@ fn user_route_add_args(network: &IpNet, gateway: &IpAddr) -> Vec<String> {
         "via".to_string(),
         gateway.to_string(),
     ]);
+
+    if let Some(metric) = route.metric {
+        args.extend(["metric".to_string(), metric.to_string()]);
+    }
+
     args
 }
 
 /// Builds the argv for a user-route add command on macOS.
+///
+/// The route metric is not supported and ignored.
 #[cfg(target_os = "macos")]
-fn user_route_add_args(network: &IpNet, gateway: &IpAddr) -> Vec<String> {
+fn user_route_add_args(route: &RouteSpec, gateway: &IpAddr) -> Vec<String> {
+    let network = &route.net;
     match network {
         IpNet::V4(_) => vec![
             ROUTE_COMMAND.to_string(),
@@ -254,8 +356,11 @@ fn user_route_add_args(network: &IpNet, gateway: &IpAddr) -> Vec<String> {
 }
 
 /// Builds the argv for a user-route add command on FreeBSD.
+///
+/// The route metric is not supported and ignored.
 #[cfg(target_os = "freebsd")]
-fn user_route_add_args(network: &IpNet, gateway: &IpAddr) -> Vec<String> {
+fn user_route_add_args(route: &RouteSpec, gateway: &IpAddr) -> Vec<String> {
+    let network = &route.net;
     match network {
         IpNet::V4(_) => vec![
             ROUTE_COMMAND.to_string(),
@@ -459,6 +564,125 @@ fn run_exclusion_remove(args: &[String]) -> Result<Output> {
     Ok(output)
 }
 
+/// Installs a route for each excluded network via the next-hop it is
+/// currently reached through, so it bypasses the tunnel routes containing it.
+///
+/// Must be called before the tunnel routes are installed, while the networks
+/// are still reached through their original next-hop.  Pre-existing routes
+/// for a network are not adopted.  If a route cannot be installed, the routes
+/// installed so far are removed before the error is returned.
+///
+/// ### Arguments
+/// - `networks` - the excluded networks, see [`super::bypass_networks`]
+/// - `tunnel_interface` - the name of the tunnel interface; used to reject
+///   next-hops that resolve through the tunnel itself
+pub fn add_bypass_routes(
+    networks: &[IpNet],
+    tunnel_interface: &str,
+) -> Result<Vec<InstalledBypassRoute>> {
+    let mut installed = Vec::with_capacity(networks.len());
+
+    for net in networks {
+        match add_bypass_route(net, tunnel_interface) {
+            Ok(route) => installed.push(route),
+            Err(add_err) => {
+                if let Err(rm_err) = remove_bypass_routes(&installed) {
+                    warn!("failed to roll back bypass routes: {rm_err}");
+                }
+                return Err(add_err);
+            }
+        }
+    }
+
+    Ok(installed)
+}
+
+fn add_bypass_route(net: &IpNet, tunnel_interface: &str) -> Result<InstalledBypassRoute> {
+    let next_hop = get_route_to(&net.network())?;
+
+    if next_hop_uses_interface(&next_hop, tunnel_interface) {
+        return Err(RouteError::PlatformError {
+            message: format!(
+                "refusing to install bypass route for {net}: resolved next-hop \
+                 egress interface '{tunnel_interface}' is the tunnel itself"
+            ),
+        }
+        .into());
+    }
+
+    let args = bypass_route_cmd_args(net, &next_hop, ExclusionAction::Add);
+    let output = run_command(&args[0], &args[1..])
+        .map_err(|e| RouteError::PlatformError {
+            message: format!("failed to execute bypass route add command: {e}"),
+        })?
+        .wait_with_output()
+        .map_err(|e| RouteError::PlatformError {
+            message: format!("failed to wait for bypass route add command: {e}"),
+        })?;
+
+    // Some platforms report an existing route with a zero exit code
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if !output.status.success()
+        || output_indicates_already_exists(&stdout)
+        || output_indicates_already_exists(&stderr)
+    {
+        return Err(RouteError::AddFailed {
+            destination: net.to_string(),
+            message: stderr.trim().to_string(),
+        }
+        .into());
+    }
+
+    Ok(InstalledBypassRoute {
+        net: *net,
+        next_hop,
+    })
+}
+
+/// Removes bypass routes previously installed with [`add_bypass_routes`].
+///
+/// Every route is attempted even if an earlier removal fails; the first
+/// failure is returned.  Routes that are already absent are skipped.
+pub fn remove_bypass_routes(routes: &[InstalledBypassRoute]) -> Result<()> {
+    let mut result = Ok(());
+
+    for route in routes {
+        let args = bypass_route_cmd_args(&route.net, &route.next_hop, ExclusionAction::Remove);
+        let output = run_command(&args[0], &args[1..])
+            .map_err(|e| RouteError::PlatformError {
+                message: format!("failed to execute bypass route remove command: {e}"),
+            })?
+            .wait_with_output()
+            .map_err(|e| RouteError::PlatformError {
+                message: format!("failed to wait for bypass route remove command: {e}"),
+            })?;
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        if output.status.success()
+            || output_indicates_not_found(&stdout)
+            || output_indicates_not_found(&stderr)
+        {
+            continue;
+        }
+
+        warn!(
+            "failed to remove bypass route {}: {}",
+            route.net,
+            stderr.trim()
+        );
+        if result.is_ok() {
+            result = Err(RouteError::RemoveFailed {
+                destination: route.net.to_string(),
+            }
+            .into());
+        }
+    }
+
+    result
+}
+
 /// Returns the egress interface of a next-hop.
 fn next_hop_interface(next_hop: &NextHop) -> &str {
     match next_hop {
@@ -533,21 +757,43 @@ fn exclusion_route_cmd_args(
         args.push("-6".to_string());
     }
     args.extend(["route".to_string(), action_str.to_string(), host_cidr]);
+    args.extend(next_hop_args(next_hop));
 
+    args
+}
+
+/// Builds the `ip route` arguments selecting `next_hop`.
+#[cfg(target_os = "linux")]
+fn next_hop_args(next_hop: &NextHop) -> Vec<String> {
     match next_hop {
         NextHop::Gateway { address, interface } => {
-            args.extend(["via".to_string(), address.to_string()]);
+            let mut args = vec!["via".to_string(), address.to_string()];
             // Link-local gateways (IPv4 169.254.0.0/16, IPv6 fe80::/10) are
             // ambiguous on multi-homed hosts without explicit interface
             // context, so pin them to the discovered interface.
             if is_link_local(address) {
                 args.extend(["dev".to_string(), interface.clone()]);
             }
+            args
         }
-        NextHop::OnLink { interface } => {
-            args.extend(["dev".to_string(), interface.clone()]);
-        }
+        NextHop::OnLink { interface } => vec!["dev".to_string(), interface.clone()],
     }
+}
+
+/// Builds bypass-route add/remove commands for an excluded network.
+#[cfg(target_os = "linux")]
+fn bypass_route_cmd_args(net: &IpNet, next_hop: &NextHop, action: ExclusionAction) -> Vec<String> {
+    let action_str = match action {
+        ExclusionAction::Add => "add",
+        ExclusionAction::Remove => "delete",
+    };
+
+    let mut args = vec![IP_COMMAND.to_string()];
+    if matches!(net, IpNet::V6(_)) {
+        args.push("-6".to_string());
+    }
+    args.extend(["route".to_string(), action_str.to_string(), net.to_string()]);
+    args.extend(next_hop_args(next_hop));
 
     args
 }
@@ -573,21 +819,46 @@ fn exclusion_route_cmd_args(
         args.push("-inet6".to_string());
     }
     args.extend(["-host".to_string(), server.to_string()]);
+    args.extend(next_hop_args(next_hop));
 
+    args
+}
+
+/// Builds the `route` arguments selecting `next_hop`.
+#[cfg(any(target_os = "macos", target_os = "freebsd"))]
+fn next_hop_args(next_hop: &NextHop) -> Vec<String> {
     match next_hop {
         NextHop::Gateway { address, interface } => {
             // Link-local IPv6 gateways require the scoped form `addr%iface`
             // so the BSD kernel can resolve the on-link neighbour.
             if is_ipv6_link_local(address) {
-                args.push(format!("{address}%{interface}"));
+                vec![format!("{address}%{interface}")]
             } else {
-                args.push(address.to_string());
+                vec![address.to_string()]
             }
         }
-        NextHop::OnLink { interface } => {
-            args.extend(["-interface".to_string(), interface.clone()]);
-        }
+        NextHop::OnLink { interface } => vec!["-interface".to_string(), interface.clone()],
     }
+}
+
+/// Builds bypass-route add/remove commands for an excluded network.
+#[cfg(any(target_os = "macos", target_os = "freebsd"))]
+fn bypass_route_cmd_args(net: &IpNet, next_hop: &NextHop, action: ExclusionAction) -> Vec<String> {
+    let action_str = match action {
+        ExclusionAction::Add => "add",
+        ExclusionAction::Remove => "delete",
+    };
+
+    let mut args = vec![
+        ROUTE_COMMAND.to_string(),
+        "-n".to_string(),
+        action_str.to_string(),
+    ];
+    if matches!(net, IpNet::V6(_)) {
+        args.push("-inet6".to_string());
+    }
+    args.extend(["-net".to_string(), net.to_string()]);
+    args.extend(next_hop_args(next_hop));
 
     args
 }
@@ -641,7 +912,7 @@ fn output_indicates_already_exists(output: &str) -> bool {
 /// a hard error.  Matches Linux (`RTNETLINK answers: No such process`,
 /// `Cannot find`) and BSD (`not in table`, `no such process`,
 /// `no such file or directory`).
-fn output_indicates_not_found(output: &str) -> bool {
+pub(super) fn output_indicates_not_found(output: &str) -> bool {
     let lower = output.to_lowercase();
     lower.contains("no such process")
         || lower.contains("not in table")
@@ -1443,6 +1714,101 @@ destination: default
         }
     }
 
+    #[cfg(target_os = "linux")]
+    mod bypass_linux {
+        use super::*;
+
+        #[test]
+        fn add_gateway_ipv4() {
+            let net: IpNet = "192.168.0.0/16".parse().unwrap();
+            let hop = NextHop::Gateway {
+                address: IpAddr::V4(Ipv4Addr::new(192, 168, 1, 1)),
+                interface: "eth0".to_string(),
+            };
+            let args = bypass_route_cmd_args(&net, &hop, ExclusionAction::Add);
+            assert_eq!(
+                args,
+                [
+                    IP_COMMAND,
+                    "route",
+                    "add",
+                    "192.168.0.0/16",
+                    "via",
+                    "192.168.1.1"
+                ]
+            );
+        }
+
+        #[test]
+        fn remove_onlink_ipv6() {
+            let net: IpNet = "fd00:1::/64".parse().unwrap();
+            let hop = NextHop::OnLink {
+                interface: "eth0".to_string(),
+            };
+            let args = bypass_route_cmd_args(&net, &hop, ExclusionAction::Remove);
+            assert_eq!(
+                args,
+                [
+                    IP_COMMAND,
+                    "-6",
+                    "route",
+                    "delete",
+                    "fd00:1::/64",
+                    "dev",
+                    "eth0"
+                ]
+            );
+        }
+    }
+
+    #[cfg(any(target_os = "macos", target_os = "freebsd"))]
+    mod bypass_bsd {
+        use super::*;
+
+        #[test]
+        fn add_gateway_ipv4() {
+            let net: IpNet = "192.168.0.0/16".parse().unwrap();
+            let hop = NextHop::Gateway {
+                address: IpAddr::V4(Ipv4Addr::new(192, 168, 1, 1)),
+                interface: "en0".to_string(),
+            };
+            let args = bypass_route_cmd_args(&net, &hop, ExclusionAction::Add);
+            assert_eq!(
+                args,
+                [
+                    ROUTE_COMMAND,
+                    "-n",
+                    "add",
+                    "-net",
+                    "192.168.0.0/16",
+                    "192.168.1.1"
+                ]
+            );
+        }
+
+        #[test]
+        fn remove_onlink_ipv6() {
+            let net: IpNet = "fd00:1::/64".parse().unwrap();
+            let hop = NextHop::OnLink {
+                interface: "en0".to_string(),
+            };
+            let args = bypass_route_cmd_args(&net, &hop, ExclusionAction::Remove);
+            assert_eq!(
+                args,
+                [
+                    ROUTE_COMMAND,
+                    "-n",
+                    "delete",
+                    "-inet6",
+                    "-net",
+                    "fd00:1::/64",
+                    "-interface",
+                    "en0"
+                ]
+            );
+        }
+    }
+
     #[cfg(any(target_os = "macos", target_os = "freebsd"))]
     mod exclusion_bsd {
         use super::*;
@@ -1848,7 +2214,7 @@ destination: default
         fn ipv4_add_argv() {
             let net: IpNet = "10.0.0.0/24".parse().unwrap();
             let gw: IpAddr = "192.168.1.1".parse().unwrap();
-            let args = user_route_add_args(&net, &gw);
+            let args = user_route_add_args(&RouteSpec::from(net), &gw);
             assert_eq!(
                 args,
                 [
@@ -1866,7 +2232,7 @@ destination: default
         fn ipv6_add_argv() {
             let net: IpNet = "2001:db8::/32".parse().unwrap();
             let gw: IpAddr = "2001:db8::1".parse().unwrap();
-            let args = user_route_add_args(&net, &gw);
+            let args = user_route_add_args(&RouteSpec::from(net), &gw);
             assert_eq!(
                 args,
                 [
@@ -1880,6 +2246,42 @@ destination: default
                 ]
             );
         }
+
+        #[test]
+        fn metric_is_appended_to_add_and_delete_argv() {
+            let route = RouteSpec {
+                net: "10.0.0.0/24".parse().unwrap(),
+                metric: Some(50),
+            };
+            let gw: IpAddr = "192.168.1.1".parse().unwrap();
+
+            assert_eq!(
+                user_route_add_args(&route, &gw),
+                [
+                    IP_COMMAND,
+                    "route",
+                    "add",
+                    "10.0.0.0/24",
+                    "via",
+                    "192.168.1.1",
+                    "metric",
+                    "50"
+                ]
+            );
+            assert_eq!(
+                user_route_delete_args(&route, &gw),
+                [
+                    IP_COMMAND,
+                    "route",
+                    "delete",
+                    "10.0.0.0/24",
+                    "via",
+                    "192.168.1.1",
+                    "metric",
+                    "50"
+                ]
+            );
+        }
     }
 
     #[cfg(target_os = "macos")]
@@ -1890,7 +2292,7 @@ destination: default
         fn ipv4_add_argv() {
             let net: IpNet = "10.0.0.0/24".parse().unwrap();
             let gw: IpAddr = "192.168.1.1".parse().unwrap();
-            let args = user_route_add_args(&net, &gw);
+            let args = user_route_add_args(&RouteSpec::from(net), &gw);
             assert_eq!(
                 args,
                 [
@@ -1910,7 +2312,7 @@ destination: default
         fn ipv6_add_argv() {
             let net: IpNet = "2001:db8::/32".parse().unwrap();
             let gw: IpAddr = "2001:db8::1".parse().unwrap();
-            let args = user_route_add_args(&net, &gw);
+            let args = user_route_add_args(&RouteSpec::from(net), &gw);
             assert_eq!(
                 args,
                 [
@@ -1933,7 +2335,7 @@ destination: default
         fn ipv4_add_argv() {
             let net: IpNet = "10.0.0.0/24".parse().unwrap();
             let gw: IpAddr = "192.168.1.1".parse().unwrap();
-            let args = user_route_add_args(&net, &gw);
+            let args = user_route_add_args(&RouteSpec::from(net), &gw);
             assert_eq!(
                 args,
                 [
@@ -1952,7 +2354,7 @@ destination: default
         fn ipv6_add_argv() {
             let net: IpNet = "2001:db8::/32".parse().unwrap();
             let gw: IpAddr = "2001:db8::1".parse().unwrap();
-            let args = user_route_add_args(&net, &gw);
+            let args = user_route_add_args(&RouteSpec::from(net), &gw);
             assert_eq!(
                 args,
                 [
@@ -0,0 +1,439 @@
+//! Split tunneling by user ID on Linux.
+//!
+//! Instead of the main routing table, the tunnel routes are installed into a dedicated routing
+//! table, which policy routing rules (`ip rule add uidrange ...`) only look up for the traffic
+//! of the configured users. Traffic of other users, and traffic of the configured users to
+//! destinations without a tunnel route, keeps using the main table. Requires `CAP_NET_ADMIN`.
+//!
+//! The rules use the priorities [`SPLIT_TUNNEL_SERVER_PRIORITY`] and
+//! [`SPLIT_TUNNEL_RULE_PRIORITY`], which are evaluated before the main table (32766).
+
+use crate::Result;
+use crate::error::RouteError;
+use crate::network::IpFamily;
+use crate::network::route::posix::output_indicates_not_found;
+use crate::network::route::{InstalledSplitTunnel, RouteSpec, SplitTunnel};
+use crate::utils::command::run_command;
+use ipnet::IpNet;
+use std::net::IpAddr;
+use tracing::warn;
+
+/// Command name for the Linux `ip` utility.
+const IP_COMMAND: &str = "ip";
+/// Priority of the rules keeping the traffic to the server, and the marked packets of the
+/// tunnel itself, in the main routing table.
+pub const SPLIT_TUNNEL_SERVER_PRIORITY: u32 = 30999;
+/// Priority of the rules selecting the split tunnel routing table for the configured users.
+pub const SPLIT_TUNNEL_RULE_PRIORITY: u32 = 31000;
+
+#[derive(Clone, Copy)]
+enum SplitTunnelAction {
+    Add,
+    Remove,
+}
+
+impl SplitTunnelAction {
+    fn as_str(self) -> &'static str {
+        match self {
+            SplitTunnelAction::Add => "add",
+            SplitTunnelAction::Remove => "delete",
+        }
+    }
+}
+
+/// Installs the policy routing rules of split tunneling and the throw routes of the networks
+/// excluded from the tunnel.
+///
+/// Traffic to `server` keeps using the main routing table, so that the tunnel's own traffic
+/// is not sent into the tunnel when the user running Quincy is one of the configured users.
+/// On failure, the rules installed so far are removed again.
+///
+/// ### Arguments
+/// - `split_tunnel` - the users and routing table of split tunneling
+/// - `bypass_networks` - the networks excluded from the tunnel routes
+/// - `server` - the address of the VPN server, if known
+///
+/// ### Errors
+/// Returns `RouteError::AddFailed` if a rule or route cannot be added.
+pub fn add_split_tunnel(
+    split_tunnel: &SplitTunnel,
+    bypass_networks: &[IpNet],
+    server: Option<IpAddr>,
+) -> Result<InstalledSplitTunnel> {
+    let installed = InstalledSplitTunnel {
+        split_tunnel: split_tunnel.clone(),
+        bypass_networks: bypass_networks.to_vec(),
+        server,
+    };
+
+    for args in split_tunnel_args(&installed, SplitTunnelAction::Add) {
+        if let Err(add_err) = run_ip(&args, SplitTunnelAction::Add) {
+            if let Err(rm_err) = remove_split_tunnel(&installed) {
+                warn!("failed to roll back split tunneling: {rm_err}");
+            }
+            return Err(add_err);
+        }
+    }
+
+    Ok(installed)
+}
+
+/// Removes the rules and throw routes installed by [`add_split_tunnel`].
+///
+/// Every rule is attempted even if an earlier removal fails; the first failure is returned.
+/// Rules that are already absent count as removed.
+///
+/// ### Errors
+/// Returns `RouteError::RemoveFailed` if a rule or route cannot be removed.
+pub fn remove_split_tunnel(installed: &InstalledSplitTunnel) -> Result<()> {
+    let mut result = Ok(());
+
+    for args in split_tunnel_args(installed, SplitTunnelAction::Remove) {
+        if let Err(e) = run_ip(&args, SplitTunnelAction::Remove) {
+            if result.is_ok() {
+                result = Err(e);
+            }
+        }
+    }
+
+    result
+}
+
+/// Adds routes through `gateway` to the routing table of split tunneling.
+///
+/// On failure, the routes added so far are removed again.
+///
+/// ### Arguments
+/// - `routes` - the routes to be sent through the gateway
+/// - `gateway` - the gateway to be used for the routes
+/// - `tunnel_interface` - the name of the tunnel interface
+/// - `table` - the routing table of split tunneling
+pub fn add_table_routes(
+    routes: &[RouteSpec],
+    gateway: &IpAddr,
+    tunnel_interface: &str,
+    table: u32,
+) -> Result<()> {
+    for (index, route) in routes.iter().enumerate() {
+        let args = table_route_args(
+            route,
+            gateway,
+            tunnel_interface,
+            table,
+            SplitTunnelAction::Add,
+        );
+        if let Err(add_err) = run_ip(&args, SplitTunnelAction::Add) {
+            if let Err(rm_err) =
+                remove_table_routes(&routes[..index], gateway, tunnel_interface, table)
+            {
+                warn!("failed to roll back split tunnel routes: {rm_err}");
+            }
+            return Err(add_err);
+        }
+    }
+
+    Ok(())
+}
+
+/// Removes routes previously added with [`add_table_routes`].
+///
+/// Every route is attempted even if an earlier removal fails; the first failure is returned.
+/// Routes that are already absent count as removed.
+pub fn remove_table_routes(
+    routes: &[RouteSpec],
+    gateway: &IpAddr,
+    tunnel_interface: &str,
+    table: u32,
+) -> Result<()> {
+    let mut result = Ok(());
+
+    for route in routes {
+        let args = table_route_args(
+            route,
+            gateway,
+            tunnel_interface,
+            table,
+            SplitTunnelAction::Remove,
+        );
+        if let Err(e) = run_ip(&args, SplitTunnelAction::Remove) {
+            if result.is_ok() {
+                result = Err(e);
+            }
+        }
+    }
+
+    result
+}
+
+/// Runs an `ip` command, treating an absent rule or route as removed.
+fn run_ip(args: &[String], action: SplitTunnelAction) -> Result<()> {
+    let output = run_command(IP_COMMAND, args)
+        .map_err(|e| RouteError::PlatformError {
+            message: format!("failed to execute split tunnel command: {e}"),
+        })?
+        .wait_with_output()
+        .map_err(|e| RouteError::PlatformError {
+            message: format!("failed to wait for split tunnel command: {e}"),
+        })?;
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if output.status.success() {
+        return Ok(());
+    }
+
+    let destination = args.join(" ");
+    match action {
+        SplitTunnelAction::Add => Err(RouteError::AddFailed {
+            destination,
+            message: stderr.trim().to_string(),
+        }
+        .into()),
+        SplitTunnelAction::Remove if output_indicates_not_found(&stderr) => Ok(()),
+        SplitTunnelAction::Remove => {
+            warn!(
+                "failed to remove split tunnel rule '{destination}': {}",
+                stderr.trim()
+            );
+            Err(RouteError::RemoveFailed { destination }.into())
+        }
+    }
+}
+
+/// Builds the arguments of the `ip` commands adding or removing the rules and throw routes of
+/// split tunneling.
+fn split_tunnel_args(
+    installed: &InstalledSplitTunnel,
+    action: SplitTunnelAction,
+) -> Vec<Vec<String>> {
+    let table = installed.split_tunnel.table.to_string();
+    let action = action.as_str();
+    let mut commands = Vec::new();
+
+    for family in [IpFamily::V4, IpFamily::V6] {
+        let mut ip = vec![];
+        if family == IpFamily::V6 {
+            ip.push("-6");
+        }
+
+        if let Some(server) = installed
+            .server
+            .filter(|server| IpFamily::of(server) == family)
+        {
+            commands.push(args(
+                &ip,
+                &[
+                    "rule",
+                    action,
+                    "to",
+                    &server.to_string(),
+                    "lookup",
+                    "main",
+                    "priority",
+                    &SPLIT_TUNNEL_SERVER_PRIORITY.to_string(),
+                ],
+            ));
+        }
+
+        if let Some(fwmark) = installed.split_tunnel.fwmark {
+            commands.push(args(
+                &ip,
+                &[
+                    "rule",
+                    action,
+                    "fwmark",
+                    &format!("{fwmark:#x}"),
+                    "lookup",
+                    "main",
+                    "priority",
+                    &SPLIT_TUNNEL_SERVER_PRIORITY.to_string(),
+                ],
+            ));
+        }
+
+        for uid in &installed.split_tunnel.uids {
+            commands.push(args(
+                &ip,
+                &[
+                    "rule",
+                    action,
+                    "uidrange",
+                    &format!("{uid}-{uid}"),
+                    "lookup",
+                    &table,
+                    "priority",
+                    &SPLIT_TUNNEL_RULE_PRIORITY.to_string(),
+                ],
+            ));
+        }
+
+        for net in installed
+            .bypass_networks
+            .iter()
+            .filter(|net| IpFamily::of(&net.addr()) == family)
+        {
+            commands.push(args(
+                &ip,
+                &["route", action, "throw", &net.to_string(), "table", &table],
+            ));
+        }
+    }
+
+    commands
+}
+
+/// Builds the arguments of the `ip` command adding or removing a route of the split tunnel
+/// routing table.
+fn table_route_args(
+    route: &RouteSpec,
+    gateway: &IpAddr,
+    tunnel_interface: &str,
+    table: u32,
+    action: SplitTunnelAction,
+) -> Vec<String> {
+    let mut ip = vec![];
+    if matches!(route.net, IpNet::V6(_)) {
+        ip.push("-6");
+    }
+
+    let mut command = args(
+        &ip,
+        &[
+            "route",
+            action.as_str(),
+            &route.net.to_string(),
+            "via",
+            &gateway.to_string(),
+            "dev",
+            tunnel_interface,
+            "table",
+            &table.to_string(),
+        ],
+    );
+    if let Some(metric) = route.metric {
+        command.extend(["metric".to_string(), metric.to_string()]);
+    }
+
+    command
+}
+
+fn args(prefix: &[&str], args: &[&str]) -> Vec<String> {
+    prefix
+        .iter()
+        .chain(args)
+        .map(|arg| arg.to_string())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn installed(server: Option<&str>, bypass_networks: &[&str]) -> InstalledSplitTunnel {
+        InstalledSplitTunnel {
+            split_tunnel: SplitTunnel {
+                uids: vec![1000, 1001],
+                table: 51900,
+                fwmark: None,
+            },
+            bypass_networks: bypass_networks
+                .iter()
+                .map(|net| net.parse().unwrap())
+                .collect(),
+            server: server.map(|server| server.parse().unwrap()),
+        }
+    }
+
+    fn joined(commands: Vec<Vec<String>>) -> Vec<String> {
+        commands.into_iter().map(|args| args.join(" ")).collect()
+    }
+
+    #[test]
+    fn rules_select_the_table_for_configured_users() {
+        let commands = joined(split_tunnel_args(
+            &installed(Some("203.0.113.1"), &["192.168.0.0/16"]),
+            SplitTunnelAction::Add,
+        ));
+
+        assert_eq!(
+            commands,
+            vec![
+                "rule add to 203.0.113.1 lookup main priority 30999",
+                "rule add uidrange 1000-1000 lookup 51900 priority 31000",
+                "rule add uidrange 1001-1001 lookup 51900 priority 31000",
+                "route add throw 192.168.0.0/16 table 51900",
+                "-6 rule add uidrange 1000-1000 lookup 51900 priority 31000",
+                "-6 rule add uidrange 1001-1001 lookup 51900 priority 31000",
+            ]
+        );
+    }
+
+    #[test]
+    fn rules_are_removed_with_the_same_selectors() {
+        let commands = joined(split_tunnel_args(
+            &installed(Some("2001:db8::1"), &["fd00:1::/64"]),
+            SplitTunnelAction::Remove,
+        ));
+
+        assert_eq!(
+            commands,
+            vec![
+                "rule delete uidrange 1000-1000 lookup 51900 priority 31000",
+                "rule delete uidrange 1001-1001 lookup 51900 priority 31000",
+                "-6 rule delete to 2001:db8::1 lookup main priority 30999",
+                "-6 rule delete uidrange 1000-1000 lookup 51900 priority 31000",
+                "-6 rule delete uidrange 1001-1001 lookup 51900 priority 31000",
+                "-6 route delete throw fd00:1::/64 table 51900",
+            ]
+        );
+    }
+
+    #[test]
+    fn marked_packets_keep_using_the_main_table() {
+        let mut installed = installed(None, &[]);
+        installed.split_tunnel.uids = vec![1000];
+        installed.split_tunnel.fwmark = Some(0x51);
+
+        let commands = joined(split_tunnel_args(&installed, SplitTunnelAction::Add));
+
+        assert_eq!(
+            commands,
+            vec![
+                "rule add fwmark 0x51 lookup main priority 30999",
+                "rule add uidrange 1000-1000 lookup 51900 priority 31000",
+                "-6 rule add fwmark 0x51 lookup main priority 30999",
+                "-6 rule add uidrange 1000-1000 lookup 51900 priority 31000",
+            ]
+        );
+    }
+
+    #[test]
+    fn table_routes_are_installed_on_the_tunnel_interface() {
+        let route = RouteSpec {
+            net: "10.0.0.0/8".parse().unwrap(),
+            metric: Some(50),
+        };
+
+        assert_eq!(
+            table_route_args(
+                &route,
+                &"10.214.0.1".parse().unwrap(),
+                "quincy0",
+                51900,
+                SplitTunnelAction::Add
+            )
+            .join(" "),
+            "route add 10.0.0.0/8 via 10.214.0.1 dev quincy0 table 51900 metric 50"
+        );
+        assert_eq!(
+            table_route_args(
+                &"::/0".parse().unwrap(),
+                &"fd00::1".parse().unwrap(),
+                "quincy0",
+                51900,
+                SplitTunnelAction::Remove
+            )
+            .join(" "),
+            "-6 route delete ::/0 via fd00::1 dev quincy0 table 51900"
+        );
+    }
+}
@@ -1,6 +1,6 @@
 use crate::Result;
 use crate::error::RouteError;
-use crate::network::route::{InstalledExclusionRoute, NextHop};
+use crate::network::route::{InstalledBypassRoute, InstalledExclusionRoute, NextHop, RouteSpec};
 use crate::utils::command::run_command;
 use ipnet::IpNet;
 use serde::Deserialize;
@@ -29,12 +29,12 @@ const POWERSHELL_COMMAND: &str = "powershell.exe";
 /// original error is returned.
 ///
 /// ### Arguments
-/// - `networks` - the networks to be routed through the gateway
+/// - `routes` - the routes to be sent through the gateway
 /// - `gateway` - the gateway to be used for the routes
 /// - `interface_name` - the name of the interface to add the routes to
 /// - `remote_address` - optional VPN server address requiring an exclusion route
 pub fn add_routes(
-    networks: &[IpNet],
+    routes: &[RouteSpec],
     gateway: &IpAddr,
     interface_name: &str,
     remote_address: Option<IpAddr>,
@@ -48,7 +48,8 @@ pub fn add_routes(
         Some(server) => match install_exclusion_for_server(&server, tunnel_if_index) {
             Ok(token) => Some(token),
             Err(err) => {
-                if any_route_covers_address(networks, &server) {
+                let networks: Vec<IpNet> = routes.iter().map(|route| route.net).collect();
+                if any_route_covers_address(&networks, &server) {
                     return Err(RouteError::ExclusionRequired { server }.into());
                 }
                 warn!(
@@ -62,7 +63,7 @@ pub fn add_routes(
         None => None,
     };
 
-    if let Err(add_err) = add_user_routes_with_index(networks, gateway, tunnel_if_index) {
+    if let Err(add_err) = add_user_routes_with_index(routes, gateway, tunnel_if_index) {
         // Roll back the exclusion route if it was installed.
         if let Some(ref token) = exclusion {
             if let Err(rm_err) = remove_exclusion_route(token) {
@@ -78,6 +79,46 @@ pub fn add_routes(
     Ok(exclusion)
 }
 
+/// Removes a list of routes previously added with [`add_routes`] in a single
+/// batched PowerShell invocation.
+///
+/// Routes that are already absent count as removed, so removal is
+/// idempotent.  Exclusion routes are not touched.
+///
+/// ### Arguments
+/// - `routes` - the routes sent through the gateway
+/// - `gateway` - the gateway the routes were added with
+/// - `interface_name` - the name of the interface the routes were added to
+pub fn remove_routes(routes: &[RouteSpec], gateway: &IpAddr, interface_name: &str) -> Result<()> {
+    if routes.is_empty() {
+        return Ok(());
+    }
+
+    let if_index = resolve_interface_index(interface_name)?;
+    let script = build_remove_user_routes_script(routes, gateway, if_index);
+    let args = vec!["-NoProfile", "-NonInteractive", "-Command", &script];
+
+    let output = run_command(POWERSHELL_COMMAND, &args)
+        .map_err(|e| RouteError::PlatformError {
+            message: format!("failed to execute user route remove command: {e}"),
+        })?
+        .wait_with_output()
+        .map_err(|e| RouteError::PlatformError {
+            message: format!("failed to wait for user route remove command: {e}"),
+        })?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        warn!("failed to remove user routes: {}", stderr.trim());
+        return Err(RouteError::RemoveFailed {
+            destination: format!("{} network(s) via ifIndex {}", routes.len(), if_index),
+        }
+        .into());
+    }
+
+    Ok(())
+}
+
 /// Attempts to discover the current next-hop for `server` and install an
 /// exclusion host-route via that next-hop.
 ///
@@ -125,12 +166,12 @@ fn is_self_referential_next_hop(server: &IpAddr, next_hop: &NextHop, tunnel_if_i
 
 /// Adds user routes in a single batched PowerShell invocation, using a
 /// pre-resolved tunnel interface index.
-fn add_user_routes_with_index(networks: &[IpNet], gateway: &IpAddr, if_index: u32) -> Result<()> {
-    if networks.is_empty() {
+fn add_user_routes_with_index(routes: &[RouteSpec], gateway: &IpAddr, if_index: u32) -> Result<()> {
+    if routes.is_empty() {
         return Ok(());
     }
 
-    let script = build_user_routes_script(networks, gateway, if_index);
+    let script = build_user_routes_script(routes, gateway, if_index);
     let args = vec!["-NoProfile", "-NonInteractive", "-Command", &script];
 
     let output = run_command(POWERSHELL_COMMAND, &args)
@@ -145,7 +186,7 @@ fn add_user_routes_with_index(networks: &[IpNet], gateway: &IpAddr, if_index: u3
     if !output.status.success() {
         let stderr = String::from_utf8_lossy(&output.stderr);
         return Err(RouteError::AddFailed {
-            destination: format!("{} network(s) via ifIndex {}", networks.len(), if_index),
+            destination: format!("{} network(s) via ifIndex {}", routes.len(), if_index),
             message: stderr.trim().to_string(),
         }
         .into());
@@ -223,20 +264,56 @@ fn resolve_interface_index(interface_name: &str) -> Result<u32> {
 /// invocation using `New-NetRoute`.
 ///
 /// Each route is added with `-PolicyStore ActiveStore` so it is not
-/// persisted across reboots, matching the active-only lifecycle.
+/// persisted across reboots, matching the active-only lifecycle. Routes with
+/// a metric are added with `-RouteMetric`.
 ///
 /// The script uses `$ErrorActionPreference = 'Stop'` so the first failure
 /// terminates execution immediately.
-fn build_user_routes_script(networks: &[IpNet], gateway: &IpAddr, interface_index: u32) -> String {
+fn build_user_routes_script(
+    routes: &[RouteSpec],
+    gateway: &IpAddr,
+    interface_index: u32,
+) -> String {
     let mut script = String::from("$ErrorActionPreference = 'Stop'; ");
     let gateway_str = gateway.to_string();
 
-    for network in networks {
+    for route in routes {
+        let metric = route
+            .metric
+            .map(|metric| format!(" -RouteMetric {metric}"))
+            .unwrap_or_default();
+        script.push_str(&format!(
+            "New-NetRoute -DestinationPrefix '{}' -InterfaceIndex {} -NextHop '{}'{} -PolicyStore ActiveStore; ",
+            route.net, interface_index, gateway_str, metric
+        ));
+    }
+
+    script
+}
+
+/// Builds a PowerShell script that removes multiple routes in a single
+/// invocation using `Remove-NetRoute`.
+///
+/// Unlike [`build_user_routes_script`], failures do not stop the script so
+/// every route is attempted.  Routes that are already absent count as
+/// removed, detected through the locale-independent `NotFound` CIM error
+/// code; any other failure makes the script exit non-zero at the end.
+fn build_remove_user_routes_script(
+    routes: &[RouteSpec],
+    gateway: &IpAddr,
+    interface_index: u32,
+) -> String {
+    let mut script = String::from("$failed = $false; ");
+    let gateway_str = gateway.to_string();
+
+    for route in routes {
         script.push_str(&format!(
-            "New-NetRoute -DestinationPrefix '{}' -InterfaceIndex {} -NextHop '{}' -PolicyStore ActiveStore; ",
-            network, interface_index, gateway_str
+            "try {{ Remove-NetRoute -DestinationPrefix '{}' -InterfaceIndex {} -NextHop '{}' -PolicyStore ActiveStore -Confirm:$false -ErrorAction Stop }} \
+             catch {{ if ($_.Exception.NativeErrorCode -ne [Microsoft.Management.Infrastructure.NativeErrorCode]::NotFound) {{ Write-Error $_ -ErrorAction Continue; $failed = $true }} }}; ",
+            route.net, interface_index, gateway_str
         ));
     }
+    script.push_str("if ($failed) { exit 1 }");
 
     script
 }
@@ -460,6 +537,125 @@ pub fn remove_exclusion_route(exclusion: &InstalledExclusionRoute) -> Result<()>
     Ok(())
 }
 
+/// Installs a route for each excluded network via the next-hop it is
+/// currently reached through, so it bypasses the tunnel routes containing it.
+///
+/// Must be called before the tunnel routes are installed, while the networks
+/// are still reached through their original next-hop.  Pre-existing routes
+/// for a network are not adopted.  If a route cannot be installed, the routes
+/// installed so far are removed before the error is returned.
+///
+/// ### Arguments
+/// - `networks` - the excluded networks, see [`super::bypass_networks`]
+/// - `interface_name` - the name of the tunnel interface; used to reject
+///   next-hops that resolve through the tunnel itself
+pub fn add_bypass_routes(
+    networks: &[IpNet],
+    interface_name: &str,
+) -> Result<Vec<InstalledBypassRoute>> {
+    if networks.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let tunnel_if_index = resolve_interface_index(interface_name)?;
+    let mut installed = Vec::with_capacity(networks.len());
+
+    for net in networks {
+        match add_bypass_route(net, tunnel_if_index) {
+            Ok(route) => installed.push(route),
+            Err(add_err) => {
+                if let Err(rm_err) = remove_bypass_routes(&installed) {
+                    warn!("failed to roll back bypass routes: {rm_err}");
+                }
+                return Err(add_err);
+            }
+        }
+    }
+
+    Ok(installed)
+}
+
+fn add_bypass_route(net: &IpNet, tunnel_if_index: u32) -> Result<InstalledBypassRoute> {
+    let next_hop = get_route_to(&net.network())?;
+
+    if is_self_referential_next_hop(&net.network(), &next_hop, tunnel_if_index) {
+        return Err(RouteError::PlatformError {
+            message: format!(
+                "route lookup for {net} resolved to a self-referential next-hop \
+                 ({next_hop:?}); refusing to install bypass route"
+            ),
+        }
+        .into());
+    }
+
+    let script = route_add_script(&net.to_string(), &next_hop);
+    let args = vec!["-NoProfile", "-NonInteractive", "-Command", &script];
+
+    let output = run_command(POWERSHELL_COMMAND, &args)
+        .map_err(|e| RouteError::PlatformError {
+            message: format!("failed to execute bypass route add command: {e}"),
+        })?
+        .wait_with_output()
+        .map_err(|e| RouteError::PlatformError {
+            message: format!("failed to wait for bypass route add command: {e}"),
+        })?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(RouteError::AddFailed {
+            destination: net.to_string(),
+            message: stderr.trim().to_string(),
+        }
+        .into());
+    }
+
+    Ok(InstalledBypassRoute {
+        net: *net,
+        next_hop,
+    })
+}
+
+/// Removes bypass routes previously installed with [`add_bypass_routes`].
+///
+/// Routes are removed by destination prefix on the interface they were
+/// installed on, so a gateway change since installation does not leak them.
+/// Every route is attempted even if an earlier removal fails; the first
+/// failure is returned.
+pub fn remove_bypass_routes(routes: &[InstalledBypassRoute]) -> Result<()> {
+    let mut result = Ok(());
+
+    for route in routes {
+        let script = route_remove_fallback_script(&route.net.to_string(), &route.next_hop);
+        let args = vec!["-NoProfile", "-NonInteractive", "-Command", &script];
+
+        let output = run_command(POWERSHELL_COMMAND, &args)
+            .map_err(|e| RouteError::PlatformError {
+                message: format!("failed to execute bypass route remove command: {e}"),
+            })?
+            .wait_with_output()
+            .map_err(|e| RouteError::PlatformError {
+                message: format!("failed to wait for bypass route remove command: {e}"),
+            })?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            warn!(
+                "failed to remove bypass route {}: {}",
+                route.net,
+                stderr.trim()
+            );
+            if result.is_ok() {
+                result = Err(RouteError::RemoveFailed {
+                    destination: route.net.to_string(),
+                }
+                .into());
+            }
+        }
+    }
+
+    result
+}
+
 /// Returns the host-route destination prefix for `server` (`/32` for IPv4,
 /// `/128` for IPv6).
 fn host_prefix_for(server: &IpAddr) -> String {
@@ -497,7 +693,15 @@ fn interface_index_of(next_hop: &NextHop) -> &str {
 ///
 /// [`NativeErrorCode`]: https://learn.microsoft.com/dotnet/api/microsoft.management.infrastructure.nativeerrorcode
 fn exclusion_route_add_script(server: &IpAddr, next_hop: &NextHop) -> String {
-    let prefix = host_prefix_for(server);
+    route_add_script(&host_prefix_for(server), next_hop)
+}
+
+/// Builds the PowerShell script for adding a route for `prefix` via
+/// `next_hop`, refusing to adopt a pre-existing route.
+///
+/// Shared by exclusion host routes and bypass routes of excluded networks;
+/// see [`exclusion_route_add_script`].
+fn route_add_script(prefix: &str, next_hop: &NextHop) -> String {
     let interface_index = interface_index_of(next_hop);
 
     let next_hop_arg = if let NextHop::Gateway { address, .. } = next_hop {
@@ -518,7 +722,7 @@ fn exclusion_route_add_script(server: &IpAddr, next_hop: &NextHop) -> String {
     // we re-throw so the original error surfaces verbatim.
     let catch_body = format!(
         "if ($_.Exception.NativeErrorCode -eq [Microsoft.Management.Infrastructure.NativeErrorCode]::AlreadyExists) {{ \
-         Write-Error \"refusing to adopt a pre-existing exclusion route for '{prefix}' on ifIndex {interface_index} that Quincy did not install\"; \
+         Write-Error \"refusing to adopt a pre-existing route for '{prefix}' on ifIndex {interface_index} that Quincy did not install\"; \
          exit 1 \
          }}; \
          throw"
@@ -582,7 +786,15 @@ fn exclusion_route_remove_script(server: &IpAddr, next_hop: &NextHop) -> String
 /// behaviour is stable on non-English Windows hosts.  Any other failure
 /// still surfaces to the Rust caller as a non-zero script exit.
 fn exclusion_route_remove_fallback_script(server: &IpAddr, next_hop: &NextHop) -> String {
-    let prefix = host_prefix_for(server);
+    route_remove_fallback_script(&host_prefix_for(server), next_hop)
+}
+
+/// Builds the PowerShell script removing the route for `prefix` on the
+/// interface of `next_hop`, treating an absent route as removed.
+///
+/// Shared by exclusion host routes and bypass routes of excluded networks;
+/// see [`exclusion_route_remove_fallback_script`].
+fn route_remove_fallback_script(prefix: &str, next_hop: &NextHop) -> String {
     let interface_index = interface_index_of(next_hop);
 
     let remove_cmd = format!(
@@ -604,6 +816,24 @@ mod tests {
     use super::*;
     use std::net::{Ipv4Addr, Ipv6Addr};
 
+    #[test]
+    fn remove_user_routes_script_ignores_absent_routes() {
+        let routes: Vec<RouteSpec> = vec![
+            "10.0.0.0/8".parse().unwrap(),
+            "192.168.0.0/16".parse().unwrap(),
+        ];
+        let script =
+            build_remove_user_routes_script(&routes, &IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)), 12);
+
+        assert!(script.starts_with("$failed = $false; "));
+        assert_eq!(script.matches("-ErrorAction Stop").count(), 2);
+        assert!(script.contains(
+            "Remove-NetRoute -DestinationPrefix '192.168.0.0/16' -InterfaceIndex 12 -NextHop '10.0.0.1'"
+        ));
+        assert!(script.contains("NativeErrorCode]::NotFound"));
+        assert!(script.ends_with("if ($failed) { exit 1 }"));
+    }
+
     #[test]
     fn parse_entries_single_object() {
         let json = r#"{"InterfaceIndex": 12, "NextHop": "192.168.1.1"}"#;
@@ -1100,6 +1330,34 @@ mod tests {
         }
     }
 
+    mod bypass_windows {
+        use super::*;
+
+        #[test]
+        fn add_gateway_ipv4_uses_network_prefix() {
+            let hop = NextHop::Gateway {
+                address: IpAddr::V4(Ipv4Addr::new(192, 168, 1, 1)),
+                interface: "12".to_string(),
+            };
+            let script = route_add_script("192.168.0.0/16", &hop);
+            assert!(script.contains(
+                "New-NetRoute -DestinationPrefix '192.168.0.0/16' -InterfaceIndex 12 -PolicyStore ActiveStore -NextHop '192.168.1.1'"
+            ));
+        }
+
+        #[test]
+        fn remove_swallows_not_found() {
+            let hop = NextHop::OnLink {
+                interface: "5".to_string(),
+            };
+            let script = route_remove_fallback_script("fd00:1::/64", &hop);
+            assert!(script.contains(
+                "Remove-NetRoute -DestinationPrefix 'fd00:1::/64' -InterfaceIndex 5 -PolicyStore ActiveStore -Confirm:$false"
+            ));
+            assert!(script.contains("NativeErrorCode]::NotFound"));
+        }
+    }
+
     mod self_referential {
         use super::*;
 
@@ -1272,7 +1530,7 @@ mod tests {
 
         #[test]
         fn single_ipv4_route() {
-            let networks = vec!["10.0.0.0/8".parse::<IpNet>().unwrap()];
+            let networks = vec!["10.0.0.0/8".parse::<RouteSpec>().unwrap()];
             let gateway: IpAddr = "192.168.1.1".parse().unwrap();
             let script = build_user_routes_script(&networks, &gateway, 12);
             assert_eq!(
@@ -1284,7 +1542,7 @@ mod tests {
 
         #[test]
         fn multiple_ipv4_routes() {
-            let networks: Vec<IpNet> = vec![
+            let networks: Vec<RouteSpec> = vec![
                 "10.0.0.0/8".parse().unwrap(),
                 "172.16.0.0/12".parse().unwrap(),
                 "192.168.0.0/16".parse().unwrap(),
@@ -1305,7 +1563,7 @@ mod tests {
 
         #[test]
         fn ipv6_route() {
-            let networks = vec!["2001:db8::/32".parse::<IpNet>().unwrap()];
+            let networks = vec!["2001:db8::/32".parse::<RouteSpec>().unwrap()];
             let gateway: IpAddr = "fe80::1".parse().unwrap();
             let script = build_user_routes_script(&networks, &gateway, 7);
             assert_eq!(
@@ -1317,7 +1575,8 @@ mod tests {
 
         #[test]
         fn mixed_ipv4_and_ipv6_routes() {
-            let networks: Vec<IpNet> = vec!["0.0.0.0/0".parse().unwrap(), "::/0".parse().unwrap()];
+            let networks: Vec<RouteSpec> =
+                vec!["0.0.0.0/0".parse().unwrap(), "::/0".parse().unwrap()];
             let gateway: IpAddr = "10.0.0.1".parse().unwrap();
             let script = build_user_routes_script(&networks, &gateway, 5);
             assert!(script.contains(
@@ -1328,6 +1587,21 @@ mod tests {
             ));
         }
 
+        #[test]
+        fn route_metric_is_passed_to_new_net_route() {
+            let routes = vec![RouteSpec {
+                net: "10.0.0.0/8".parse().unwrap(),
+                metric: Some(50),
+            }];
+            let gateway: IpAddr = "192.168.1.1".parse().unwrap();
+            let script = build_user_routes_script(&routes, &gateway, 12);
+            assert_eq!(
+                script,
+                "$ErrorActionPreference = 'Stop'; \
+                 New-NetRoute -DestinationPrefix '10.0.0.0/8' -InterfaceIndex 12 -NextHop '192.168.1.1' -RouteMetric 50 -PolicyStore ActiveStore; "
+            );
+        }
+
         #[test]
         fn empty_networks_produces_only_preamble() {
             let gateway: IpAddr = "10.0.0.1".parse().unwrap();
@@ -1,70 +1,119 @@
 use std::net::SocketAddr;
 
+use quinn::udp::UdpSocketState;
 use socket2::{Domain, Protocol, Socket, Type};
-use tracing::warn;
+use tracing::{debug, warn};
 
-use crate::constants::MIN_SOCKET_BUFFER_SIZE;
+use crate::constants::{MAX_DSCP, MIN_SOCKET_BUFFER_SIZE, SOCKET_BUFFER_WARNING_THRESHOLD_PERCENT};
 use crate::error::{Result, SocketError};
 
+/// Additional options applied to the UDP socket of a QUIC endpoint.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SocketOptions {
+    /// The firewall mark (`SO_MARK`) set on outgoing packets, Linux only
+    pub mark: Option<u32>,
+    /// The DSCP value (0-63) set in the IP header of outgoing packets
+    pub dscp: Option<u8>,
+}
+
+/// Segmentation offload used by Quinn on a UDP socket.
+///
+/// Quinn already batches the system calls of its endpoints: on Linux, it receives up to
+/// [`quinn::udp::BATCH_SIZE`] messages per `recvmmsg` call, each holding up to
+/// `gro_segments` datagrams coalesced by UDP GRO, and sends up to `max_gso_segments`
+/// datagrams per `sendmsg` call with UDP GSO. Neither the socket nor the `EndpointConfig`
+/// needs further configuration.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct UdpOffload {
+    /// Largest number of datagrams sent with a single system call (1 without GSO)
+    pub max_gso_segments: usize,
+    /// Largest number of datagrams received in a single buffer (1 without GRO)
+    pub gro_segments: usize,
+}
+
+/// The sysctls limiting the socket send and receive buffer sizes.
+#[cfg(target_os = "linux")]
+const BUFFER_SIZE_SYSCTLS: Option<(&str, &str)> = Some(("net.core.wmem_max", "net.core.rmem_max"));
+#[cfg(any(target_os = "macos", target_os = "freebsd"))]
+const BUFFER_SIZE_SYSCTLS: Option<(&str, &str)> =
+    Some(("kern.ipc.maxsockbuf", "kern.ipc.maxsockbuf"));
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "freebsd")))]
+const BUFFER_SIZE_SYSCTLS: Option<(&str, &str)> = None;
+
 /// Binds a UDP socket to the given address and sets the send and receive buffer sizes.
 ///
+/// IPv6 sockets are dual-stack (`IPV6_V6ONLY` disabled), so a socket bound to the
+/// unspecified IPv6 address can also exchange packets with IPv4 peers.
+///
 /// Buffer sizes are set on a best-effort basis - if the OS rejects the requested
 /// size with `ENOBUFS` (e.g. FreeBSD when the value exceeds system limits),
 /// the function halves the request repeatedly until it is accepted or
 /// [`MIN_SOCKET_BUFFER_SIZE`] is reached. Other errors are propagated
-/// immediately.
+/// immediately. Buffers the OS silently clamps (e.g. Linux to `net.core.rmem_max`)
+/// are reported with a warning naming the sysctl to raise.
 ///
 /// ### Arguments
 /// - `addr` - the address to bind the socket to
 /// - `send_buffer_size` - the desired size of the send buffer
 /// - `recv_buffer_size` - the desired size of the receive buffer
 /// - `reuse_socket` - whether to reuse the socket across multiple Quincy instances
+/// - `options` - additional options applied to the socket
 ///
 /// ### Returns
 /// - `std::net::UdpSocket` - the bound socket
+///
+/// ### Errors
+/// Returns `SocketError::AddressInUse` if the requested address and port are
+/// already taken by another socket, `SocketError::ConfigFailed` if the firewall
+/// mark or DSCP value cannot be set and `SocketError::NotSupported` if IPv6 or
+/// dual-stack sockets are not available, or a firewall mark is requested on a
+/// platform other than Linux.
 pub fn bind_socket(
     addr: SocketAddr,
     send_buffer_size: usize,
     recv_buffer_size: usize,
     reuse_socket: bool,
+    options: &SocketOptions,
 ) -> Result<std::net::UdpSocket> {
-    let socket = Socket::new(Domain::for_address(addr), Type::DGRAM, Some(Protocol::UDP))
-        .map_err(|_| SocketError::CreationFailed)?;
+    let socket =
+        Socket::new(Domain::for_address(addr), Type::DGRAM, Some(Protocol::UDP)).map_err(|_| {
+            match addr {
+                SocketAddr::V4(_) => SocketError::CreationFailed,
+                SocketAddr::V6(_) => SocketError::NotSupported {
+                    operation: "IPv6 sockets".to_string(),
+                },
+            }
+        })?;
 
     if addr.is_ipv6() {
         socket
             .set_only_v6(false)
-            .map_err(|_| SocketError::ConfigFailed {
-                option: "dual-stack (not IPv6-only)".to_string(),
+            .map_err(|_| SocketError::NotSupported {
+                operation: "dual-stack sockets (IPV6_V6ONLY=false)".to_string(),
             })?;
     }
 
     if reuse_socket {
-        socket
-            .set_reuse_address(true)
-            .map_err(|_| SocketError::ConfigFailed {
-                option: "SO_REUSEADDR".to_string(),
-            })?;
+        set_reuse(&socket)?;
+    }
 
-        #[cfg(any(target_os = "linux", target_os = "macos"))]
-        socket
-            .set_reuse_port(true)
-            .map_err(|_| SocketError::ConfigFailed {
-                option: "SO_REUSEPORT".to_string(),
-            })?;
+    if let Some(mark) = options.mark {
+        set_mark(&socket, mark)?;
+    }
 
-        #[cfg(target_os = "freebsd")]
-        socket
-            .set_reuse_port_lb(true)
-            .map_err(|_| SocketError::ConfigFailed {
-                option: "SO_REUSEPORT_LB".to_string(),
-            })?;
+    if let Some(dscp) = options.dscp {
+        set_dscp(&socket, addr, dscp)?;
     }
 
     socket
         .bind(&socket2::SockAddr::from(addr))
-        .map_err(|_| SocketError::BindFailed {
-            address: addr.to_string(),
+        .map_err(|e| match e.kind() {
+            std::io::ErrorKind::AddrInUse => SocketError::AddressInUse {
+                address: addr.to_string(),
+            },
+            _ => SocketError::BindFailed {
+                address: addr.to_string(),
+            },
         })?;
 
     try_set_buffer_size(
@@ -73,6 +122,7 @@ pub fn bind_socket(
         Socket::set_send_buffer_size,
         Socket::send_buffer_size,
         "send buffer size",
+        BUFFER_SIZE_SYSCTLS.map(|(send_sysctl, _)| send_sysctl),
     )?;
     try_set_buffer_size(
         &socket,
@@ -80,11 +130,163 @@ pub fn bind_socket(
         Socket::set_recv_buffer_size,
         Socket::recv_buffer_size,
         "recv buffer size",
+        BUFFER_SIZE_SYSCTLS.map(|(_, recv_sysctl)| recv_sysctl),
     )?;
 
     Ok(socket.into())
 }
 
+/// Detects the segmentation offload Quinn uses on a socket.
+///
+/// Warns if a GSO segment size is configured, but the socket does not support GSO.
+///
+/// ### Arguments
+/// - `socket` - the bound socket
+/// - `gso_segment_size` - the configured GSO segment size, if any
+///
+/// ### Returns
+/// - `UdpOffload` - the segmentation offload available on the socket
+pub fn check_udp_offload(
+    socket: &std::net::UdpSocket,
+    gso_segment_size: Option<u16>,
+) -> Result<UdpOffload> {
+    let state = UdpSocketState::new(socket.into())?;
+    let offload = UdpOffload {
+        max_gso_segments: state.max_gso_segments(),
+        gro_segments: state.gro_segments(),
+    };
+
+    debug!(
+        "UDP segmentation offload: up to {} GSO segments, {} GRO segments",
+        offload.max_gso_segments, offload.gro_segments
+    );
+    if gso_segment_size.is_some_and(|size| size > 0) && offload.max_gso_segments <= 1 {
+        warn!("gso_segment_size has no effect: the socket does not support UDP GSO");
+    }
+
+    Ok(offload)
+}
+
+/// Lets other sockets bind to the same address and port, with the kernel
+/// distributing incoming packets between them.
+#[cfg(any(target_os = "linux", target_os = "macos", target_os = "freebsd"))]
+fn set_reuse(socket: &Socket) -> Result<()> {
+    socket
+        .set_reuse_address(true)
+        .map_err(|_| SocketError::ConfigFailed {
+            option: "SO_REUSEADDR".to_string(),
+        })?;
+
+    #[cfg(any(target_os = "linux", target_os = "macos"))]
+    socket
+        .set_reuse_port(true)
+        .map_err(|_| SocketError::ConfigFailed {
+            option: "SO_REUSEPORT".to_string(),
+        })?;
+
+    #[cfg(target_os = "freebsd")]
+    socket
+        .set_reuse_port_lb(true)
+        .map_err(|_| SocketError::ConfigFailed {
+            option: "SO_REUSEPORT_LB".to_string(),
+        })?;
+
+    Ok(())
+}
+
+/// Windows has no load-balancing port reuse: `SO_REUSEADDR` would let another
+/// socket take over the port instead, so the socket is bound exclusively.
+#[cfg(target_os = "windows")]
+fn set_reuse(_socket: &Socket) -> Result<()> {
+    warn!("Socket reuse is not supported on Windows, binding the socket exclusively");
+
+    Ok(())
+}
+
+#[cfg(not(any(
+    target_os = "linux",
+    target_os = "macos",
+    target_os = "freebsd",
+    target_os = "windows"
+)))]
+fn set_reuse(_socket: &Socket) -> Result<()> {
+    Err(SocketError::NotSupported {
+        operation: "SO_REUSEPORT".to_string(),
+    }
+    .into())
+}
+
+/// Sets the firewall mark of the packets sent through the socket.
+///
+/// Requires `CAP_NET_ADMIN`.
+#[cfg(target_os = "linux")]
+fn set_mark(socket: &Socket, mark: u32) -> Result<()> {
+    socket.set_mark(mark).map_err(|e| {
+        SocketError::ConfigFailed {
+            option: format!("SO_MARK {mark:#x}: {e}"),
+        }
+        .into()
+    })
+}
+
+#[cfg(not(target_os = "linux"))]
+fn set_mark(_socket: &Socket, _mark: u32) -> Result<()> {
+    Err(SocketError::NotSupported {
+        operation: "SO_MARK".to_string(),
+    }
+    .into())
+}
+
+/// Sets the DSCP value of the packets sent through the socket.
+///
+/// The DSCP value occupies the upper six bits of the IPv4 ToS and IPv6 traffic class
+/// fields; the lower two ECN bits are left to Quinn.
+fn set_dscp(socket: &Socket, addr: SocketAddr, dscp: u8) -> Result<()> {
+    if dscp > MAX_DSCP {
+        return Err(SocketError::ConfigFailed {
+            option: format!("DSCP {dscp}: must be between 0 and {MAX_DSCP}"),
+        }
+        .into());
+    }
+    let traffic_class = u32::from(dscp) << 2;
+
+    if addr.is_ipv6() {
+        set_traffic_class_v6(socket, traffic_class)?;
+        // IPv4-mapped traffic of dual-stack sockets uses the IPv4 ToS field
+        #[cfg(target_os = "linux")]
+        if let Err(e) = socket.set_tos_v4(traffic_class) {
+            warn!("Failed to set the DSCP value of IPv4 traffic on a dual-stack socket: {e}");
+        }
+
+        return Ok(());
+    }
+
+    socket.set_tos_v4(traffic_class).map_err(|e| {
+        SocketError::ConfigFailed {
+            option: format!("IP_TOS {traffic_class:#x}: {e}"),
+        }
+        .into()
+    })
+}
+
+#[cfg(any(target_os = "linux", target_os = "macos", target_os = "freebsd"))]
+fn set_traffic_class_v6(socket: &Socket, traffic_class: u32) -> Result<()> {
+    socket.set_tclass_v6(traffic_class).map_err(|e| {
+        SocketError::ConfigFailed {
+            option: format!("IPV6_TCLASS {traffic_class:#x}: {e}"),
+        }
+        .into()
+    })
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "freebsd")))]
+fn set_traffic_class_v6(_socket: &Socket, traffic_class: u32) -> Result<()> {
+    Err(SocketError::ConfigFailed {
+        option: format!("IPV6_TCLASS {traffic_class:#x}: not supported on this platform"),
+    }
+    .into())
+}
+
 /// Tries to set a socket buffer size. On `ENOBUFS`, halves the request
 /// repeatedly until it is accepted or [`MIN_SOCKET_BUFFER_SIZE`] is reached.
 /// Other errors are propagated. Emits at most one warning.
@@ -94,12 +296,18 @@ fn try_set_buffer_size(
     set_fn: fn(&Socket, usize) -> std::io::Result<()>,
     get_fn: fn(&Socket) -> std::io::Result<usize>,
     label: &str,
+    sysctl: Option<&str>,
 ) -> Result<()> {
+    let hint = sysctl
+        .map(|sysctl| format!("; raise the {sysctl} sysctl to at least {requested}"))
+        .unwrap_or_default();
+
     if try_set_fn(socket, requested, set_fn, label)? {
-        if let Ok(actual) = get_fn(socket) {
-            if actual < requested {
-                warn!("Unable to set desired {label}. Desired: {requested}, Actual: {actual}",);
-            }
+        if let Some(error) = get_fn(socket)
+            .ok()
+            .and_then(|actual| clamped_buffer_size(requested, actual))
+        {
+            warn!("The OS clamped the {label}: {error}{hint}");
         }
         return Ok(());
     }
@@ -108,7 +316,7 @@ fn try_set_buffer_size(
     let mut size = (requested / 2).max(MIN_SOCKET_BUFFER_SIZE);
     while size >= MIN_SOCKET_BUFFER_SIZE {
         if try_set_fn(socket, size, set_fn, label)? {
-            warn!("Reduced {label} from {requested} to {size} due to OS buffer size limits",);
+            warn!("Reduced {label} from {requested} to {size} due to OS buffer size limits{hint}");
             return Ok(());
         }
         size /= 2;
@@ -119,6 +327,23 @@ fn try_set_buffer_size(
     Ok(())
 }
 
+/// Compares the buffer size read back from the socket with the requested size.
+///
+/// Linux reports twice the size that was set to account for bookkeeping overhead, so
+/// only buffers materially smaller than requested are considered clamped.
+///
+/// ### Arguments
+/// - `requested` - the requested buffer size
+/// - `actual` - the buffer size read back from the socket
+///
+/// ### Returns
+/// - `Option<SocketError>` - `SocketError::BufferSizeFailed` if the buffer was clamped
+fn clamped_buffer_size(requested: usize, actual: usize) -> Option<SocketError> {
+    let threshold = requested.saturating_mul(SOCKET_BUFFER_WARNING_THRESHOLD_PERCENT) / 100;
+
+    (actual < threshold).then_some(SocketError::BufferSizeFailed { requested, actual })
+}
+
 /// Calls `set_fn` and returns `Ok(true)` on success, `Ok(false)` on `ENOBUFS`,
 /// or propagates any other error as `SocketError::ConfigFailed`.
 fn try_set_fn(
@@ -136,3 +361,277 @@ fn try_set_fn(
         .into()),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::QuincyError;
+    use std::net::{Ipv4Addr, Ipv6Addr, UdpSocket};
+
+    /// Returns a port that was free at the time of the call.
+    fn free_port() -> u16 {
+        UdpSocket::bind((Ipv4Addr::LOCALHOST, 0))
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port()
+    }
+
+    #[test]
+    fn buffer_clamped_below_threshold_is_reported() {
+        assert!(matches!(
+            clamped_buffer_size(2_097_152, 425_984),
+            Some(SocketError::BufferSizeFailed {
+                requested: 2_097_152,
+                actual: 425_984
+            })
+        ));
+        assert!(clamped_buffer_size(1_000_000, 899_999).is_some());
+    }
+
+    #[test]
+    fn buffer_near_or_above_requested_size_is_not_reported() {
+        // Linux reports twice the size that was set
+        assert!(clamped_buffer_size(2_097_152, 4_194_304).is_none());
+        assert!(clamped_buffer_size(1_000_000, 900_000).is_none());
+        assert!(clamped_buffer_size(usize::MAX, usize::MAX).is_none());
+    }
+
+    #[test]
+    fn udp_offload_is_detected() {
+        let socket = bind_socket(
+            SocketAddr::new(Ipv4Addr::LOCALHOST.into(), 0),
+            MIN_SOCKET_BUFFER_SIZE,
+            MIN_SOCKET_BUFFER_SIZE,
+            false,
+            &SocketOptions::default(),
+        )
+        .unwrap();
+
+        let offload = check_udp_offload(&socket, Some(1280)).unwrap();
+
+        assert!(offload.max_gso_segments >= 1);
+        assert!(offload.gro_segments >= 1);
+    }
+
+    #[test]
+    fn bind_socket_uses_requested_port() {
+        let port = free_port();
+        let addr = SocketAddr::new(Ipv4Addr::LOCALHOST.into(), port);
+
+        let socket = bind_socket(
+            addr,
+            MIN_SOCKET_BUFFER_SIZE,
+            MIN_SOCKET_BUFFER_SIZE,
+            false,
+            &SocketOptions::default(),
+        )
+        .expect("port should be free");
+
+        assert_eq!(socket.local_addr().unwrap().port(), port);
+    }
+
+    #[test]
+    fn bind_socket_ephemeral_port() {
+        let addr = SocketAddr::new(Ipv4Addr::LOCALHOST.into(), 0);
+
+        let socket = bind_socket(
+            addr,
+            MIN_SOCKET_BUFFER_SIZE,
+            MIN_SOCKET_BUFFER_SIZE,
+            false,
+            &SocketOptions::default(),
+        )
+        .expect("ephemeral bind should succeed");
+
+        assert_ne!(socket.local_addr().unwrap().port(), 0);
+    }
+
+    #[test]
+    fn bind_socket_unspecified_ipv6_is_dual_stack() {
+        let socket = match bind_socket(
+            SocketAddr::new(Ipv6Addr::UNSPECIFIED.into(), 0),
+            MIN_SOCKET_BUFFER_SIZE,
+            MIN_SOCKET_BUFFER_SIZE,
+            false,
+            &SocketOptions::default(),
+        ) {
+            Ok(socket) => socket,
+            // IPv6 or dual-stack sockets are not available on this host
+            Err(QuincyError::Socket(SocketError::NotSupported { .. })) => return,
+            Err(e) => panic!("unexpected error: {e}"),
+        };
+        let port = socket.local_addr().unwrap().port();
+
+        let peer = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        peer.send_to(b"ping", (Ipv4Addr::LOCALHOST, port)).unwrap();
+
+        let mut buf = [0; 4];
+        let (len, from) = socket.recv_from(&mut buf).unwrap();
+        assert_eq!(&buf[..len], b"ping");
+        assert_eq!(
+            from,
+            SocketAddr::new(
+                Ipv4Addr::LOCALHOST.to_ipv6_mapped().into(),
+                peer.local_addr().unwrap().port()
+            )
+        );
+    }
+
+    #[test]
+    fn bind_socket_reports_address_in_use() {
+        let existing = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let addr = existing.local_addr().unwrap();
+
+        let result = bind_socket(
+            addr,
+            MIN_SOCKET_BUFFER_SIZE,
+            MIN_SOCKET_BUFFER_SIZE,
+            false,
+            &SocketOptions::default(),
+        );
+
+        assert!(matches!(
+            result,
+            Err(QuincyError::Socket(SocketError::AddressInUse { .. }))
+        ));
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "macos", target_os = "freebsd"))]
+    #[test]
+    fn bind_socket_reuse_shares_the_port() {
+        let bind = |addr| {
+            bind_socket(
+                addr,
+                MIN_SOCKET_BUFFER_SIZE,
+                MIN_SOCKET_BUFFER_SIZE,
+                true,
+                &SocketOptions::default(),
+            )
+        };
+
+        let first = bind(SocketAddr::new(Ipv4Addr::LOCALHOST.into(), 0)).unwrap();
+        let addr = first.local_addr().unwrap();
+
+        let second = bind(addr).expect("reused port should be shared");
+
+        assert_eq!(second.local_addr().unwrap(), addr);
+    }
+
+    #[cfg(target_os = "windows")]
+    #[test]
+    fn bind_socket_reuse_binds_exclusively_on_windows() {
+        let bind = |addr| {
+            bind_socket(
+                addr,
+                MIN_SOCKET_BUFFER_SIZE,
+                MIN_SOCKET_BUFFER_SIZE,
+                true,
+                &SocketOptions::default(),
+            )
+        };
+
+        let first = bind(SocketAddr::new(Ipv4Addr::LOCALHOST.into(), 0)).unwrap();
+        let addr = first.local_addr().unwrap();
+
+        assert!(matches!(
+            bind(addr),
+            Err(QuincyError::Socket(SocketError::AddressInUse { .. }))
+        ));
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "macos", target_os = "freebsd"))]
+    #[test]
+    fn bind_socket_sets_dscp_for_both_families() {
+        let options = SocketOptions {
+            dscp: Some(46),
+            ..SocketOptions::default()
+        };
+
+        let v4 = bind_socket(
+            SocketAddr::new(Ipv4Addr::LOCALHOST.into(), 0),
+            MIN_SOCKET_BUFFER_SIZE,
+            MIN_SOCKET_BUFFER_SIZE,
+            false,
+            &options,
+        )
+        .expect("IPv4 socket should accept the DSCP value");
+        let v6 = bind_socket(
+            SocketAddr::new(Ipv6Addr::UNSPECIFIED.into(), 0),
+            MIN_SOCKET_BUFFER_SIZE,
+            MIN_SOCKET_BUFFER_SIZE,
+            false,
+            &options,
+        )
+        .expect("IPv6 socket should accept the DSCP value");
+
+        assert_eq!(Socket::from(v4).tos_v4().unwrap(), 46 << 2);
+        assert_eq!(Socket::from(v6).tclass_v6().unwrap(), 46 << 2);
+    }
+
+    #[test]
+    fn bind_socket_rejects_dscp_wider_than_six_bits() {
+        let options = SocketOptions {
+            dscp: Some(64),
+            ..SocketOptions::default()
+        };
+
+        let result = bind_socket(
+            SocketAddr::new(Ipv4Addr::LOCALHOST.into(), 0),
+            MIN_SOCKET_BUFFER_SIZE,
+            MIN_SOCKET_BUFFER_SIZE,
+            false,
+            &options,
+        );
+
+        assert!(matches!(
+            result,
+            Err(QuincyError::Socket(SocketError::ConfigFailed { .. }))
+        ));
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    #[test]
+    fn bind_socket_rejects_mark_outside_linux() {
+        let addr = SocketAddr::new(Ipv4Addr::LOCALHOST.into(), 0);
+        let options = SocketOptions {
+            mark: Some(0x51),
+            ..SocketOptions::default()
+        };
+
+        let result = bind_socket(
+            addr,
+            MIN_SOCKET_BUFFER_SIZE,
+            MIN_SOCKET_BUFFER_SIZE,
+            false,
+            &options,
+        );
+
+        assert!(matches!(
+            result,
+            Err(QuincyError::Socket(SocketError::NotSupported { .. }))
+        ));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    #[ignore = "requires CAP_NET_ADMIN to set SO_MARK"]
+    fn bind_socket_sets_mark() {
+        let addr = SocketAddr::new(Ipv4Addr::LOCALHOST.into(), 0);
+        let options = SocketOptions {
+            mark: Some(0x51),
+            ..SocketOptions::default()
+        };
+
+        let socket = bind_socket(
+            addr,
+            MIN_SOCKET_BUFFER_SIZE,
+            MIN_SOCKET_BUFFER_SIZE,
+            false,
+            &options,
+        )
+        .expect("mark should be set with CAP_NET_ADMIN");
+
+        assert_eq!(Socket::from(socket).mark().unwrap(), 0x51);
+    }
+}
@@ -0,0 +1,198 @@
+//! Relaying of tunnel packets over QUIC datagrams or a reliable QUIC stream.
+//!
+//! Datagrams are unreliable and limited to the path MTU. In stream mode, packets are sent
+//! over a single bidirectional stream instead, each prefixed by its length as a big-endian
+//! `u16`. QUIC flow control on the stream applies backpressure to the sender. As a stream
+//! is only announced to the peer once data is sent on it, the client opens it with an empty
+//! frame, and empty frames are skipped by the receiver.
+//!
+//! Application keep-alive pings are always sent as datagrams, which the server echoes back.
+//! They start with a marker whose first nibble is not a valid IP version, so they are never
+//! mistaken for tunnel packets.
+
+use bytes::{BufMut, Bytes, BytesMut};
+use quinn::{Connection, ReadExactError, RecvStream, SendStream};
+
+use crate::Result;
+use crate::error::NetworkError;
+use crate::network::packet::Packet;
+
+/// Length of the frame header carrying the packet length.
+pub const FRAME_HEADER_LEN: usize = 2;
+/// Marker starting an application keep-alive ping.
+pub const PING_MARKER: [u8; 4] = *b"QPNG";
+/// Length of a ping: the marker followed by a big-endian `u64` sequence number.
+pub const PING_LEN: usize = PING_MARKER.len() + 8;
+
+/// Sends tunnel packets to the peer.
+pub enum PacketSender {
+    /// Sends each packet as a QUIC datagram
+    Datagram(Connection),
+    /// Sends length-framed packets over a QUIC stream
+    Stream(SendStream),
+}
+
+impl PacketSender {
+    /// Opens a packet stream on the connection, announcing it to the peer.
+    ///
+    /// ### Arguments
+    /// - `connection` - the connection to open the stream on
+    ///
+    /// ### Returns
+    /// - `(PacketSender, PacketReceiver)` - the two directions of the stream
+    pub async fn open_stream(connection: &Connection) -> Result<(Self, PacketReceiver)> {
+        let (mut send_stream, recv_stream) = connection.open_bi().await?;
+        send_stream.write_all(&encode_frame(&[])?).await?;
+
+        Ok((
+            Self::Stream(send_stream),
+            PacketReceiver::Stream(recv_stream),
+        ))
+    }
+
+    /// Sends a packet to the peer.
+    ///
+    /// In stream mode, waits until flow control lets the packet be sent.
+    ///
+    /// ### Arguments
+    /// - `packet` - the packet to send
+    ///
+    /// ### Errors
+    /// Returns an error if the connection or stream is closed, or the packet is too large.
+    pub async fn send(&mut self, packet: Bytes) -> Result<()> {
+        match self {
+            Self::Datagram(connection) => connection.send_datagram(packet)?,
+            Self::Stream(send_stream) => send_stream.write_all(&encode_frame(&packet)?).await?,
+        }
+
+        Ok(())
+    }
+}
+
+/// Receives tunnel packets from the peer.
+pub enum PacketReceiver {
+    /// Receives packets from QUIC datagrams
+    Datagram(Connection),
+    /// Receives length-framed packets from a QUIC stream
+    Stream(RecvStream),
+}
+
+impl PacketReceiver {
+    /// Receives the next packet from the peer.
+    ///
+    /// ### Errors
+    /// Returns `NetworkError::ConnectionClosed` if the peer finished the stream, or an
+    /// error if the connection or stream is closed.
+    pub async fn recv(&mut self) -> Result<Packet> {
+        match self {
+            Self::Datagram(connection) => Ok(connection.read_datagram().await?.into()),
+            Self::Stream(recv_stream) => loop {
+                let mut header = [0; FRAME_HEADER_LEN];
+                read_exact(recv_stream, &mut header).await?;
+
+                let len = usize::from(u16::from_be_bytes(header));
+                if len == 0 {
+                    continue;
+                }
+
+                let mut data = BytesMut::zeroed(len);
+                read_exact(recv_stream, &mut data).await?;
+
+                return Ok(Packet::new(data.freeze()));
+            },
+        }
+    }
+}
+
+/// Prefixes a packet with its length.
+///
+/// ### Arguments
+/// - `packet` - the packet to frame
+///
+/// ### Errors
+/// Returns `NetworkError::PacketError` if the packet is longer than a frame can describe.
+pub fn encode_frame(packet: &[u8]) -> Result<Bytes> {
+    let len = u16::try_from(packet.len()).map_err(|_| NetworkError::PacketError {
+        reason: format!("packet of {} bytes is too large to frame", packet.len()),
+    })?;
+
+    let mut frame = BytesMut::with_capacity(FRAME_HEADER_LEN + packet.len());
+    frame.put_u16(len);
+    frame.put_slice(packet);
+
+    Ok(frame.freeze())
+}
+
+/// Encodes an application keep-alive ping.
+///
+/// ### Arguments
+/// - `sequence` - the sequence number identifying the ping
+pub fn encode_ping(sequence: u64) -> Bytes {
+    let mut ping = BytesMut::with_capacity(PING_LEN);
+    ping.put_slice(&PING_MARKER);
+    ping.put_u64(sequence);
+
+    ping.freeze()
+}
+
+/// Decodes an application keep-alive ping.
+///
+/// ### Arguments
+/// - `data` - the received datagram
+///
+/// ### Returns
+/// - `Option<u64>` - the sequence number of the ping, if the datagram is one
+pub fn decode_ping(data: &[u8]) -> Option<u64> {
+    let sequence = data.strip_prefix(&PING_MARKER)?;
+    let sequence: [u8; 8] = sequence.try_into().ok()?;
+
+    Some(u64::from_be_bytes(sequence))
+}
+
+/// Fills the buffer from the stream, treating a finished stream as a closed tunnel.
+async fn read_exact(recv_stream: &mut RecvStream, buf: &mut [u8]) -> Result<()> {
+    recv_stream.read_exact(buf).await.map_err(|e| match e {
+        ReadExactError::FinishedEarly(_) => NetworkError::ConnectionClosed.into(),
+        ReadExactError::ReadError(e) => e.into(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frame_is_prefixed_with_packet_length() {
+        let frame = encode_frame(&[0x45, 0x00, 0x00]).unwrap();
+
+        assert_eq!(&frame[..], &[0x00, 0x03, 0x45, 0x00, 0x00]);
+    }
+
+    #[test]
+    fn oversized_packet_is_rejected() {
+        let packet = vec![0; usize::from(u16::MAX) + 1];
+
+        assert!(matches!(
+            encode_frame(&packet),
+            Err(crate::QuincyError::Network(
+                NetworkError::PacketError { .. }
+            ))
+        ));
+    }
+
+    #[test]
+    fn ping_round_trips() {
+        let ping = encode_ping(42);
+
+        assert_eq!(ping.len(), PING_LEN);
+        assert_eq!(decode_ping(&ping), Some(42));
+    }
+
+    #[test]
+    fn ip_packets_are_not_pings() {
+        let ipv4_header = [0x45, 0x00, 0x00, 0x0c, 0, 0, 0, 0, 64, 17, 0, 0];
+
+        assert_eq!(decode_ping(&ipv4_header), None);
+        assert_eq!(decode_ping(&PING_MARKER), None);
+    }
+}
@@ -3,6 +3,16 @@ use std::process::{Child, Command, Stdio};
 
 use crate::error::{QuincyError, Result};
 
+/// Returns whether `program` is found in one of the directories of `PATH`.
+///
+/// ### Arguments
+/// - `program` - the name of the program
+pub fn command_exists(program: &str) -> bool {
+    std::env::var_os("PATH").is_some_and(|paths| {
+        std::env::split_paths(&paths).any(|directory| directory.join(program).is_file())
+    })
+}
+
 pub fn run_command<I: IntoIterator<Item = S>, S: AsRef<OsStr>>(
     program: &str,
     arguments: I,
@@ -1,3 +1,5 @@
 pub mod command;
+#[cfg(feature = "profiling")]
+pub mod profiling;
 pub mod tasks;
 pub mod tracing;
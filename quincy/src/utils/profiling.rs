@@ -0,0 +1,161 @@
+//! Relay path profiling.
+//!
+//! Records latency and batch size histograms of the packet relay path for
+//! performance tuning. Only compiled with the `profiling` feature.
+
+use std::fmt::Write;
+use std::sync::Mutex;
+use std::time::Instant;
+
+use hdrhistogram::Histogram;
+
+/// Significant decimal digits kept by the histograms.
+const SIGNIFICANT_DIGITS: u8 = 3;
+/// Quantiles included in the report.
+const REPORT_QUANTILES: [f64; 4] = [0.5, 0.9, 0.99, 0.999];
+
+/// Latency and throughput histograms of the packet relay path.
+pub struct RelayProfiler {
+    /// Microseconds between reading a batch from the TUN interface and queueing it on the QUIC connection
+    outbound_latency_us: Mutex<Histogram<u64>>,
+    /// Number of packets per batch read from the TUN interface
+    outbound_batch_size: Mutex<Histogram<u64>>,
+    /// Microseconds between receiving a datagram and writing it to the TUN interface
+    inbound_latency_us: Mutex<Histogram<u64>>,
+}
+
+impl RelayProfiler {
+    /// Creates a new profiler with empty histograms.
+    pub fn new() -> Self {
+        Self {
+            outbound_latency_us: Mutex::new(new_histogram()),
+            outbound_batch_size: Mutex::new(new_histogram()),
+            inbound_latency_us: Mutex::new(new_histogram()),
+        }
+    }
+
+    /// Records a batch of packets relayed from the TUN interface to the QUIC connection.
+    ///
+    /// ### Arguments
+    /// - `read_at` - when the batch was read from the TUN interface
+    /// - `packets` - the number of packets in the batch
+    pub fn record_outbound(&self, read_at: Instant, packets: usize) {
+        let latency_us = read_at.elapsed().as_micros() as u64;
+
+        let mut latency = lock(&self.outbound_latency_us);
+        for _ in 0..packets {
+            latency.saturating_record(latency_us);
+        }
+        lock(&self.outbound_batch_size).saturating_record(packets as u64);
+    }
+
+    /// Records a packet relayed from the QUIC connection to the TUN interface.
+    ///
+    /// ### Arguments
+    /// - `received_at` - when the datagram was received from the QUIC connection
+    pub fn record_inbound(&self, received_at: Instant) {
+        lock(&self.inbound_latency_us).saturating_record(received_at.elapsed().as_micros() as u64);
+    }
+
+    /// Returns the number of packets recorded in the outbound direction.
+    pub fn outbound_packets(&self) -> u64 {
+        lock(&self.outbound_latency_us).len()
+    }
+
+    /// Returns the number of packets recorded in the inbound direction.
+    pub fn inbound_packets(&self) -> u64 {
+        lock(&self.inbound_latency_us).len()
+    }
+
+    /// Returns a human-readable summary of all histograms.
+    pub fn report(&self) -> String {
+        let mut report = String::new();
+
+        write_summary(
+            &mut report,
+            "TUN -> QUIC latency (us)",
+            &lock(&self.outbound_latency_us),
+        );
+        write_summary(
+            &mut report,
+            "TUN read batch size (packets)",
+            &lock(&self.outbound_batch_size),
+        );
+        write_summary(
+            &mut report,
+            "QUIC -> TUN latency (us)",
+            &lock(&self.inbound_latency_us),
+        );
+
+        report
+    }
+}
+
+impl Default for RelayProfiler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn new_histogram() -> Histogram<u64> {
+    Histogram::new(SIGNIFICANT_DIGITS).expect("significant digits are within bounds")
+}
+
+fn lock(histogram: &Mutex<Histogram<u64>>) -> std::sync::MutexGuard<'_, Histogram<u64>> {
+    histogram
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+fn write_summary(report: &mut String, name: &str, histogram: &Histogram<u64>) {
+    let _ = write!(
+        report,
+        "{name}: samples = {}, min = {}, mean = {:.1}, max = {}",
+        histogram.len(),
+        histogram.min(),
+        histogram.mean(),
+        histogram.max(),
+    );
+    for quantile in REPORT_QUANTILES {
+        let _ = write!(
+            report,
+            ", p{} = {}",
+            quantile * 100.0,
+            histogram.value_at_quantile(quantile)
+        );
+    }
+    report.push('\n');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn records_samples_for_packet_batch() {
+        let profiler = RelayProfiler::new();
+
+        profiler.record_outbound(Instant::now(), 32);
+        profiler.record_outbound(Instant::now(), 8);
+        for _ in 0..5 {
+            profiler.record_inbound(Instant::now());
+        }
+
+        assert_eq!(profiler.outbound_packets(), 40);
+        assert_eq!(profiler.inbound_packets(), 5);
+        assert_eq!(lock(&profiler.outbound_batch_size).len(), 2);
+        assert_eq!(lock(&profiler.outbound_batch_size).max(), 32);
+    }
+
+    #[test]
+    fn report_lists_all_histograms() {
+        let profiler = RelayProfiler::new();
+        profiler.record_outbound(Instant::now(), 1);
+
+        let report = profiler.report();
+
+        assert!(report.contains("TUN -> QUIC latency (us): samples = 1"));
+        assert!(report.contains("TUN read batch size (packets): samples = 1"));
+        assert!(report.contains("QUIC -> TUN latency (us): samples = 0"));
+    }
+}